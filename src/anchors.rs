@@ -0,0 +1,150 @@
+//! Anchor comments: `TODO`, `FIXME`, `HACK`, `XXX` and `SAFETY` markers.
+//!
+//! Extraction is a language-agnostic line scan, run by the indexer after the
+//! symbol extractor. A tag counts only when it is the first word of a comment
+//! (`# TODO`, `// FIXME:`, `/* HACK`, ` * XXX`), which keeps identifiers and
+//! prose out.
+
+use crate::types::{Anchor, Symbol, SymbolKind};
+
+/// Tags recognised as anchors. Matching is case-sensitive.
+pub const ANCHOR_TAGS: &[&str] = &["TODO", "FIXME", "HACK", "XXX", "SAFETY"];
+
+/// Scan `source` for anchor comments, linking each to its innermost enclosing symbol.
+pub fn extract_anchors(
+    source: &str,
+    file_path: &str,
+    language: &str,
+    symbols: &[Symbol],
+) -> Vec<Anchor> {
+    let markers = comment_markers(language);
+    let mut anchors = Vec::new();
+
+    for (idx, line) in source.lines().enumerate() {
+        let Some((tag, text)) = find_anchor(line, markers) else {
+            continue;
+        };
+        let line_no = idx as u32 + 1;
+        let enclosing = enclosing_symbol(symbols, line_no);
+        anchors.push(Anchor {
+            tag: tag.to_string(),
+            text,
+            file_path: file_path.to_string(),
+            line: line_no,
+            symbol_id: enclosing.map(|s| s.id.clone()),
+            symbol_name: enclosing.map(|s| s.name.clone()),
+        });
+    }
+    anchors
+}
+
+/// Line/block comment openers for a language.
+fn comment_markers(language: &str) -> &'static [&'static str] {
+    match language {
+        "python" | "ruby" => &["#"],
+        _ => &["//", "/*", "*"],
+    }
+}
+
+/// Return `(tag, text)` if a comment on this line starts with an anchor tag.
+fn find_anchor(line: &str, markers: &[&str]) -> Option<(&'static str, String)> {
+    for marker in markers {
+        for (pos, _) in line.match_indices(marker) {
+            // Skip repeated marker chars (`///`, `##`, `/**`) and doc-comment bangs (`//!`).
+            let body = line[pos + marker.len()..]
+                .trim_start_matches(['/', '*', '#', '!'])
+                .trim_start();
+            if let Some(found) = match_tag(body) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+fn match_tag(body: &str) -> Option<(&'static str, String)> {
+    let tag = ANCHOR_TAGS.iter().find(|t| body.starts_with(**t))?;
+    let rest = &body[tag.len()..];
+    // Reject longer identifiers such as `TODOS` or `TODO_LIST`.
+    if rest
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+    let text = rest
+        .trim_end()
+        .trim_end_matches("*/")
+        .trim_start_matches(':')
+        .trim()
+        .to_string();
+    Some((tag, text))
+}
+
+/// Innermost non-import symbol whose line range contains `line`.
+pub(crate) fn enclosing_symbol(symbols: &[Symbol], line: u32) -> Option<&Symbol> {
+    symbols
+        .iter()
+        .filter(|s| s.kind != SymbolKind::Import && s.start_line <= line && line <= s.end_line)
+        .min_by_key(|s| s.end_line - s.start_line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_anchors_python() {
+        let source = "\
+def login(user):
+    # TODO: rate-limit attempts
+    token = make_token(user)  # FIXME(alice): leaks timing
+    return token
+# HACK remove once v2 ships
+TODOS = []  # TODOS is not an anchor
+";
+        let login = Symbol::new("login", SymbolKind::Function, "auth.py", 1, 4, 0, 100);
+        let anchors = extract_anchors(source, "auth.py", "python", std::slice::from_ref(&login));
+
+        let found: Vec<(&str, &str, u32)> = anchors
+            .iter()
+            .map(|a| (a.tag.as_str(), a.text.as_str(), a.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("TODO", "rate-limit attempts", 2),
+                ("FIXME", "(alice): leaks timing", 3),
+                ("HACK", "remove once v2 ships", 5),
+            ]
+        );
+        assert_eq!(anchors[0].symbol_id.as_deref(), Some(login.id.as_str()));
+        assert_eq!(anchors[0].symbol_name.as_deref(), Some("login"));
+        assert!(anchors[2].symbol_id.is_none());
+    }
+
+    #[test]
+    fn test_extract_anchors_rust_innermost_symbol() {
+        let source = "\
+impl Buf {
+    fn read(&self) -> u8 {
+        // SAFETY: index checked above
+        unsafe { *self.ptr }
+    }
+    /* XXX: revisit */
+}
+";
+        let imp = Symbol::new("Buf", SymbolKind::Class, "buf.rs", 1, 7, 0, 100);
+        let read = Symbol::new("read", SymbolKind::Method, "buf.rs", 2, 5, 0, 50);
+        let anchors = extract_anchors(source, "buf.rs", "rust", &[imp.clone(), read.clone()]);
+
+        assert_eq!(anchors.len(), 2);
+        assert_eq!(anchors[0].tag, "SAFETY");
+        assert_eq!(anchors[0].text, "index checked above");
+        assert_eq!(anchors[0].symbol_id.as_deref(), Some(read.id.as_str()));
+        assert_eq!(anchors[1].tag, "XXX");
+        assert_eq!(anchors[1].text, "revisit");
+        assert_eq!(anchors[1].symbol_id.as_deref(), Some(imp.id.as_str()));
+    }
+}
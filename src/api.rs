@@ -0,0 +1,333 @@
+//! `cartog serve --api`: a read-only HTTP JSON API over the index.
+//!
+//! For dashboards and bots that want plain REST rather than MCP. Each endpoint
+//! returns the same JSON as the matching CLI command with `--json`, including
+//! `estimated_tokens`. Browsers may call it from the origins given with
+//! `--cors-origin`; other origins get no CORS headers and are blocked by the browser.
+//! Bound to a loopback address, it refuses other `Host` names like `cartog viz`.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write as _};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use crate::db::{unix_now, MAX_SEARCH_LIMIT};
+use crate::languages::QUERY_LANGUAGES;
+use crate::overloads::Reference;
+use crate::shard::ShardedDatabase;
+use crate::tokens;
+use crate::types::{collapse_similar, EdgeKind, SymbolKind, SymbolMatch};
+use crate::viz::{is_local_host, parse_query};
+
+/// Settings of the API listener.
+#[derive(Debug, Clone)]
+pub struct ApiConfig {
+    pub addr: SocketAddr,
+    /// Origins allowed to make cross-origin requests (`*` allows any).
+    pub cors_origins: Vec<String>,
+}
+
+/// Serve the API on `config.addr` until the process is interrupted.
+///
+/// Requests are handled one at a time on the calling thread, like `cartog viz`:
+/// each is a single short query.
+pub fn run_api(config: &ApiConfig, db_path: &Path) -> Result<()> {
+    let db = ShardedDatabase::open(db_path).context("failed to open database for api")?;
+    let listener = TcpListener::bind(config.addr)
+        .with_context(|| format!("failed to bind api to {}", config.addr))?;
+    info!(addr = %config.addr, "api listening");
+    eprintln!("cartog api: http://{}/ (Ctrl+C to stop)", config.addr);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle_connection(stream, &db, config) {
+                    debug!(error = %e, "api request failed");
+                }
+            }
+            Err(e) => warn!(error = %e, "api connection failed"),
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    db: &ShardedDatabase,
+    config: &ApiConfig,
+) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    let mut origin = None;
+    let mut host = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("origin") {
+                origin = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("host") {
+                host = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = parse_query(query);
+
+    let (status, body) = match method {
+        _ if !host_allowed(config, host.as_deref()) => {
+            ("403 Forbidden", json!({ "error": "forbidden" }).to_string())
+        }
+        "OPTIONS" => ("204 No Content", String::new()),
+        "GET" => match route(db, path, &params) {
+            Ok(Some(value)) => ("200 OK", value.to_string()),
+            Ok(None) => ("404 Not Found", json!({ "error": "not found" }).to_string()),
+            Err(e) => (
+                "400 Bad Request",
+                json!({ "error": e.to_string() }).to_string(),
+            ),
+        },
+        _ => (
+            "405 Method Not Allowed",
+            json!({ "error": "GET only" }).to_string(),
+        ),
+    };
+
+    let mut headers = String::new();
+    if let Some(allowed) = origin
+        .as_deref()
+        .and_then(|o| allowed_origin(&config.cors_origins, o))
+    {
+        headers.push_str(&format!(
+            "Access-Control-Allow-Origin: {allowed}\r\nVary: Origin\r\n\
+             Access-Control-Allow-Methods: GET, OPTIONS\r\n\
+             Access-Control-Allow-Headers: Content-Type\r\n"
+        ));
+    }
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\n{headers}\
+         Content-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    Ok(())
+}
+
+/// Whether to answer a request for `host`. On a loopback address, only local
+/// names are, so a web page can't reach the API via DNS rebinding.
+fn host_allowed(config: &ApiConfig, host: Option<&str>) -> bool {
+    !config.addr.ip().is_loopback() || host.map_or(true, is_local_host)
+}
+
+/// The `Access-Control-Allow-Origin` value for a request from `origin`, if allowed.
+fn allowed_origin(allowed: &[String], origin: &str) -> Option<String> {
+    if allowed.iter().any(|a| a == "*") {
+        Some("*".to_string())
+    } else {
+        allowed
+            .iter()
+            .find(|a| a.trim_end_matches('/') == origin)
+            .cloned()
+    }
+}
+
+/// Run the query for `path`. `None` means an unknown path or symbol ID.
+fn route(
+    db: &ShardedDatabase,
+    path: &str,
+    params: &HashMap<String, String>,
+) -> Result<Option<Value>> {
+    let param = |key: &str| {
+        params
+            .get(key)
+            .map(String::as_str)
+            .filter(|v| !v.is_empty())
+    };
+    let required =
+        |key: &str| param(key).ok_or_else(|| anyhow::anyhow!("missing query parameter `{key}`"));
+    let lang = || match param("lang") {
+        Some(l) if !QUERY_LANGUAGES.contains(&l) => Err(anyhow::anyhow!(
+            "invalid `lang` '{l}' (expected one of: {})",
+            QUERY_LANGUAGES.join(", ")
+        )),
+        lang => Ok(lang),
+    };
+
+    if let Some(id) = path.strip_prefix("/symbols/") {
+        let id = crate::viz::percent_decode(id);
+        return db.get_symbol(&id)?.map(|sym| annotated(&sym)).transpose();
+    }
+    let value = match path {
+        "/search" => {
+            let query = required("q")?;
+            let kind = param("kind").map(str::parse::<SymbolKind>).transpose()?;
+            let limit: u32 = match param("limit") {
+                Some(limit) => limit.parse().context("invalid `limit`")?,
+                None => 30,
+            };
+            let limit = limit.clamp(1, MAX_SEARCH_LIMIT);
+            let collapse = param("collapse").is_some_and(|v| v != "false");
+            let fetch = if collapse { MAX_SEARCH_LIMIT } else { limit };
+            let mut matches: Vec<SymbolMatch> = db
+                .search_in(query, kind, param("file"), lang()?, fetch)?
+                .into_iter()
+                .map(|sym| SymbolMatch::new(sym, query))
+                .collect();
+            if collapse {
+                matches = collapse_similar(matches);
+                matches.truncate(limit as usize);
+            }
+            db.attach_notes(&mut matches, unix_now())?;
+            if param("context").is_some_and(|v| v != "false") {
+                db.attach_parent_docs(&mut matches)?;
+            }
+            annotated(&matches)?
+        }
+        "/refs" => {
+            let kind = param("kind").map(str::parse::<EdgeKind>).transpose()?;
+            let target_kind = param("target_kind")
+                .map(str::parse::<SymbolKind>)
+                .transpose()?;
+            let refs: Vec<Reference> = db
+                .refs_in(required("name")?, kind, param("in"), target_kind, lang()?)?
+                .into_iter()
+                .map(|(edge, source)| Reference { edge, source })
+                .collect();
+            annotated(&refs)?
+        }
+        "/outline" => annotated(&db.outline(required("file")?)?)?,
+        "/stats" => annotated(&db.stats()?)?,
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+/// Serialize `data` the way `--json` prints it, with `estimated_tokens`.
+fn annotated<T: Serialize>(data: &T) -> Result<Value> {
+    let mut value = serde_json::to_value(data)?;
+    tokens::annotate(&mut value);
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::types::{Edge, Symbol};
+
+    #[test]
+    fn test_allowed_origin() {
+        let allowed = vec!["https://dash.example".to_string()];
+        assert_eq!(
+            allowed_origin(&allowed, "https://dash.example").as_deref(),
+            Some("https://dash.example")
+        );
+        assert_eq!(allowed_origin(&allowed, "https://evil.example"), None);
+        assert_eq!(
+            allowed_origin(&["*".to_string()], "https://any.example").as_deref(),
+            Some("*")
+        );
+        assert_eq!(allowed_origin(&[], "https://dash.example"), None);
+    }
+
+    #[test]
+    fn test_loopback_api_refuses_other_hosts() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let config = ApiConfig {
+            addr: listener.local_addr().unwrap(),
+            cors_origins: Vec::new(),
+        };
+        let db = ShardedDatabase::from(Database::open_memory().unwrap());
+        let request = |host: &str| {
+            let mut client = TcpStream::connect(config.addr).unwrap();
+            write!(client, "GET /stats HTTP/1.1\r\nHost: {host}\r\n\r\n").unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            handle_connection(stream, &db, &config).unwrap();
+            let mut response = String::new();
+            std::io::Read::read_to_string(&mut client, &mut response).unwrap();
+            response
+        };
+        assert!(request("localhost").starts_with("HTTP/1.1 200 OK"));
+        assert!(request("evil.example:8765").starts_with("HTTP/1.1 403 Forbidden"));
+
+        let exposed = ApiConfig {
+            addr: "0.0.0.0:8765".parse().unwrap(),
+            cors_origins: Vec::new(),
+        };
+        assert!(host_allowed(&exposed, Some("api.internal:8765")));
+        assert!(!host_allowed(&config, Some("api.internal:8765")));
+        assert!(host_allowed(&config, None));
+    }
+
+    #[test]
+    fn test_route_endpoints() {
+        let db = Database::open_memory().unwrap();
+        let sym =
+            |name: &str, file: &str| Symbol::new(name, SymbolKind::Function, file, 1, 2, 0, 10);
+        let (main, helper) = (sym("main", "src/app.py"), sym("helper", "src/util.py"));
+        db.insert_symbols(&[main.clone(), helper.clone()]).unwrap();
+        db.insert_edges(&[Edge::new(
+            &main.id,
+            "helper",
+            EdgeKind::Calls,
+            "src/app.py",
+            1,
+        )])
+        .unwrap();
+        db.resolve_edges().unwrap();
+        let db = ShardedDatabase::from(db);
+        let params = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+
+        let found = route(&db, "/search", &params(&[("q", "help")]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(found[0]["name"], "helper");
+        assert!(found[0]["estimated_tokens"].is_number());
+
+        let refs = route(
+            &db,
+            "/refs",
+            &params(&[("name", "helper"), ("kind", "calls")]),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(refs[0]["source"]["name"], "main");
+
+        let path = format!("/symbols/{}", helper.id.replace('/', "%2F"));
+        let symbol = route(&db, &path, &HashMap::new()).unwrap().unwrap();
+        assert_eq!(symbol["file_path"], "src/util.py");
+        assert!(route(&db, "/symbols/nope", &HashMap::new())
+            .unwrap()
+            .is_none());
+
+        let outline = route(&db, "/outline", &params(&[("file", "src/app.py")]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(outline[0]["name"], "main");
+        let stats = route(&db, "/stats", &HashMap::new()).unwrap().unwrap();
+        assert_eq!(stats["num_symbols"], 2);
+
+        assert!(route(&db, "/search", &HashMap::new()).is_err());
+        assert!(route(&db, "/refs", &params(&[("name", "x"), ("kind", "bogus")])).is_err());
+        assert!(route(&db, "/nope", &HashMap::new()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,365 @@
+//! Call paths between two symbols (`cartog path`): how `login_handler` reaches
+//! `Database.open`, as the chains of calls and references leading from one to
+//! the other.
+//!
+//! The walk follows resolved call and reference edges forward. An unresolved
+//! edge is followed too when its name is defined by a single function, method or
+//! class in the index, and the hop is marked [`PathHop::by_name`]. Paths are
+//! simple (no symbol twice), stop at the first target they reach, and are
+//! listed shortest first.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::shard::ShardedDatabase;
+use crate::testmap::short_name;
+use crate::types::{Edge, EdgeKind, Symbol, SymbolKind};
+
+/// Default maximum number of hops.
+pub const DEFAULT_DEPTH: u32 = 6;
+
+/// One way `from` reaches `to`.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct CallPath {
+    /// Number of edges followed.
+    pub hops: u32,
+    /// The symbols along the path, from the start to the target.
+    pub steps: Vec<PathStep>,
+}
+
+/// A symbol on a [`CallPath`].
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct PathStep {
+    pub id: String,
+    pub name: String,
+    pub kind: SymbolKind,
+    pub file_path: String,
+    pub start_line: u32,
+    /// The edge from the previous step to this one; absent on the first step.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub via: Option<PathHop>,
+}
+
+/// The edge leading to a [`PathStep`], made in the previous step's file.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct PathHop {
+    pub kind: EdgeKind,
+    pub line: u32,
+    /// Followed by name: the edge is unresolved, but a single symbol has the name.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub by_name: bool,
+}
+
+/// An edge of the walk: the target symbol, the edge and whether it was
+/// followed by name.
+type Link<'a> = (&'a str, &'a Edge, bool);
+
+/// Up to `limit` paths of at most `depth` hops from a symbol matching `from` to
+/// one matching `to`, shortest first. Both are a symbol ID, a name, or a name
+/// qualified by its parent (`Database.open`, `Database::open`).
+pub fn find_paths(
+    db: &ShardedDatabase,
+    from: &str,
+    to: &str,
+    depth: u32,
+    limit: usize,
+) -> Result<Vec<CallPath>> {
+    let symbols = db.all_symbols()?;
+    let by_id: HashMap<&str, &Symbol> = symbols.iter().map(|s| (s.id.as_str(), s)).collect();
+    let sources = matching(&symbols, &by_id, from);
+    if sources.is_empty() {
+        bail!("No symbol named '{from}' in the index");
+    }
+    let targets: HashSet<&str> = matching(&symbols, &by_id, to)
+        .into_iter()
+        .map(|s| s.id.as_str())
+        .collect();
+    if targets.is_empty() {
+        bail!("No symbol named '{to}' in the index");
+    }
+
+    // Names defined once, for unresolved edges
+    let mut named: HashMap<&str, Option<&str>> = HashMap::new();
+    for s in &symbols {
+        if is_callable(s.kind) {
+            named
+                .entry(s.name.as_str())
+                .and_modify(|id| *id = None)
+                .or_insert(Some(s.id.as_str()));
+        }
+    }
+
+    let edges = db.all_edges()?;
+    let mut links: HashMap<&str, Vec<Link>> = HashMap::new();
+    for edge in &edges {
+        if !matches!(edge.kind, EdgeKind::Calls | EdgeKind::References) {
+            continue;
+        }
+        let (target, by_name) = match &edge.target_id {
+            Some(target) => (target.as_str(), false),
+            None => match named.get(short_name(&edge.target_name)) {
+                Some(Some(target)) => (*target, true),
+                _ => continue,
+            },
+        };
+        let out = links.entry(edge.source_id.as_str()).or_default();
+        // One edge per pair, so call sites on several lines give one path
+        if target != edge.source_id && !out.iter().any(|(t, _, _)| *t == target) {
+            out.push((target, edge, by_name));
+        }
+    }
+
+    // Hops from each symbol to the nearest target, walking the edges backwards
+    let mut callers: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (source, out) in &links {
+        for (target, _, _) in out {
+            callers.entry(*target).or_default().push(*source);
+        }
+    }
+    let mut distance: HashMap<&str, u32> = targets.iter().map(|t| (*t, 0)).collect();
+    let mut queue: VecDeque<&str> = targets.iter().copied().collect();
+    while let Some(id) = queue.pop_front() {
+        let d = distance[id] + 1;
+        if d > depth {
+            continue;
+        }
+        for caller in callers.get(id).into_iter().flatten() {
+            if !distance.contains_key(caller) {
+                distance.insert(*caller, d);
+                queue.push_back(*caller);
+            }
+        }
+    }
+
+    let mut walk = Walk {
+        links: &links,
+        targets: &targets,
+        distance: &distance,
+        limit,
+        path: Vec::new(),
+        found: Vec::new(),
+    };
+    let shortest = sources
+        .iter()
+        .filter_map(|s| distance.get(s.id.as_str()))
+        .min()
+        .map_or(depth + 1, |d| (*d).max(1));
+    for hops in shortest..=depth {
+        for source in &sources {
+            walk.path.push((source.id.as_str(), None));
+            walk.extend(hops);
+            walk.path.pop();
+        }
+        if walk.found.len() >= limit {
+            break;
+        }
+    }
+
+    Ok(walk
+        .found
+        .into_iter()
+        .map(|path| CallPath {
+            hops: path.len() as u32 - 1,
+            steps: path
+                .into_iter()
+                .map(|(id, via)| {
+                    let s = by_id[id];
+                    PathStep {
+                        id: s.id.clone(),
+                        name: s.name.clone(),
+                        kind: s.kind,
+                        file_path: s.file_path.clone(),
+                        start_line: s.start_line,
+                        via: via.map(|(edge, by_name)| PathHop {
+                            kind: edge.kind,
+                            line: edge.line,
+                            by_name,
+                        }),
+                    }
+                })
+                .collect(),
+        })
+        .collect())
+}
+
+type Trail<'a> = Vec<(&'a str, Option<(&'a Edge, bool)>)>;
+
+/// Depth-first enumeration of the paths of an exact length.
+struct Walk<'a> {
+    links: &'a HashMap<&'a str, Vec<Link<'a>>>,
+    targets: &'a HashSet<&'a str>,
+    distance: &'a HashMap<&'a str, u32>,
+    limit: usize,
+    path: Trail<'a>,
+    found: Vec<Trail<'a>>,
+}
+
+impl<'a> Walk<'a> {
+    /// Extend `path` by `hops` more edges to a target, recording each path found.
+    fn extend(&mut self, hops: u32) {
+        let Some(&(id, _)) = self.path.last() else {
+            return;
+        };
+        if hops == 0 {
+            if self.targets.contains(id) {
+                self.found.push(self.path.clone());
+            }
+            return;
+        }
+        if self.path.len() > 1 && self.targets.contains(id) {
+            return;
+        }
+        let links = self.links;
+        for &(next, edge, by_name) in links.get(id).into_iter().flatten() {
+            if self.found.len() >= self.limit {
+                return;
+            }
+            // Only go where a target is still within reach
+            let within = self.distance.get(next).is_some_and(|d| *d < hops);
+            if !within || self.path.iter().any(|(seen, _)| *seen == next) {
+                continue;
+            }
+            self.path.push((next, Some((edge, by_name))));
+            self.extend(hops - 1);
+            self.path.pop();
+        }
+    }
+}
+
+fn is_callable(kind: SymbolKind) -> bool {
+    matches!(
+        kind,
+        SymbolKind::Function | SymbolKind::Method | SymbolKind::Class
+    )
+}
+
+/// Symbols `query` names: an ID, a name, or `Parent.name` / `Parent::name`.
+/// Imports are left out.
+fn matching<'a>(
+    symbols: &'a [Symbol],
+    by_id: &HashMap<&str, &'a Symbol>,
+    query: &str,
+) -> Vec<&'a Symbol> {
+    if let Some(symbol) = by_id.get(query) {
+        return vec![*symbol];
+    }
+    let named = |name: &str| -> Vec<&'a Symbol> {
+        symbols
+            .iter()
+            .filter(|s| s.name == name && s.kind != SymbolKind::Import)
+            .collect()
+    };
+    let found = named(query);
+    if !found.is_empty() {
+        return found;
+    }
+    let Some((parent, name)) = query.rsplit_once("::").or_else(|| query.rsplit_once('.')) else {
+        return found;
+    };
+    let parent = short_name(parent);
+    named(name)
+        .into_iter()
+        .filter(|s| {
+            s.parent_id
+                .as_deref()
+                .and_then(|p| by_id.get(p))
+                .is_some_and(|p| p.name == parent)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn setup() -> ShardedDatabase {
+        let db = Database::open_memory().unwrap();
+        let sym = |name: &str, kind, file: &str, line| {
+            Symbol::new(name, kind, file, line, line + 2, 0, 10)
+        };
+        let handler = sym("login_handler", SymbolKind::Function, "api/views.py", 1);
+        let auth = sym("authenticate", SymbolKind::Function, "api/auth.py", 1);
+        let session = sym("load_session", SymbolKind::Function, "api/session.py", 1);
+        let database = sym("Database", SymbolKind::Class, "api/db.py", 1);
+        let open = sym("open", SymbolKind::Method, "api/db.py", 2).with_parent(Some(&database.id));
+        let file_open = sym("open", SymbolKind::Function, "api/files.py", 1);
+        let audit = sym("audit", SymbolKind::Function, "api/audit.py", 1);
+        let call = |from: &Symbol, name: &str, to: Option<&Symbol>, line| Edge {
+            target_id: to.map(|s| s.id.clone()),
+            ..Edge::new(&from.id, name, EdgeKind::Calls, &from.file_path, line)
+        };
+        db.insert_symbols(&[
+            handler.clone(),
+            auth.clone(),
+            session.clone(),
+            database,
+            open.clone(),
+            file_open,
+            audit.clone(),
+        ])
+        .unwrap();
+        db.insert_edges(&[
+            call(&handler, "authenticate", Some(&auth), 2),
+            call(&handler, "authenticate", Some(&auth), 3),
+            call(&handler, "load_session", Some(&session), 4),
+            call(&auth, "Database.open", Some(&open), 2),
+            call(&auth, "load_session", Some(&session), 3),
+            // Unresolved: `open` is ambiguous, `audit` is not
+            call(&session, "db.open", None, 2),
+            call(&session, "self.audit", None, 3),
+            call(&audit, "authenticate", Some(&auth), 2),
+        ])
+        .unwrap();
+        ShardedDatabase::from(db)
+    }
+
+    fn names(path: &CallPath) -> Vec<&str> {
+        path.steps.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_find_paths() {
+        let db = setup();
+        let paths = find_paths(&db, "login_handler", "Database.open", DEFAULT_DEPTH, 5).unwrap();
+        let found: Vec<Vec<&str>> = paths.iter().map(names).collect();
+        // Shortest first; the second call to authenticate adds no path, and no
+        // path goes through a symbol twice.
+        assert_eq!(
+            found,
+            [
+                vec!["login_handler", "authenticate", "open"],
+                vec![
+                    "login_handler",
+                    "load_session",
+                    "audit",
+                    "authenticate",
+                    "open"
+                ],
+            ]
+        );
+        assert_eq!(paths[0].hops, 2);
+        assert!(paths[0].steps[0].via.is_none());
+        let via = paths[0].steps[1].via.as_ref().unwrap();
+        assert_eq!(
+            (via.kind, via.line, via.by_name),
+            (EdgeKind::Calls, 2, false)
+        );
+        assert_eq!(paths[0].steps[2].file_path, "api/db.py");
+        assert!(paths[1].steps[2].via.as_ref().unwrap().by_name);
+
+        let within = |depth, limit| {
+            find_paths(&db, "login_handler", "api/db.py:open:2", depth, limit)
+                .unwrap()
+                .len()
+        };
+        assert_eq!((within(1, 5), within(3, 5), within(4, 5)), (0, 1, 2));
+        assert_eq!(within(DEFAULT_DEPTH, 1), 1);
+
+        assert!(find_paths(&db, "missing", "audit", 3, 5).is_err());
+        assert!(find_paths(&db, "audit", "Session.open", 3, 5).is_err());
+    }
+}
@@ -1,6 +1,14 @@
 use clap::{Parser, Subcommand, ValueEnum};
 
-use crate::types::{EdgeKind, SymbolKind};
+use crate::cycles::CycleScope;
+use crate::db::FtsWeights;
+use crate::export::{ExportFormat, ExportTable};
+use crate::packages::BuildTool;
+use crate::rag::embeddings::EmbeddingModelId;
+use crate::rag::freshness::EmbedPolicy;
+use crate::setops::SetOp;
+use crate::testmap::TestRunner;
+use crate::types::{EdgeKind, SymbolKind, Visibility, ENTRYPOINT_KINDS};
 
 #[derive(Debug, Parser)]
 #[command(name = "cartog")]
@@ -13,6 +21,15 @@ pub struct Cli {
     /// Output as JSON
     #[arg(long, global = true)]
     pub json: bool,
+
+    /// Answer the query against the code as of a git revision (commit, tag, branch)
+    #[arg(long, global = true, value_name = "REV")]
+    pub as_of: Option<String>,
+
+    /// Use the named index instead of `.cartog.db`, e.g. one per branch
+    /// (see `[index] per_branch` in .cartog.toml)
+    #[arg(long, global = true, value_name = "NAME", conflicts_with = "as_of")]
+    pub index: Option<String>,
 }
 
 /// Filter for symbol kinds in the search command.
@@ -59,6 +76,138 @@ impl From<EdgeKindFilter> for EdgeKind {
     }
 }
 
+/// Visibility filter for the doc-coverage command.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum VisibilityFilter {
+    Public,
+    Private,
+    Protected,
+    All,
+}
+
+impl From<VisibilityFilter> for Option<Visibility> {
+    fn from(f: VisibilityFilter) -> Self {
+        match f {
+            VisibilityFilter::Public => Some(Visibility::Public),
+            VisibilityFilter::Private => Some(Visibility::Private),
+            VisibilityFilter::Protected => Some(Visibility::Protected),
+            VisibilityFilter::All => None,
+        }
+    }
+}
+
+/// File format for the export command.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormatArg {
+    Csv,
+    Parquet,
+    /// Graphviz digraph of symbols and edges
+    Dot,
+}
+
+impl From<ExportFormatArg> for ExportFormat {
+    fn from(f: ExportFormatArg) -> Self {
+        match f {
+            ExportFormatArg::Csv => ExportFormat::Csv,
+            ExportFormatArg::Parquet => ExportFormat::Parquet,
+            ExportFormatArg::Dot => ExportFormat::Dot,
+        }
+    }
+}
+
+/// Table selection for the export command.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportTableArg {
+    Symbols,
+    Edges,
+    Files,
+}
+
+impl From<ExportTableArg> for ExportTable {
+    fn from(t: ExportTableArg) -> Self {
+        match t {
+            ExportTableArg::Symbols => ExportTable::Symbols,
+            ExportTableArg::Edges => ExportTable::Edges,
+            ExportTableArg::Files => ExportTable::Files,
+        }
+    }
+}
+
+/// Import graph granularity for the cycles command.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CycleScopeArg {
+    File,
+    Dir,
+}
+
+impl From<CycleScopeArg> for CycleScope {
+    fn from(s: CycleScopeArg) -> Self {
+        match s {
+            CycleScopeArg::File => CycleScope::File,
+            CycleScopeArg::Dir => CycleScope::Dir,
+        }
+    }
+}
+
+/// Set operation for the set command.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum SetOpArg {
+    /// Symbols in every operand
+    Intersect,
+    /// Symbols in any operand
+    Union,
+    /// Symbols of the first operand in none of the others
+    Diff,
+}
+
+impl From<SetOpArg> for SetOp {
+    fn from(op: SetOpArg) -> Self {
+        match op {
+            SetOpArg::Intersect => SetOp::Intersect,
+            SetOpArg::Union => SetOp::Union,
+            SetOpArg::Diff => SetOp::Diff,
+        }
+    }
+}
+
+/// Build tool for `packages sync`.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum BuildToolArg {
+    Cargo,
+    Nx,
+    Bazel,
+}
+
+impl From<BuildToolArg> for BuildTool {
+    fn from(t: BuildToolArg) -> Self {
+        match t {
+            BuildToolArg::Cargo => BuildTool::Cargo,
+            BuildToolArg::Nx => BuildTool::Nx,
+            BuildToolArg::Bazel => BuildTool::Bazel,
+        }
+    }
+}
+
+/// Test runner for the select-tests command.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum TestRunnerArg {
+    Pytest,
+    Go,
+    Cargo,
+    Jest,
+}
+
+impl From<TestRunnerArg> for TestRunner {
+    fn from(r: TestRunnerArg) -> Self {
+        match r {
+            TestRunnerArg::Pytest => TestRunner::Pytest,
+            TestRunnerArg::Go => TestRunner::Go,
+            TestRunnerArg::Cargo => TestRunner::Cargo,
+            TestRunnerArg::Jest => TestRunner::Jest,
+        }
+    }
+}
+
 #[derive(Debug, Subcommand)]
 pub enum Command {
     /// Build or rebuild the code graph index
@@ -70,18 +219,122 @@ pub enum Command {
         /// Force full re-index, bypassing change detection
         #[arg(long)]
         force: bool,
+
+        /// Split the index into one database per top-level directory (for very large
+        /// repos; enabled automatically past 2M symbols)
+        #[arg(long)]
+        shard: bool,
+
+        /// Also scan string literals for embedded SQL, HTML and regexes (kept for later
+        /// runs; see `cartog injections`)
+        #[arg(long, conflicts_with = "no_injections")]
+        injections: bool,
+
+        /// Stop scanning string literals and drop the stored injections
+        #[arg(long)]
+        no_injections: bool,
+
+        /// Cache each file's extraction by content hash and grammar version, so
+        /// re-indexing reverted or force-indexed files skips parsing (kept for later runs)
+        #[arg(long, conflicts_with = "no_parse_cache")]
+        parse_cache: bool,
+
+        /// Stop caching extractions and drop the cached ones
+        #[arg(long)]
+        no_parse_cache: bool,
+
+        /// Also index files that `.gitignore` files ignore, for this run (see
+        /// `[index] gitignore` to make it permanent)
+        #[arg(long)]
+        no_ignore: bool,
+
+        /// Threads parsing files in parallel (default: one per CPU)
+        #[arg(long, short = 'j', value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+
+        /// Start from an index built in another clone (e.g. a `cartog backup` from CI)
+        /// when it was built from the same commit; only files that differ are
+        /// re-indexed. Falls back to a normal index otherwise
+        #[arg(long, value_name = "ARTIFACT", conflicts_with = "shard")]
+        reuse: Option<std::path::PathBuf>,
     },
 
     /// Show symbols and structure of a file
     Outline {
         /// File path to outline
         file: String,
+
+        /// Only public symbols: no private helpers, no imports
+        #[arg(long)]
+        public_only: bool,
+
+        /// Only kind, name and signature per symbol: no variables, no line or byte ranges
+        #[arg(long)]
+        signature_only: bool,
+    },
+
+    /// Print the source of a symbol's definitions, with signature and docstring
+    Show {
+        /// Symbol name to show
+        name: String,
+
+        /// Only the definition in this file
+        #[arg(long)]
+        file: Option<String>,
+
+        /// Lines of context before and after each definition (at most 50)
+        #[arg(long, short = 'C', default_value_t = 0)]
+        context: u32,
+    },
+
+    /// Bundle a symbol's code, callers, callees and related code under a token budget
+    Context {
+        /// Symbol name, or a search query whose best hit is the subject
+        query: String,
+
+        /// Token budget of the bundle (estimated at ~4 bytes per token)
+        #[arg(long, default_value_t = 4000)]
+        budget: u64,
+
+        /// Find related code by keyword only, without loading any model
+        #[arg(long)]
+        keyword_only: bool,
     },
 
     /// Find what a symbol calls
     Callees {
         /// Symbol name to search for
         name: String,
+
+        /// Show the source text of each call line
+        #[arg(long)]
+        lines: bool,
+
+        /// Only calls made in files of this language (typescript includes .tsx)
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(
+            ["python", "typescript", "javascript", "rust", "go", "ruby", "csharp", "prisma"]
+        ))]
+        lang: Option<String>,
+
+        /// Maximum results to return (default: all, or `limits.callees` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+
+    /// Find what calls a symbol (reverse of `callees`)
+    Callers {
+        /// Symbol name to search for
+        name: String,
+
+        /// Only calls made in files of this language (typescript includes .tsx)
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(
+            ["python", "typescript", "javascript", "rust", "go", "ruby", "csharp", "prisma"]
+        ))]
+        lang: Option<String>,
+
+        /// Maximum results to return (default: all, or `limits.callers` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
     },
 
     /// Transitive impact analysis — what breaks if this changes?
@@ -92,6 +345,63 @@ pub enum Command {
         /// Maximum depth of transitive analysis
         #[arg(long, default_value = "3")]
         depth: u32,
+
+        /// Only start from references resolved to a symbol of this kind
+        #[arg(long)]
+        target_kind: Option<SymbolKindFilter>,
+
+        /// Only follow references made in files of this language (typescript includes .tsx)
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(
+            ["python", "typescript", "javascript", "rust", "go", "ruby", "csharp", "prisma"]
+        ))]
+        lang: Option<String>,
+
+        /// Group by depth, then file, then referencing symbol
+        #[arg(long, conflicts_with = "summary")]
+        tree: bool,
+
+        /// Only count the impacted symbols per file
+        #[arg(long)]
+        summary: bool,
+
+        /// Maximum results to return (default: all, or `limits.impact` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+
+    /// Symbols that can raise an exception or error type, directly or through calls
+    Raises {
+        /// Exception or error type name (e.g. ValueError, ParseError, ErrNotFound)
+        name: String,
+
+        /// Maximum depth of transitive callers
+        #[arg(long, default_value = "3")]
+        depth: u32,
+
+        /// Maximum results to return (default: all, or `limits.raises` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+
+    /// Call and reference paths from one symbol to another, shortest first
+    ///
+    /// Symbols are given by name, ID, or name qualified by their class
+    /// (`Database.open`). Unresolved calls are followed when a single function,
+    /// method or class has the name.
+    Path {
+        /// Symbol the paths start from
+        from: String,
+
+        /// Symbol the paths lead to
+        to: String,
+
+        /// Maximum number of hops
+        #[arg(long, default_value = "6")]
+        depth: u32,
+
+        /// Maximum paths to return (default: 5, or `limits.path` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
     },
 
     /// All references to a symbol (calls, imports, inherits, references, raises)
@@ -102,6 +412,28 @@ pub enum Command {
         /// Filter by edge kind
         #[arg(long)]
         kind: Option<EdgeKindFilter>,
+
+        /// Only references in files matching this glob (e.g. 'src/auth/**')
+        #[arg(long = "in", value_name = "GLOB")]
+        path_glob: Option<String>,
+
+        /// Only references resolved to a symbol of this kind (e.g. calls to a class)
+        #[arg(long)]
+        target_kind: Option<SymbolKindFilter>,
+
+        /// Only references made in files of this language (typescript includes .tsx)
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(
+            ["python", "typescript", "javascript", "rust", "go", "ruby", "csharp", "prisma"]
+        ))]
+        lang: Option<String>,
+
+        /// Group references by the qualified name of their target, listing its definitions
+        #[arg(long)]
+        group: bool,
+
+        /// Maximum results to return (default: all, or `limits.refs` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
     },
 
     /// Show inheritance hierarchy for a class
@@ -114,10 +446,71 @@ pub enum Command {
     Deps {
         /// File path
         file: String,
+
+        /// Maximum results to return (default: all, or `limits.deps` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
     },
 
     /// Index statistics summary
-    Stats,
+    Stats {
+        /// Break the counts down per directory
+        #[arg(long)]
+        by_dir: bool,
+
+        /// Directory components to group by with --by-dir
+        #[arg(long, default_value = "2", requires = "by_dir")]
+        depth: usize,
+
+        /// Symbols most often returned by MCP tool calls (needs `serve --audit`)
+        #[arg(long, conflicts_with = "by_dir")]
+        hot_symbols: bool,
+
+        /// Only count tool calls within this age (e.g. `12h`, `7d`, `2w`)
+        #[arg(long, value_name = "AGE", value_parser = parse_age, requires = "hot_symbols")]
+        since: Option<std::time::Duration>,
+
+        /// Number of symbols to show with --hot-symbols
+        #[arg(short = 'n', long, default_value = "20", requires = "hot_symbols")]
+        limit: u32,
+    },
+
+    /// Index presence, freshness, lock and model readiness: whether to index before querying
+    Health,
+
+    /// Check the index database for corruption and keyword search index drift
+    Verify {
+        /// Rebuild the keyword search index if it drifted from the symbol contents
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Check the index for integrity problems and missing models
+    ///
+    /// Everything `verify` checks, plus edges, contents and embeddings left by
+    /// removed symbols, indexed files whose content changed since (by hash), and
+    /// whether the ONNX models semantic search needs are downloaded. Fails on any
+    /// problem; missing models only count when the index has embeddings.
+    Doctor {
+        /// Delete orphaned rows, re-index changed files and rebuild a drifted keyword search index
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Run the extractors over the benchmark fixtures and report drift from their golden files
+    ///
+    /// Compares file, symbol and edge counts (per kind) and key symbols with
+    /// `benchmarks/selftest/<fixture>.json`, and names the grammars that changed since
+    /// the golden files were made. Fails on any drift.
+    Selftest {
+        /// Benchmarks directory holding `fixtures/` and `selftest/` (default: `benchmarks/`)
+        #[arg(long)]
+        dir: Option<std::path::PathBuf>,
+
+        /// Rewrite the golden counts from this build, keeping their key symbols
+        #[arg(long)]
+        update: bool,
+    },
 
     /// Search symbols by name (case-insensitive prefix + substring match)
     Search {
@@ -132,9 +525,338 @@ pub enum Command {
         #[arg(long)]
         file: Option<String>,
 
-        /// Maximum results to return (default: 30, max: 100)
-        #[arg(long, default_value = "30")]
-        limit: u32,
+        /// Only symbols in files of this language (typescript includes .tsx)
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(
+            ["python", "typescript", "javascript", "rust", "go", "ruby", "csharp", "prisma"]
+        ))]
+        lang: Option<String>,
+
+        /// Maximum results to return (default: 30, or `limits.search` in .cartog.toml; max: 100)
+        #[arg(long)]
+        limit: Option<u32>,
+
+        /// Also list TODO/FIXME/HACK/XXX/SAFETY comments whose text matches the query
+        #[arg(long)]
+        anchors: bool,
+
+        /// Fold names differing only by numbers or generated suffixes (FooImpl1, FooImpl2)
+        #[arg(long)]
+        collapse: bool,
+
+        /// Group overloads and same-name definitions by qualified name (Parser::parse)
+        #[arg(long)]
+        group: bool,
+
+        /// Include the docstring of each hit's parent (a method's class) as `parent_docstring`
+        #[arg(long)]
+        context: bool,
+    },
+
+    /// Report functions, methods and classes lacking docstrings, per file
+    DocCoverage {
+        /// Which symbols to count
+        #[arg(long, default_value = "public")]
+        visibility: VisibilityFilter,
+
+        /// Number of largest undocumented symbols to list (default: 10, or
+        /// `limits.doc-coverage` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+
+    /// List TODO/FIXME/HACK/XXX/SAFETY comments with their enclosing symbol
+    Todos {
+        /// Only comments in this file or directory (path prefix)
+        #[arg(long)]
+        path: Option<String>,
+
+        /// Only this tag (todo, fixme, hack, xxx, safety)
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Maximum results to return (default: 100, or `limits.todos` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+
+    /// Search string literals holding SQL, HTML or regexes (needs `index --injections`)
+    ///
+    /// Every word of QUERY must appear in the literal (case-insensitive), e.g.
+    /// `cartog injections "users where" --lang sql`.
+    Injections {
+        /// Words the literal must contain
+        query: Option<String>,
+
+        /// Only this embedded language (sql, html, regex)
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Only literals in this file or directory (path prefix)
+        #[arg(long)]
+        path: Option<String>,
+
+        /// Maximum results to return (default: 100, or `limits.injections` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+
+    /// List the tests that exercise a symbol, directly or through helpers
+    ///
+    /// Tests are recognized by file (tests/, test_*.py, *_test.go, *.spec.ts, ...) and
+    /// by name (test_*, Test*, *Test, or nested in a `mod tests` / test class).
+    TestsFor {
+        /// Symbol name
+        name: String,
+
+        /// Maximum number of hops from a test to the symbol
+        #[arg(long, default_value = "3")]
+        depth: u32,
+    },
+
+    /// Select the tests affected by a git revision range, for CI
+    ///
+    /// Maps changed lines to symbols and follows refs back to the tests reaching them.
+    /// With --format, prints arguments for the runner, e.g.
+    /// `pytest $(cartog select-tests --rev main..HEAD --format pytest)`.
+    SelectTests {
+        /// Revision range passed to `git diff` (e.g. main..HEAD, or a single rev vs working tree)
+        #[arg(long, default_value = "HEAD")]
+        rev: String,
+
+        /// Print runner-specific filter arguments instead of a report
+        #[arg(long)]
+        format: Option<TestRunnerArg>,
+
+        /// Maximum number of hops from a test to a changed symbol
+        #[arg(long, default_value = "3")]
+        depth: u32,
+    },
+
+    /// List public functions, methods and classes that no test references
+    Untested {
+        /// Only symbols in this file or directory (path prefix)
+        #[arg(long)]
+        dir: Option<String>,
+
+        /// Maximum results to return (default: 100, or `limits.untested` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+
+    /// List entry points: main functions, routes, CLI commands, tasks, script guards
+    Entrypoints {
+        /// Only entry points of this kind
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(ENTRYPOINT_KINDS))]
+        kind: Option<String>,
+
+        /// Maximum results to return (default: all, or `limits.entrypoints` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+
+    /// List code no entry point reaches through calls, references or inheritance
+    ///
+    /// Unlike a zero-fan-in check, code only used by other dead code (or by tests)
+    /// is reported too. Symbols that dynamic dispatch or an unresolved call from
+    /// live code may still reach are marked low-confidence.
+    Reachable {
+        /// Start from this symbol (name or ID) instead of every entry point
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Only report symbols in this file or directory (path prefix)
+        #[arg(long)]
+        dir: Option<String>,
+
+        /// Maximum unreachable symbols to list (default: 100, or `limits.reachable` in
+        /// .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+
+    /// Find circular imports between files or directories
+    ///
+    /// Reports each group of files that import each other (a strongly connected
+    /// component of the resolved import graph) with a shortest cycle through it
+    /// and the import lines along that cycle.
+    Cycles {
+        /// Only files of this language
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(
+            ["python", "typescript", "javascript", "rust", "go", "ruby", "csharp"]
+        ))]
+        lang: Option<String>,
+
+        /// Graph nodes: files, or directories (imports within a directory are ignored)
+        #[arg(long, default_value = "file")]
+        scope: CycleScopeArg,
+
+        /// Exit with an error when a cycle is found (for CI)
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Find ORM models and raw SQL that use a database table
+    ///
+    /// Models are SQLAlchemy/Django classes, ActiveRecord models, GORM structs and
+    /// Prisma models, linked to their table at index time. SQL references are lines
+    /// naming the table after FROM, JOIN, INTO, UPDATE or TABLE, in any language.
+    TableUsages {
+        /// Table name (case-insensitive, e.g. users)
+        table: String,
+    },
+
+    /// Explain how edges to a name resolve (or why they don't)
+    ///
+    /// Replays resolution step by step: module file (relative JS/TS imports), same
+    /// file, pinned definition, same directory, unique project-wide match. Lists the
+    /// definitions each step considered and why each was accepted or rejected.
+    ExplainResolution {
+        /// Target name (e.g. validate_token) or an edge ID from a previous explanation
+        target: String,
+
+        /// Only edges from this file
+        #[arg(long)]
+        file: Option<String>,
+
+        /// Maximum number of edges to explain (default: 10, or `limits.explain-resolution`
+        /// in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+
+    /// Pin the canonical definition of an ambiguous name
+    ///
+    /// Search lists the pinned definition first and edge resolution prefers it.
+    /// Pins are stored in the index database and listed by `cartog stats`.
+    Pin {
+        /// Symbol name (e.g. Config)
+        name: String,
+
+        /// File containing the canonical definition (e.g. src/config.rs)
+        file: String,
+    },
+
+    /// Remove a pin set with `cartog pin`
+    Unpin {
+        /// Symbol name
+        name: String,
+    },
+
+    /// Delete scratch notes agents attached to symbols (`cartog_annotate_symbol`) and
+    /// their symbol reservations (`cartog_reserve_symbols`)
+    Clean,
+
+    /// Print the JSON Schema of a command's `--json` output
+    ///
+    /// Without a command, lists the commands that have one. The same schemas are
+    /// served to MCP clients as `cartog://schema/<command>` resources.
+    Schema {
+        /// Command name, e.g. `refs` or `rag search`
+        command: Vec<String>,
+    },
+
+    /// Export symbols, edges and files to CSV or Parquet, or the graph to Graphviz DOT
+    ///
+    /// Writes one file per table to the output directory. Edges include their source
+    /// symbol and, when resolved, their target symbol's kind, file and line. With
+    /// `--format dot`, writes `graph.dot` instead: symbols clustered by file, edges
+    /// colored by kind, unresolved targets dashed.
+    Export {
+        /// Output format
+        #[arg(long, default_value = "csv")]
+        format: ExportFormatArg,
+
+        /// Tables to export (comma-separated)
+        #[arg(long, value_delimiter = ',', default_value = "symbols,edges,files")]
+        tables: Vec<ExportTableArg>,
+
+        /// Output directory
+        #[arg(long, default_value = "cartog-export")]
+        out: std::path::PathBuf,
+
+        /// DOT only: keep the symbols within --depth edges of the symbols with this name
+        #[arg(long)]
+        root: Option<String>,
+
+        /// DOT only: edges to follow from --root, in either direction
+        #[arg(long, default_value = "2", requires = "root")]
+        depth: u32,
+    },
+
+    /// List the named indexes of this project (built with `--index <NAME>`)
+    Indexes,
+
+    /// Back up the index database, safe while `watch` or `serve` is running
+    ///
+    /// Uses SQLite's online backup API, so the copy is a consistent snapshot rather
+    /// than a torn copy of the database and its write-ahead log.
+    Backup {
+        /// Destination file (replaced if it exists)
+        #[arg(long)]
+        to: std::path::PathBuf,
+    },
+
+    /// Optimize the index database: planner statistics, FTS merge, free pages
+    ///
+    /// Runs `ANALYZE`, `PRAGMA optimize`, an FTS5 segment merge and an incremental
+    /// vacuum, then truncates the write-ahead log. A lighter pass also runs on its own
+    /// after every 500 re-indexed files.
+    Maintain {
+        /// Rebuild the whole file with VACUUM (slow; needs exclusive access). Also
+        /// enables incremental auto-vacuum on databases created by older versions.
+        #[arg(long)]
+        vacuum: bool,
+    },
+
+    /// Remove index entries of files that no longer exist in the working tree
+    ///
+    /// Checks every indexed path without parsing anything, e.g. for ghost entries of
+    /// files deleted while no watcher was running. Reports the rows reclaimed.
+    Prune {
+        /// Indexed root to check paths against (defaults to current directory)
+        #[arg(default_value = ".")]
+        path: String,
+
+        /// Only remove files last indexed with a modification time at least this old
+        /// (e.g. `90m`, `12h`, `7d`, `2w`)
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        older_than: Option<std::time::Duration>,
+
+        /// List what would be removed without removing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Outline digests of files changed since an index generation, for client-side mirrors
+    ///
+    /// Lists each file (re)indexed after `--since` with a digest of its outline, and the
+    /// files removed from the index since. Pass the returned generation as `--since` on
+    /// the next call; refetch outlines whose digest changed.
+    Sync {
+        /// Index generation the client last synced to (every file when omitted)
+        #[arg(long, value_name = "GENERATION")]
+        since: Option<u64>,
+
+        /// Only this file (repeatable), e.g. the files open in the editor
+        #[arg(long = "file", value_name = "PATH")]
+        files: Vec<String>,
+
+        /// Include the outline of each listed file
+        #[arg(long)]
+        outlines: bool,
+    },
+
+    /// Per-owner summary of symbols changed or impacted by a git revision range
+    ///
+    /// Maps changed lines to symbols, follows their transitive impact, and groups
+    /// everything by CODEOWNERS owner. Run against an index of the range's new side.
+    OwnersImpact {
+        /// Revision range passed to `git diff` (e.g. main..HEAD, HEAD~3, or a single rev vs working tree)
+        range: String,
+
+        /// Maximum depth of transitive impact
+        #[arg(long, default_value = "3")]
+        depth: u32,
     },
 
     /// Watch for file changes and auto-re-index
@@ -154,6 +876,76 @@ pub enum Command {
         /// Delay in seconds before batch embedding after last index
         #[arg(long, default_value = "30")]
         rag_delay: u64,
+
+        /// Don't re-index on changes to files of these languages (comma-separated)
+        #[arg(long, value_delimiter = ',', value_parser = clap::builder::PossibleValuesParser::new(
+            ["python", "typescript", "javascript", "rust", "go", "ruby", "csharp", "prisma"]
+        ))]
+        skip_lang: Vec<String>,
+
+        /// Don't re-index on changes to test files (tests/, test_*.py, *_test.go, ...)
+        #[arg(long)]
+        skip_tests: bool,
+
+        /// Don't embed symbols of test files
+        #[arg(long, requires = "rag")]
+        rag_skip_tests: bool,
+
+        /// Only embed public symbols
+        #[arg(long, requires = "rag")]
+        rag_public_only: bool,
+
+        /// Replay the file changes scripted in this JSON file against a temporary
+        /// copy of the directory, check the index after each step, and exit
+        #[arg(long, value_name = "SCRIPT", conflicts_with = "rag")]
+        simulate: Option<String>,
+    },
+
+    /// Serve a local web UI of the module graph and symbol neighborhoods
+    ///
+    /// Binds to 127.0.0.1 only. The page reloads when the index changes, e.g. while
+    /// `cartog watch` runs or with `--watch`.
+    Viz {
+        /// Port to listen on
+        #[arg(long, default_value = "8080")]
+        port: u16,
+
+        /// Also watch for file changes and re-index, like `cartog watch`
+        #[arg(long)]
+        watch: bool,
+    },
+
+    /// Browse the index in the terminal: search, outline, references and source
+    ///
+    /// Enter opens the selected symbol from any pane (its file's outline and its
+    /// references), r/c/e switch between references, callers and callees, and
+    /// Backspace goes back to the previous symbol. Read-only.
+    Tui {
+        /// Start with the results of this search
+        query: Option<String>,
+    },
+
+    /// Run queries at a prompt, with numbered results to refer back to
+    ///
+    /// `search`, `rag`, `outline`, `refs`, `callers`, `callees` and `impact` list
+    /// numbered results; a number stands for that result in the next command
+    /// (`refs 3`, `show 2`). The index and models stay loaded between queries.
+    /// Reads commands from stdin, so they can be piped in. Read-only.
+    Repl,
+
+    /// Intersect, union or diff the symbols of several query results
+    ///
+    /// Each operand is a `kind:argument` query run on the spot (`search:login`,
+    /// `refs:check`, `callers:check`, `callees:check`, `impact:check`,
+    /// `outline:src/auth.py`, `ids:ID1,ID2`), a file holding an earlier `--json`
+    /// output, or `-` for one on stdin. Results keep the first operand's order.
+    Set {
+        /// How to combine the operands
+        op: SetOpArg,
+
+        /// Queries or `--json` output files (at least two)
+        #[arg(required = true, num_args = 2..)]
+        operands: Vec<String>,
     },
 
     /// Start MCP server over stdio (for Claude Code, Cursor, and other MCP clients)
@@ -162,14 +954,123 @@ pub enum Command {
         #[arg(long)]
         watch: bool,
 
-        /// Enable automatic RAG embedding when watching
+        /// When embeddings catch up with index updates: eager (after each watcher
+        /// update, the default with a bare `--rag`; needs --watch), lazy (on the first
+        /// semantic search after an update) or manual (`cartog rag index` only)
+        #[arg(long, value_name = "POLICY", num_args = 0..=1, default_missing_value = "eager")]
+        rag: Option<EmbedPolicy>,
+
+        /// Expose Prometheus metrics at http://<ADDR>/metrics (e.g. 127.0.0.1:9464)
+        #[arg(long, value_name = "ADDR")]
+        metrics_addr: Option<std::net::SocketAddr>,
+
+        /// Record every MCP tool call in the local audit log
         #[arg(long)]
-        rag: bool,
+        audit: bool,
+
+        /// Days of audit history to keep (older entries are pruned)
+        #[arg(long, default_value = "30", requires = "audit")]
+        audit_retention_days: u32,
+
+        /// Serve this project's index as well, as `[NAME=]PATH` (repeatable). Tools take a
+        /// `project` parameter; without it they query every project. Replaces the default
+        /// of serving the current directory, so pass `--project .` to keep it
+        #[arg(long = "project", value_name = "[NAME=]PATH")]
+        projects: Vec<String>,
+
+        /// Load the downloaded semantic search models in the background at startup, so
+        /// the first cartog_rag_search does not wait for them (needs --rag; never downloads)
+        #[arg(long, requires = "rag")]
+        preload_models: bool,
+
+        /// Also allow returned code from files under this directory, e.g. a vendored
+        /// tree symlinked from outside the project (repeatable). Code from files that
+        /// resolve outside the project roots and these is withheld
+        #[arg(long = "allow-root", value_name = "PATH", conflicts_with = "api")]
+        allow_roots: Vec<std::path::PathBuf>,
+
+        /// Most bytes of code one tool result returns; longer bodies are cut
+        #[arg(
+            long,
+            default_value_t = 65536,
+            value_name = "N",
+            conflicts_with = "api"
+        )]
+        max_read_bytes: usize,
+
+        /// Serve a read-only HTTP JSON API on ADDR instead of MCP over stdio (GET /search,
+        /// /symbols/<ID>, /refs, /outline, /stats; same JSON as the CLI with --json)
+        #[arg(long, value_name = "ADDR", conflicts_with_all = ["rag", "metrics_addr", "audit", "projects"])]
+        api: Option<std::net::SocketAddr>,
+
+        /// Origin allowed to call the API from a browser (repeatable; `*` allows any)
+        #[arg(long = "cors-origin", value_name = "ORIGIN", requires = "api")]
+        cors_origins: Vec<String>,
     },
 
     /// Semantic code search (RAG pipeline)
     #[command(subcommand)]
     Rag(RagCommand),
+
+    /// Inspect the MCP tool-call audit log (recorded by `serve --audit`)
+    #[command(subcommand)]
+    Audit(AuditCommand),
+
+    /// Export audited search queries with the results used after them, for fine-tuning
+    ///
+    /// Reads the audit log of `serve --audit`. Each line pairs a `cartog_search` or
+    /// `cartog_rag_search` query with the results a later call named within 10 minutes
+    /// (positives) and the ones left unused (hard negatives). Results are written as
+    /// symbol IDs and hashes of their code, never the code itself.
+    EmitTrainingData {
+        /// Destination JSONL file (replaced if it exists)
+        #[arg(long)]
+        to: std::path::PathBuf,
+
+        /// Only queries within this age (e.g. `12h`, `7d`, `2w`)
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        since: Option<std::time::Duration>,
+    },
+
+    /// Package boundaries and dependencies from the build system (Cargo, Nx, Bazel)
+    #[command(subcommand)]
+    Packages(PackagesCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PackagesCommand {
+    /// Read the package graph from the build tool and store it with the index
+    ///
+    /// Runs `cargo metadata`, `nx graph` or `bazel query` in the current directory.
+    /// Re-run after adding packages or changing their dependencies.
+    Sync {
+        /// Build tool to read (detected from MODULE.bazel/WORKSPACE, nx.json, Cargo.toml)
+        #[arg(long)]
+        tool: Option<BuildToolArg>,
+    },
+
+    /// List the stored packages with their root and declared dependencies
+    List,
+
+    /// Compare a package's declared dependencies with the imports its files make
+    Deps {
+        /// Package name (as the build tool names it, e.g. `//lib/auth` for Bazel)
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AuditCommand {
+    /// Show the most recent audit entries, oldest first
+    Tail {
+        /// Number of entries to show
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: u32,
+
+        /// Only show calls to this tool (e.g. cartog_refs)
+        #[arg(long)]
+        tool: Option<String>,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -188,6 +1089,53 @@ pub enum RagCommand {
         force: bool,
     },
 
+    /// Switch the embedding model without a search outage, or show the switch's progress
+    ///
+    /// Keeps the current vectors while the new model's fill in on `rag index` (or in the
+    /// background under `watch --rag`); searches query both in the meantime. Once every
+    /// symbol has a new vector, the new model replaces the old one. Without MODEL,
+    /// shows the models and the migration's progress.
+    Migrate {
+        /// Model to migrate to: bge-small-en-v1.5 (default), bge-base-en-v1.5, all-minilm-l6-v2
+        #[arg(conflicts_with = "cancel")]
+        model: Option<EmbeddingModelId>,
+
+        /// Abandon the migration in progress, dropping the new model's vectors
+        #[arg(long)]
+        cancel: bool,
+    },
+
+    /// Show the embedding policy, how many symbols are embedded and the backlog
+    Status,
+
+    /// Rebuild the keyword search (FTS5) index from the stored symbol contents
+    RebuildFts,
+
+    /// Show or set the keyword search weights of name, split name and body matches
+    FtsWeights {
+        /// New weights as NAME,NORMALIZED,CONTENT (e.g. 10,5,1), stored in the index
+        weights: Option<FtsWeights>,
+
+        /// Restore the default weights
+        #[arg(long, conflicts_with = "weights")]
+        reset: bool,
+    },
+
+    /// Show or set how re-ranker scores map to `relevance` and which are dropped
+    Calibration {
+        /// Sigmoid temperature applied to the cross-encoder logits, stored in the index
+        #[arg(long)]
+        temperature: Option<f64>,
+
+        /// Relevance below which re-ranked results are dropped without --min-score
+        #[arg(long)]
+        min_relevance: Option<f64>,
+
+        /// Restore the default calibration
+        #[arg(long, conflicts_with_all = ["temperature", "min_relevance"])]
+        reset: bool,
+    },
+
     /// Semantic search over code symbols
     Search {
         /// Natural language query
@@ -197,8 +1145,60 @@ pub enum RagCommand {
         #[arg(long)]
         kind: Option<SymbolKindFilter>,
 
-        /// Maximum results to return
-        #[arg(long, default_value = "10")]
-        limit: u32,
+        /// Only symbols in files matching this gitignore-style glob (e.g. 'src/auth/**')
+        #[arg(long, value_name = "GLOB")]
+        path: Option<String>,
+
+        /// Only symbols in files of this language (typescript includes .tsx)
+        #[arg(long, value_parser = clap::builder::PossibleValuesParser::new(
+            ["python", "typescript", "javascript", "rust", "go", "ruby", "csharp", "prisma"]
+        ))]
+        lang: Option<String>,
+
+        /// Leave out symbols in files matching this glob (e.g. '*.test.ts')
+        #[arg(long, value_name = "GLOB")]
+        exclude: Option<String>,
+
+        /// Maximum results to return (default: 10, or `limits.rag-search` in .cartog.toml)
+        #[arg(long)]
+        limit: Option<u32>,
+
+        /// Keyword-only search (FTS5/BM25): no embedding or re-ranker model is loaded
+        #[arg(long)]
+        keyword_only: bool,
+
+        /// Drop re-ranked results with a calibrated relevance below this (0-1, default 0.01; see `rag calibration`)
+        #[arg(long, value_parser = parse_unit_interval)]
+        min_score: Option<f64>,
+
+        /// Fit result contents into about this many tokens: best score per token keeps
+        /// its body, the rest are cut to their signature line
+        #[arg(long)]
+        budget: Option<u64>,
     },
 }
+
+/// Parse an age such as `30s`, `90m`, `12h`, `7d` or `2w`.
+fn parse_age(s: &str) -> Result<std::time::Duration, String> {
+    let invalid = || format!("'{s}' is not an age like 90m, 12h, 7d or 2w");
+    let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (count, unit) = s.split_at(split);
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return Err(invalid()),
+    };
+    Ok(std::time::Duration::from_secs(count.saturating_mul(secs)))
+}
+
+/// Parse a score in `[0, 1]`.
+fn parse_unit_interval(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(v) if (0.0..=1.0).contains(&v) => Ok(v),
+        _ => Err(format!("'{s}' is not a number between 0 and 1")),
+    }
+}
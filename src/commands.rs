@@ -1,36 +1,187 @@
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+#[cfg(feature = "watch")]
 use std::time::Duration;
 
 use anyhow::{Context, Result};
+use schemars::JsonSchema;
 use serde::Serialize;
 
+use crate::api::{self, ApiConfig};
+use crate::callpath;
 use crate::cli::{EdgeKindFilter, SymbolKindFilter};
-use crate::db::{Database, DB_FILE, MAX_SEARCH_LIMIT};
+use crate::config::{ProjectConfig, CONFIG_FILE};
+use crate::context;
+use crate::cycles::{self, CycleScope};
+use crate::db::{self, DB_FILE, MAX_SEARCH_LIMIT};
+use crate::doctor;
+use crate::export::{self, ExportFormat, ExportTable};
+use crate::fingerprint;
+use crate::health;
+use crate::hooks::Hooks;
+use crate::impact;
 use crate::indexer;
+use crate::indexes;
+use crate::orm;
+use crate::overloads::{self, Reference, SymbolGroup};
+use crate::owners;
+use crate::packages::{self, BuildTool};
 use crate::rag;
-use crate::types::{EdgeKind, SymbolKind};
-use crate::watch::{self, WatchConfig};
+use crate::reach;
+use crate::schema;
+use crate::selftest;
+use crate::setops::{self, SetOp, SetQuery};
+use crate::shard::ShardedDatabase;
+#[cfg(feature = "watch")]
+use crate::simulate;
+use crate::skeleton;
+use crate::source;
+use crate::sync;
+use crate::testmap::{self, TestRunner};
+use crate::tokens;
+use crate::training;
+use crate::types::{
+    collapse_similar, Anchor, Edge, EdgeKind, HierarchyEntry, ImpactEntry, SymbolKind, SymbolMatch,
+    Visibility,
+};
+use crate::viz;
+#[cfg(feature = "watch")]
+use crate::watch::{self, WatchConfig, WatchFilter};
 
-fn open_db() -> Result<Database> {
-    Database::open(DB_FILE).context("Failed to open cartog database")
+/// Database used by commands instead of [`DB_FILE`] (set by `--as-of` or `--index`).
+static DB_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Point query commands at another index, e.g. a historical snapshot.
+pub fn use_db_path(path: PathBuf) {
+    let _ = DB_PATH.set(path);
+}
+
+/// Project settings from `.cartog.toml` (set once at startup).
+static CONFIG: OnceLock<ProjectConfig> = OnceLock::new();
+
+/// Take command defaults from the project settings.
+pub fn use_config(config: ProjectConfig) {
+    let _ = CONFIG.set(config);
+}
+
+/// Result limit of `command`, see [`ProjectConfig::limit`].
+fn limit_for(command: &str, flag: Option<u32>) -> Option<u32> {
+    CONFIG
+        .get_or_init(ProjectConfig::default)
+        .limit(command, flag)
+}
+
+/// What to ask the database for, one more than `limit`, so that
+/// [`truncate_results`] can tell whether results were left out.
+fn fetch_limit(limit: Option<u32>) -> u32 {
+    limit.map_or(u32::MAX, |limit| limit.saturating_add(1))
+}
+
+/// Keep the first `limit` of `items`, noting on stderr when results were dropped.
+///
+/// `items` holds either every result (`complete`), or one more than `limit` from a
+/// query limited with [`fetch_limit`].
+fn truncate_results<T>(command: &str, items: &mut Vec<T>, limit: Option<u32>, complete: bool) {
+    let Some(limit) = limit else {
+        return;
+    };
+    let total = items.len();
+    if total > limit as usize {
+        items.truncate(limit as usize);
+        note_truncated(command, limit, complete.then_some(total));
+    }
+}
+
+/// Tell on stderr (so `--json` output keeps its shape) that only `shown` of
+/// `total` results are listed, and how to see more.
+fn note_truncated(command: &str, shown: u32, total: Option<usize>) {
+    let shown = match total {
+        Some(total) => format!("{shown} of {total}"),
+        None => format!("the first {shown}"),
+    };
+    eprintln!("(showing {shown} results; raise with --limit or limits.{command} in {CONFIG_FILE})");
+}
+
+/// The database commands work on: [`DB_FILE`], unless [`use_db_path`] chose another.
+fn db_file() -> &'static Path {
+    DB_PATH.get().map_or(Path::new(DB_FILE), PathBuf::as_path)
+}
+
+fn open_db() -> Result<ShardedDatabase> {
+    ShardedDatabase::open(db_file()).context("Failed to open cartog database")
 }
 
-/// Print `data` as pretty JSON if `json` is true, otherwise call `human_fmt`.
+/// Print `data` as pretty JSON if `json` is true, otherwise call `human_fmt`
+/// followed by the estimated token cost of the results on stderr.
 fn output<T: Serialize>(data: &T, json: bool, human_fmt: impl FnOnce(&T)) -> Result<()> {
     if json {
-        println!("{}", serde_json::to_string_pretty(data)?);
+        print_json(data)?;
     } else {
         human_fmt(data);
+        print_token_total(data)?;
+    }
+    Ok(())
+}
+
+/// Print `data` as pretty JSON, with `estimated_tokens` on each result.
+fn print_json<T: Serialize>(data: &T) -> Result<()> {
+    let mut value = serde_json::to_value(data)?;
+    tokens::annotate(&mut value);
+    println!("{}", serde_json::to_string_pretty(&value)?);
+    Ok(())
+}
+
+/// Note what reading the bodies of the results would cost, if they carry code.
+fn print_token_total<T: Serialize>(data: &T) -> Result<()> {
+    let total = tokens::annotate(&mut serde_json::to_value(data)?);
+    if total > 0 {
+        eprintln!("({} to read these results)", tokens::format_total(total));
     }
     Ok(())
 }
 
 /// Build or rebuild the code graph index.
-pub fn cmd_index(path: &str, force: bool, json: bool) -> Result<()> {
+/// `injections` turns string literal scanning on or off for this and later runs,
+/// `parse_cache` the extraction cache.
+pub fn cmd_index(
+    path: &str,
+    mut force: bool,
+    shard: bool,
+    injections: Option<bool>,
+    parse_cache: Option<bool>,
+    gitignore: bool,
+    reuse: Option<&Path>,
+    json: bool,
+) -> Result<()> {
     let root = Path::new(path);
-    let db = open_db()?;
+    let adoption = match reuse {
+        Some(artifact) => reuse_index(artifact, root)?,
+        None => None,
+    };
+    let mut db = open_db()?;
+    if shard {
+        db.enable_sharding()?;
+    }
+    if let Some(on) = injections {
+        // Unchanged files are skipped, so turning scanning on needs a full pass.
+        force |= on && !db.scan_injections()?;
+        db.set_scan_injections(on)?;
+    }
+    if let Some(on) = parse_cache {
+        db.set_parse_cache(on)?;
+    }
 
-    let result = indexer::index_directory(&db, root, force)?;
+    let hooks = Hooks::new(
+        CONFIG.get_or_init(ProjectConfig::default).hooks.clone(),
+        Path::new("."),
+    );
+    let run = hooks.start(&db)?;
+    if let Some(adoption) = &adoption {
+        db.index_files(root, &fingerprint::stale_paths(adoption))?;
+    }
+    let result = db.index_with(root, indexer::IndexOptions { force, gitignore })?;
+    let shards = db.shard_count();
+    hooks.run(&hooks.finish(run, &db, root, &result)?);
 
     output(&result, json, |r| {
         println!(
@@ -41,13 +192,79 @@ pub fn cmd_index(path: &str, force: bool, json: bool) -> Result<()> {
             "  {} symbols, {} edges ({} resolved)",
             r.symbols_added, r.edges_added, r.edges_resolved
         );
+        if r.symbols_renamed > 0 {
+            println!(
+                "  {} renamed symbols kept their embeddings, notes and pins",
+                r.symbols_renamed
+            );
+        }
+        if r.files_from_cache > 0 {
+            println!("  {} files reused a cached parse", r.files_from_cache);
+        }
+        if !r.oversized.is_empty() {
+            println!(
+                "  {} files over the per-file limits of [index]:",
+                r.oversized.len()
+            );
+            for file in &r.oversized {
+                let cut = if file.truncated { " (truncated)" } else { "" };
+                println!(
+                    "    {}: {} symbols, {} edges{cut}",
+                    file.path, file.symbols, file.edges
+                );
+            }
+        }
+        if shards > 0 {
+            println!("  sharded across {shards} top-level directories");
+        }
     })
 }
 
+/// Replace the local index with `artifact` when it was built from the commit checked
+/// out at `root`. The outcome is noted on stderr, so `--json` output keeps its shape.
+fn reuse_index(artifact: &Path, root: &Path) -> Result<Option<fingerprint::Adoption>> {
+    let adoption = fingerprint::adopt_index(artifact, root, db_file())?;
+    match &adoption {
+        Some(a) => eprintln!(
+            "Reused index of {} from {}; re-indexing {} differing files",
+            a.fingerprint,
+            artifact.display(),
+            a.stale_files.len()
+        ),
+        None => eprintln!(
+            "{} was not built from this commit; indexing without it",
+            artifact.display()
+        ),
+    }
+    Ok(adoption)
+}
+
 /// Show symbols and structure of a file.
-pub fn cmd_outline(file: &str, json: bool) -> Result<()> {
+pub fn cmd_outline(file: &str, public_only: bool, signature_only: bool, json: bool) -> Result<()> {
     let db = open_db()?;
-    let symbols = db.outline(file)?;
+    let mut symbols = db.outline(file)?;
+    if public_only {
+        symbols = skeleton::public_only(symbols);
+    }
+
+    if signature_only {
+        let lines = skeleton::signature_lines(&symbols);
+        return output(&lines, json, |lines| {
+            if lines.is_empty() {
+                println!("No symbols found in {file}");
+            }
+            for line in lines {
+                println!(
+                    "{indent}{async_prefix}{kind} {name}{sig}",
+                    indent = "  ".repeat(line.depth as usize),
+                    async_prefix = if line.is_async { "async " } else { "" },
+                    kind = line.kind,
+                    name = line.name,
+                    sig = line.signature.as_deref().unwrap_or(""),
+                );
+            }
+        });
+    }
 
     output(&symbols, json, |syms| {
         if syms.is_empty() {
@@ -77,43 +294,250 @@ pub fn cmd_outline(file: &str, json: bool) -> Result<()> {
     })
 }
 
-/// Find what a symbol calls.
-pub fn cmd_callees(name: &str, json: bool) -> Result<()> {
+/// Print the source of a symbol's definitions.
+pub fn cmd_show(name: &str, file: Option<&str>, context: u32, json: bool) -> Result<()> {
     let db = open_db()?;
-    let edges = db.callees(name)?;
+    let sources = source::symbol_sources(&db, Path::new("."), name, file, context)?;
 
-    output(&edges, json, |edges| {
-        if edges.is_empty() {
+    output(&sources, json, |sources| {
+        if sources.is_empty() {
+            println!("No definition of '{name}' found");
+            return;
+        }
+        for (i, src) in sources.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            let sym = &src.symbol;
+            let stale = if src.stale {
+                "  (changed since indexed; re-index for current code)"
+            } else {
+                ""
+            };
+            println!(
+                "{kind} {name}  {file}:{start}-{end}{stale}",
+                kind = sym.kind,
+                name = sym.name,
+                file = sym.file_path,
+                start = sym.start_line,
+                end = sym.end_line,
+            );
+            match &src.code {
+                Some(code) => {
+                    let width = src.last_line.to_string().len();
+                    for (n, line) in (src.first_line..).zip(code.lines()) {
+                        println!("{n:>width$}  {line}");
+                    }
+                }
+                None => println!("  (source unavailable)"),
+            }
+        }
+    })
+}
+
+/// Bundle a symbol and its surroundings under a token budget.
+pub fn cmd_context(query: &str, budget: u64, keyword_only: bool, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let mode = if keyword_only {
+        rag::search::SearchMode::Keyword
+    } else {
+        rag::search::SearchMode::Hybrid
+    };
+    let Some(pack) = context::build(&db, Path::new("."), query, budget, mode)? else {
+        println!("No symbol or search hit for '{query}'");
+        return Ok(());
+    };
+
+    output(&pack, json, |p| {
+        let target = &p.target;
+        println!("## {}", context::header(&target.symbol));
+        if let Some(doc) = &p.docstring {
+            println!("{doc}");
+        }
+        println!("{}", target.content);
+        if target.truncated_lines > 0 {
+            println!("[... {} more lines truncated]", target.truncated_lines);
+        }
+        for (title, refs, omitted) in [
+            ("Callers", &p.callers, p.omitted.callers),
+            ("Callees", &p.callees, p.omitted.callees),
+        ] {
+            if refs.is_empty() && omitted == 0 {
+                continue;
+            }
+            println!("\n## {title}");
+            for r in refs {
+                println!("{}", context::ref_line(r));
+            }
+            if omitted > 0 {
+                println!("[... {omitted} more omitted]");
+            }
+        }
+        if !p.related.is_empty() || p.omitted.related > 0 {
+            println!("\n## Related");
+            for item in &p.related {
+                println!("### {}", context::header(&item.symbol));
+                println!("{}", item.content);
+                if item.truncated_lines > 0 {
+                    println!("[... body omitted, {} more lines]", item.truncated_lines);
+                }
+            }
+            if p.omitted.related > 0 {
+                println!("[... {} more related omitted]", p.omitted.related);
+            }
+        }
+        println!("\n({} of {} tokens)", p.tokens, p.budget);
+    })
+}
+
+/// Find what a symbol calls, optionally only calls made in files of `lang`.
+pub fn cmd_callees(
+    name: &str,
+    lines: bool,
+    lang: Option<&str>,
+    limit: Option<u32>,
+    json: bool,
+) -> Result<()> {
+    let db = open_db()?;
+    let mut sites = db.call_sites(name, lines, lang)?;
+    truncate_results("callees", &mut sites, limit_for("callees", limit), true);
+
+    output(&sites, json, |sites| {
+        if sites.is_empty() {
             println!("No callees found for '{name}'");
             return;
         }
-        for edge in edges {
+        for site in sites {
+            let edge = &site.edge;
+            match &site.target {
+                Some(t) => println!(
+                    "{target}  {file}:{line}  -> {tfile}:{tline}  {signature}",
+                    target = edge.target_name,
+                    file = edge.file_path,
+                    line = edge.line,
+                    tfile = t.file_path,
+                    tline = t.start_line,
+                    signature = t.signature.as_deref().unwrap_or(&t.name),
+                ),
+                None => println!(
+                    "{target}  {file}:{line}",
+                    target = edge.target_name,
+                    file = edge.file_path,
+                    line = edge.line,
+                ),
+            }
+            if let Some(text) = &site.line_text {
+                println!("    {text}");
+            }
+        }
+    })
+}
+
+/// Find what calls a symbol.
+pub fn cmd_callers(name: &str, lang: Option<&str>, limit: Option<u32>, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let mut callers = db.callers(name, lang)?;
+    truncate_results("callers", &mut callers, limit_for("callers", limit), true);
+
+    output(&callers, json, |callers| {
+        if callers.is_empty() {
+            println!("No callers found for '{name}'");
+            return;
+        }
+        for c in callers {
             println!(
-                "{target}  {file}:{line}",
-                target = edge.target_name,
-                file = edge.file_path,
-                line = edge.line,
+                "{kind} {caller}  {file}:{line}  ({start}-{end})",
+                kind = c.caller.kind,
+                caller = c.caller.name,
+                file = c.edge.file_path,
+                line = c.edge.line,
+                start = c.caller.start_line,
+                end = c.caller.end_line,
             );
         }
     })
 }
 
 /// Transitive impact analysis — what breaks if this changes?
-pub fn cmd_impact(name: &str, depth: u32, json: bool) -> Result<()> {
+///
+/// `tree` groups the results by depth, file and symbol; `summary` only counts the
+/// impacted symbols per file. `lang` follows only references made in files of
+/// that language.
+#[allow(clippy::too_many_arguments)]
+pub fn cmd_impact(
+    name: &str,
+    depth: u32,
+    target_kind: Option<SymbolKindFilter>,
+    lang: Option<&str>,
+    tree: bool,
+    summary: bool,
+    limit: Option<u32>,
+    json: bool,
+) -> Result<()> {
     let db = open_db()?;
-    let results = db.impact(name, depth)?;
+    let mut results = db.impact_of(name, depth, target_kind.map(SymbolKind::from), lang)?;
+    truncate_results("impact", &mut results, limit_for("impact", limit), true);
+
+    if summary {
+        let summary = impact::impact_summary(&results);
+        return output(&summary, json, |s| {
+            if s.files.is_empty() {
+                println!("No impact found for '{name}'");
+                return;
+            }
+            for f in &s.files {
+                let conditional = match f.conditional {
+                    0 => String::new(),
+                    n => format!(", {n} conditional"),
+                };
+                println!(
+                    "{count:>4}  {file}  (depth {depth}{conditional})",
+                    count = f.symbols,
+                    file = f.file_path,
+                    depth = f.nearest_depth,
+                );
+            }
+            println!("{} symbols in {} files", s.total_symbols, s.files.len());
+        });
+    }
+    if tree {
+        let levels = impact::impact_tree(&results);
+        return output(&levels, json, |levels| {
+            if levels.is_empty() {
+                println!("No impact found for '{name}'");
+                return;
+            }
+            for level in levels {
+                println!("depth {}", level.depth);
+                for file in &level.files {
+                    println!("  {}", file.file_path);
+                    for sym in &file.symbols {
+                        let kinds: Vec<&str> = sym.kinds.iter().map(|k| k.as_str()).collect();
+                        let lines: Vec<String> =
+                            sym.lines.iter().map(|l| format!("L{l}")).collect();
+                        println!(
+                            "    {name}  {kinds}  {lines}{note}",
+                            name = sym.name,
+                            kinds = kinds.join(","),
+                            lines = lines.join(", "),
+                            note = if sym.conditional {
+                                "  (conditional)"
+                            } else {
+                                ""
+                            },
+                        );
+                    }
+                }
+            }
+        });
+    }
 
     if json {
-        let items: Vec<_> = results
-            .iter()
-            .map(|(edge, d)| {
-                serde_json::json!({
-                    "edge": edge,
-                    "depth": d,
-                })
-            })
+        let items: Vec<ImpactEntry> = results
+            .into_iter()
+            .map(|(edge, depth)| ImpactEntry { edge, depth })
             .collect();
-        println!("{}", serde_json::to_string_pretty(&items)?);
+        print_json(&items)?;
     } else {
         if results.is_empty() {
             println!("No impact found for '{name}'");
@@ -122,11 +546,45 @@ pub fn cmd_impact(name: &str, depth: u32, json: bool) -> Result<()> {
         for (edge, depth) in &results {
             let indent = "  ".repeat(*depth as usize);
             println!(
-                "{indent}{kind}  {source}  {file}:{line}",
+                "{indent}{kind}  {source}  {file}:{line}{note}",
+                kind = edge.kind,
+                source = edge.source_id,
+                file = edge.file_path,
+                line = edge.line,
+                note = conditional_note(edge),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Symbols that can raise `name`: direct raisers at depth 1, their callers beyond.
+pub fn cmd_raises(name: &str, depth: u32, limit: Option<u32>, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let mut results = db.raises(name, depth)?;
+    truncate_results("raises", &mut results, limit_for("raises", limit), true);
+
+    if json {
+        let items: Vec<ImpactEntry> = results
+            .into_iter()
+            .map(|(edge, depth)| ImpactEntry { edge, depth })
+            .collect();
+        print_json(&items)?;
+    } else {
+        if results.is_empty() {
+            println!("Nothing raises '{name}'");
+            return Ok(());
+        }
+        for (edge, depth) in &results {
+            let indent = "  ".repeat(*depth as usize);
+            println!(
+                "{indent}{kind}  {source}  {file}:{line}{note}",
                 kind = edge.kind,
                 source = edge.source_id,
                 file = edge.file_path,
                 line = edge.line,
+                note = conditional_note(edge),
             );
         }
     }
@@ -134,26 +592,110 @@ pub fn cmd_impact(name: &str, depth: u32, json: bool) -> Result<()> {
     Ok(())
 }
 
-/// All references to a symbol (calls, imports, inherits, references, raises).
-pub fn cmd_refs(name: &str, kind: Option<EdgeKindFilter>, json: bool) -> Result<()> {
+/// Call and reference paths from `from` to `to`, shortest first.
+pub fn cmd_path(from: &str, to: &str, depth: u32, limit: Option<u32>, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let limit = limit_for("path", limit);
+    let mut paths = callpath::find_paths(&db, from, to, depth, fetch_limit(limit) as usize)?;
+    truncate_results("path", &mut paths, limit, false);
+
+    output(&paths, json, |paths| {
+        if paths.is_empty() {
+            println!("No path from '{from}' to '{to}' within {depth} hops");
+            return;
+        }
+        for (i, path) in paths.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("Path {} ({} hops):", i + 1, path.hops);
+            let mut previous: Option<&str> = None;
+            for step in &path.steps {
+                if let (Some(via), Some(file)) = (&step.via, previous) {
+                    let by_name = if via.by_name { ", by name" } else { "" };
+                    println!(
+                        "    {kind} at {file}:{line}{by_name}",
+                        kind = via.kind,
+                        line = via.line
+                    );
+                }
+                println!(
+                    "  {kind} {name}  {file}:{line}",
+                    kind = step.kind,
+                    name = step.name,
+                    file = step.file_path,
+                    line = step.start_line,
+                );
+                previous = Some(step.file_path.as_str());
+            }
+        }
+    })
+}
+
+/// All references to a symbol (calls, imports, inherits, references, raises),
+/// optionally only those in files matching `path_glob` or of `lang`, or resolved to a
+/// `target_kind` symbol.
+#[allow(clippy::too_many_arguments)]
+pub fn cmd_refs(
+    name: &str,
+    kind: Option<EdgeKindFilter>,
+    path_glob: Option<&str>,
+    target_kind: Option<SymbolKindFilter>,
+    lang: Option<&str>,
+    group: bool,
+    limit: Option<u32>,
+    json: bool,
+) -> Result<()> {
     let db = open_db()?;
     let kind_filter = kind.map(EdgeKind::from);
-    let results = db.refs(name, kind_filter)?;
+    let target_kind = target_kind.map(SymbolKind::from);
+    let mut results = db.refs_in(name, kind_filter, path_glob, target_kind, lang)?;
+    let limit = limit_for("refs", limit);
+
+    if group {
+        let mut groups = overloads::group_refs(&db, results)?;
+        truncate_results("refs", &mut groups, limit, true);
+        return output(&groups, json, |groups| {
+            if groups.is_empty() {
+                println!("No references found for '{name}'");
+            }
+            for g in groups {
+                let defs: Vec<_> = g
+                    .definitions
+                    .iter()
+                    .map(|d| format!("{}:{}", d.file_path, d.start_line))
+                    .collect();
+                match defs.len() {
+                    0 => println!("{}  (unresolved)", g.qualified_name),
+                    _ => println!("{}  ({})", g.qualified_name, defs.join(", ")),
+                }
+                for r in &g.references {
+                    let source = r.source.as_ref().map_or(&r.edge.source_id, |s| &s.name);
+                    println!(
+                        "  {kind}  {source}  {file}:{line}{note}",
+                        kind = r.edge.kind,
+                        file = r.edge.file_path,
+                        line = r.edge.line,
+                        note = conditional_note(&r.edge),
+                    );
+                }
+            }
+        });
+    }
 
+    truncate_results("refs", &mut results, limit, true);
     if json {
-        let items: Vec<_> = results
-            .iter()
-            .map(|(edge, sym)| {
-                serde_json::json!({
-                    "edge": edge,
-                    "source": sym,
-                })
-            })
+        let items: Vec<Reference> = results
+            .into_iter()
+            .map(|(edge, source)| Reference { edge, source })
             .collect();
-        println!("{}", serde_json::to_string_pretty(&items)?);
+        print_json(&items)?;
     } else {
         if results.is_empty() {
-            println!("No references found for '{name}'");
+            match path_glob {
+                Some(glob) => println!("No references found for '{name}' in {glob}"),
+                None => println!("No references found for '{name}'"),
+            }
             return Ok(());
         }
         for (edge, sym) in &results {
@@ -162,34 +704,41 @@ pub fn cmd_refs(name: &str, kind: Option<EdgeKindFilter>, json: bool) -> Result<
                 .map(|s| s.name.as_str())
                 .unwrap_or(&edge.source_id);
             println!(
-                "{kind}  {source}  {file}:{line}",
+                "{kind}  {source}  {file}:{line}{note}",
                 kind = edge.kind,
                 source = source_name,
                 file = edge.file_path,
                 line = edge.line,
+                note = conditional_note(edge),
             );
         }
+        let sources: Vec<_> = results.iter().filter_map(|(_, sym)| sym.as_ref()).collect();
+        print_token_total(&sources)?;
     }
 
     Ok(())
 }
 
+/// Marker for edges made under a condition, appended to reference lines.
+fn conditional_note(edge: &Edge) -> &'static str {
+    if edge.conditional {
+        "  (conditional)"
+    } else {
+        ""
+    }
+}
+
 /// Show inheritance hierarchy for a class.
 pub fn cmd_hierarchy(name: &str, json: bool) -> Result<()> {
     let db = open_db()?;
     let pairs = db.hierarchy(name)?;
 
     if json {
-        let items: Vec<_> = pairs
-            .iter()
-            .map(|(child, parent)| {
-                serde_json::json!({
-                    "child": child,
-                    "parent": parent,
-                })
-            })
+        let items: Vec<HierarchyEntry> = pairs
+            .into_iter()
+            .map(|(child, parent)| HierarchyEntry { child, parent })
             .collect();
-        println!("{}", serde_json::to_string_pretty(&items)?);
+        print_json(&items)?;
     } else {
         if pairs.is_empty() {
             println!("No hierarchy found for '{name}'");
@@ -204,9 +753,10 @@ pub fn cmd_hierarchy(name: &str, json: bool) -> Result<()> {
 }
 
 /// File-level import dependencies.
-pub fn cmd_deps(file: &str, json: bool) -> Result<()> {
+pub fn cmd_deps(file: &str, limit: Option<u32>, json: bool) -> Result<()> {
     let db = open_db()?;
-    let edges = db.file_deps(file)?;
+    let mut edges = db.file_deps(file)?;
+    truncate_results("deps", &mut edges, limit_for("deps", limit), true);
 
     output(&edges, json, |edges| {
         if edges.is_empty() {
@@ -224,117 +774,1551 @@ pub fn cmd_deps(file: &str, json: bool) -> Result<()> {
 }
 
 /// Search for symbols by name (case-insensitive prefix + substring match).
+#[allow(clippy::too_many_arguments)]
 pub fn cmd_search(
     query: &str,
     kind: Option<SymbolKindFilter>,
     file: Option<&str>,
-    limit: u32,
+    lang: Option<&str>,
+    limit: Option<u32>,
+    include_anchors: bool,
+    collapse: bool,
+    group: bool,
+    context: bool,
     json: bool,
 ) -> Result<()> {
     let db = open_db()?;
     let kind_filter = kind.map(crate::types::SymbolKind::from);
-    let limit = limit.min(MAX_SEARCH_LIMIT);
-    let symbols = db.search(query, kind_filter, file, limit)?;
+    let limit = limit_for("search", limit)
+        .unwrap_or(MAX_SEARCH_LIMIT)
+        .min(MAX_SEARCH_LIMIT);
+    // Fetch the full page when folding so folded hits don't shrink the result list
+    let fetch = if collapse || group {
+        MAX_SEARCH_LIMIT
+    } else {
+        fetch_limit(Some(limit)).min(MAX_SEARCH_LIMIT)
+    };
+    let mut matches: Vec<SymbolMatch> = db
+        .search_in(query, kind_filter, file, lang, fetch)?
+        .into_iter()
+        .map(|sym| SymbolMatch::new(sym, query))
+        .collect();
+    if collapse {
+        matches = collapse_similar(matches);
+    }
+    if !group {
+        truncate_results("search", &mut matches, Some(limit), false);
+    }
+    db.attach_notes(&mut matches, db::unix_now())?;
+    if context {
+        db.attach_parent_docs(&mut matches)?;
+    }
 
-    output(&symbols, json, |syms| {
-        if syms.is_empty() {
-            println!("No symbols found matching '{query}'");
-            return;
+    if group {
+        let mut groups = overloads::group_matches(&db, matches)?;
+        truncate_results("search", &mut groups, Some(limit), false);
+        if include_anchors {
+            let anchors = db.anchors(file, None, Some(query), limit)?;
+            let data = SearchWithAnchors {
+                symbols: groups,
+                anchors,
+            };
+            return output(&data, json, |data| {
+                print_symbol_groups(&data.symbols, query);
+                print_anchors(&data.anchors);
+            });
         }
-        for sym in syms {
+        return output(&groups, json, |groups| print_symbol_groups(groups, query));
+    }
+
+    if include_anchors {
+        let anchors = db.anchors(file, None, Some(query), limit)?;
+        let data = SearchWithAnchors {
+            symbols: matches,
+            anchors,
+        };
+        return output(&data, json, |data| {
+            print_symbol_matches(&data.symbols, query);
+            print_anchors(&data.anchors);
+        });
+    }
+
+    output(&matches, json, |matches| {
+        print_symbol_matches(matches, query)
+    })
+}
+
+/// `search --anchors` output: symbol matches (or `--group` groups) plus matching anchor comments.
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct SearchWithAnchors<T> {
+    symbols: Vec<T>,
+    anchors: Vec<Anchor>,
+}
+
+fn print_symbol_groups(groups: &[SymbolGroup], query: &str) {
+    if groups.is_empty() {
+        println!("No symbols found matching '{query}'");
+        return;
+    }
+    for g in groups {
+        if let [only] = g.definitions.as_slice() {
             println!(
                 "{kind}  {name}  {file}:{line}",
-                kind = sym.kind,
-                name = sym.name,
-                file = sym.file_path,
-                line = sym.start_line,
+                kind = g.kind,
+                name = g.qualified_name,
+                file = only.symbol.file_path,
+                line = only.symbol.start_line,
             );
+            continue;
         }
-    })
+        println!(
+            "{kind}  {name}  ({n} definitions)",
+            kind = g.kind,
+            name = g.qualified_name,
+            n = g.definitions.len(),
+        );
+        for d in &g.definitions {
+            let sig = d.symbol.signature.as_deref().unwrap_or_default();
+            println!("  {}:{}  {sig}", d.symbol.file_path, d.symbol.start_line);
+        }
+    }
 }
 
-/// Index statistics summary.
-pub fn cmd_stats(json: bool) -> Result<()> {
+fn print_symbol_matches(matches: &[SymbolMatch], query: &str) {
+    if matches.is_empty() {
+        println!("No symbols found matching '{query}'");
+        return;
+    }
+    for m in matches {
+        let sym = &m.symbol;
+        let similar = match m.similar.len() {
+            0 => String::new(),
+            n => format!("  (+{n} similar)"),
+        };
+        println!(
+            "{kind}  {name}  {file}:{line}{similar}",
+            kind = sym.kind,
+            name = sym.name,
+            file = sym.file_path,
+            line = sym.start_line,
+        );
+        if let Some(doc) = &m.parent_docstring {
+            println!("  parent: {}", doc.lines().next().unwrap_or_default());
+        }
+        for note in &m.notes {
+            println!("  note: {}  (until {})", note.text, note.expires);
+        }
+    }
+}
+
+fn print_anchors(anchors: &[Anchor]) {
+    for a in anchors {
+        let within = a
+            .symbol_name
+            .as_deref()
+            .map(|n| format!("  (in {n})"))
+            .unwrap_or_default();
+        println!(
+            "{tag}  {file}:{line}{within}  {text}",
+            tag = a.tag,
+            file = a.file_path,
+            line = a.line,
+            text = a.text,
+        );
+    }
+}
+
+/// Documentation coverage per file, with the largest undocumented symbols.
+pub fn cmd_doc_coverage(
+    visibility: Option<Visibility>,
+    limit: Option<u32>,
+    json: bool,
+) -> Result<()> {
     let db = open_db()?;
-    let stats = db.stats()?;
+    let limit = limit_for("doc-coverage", limit);
+    let mut report = db.doc_coverage(visibility, fetch_limit(limit))?;
+    truncate_results(
+        "doc-coverage",
+        &mut report.largest_undocumented,
+        limit,
+        false,
+    );
 
-    output(&stats, json, |stats| {
-        println!("Files:    {}", stats.num_files);
-        println!("Symbols:  {}", stats.num_symbols);
+    output(&report, json, |r| {
+        if r.total == 0 {
+            println!("No functions, methods or classes found");
+            return;
+        }
         println!(
-            "Edges:    {} ({} resolved)",
-            stats.num_edges, stats.num_resolved
+            "Documented: {}/{} ({:.1}%)",
+            r.documented,
+            r.total,
+            r.coverage * 100.0
         );
-        if !stats.languages.is_empty() {
-            println!("Languages:");
-            for (lang, count) in &stats.languages {
-                println!("  {lang}: {count} files");
-            }
+        println!("\nBy file (least covered first):");
+        for m in &r.modules {
+            println!(
+                "  {:>5.1}%  {}/{}  {}",
+                m.coverage * 100.0,
+                m.documented,
+                m.total,
+                m.file_path
+            );
         }
-        if !stats.symbol_kinds.is_empty() {
-            println!("Symbols by kind:");
-            for (kind, count) in &stats.symbol_kinds {
-                println!("  {kind}: {count}");
+        if !r.largest_undocumented.is_empty() {
+            println!("\nLargest undocumented:");
+            for sym in &r.largest_undocumented {
+                println!(
+                    "  {kind}  {name}  {file}:{start}-{end}",
+                    kind = sym.kind,
+                    name = sym.name,
+                    file = sym.file_path,
+                    start = sym.start_line,
+                    end = sym.end_line,
+                );
             }
         }
     })
 }
 
-// ── RAG Commands ──
-
-/// Download the embedding model.
-pub fn cmd_rag_setup(json: bool) -> Result<()> {
-    // Download bi-encoder (embeddings)
-    let embed_result = rag::setup::download_model()?;
-    // Download cross-encoder (re-ranking)
-    let rerank_result = rag::setup::download_cross_encoder()?;
-
-    #[derive(serde::Serialize)]
-    struct CombinedSetup {
-        embedding: rag::setup::SetupResult,
-        reranker: rag::setup::SetupResult,
-    }
-
-    let combined = CombinedSetup {
-        embedding: embed_result,
-        reranker: rerank_result,
-    };
+/// List anchor comments (TODO/FIXME/...), optionally scoped to a path and tag.
+pub fn cmd_todos(
+    path: Option<&str>,
+    tag: Option<&str>,
+    limit: Option<u32>,
+    json: bool,
+) -> Result<()> {
+    let db = open_db()?;
+    let limit = limit_for("todos", limit);
+    let mut anchors = db.anchors(path, tag, None, fetch_limit(limit))?;
+    truncate_results("todos", &mut anchors, limit, false);
 
-    output(&combined, json, |c| {
-        println!("Embedding model: {}", c.embedding.model_dir);
-        println!("Re-ranker model: {}", c.reranker.model_dir);
-        println!("Models ready. You can now run 'cartog rag index'.");
+    output(&anchors, json, |anchors| {
+        if anchors.is_empty() {
+            println!("No anchor comments found");
+            return;
+        }
+        print_anchors(anchors);
     })
 }
 
+/// String literals holding SQL, HTML or regexes, by file and line.
+pub fn cmd_injections(
+    query: Option<&str>,
+    language: Option<&str>,
+    path: Option<&str>,
+    limit: Option<u32>,
+    json: bool,
+) -> Result<()> {
+    let db = open_db()?;
+    if !db.scan_injections()? {
+        anyhow::bail!("string literals are not scanned; run `cartog index --injections` first");
+    }
+    let limit = limit_for("injections", limit);
+    let mut injections = db.injections(query, language, path, fetch_limit(limit))?;
+    truncate_results("injections", &mut injections, limit, false);
+
+    output(&injections, json, |injections| {
+        if injections.is_empty() {
+            println!("No matching string literals found");
+            return;
+        }
+        for i in injections {
+            let within = i
+                .symbol_name
+                .as_deref()
+                .map(|n| format!("  (in {n})"))
+                .unwrap_or_default();
+            println!(
+                "{lang}  {file}:{line}{within}  {text}",
+                lang = i.language,
+                file = i.file_path,
+                line = i.line,
+                text = i.text,
+            );
+        }
+    })
+}
+
+/// Tests reaching a symbol, closest first.
+pub fn cmd_tests_for(name: &str, depth: u32, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let hits = testmap::tests_for(&db, name, depth)?;
+
+    output(&hits, json, |hits| {
+        if hits.is_empty() {
+            println!("No tests found for '{name}' within {depth} hops");
+            return;
+        }
+        for h in hits {
+            let via = if h.via == name {
+                String::new()
+            } else {
+                format!("  via {}", h.via)
+            };
+            println!(
+                "{depth}  {role}  {test}  {file}:{line}{via}",
+                depth = h.depth,
+                role = h.role,
+                test = h.qualified_name,
+                file = h.test.file_path,
+                line = h.test.start_line,
+            );
+        }
+    })
+}
+
+/// Tests affected by a git range, as a report or as runner arguments.
+pub fn cmd_select_tests(
+    range: &str,
+    runner: Option<TestRunner>,
+    depth: u32,
+    json: bool,
+) -> Result<()> {
+    let db = open_db()?;
+    let changes = owners::changed_lines(Path::new("."), range)?;
+    let selection = testmap::select_tests(&db, range, &changes, depth)?;
+
+    if let Some(runner) = runner {
+        if !selection.unmapped_files.is_empty() {
+            eprintln!(
+                "note: {} changed files have no indexed symbols and may affect any test: {}",
+                selection.unmapped_files.len(),
+                selection.unmapped_files.join(", ")
+            );
+        }
+        let args = runner.args(&selection.tests);
+        if json {
+            println!("{}", serde_json::to_string_pretty(&args)?);
+        } else if !args.is_empty() {
+            let quoted: Vec<String> = args.iter().map(|a| testmap::shell_quote(a)).collect();
+            println!("{}", quoted.join(" "));
+        }
+        return Ok(());
+    }
+
+    output(&selection, json, |s| {
+        println!(
+            "{} changed symbols, {} tests selected (depth {depth})",
+            s.changed_symbols.len(),
+            s.tests.len()
+        );
+        for t in &s.tests {
+            println!(
+                "{depth}  {role}  {name}  {file}:{line}",
+                depth = t.depth,
+                role = t.role,
+                name = t.qualified_name,
+                file = t.test.file_path,
+                line = t.test.start_line,
+            );
+        }
+        if !s.unmapped_files.is_empty() {
+            println!("Changed files without indexed symbols (may affect any test):");
+            for f in &s.unmapped_files {
+                println!("  {f}");
+            }
+        }
+    })
+}
+
+/// Symbols invoked from outside the code graph (mains, routes, CLI commands, ...).
+pub fn cmd_entrypoints(kind: Option<&str>, limit: Option<u32>, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let mut symbols = db.entrypoints(kind)?;
+    truncate_results(
+        "entrypoints",
+        &mut symbols,
+        limit_for("entrypoints", limit),
+        true,
+    );
+
+    output(&symbols, json, |symbols| {
+        if symbols.is_empty() {
+            println!("No entry points found");
+            return;
+        }
+        for sym in symbols {
+            println!(
+                "{entry}  {kind}  {name}  {file}:{line}",
+                entry = sym.entrypoint.as_deref().unwrap_or_default(),
+                kind = sym.kind,
+                name = sym.name,
+                file = sym.file_path,
+                line = sym.start_line,
+            );
+        }
+    })
+}
+
+/// Functions, methods, classes and files not reachable from entry points (or `from`).
+pub fn cmd_reachable(
+    from: Option<&str>,
+    dir: Option<&str>,
+    limit: Option<u32>,
+    json: bool,
+) -> Result<()> {
+    let db = open_db()?;
+    let mut report = reach::reachable(&db, from, dir)?;
+    let limit = limit_for("reachable", limit);
+    truncate_results("reachable", &mut report.unreachable, limit, true);
+
+    output(&report, json, |r| {
+        println!(
+            "{} of {} functions, methods and classes reachable from {} seed(s)",
+            r.reachable,
+            r.total,
+            r.seeds.len()
+        );
+        if r.unreachable.is_empty() {
+            return;
+        }
+        println!();
+        for u in &r.unreachable {
+            print!(
+                "{confidence}  {kind}  {name}  {file}:{line}",
+                confidence = u.confidence,
+                kind = u.symbol.kind,
+                name = u.symbol.name,
+                file = u.symbol.file_path,
+                line = u.symbol.start_line,
+            );
+            match &u.reason {
+                Some(reason) => println!("  ({reason})"),
+                None => println!(),
+            }
+        }
+        if !r.unreachable_files.is_empty() {
+            println!("\nUnreachable files:");
+            for f in &r.unreachable_files {
+                println!("  {f}");
+            }
+        }
+    })
+}
+
+/// Circular imports, as groups of files (or directories) with one cycle each.
+pub fn cmd_cycles(lang: Option<&str>, scope: CycleScope, check: bool, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let report = cycles::find_cycles(&db, lang, scope)?;
+
+    output(&report, json, |r| {
+        if r.cycles.is_empty() {
+            println!("No import cycles among {} {}", r.nodes, scope_noun(r.scope));
+            return;
+        }
+        for (i, cycle) in r.cycles.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!(
+                "Cycle {} ({} {}):",
+                i + 1,
+                cycle.members.len(),
+                scope_noun(r.scope)
+            );
+            for link in &cycle.links {
+                println!(
+                    "  {from} -> {to}  ({file}:{line} imports {name})",
+                    from = link.from,
+                    to = link.to,
+                    file = link.file,
+                    line = link.line,
+                    name = link.name,
+                );
+            }
+            if cycle.members.len() > cycle.path.len() {
+                println!("  also in this group: {}", extra_members(cycle).join(", "));
+            }
+        }
+    })?;
+
+    if check && !report.cycles.is_empty() {
+        anyhow::bail!("{} import cycle(s) found", report.cycles.len());
+    }
+    Ok(())
+}
+
+fn scope_noun(scope: CycleScope) -> &'static str {
+    match scope {
+        CycleScope::File => "files",
+        CycleScope::Dir => "directories",
+    }
+}
+
+/// Members of a cycle group that its reported cycle does not go through.
+fn extra_members(cycle: &cycles::ImportCycle) -> Vec<&str> {
+    cycle
+        .members
+        .iter()
+        .filter(|m| !cycle.path.contains(m))
+        .map(String::as_str)
+        .collect()
+}
+
+/// ORM models mapped to a database table and raw SQL naming it.
+pub fn cmd_table_usages(table: &str, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let usages = orm::table_usages(&db, table)?;
+
+    output(&usages, json, |u| {
+        if u.models.is_empty() && u.sql_references.is_empty() {
+            println!("No usages of table '{}'", u.table);
+            return;
+        }
+        if !u.models.is_empty() {
+            println!("Models:");
+            for m in &u.models {
+                println!("  {} {}:{}", m.name, m.file_path, m.start_line);
+            }
+        }
+        if !u.sql_references.is_empty() {
+            println!("SQL:");
+            for r in &u.sql_references {
+                println!(
+                    "  {}:{}  in {}  {}",
+                    r.symbol.file_path, r.line, r.symbol.name, r.snippet
+                );
+            }
+        }
+    })
+}
+
+/// Public symbols without incoming edges from test code.
+pub fn cmd_untested(dir: Option<&str>, limit: Option<u32>, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let mut symbols = testmap::untested(&db, dir)?;
+    truncate_results("untested", &mut symbols, limit_for("untested", limit), true);
+
+    output(&symbols, json, |symbols| {
+        if symbols.is_empty() {
+            println!("Every public symbol is referenced from test code");
+            return;
+        }
+        for s in symbols {
+            println!(
+                "{kind}  {name}  {file}:{line}",
+                kind = s.kind,
+                name = s.name,
+                file = s.file_path,
+                line = s.start_line,
+            );
+        }
+    })
+}
+
+/// Outline digests of files changed since a generation.
+pub fn cmd_sync(since: Option<u64>, files: &[String], outlines: bool, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let delta = sync::sync(&db, since, files, outlines)?;
+
+    output(&delta, json, |d| {
+        match since {
+            Some(_) if !d.full => {
+                println!("Generation {} (changes since {})", d.generation, d.since)
+            }
+            _ => println!("Generation {} (all files)", d.generation),
+        }
+        for f in &d.files {
+            println!(
+                "  {}  {}  {} symbols  (gen {})",
+                f.digest, f.path, f.num_symbols, f.generation
+            );
+            for sym in f.symbols.iter().flatten() {
+                println!(
+                    "      {} {}  L{}-{}",
+                    sym.kind, sym.name, sym.start_line, sym.end_line
+                );
+            }
+        }
+        for path in &d.removed {
+            println!("  removed  {path}");
+        }
+        if d.files.is_empty() && d.removed.is_empty() {
+            println!("  no changes");
+        }
+    })
+}
+
+/// Changed + transitively impacted symbols of a git range, grouped by CODEOWNERS owner.
+pub fn cmd_owners_impact(range: &str, depth: u32, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let root = Path::new(".");
+    let (toplevel, prefix) = indexer::repo_location(root)?;
+    let codeowners = owners::CodeOwners::load(&toplevel)?;
+    let has_codeowners = codeowners.is_some();
+    let codeowners = codeowners.unwrap_or_default().with_path_prefix(&prefix);
+
+    let changes = owners::changed_lines(root, range)?;
+    let report = owners::owners_impact(&db, &codeowners, range, &changes, depth)?;
+
+    output(&report, json, |r| {
+        println!("## Owners impacted by `{}`\n", r.range);
+        println!(
+            "{} changed symbols in {} files, {} impacted symbols (depth {depth}).\n",
+            r.changed_symbols, r.changed_files, r.impacted_symbols
+        );
+        if !has_codeowners {
+            println!("_No CODEOWNERS file found; everything is listed as unowned._\n");
+        }
+        if r.owners.is_empty() {
+            println!("No indexed symbols touched by this range.");
+            return;
+        }
+        for o in &r.owners {
+            println!("### {}\n", o.owner);
+            println!("Files: {}\n", o.files.join(", "));
+            for c in &o.changed {
+                println!(
+                    "- changed `{}` ({}) {}:{}",
+                    c.name, c.kind, c.file_path, c.line
+                );
+            }
+            for i in &o.impacted {
+                println!(
+                    "- impacted `{}` ({}) {}:{}, depth {} via `{}`",
+                    i.name, i.kind, i.file_path, i.line, i.depth, i.via
+                );
+            }
+            println!();
+        }
+    })
+}
+
+/// Show the most recent MCP tool calls from the audit log.
+pub fn cmd_audit_tail(limit: u32, tool: Option<&str>, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let entries = db.audit_tail(limit, tool)?;
+
+    output(&entries, json, |entries| {
+        if entries.is_empty() {
+            println!("No audit entries (start the server with 'cartog serve --audit')");
+            return;
+        }
+        for e in entries {
+            let count = e
+                .result_count
+                .map(|c| format!(" results={c}"))
+                .unwrap_or_default();
+            let status = if e.ok { "ok" } else { "error" };
+            println!(
+                "{}  {}  {status} {}ms{count}  {}",
+                e.time, e.tool, e.duration_ms, e.params
+            );
+        }
+    })
+}
+
+/// Write audited search queries and the results used after them as a training set.
+pub fn cmd_emit_training_data(
+    to: &Path,
+    since: Option<std::time::Duration>,
+    json: bool,
+) -> Result<()> {
+    let cutoff = since.map(|age| db::unix_now() - age.as_secs_f64());
+    let set = training::emit(&open_db()?, cutoff, to)?;
+
+    output(&set, json, |s| {
+        if s.queries == 0 {
+            println!("No search queries recorded (start the server with 'cartog serve --audit')");
+            return;
+        }
+        println!(
+            "Wrote {} of {} queries to {} ({} positives, {} hard negatives)",
+            s.examples, s.queries, s.path, s.positives, s.negatives
+        );
+    })
+}
+
+/// Read the build system's package graph into the index database.
+pub fn cmd_packages_sync(tool: Option<BuildTool>, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let result = packages::sync(&db, Path::new("."), tool)?;
+
+    output(&result, json, |r| {
+        println!(
+            "Stored {} {} packages ({} dependencies between them)",
+            r.packages, r.tool, r.dependencies
+        );
+    })
+}
+
+/// Stored packages and their declared dependencies.
+pub fn cmd_packages_list(json: bool) -> Result<()> {
+    let db = open_db()?;
+    let packages = db.packages()?;
+
+    output(&packages, json, |packages| {
+        if packages.is_empty() {
+            println!("No packages stored (run 'cartog packages sync')");
+            return;
+        }
+        for p in packages {
+            println!("{}  {}", p.name, p.root);
+            if !p.dependencies.is_empty() {
+                println!("  depends on: {}", p.dependencies.join(", "));
+            }
+        }
+    })
+}
+
+/// A package's declared dependencies next to the packages its files import.
+pub fn cmd_packages_deps(name: &str, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let deps = packages::package_deps(&db, name)?;
+
+    output(&deps, json, |d| {
+        println!("{}  {}", d.package.name, d.package.root);
+        if d.imports.is_empty() {
+            println!("  imports no other package");
+        }
+        for i in &d.imports {
+            let status = if i.declared { "" } else { "  (undeclared)" };
+            println!("  -> {}  {} imports{status}", i.package, i.imports);
+        }
+        if !d.unused.is_empty() {
+            println!("  declared, never imported: {}", d.unused.join(", "));
+        }
+        if !d.dependents.is_empty() {
+            println!("  depended on by: {}", d.dependents.join(", "));
+        }
+    })
+}
+
+/// Index statistics summary.
+pub fn cmd_stats(json: bool) -> Result<()> {
+    let db = open_db()?;
+    let stats = db.stats()?;
+
+    output(&stats, json, |stats| {
+        println!("Files:    {}", stats.num_files);
+        println!("Symbols:  {}", stats.num_symbols);
+        println!(
+            "Edges:    {} ({} resolved)",
+            stats.num_edges, stats.num_resolved
+        );
+        if stats.lines.total() > 0 {
+            println!(
+                "Lines:    {} code, {} comment, {} blank",
+                stats.lines.code, stats.lines.comment, stats.lines.blank
+            );
+        }
+        if !stats.languages.is_empty() {
+            println!("Languages:");
+            for (lang, count) in &stats.languages {
+                match stats.language_lines.iter().find(|(l, _)| l == lang) {
+                    Some((_, lines)) if lines.total() > 0 => println!(
+                        "  {lang}: {count} files, {} code lines, {} comment lines",
+                        lines.code, lines.comment
+                    ),
+                    _ => println!("  {lang}: {count} files"),
+                }
+            }
+        }
+        if !stats.largest_files.is_empty() {
+            let limits = CONFIG
+                .get_or_init(ProjectConfig::default)
+                .index
+                .file_limits();
+            println!("Largest files:");
+            for file in &stats.largest_files {
+                let flag = if file.truncated {
+                    "  (truncated)"
+                } else if limits.exceeded(file.symbols, file.edges) {
+                    "  (over the per-file warning threshold)"
+                } else {
+                    ""
+                };
+                println!(
+                    "  {}: {} symbols, {} edges{flag}",
+                    file.path, file.symbols, file.edges
+                );
+            }
+        }
+        if !stats.symbol_kinds.is_empty() {
+            println!("Symbols by kind:");
+            for (kind, count) in &stats.symbol_kinds {
+                println!("  {kind}: {count}");
+            }
+        }
+        if !stats.pins.is_empty() {
+            println!("Pinned:");
+            for (name, file) in &stats.pins {
+                println!("  {name} -> {file}");
+            }
+        }
+        if !stats.resolution.is_empty() {
+            println!("Last edge resolution:");
+            for pass in &stats.resolution {
+                println!(
+                    "  {:<15} {:>8} edges  {:>8.1} ms",
+                    pass.stage.as_str(),
+                    pass.resolved,
+                    pass.duration_ms
+                );
+            }
+        }
+    })
+}
+
+/// Index statistics per directory, to spot code extraction covers poorly.
+pub fn cmd_stats_by_dir(depth: usize, json: bool) -> Result<()> {
+    let files = open_db()?.file_stats()?;
+    let dirs = db::stats_by_dir(&files, depth);
+
+    output(&dirs, json, |dirs| {
+        if dirs.is_empty() {
+            println!("No files indexed");
+            return;
+        }
+        let width = dirs.iter().map(|d| d.dir.len()).max().unwrap_or(0).max(3);
+        println!(
+            "{:<width$}  {:>6}  {:>8}  {:>8}  {:>8}  {:>10}  languages",
+            "dir", "files", "lines", "symbols", "edges", "unresolved"
+        );
+        for d in dirs {
+            let languages: Vec<String> = d
+                .languages
+                .iter()
+                .map(|(lang, n)| format!("{lang} {n}"))
+                .collect();
+            println!(
+                "{:<width$}  {:>6}  {:>8}  {:>8}  {:>8}  {:>9.0}%  {}",
+                d.dir,
+                d.files,
+                d.lines,
+                d.symbols,
+                d.edges,
+                d.unresolved_rate * 100.0,
+                languages.join(", ")
+            );
+        }
+    })
+}
+
+/// Symbols most often returned by audited MCP tool calls, optionally within `since`.
+pub fn cmd_stats_hot_symbols(
+    limit: u32,
+    since: Option<std::time::Duration>,
+    json: bool,
+) -> Result<()> {
+    let cutoff = since.map(|age| db::unix_now() - age.as_secs_f64());
+    let hot = open_db()?.hot_symbols(limit, cutoff)?;
+
+    output(&hot, json, |hot| {
+        if hot.is_empty() {
+            println!("No symbol hits recorded (start the server with 'cartog serve --audit')");
+            return;
+        }
+        let width = hot.iter().map(|h| h.name.len()).max().unwrap_or(0);
+        for h in hot {
+            println!(
+                "{:>6}  {:<8}  {:<width$}  {}  (last {}, {})",
+                h.hits,
+                h.kind.as_str(),
+                h.name,
+                h.file_path,
+                h.last_hit,
+                h.tools.join(", ")
+            );
+        }
+    })
+}
+
+/// Index and model readiness. Does not create the database when it is missing.
+pub fn cmd_health(json: bool) -> Result<()> {
+    let report = health::check(db_file(), Path::new("."), None)?;
+
+    output(&report, json, |h| {
+        println!("cartog {}", h.version);
+        match &h.index {
+            Some(index) => {
+                println!(
+                    "Index:    {} files, schema {}, generation {}",
+                    index.files, index.schema_version, index.generation
+                );
+                let s = &index.staleness;
+                println!(
+                    "Changes:  {} new, {} modified, {} deleted since indexed",
+                    s.new_files, s.modified_files, s.deleted_files
+                );
+                println!("FTS:      {}", describe_fts(&index.fts));
+            }
+            None => println!("Index:    missing ({})", db_file().display()),
+        }
+        println!("Locked:   {}", if h.locked { "yes" } else { "no" });
+        println!(
+            "Models:   embedding {}, re-ranker {}",
+            h.models.embedding, h.models.reranker
+        );
+        match &h.vectors.error {
+            None => println!("Vectors:  ok"),
+            Some(error) => {
+                println!("Vectors:  unavailable, semantic search is keyword-only ({error})");
+                if let Some(fix) = h.vectors.fix {
+                    println!("          {fix}");
+                }
+            }
+        }
+        if h.index_recommended {
+            println!("Run `cartog index` before querying.");
+        }
+    })
+}
+
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct VerifyReport {
+    /// Problems reported by SQLite's `quick_check`.
+    database: Vec<String>,
+    fts: db::FtsCheck,
+    /// Rows indexed by `--fix`, when the FTS index was rebuilt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fts_rebuilt: Option<u32>,
+}
+
+/// Check the database and the FTS index, rebuilding the latter with `fix`.
+/// Fails when problems remain, so scripts can test the exit status.
+pub fn cmd_verify(fix: bool, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let mut report = VerifyReport {
+        database: db.quick_check()?,
+        fts: db.check_fts()?,
+        fts_rebuilt: None,
+    };
+    if fix && !report.fts.is_ok() {
+        report.fts_rebuilt = Some(db.rebuild_fts()?);
+        report.fts = db.check_fts()?;
+    }
+
+    output(&report, json, |r| {
+        if r.database.is_empty() {
+            println!("Database: ok");
+        } else {
+            println!("Database: {} problems", r.database.len());
+            for problem in &r.database {
+                println!("  {problem}");
+            }
+        }
+        if let Some(rows) = r.fts_rebuilt {
+            println!("FTS:      rebuilt from {rows} symbol contents");
+        }
+        println!("FTS:      {}", describe_fts(&r.fts));
+    })?;
+
+    if !report.database.is_empty() {
+        anyhow::bail!(
+            "the database is damaged; delete {} and run `cartog index`",
+            db_file().display()
+        );
+    }
+    if !report.fts.is_ok() {
+        anyhow::bail!(
+            "the FTS index drifted; run `cartog verify --fix` or `cartog rag rebuild-fts`"
+        );
+    }
+    Ok(())
+}
+
+/// Check the index for integrity problems, repairing what it can with `fix`.
+/// Fails when problems remain, like `cmd_verify`.
+pub fn cmd_doctor(fix: bool, json: bool) -> Result<()> {
+    let mut db = open_db()?;
+    let report = doctor::diagnose(&mut db, Path::new("."), fix)?;
+
+    output(&report, json, |r| {
+        println!("Schema:   {} (current)", r.schema_version);
+        if r.database.is_empty() {
+            println!("Database: ok");
+        } else {
+            println!("Database: {} problems", r.database.len());
+            for problem in &r.database {
+                println!("  {problem}");
+            }
+        }
+        if let Some(fixed) = &r.fixed {
+            if fixed.orphans.total() > 0 {
+                println!("Orphans:  deleted {}", describe_orphans(&fixed.orphans));
+            }
+            if fixed.files_reindexed > 0 {
+                println!("Files:    re-indexed {}", fixed.files_reindexed);
+            }
+            if let Some(rows) = fixed.fts_rebuilt {
+                println!("FTS:      rebuilt from {rows} symbol contents");
+            }
+        }
+        if r.orphans.total() == 0 {
+            println!("Orphans:  none");
+        } else {
+            println!("Orphans:  {}", describe_orphans(&r.orphans));
+        }
+        println!("FTS:      {}", describe_fts(&r.fts));
+        if r.stale_files.is_empty() {
+            println!("Files:    all match the index");
+        } else {
+            println!("Files:    {} changed since indexed", r.stale_files.len());
+            for path in &r.stale_files {
+                println!("  {path}");
+            }
+        }
+        let models: Vec<String> = r
+            .models
+            .iter()
+            .map(|m| {
+                let state = if m.downloaded { "ok" } else { "missing" };
+                format!("{} {state}", m.name)
+            })
+            .collect();
+        println!("Models:   {}", models.join(", "));
+        match &r.vectors.error {
+            None => println!("Vectors:  ok"),
+            Some(error) => {
+                println!("Vectors:  unavailable ({error})");
+                if let Some(fix) = r.vectors.fix {
+                    println!("          {fix}");
+                }
+            }
+        }
+    })?;
+
+    if !report.database.is_empty() {
+        anyhow::bail!(
+            "the database is damaged; delete {} and run `cartog index`",
+            db_file().display()
+        );
+    }
+    let mut problems = Vec::new();
+    if report.orphans.total() > 0 || !report.stale_files.is_empty() || !report.fts.is_ok() {
+        problems.push("run `cartog doctor --fix`");
+    }
+    if report.models_missing() {
+        problems.push("run `cartog rag setup` to download the models");
+    }
+    if report.vectors_unavailable() {
+        problems.push("restore vector support as shown under `Vectors`");
+    }
+    if !problems.is_empty() {
+        anyhow::bail!("the index has problems; {}", problems.join(", and "));
+    }
+    Ok(())
+}
+
+fn describe_orphans(orphans: &db::Orphans) -> String {
+    format!(
+        "{} edges, {} contents, {} embedding IDs, {} vectors",
+        orphans.edges, orphans.contents, orphans.embedding_ids, orphans.vectors
+    )
+}
+
+/// Run the extractors over the benchmark fixtures and compare with their golden files.
+pub fn cmd_selftest(dir: Option<&Path>, update: bool, json: bool) -> Result<()> {
+    let dir = selftest::benchmarks_dir(dir)?;
+    let report = selftest::run(&dir, update)?;
+
+    output(&report, json, |r| {
+        println!("cartog {} selftest: {}", r.version, dir.display());
+        for f in &r.fixtures {
+            let status = if f.ok { "ok" } else { "FAIL" };
+            println!(
+                "{status:<6} {}  {} files, {} symbols, {} edges",
+                f.fixture, f.files, f.symbols, f.edges
+            );
+            for d in &f.drift {
+                let delta = i64::from(d.actual) - i64::from(d.expected);
+                println!("  {}: {} -> {} ({delta:+})", d.count, d.expected, d.actual);
+            }
+            for sym in &f.missing {
+                println!("  missing {} {}  {}", sym.kind, sym.name, sym.file);
+            }
+            if !f.grammars_changed.is_empty() && !f.ok {
+                println!("  grammar changed: {}", f.grammars_changed.join(", "));
+            }
+            for error in &f.errors {
+                println!("  error: {error}");
+            }
+        }
+        if r.updated {
+            println!("Golden files updated.");
+        }
+    })?;
+
+    if !report.ok {
+        anyhow::bail!(
+            "extraction drifted from the golden files; if intended, run `cartog selftest --update`"
+        );
+    }
+    Ok(())
+}
+
+/// One-line summary of an FTS check.
+fn describe_fts(fts: &db::FtsCheck) -> String {
+    if fts.is_ok() {
+        return format!("ok ({} rows)", fts.content_rows);
+    }
+    let mut problems = vec![
+        format!("{} missing", fts.missing),
+        format!("{} orphaned", fts.orphaned),
+    ];
+    if fts.corrupt {
+        problems.push("index structure corrupt".to_string());
+    }
+    format!("{} of {} rows", problems.join(", "), fts.content_rows)
+}
+
+/// Replay edge resolution for `target`, a name or an edge ID.
+pub fn cmd_explain_resolution(
+    target: &str,
+    file: Option<&str>,
+    limit: Option<u32>,
+    json: bool,
+) -> Result<()> {
+    let db = open_db()?;
+    let limit = limit_for("explain-resolution", limit);
+    let mut explanations = db.explain_resolution(target, file, fetch_limit(limit))?;
+    truncate_results("explain-resolution", &mut explanations, limit, false);
+
+    output(&explanations, json, |explanations| {
+        if explanations.is_empty() {
+            println!("No edges to '{target}'");
+            return;
+        }
+        for (i, x) in explanations.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            let module = x
+                .module
+                .as_deref()
+                .map(|m| format!(" from '{m}'"))
+                .unwrap_or_default();
+            println!(
+                "Edge {}: {}:{} {} {}{module}",
+                x.edge_id,
+                x.edge.file_path,
+                x.edge.line,
+                x.edge.kind.as_str(),
+                x.edge.target_name,
+            );
+            for (n, step) in x.steps.iter().enumerate() {
+                println!("  {}. {}: {}", n + 1, step.stage.as_str(), step.outcome);
+                for c in &step.candidates {
+                    println!(
+                        "     {} {}:{} {} {}  ({})",
+                        if c.accepted { "accept" } else { "reject" },
+                        c.symbol.file_path,
+                        c.symbol.start_line,
+                        c.symbol.kind.as_str(),
+                        c.symbol.name,
+                        c.reason,
+                    );
+                }
+            }
+            match &x.resolved_to {
+                Some(id) => println!("  -> {id}"),
+                None => println!("  -> unresolved"),
+            }
+            if x.resolved_to != x.edge.target_id {
+                println!(
+                    "  (stored target: {}; run `cartog index` to refresh)",
+                    x.edge.target_id.as_deref().unwrap_or("none")
+                );
+            }
+        }
+    })
+}
+
+/// Pin the canonical definition of `name`.
+pub fn cmd_pin(name: &str, file: &str, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let symbol = db.pin(name, file)?;
+
+    output(&symbol, json, |s| {
+        println!(
+            "Pinned {} {}  {}:{}-{}",
+            s.kind, s.name, s.file_path, s.start_line, s.end_line
+        );
+    })
+}
+
+/// Remove the pin for `name`.
+pub fn cmd_unpin(name: &str, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let removed = db.unpin(name)?;
+
+    output(
+        &UnpinResult {
+            name: name.to_string(),
+            removed,
+        },
+        json,
+        |_| {
+            if removed {
+                println!("Unpinned {name}");
+            } else {
+                println!("'{name}' was not pinned");
+            }
+        },
+    )
+}
+
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct UnpinResult {
+    name: String,
+    removed: bool,
+}
+
+/// Delete all scratch notes and symbol reservations.
+pub fn cmd_clean(json: bool) -> Result<()> {
+    let db = open_db()?;
+    let removed = db.clear_notes()?;
+    let released = db.clear_reservations()?;
+
+    output(
+        &CleanResult {
+            notes_removed: removed,
+            reservations_removed: released,
+        },
+        json,
+        |_| {
+            println!("Removed {removed} scratch notes and {released} symbol reservations");
+        },
+    )
+}
+
+pub fn cmd_schema(command: &str) -> Result<()> {
+    if command.is_empty() {
+        for name in schema::COMMANDS {
+            println!("{}", schema::subcommand(name));
+        }
+        return Ok(());
+    }
+    let Some(schema) = schema::schema(command) else {
+        anyhow::bail!("no schema for `{command}`; run `cartog schema` for the list of commands");
+    };
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
+
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct CleanResult {
+    notes_removed: usize,
+    reservations_removed: usize,
+}
+
+/// Write the graph tables to `out` as CSV or Parquet files.
+pub fn cmd_export(
+    format: ExportFormat,
+    tables: &[ExportTable],
+    out: &Path,
+    root: Option<&str>,
+    depth: u32,
+    json: bool,
+) -> Result<()> {
+    let db = open_db()?;
+    let written = match format {
+        ExportFormat::Dot => vec![export::export_dot(&db, root, depth, out)?],
+        _ => {
+            anyhow::ensure!(root.is_none(), "--root only applies to --format dot");
+            export::export(&db, format, tables, out)?
+        }
+    };
+
+    output(&written, json, |w| {
+        for t in w {
+            println!("{:>8} rows  {}", t.rows, t.path);
+        }
+    })
+}
+
+/// List the named indexes of the project, marking the one in use.
+pub fn cmd_indexes(json: bool) -> Result<()> {
+    let named = indexes::list(Path::new("."))?;
+
+    output(&named, json, |list| {
+        if list.is_empty() {
+            println!("No named indexes (build one with `cartog --index <NAME> index .`)");
+            return;
+        }
+        for index in list {
+            let current = if Path::new(&index.path) == db_file() {
+                "*"
+            } else {
+                " "
+            };
+            println!(
+                "{current} {:<30} {:>8} KiB  {}",
+                index.name,
+                (index.size_bytes + 1023) / 1024,
+                index.path
+            );
+        }
+    })
+}
+
+/// Copy the index database to `to`, safe while `watch` or `serve` is running.
+pub fn cmd_backup(to: &Path, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let result = db.backup_to(to)?;
+
+    output(&result, json, |r| {
+        println!(
+            "Backed up {} pages ({} KiB) to {}",
+            r.pages,
+            (r.size_bytes + 1023) / 1024,
+            r.path
+        );
+    })
+}
+
+/// Run database maintenance on the index.
+pub fn cmd_maintain(vacuum: bool, json: bool) -> Result<()> {
+    let db = open_db()?;
+    let report = db.maintain(true, vacuum)?;
+
+    output(&report, json, |r| {
+        let freed = r.size_before.saturating_sub(r.size_after);
+        println!(
+            "Maintained {} database(s) in {} ms: {} KiB -> {} KiB ({} KiB freed){}",
+            r.databases,
+            r.duration_ms,
+            r.size_before / 1024,
+            r.size_after / 1024,
+            freed / 1024,
+            if r.vacuumed { ", vacuumed" } else { "" }
+        );
+    })
+}
+
+/// Remove index entries of files missing from the working tree.
+pub fn cmd_prune(
+    path: &str,
+    older_than: Option<std::time::Duration>,
+    dry_run: bool,
+    json: bool,
+) -> Result<()> {
+    let db = open_db()?;
+    let result = db.prune_missing(Path::new(path), older_than, dry_run)?;
+
+    output(&result, json, |r| {
+        for file in &r.files {
+            println!("  {}  ({} rows)", file.path, file.rows);
+        }
+        let verb = if r.dry_run { "Would remove" } else { "Removed" };
+        println!(
+            "{verb} {} of {} indexed files ({} rows)",
+            r.files.len(),
+            r.files_checked,
+            r.rows_removed
+        );
+    })
+}
+
+// ── RAG Commands ──
+
+/// Download the embedding model.
+pub fn cmd_rag_setup(json: bool) -> Result<()> {
+    // Download bi-encoder (embeddings)
+    let embed_result = rag::setup::download_model()?;
+    // Download cross-encoder (re-ranking)
+    let rerank_result = rag::setup::download_cross_encoder()?;
+
+    let combined = RagSetup {
+        embedding: embed_result,
+        reranker: rerank_result,
+    };
+
+    output(&combined, json, |c| {
+        println!("Embedding model: {}", c.embedding.model_dir);
+        println!("Re-ranker model: {}", c.reranker.model_dir);
+        println!("Models ready. You can now run 'cartog rag index'.");
+    })
+}
+
+/// `rag setup` output: where each model was installed.
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct RagSetup {
+    embedding: rag::setup::SetupResult,
+    reranker: rag::setup::SetupResult,
+}
+
 /// Build embedding index for semantic search.
 pub fn cmd_rag_index(path: &str, force: bool, json: bool) -> Result<()> {
     // First ensure the standard code graph index is up to date
     let root = Path::new(path);
-    let db = open_db()?;
-    let _index_result = indexer::index_directory(&db, root, false)?;
+    let mut db = open_db()?;
+    db.ensure_rag_supported()?;
+    let _index_result = db.index(root, false)?;
 
-    let result = rag::indexer::index_embeddings(&db, force)?;
+    let result = rag::indexer::index_embeddings(&db, force, Default::default())?;
 
     output(&result, json, |r| {
         println!(
             "Embedded {} symbols ({} skipped, {} total with content)",
             r.symbols_embedded, r.symbols_skipped, r.total_content_symbols
         );
+        if let Some(m) = &r.migration {
+            print_migration(m);
+        }
+    })
+}
+
+/// Start, cancel or show the embedding model migration.
+pub fn cmd_rag_migrate(
+    model: Option<rag::embeddings::EmbeddingModelId>,
+    cancel: bool,
+    json: bool,
+) -> Result<()> {
+    let db = open_db()?;
+    db.ensure_rag_supported()?;
+    let status = match model {
+        Some(model) => rag::migrate::start(&db, model)?,
+        None if cancel => rag::migrate::cancel(&db)?,
+        None => rag::migrate::status(&db)?,
+    };
+
+    output(&status, json, |s| {
+        print_migration(s);
+        if s.next.is_some() && s.next_embedded == 0 {
+            println!(
+                "Run `cartog rag index` (or keep `cartog watch --rag` running) to fill it in."
+            );
+        }
+    })
+}
+
+fn print_migration(status: &rag::migrate::MigrationStatus) {
+    match &status.next {
+        Some(next) => println!(
+            "Migrating embeddings from {} to {next}: {}/{} symbols embedded",
+            status.current, status.next_embedded, status.embeddable
+        ),
+        None if status.completed => {
+            println!(
+                "Embedding model migration complete: now using {}",
+                status.current
+            )
+        }
+        None => println!(
+            "Embedding model: {} (no migration in progress)",
+            status.current
+        ),
+    }
+}
+
+/// Show the embedding policy, counts and backlog.
+pub fn cmd_rag_status(json: bool) -> Result<()> {
+    let db = open_db()?;
+    db.ensure_rag_supported()?;
+    let status = rag::freshness::status(&db)?;
+
+    output(&status, json, |s| {
+        println!("Embedding policy: {}", s.policy);
+        println!(
+            "Embedded {}/{} symbols ({} waiting)",
+            s.embedded, s.embeddable, s.backlog
+        );
+        print_migration(&s.models);
+        if s.backlog > 0 && s.policy == rag::freshness::EmbedPolicy::Manual {
+            println!("Run `cartog rag index` to embed the backlog.");
+        }
+    })
+}
+
+#[derive(Serialize, JsonSchema)]
+pub(crate) struct RebuildFtsResult {
+    rows_indexed: u32,
+    /// State of the index before the rebuild.
+    before: db::FtsCheck,
+}
+
+/// Rebuild the FTS index from `symbol_content`.
+pub fn cmd_rag_rebuild_fts(json: bool) -> Result<()> {
+    let db = open_db()?;
+    db.ensure_rag_supported()?;
+    let before = db.check_fts()?;
+    let rows_indexed = db.rebuild_fts()?;
+
+    output(
+        &RebuildFtsResult {
+            rows_indexed,
+            before,
+        },
+        json,
+        |r| {
+            println!("Rebuilt FTS index from {} symbol contents", r.rows_indexed);
+            println!("Before:   {}", describe_fts(&r.before));
+        },
+    )
+}
+
+/// Show the keyword search column weights, after storing `weights` or
+/// restoring the defaults with `reset`.
+pub fn cmd_rag_fts_weights(weights: Option<db::FtsWeights>, reset: bool, json: bool) -> Result<()> {
+    let db = open_db()?;
+    if reset || weights.is_some() {
+        db.set_fts_weights(weights)?;
+    }
+    let weights = db.fts_weights()?;
+
+    output(&weights, json, |w| {
+        println!("name:            {}", w.name);
+        println!("normalized name: {}", w.normalized_name);
+        println!("content:         {}", w.content);
+    })
+}
+
+/// Show the re-ranker calibration, after storing a new `temperature` and/or
+/// `min_relevance` or restoring the defaults with `reset`.
+pub fn cmd_rag_calibration(
+    temperature: Option<f64>,
+    min_relevance: Option<f64>,
+    reset: bool,
+    json: bool,
+) -> Result<()> {
+    let db = open_db()?;
+    if reset {
+        db.set_rerank_calibration(None)?;
+    } else if temperature.is_some() || min_relevance.is_some() {
+        let mut calibration = db.rerank_calibration()?;
+        calibration.temperature = temperature.unwrap_or(calibration.temperature);
+        calibration.min_relevance = min_relevance.unwrap_or(calibration.min_relevance);
+        calibration.validate().map_err(anyhow::Error::msg)?;
+        db.set_rerank_calibration(Some(calibration))?;
+    }
+    let calibration = db.rerank_calibration()?;
+
+    output(&calibration, json, |c| {
+        println!("temperature:   {}", c.temperature);
+        println!("min relevance: {}", c.min_relevance);
     })
 }
 
 /// Semantic search over code symbols.
 pub fn cmd_rag_search(
     query: &str,
-    kind: Option<SymbolKindFilter>,
-    limit: u32,
+    filter: rag::search::SearchFilter<'_>,
+    limit: Option<u32>,
+    keyword_only: bool,
+    min_score: Option<f64>,
+    budget: Option<u64>,
     json: bool,
 ) -> Result<()> {
     let db = open_db()?;
-    let kind_filter = kind.map(crate::types::SymbolKind::from);
+    db.ensure_rag_supported()?;
+    let mode = if keyword_only {
+        rag::search::SearchMode::Keyword
+    } else {
+        rag::search::SearchMode::Hybrid
+    };
 
-    let search_result = rag::search::hybrid_search(&db, query, limit, kind_filter)?;
+    let limit = limit_for("rag-search", limit).unwrap_or(MAX_SEARCH_LIMIT);
+    let mut search_result = rag::search::search(&db, query, limit, filter, mode, min_score)?;
+    // Ranking keeps the best `limit` hits: say so when candidates were left out.
+    if search_result.results.len() == limit as usize && search_result.merged_count > limit {
+        note_truncated("rag-search", limit, None);
+    }
+    if let Some(budget) = budget {
+        rag::pack::pack(&mut search_result, budget);
+    }
 
     output(&search_result, json, |sr| {
         if sr.results.is_empty() {
@@ -345,17 +2329,25 @@ pub fn cmd_rag_search(
             return;
         }
         println!(
-            "Found {} results (FTS: {}, vector: {}, merged: {})\n",
+            "Found {} results ({} search; FTS: {}, vector: {}, merged: {})\n",
             sr.results.len(),
+            sr.mode,
             sr.fts_count,
             sr.vec_count,
             sr.merged_count
         );
+        if let Some(p) = sr.packing {
+            println!(
+                "Packed into {} of {} tokens: {} full, {} signature only\n",
+                p.tokens, p.budget, p.full_bodies, p.signatures_only
+            );
+        }
         for (i, r) in sr.results.iter().enumerate() {
             let sources = r.sources.join("+");
             let rerank_str = r
                 .rerank_score
-                .map(|s| format!(" rerank={s:.2}"))
+                .zip(r.relevance)
+                .map(|(s, rel)| format!(" rerank={s:.2} relevance={rel:.2}"))
                 .unwrap_or_default();
             println!(
                 "{}. {} {}  {}:{}-{}  [{}] score={:.4}{rerank_str}",
@@ -368,7 +2360,13 @@ pub fn cmd_rag_search(
                 sources,
                 r.rrf_score,
             );
-            if let Some(ref content) = r.content {
+            if !r.why.is_empty() {
+                println!("    why: {}", r.why);
+            }
+            if r.body_omitted {
+                let signature = r.content.as_deref().unwrap_or_default().trim();
+                println!("    {signature}  (body omitted)\n");
+            } else if let Some(ref content) = r.content {
                 // Show first 3 lines of content as preview
                 let preview: String = content
                     .lines()
@@ -382,12 +2380,169 @@ pub fn cmd_rag_search(
     })
 }
 
+/// Serve the graph UI on localhost, optionally re-indexing on file changes.
+pub fn cmd_viz(port: u16, watch: bool) -> Result<()> {
+    #[cfg(not(feature = "watch"))]
+    if watch {
+        anyhow::bail!(
+            "`viz --watch` is not available: cartog was built without the `watch` feature \
+             (rebuild with `cargo install cartog --features watch`)"
+        );
+    }
+    #[cfg(feature = "watch")]
+    let _watch_handle = if watch {
+        Some(watch::spawn_watch(
+            WatchConfig::new(PathBuf::from(".")),
+            &db_file().to_string_lossy(),
+        )?)
+    } else {
+        None
+    };
+
+    viz::run_viz(port, db_file())
+}
+
+/// Browse the index in the terminal, starting from the results of `query`.
+#[cfg(feature = "tui")]
+pub fn cmd_tui(query: Option<&str>) -> Result<()> {
+    let db = open_db()?;
+    anyhow::ensure!(
+        db.has_indexed_files()?,
+        "the index is empty; run `cartog index` first"
+    );
+    crate::tui::run_tui(&db, Path::new("."), query)
+}
+
+/// Run queries at a prompt until the user quits.
+pub fn cmd_repl() -> Result<()> {
+    let db = open_db()?;
+    anyhow::ensure!(
+        db.has_indexed_files()?,
+        "the index is empty; run `cartog index` first"
+    );
+    crate::repl::run_repl(&db, Path::new("."))
+}
+
+/// Combine the symbols of query results or saved `--json` outputs.
+pub fn cmd_set(op: SetOp, operands: &[String], json: bool) -> Result<()> {
+    let db = open_db()?;
+    let sets = operands
+        .iter()
+        .map(|operand| operand_ids(&db, operand))
+        .collect::<Result<Vec<_>>>()?;
+    let result = setops::evaluate(&db, op, &sets)?;
+
+    output(&result, json, |r| {
+        if r.symbols.is_empty() {
+            println!("No symbols");
+        }
+        for sym in &r.symbols {
+            println!(
+                "{kind}  {name}  {file}:{line}",
+                kind = sym.kind,
+                name = sym.name,
+                file = sym.file_path,
+                line = sym.start_line,
+            );
+        }
+        if !r.missing.is_empty() {
+            println!(
+                "{} symbol(s) no longer indexed; re-run the queries they came from",
+                r.missing.len()
+            );
+        }
+    })
+}
+
+/// Symbol IDs of a `cartog set` operand: a `--json` output file (`-` for stdin)
+/// or a `kind:argument` query.
+fn operand_ids(db: &ShardedDatabase, operand: &str) -> Result<Vec<String>> {
+    let text = if operand == "-" {
+        std::io::read_to_string(std::io::stdin()).context("failed to read stdin")?
+    } else if Path::new(operand).is_file() {
+        std::fs::read_to_string(operand).with_context(|| format!("failed to read {operand}"))?
+    } else {
+        let query = SetQuery::parse(operand).with_context(|| {
+            format!("'{operand}' is neither a file nor a query like refs:NAME or search:TEXT")
+        })?;
+        return query.symbol_ids(db);
+    };
+    let value: serde_json::Value = serde_json::from_str(&text)
+        .with_context(|| format!("{operand} is not a cartog --json output"))?;
+    Ok(setops::symbol_ids(&value))
+}
+
+/// Serve the read-only HTTP JSON API, optionally re-indexing on file changes.
+pub fn cmd_serve_api(config: ApiConfig, watch: bool) -> Result<()> {
+    #[cfg(not(feature = "watch"))]
+    if watch {
+        anyhow::bail!(
+            "`serve --api --watch` is not available: cartog was built without the `watch` feature \
+             (rebuild with `cargo install cartog --features watch`)"
+        );
+    }
+    #[cfg(feature = "watch")]
+    let _watch_handle = if watch {
+        Some(watch::spawn_watch(
+            WatchConfig::new(PathBuf::from(".")),
+            &db_file().to_string_lossy(),
+        )?)
+    } else {
+        None
+    };
+
+    api::run_api(&config, db_file())
+}
+
 /// Watch for file changes and auto-re-index.
-pub fn cmd_watch(path: &str, debounce: u64, rag: bool, rag_delay: u64) -> Result<()> {
+#[cfg(feature = "watch")]
+pub fn cmd_watch(
+    path: &str,
+    debounce: u64,
+    rag: bool,
+    rag_delay: u64,
+    filter: WatchFilter,
+    simulate: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    if rag {
+        rag::ensure_models_enabled()?;
+    }
     let mut config = WatchConfig::new(PathBuf::from(path));
     config.debounce = Duration::from_secs(debounce);
     config.rag = rag;
     config.rag_delay = Duration::from_secs(rag_delay);
+    config.filter = filter;
+
+    let Some(script) = simulate else {
+        return watch::run_watch(config, &db_file().to_string_lossy());
+    };
+    let script = simulate::Script::load(Path::new(script))?;
+    let report = simulate::simulate(Path::new(path), &script, config)?;
+    output(&report, json, |r| {
+        for step in &r.steps {
+            let outcome = if step.reindexed {
+                format!(
+                    "re-indexed ({} indexed, {} removed)",
+                    step.files_indexed, step.files_removed
+                )
+            } else {
+                "no re-index".to_string()
+            };
+            let verdict = if step.failures.is_empty() {
+                "ok"
+            } else {
+                "FAILED"
+            };
+            println!("step {}: {outcome}  {verdict}", step.step);
+            for failure in &step.failures {
+                println!("  {failure}");
+            }
+        }
+    })?;
 
-    watch::run_watch(config, DB_FILE)
+    if report.failures > 0 {
+        anyhow::bail!("{} expectation(s) failed", report.failures);
+    }
+    Ok(())
 }
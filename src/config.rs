@@ -0,0 +1,316 @@
+//! Project settings from `.cartog.toml`, next to `.cartog.db`.
+//!
+//! Teams tune how much list commands print without passing `--limit` every time:
+//!
+//! ```toml
+//! [limits]
+//! refs = 50          # default --limit of `cartog refs`
+//! rag-search = 5
+//!
+//! [output]
+//! max_results = 200  # cap for list commands without a [limits] entry
+//!
+//! [hooks]            # commands run on index events, see `crate::hooks`
+//! on_index_complete = "curl -s -d @- http://localhost:9000/cartog"
+//!
+//! [index]            # files to index, see `crate::ignore`
+//! exclude = ["third_party/", "*.min.js"]
+//! per_branch = true  # one index per git branch, see `crate::indexes`
+//! max_edges_per_file = 50000  # cut extraction of larger files
+//! ```
+//!
+//! A `--limit` flag always wins over the file.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// File name of the project settings, at the project root.
+pub const CONFIG_FILE: &str = ".cartog.toml";
+
+/// Commands whose result count can be set under `[limits]`, with their built-in
+/// default (`None`: every result). Named like their subcommand (`rag search` →
+/// `rag-search`), as in `cartog schema`.
+pub const COMMAND_LIMITS: &[(&str, Option<u32>)] = &[
+    ("search", Some(30)),
+    ("refs", None),
+    ("callees", None),
+    ("callers", None),
+    ("impact", None),
+    ("raises", None),
+    ("path", Some(5)),
+    ("deps", None),
+    ("doc-coverage", Some(10)),
+    ("todos", Some(100)),
+    ("injections", Some(100)),
+    ("untested", Some(100)),
+    ("entrypoints", None),
+    ("reachable", Some(100)),
+    ("explain-resolution", Some(10)),
+    ("rag-search", Some(10)),
+];
+
+/// Settings read from [`CONFIG_FILE`]. Every section is optional.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Default `--limit` per command (see [`COMMAND_LIMITS`]).
+    #[serde(default)]
+    pub limits: BTreeMap<String, u32>,
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub index: IndexConfig,
+}
+
+/// The `[output]` section.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Most results a list command prints when neither `--limit` nor `[limits]`
+    /// sets its limit. Built-in defaults below it still apply.
+    pub max_results: Option<u32>,
+}
+
+/// The `[hooks]` section: shell commands run with a JSON payload on stdin.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Run after every index run, with its counts.
+    pub on_index_complete: Option<String>,
+    /// Run once per file an index run (re-)extracted.
+    pub on_file_indexed: Option<String>,
+    /// Run when an index run lowers the share of resolved edges.
+    pub on_resolution_drop: Option<String>,
+    /// Drop in the resolved share (0-1) that triggers `on_resolution_drop`
+    /// (default 0.01, one percentage point).
+    pub resolution_drop: Option<f64>,
+}
+
+/// The `[index]` section: gitignore-style globs relative to the indexed root, and
+/// which database to index into.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IndexConfig {
+    /// Only index files matching one of these (default: every supported file).
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Never index files matching one of these, on top of `.cartogignore`.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Index files matching a glob as a language (`"scripts/*" = "python"`),
+    /// whatever their extension or content says. The longest matching glob wins.
+    #[serde(default)]
+    pub languages: BTreeMap<String, String>,
+    /// Keep one index per git branch, as if `--index <branch>` were passed.
+    #[serde(default)]
+    pub per_branch: bool,
+    /// Leave out the files `.gitignore` files ignore (default true).
+    pub gitignore: Option<bool>,
+    /// Warn about files extracting more symbols than this (default
+    /// [`WARN_SYMBOLS_PER_FILE`]).
+    pub warn_symbols_per_file: Option<u32>,
+    /// Warn about files extracting more edges than this (default
+    /// [`WARN_EDGES_PER_FILE`]).
+    pub warn_edges_per_file: Option<u32>,
+    /// Keep at most this many symbols of a file, marking it truncated.
+    pub max_symbols_per_file: Option<u32>,
+    /// Keep at most this many edges of a file, marking it truncated.
+    pub max_edges_per_file: Option<u32>,
+}
+
+/// Symbols per file above which indexing and `stats` warn, by default.
+pub const WARN_SYMBOLS_PER_FILE: u32 = 5_000;
+/// Edges per file above which indexing and `stats` warn, by default.
+pub const WARN_EDGES_PER_FILE: u32 = 20_000;
+
+/// How much of the graph one file may add, from `[index]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileLimits {
+    pub warn_symbols: u32,
+    pub warn_edges: u32,
+    pub max_symbols: Option<u32>,
+    pub max_edges: Option<u32>,
+}
+
+impl FileLimits {
+    /// Whether `symbols` and `edges` extracted from one file call for a warning.
+    pub fn exceeded(&self, symbols: u32, edges: u32) -> bool {
+        symbols > self.warn_symbols || edges > self.warn_edges
+    }
+}
+
+impl IndexConfig {
+    /// Per-file thresholds and caps, with their defaults.
+    pub fn file_limits(&self) -> FileLimits {
+        FileLimits {
+            warn_symbols: self.warn_symbols_per_file.unwrap_or(WARN_SYMBOLS_PER_FILE),
+            warn_edges: self.warn_edges_per_file.unwrap_or(WARN_EDGES_PER_FILE),
+            max_symbols: self.max_symbols_per_file,
+            max_edges: self.max_edges_per_file,
+        }
+    }
+}
+
+impl ProjectConfig {
+    /// Settings of the project at `root`; defaults when it has no [`CONFIG_FILE`].
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(CONFIG_FILE);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+        Self::parse(&text).with_context(|| format!("invalid {}", path.display()))
+    }
+
+    /// Parse and check settings: commands under `[limits]` must take a limit, and
+    /// limits must be at least 1.
+    pub fn parse(text: &str) -> Result<Self> {
+        let config: Self = toml::from_str(text)?;
+        for (command, &limit) in &config.limits {
+            if !COMMAND_LIMITS.iter().any(|(c, _)| c == command) {
+                let known: Vec<&str> = COMMAND_LIMITS.iter().map(|(c, _)| *c).collect();
+                anyhow::bail!(
+                    "unknown command `{command}` under [limits] (expected one of: {})",
+                    known.join(", ")
+                );
+            }
+            if limit == 0 {
+                anyhow::bail!("limits.{command} must be at least 1");
+            }
+        }
+        if config.output.max_results == Some(0) {
+            anyhow::bail!("output.max_results must be at least 1");
+        }
+        for (pattern, language) in &config.index.languages {
+            if crate::languages::indexed_language(language).is_none() {
+                anyhow::bail!(
+                    "unknown language `{language}` for `{pattern}` under [index.languages] (expected one of: {}, tsx)",
+                    crate::languages::QUERY_LANGUAGES.join(", ")
+                );
+            }
+        }
+        let index = &config.index;
+        for (key, value) in [
+            ("warn_symbols_per_file", index.warn_symbols_per_file),
+            ("warn_edges_per_file", index.warn_edges_per_file),
+            ("max_symbols_per_file", index.max_symbols_per_file),
+            ("max_edges_per_file", index.max_edges_per_file),
+        ] {
+            if value == Some(0) {
+                anyhow::bail!("index.{key} must be at least 1");
+            }
+        }
+        if let Some(drop) = config.hooks.resolution_drop {
+            if !(drop > 0.0 && drop <= 1.0) {
+                anyhow::bail!("hooks.resolution_drop must be above 0 and at most 1");
+            }
+        }
+        Ok(config)
+    }
+
+    /// Result limit of `command`: `flag` if given, else its `[limits]` entry, else
+    /// its built-in default capped by `output.max_results`. `None` means no limit.
+    pub fn limit(&self, command: &str, flag: Option<u32>) -> Option<u32> {
+        let builtin = COMMAND_LIMITS
+            .iter()
+            .find(|(c, _)| *c == command)
+            .and_then(|(_, limit)| *limit);
+        flag.or_else(|| self.limits.get(command).copied()).or(
+            match (builtin, self.output.max_results) {
+                (Some(builtin), Some(max)) => Some(builtin.min(max)),
+                (builtin, max) => builtin.or(max),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_precedence() {
+        let config = ProjectConfig::parse(
+            "[limits]\nrefs = 50\nrag-search = 5\n\n[output]\nmax_results = 20\n",
+        )
+        .unwrap();
+        assert_eq!(config.limit("refs", None), Some(50));
+        assert_eq!(config.limit("refs", Some(500)), Some(500));
+        assert_eq!(config.limit("rag-search", None), Some(5));
+        // Built-in defaults are capped by max_results; unlimited commands get it.
+        assert_eq!(config.limit("search", None), Some(20));
+        assert_eq!(config.limit("doc-coverage", None), Some(10));
+        assert_eq!(config.limit("callees", None), Some(20));
+
+        let defaults = ProjectConfig::default();
+        assert_eq!(defaults.limit("search", None), Some(30));
+        assert_eq!(defaults.limit("refs", None), None);
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_and_zero() {
+        assert_eq!(ProjectConfig::parse("").unwrap(), ProjectConfig::default());
+        let err = ProjectConfig::parse("[limits]\nref = 50\n").unwrap_err();
+        assert!(err.to_string().contains("unknown command `ref`"));
+        assert!(ProjectConfig::parse("[limits]\nrefs = 0\n").is_err());
+        assert!(ProjectConfig::parse("[output]\nmax_result = 5\n").is_err());
+        assert!(ProjectConfig::parse("[limits]\nrefs = \"many\"\n").is_err());
+        assert!(ProjectConfig::parse("[hooks]\non_index_done = \"true\"\n").is_err());
+        assert!(ProjectConfig::parse("[hooks]\nresolution_drop = 0.0\n").is_err());
+        assert!(ProjectConfig::parse("[index]\nexcludes = [\"third_party/\"]\n").is_err());
+        let err = ProjectConfig::parse("[index.languages]\n\"*.h\" = \"c\"\n").unwrap_err();
+        assert!(err.to_string().contains("unknown language `c`"));
+        assert!(ProjectConfig::parse("[index.languages]\n\"bin/*\" = \"python\"\n").is_ok());
+        assert!(
+            ProjectConfig::parse("[index]\nper_branch = true\n")
+                .unwrap()
+                .index
+                .per_branch
+        );
+        assert!(ProjectConfig::parse(
+            "[index]
+max_edges_per_file = 0
+"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_file_limits_defaults() {
+        let limits = ProjectConfig::default().index.file_limits();
+        assert_eq!(limits.warn_symbols, WARN_SYMBOLS_PER_FILE);
+        assert_eq!(limits.max_edges, None);
+        assert!(!limits.exceeded(WARN_SYMBOLS_PER_FILE, WARN_EDGES_PER_FILE));
+        assert!(limits.exceeded(0, WARN_EDGES_PER_FILE + 1));
+
+        let config = ProjectConfig::parse(
+            "[index]
+warn_symbols_per_file = 10
+max_symbols_per_file = 20
+",
+        )
+        .unwrap();
+        let limits = config.index.file_limits();
+        assert!(limits.exceeded(11, 0));
+        assert_eq!(limits.max_symbols, Some(20));
+    }
+
+    #[test]
+    fn test_load_missing_file_is_default() {
+        let tmp = std::env::temp_dir().join(format!("cartog_config_{}", std::process::id()));
+        std::fs::create_dir_all(&tmp).unwrap();
+        assert_eq!(ProjectConfig::load(&tmp).unwrap(), ProjectConfig::default());
+        std::fs::write(tmp.join(CONFIG_FILE), "[limits]\ntodos = 7\n").unwrap();
+        assert_eq!(
+            ProjectConfig::load(&tmp).unwrap().limit("todos", None),
+            Some(7)
+        );
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+}
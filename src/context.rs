@@ -0,0 +1,322 @@
+//! `cartog context`: a symbol and what surrounds it, packed into a token budget.
+//!
+//! The bundle is filled greedily in order of usefulness: the symbol's signature and
+//! location always, then its code (cut by lines when too long), one line per caller
+//! and callee, and finally related search hits, as signature lines first and with
+//! their bodies while budget remains. Whatever doesn't fit is counted, so the
+//! agent knows what it is not seeing and can ask for it.
+
+use std::path::Path;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::rag::search::{self, SearchFilter, SearchMode};
+use crate::shard::ShardedDatabase;
+use crate::source;
+use crate::tokens;
+use crate::types::{Symbol, SymbolKind};
+
+/// Most callers and callees listed.
+const MAX_NEIGHBORS: usize = 10;
+/// Most related search hits considered.
+const MAX_RELATED: u32 = 5;
+
+/// A symbol with the part of its code that fit.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ContextItem {
+    #[serde(flatten)]
+    pub symbol: Symbol,
+    /// The code, or as many of its first lines as fit.
+    pub content: String,
+    /// Lines cut from the end of `content` to fit the budget.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub truncated_lines: u32,
+}
+
+/// A caller or callee, in one line.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ContextRef {
+    pub name: String,
+    pub kind: Option<SymbolKind>,
+    /// Where the neighbor is defined, or the call site when it is unresolved.
+    pub file_path: String,
+    pub line: u32,
+    pub signature: Option<String>,
+}
+
+/// Entries that did not fit in the budget (or past [`MAX_NEIGHBORS`]).
+#[derive(Debug, Clone, Copy, Default, Serialize, JsonSchema)]
+pub struct Omitted {
+    pub callers: u32,
+    pub callees: u32,
+    pub related: u32,
+}
+
+/// Result of [`build`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ContextPack {
+    pub target: ContextItem,
+    /// Docstring of the target when its code is cut or doesn't include it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docstring: Option<String>,
+    pub callers: Vec<ContextRef>,
+    pub callees: Vec<ContextRef>,
+    /// Search hits for the target, bodies included while budget remained.
+    pub related: Vec<ContextItem>,
+    pub omitted: Omitted,
+    pub budget: u64,
+    /// Estimated tokens of the bundle. Exceeds `budget` only when the target's
+    /// signature line alone does.
+    pub tokens: u64,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// Assemble the context of `query` under `budget` tokens: the definition named
+/// `query`, else the best search hit for it. `None` when neither exists.
+///
+/// Related hits come from `rag search` in `mode`, and are left out on a sharded
+/// index or without a `rag index`.
+pub fn build(
+    db: &ShardedDatabase,
+    root: &Path,
+    query: &str,
+    budget: u64,
+    mode: SearchMode,
+) -> Result<Option<ContextPack>> {
+    let rag = db.ensure_rag_supported().is_ok();
+    let target = match db.definitions(query, None)?.into_iter().next() {
+        Some(symbol) => symbol,
+        None if rag => {
+            let hits = search::search(db, query, 1, SearchFilter::default(), mode, None)?;
+            match hits.results.into_iter().next() {
+                Some(hit) => hit.symbol,
+                None => return Ok(None),
+            }
+        }
+        None => return Ok(None),
+    };
+
+    let mut packer = Packer { budget, used: 0 };
+    packer.used += tokens::estimate(&header(&target));
+    let code = source::snippet(db, root, target.clone(), 0)?
+        .code
+        .unwrap_or_default();
+    let (content, truncated_lines) = packer.take_lines(&code);
+    let docstring = target.docstring.clone().filter(|doc| {
+        let first = doc.lines().next().unwrap_or_default().trim();
+        (truncated_lines > 0 || !content.contains(first)) && packer.take(doc)
+    });
+
+    let mut omitted = Omitted::default();
+    let callers = db
+        .callers(&target.name, None)?
+        .into_iter()
+        .filter(|c| {
+            c.edge
+                .target_id
+                .as_deref()
+                .map_or(true, |id| id == target.id)
+        })
+        .map(|c| ContextRef {
+            name: c.caller.name,
+            kind: Some(c.caller.kind),
+            file_path: c.edge.file_path,
+            line: c.edge.line,
+            signature: c.caller.signature,
+        });
+    let callers = packer.take_refs(callers, &mut omitted.callers);
+    let callees = db
+        .call_sites(&target.name, false, None)?
+        .into_iter()
+        .filter(|site| site.edge.source_id == target.id)
+        .map(|site| match site.target {
+            Some(t) => ContextRef {
+                name: t.name,
+                kind: Some(t.kind),
+                file_path: t.file_path,
+                line: t.start_line,
+                signature: t.signature,
+            },
+            None => ContextRef {
+                name: site.edge.target_name,
+                kind: None,
+                file_path: site.edge.file_path,
+                line: site.edge.line,
+                signature: None,
+            },
+        });
+    let callees = packer.take_refs(callees, &mut omitted.callees);
+
+    let mut related = Vec::new();
+    if rag {
+        let related_query = match target.docstring.as_deref().and_then(|d| d.lines().next()) {
+            Some(summary) => format!("{} {summary}", target.name),
+            None => target.name.clone(),
+        };
+        let listed = |s: &Symbol| {
+            s.id == target.id
+                || callers
+                    .iter()
+                    .chain(&callees)
+                    .any(|r| r.name == s.name && r.file_path == s.file_path)
+        };
+        let hits = search::search(
+            db,
+            &related_query,
+            MAX_RELATED + 1,
+            SearchFilter::default(),
+            mode,
+            None,
+        )?;
+        // Signature lines first, so every hit that fits is listed, then bodies.
+        let mut bodies = Vec::new();
+        for hit in hits.results.into_iter().filter(|h| !listed(&h.symbol)) {
+            let content = hit.content.unwrap_or_default();
+            let signature = first_line(&content).to_string();
+            let entry = format!("{}\n{signature}", header(&hit.symbol));
+            if related.len() < MAX_RELATED as usize && packer.take(&entry) {
+                let rest = content.lines().count().saturating_sub(1) as u32;
+                bodies.push(content);
+                related.push(ContextItem {
+                    symbol: hit.symbol,
+                    content: signature,
+                    truncated_lines: rest,
+                });
+            } else {
+                omitted.related += 1;
+            }
+        }
+        for (item, body) in related.iter_mut().zip(bodies) {
+            let extra = tokens::estimate(&body).saturating_sub(tokens::estimate(&item.content));
+            if item.truncated_lines > 0 && packer.fits(extra) {
+                packer.used += extra;
+                item.content = body;
+                item.truncated_lines = 0;
+            }
+        }
+    }
+
+    Ok(Some(ContextPack {
+        target: ContextItem {
+            symbol: target,
+            content,
+            truncated_lines,
+        },
+        docstring,
+        callers,
+        callees,
+        related,
+        omitted,
+        budget,
+        tokens: packer.used,
+    }))
+}
+
+/// Tokens spent so far against the budget.
+struct Packer {
+    budget: u64,
+    used: u64,
+}
+
+impl Packer {
+    fn fits(&self, tokens: u64) -> bool {
+        self.used + tokens <= self.budget
+    }
+
+    /// Spend the cost of `text` if it fits.
+    fn take(&mut self, text: &str) -> bool {
+        let cost = tokens::estimate(text) + 1;
+        let fits = self.fits(cost);
+        if fits {
+            self.used += cost;
+        }
+        fits
+    }
+
+    /// The first lines of `code` that fit, and the number of lines left out.
+    fn take_lines(&mut self, code: &str) -> (String, u32) {
+        let lines: Vec<&str> = code.lines().collect();
+        let kept = lines.iter().take_while(|line| self.take(line)).count();
+        (lines[..kept].join("\n"), (lines.len() - kept) as u32)
+    }
+
+    /// The first [`MAX_NEIGHBORS`] of `refs` that fit, counting the rest in `omitted`.
+    fn take_refs(
+        &mut self,
+        refs: impl Iterator<Item = ContextRef>,
+        omitted: &mut u32,
+    ) -> Vec<ContextRef> {
+        let mut taken = Vec::new();
+        for r in refs {
+            if taken.len() < MAX_NEIGHBORS && self.take(&ref_line(&r)) {
+                taken.push(r);
+            } else {
+                *omitted += 1;
+            }
+        }
+        taken
+    }
+}
+
+fn first_line(content: &str) -> &str {
+    content
+        .lines()
+        .find(|l| !l.trim().is_empty())
+        .unwrap_or_default()
+        .trim_end()
+}
+
+/// Heading line of a symbol in the bundle.
+pub fn header(symbol: &Symbol) -> String {
+    format!(
+        "{} {}  {}:{}-{}",
+        symbol.kind, symbol.name, symbol.file_path, symbol.start_line, symbol.end_line
+    )
+}
+
+/// One line of the bundle for a caller or callee.
+pub fn ref_line(r: &ContextRef) -> String {
+    let kind = r.kind.map_or("unresolved".to_string(), |k| k.to_string());
+    let signature = r.signature.as_deref().unwrap_or_default();
+    format!("{kind} {}  {}:{}  {signature}", r.name, r.file_path, r.line)
+        .trim_end()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packer_cuts_lines_and_refs() {
+        let mut packer = Packer {
+            budget: 10,
+            used: 0,
+        };
+        // Each line costs its estimate plus one for the newline.
+        let (kept, cut) = packer.take_lines("fn a() {\n    b();\n}\n");
+        assert_eq!(kept, "fn a() {\n    b();\n}");
+        assert_eq!(cut, 0);
+        assert_eq!(packer.used, 3 + 3 + 2);
+
+        let refs = (0..3).map(|i| ContextRef {
+            name: format!("f{i}"),
+            kind: None,
+            file_path: "a.py".to_string(),
+            line: i,
+            signature: None,
+        });
+        let mut omitted = 0;
+        assert!(packer.take_refs(refs, &mut omitted).is_empty());
+        assert_eq!(omitted, 3);
+
+        let (kept, cut) = packer.take_lines("x\ny\nz");
+        assert_eq!((kept.as_str(), cut), ("x", 2));
+        assert!(packer.used <= packer.budget);
+    }
+}
@@ -0,0 +1,341 @@
+//! Circular import detection (`cartog cycles`).
+//!
+//! Builds a graph of files (or directories) from resolved import edges: `a.py`
+//! imports `b.py` when an import in `a.py` resolved to a symbol defined in `b.py`.
+//! Each strongly connected component with more than one node is a cycle group,
+//! reported with a shortest cycle through it and the import lines along the way,
+//! which is where one would cut it. Unresolved imports (third-party modules) are
+//! not part of the graph.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::shard::ShardedDatabase;
+use crate::types::EdgeKind;
+
+/// Granularity of the import graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CycleScope {
+    /// One node per file.
+    File,
+    /// One node per directory: imports between files of the same directory are ignored.
+    Dir,
+}
+
+/// One import along a cycle: `from` imports `to` at `file:line`.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ImportLink {
+    pub from: String,
+    pub to: String,
+    /// File holding the import (`from` itself in file scope).
+    pub file: String,
+    pub line: u32,
+    /// Imported name.
+    pub name: String,
+}
+
+/// A group of files (or directories) that import each other, directly or not.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ImportCycle {
+    /// Every node of the strongly connected component, sorted.
+    pub members: Vec<String>,
+    /// A shortest cycle through the first member: each node imports the next,
+    /// and the last imports the first.
+    pub path: Vec<String>,
+    /// The import behind each step of `path`, closing back to its start.
+    pub links: Vec<ImportLink>,
+}
+
+/// Result of [`find_cycles`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct CycleReport {
+    pub scope: CycleScope,
+    /// Nodes with at least one resolved import to or from another node.
+    pub nodes: usize,
+    /// Cycle groups, largest first, then by first member.
+    pub cycles: Vec<ImportCycle>,
+}
+
+/// Find import cycles, optionally only among files of one language
+/// (`typescript` includes `.tsx`).
+pub fn find_cycles(
+    db: &ShardedDatabase,
+    language: Option<&str>,
+    scope: CycleScope,
+) -> Result<CycleReport> {
+    let symbols = db.all_symbols()?;
+    let file_of: HashMap<&str, &str> = symbols
+        .iter()
+        .map(|s| (s.id.as_str(), s.file_path.as_str()))
+        .collect();
+    // Language each file was indexed as, only needed to filter by one.
+    let file_language: HashMap<String, String> = match language {
+        None => HashMap::new(),
+        Some(_) => db
+            .file_stats()?
+            .into_iter()
+            .map(|f| (f.path, f.language))
+            .collect(),
+    };
+    let in_language = |file: &str| match language {
+        None => true,
+        Some(lang) => file_language
+            .get(file)
+            .map(|l| if l == "tsx" { "typescript" } else { l.as_str() })
+            .is_some_and(|l| l == lang),
+    };
+    let node_of = |file: &str| -> String {
+        match scope {
+            CycleScope::File => file.to_string(),
+            CycleScope::Dir => match file.rsplit_once('/') {
+                Some((dir, _)) => dir.to_string(),
+                None => ".".to_string(),
+            },
+        }
+    };
+
+    // First import (by file and line) for each pair of distinct nodes.
+    let mut links: BTreeMap<(String, String), ImportLink> = BTreeMap::new();
+    for edge in db.all_edges()? {
+        if edge.kind != EdgeKind::Imports {
+            continue;
+        }
+        let Some(&target_file) = edge.target_id.as_deref().and_then(|id| file_of.get(id)) else {
+            continue;
+        };
+        if !in_language(&edge.file_path) || !in_language(target_file) {
+            continue;
+        }
+        let (from, to) = (node_of(&edge.file_path), node_of(target_file));
+        if from == to {
+            continue;
+        }
+        let link = ImportLink {
+            from: from.clone(),
+            to: to.clone(),
+            file: edge.file_path,
+            line: edge.line,
+            name: edge.target_name,
+        };
+        links
+            .entry((from, to))
+            .and_modify(|l| {
+                if (&link.file, link.line) < (&l.file, l.line) {
+                    *l = link.clone();
+                }
+            })
+            .or_insert(link);
+    }
+
+    let mut names: Vec<&str> = links
+        .keys()
+        .flat_map(|(from, to)| [from.as_str(), to.as_str()])
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    let index: HashMap<&str, usize> = names.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+    let mut adjacency = vec![Vec::new(); names.len()];
+    for (from, to) in links.keys() {
+        adjacency[index[from.as_str()]].push(index[to.as_str()]);
+    }
+
+    let mut cycles: Vec<ImportCycle> = strongly_connected(&adjacency)
+        .into_iter()
+        .filter(|component| component.len() > 1)
+        .map(|mut component| {
+            component.sort_unstable();
+            let path = shortest_cycle(&adjacency, &component);
+            let links = (0..path.len())
+                .map(|i| {
+                    let key = (
+                        names[path[i]].to_string(),
+                        names[path[(i + 1) % path.len()]].to_string(),
+                    );
+                    links[&key].clone()
+                })
+                .collect();
+            ImportCycle {
+                members: component.iter().map(|&n| names[n].to_string()).collect(),
+                path: path.iter().map(|&n| names[n].to_string()).collect(),
+                links,
+            }
+        })
+        .collect();
+    cycles.sort_by(|a, b| {
+        b.members
+            .len()
+            .cmp(&a.members.len())
+            .then_with(|| a.members.cmp(&b.members))
+    });
+
+    Ok(CycleReport {
+        scope,
+        nodes: names.len(),
+        cycles,
+    })
+}
+
+/// Strongly connected components (Tarjan), iterative so deep import chains
+/// cannot overflow the stack.
+fn strongly_connected(adjacency: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let n = adjacency.len();
+    let mut order = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut next = 0;
+
+    for root in 0..n {
+        if order[root] != UNVISITED {
+            continue;
+        }
+        // (node, index of the next neighbour to visit)
+        let mut work = vec![(root, 0)];
+        order[root] = next;
+        low[root] = next;
+        next += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&mut (node, ref mut child)) = work.last_mut() {
+            if let Some(&neighbour) = adjacency[node].get(*child) {
+                *child += 1;
+                if order[neighbour] == UNVISITED {
+                    order[neighbour] = next;
+                    low[neighbour] = next;
+                    next += 1;
+                    stack.push(neighbour);
+                    on_stack[neighbour] = true;
+                    work.push((neighbour, 0));
+                } else if on_stack[neighbour] {
+                    low[node] = low[node].min(order[neighbour]);
+                }
+                continue;
+            }
+            work.pop();
+            if let Some(&(parent, _)) = work.last() {
+                low[parent] = low[parent].min(low[node]);
+            }
+            if low[node] == order[node] {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// Shortest cycle from the first (smallest) node of a sorted component back to
+/// itself, staying inside the component.
+fn shortest_cycle(adjacency: &[Vec<usize>], component: &[usize]) -> Vec<usize> {
+    let start = component[0];
+    let mut previous: HashMap<usize, usize> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(node) = queue.pop_front() {
+        for &next in &adjacency[node] {
+            if next == start {
+                let mut path = vec![node];
+                while let Some(&p) = previous.get(path.last().unwrap()) {
+                    path.push(p);
+                }
+                path.reverse();
+                return path;
+            }
+            if component.binary_search(&next).is_ok() && !previous.contains_key(&next) {
+                previous.insert(next, node);
+                queue.push_back(next);
+            }
+        }
+    }
+    unreachable!("a strongly connected component has a cycle through every member")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::types::{Edge, Symbol, SymbolKind};
+
+    /// `a -> b -> c -> a` and `c -> d` in `pkg/`, `d <-> e` across directories,
+    /// plus an import of an unknown module.
+    fn setup() -> ShardedDatabase {
+        let db = Database::open_memory().unwrap();
+        let files = ["pkg/a.py", "pkg/b.py", "pkg/c.py", "pkg/d.py", "lib/e.py"];
+        let defs: Vec<Symbol> = files
+            .iter()
+            .map(|f| Symbol::new("thing", SymbolKind::Function, f, 1, 2, 0, 10))
+            .collect();
+        db.insert_symbols(&defs).unwrap();
+        let import = |from: usize, to: Option<usize>, line| {
+            let mut e = Edge::new(
+                &defs[from].id,
+                "thing",
+                EdgeKind::Imports,
+                files[from],
+                line,
+            );
+            e.target_id = to.map(|t| defs[t].id.clone());
+            e
+        };
+        db.insert_edges(&[
+            import(0, Some(1), 3),
+            import(1, Some(2), 4),
+            import(2, Some(0), 5),
+            import(2, Some(3), 6),
+            import(3, Some(4), 7),
+            import(4, Some(3), 8),
+            import(4, None, 9),
+        ])
+        .unwrap();
+        ShardedDatabase::from(db)
+    }
+
+    #[test]
+    fn test_file_cycles_with_paths() {
+        let report = find_cycles(&setup(), None, CycleScope::File).unwrap();
+        assert_eq!(report.nodes, 5);
+        let cycles: Vec<(Vec<&str>, Vec<u32>)> = report
+            .cycles
+            .iter()
+            .map(|c| {
+                (
+                    c.path.iter().map(String::as_str).collect(),
+                    c.links.iter().map(|l| l.line).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            cycles,
+            [
+                (vec!["pkg/a.py", "pkg/b.py", "pkg/c.py"], vec![3, 4, 5]),
+                (vec!["lib/e.py", "pkg/d.py"], vec![8, 7]),
+            ]
+        );
+        assert_eq!(report.cycles[1].links[0].to, "pkg/d.py");
+    }
+
+    #[test]
+    fn test_dir_scope_and_language_filter() {
+        let db = setup();
+        let report = find_cycles(&db, None, CycleScope::Dir).unwrap();
+        assert_eq!(report.cycles.len(), 1);
+        assert_eq!(report.cycles[0].path, ["lib", "pkg"]);
+
+        let report = find_cycles(&db, Some("rust"), CycleScope::File).unwrap();
+        assert_eq!(report.nodes, 0);
+        assert!(report.cycles.is_empty());
+    }
+}
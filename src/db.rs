@@ -1,20 +1,80 @@
+use std::cmp::Reverse;
+use std::time::Instant;
+
 use anyhow::{Context, Result};
-use rusqlite::ffi::sqlite3_auto_extension;
+use rusqlite::ffi;
 use rusqlite::{params, Connection, OptionalExtension};
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use sqlite_vec::sqlite3_vec_init;
 use tracing::warn;
 
-use crate::types::{Edge, EdgeKind, FileInfo, Symbol, SymbolKind, Visibility};
+use crate::export::{Cell, ExportTable};
+use crate::fingerprint::RepoFingerprint;
+use crate::packages::{BuildTool, Package};
+use crate::renames::{Rename, SymbolBody};
+use crate::types::{
+    Anchor, CallSite, Caller, Edge, EdgeKind, FileInfo, ImportAlias, Injection, LineCounts, Symbol,
+    SymbolKind, SymbolMatch, Visibility,
+};
 
 const SQL_INSERT_SYMBOL: &str = "INSERT OR REPLACE INTO symbols
      (id, name, kind, file_path, start_line, end_line, start_byte, end_byte,
-      parent_id, signature, visibility, is_async, docstring)
-     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)";
+      parent_id, signature, visibility, is_async, docstring, entrypoint,
+      db_table, generation)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)";
+
+// Candidate lookups of [`Database::resolve_edges`], in priority order. Shared with
+// [`Database::explain_resolution`] so explanations pick the same definition.
+const SQL_RESOLVE_SAME_FILE: &str =
+    "SELECT id FROM symbols WHERE name = ?1 AND file_path = ?2 LIMIT 1";
+const SQL_RESOLVE_PINNED: &str = "SELECT s.id FROM pins p
+     JOIN symbols s ON s.name = p.name AND s.file_path = p.file_path
+     WHERE p.name = ?1 AND s.kind != 'import'
+     ORDER BY s.start_line LIMIT 1";
+const SQL_RESOLVE_SAME_DIR: &str =
+    "SELECT id FROM symbols WHERE name = ?1 AND file_path LIKE ?2 LIMIT 1";
+const SQL_RESOLVE_ANYWHERE: &str = "SELECT id FROM symbols WHERE name = ?1 LIMIT 2";
+
+// Edges awaiting resolution: the lookup name, the edge's file and its directory
+// pattern (empty at the root), and the target once a pass finds one.
+const SQL_RESOLVE_QUEUE: &str = "
+CREATE TEMP TABLE IF NOT EXISTS resolve_queue (
+    edge_id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    dir TEXT NOT NULL,
+    target_id TEXT
+);
+CREATE INDEX IF NOT EXISTS temp.idx_resolve_queue_name ON resolve_queue(name);
+DELETE FROM temp.resolve_queue;";
+
+// The lookups above as set-based passes over the queue.
+const SQL_PASS_SAME_FILE: &str = "UPDATE temp.resolve_queue SET target_id = (
+         SELECT id FROM symbols WHERE name = resolve_queue.name
+           AND file_path = resolve_queue.file_path LIMIT 1)
+     WHERE target_id IS NULL";
+const SQL_PASS_PINNED: &str = "UPDATE temp.resolve_queue SET target_id = (
+         SELECT s.id FROM pins p
+         JOIN symbols s ON s.name = p.name AND s.file_path = p.file_path
+         WHERE p.name = resolve_queue.name AND s.kind != 'import'
+         ORDER BY s.start_line LIMIT 1)
+     WHERE target_id IS NULL AND name IN (SELECT name FROM pins)";
+const SQL_PASS_SAME_DIR: &str = "UPDATE temp.resolve_queue SET target_id = (
+         SELECT id FROM symbols WHERE name = resolve_queue.name
+           AND file_path LIKE resolve_queue.dir LIMIT 1)
+     WHERE target_id IS NULL AND dir != ''";
+
+/// Names looked up per query in the unique-definition pass of [`Database::resolve_edges`].
+const RESOLVE_NAME_BATCH: usize = 500;
+
+/// Candidates listed per step by [`Database::explain_resolution`].
+const MAX_EXPLAINED_CANDIDATES: u32 = 20;
 
 const SQL_INSERT_EDGE: &str =
-    "INSERT INTO edges (source_id, target_name, target_id, kind, file_path, line)
-     VALUES (?1, ?2, ?3, ?4, ?5, ?6)";
+    "INSERT INTO edges (source_id, target_name, target_id, kind, file_path, line, target_kind,
+                        conditional)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
 
 const SCHEMA: &str = r#"
 CREATE TABLE IF NOT EXISTS symbols (
@@ -30,7 +90,10 @@ CREATE TABLE IF NOT EXISTS symbols (
     signature TEXT,
     visibility TEXT,
     is_async BOOLEAN DEFAULT FALSE,
-    docstring TEXT
+    docstring TEXT,
+    entrypoint TEXT,
+    db_table TEXT,
+    generation INTEGER NOT NULL DEFAULT 0
 );
 
 CREATE TABLE IF NOT EXISTS edges (
@@ -41,6 +104,8 @@ CREATE TABLE IF NOT EXISTS edges (
     kind TEXT NOT NULL,
     file_path TEXT NOT NULL,
     line INTEGER,
+    target_kind TEXT,
+    conditional BOOLEAN NOT NULL DEFAULT FALSE,
     FOREIGN KEY (source_id) REFERENCES symbols(id)
 );
 
@@ -49,7 +114,17 @@ CREATE TABLE IF NOT EXISTS files (
     last_modified REAL,
     hash TEXT,
     language TEXT,
-    num_symbols INTEGER DEFAULT 0
+    num_symbols INTEGER DEFAULT 0,
+    generation INTEGER NOT NULL DEFAULT 0,
+    code_lines INTEGER NOT NULL DEFAULT 0,
+    comment_lines INTEGER NOT NULL DEFAULT 0,
+    blank_lines INTEGER NOT NULL DEFAULT 0,
+    truncated BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE IF NOT EXISTS removed_files (
+    path TEXT PRIMARY KEY,
+    generation INTEGER NOT NULL
 );
 
 CREATE TABLE IF NOT EXISTS metadata (
@@ -65,6 +140,121 @@ CREATE INDEX IF NOT EXISTS idx_edges_source ON edges(source_id);
 CREATE INDEX IF NOT EXISTS idx_edges_target ON edges(target_name);
 CREATE INDEX IF NOT EXISTS idx_edges_target_id ON edges(target_id);
 CREATE INDEX IF NOT EXISTS idx_edges_kind ON edges(kind);
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ts REAL NOT NULL,
+    tool TEXT NOT NULL,
+    params TEXT NOT NULL,
+    result_count INTEGER,
+    duration_ms INTEGER NOT NULL,
+    ok BOOLEAN NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_ts ON audit_log(ts);
+
+CREATE TABLE IF NOT EXISTS symbol_hits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ts REAL NOT NULL,
+    tool TEXT NOT NULL,
+    name TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    kind TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_symbol_hits_ts ON symbol_hits(ts);
+
+CREATE TABLE IF NOT EXISTS jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    params TEXT NOT NULL,
+    status TEXT NOT NULL,
+    phase TEXT NOT NULL,
+    done INTEGER NOT NULL DEFAULT 0,
+    total INTEGER NOT NULL DEFAULT 0,
+    started REAL NOT NULL,
+    finished REAL,
+    result TEXT,
+    error TEXT
+);
+
+CREATE TABLE IF NOT EXISTS anchors (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tag TEXT NOT NULL,
+    text TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    line INTEGER NOT NULL,
+    symbol_id TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_anchors_file ON anchors(file_path);
+CREATE INDEX IF NOT EXISTS idx_anchors_tag ON anchors(tag);
+
+CREATE TABLE IF NOT EXISTS injections (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    language TEXT NOT NULL,
+    text TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    line INTEGER NOT NULL,
+    symbol_id TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_injections_file ON injections(file_path);
+
+CREATE TABLE IF NOT EXISTS import_aliases (
+    file_path TEXT NOT NULL,
+    alias TEXT NOT NULL,
+    name TEXT NOT NULL,
+    line INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_import_aliases_file ON import_aliases(file_path, alias);
+
+CREATE TABLE IF NOT EXISTS pins (
+    name TEXT PRIMARY KEY,
+    file_path TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS notes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    symbol_name TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    text TEXT NOT NULL,
+    created REAL NOT NULL,
+    expires REAL NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_notes_symbol ON notes(symbol_name, file_path);
+
+CREATE TABLE IF NOT EXISTS reservations (
+    symbol_name TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    created REAL NOT NULL,
+    expires REAL NOT NULL,
+    PRIMARY KEY (symbol_name, file_path)
+);
+
+CREATE TABLE IF NOT EXISTS packages (
+    name TEXT PRIMARY KEY,
+    root TEXT NOT NULL,
+    tool TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS package_deps (
+    package TEXT NOT NULL,
+    dependency TEXT NOT NULL,
+    PRIMARY KEY (package, dependency)
+);
+
+CREATE TABLE IF NOT EXISTS parse_cache (
+    file_path TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    grammar TEXT NOT NULL,
+    extraction TEXT NOT NULL,
+    used REAL NOT NULL,
+    PRIMARY KEY (file_path, hash)
+);
 "#;
 
 /// Schema for RAG semantic search tables.
@@ -112,9 +302,91 @@ CREATE INDEX IF NOT EXISTS idx_embedding_map_symbol ON symbol_embedding_map(symb
 const RAG_VEC_SCHEMA: &str =
     "CREATE VIRTUAL TABLE IF NOT EXISTS symbol_vec USING vec0(embedding float[384])";
 
+/// Embedding IDs of existing symbols: vector rowids outside it are orphans.
+const LIVE_EMBEDDING_IDS: &str =
+    "SELECT em.id FROM symbol_embedding_map em JOIN symbols s ON s.id = em.symbol_id";
+
+/// sqlite-vec table an embedding read or write goes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorTable {
+    /// `symbol_vec`: vectors of the index's embedding model.
+    Current,
+    /// `symbol_vec_next`: vectors of the model being migrated to (see
+    /// [`Database::start_embedding_transition`]).
+    Next,
+}
+
+impl VectorTable {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Current => "symbol_vec",
+            Self::Next => "symbol_vec_next",
+        }
+    }
+}
+
 /// Default database filename, stored in the project root.
 pub const DB_FILE: &str = ".cartog.db";
 
+/// Columns added to existing tables after their first release, as
+/// `(table, column, type)`. `CREATE TABLE IF NOT EXISTS` leaves older databases'
+/// tables as they are, so [`add_missing_columns`] adds these on open. Older
+/// binaries ignore the extra nullable columns, so no [`SCHEMA_VERSION`] bump.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("symbols", "entrypoint", "TEXT"),
+    ("symbols", "db_table", "TEXT"),
+    ("symbols", "generation", "INTEGER NOT NULL DEFAULT 0"),
+    ("files", "generation", "INTEGER NOT NULL DEFAULT 0"),
+    ("edges", "target_kind", "TEXT"),
+    ("edges", "conditional", "BOOLEAN NOT NULL DEFAULT FALSE"),
+    ("files", "code_lines", "INTEGER NOT NULL DEFAULT 0"),
+    ("files", "comment_lines", "INTEGER NOT NULL DEFAULT 0"),
+    ("files", "blank_lines", "INTEGER NOT NULL DEFAULT 0"),
+    ("files", "truncated", "BOOLEAN NOT NULL DEFAULT FALSE"),
+];
+
+/// Statements filling a column of [`ADDED_COLUMNS`] from existing rows when it is added.
+const COLUMN_BACKFILLS: &[(&str, &str, &str)] = &[(
+    "edges",
+    "target_kind",
+    "UPDATE edges SET target_kind = (SELECT kind FROM symbols WHERE id = edges.target_id)
+     WHERE target_id IS NOT NULL",
+)];
+
+/// Version of [`SCHEMA`] this binary writes. Bump it when a schema change would
+/// break older binaries writing to the same database; they will then refuse to open it.
+///
+/// 2: stable symbol IDs. Older binaries would write line-based IDs among them.
+pub const SCHEMA_VERSION: u32 = 2;
+
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+const WRITER_VERSION_KEY: &str = "writer_version";
+const FTS_WEIGHTS_KEY: &str = "fts_weights";
+const RERANK_CALIBRATION_KEY: &str = "rerank_calibration";
+const WRITES_SINCE_MAINTENANCE_KEY: &str = "writes_since_maintenance";
+const LAST_RESOLUTION_KEY: &str = "last_resolution";
+/// Set when `index --injections` turned on string literal scanning for the index.
+const SCAN_INJECTIONS_KEY: &str = "scan_injections";
+/// Set when `index --parse-cache` turned on the extraction cache.
+const PARSE_CACHE_KEY: &str = "parse_cache";
+/// Set once every symbol ID is a stable one (see [`crate::types::stable_symbol_id`]).
+const STABLE_IDS_KEY: &str = "stable_symbol_ids";
+/// JSON map of language → grammar fingerprint the index was last fully built with.
+const GRAMMAR_VERSIONS_KEY: &str = "grammar_versions";
+/// Cached extractions kept per file, most recently used first.
+const PARSE_CACHE_PER_FILE: u32 = 3;
+/// Name of the model the `symbol_vec` vectors come from; absent for the default model.
+const EMBEDDING_MODEL_KEY: &str = "embedding_model";
+/// Name of the model being migrated to while `symbol_vec_next` fills in.
+const EMBEDDING_MODEL_NEXT_KEY: &str = "embedding_model_next";
+/// Embedding policy of the last `serve --rag` or `watch --rag` (see `rag::freshness`).
+const EMBED_POLICY_KEY: &str = "embed_policy";
+/// Tree the last full index was built from (see [`crate::fingerprint`]).
+const REPO_FINGERPRINT_KEY: &str = "repo_fingerprint";
+
+/// Files re-indexed or removed after which [`Database::record_writes`] runs maintenance.
+pub const AUTO_MAINTENANCE_FILES: u32 = 500;
+
 /// Maximum number of results returned by [`Database::search`].
 /// Enforced here and referenced by CLI and MCP layers.
 pub const MAX_SEARCH_LIMIT: u32 = 100;
@@ -146,6 +418,11 @@ pub fn normalize_symbol_name(name: &str) -> String {
             continue;
         }
 
+        if !current.is_empty() && script_changes(&current, c) {
+            // Script boundary: `get用户` → split before 用, `ユーザー取得` → before 取
+            words.push(std::mem::take(&mut current));
+        }
+
         if c.is_uppercase() {
             let next_is_lower = i + 1 < len && chars[i + 1].is_lowercase();
             let prev_is_lower = !current.is_empty() && chars[i - 1].is_lowercase();
@@ -157,9 +434,9 @@ pub fn normalize_symbol_name(name: &str) -> String {
                 // SCREAMING to PascalCase boundary: `HTTPResponse` → split before R
                 words.push(std::mem::take(&mut current));
             }
-            current.push(c.to_lowercase().next().unwrap());
+            current.extend(c.to_lowercase());
         } else if c.is_alphanumeric() {
-            current.push(c.to_lowercase().next().unwrap());
+            current.extend(c.to_lowercase());
         } else {
             // Non-alphanumeric (other than _ and -): treat as separator
             if !current.is_empty() {
@@ -175,8 +452,258 @@ pub fn normalize_symbol_name(name: &str) -> String {
     words.join(" ")
 }
 
+/// Writing systems whose boundaries separate words in identifiers that mix them.
+#[derive(PartialEq)]
+enum Script {
+    /// Letters with case (Latin, Greek, Cyrillic, ...); camelCase rules apply within.
+    Cased,
+    Katakana,
+    /// Caseless letters: Han, Hiragana, Hangul, ... Han followed by Hiragana
+    /// (`読み込む`) is one word, so they share a class.
+    Caseless,
+}
+
+fn script_of(c: char) -> Option<Script> {
+    if c.is_numeric() {
+        None // digits join whichever word they follow
+    } else if matches!(c, '\u{30A0}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}')
+    {
+        Some(Script::Katakana)
+    } else if c.is_lowercase() || c.is_uppercase() {
+        Some(Script::Cased)
+    } else {
+        Some(Script::Caseless)
+    }
+}
+
+/// Whether `next` starts a different script than the last non-digit of `word`.
+fn script_changes(word: &str, next: char) -> bool {
+    let prev = word.chars().rev().find_map(script_of);
+    matches!((prev, script_of(next)), (Some(a), Some(b)) if a != b)
+}
+
+fn add_missing_columns(conn: &Connection) -> Result<()> {
+    for (table, column, ty) in ADDED_COLUMNS {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+            params![table, column],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {ty}"))
+                .with_context(|| format!("Failed to add column {table}.{column}"))?;
+            if let Some((_, _, backfill)) = COLUMN_BACKFILLS
+                .iter()
+                .find(|(t, c, _)| t == table && c == column)
+            {
+                conn.execute_batch(backfill)
+                    .with_context(|| format!("Failed to fill column {table}.{column}"))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Unicode lower-casing for SQL, registered on every connection as `casefold(X)`.
+/// SQLite's built-in `LOWER()` only folds ASCII, so non-ASCII identifiers would
+/// only match with their exact case.
+fn register_casefold(conn: &Connection) -> Result<()> {
+    use rusqlite::functions::FunctionFlags;
+    conn.create_scalar_function(
+        "casefold",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let text: Option<String> = ctx.get(0)?;
+            Ok(text.map(|t| {
+                if t.is_ascii() {
+                    t.to_ascii_lowercase()
+                } else {
+                    t.to_lowercase()
+                }
+            }))
+        },
+    )
+    .context("Failed to register casefold()")
+}
+
+/// Extensions tried, in order, when mapping a relative JS/TS import to a file.
+const JS_MODULE_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+
+/// Maximum number of `export * from` hops followed during edge resolution.
+const MAX_REEXPORT_DEPTH: usize = 8;
+
+/// `LIKE` pattern of the files under `file`'s directory (`app/views.py` → `app/%`),
+/// or empty for a file at the root.
+fn dir_pattern(file: &str) -> String {
+    file.rsplit_once('/')
+        .map(|(d, _)| format!("{d}/%"))
+        .unwrap_or_default()
+}
+
+/// `./x` and `../x` style module specifiers.
+fn is_relative_specifier(spec: &str) -> bool {
+    spec.starts_with("./") || spec.starts_with("../")
+}
+
+/// Join a relative module specifier onto the directory of `from_file`.
+///
+/// `("src/app.js", "../lib/util")` → `"lib/util"`. Returns `None` if the
+/// specifier escapes the project root.
+fn join_module_path(from_file: &str, specifier: &str) -> Option<String> {
+    let mut parts: Vec<&str> = from_file.split('/').collect();
+    parts.pop(); // drop the file name
+    for seg in specifier.split('/') {
+        match seg {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            s => parts.push(s),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Structural distance between two files: 0 same file, 1 same directory,
+/// 2 same top-level directory (package), 3 elsewhere, paired with the number
+/// of shared leading directories (more is closer).
+fn path_proximity(a: &str, b: &str) -> (u8, Reverse<usize>) {
+    if a == b {
+        return (0, Reverse(usize::MAX));
+    }
+    fn parent(path: &str) -> &str {
+        path.rsplit_once('/').map_or("", |(dir, _)| dir)
+    }
+    let (da, db) = (parent(a), parent(b));
+    let shared = da
+        .split('/')
+        .zip(db.split('/'))
+        .take_while(|(x, y)| x == y && !x.is_empty())
+        .count();
+    let level = if da == db {
+        1
+    } else if shared > 0 {
+        2
+    } else {
+        3
+    };
+    (level, Reverse(shared))
+}
+
+/// Order references by structural proximity to the referenced definition:
+/// same file, then same directory, then same top-level package, then elsewhere.
+///
+/// Resolved edges are measured against their target in `defs` (symbol id → file);
+/// unresolved ones against the nearest definition. Ties prefer the longer shared
+/// directory prefix, then path and line.
+pub(crate) fn sort_refs_by_proximity(
+    rows: &mut [(Edge, Option<Symbol>)],
+    defs: &std::collections::HashMap<String, String>,
+) {
+    rows.sort_by_cached_key(|(edge, _)| {
+        let target_file = edge.target_id.as_deref().and_then(|id| defs.get(id));
+        let proximity = match target_file {
+            Some(file) => path_proximity(&edge.file_path, file),
+            None => defs
+                .values()
+                .map(|file| path_proximity(&edge.file_path, file))
+                .min()
+                .unwrap_or((3, Reverse(0))),
+        };
+        let (file, line, kind, source, target) = edge_order(edge);
+        (
+            proximity,
+            file.to_string(),
+            line,
+            kind,
+            source.to_string(),
+            target.to_string(),
+        )
+    });
+}
+
+/// Sort key for edges that survives a re-index, unlike row ids: file, line, kind,
+/// source symbol, then target name.
+pub(crate) fn edge_order(edge: &Edge) -> (&str, u32, &'static str, &str, &str) {
+    (
+        &edge.file_path,
+        edge.line,
+        edge.kind.as_str(),
+        &edge.source_id,
+        &edge.target_name,
+    )
+}
+
+/// `major.minor.patch` of a cartog version; pre-release suffixes are ignored.
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|p| p.parse().ok());
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+/// Traversal behind [`Database::impact`]: follow `refs` from `name` up to `max_depth` hops,
+/// visiting each referencing symbol name once, at its shortest distance.
+/// Results are ordered by depth, then by [`edge_order`].
+pub(crate) fn transitive_impact(
+    name: &str,
+    max_depth: u32,
+    mut refs: impl FnMut(&str) -> Result<Vec<(Edge, Option<Symbol>)>>,
+) -> Result<Vec<(Edge, u32)>> {
+    let mut results = Vec::new();
+    let mut visited = std::collections::HashSet::new();
+    let mut frontier: std::collections::VecDeque<(String, u32)> =
+        std::collections::VecDeque::from([(name.to_string(), 0)]);
+
+    while let Some((current, depth)) = frontier.pop_front() {
+        if depth >= max_depth || visited.contains(&current) {
+            continue;
+        }
+        visited.insert(current.clone());
+
+        for (edge, sym) in refs(&current)? {
+            results.push((edge, depth + 1));
+            if let Some(s) = sym {
+                if !visited.contains(&s.name) {
+                    frontier.push_back((s.name, depth + 1));
+                }
+            }
+        }
+    }
+
+    results.sort_by(|a, b| impact_order(a).cmp(&impact_order(b)));
+    Ok(results)
+}
+
+/// Order of `impact` results: by depth, conditional edges after the others at
+/// each depth, then by [`edge_order`].
+fn impact_order((edge, depth): &(Edge, u32)) -> (u32, bool, (&str, u32, &'static str, &str, &str)) {
+    (*depth, edge.conditional, edge_order(edge))
+}
+
+/// Traversal behind [`Database::raises`]: symbols raising `exception` directly (depth 1),
+/// then their callers up to `max_depth` hops.
+pub(crate) fn transitive_raisers(
+    exception: &str,
+    max_depth: u32,
+    mut refs: impl FnMut(&str, Option<EdgeKind>) -> Result<Vec<(Edge, Option<Symbol>)>>,
+) -> Result<Vec<(Edge, u32)>> {
+    transitive_impact(exception, max_depth, |current| {
+        let kind = if current == exception {
+            EdgeKind::Raises
+        } else {
+            EdgeKind::Calls
+        };
+        refs(current, Some(kind))
+    })
+}
+
 pub struct Database {
     conn: Connection,
+    /// Why sqlite-vec failed to load on `conn`; vectors are unavailable when set.
+    vec_error: Option<String>,
+    /// `symbol_vec` exists (created on first use, see [`Database::require_vectors`]).
+    vec_table: std::cell::Cell<bool>,
 }
 
 impl std::fmt::Debug for Database {
@@ -185,56 +712,247 @@ impl std::fmt::Debug for Database {
     }
 }
 
-/// Register the sqlite-vec extension globally.
+/// Load the sqlite-vec extension into `conn`, or return why it failed.
 ///
-/// Must be called once before opening any database connections.
-/// Safe to call multiple times (idempotent via `std::sync::Once`).
-pub fn register_sqlite_vec() {
-    use std::sync::Once;
-    static INIT: Once = Once::new();
-    INIT.call_once(|| unsafe {
-        #[allow(clippy::missing_transmute_annotations)]
-        sqlite3_auto_extension(Some(std::mem::transmute(sqlite3_vec_init as *const ())));
+/// Loaded per connection rather than with `sqlite3_auto_extension`: an automatic
+/// extension that fails to initialize makes every connection open fail, taking
+/// the code graph down with vector search.
+fn load_sqlite_vec(conn: &Connection) -> std::result::Result<(), String> {
+    type Init = unsafe extern "C" fn(
+        *mut ffi::sqlite3,
+        *mut *mut std::os::raw::c_char,
+        *const ffi::sqlite3_api_routines,
+    ) -> std::os::raw::c_int;
+    let mut message = std::ptr::null_mut();
+    // SAFETY: `sqlite3_vec_init` is the extension entry point, declared without
+    // arguments by the sqlite-vec crate. Statically linked, it ignores the API
+    // routines pointer.
+    let rc = unsafe {
+        let init: Init = std::mem::transmute(sqlite3_vec_init as *const ());
+        init(conn.handle(), &mut message, std::ptr::null())
+    };
+    if rc == ffi::SQLITE_OK {
+        return Ok(());
+    }
+    if message.is_null() {
+        return Err(format!("sqlite-vec initialization failed with code {rc}"));
+    }
+    // SAFETY: on failure the entry point sets `message` to a string allocated by
+    // SQLite, which the caller frees.
+    unsafe {
+        let text = std::ffi::CStr::from_ptr(message)
+            .to_string_lossy()
+            .into_owned();
+        ffi::sqlite3_free(message.cast());
+        Err(text)
+    }
+}
+
+/// Whether vectors (`rag index` embeddings and their KNN search) work in this
+/// process, as probed on an in-memory database (see [`vector_support`]).
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct VectorSupport {
+    pub available: bool,
+    /// Why the sqlite-vec extension failed to load.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// How to get vectors back, when unavailable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<&'static str>,
+}
+
+/// [`VectorSupport::fix`].
+const VECTOR_FIX: &str = "cartog links sqlite-vec statically against its bundled SQLite. \
+    A load failure means another SQLite took its place: reinstall cartog from a release \
+    binary or with `cargo install cartog` (default features), and check that no \
+    LD_PRELOAD or LD_LIBRARY_PATH entry substitutes a system libsqlite3. The code graph \
+    and keyword search work meanwhile.";
+
+/// Probe sqlite-vec once per process. Without it the code graph and keyword
+/// search work as usual, and `rag search` falls back to keyword-only.
+pub fn vector_support() -> VectorSupport {
+    static SUPPORT: std::sync::OnceLock<Option<String>> = std::sync::OnceLock::new();
+    let error = SUPPORT.get_or_init(|| {
+        let probe = Connection::open_in_memory()
+            .map_err(|e| e.to_string())
+            .and_then(|conn| {
+                load_sqlite_vec(&conn)?;
+                conn.execute_batch(RAG_VEC_SCHEMA)
+                    .map_err(|e| e.to_string())
+            });
+        probe.err()
     });
+    VectorSupport {
+        available: error.is_none(),
+        error: error.clone(),
+        fix: error.is_some().then_some(VECTOR_FIX),
+    }
+}
+
+/// Whether another connection holds the write lock on the database at `path`,
+/// e.g. an index run in progress. Checked without waiting, from a separate connection.
+pub fn is_write_locked(path: impl AsRef<std::path::Path>) -> Result<bool> {
+    let conn = Connection::open_with_flags(
+        path.as_ref(),
+        rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .context("Failed to open database")?;
+    conn.busy_timeout(std::time::Duration::ZERO)?;
+    match conn.execute_batch("BEGIN IMMEDIATE; ROLLBACK;") {
+        Ok(()) => Ok(false),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if matches!(
+                e.code,
+                rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+            ) =>
+        {
+            Ok(true)
+        }
+        Err(e) => Err(e.into()),
+    }
 }
 
 impl Database {
     /// Open or create the database at the given path.
     pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
-        register_sqlite_vec();
         let conn = Connection::open(path.as_ref()).context("Failed to open database")?;
-        conn.execute_batch(
-            "PRAGMA journal_mode=WAL;
+        // Existing databases keep their auto_vacuum mode until `cartog maintain --vacuum`.
+        let db_bytes = std::fs::metadata(path.as_ref()).map_or(0, |m| m.len());
+        let tuning = PragmaTuning::for_size(db_bytes, available_memory());
+        conn.execute_batch(&format!(
+            "PRAGMA auto_vacuum=INCREMENTAL;
+             PRAGMA journal_mode=WAL;
              PRAGMA foreign_keys=ON;
              PRAGMA synchronous=NORMAL;
-             PRAGMA cache_size=-65536;
+             PRAGMA cache_size=-{};
              PRAGMA temp_store=MEMORY;
-             PRAGMA mmap_size=268435456;",
-        )
+             PRAGMA mmap_size={};",
+            tuning.cache_kib, tuning.mmap_bytes
+        ))
         .context("Failed to set pragmas")?;
+        register_casefold(&conn)?;
         conn.execute_batch(SCHEMA)
             .context("Failed to create schema")?;
+        add_missing_columns(&conn)?;
         conn.execute_batch(RAG_SCHEMA)
             .context("Failed to create RAG schema")?;
-        conn.execute_batch(RAG_VEC_SCHEMA)
-            .context("Failed to create sqlite-vec table")?;
-        Ok(Self { conn })
+        let db = Self::with_vectors(conn);
+        db.claim_writer_version()
+            .with_context(|| format!("Cannot use {}", path.as_ref().display()))?;
+        Ok(db)
     }
 
     /// Open an in-memory database (for tests and benchmarks).
     #[doc(hidden)]
     pub fn open_memory() -> Result<Self> {
-        register_sqlite_vec();
         let conn = Connection::open_in_memory()?;
         conn.execute_batch("PRAGMA foreign_keys=ON;")?;
+        register_casefold(&conn)?;
         conn.execute_batch(SCHEMA)?;
         conn.execute_batch(RAG_SCHEMA)?;
-        conn.execute_batch(RAG_VEC_SCHEMA)?;
-        Ok(Self { conn })
+        let db = Self::with_vectors(conn);
+        db.claim_writer_version()?;
+        Ok(db)
+    }
+
+    /// Wrap `conn`, loading sqlite-vec into it. A failure is warned about once per
+    /// process and leaves the database usable without vectors.
+    fn with_vectors(conn: Connection) -> Self {
+        let vec_error = load_sqlite_vec(&conn).err();
+        if let Some(error) = &vec_error {
+            static WARNED: std::sync::Once = std::sync::Once::new();
+            WARNED.call_once(|| {
+                warn!(
+                    %error,
+                    "sqlite-vec failed to load: semantic search falls back to keyword-only \
+                     (see `cartog health`)"
+                );
+            });
+        }
+        Self {
+            conn,
+            vec_error,
+            vec_table: std::cell::Cell::new(false),
+        }
+    }
+
+    /// Whether vectors can be stored and searched on this database.
+    pub fn has_vectors(&self) -> bool {
+        self.vec_error.is_none()
+    }
+
+    /// Fail with the reason when sqlite-vec is unavailable; otherwise create
+    /// `symbol_vec` on first use.
+    fn require_vectors(&self) -> Result<()> {
+        if let Some(error) = &self.vec_error {
+            anyhow::bail!(
+                "vector storage is unavailable: sqlite-vec failed to load ({error}). \
+                 Keyword search still works; run `cartog health` for how to fix it."
+            );
+        }
+        if !self.vec_table.get() {
+            self.conn
+                .execute_batch(RAG_VEC_SCHEMA)
+                .context("Failed to create sqlite-vec table")?;
+            self.vec_table.set(true);
+        }
+        Ok(())
+    }
+
+    /// Refuse a database whose schema is newer than this binary's, warn when it was
+    /// last written by a newer cartog, and otherwise record this binary as its writer.
+    ///
+    /// Databases from before versions were recorded count as schema 1.
+    fn claim_writer_version(&self) -> Result<()> {
+        let ours = env!("CARGO_PKG_VERSION");
+        let stored_schema = self.get_metadata(SCHEMA_VERSION_KEY)?;
+        let schema: u32 = stored_schema
+            .as_deref()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1);
+        let writer = self.get_metadata(WRITER_VERSION_KEY)?;
+        let writer_name = writer.as_deref().unwrap_or("unknown");
+
+        if schema > SCHEMA_VERSION {
+            anyhow::bail!(
+                "the index was written by cartog {writer_name} (schema {schema}), \
+                 which is newer than this cartog {ours} (schema {SCHEMA_VERSION}). \
+                 Upgrade cartog, or delete the database and re-index."
+            );
+        }
+        let writer_is_newer = writer
+            .as_deref()
+            .and_then(parse_version)
+            .zip(parse_version(ours))
+            .is_some_and(|(theirs, ours)| theirs > ours);
+        if writer_is_newer {
+            warn!(
+                writer = writer_name,
+                version = ours,
+                "index was last written by a newer cartog; results may miss what it extracts"
+            );
+            return Ok(());
+        }
+
+        if stored_schema != Some(SCHEMA_VERSION.to_string()) {
+            self.set_metadata(SCHEMA_VERSION_KEY, &SCHEMA_VERSION.to_string())?;
+        }
+        if writer.as_deref() != Some(ours) {
+            self.set_metadata(WRITER_VERSION_KEY, ours)?;
+        }
+        Ok(())
     }
 
     // ── Metadata ──
 
+    /// Schema version recorded in the database (1 for databases from before it was recorded).
+    pub fn schema_version(&self) -> Result<u32> {
+        Ok(self
+            .get_metadata(SCHEMA_VERSION_KEY)?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1))
+    }
+
     /// Retrieve a metadata value by key.
     pub fn get_metadata(&self, key: &str) -> Result<Option<String>> {
         self.conn
@@ -256,21 +974,46 @@ impl Database {
         Ok(())
     }
 
+    /// Number of index runs that changed the graph (0 for a fresh database).
+    pub fn index_generation(&self) -> Result<u64> {
+        Ok(self
+            .get_metadata("index_generation")?
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0))
+    }
+
+    /// Increment the index generation and return the new value.
+    pub fn bump_index_generation(&self) -> Result<u64> {
+        let next = self.index_generation()? + 1;
+        self.set_metadata("index_generation", &next.to_string())?;
+        Ok(next)
+    }
+
     // ── Files ──
 
-    /// Insert or update file metadata.
+    /// Insert or update file metadata. Clears a removal recorded for the path.
     pub fn upsert_file(&self, file: &FileInfo) -> Result<()> {
         self.conn.execute(
-            "INSERT OR REPLACE INTO files (path, last_modified, hash, language, num_symbols)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
+            "INSERT OR REPLACE INTO files (path, last_modified, hash, language, num_symbols, generation,
+                                            code_lines, comment_lines, blank_lines, truncated)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
             params![
                 file.path,
                 file.last_modified,
                 file.hash,
                 file.language,
                 file.num_symbols,
+                file.generation,
+                file.lines.code,
+                file.lines.comment,
+                file.lines.blank,
+                file.truncated,
             ],
         )?;
+        self.conn.execute(
+            "DELETE FROM removed_files WHERE path = ?1",
+            params![file.path],
+        )?;
         Ok(())
     }
 
@@ -278,25 +1021,73 @@ impl Database {
     pub fn get_file(&self, path: &str) -> Result<Option<FileInfo>> {
         self.conn
             .query_row(
-                "SELECT path, last_modified, hash, language, num_symbols FROM files WHERE path = ?1",
+                "SELECT path, last_modified, hash, language, num_symbols, generation,
+                        code_lines, comment_lines, blank_lines, truncated
+                 FROM files WHERE path = ?1",
                 params![path],
-                |row| {
-                    Ok(FileInfo {
-                        path: row.get(0)?,
-                        last_modified: row.get(1)?,
-                        hash: row.get(2)?,
-                        language: row.get(3)?,
-                        num_symbols: row.get(4)?,
-                    })
-                },
+                row_to_file,
             )
             .optional()
             .context("Failed to query file")
     }
 
+    /// Files (re)indexed by a run after generation `since`, ordered by path. All
+    /// files for 0, including those indexed before files recorded their generation.
+    pub fn files_changed_since(&self, since: u64) -> Result<Vec<FileInfo>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, last_modified, hash, language, num_symbols, generation,
+                    code_lines, comment_lines, blank_lines, truncated
+             FROM files WHERE ?1 = 0 OR generation > ?1 ORDER BY path",
+        )?;
+        let rows = stmt
+            .query_map(params![since], row_to_file)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Paths removed from the index by a run after generation `since` and not
+    /// indexed again since, ordered by path.
+    pub fn files_removed_since(&self, since: u64) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path FROM removed_files WHERE generation > ?1 ORDER BY path")?;
+        let rows = stmt
+            .query_map(params![since], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Record that `path` left the index in `generation`, for [`Self::files_removed_since`].
+    pub fn record_removed_file(&self, path: &str, generation: u64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO removed_files (path, generation) VALUES (?1, ?2)",
+            params![path, generation],
+        )?;
+        Ok(())
+    }
+
     /// Remove all symbols, edges, and RAG data for a file (before re-indexing it).
     pub fn clear_file_data(&self, path: &str) -> Result<()> {
-        self.clear_rag_data_for_file(path)?;
+        self.clear_file_data_keeping(path, &std::collections::HashSet::new())
+    }
+
+    /// [`Self::clear_file_data`], but keep the embeddings of the symbols in `keep`:
+    /// IDs hash a symbol's code, so a re-indexed symbol with the same ID has the same
+    /// code and its embedding still applies.
+    pub fn clear_file_data_keeping(
+        &self,
+        path: &str,
+        keep: &std::collections::HashSet<&str>,
+    ) -> Result<()> {
+        self.clear_rag_data_keeping(path, keep)?;
+        self.conn
+            .execute("DELETE FROM anchors WHERE file_path = ?1", params![path])?;
+        self.conn
+            .execute("DELETE FROM injections WHERE file_path = ?1", params![path])?;
+        self.conn.execute(
+            "DELETE FROM import_aliases WHERE file_path = ?1",
+            params![path],
+        )?;
         self.conn
             .execute("DELETE FROM edges WHERE file_path = ?1", params![path])?;
         self.conn
@@ -304,12 +1095,30 @@ impl Database {
         Ok(())
     }
 
-    /// Remove a file and all its symbols and edges from the index.
+    /// Rows stored for a file: its `files` entry, symbols, symbol contents, edges,
+    /// anchors, injections and import aliases.
+    pub fn file_row_count(&self, path: &str) -> Result<u64> {
+        Ok(self.conn.query_row(
+            "SELECT 1
+                + (SELECT COUNT(*) FROM symbols WHERE file_path = ?1)
+                + (SELECT COUNT(*) FROM symbol_content c JOIN symbols s ON s.id = c.symbol_id
+                   WHERE s.file_path = ?1)
+                + (SELECT COUNT(*) FROM edges WHERE file_path = ?1)
+                + (SELECT COUNT(*) FROM anchors WHERE file_path = ?1)
+                + (SELECT COUNT(*) FROM injections WHERE file_path = ?1)
+                + (SELECT COUNT(*) FROM import_aliases WHERE file_path = ?1)",
+            params![path],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Remove a file and all its symbols and edges from the index, recording the
+    /// removal under the generation the running index moves to.
     pub fn remove_file(&self, path: &str) -> Result<()> {
         self.clear_file_data(path)?;
         self.conn
             .execute("DELETE FROM files WHERE path = ?1", params![path])?;
-        Ok(())
+        self.record_removed_file(path, self.index_generation()? + 1)
     }
 
     // ── Symbols ──
@@ -333,33 +1142,59 @@ impl Database {
                 sym.visibility.as_str(),
                 sym.is_async,
                 sym.docstring,
+                sym.entrypoint,
+                sym.db_table,
+                sym.generation,
             ])?;
         Ok(())
     }
 
+    /// Run `f` in a transaction: its writes commit together or not at all.
+    ///
+    /// Uses a savepoint, so it nests: the batch inserts below run in their own
+    /// transaction when called alone, and join the caller's inside `in_transaction`.
+    pub fn in_transaction<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.conn.execute_batch("SAVEPOINT cartog_write")?;
+        match f() {
+            Ok(value) => {
+                self.conn.execute_batch("RELEASE cartog_write")?;
+                Ok(value)
+            }
+            Err(e) => {
+                let _ = self
+                    .conn
+                    .execute_batch("ROLLBACK TO cartog_write; RELEASE cartog_write");
+                Err(e)
+            }
+        }
+    }
+
     /// Insert or replace multiple symbols in a single transaction.
     pub fn insert_symbols(&self, symbols: &[Symbol]) -> Result<()> {
-        let tx = self.conn.unchecked_transaction()?;
-        let mut stmt = self.conn.prepare_cached(SQL_INSERT_SYMBOL)?;
-        for sym in symbols {
-            stmt.execute(params![
-                sym.id,
-                sym.name,
-                sym.kind.as_str(),
-                sym.file_path,
-                sym.start_line,
-                sym.end_line,
-                sym.start_byte,
-                sym.end_byte,
-                sym.parent_id,
-                sym.signature,
-                sym.visibility.as_str(),
-                sym.is_async,
-                sym.docstring,
-            ])?;
-        }
-        tx.commit()?;
-        Ok(())
+        self.in_transaction(|| {
+            let mut stmt = self.conn.prepare_cached(SQL_INSERT_SYMBOL)?;
+            for sym in symbols {
+                stmt.execute(params![
+                    sym.id,
+                    sym.name,
+                    sym.kind.as_str(),
+                    sym.file_path,
+                    sym.start_line,
+                    sym.end_line,
+                    sym.start_byte,
+                    sym.end_byte,
+                    sym.parent_id,
+                    sym.signature,
+                    sym.visibility.as_str(),
+                    sym.is_async,
+                    sym.docstring,
+                    sym.entrypoint,
+                    sym.db_table,
+                    sym.generation,
+                ])?;
+            }
+            Ok(())
+        })
     }
 
     // ── Edges ──
@@ -374,103 +1209,575 @@ impl Database {
             edge.kind.as_str(),
             edge.file_path,
             edge.line,
+            edge.target_kind.map(|k| k.as_str()),
+            edge.conditional,
         ])?;
         Ok(())
     }
 
     /// Insert multiple edges in a single transaction.
     pub fn insert_edges(&self, edges: &[Edge]) -> Result<()> {
-        let tx = self.conn.unchecked_transaction()?;
-        let mut stmt = self.conn.prepare_cached(SQL_INSERT_EDGE)?;
-        for edge in edges {
-            stmt.execute(params![
-                edge.source_id,
-                edge.target_name,
-                edge.target_id,
-                edge.kind.as_str(),
-                edge.file_path,
-                edge.line,
-            ])?;
-        }
-        tx.commit()?;
-        Ok(())
+        self.in_transaction(|| {
+            let mut stmt = self.conn.prepare_cached(SQL_INSERT_EDGE)?;
+            for edge in edges {
+                stmt.execute(params![
+                    edge.source_id,
+                    edge.target_name,
+                    edge.target_id,
+                    edge.kind.as_str(),
+                    edge.file_path,
+                    edge.line,
+                    edge.target_kind.map(|k| k.as_str()),
+                    edge.conditional,
+                ])?;
+            }
+            Ok(())
+        })
     }
 
     // ── Edge Resolution ──
 
     /// Resolve target_name → target_id for all unresolved edges.
-    /// Priority: exact match in same file > same directory > unique project-wide match.
+    /// Priority: exact match in same file > pinned definition > same directory >
+    /// unique project-wide match.
+    ///
+    /// Relative module imports are followed edge by edge; every other step is one
+    /// set-based pass over the edges still unresolved, queued in a temporary table.
+    /// The passes run the candidate lookups of [`Self::explain_resolution`] as
+    /// correlated subqueries. Per-pass counts and timings are kept for `stats`.
     pub fn resolve_edges(&self) -> Result<u32> {
-        let mut resolved = 0u32;
-
+        // For imports edges, the source symbol's name is the module specifier.
         let mut unresolved_stmt = self.conn.prepare(
-            "SELECT e.id, e.target_name, e.file_path
-             FROM edges e WHERE e.target_id IS NULL",
+            "SELECT e.id, e.target_name, e.file_path,
+                    CASE WHEN e.kind = 'imports' THEN s.name END
+             FROM edges e LEFT JOIN symbols s ON s.id = e.source_id
+             WHERE e.target_id IS NULL",
         )?;
 
-        let unresolved: Vec<(i64, String, String)> = unresolved_stmt
-            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        type UnresolvedRow = (i64, String, String, Option<String>);
+        let unresolved: Vec<UnresolvedRow> = unresolved_stmt
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
         let tx = self.conn.unchecked_transaction()?;
-
-        let mut same_file_stmt = self
-            .conn
-            .prepare("SELECT id FROM symbols WHERE name = ?1 AND file_path = ?2 LIMIT 1")?;
-        let mut same_dir_stmt = self
-            .conn
-            .prepare("SELECT id FROM symbols WHERE name = ?1 AND file_path LIKE ?2 LIMIT 1")?;
-        let mut anywhere_stmt = self
-            .conn
-            .prepare("SELECT id FROM symbols WHERE name = ?1 LIMIT 2")?;
-        let mut update_stmt = self
-            .conn
-            .prepare("UPDATE edges SET target_id = ?1 WHERE id = ?2")?;
-
-        for (edge_id, target_name, edge_file) in &unresolved {
-            let simple_name = target_name.rsplit('.').next().unwrap_or(target_name);
-
-            // 1) Same file
-            let target_id: Option<String> = same_file_stmt
-                .query_row(params![simple_name, edge_file], |row| row.get(0))
-                .optional()?;
-
-            if let Some(tid) = target_id {
-                update_stmt.execute(params![tid, edge_id])?;
-                resolved += 1;
-                continue;
-            }
-
-            // 2) Same directory
-            let dir = edge_file
-                .rsplit_once('/')
-                .map(|(d, _)| format!("{d}/%"))
-                .unwrap_or_default();
-
-            if !dir.is_empty() {
-                let target_id: Option<String> = same_dir_stmt
-                    .query_row(params![simple_name, dir], |row| row.get(0))
-                    .optional()?;
-
-                if let Some(tid) = target_id {
-                    update_stmt.execute(params![tid, edge_id])?;
-                    resolved += 1;
-                    continue;
+        self.conn.execute_batch(SQL_RESOLVE_QUEUE)?;
+
+        // 0) Relative JS/TS module import — follow the module file and its `export *` chain
+        let started = Instant::now();
+        let mut module_resolved = 0u32;
+        {
+            let mut enqueue = self.conn.prepare(
+                "INSERT INTO temp.resolve_queue (edge_id, name, file_path, dir, target_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for (edge_id, target_name, edge_file, module) in &unresolved {
+                let simple_name = target_name.rsplit('.').next().unwrap_or(target_name);
+                let mut target_id = None;
+                if let Some(spec) = module.as_deref().filter(|m| is_relative_specifier(m)) {
+                    target_id = self.resolve_module_export(edge_file, spec, simple_name, 0)?;
+                    module_resolved += u32::from(target_id.is_some());
                 }
+                enqueue.execute(params![
+                    edge_id,
+                    simple_name,
+                    edge_file,
+                    dir_pattern(edge_file),
+                    target_id
+                ])?;
             }
+        }
+        let mut passes = vec![ResolutionPass::new(
+            ResolutionStage::Module,
+            module_resolved,
+            started,
+        )];
+
+        // 1) Same file, 2) pinned canonical definition (`cartog pin`), 3) same directory
+        for (stage, sql) in [
+            (ResolutionStage::SameFile, SQL_PASS_SAME_FILE),
+            (ResolutionStage::Pinned, SQL_PASS_PINNED),
+            (ResolutionStage::SameDir, SQL_PASS_SAME_DIR),
+        ] {
+            let started = Instant::now();
+            self.conn.execute(sql, [])?;
+            let resolved = self.queued_resolved()? - self.resolved_so_far(&passes);
+            passes.push(ResolutionPass::new(stage, resolved, started));
+        }
 
-            // 3) Unique project-wide match — fetch at most 2 rows; resolve only if exactly 1
-            let mut rows = anywhere_stmt.query(params![simple_name])?;
-            let first = rows.next()?.and_then(|r| r.get::<_, String>(0).ok());
-            let has_second = rows.next()?.is_some();
-            if let (Some(tid), false) = (first, has_second) {
-                update_stmt.execute(params![tid, edge_id])?;
-                resolved += 1;
+        // 4) Unique project-wide match — names looked up in batches, one definition only
+        let started = Instant::now();
+        let names: Vec<String> = self
+            .conn
+            .prepare("SELECT DISTINCT name FROM temp.resolve_queue WHERE target_id IS NULL")?
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut assign = self.conn.prepare(
+            "UPDATE temp.resolve_queue SET target_id = ?1 WHERE name = ?2 AND target_id IS NULL",
+        )?;
+        for batch in names.chunks(RESOLVE_NAME_BATCH) {
+            let placeholders = vec!["?"; batch.len()].join(", ");
+            let sql = format!(
+                "SELECT name, MIN(id) FROM symbols WHERE name IN ({placeholders})
+                 GROUP BY name HAVING COUNT(*) = 1"
+            );
+            let unique: Vec<(String, String)> = self
+                .conn
+                .prepare(&sql)?
+                .query_map(rusqlite::params_from_iter(batch), |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            for (name, id) in unique {
+                assign.execute(params![id, name])?;
             }
         }
+        let resolved = self.queued_resolved()? - self.resolved_so_far(&passes);
+        passes.push(ResolutionPass::new(
+            ResolutionStage::Unique,
+            resolved,
+            started,
+        ));
 
+        self.conn.execute(
+            "UPDATE edges SET target_id = q.target_id,
+                    target_kind = (SELECT kind FROM symbols WHERE id = q.target_id)
+             FROM temp.resolve_queue q
+             WHERE edges.id = q.edge_id AND q.target_id IS NOT NULL",
+            [],
+        )?;
+        self.conn.execute("DELETE FROM temp.resolve_queue", [])?;
+        self.set_metadata(LAST_RESOLUTION_KEY, &serde_json::to_string(&passes)?)?;
         tx.commit()?;
-        Ok(resolved)
+
+        Ok(self.resolved_so_far(&passes))
+    }
+
+    /// Queued edges that have a target so far in [`Self::resolve_edges`].
+    fn queued_resolved(&self) -> Result<u32> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) FROM temp.resolve_queue WHERE target_id IS NOT NULL",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn resolved_so_far(&self, passes: &[ResolutionPass]) -> u32 {
+        passes.iter().map(|p| p.resolved).sum()
+    }
+
+    /// Passes of the last [`Self::resolve_edges`] run, in order; empty before the first.
+    pub fn last_resolution(&self) -> Result<Vec<ResolutionPass>> {
+        Ok(self
+            .get_metadata(LAST_RESOLUTION_KEY)?
+            .and_then(|stored| serde_json::from_str(&stored).ok())
+            .unwrap_or_default())
+    }
+
+    /// Find `name` exported by the module `specifier` imported from `from_file`.
+    ///
+    /// Looks for a definition in the module file first, then follows `export * from`
+    /// re-exports up to `MAX_REEXPORT_DEPTH` hops (which also bounds cycles).
+    fn resolve_module_export(
+        &self,
+        from_file: &str,
+        specifier: &str,
+        name: &str,
+        depth: usize,
+    ) -> Result<Option<String>> {
+        if depth > MAX_REEXPORT_DEPTH {
+            return Ok(None);
+        }
+        let Some(module_file) = self.find_module_file(from_file, specifier)? else {
+            return Ok(None);
+        };
+
+        let found: Option<String> = self
+            .conn
+            .prepare_cached(
+                "SELECT id FROM symbols
+                 WHERE name = ?1 AND file_path = ?2 AND kind != 'import' LIMIT 1",
+            )?
+            .query_row(params![name, module_file], |row| row.get(0))
+            .optional()?;
+        if found.is_some() {
+            return Ok(found);
+        }
+
+        let star_reexports: Vec<String> = self
+            .conn
+            .prepare_cached(
+                "SELECT name FROM symbols
+                 WHERE file_path = ?1 AND kind = 'import'
+                   AND signature LIKE 'export *%' AND signature NOT LIKE 'export * as %'",
+            )?
+            .query_map(params![module_file], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        for next in star_reexports {
+            if !is_relative_specifier(&next) {
+                continue;
+            }
+            if let Some(tid) = self.resolve_module_export(&module_file, &next, name, depth + 1)? {
+                return Ok(Some(tid));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Map a relative module specifier to an indexed file, trying the usual
+    /// JS/TS extensions and `index` files.
+    fn find_module_file(&self, from_file: &str, specifier: &str) -> Result<Option<String>> {
+        let Some(base) = join_module_path(from_file, specifier) else {
+            return Ok(None);
+        };
+        let mut candidates = vec![base.clone()];
+        candidates.extend(
+            JS_MODULE_EXTENSIONS
+                .iter()
+                .map(|ext| format!("{base}.{ext}")),
+        );
+        candidates.extend(
+            JS_MODULE_EXTENSIONS
+                .iter()
+                .map(|ext| format!("{base}/index.{ext}")),
+        );
+
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT 1 FROM files WHERE path = ?1")?;
+        for candidate in candidates {
+            if stmt.exists(params![candidate])? {
+                return Ok(Some(candidate));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Replay edge resolution for edges targeting `target`, step by step.
+    ///
+    /// `target` is an edge ID (as reported in a previous explanation) or a target
+    /// name, matched plain or as the last segment of a dotted name. Edges sharing
+    /// a file, target name and module resolve alike, so only the first of each is
+    /// explained. Each step lists the definitions it considered and why each was
+    /// accepted or rejected; steps after the accepting one are not run.
+    pub fn explain_resolution(
+        &self,
+        target: &str,
+        file: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<ResolutionExplanation>> {
+        let escaped = target
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let edge_id: Option<i64> = target.parse().ok();
+        let mut stmt = self.conn.prepare(
+            "SELECT e.id, e.source_id, e.target_name, e.target_id, e.kind, e.file_path, e.line,
+                    e.target_kind, e.conditional,
+                    CASE WHEN e.kind = 'imports' THEN s.name END
+             FROM edges e LEFT JOIN symbols s ON s.id = e.source_id
+             WHERE (e.id = ?1
+                    OR (?1 IS NULL
+                        AND (e.target_name = ?2 OR e.target_name LIKE '%.' || ?3 ESCAPE '\\')))
+               AND (?4 IS NULL OR e.file_path = ?4)
+             ORDER BY e.file_path, e.line, e.id",
+        )?;
+        let edges = stmt
+            .query_map(params![edge_id, target, escaped, file], |row| {
+                Ok((row.get::<_, i64>(0)?, row_to_edge(row)?, row.get(9)?))
+            })?
+            .collect::<std::result::Result<Vec<(i64, Edge, Option<String>)>, _>>()?;
+
+        let mut seen = std::collections::HashSet::new();
+        let mut explanations = Vec::new();
+        for (edge_id, edge, module) in edges {
+            if explanations.len() >= limit as usize {
+                break;
+            }
+            let key = (
+                edge.file_path.clone(),
+                edge.target_name.clone(),
+                module.clone(),
+            );
+            if seen.insert(key) {
+                explanations.push(self.explain_edge(edge_id, edge, module)?);
+            }
+        }
+        Ok(explanations)
+    }
+
+    /// Walk the steps of [`resolve_edges`](Self::resolve_edges) for one edge.
+    fn explain_edge(
+        &self,
+        edge_id: i64,
+        edge: Edge,
+        module: Option<String>,
+    ) -> Result<ResolutionExplanation> {
+        let name = edge
+            .target_name
+            .rsplit('.')
+            .next()
+            .unwrap_or(&edge.target_name)
+            .to_string();
+        let mut explanation = ResolutionExplanation {
+            edge_id,
+            edge,
+            module,
+            lookup_name: name.clone(),
+            steps: Vec::new(),
+            resolved_to: None,
+        };
+        let file = explanation.edge.file_path.clone();
+        let first = |sql: &str, p: &[&dyn rusqlite::ToSql]| -> Result<Option<String>> {
+            Ok(self
+                .conn
+                .prepare_cached(sql)?
+                .query_row(p, |row| row.get(0))
+                .optional()?)
+        };
+
+        // 0) Relative JS/TS module import
+        if let Some(spec) = explanation.module.clone() {
+            let step = if !is_relative_specifier(&spec) {
+                ResolutionStep::new(
+                    ResolutionStage::Module,
+                    format!("'{spec}' is not a relative module, so it is looked up by name"),
+                )
+            } else {
+                match self.find_module_file(&file, &spec)? {
+                    None => ResolutionStep::new(
+                        ResolutionStage::Module,
+                        format!("'{spec}' does not match an indexed file"),
+                    ),
+                    Some(module_file) => {
+                        match self.resolve_module_export(&file, &spec, &name, 0)? {
+                            Some(id) => {
+                                let sym = self.get_symbol(&id)?.context("resolved symbol vanished")?;
+                                let reason = if sym.file_path == module_file {
+                                    format!("defined in {module_file}")
+                                } else {
+                                    format!("re-exported by {module_file} through `export *`")
+                                };
+                                let mut step = ResolutionStep::new(
+                                    ResolutionStage::Module,
+                                    format!("'{spec}' is {module_file}"),
+                                );
+                                step.candidates.push(ResolutionCandidate::new(sym, true, reason));
+                                step
+                            }
+                            None => ResolutionStep::new(
+                                ResolutionStage::Module,
+                                format!(
+                                    "'{name}' is neither defined in {module_file} nor re-exported from it"
+                                ),
+                            ),
+                        }
+                    }
+                }
+            };
+            if explanation.push(step) {
+                return Ok(explanation);
+            }
+        }
+
+        // 1) Same file
+        let chosen = first(SQL_RESOLVE_SAME_FILE, &[&name, &file])?;
+        let candidates = self.resolution_candidates(&name, Some(("file_path = ?2", &file)))?;
+        let step = ResolutionStep::pick(
+            ResolutionStage::SameFile,
+            candidates,
+            chosen.as_deref(),
+            format!("no definition of '{name}' in {file}"),
+            "first match in the same file, imports included",
+            "another match in the same file; the first in index order wins",
+        );
+        if explanation.push(step) {
+            return Ok(explanation);
+        }
+
+        // 2) Pinned canonical definition
+        let pinned_file: Option<String> =
+            first("SELECT file_path FROM pins WHERE name = ?1", &[&name])?;
+        let step = match pinned_file {
+            None => ResolutionStep::new(ResolutionStage::Pinned, format!("'{name}' is not pinned")),
+            Some(pinned_file) => {
+                let chosen = first(SQL_RESOLVE_PINNED, &[&name])?;
+                let candidates = self.resolution_candidates(
+                    &name,
+                    Some(("file_path = ?2 AND kind != 'import'", &pinned_file)),
+                )?;
+                ResolutionStep::pick(
+                    ResolutionStage::Pinned,
+                    candidates,
+                    chosen.as_deref(),
+                    format!("pinned to {pinned_file}, which no longer defines '{name}'"),
+                    "pinned with `cartog pin`",
+                    "later definition in the pinned file; the first one wins",
+                )
+            }
+        };
+        if explanation.push(step) {
+            return Ok(explanation);
+        }
+
+        // 3) Same directory (and its subdirectories)
+        let dir = dir_pattern(&file);
+        let step = if dir.is_empty() {
+            ResolutionStep::new(
+                ResolutionStage::SameDir,
+                format!("{file} is at the root, so this step is skipped"),
+            )
+        } else {
+            let chosen = first(SQL_RESOLVE_SAME_DIR, &[&name, &dir])?;
+            let candidates =
+                self.resolution_candidates(&name, Some(("file_path LIKE ?2", &dir)))?;
+            ResolutionStep::pick(
+                ResolutionStage::SameDir,
+                candidates,
+                chosen.as_deref(),
+                format!(
+                    "no definition of '{name}' under {}",
+                    dir.trim_end_matches('%')
+                ),
+                "first match in the same directory tree",
+                "another match in the same directory tree; the first in index order wins",
+            )
+        };
+        if explanation.push(step) {
+            return Ok(explanation);
+        }
+
+        // 4) Unique project-wide match
+        let count: u32 = self.conn.query_row(
+            "SELECT COUNT(*) FROM symbols WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )?;
+        let mut candidates = self.resolution_candidates(&name, None)?;
+        let step = match count {
+            0 => ResolutionStep::new(
+                ResolutionStage::Unique,
+                format!("'{name}' is not defined anywhere in the index (external or dynamic)"),
+            ),
+            1 => ResolutionStep::pick(
+                ResolutionStage::Unique,
+                candidates,
+                first(SQL_RESOLVE_ANYWHERE, &[&name])?.as_deref(),
+                String::new(),
+                "the only definition in the project",
+                "",
+            ),
+            n => {
+                for c in &mut candidates {
+                    c.reason = format!("one of {n} definitions; ambiguous names stay unresolved");
+                }
+                let mut step = ResolutionStep::new(
+                    ResolutionStage::Unique,
+                    format!("{n} definitions of '{name}'; pin one with `cartog pin` to pick it"),
+                );
+                step.candidates = candidates;
+                step
+            }
+        };
+        explanation.push(step);
+        Ok(explanation)
+    }
+
+    /// Definitions of `name`, optionally restricted by a condition with `?2` bound
+    /// to its argument, in file and line order.
+    fn resolution_candidates(
+        &self,
+        name: &str,
+        condition: Option<(&str, &str)>,
+    ) -> Result<Vec<ResolutionCandidate>> {
+        let sql = format!(
+            "SELECT id, name, kind, file_path, start_line, end_line, start_byte, end_byte,
+                    parent_id, signature, visibility, is_async, docstring, entrypoint,
+                    db_table, generation
+             FROM symbols WHERE name = ?1 {}
+             ORDER BY file_path, start_line LIMIT {MAX_EXPLAINED_CANDIDATES}",
+            condition.map_or(String::new(), |(c, _)| format!("AND {c}"))
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = match condition {
+            Some((_, arg)) => stmt.query_map(params![name, arg], row_to_symbol)?,
+            None => stmt.query_map(params![name], row_to_symbol)?,
+        }
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows
+            .into_iter()
+            .map(|sym| ResolutionCandidate::new(sym, false, String::new()))
+            .collect())
+    }
+
+    // ── Pins ──
+
+    /// Pin the definition of `name` in `file_path` as canonical.
+    ///
+    /// Search lists the pinned definition first among equally ranked matches, and
+    /// edge resolution prefers it over same-directory and ambiguous matches. Edges
+    /// targeting `name` are re-resolved immediately. Returns the pinned symbol.
+    pub fn pin(&self, name: &str, file_path: &str) -> Result<Symbol> {
+        let symbol = self
+            .conn
+            .query_row(
+                "SELECT id, name, kind, file_path, start_line, end_line,
+                        start_byte, end_byte, parent_id, signature, visibility,
+                        is_async, docstring, entrypoint, db_table, generation
+                 FROM symbols
+                 WHERE name = ?1 AND file_path = ?2 AND kind != 'import'
+                 ORDER BY start_line LIMIT 1",
+                params![name, file_path],
+                row_to_symbol,
+            )
+            .optional()?
+            .with_context(|| format!("no definition of '{name}' in {file_path}"))?;
+
+        self.conn.execute(
+            "INSERT OR REPLACE INTO pins (name, file_path) VALUES (?1, ?2)",
+            params![name, file_path],
+        )?;
+        self.reresolve_name(name)?;
+        Ok(symbol)
+    }
+
+    /// Remove the pin for `name`. Returns false if it was not pinned.
+    pub fn unpin(&self, name: &str) -> Result<bool> {
+        let removed = self
+            .conn
+            .execute("DELETE FROM pins WHERE name = ?1", params![name])?;
+        if removed > 0 {
+            self.reresolve_name(name)?;
+        }
+        Ok(removed > 0)
+    }
+
+    /// All pins as `(name, file_path)`, sorted by name.
+    pub fn pins(&self) -> Result<Vec<(String, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, file_path FROM pins ORDER BY name")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Reset and re-resolve edges targeting `name` (plain or as a dotted suffix).
+    fn reresolve_name(&self, name: &str) -> Result<()> {
+        let escaped = name
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        self.conn.execute(
+            "UPDATE edges SET target_id = NULL, target_kind = NULL
+             WHERE target_name = ?1 OR target_name LIKE '%.' || ?2 ESCAPE '\\'",
+            params![name, escaped],
+        )?;
+        self.resolve_edges()?;
+        Ok(())
     }
 
     // ── Queries ──
@@ -478,7 +1785,7 @@ impl Database {
     /// Search for symbols by name — case-insensitive, prefix match ranks before substring.
     ///
     /// `%` and `_` in `query` are treated as literals, not LIKE wildcards.
-    /// Note: `LOWER()` in SQLite is ASCII-only, which is acceptable for code identifiers.
+    /// Case folding is Unicode-aware (`casefold()`), so `ユーザー` and `Ωmega` match too.
     /// Returns an error if `query` is empty or `limit` is zero.
     pub fn search(
         &self,
@@ -486,6 +1793,19 @@ impl Database {
         kind_filter: Option<SymbolKind>,
         file_filter: Option<&str>,
         limit: u32,
+    ) -> Result<Vec<Symbol>> {
+        self.search_in(query, kind_filter, file_filter, None, limit)
+    }
+
+    /// [`search`](Self::search) restricted to symbols in files of `language`
+    /// (see [`language_condition`]).
+    pub fn search_in(
+        &self,
+        query: &str,
+        kind_filter: Option<SymbolKind>,
+        file_filter: Option<&str>,
+        language: Option<&str>,
+        limit: u32,
     ) -> Result<Vec<Symbol>> {
         anyhow::ensure!(!query.is_empty(), "search query cannot be empty");
         anyhow::ensure!(limit > 0, "search limit must be at least 1");
@@ -504,14 +1824,14 @@ impl Database {
         //   exact variable=3, prefix variable=4, substring variable=5,
         //   exact import=6, ...
         // Within the same rank score, secondary sort by kind (fn < method < class)
-        // then by file_path and start_line for determinism.
-        let mut stmt = self.conn.prepare(
+        // then by file_path, start_line and id for determinism.
+        let mut stmt = self.conn.prepare(&format!(
             "SELECT id, name, kind, file_path, start_line, end_line,
                     start_byte, end_byte, parent_id, signature, visibility,
-                    is_async, docstring,
+                    is_async, docstring, entrypoint, db_table, generation,
                     (CASE
-                       WHEN LOWER(name) = LOWER(?1)                    THEN 0
-                       WHEN LOWER(name) LIKE LOWER(?2) || '%' ESCAPE '\\' THEN 1
+                       WHEN casefold(name) = casefold(?1)                   THEN 0
+                       WHEN casefold(name) LIKE casefold(?2) || '%' ESCAPE '\\' THEN 1
                        ELSE                                                  2
                      END) +
                     (CASE kind
@@ -523,37 +1843,43 @@ impl Database {
                        ELSE                 3
                      END) AS rank
              FROM symbols
-             WHERE LOWER(name) LIKE '%' || LOWER(?2) || '%' ESCAPE '\\'
+             WHERE casefold(name) LIKE '%' || casefold(?2) || '%' ESCAPE '\\'
                AND (?3 IS NULL OR kind = ?3)
                AND (?4 IS NULL OR file_path = ?4)
+               AND {language}
              ORDER BY rank,
+                      NOT EXISTS (SELECT 1 FROM pins p
+                                  WHERE p.name = symbols.name AND p.file_path = symbols.file_path),
                       CASE kind
                         WHEN 'function' THEN 0
                         WHEN 'method'   THEN 1
                         WHEN 'class'    THEN 2
                         ELSE                 3
                       END,
-                      file_path, start_line
+                      file_path, start_line, id
              LIMIT ?5",
-        )?;
-        // rank is column 13 — row_to_symbol reads columns 0–12 and ignores it
-        // ?1 = raw query (exact equality), ?2 = escaped query (LIKE patterns), ?3 = kind, ?4 = file, ?5 = limit
+            language = language_condition("symbols.file_path", 6),
+        ))?;
+        // rank is column 16 — row_to_symbol reads columns 0–15 and ignores it
+        // ?1 = raw query (exact equality), ?2 = escaped query (LIKE patterns), ?3 = kind, ?4 = file, ?5 = limit,
+        // ?6 = language
         let rows = stmt
             .query_map(
-                params![query, escaped, kind_str, file_filter, limit],
+                params![query, escaped, kind_str, file_filter, limit, language],
                 row_to_symbol,
             )?
             .collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(rows)
     }
 
-    /// Outline: all symbols in a file, ordered by line.
+    /// Outline: all symbols in a file, ordered by line, then byte offset.
     pub fn outline(&self, file_path: &str) -> Result<Vec<Symbol>> {
         let mut stmt = self.conn.prepare(
             "SELECT id, name, kind, file_path, start_line, end_line, start_byte, end_byte,
-                    parent_id, signature, visibility, is_async, docstring
+                    parent_id, signature, visibility, is_async, docstring, entrypoint,
+                    db_table, generation
              FROM symbols WHERE file_path = ?1
-             ORDER BY start_line",
+             ORDER BY start_line, start_byte, id",
         )?;
         let rows = stmt
             .query_map(params![file_path], row_to_symbol)?
@@ -561,13 +1887,16 @@ impl Database {
         Ok(rows)
     }
 
-    /// Find what a symbol calls (edges originating from symbols matching the name).
+    /// Find what a symbol calls (edges originating from symbols matching the name),
+    /// ordered by file, line, caller and target name.
     pub fn callees(&self, name: &str) -> Result<Vec<Edge>> {
         let mut stmt = self.conn.prepare(
-            "SELECT e.id, e.source_id, e.target_name, e.target_id, e.kind, e.file_path, e.line
+            "SELECT e.id, e.source_id, e.target_name, e.target_id, e.kind, e.file_path, e.line,
+                    e.target_kind, e.conditional
              FROM edges e
              JOIN symbols s ON e.source_id = s.id
-             WHERE s.name = ?1 AND e.kind = 'calls'",
+             WHERE s.name = ?1 AND e.kind = 'calls'
+             ORDER BY e.file_path, e.line, e.source_id, e.target_name",
         )?;
         let rows = stmt
             .query_map(params![name], row_to_edge)?
@@ -575,68 +1904,193 @@ impl Database {
         Ok(rows)
     }
 
+    /// Calls made by symbols named `name`, with the resolved target symbol and,
+    /// with `line_text`, the call line read from the caller's stored source.
+    /// `language` keeps calls made in files of that language only. Ordered like
+    /// [`callees`](Self::callees).
+    pub fn call_sites(
+        &self,
+        name: &str,
+        line_text: bool,
+        language: Option<&str>,
+    ) -> Result<Vec<CallSite>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT e.id, e.source_id, e.target_name, e.target_id, e.kind, e.file_path, e.line,
+                    e.target_kind, e.conditional,
+                    t.id, t.name, t.kind, t.file_path, t.start_line, t.end_line,
+                    t.start_byte, t.end_byte, t.parent_id, t.signature, t.visibility,
+                    t.is_async, t.docstring, t.entrypoint, t.db_table, t.generation,
+                    s.start_line
+             FROM edges e
+             JOIN symbols s ON e.source_id = s.id
+             LEFT JOIN symbols t ON e.target_id = t.id
+             WHERE s.name = ?1 AND e.kind = 'calls' AND {language}
+             ORDER BY e.file_path, e.line, e.source_id, e.target_name",
+            language = language_condition("e.file_path", 2),
+        ))?;
+        let rows = stmt
+            .query_map(params![name, language], |row| {
+                let target = match row.get::<_, Option<String>>(9)? {
+                    Some(_) => Some(row_to_symbol_offset(row, 9)?),
+                    None => None,
+                };
+                Ok((row_to_edge(row)?, target, row.get::<_, u32>(25)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let mut sources: std::collections::HashMap<String, Option<String>> =
+            std::collections::HashMap::new();
+        let mut sites = Vec::with_capacity(rows.len());
+        for (edge, target, source_start) in rows {
+            let text = if line_text {
+                if !sources.contains_key(&edge.source_id) {
+                    let content = self.get_symbol_content(&edge.source_id)?.map(|(c, _)| c);
+                    sources.insert(edge.source_id.clone(), content);
+                }
+                sources[&edge.source_id].as_deref().and_then(|content| {
+                    // Stored content starts at the caller's first line
+                    let offset = edge.line.checked_sub(source_start)?;
+                    let line = content.lines().nth(offset as usize)?.trim();
+                    (!line.is_empty()).then(|| line.to_string())
+                })
+            } else {
+                None
+            };
+            sites.push(CallSite {
+                edge,
+                target,
+                line_text: text,
+            });
+        }
+        Ok(sites)
+    }
+
+    /// Calls to symbols named `name`, matched like [`refs_in`](Self::refs_in) (by call
+    /// target, resolved target or import alias), each with the symbol making the call.
+    /// Only calls made in files of `language` when given. Ordered like
+    /// [`callees`](Self::callees).
+    pub fn callers(&self, name: &str, language: Option<&str>) -> Result<Vec<Caller>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT e.id, e.source_id, e.target_name, e.target_id, e.kind, e.file_path, e.line,
+                    e.target_kind, e.conditional,
+                    s.id, s.name, s.kind, s.file_path, s.start_line, s.end_line,
+                    s.start_byte, s.end_byte, s.parent_id, s.signature, s.visibility,
+                    s.is_async, s.docstring, s.entrypoint, s.db_table, s.generation
+             FROM edges e
+             JOIN symbols s ON e.source_id = s.id
+             WHERE e.kind = 'calls' AND e.id IN ({referencing}) AND {language}
+             ORDER BY e.file_path, e.line, e.source_id, e.target_name",
+            referencing = referencing_edge_ids("?1"),
+            language = language_condition("e.file_path", 2),
+        ))?;
+        let rows = stmt
+            .query_map(params![name, language], |row| {
+                Ok(Caller {
+                    edge: row_to_edge(row)?,
+                    caller: row_to_symbol_offset(row, 9)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
     /// All references to a name, with the source symbol resolved.
     /// Optionally filter by edge kind.
+    ///
+    /// Edges written with an import alias target the imported name (see
+    /// [`ExtractionResult::new`](crate::languages::ExtractionResult::new)), so they
+    /// count for it; querying the alias finds them in the files that import under it.
     pub fn refs(
         &self,
         name: &str,
         kind_filter: Option<EdgeKind>,
+    ) -> Result<Vec<(Edge, Option<Symbol>)>> {
+        self.refs_in(name, kind_filter, None, None, None)
+    }
+
+    /// [`refs`](Self::refs) restricted to edges whose file matches `path_glob`
+    /// (SQLite `GLOB`: `*` also matches `/`, so `src/auth/**` covers the subtree)
+    /// or is of `language`, and to edges resolved to a symbol of kind `target_kind`.
+    pub fn refs_in(
+        &self,
+        name: &str,
+        kind_filter: Option<EdgeKind>,
+        path_glob: Option<&str>,
+        target_kind: Option<SymbolKind>,
+        language: Option<&str>,
     ) -> Result<Vec<(Edge, Option<Symbol>)>> {
         // Use a LEFT JOIN to resolve target_id → symbol name instead of a correlated subquery.
         let map_row = |row: &rusqlite::Row<'_>| -> rusqlite::Result<(Edge, Option<Symbol>)> {
-            let kind_str = row.get::<_, String>(4)?;
-            let kind = kind_str.parse().unwrap_or(EdgeKind::References);
-            let edge = Edge {
-                source_id: row.get(1)?,
-                target_name: row.get(2)?,
-                target_id: row.get(3)?,
-                kind,
-                file_path: row.get(5)?,
-                line: row.get(6)?,
-            };
-            let sym: Option<Symbol> = if row.get::<_, Option<String>>(7)?.is_some() {
-                Some(row_to_symbol_offset(row, 7)?)
+            let edge = row_to_edge(row)?;
+            let sym: Option<Symbol> = if row.get::<_, Option<String>>(9)?.is_some() {
+                Some(row_to_symbol_offset(row, 9)?)
             } else {
                 None
             };
             Ok((edge, sym))
         };
 
-        let rows = if let Some(kind) = kind_filter {
-            let mut stmt = self.conn.prepare_cached(
-                "SELECT e.id, e.source_id, e.target_name, e.target_id, e.kind, e.file_path, e.line,
-                        s.id, s.name, s.kind, s.file_path, s.start_line, s.end_line,
-                        s.start_byte, s.end_byte, s.parent_id, s.signature, s.visibility,
-                        s.is_async, s.docstring
-                 FROM edges e
-                 LEFT JOIN symbols s ON e.source_id = s.id
-                 LEFT JOIN symbols sym2 ON e.target_id = sym2.id
-                 WHERE (e.target_name = ?1 OR sym2.name = ?1)
-                   AND e.kind = ?2",
-            )?;
-            let rows = stmt
-                .query_map(params![name, kind.as_str()], map_row)?
-                .collect::<std::result::Result<Vec<_>, _>>()?;
-            rows
-        } else {
-            let mut stmt = self.conn.prepare_cached(
-                "SELECT e.id, e.source_id, e.target_name, e.target_id, e.kind, e.file_path, e.line,
-                        s.id, s.name, s.kind, s.file_path, s.start_line, s.end_line,
-                        s.start_byte, s.end_byte, s.parent_id, s.signature, s.visibility,
-                        s.is_async, s.docstring
-                 FROM edges e
-                 LEFT JOIN symbols s ON e.source_id = s.id
-                 LEFT JOIN symbols sym2 ON e.target_id = sym2.id
-                 WHERE e.target_name = ?1 OR sym2.name = ?1",
-            )?;
-            let rows = stmt
-                .query_map(params![name], map_row)?
-                .collect::<std::result::Result<Vec<_>, _>>()?;
-            rows
-        };
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT e.id, e.source_id, e.target_name, e.target_id, e.kind, e.file_path, e.line,
+                    e.target_kind, e.conditional,
+                    s.id, s.name, s.kind, s.file_path, s.start_line, s.end_line,
+                    s.start_byte, s.end_byte, s.parent_id, s.signature, s.visibility,
+                    s.is_async, s.docstring, s.entrypoint, s.db_table, s.generation
+             FROM edges e
+             LEFT JOIN symbols s ON e.source_id = s.id
+             LEFT JOIN symbols sym2 ON e.target_id = sym2.id
+             WHERE (e.target_name = ?1 OR sym2.name = ?1
+                    OR EXISTS (SELECT 1 FROM import_aliases a
+                               WHERE a.file_path = e.file_path AND a.alias = ?1
+                                 AND (a.name = e.target_name OR a.name = sym2.name)))
+               AND (?2 IS NULL OR e.kind = ?2)
+               AND (?3 IS NULL OR e.file_path GLOB ?3)
+               AND (?4 IS NULL OR e.target_kind = ?4)
+               AND {language}",
+            language = language_condition("e.file_path", 5),
+        ))?;
+        let path_glob = path_glob.map(|g| g.trim_start_matches("./"));
+        let rows = stmt
+            .query_map(
+                params![
+                    name,
+                    kind_filter.map(|k| k.as_str()),
+                    path_glob,
+                    target_kind.map(|k| k.as_str()),
+                    language
+                ],
+                map_row,
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        self.sort_refs_by_proximity(name, rows)
+    }
+
+    /// Order references by structural proximity to the referenced definition,
+    /// see [`sort_refs_by_proximity`].
+    fn sort_refs_by_proximity(
+        &self,
+        name: &str,
+        mut rows: Vec<(Edge, Option<Symbol>)>,
+    ) -> Result<Vec<(Edge, Option<Symbol>)>> {
+        let defs = self.definition_files(name)?;
+        sort_refs_by_proximity(&mut rows, &defs);
         Ok(rows)
     }
 
+    /// Non-import definitions named `name`, as symbol id → file path.
+    pub(crate) fn definition_files(
+        &self,
+        name: &str,
+    ) -> Result<std::collections::HashMap<String, String>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, file_path FROM symbols WHERE name = ?1 AND kind != 'import'",
+        )?;
+        let defs = stmt
+            .query_map(params![name], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(defs)
+    }
+
     /// Inheritance hierarchy rooted at a class.
     pub fn hierarchy(&self, class_name: &str) -> Result<Vec<(String, String)>> {
         // Returns (child, parent) pairs
@@ -656,7 +2110,8 @@ impl Database {
     /// File-level dependencies (imports from a file).
     pub fn file_deps(&self, file_path: &str) -> Result<Vec<Edge>> {
         let mut stmt = self.conn.prepare(
-            "SELECT e.id, e.source_id, e.target_name, e.target_id, e.kind, e.file_path, e.line
+            "SELECT e.id, e.source_id, e.target_name, e.target_id, e.kind, e.file_path, e.line,
+                    e.target_kind, e.conditional
              FROM edges e
              WHERE e.file_path = ?1 AND e.kind = 'imports'",
         )?;
@@ -668,30 +2123,63 @@ impl Database {
 
     /// Transitive impact analysis: everything reachable within `depth` hops.
     pub fn impact(&self, name: &str, max_depth: u32) -> Result<Vec<(Edge, u32)>> {
-        let mut results = Vec::new();
-        let mut visited = std::collections::HashSet::new();
-        let mut frontier: Vec<(String, u32)> = vec![(name.to_string(), 0)];
-
-        while let Some((current, depth)) = frontier.pop() {
-            if depth >= max_depth || visited.contains(&current) {
-                continue;
-            }
-            visited.insert(current.clone());
-
-            let refs = self.refs(&current, None)?;
-            for (edge, sym) in refs {
-                results.push((edge, depth + 1));
-                if let Some(s) = sym {
-                    if !visited.contains(&s.name) {
-                        frontier.push((s.name, depth + 1));
-                    }
-                }
-            }
-        }
+        self.impact_of(name, max_depth, None, None)
+    }
 
+    /// [`impact`](Self::impact) starting only from references resolved to a
+    /// `target_kind` symbol named `name` (e.g. the class, not a same-named function),
+    /// and following only references made in files of `language`.
+    pub fn impact_of(
+        &self,
+        name: &str,
+        max_depth: u32,
+        target_kind: Option<SymbolKind>,
+        language: Option<&str>,
+    ) -> Result<Vec<(Edge, u32)>> {
+        // Same traversal as `transitive_impact` over `refs_in`, in one query.
+        // `reach` holds each referencing symbol name at every depth it is found
+        // at below `max_depth`; UNION drops repeats and the depth bound ends
+        // cycles. Each name then contributes its references once, at its
+        // shortest distance. `target_kind` only narrows the references to `name`.
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "WITH RECURSIVE reach(name, depth) AS (
+                 SELECT ?1, 0 WHERE ?2 > 0
+                 UNION
+                 SELECT s.name, r.depth + 1
+                 FROM reach r
+                 JOIN edges e ON e.id IN ({reach_refs})
+                 JOIN symbols s ON s.id = e.source_id
+                 WHERE r.depth + 1 < ?2 AND {reach_filter}
+             ),
+             visited(name, depth) AS (SELECT name, MIN(depth) FROM reach GROUP BY name)
+             SELECT e.id, e.source_id, e.target_name, e.target_id, e.kind, e.file_path, e.line,
+                    e.target_kind, e.conditional, v.depth + 1
+             FROM visited v
+             JOIN edges e ON e.id IN ({visited_refs})
+             WHERE {visited_filter}
+             ORDER BY e.id",
+            reach_refs = referencing_edge_ids("r.name"),
+            reach_filter = impact_edge_filter("r.name"),
+            visited_refs = referencing_edge_ids("v.name"),
+            visited_filter = impact_edge_filter("v.name"),
+        ))?;
+        let mut results = stmt
+            .query_map(
+                params![name, max_depth, target_kind.map(|k| k.as_str()), language],
+                |row| Ok((row_to_edge(row)?, row.get(9)?)),
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        results.sort_by(|a, b| impact_order(a).cmp(&impact_order(b)));
         Ok(results)
     }
 
+    /// Who can raise `exception`: direct raisers, then their transitive callers.
+    pub fn raises(&self, exception: &str, max_depth: u32) -> Result<Vec<(Edge, u32)>> {
+        transitive_raisers(exception, max_depth, |current, kind| {
+            self.refs(current, kind)
+        })
+    }
+
     /// Index statistics.
     pub fn stats(&self) -> Result<IndexStats> {
         let num_files: u32 = self
@@ -716,6 +2204,46 @@ impl Database {
             .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
+        let mut lines_stmt = self.conn.prepare(
+            "SELECT language, SUM(code_lines), SUM(comment_lines), SUM(blank_lines)
+             FROM files GROUP BY language ORDER BY SUM(code_lines) DESC, language",
+        )?;
+        let language_lines: Vec<(String, LineCounts)> = lines_stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    LineCounts {
+                        code: row.get(1)?,
+                        comment: row.get(2)?,
+                        blank: row.get(3)?,
+                    },
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut lines = LineCounts::default();
+        for (_, counts) in &language_lines {
+            lines += *counts;
+        }
+
+        let mut largest_stmt = self.conn.prepare(
+            "SELECT f.path, f.num_symbols, COALESCE(e.n, 0), f.truncated
+             FROM files f
+             LEFT JOIN (SELECT file_path, COUNT(*) AS n FROM edges GROUP BY file_path) e
+               ON e.file_path = f.path
+             ORDER BY f.num_symbols + COALESCE(e.n, 0) DESC, f.path
+             LIMIT ?1",
+        )?;
+        let largest_files: Vec<LargeFile> = largest_stmt
+            .query_map(params![LARGEST_FILES], |row| {
+                Ok(LargeFile {
+                    path: row.get(0)?,
+                    symbols: row.get(1)?,
+                    edges: row.get(2)?,
+                    truncated: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
         let mut kind_stmt = self
             .conn
             .prepare("SELECT kind, COUNT(*) FROM symbols GROUP BY kind ORDER BY COUNT(*) DESC")?;
@@ -729,7 +2257,77 @@ impl Database {
             num_edges,
             num_resolved,
             languages,
+            lines,
+            language_lines,
+            largest_files,
             symbol_kinds,
+            pins: self.pins()?,
+            resolution: self.last_resolution()?,
+        })
+    }
+
+    /// Documentation coverage of functions, methods and classes, per file.
+    ///
+    /// A symbol counts as documented when it has a non-empty docstring.
+    /// `visibility = None` includes all symbols. `largest` caps the list of
+    /// undocumented symbols, which is ordered by line span (biggest first).
+    pub fn doc_coverage(
+        &self,
+        visibility: Option<Visibility>,
+        largest: u32,
+    ) -> Result<DocCoverage> {
+        let vis = visibility.map(|v| v.as_str());
+        let mut module_stmt = self.conn.prepare(
+            "SELECT file_path, COUNT(*),
+                    SUM(CASE WHEN docstring IS NOT NULL AND docstring != '' THEN 1 ELSE 0 END)
+             FROM symbols
+             WHERE kind IN ('function', 'method', 'class')
+               AND (?1 IS NULL OR visibility = ?1)
+             GROUP BY file_path",
+        )?;
+        let mut modules: Vec<ModuleDocCoverage> = module_stmt
+            .query_map(params![vis], |row| {
+                let total: u32 = row.get(1)?;
+                let documented: u32 = row.get(2)?;
+                Ok(ModuleDocCoverage {
+                    file_path: row.get(0)?,
+                    total,
+                    documented,
+                    coverage: coverage_ratio(documented, total),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        // Worst-covered modules first, then the ones with the most symbols.
+        modules.sort_by(|a, b| {
+            a.coverage
+                .total_cmp(&b.coverage)
+                .then(b.total.cmp(&a.total))
+                .then_with(|| a.file_path.cmp(&b.file_path))
+        });
+
+        let mut undoc_stmt = self.conn.prepare(
+            "SELECT id, name, kind, file_path, start_line, end_line, start_byte, end_byte,
+                    parent_id, signature, visibility, is_async, docstring, entrypoint,
+                    db_table, generation
+             FROM symbols
+             WHERE kind IN ('function', 'method', 'class')
+               AND (?1 IS NULL OR visibility = ?1)
+               AND (docstring IS NULL OR docstring = '')
+             ORDER BY end_line - start_line DESC, file_path, start_line
+             LIMIT ?2",
+        )?;
+        let largest_undocumented = undoc_stmt
+            .query_map(params![vis, largest], row_to_symbol)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let total = modules.iter().map(|m| m.total).sum();
+        let documented = modules.iter().map(|m| m.documented).sum();
+        Ok(DocCoverage {
+            total,
+            documented,
+            coverage: coverage_ratio(documented, total),
+            modules,
+            largest_undocumented,
         })
     }
 
@@ -754,21 +2352,194 @@ impl Database {
         Ok(rows)
     }
 
-    // ── RAG: Symbol Content ──
+    /// Indexed file paths with the modification time recorded when each was indexed.
+    pub fn file_mtimes(&self) -> Result<Vec<(String, f64)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, last_modified FROM files ORDER BY path")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
 
-    /// Insert or replace symbol content (raw source + metadata header for embedding).
-    ///
-    /// `symbol_name` is used to compute a normalized form (camelCase/snake_case split)
-    /// stored in the FTS5 index for better keyword matching.
-    pub fn upsert_symbol_content(
-        &self,
-        symbol_id: &str,
-        symbol_name: &str,
-        content: &str,
-        header: &str,
-    ) -> Result<()> {
-        let normalized = normalize_symbol_name(symbol_name);
-        self.conn.execute(
+    /// Per-file extraction counts, ordered by path. Input to [`stats_by_dir`].
+    pub fn file_stats(&self) -> Result<Vec<FileStats>> {
+        let mut per_file: std::collections::BTreeMap<String, FileStats> = self
+            .conn
+            .prepare("SELECT path, language FROM files")?
+            .query_map([], |row| {
+                Ok(FileStats {
+                    path: row.get(0)?,
+                    language: row.get(1)?,
+                    lines: 0,
+                    symbols: 0,
+                    edges: 0,
+                    unresolved: 0,
+                })
+            })?
+            .map(|row| row.map(|f| (f.path.clone(), f)))
+            .collect::<std::result::Result<_, _>>()?;
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT file_path, COUNT(*), MAX(end_line) FROM symbols GROUP BY file_path")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            if let Some(f) = per_file.get_mut(&row.get::<_, String>(0)?) {
+                f.symbols = row.get(1)?;
+                f.lines = row.get(2)?;
+            }
+        }
+
+        let mut stmt = self.conn.prepare(
+            "SELECT file_path, COUNT(*), SUM(target_id IS NULL) FROM edges GROUP BY file_path",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            if let Some(f) = per_file.get_mut(&row.get::<_, String>(0)?) {
+                f.edges = row.get(1)?;
+                f.unresolved = row.get(2)?;
+            }
+        }
+
+        Ok(per_file.into_values().collect())
+    }
+
+    /// Entry points, optionally of one kind, ordered by file and line.
+    pub fn entrypoints(&self, kind: Option<&str>) -> Result<Vec<Symbol>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, kind, file_path, start_line, end_line, start_byte, end_byte,
+                    parent_id, signature, visibility, is_async, docstring, entrypoint,
+                    db_table, generation
+             FROM symbols
+             WHERE entrypoint IS NOT NULL AND (?1 IS NULL OR entrypoint = ?1)
+             ORDER BY file_path, start_line",
+        )?;
+        let rows = stmt
+            .query_map(params![kind], row_to_symbol)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Definitions of `name` (imports excluded), optionally only those in
+    /// `file_path`, ordered by file and line.
+    pub fn definitions(&self, name: &str, file_path: Option<&str>) -> Result<Vec<Symbol>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, kind, file_path, start_line, end_line, start_byte, end_byte,
+                    parent_id, signature, visibility, is_async, docstring, entrypoint,
+                    db_table, generation
+             FROM symbols
+             WHERE name = ?1 AND kind != 'import' AND (?2 IS NULL OR file_path = ?2)
+             ORDER BY file_path, start_line",
+        )?;
+        let rows = stmt
+            .query_map(params![name, file_path], row_to_symbol)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// All symbols, ordered by file and line.
+    pub fn all_symbols(&self) -> Result<Vec<Symbol>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, kind, file_path, start_line, end_line, start_byte, end_byte,
+                    parent_id, signature, visibility, is_async, docstring, entrypoint,
+                    db_table, generation
+             FROM symbols ORDER BY file_path, start_line",
+        )?;
+        let rows = stmt
+            .query_map([], row_to_symbol)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// ORM models mapped to `table` (case-insensitive), ordered by file and line.
+    pub fn models_for_table(&self, table: &str) -> Result<Vec<Symbol>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, kind, file_path, start_line, end_line, start_byte, end_byte,
+                    parent_id, signature, visibility, is_async, docstring, entrypoint,
+                    db_table, generation
+             FROM symbols
+             WHERE db_table = ?1 COLLATE NOCASE
+             ORDER BY file_path, start_line",
+        )?;
+        let rows = stmt
+            .query_map(params![table], row_to_symbol)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Symbols whose stored content contains `needle` (ASCII case-insensitive),
+    /// with that content, ordered by file and line.
+    pub fn symbols_mentioning(&self, needle: &str) -> Result<Vec<(Symbol, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.id, s.name, s.kind, s.file_path, s.start_line, s.end_line,
+                    s.start_byte, s.end_byte, s.parent_id, s.signature, s.visibility,
+                    s.is_async, s.docstring, s.entrypoint, s.db_table, s.generation, c.content
+             FROM symbol_content c
+             JOIN symbols s ON s.id = c.symbol_id
+             WHERE instr(lower(c.content), lower(?1)) > 0
+             ORDER BY s.file_path, s.start_line",
+        )?;
+        let rows = stmt
+            .query_map(params![needle], |row| {
+                Ok((row_to_symbol(row)?, row.get(16)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// All edges, ordered by file and line.
+    pub fn all_edges(&self) -> Result<Vec<Edge>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.id, e.source_id, e.target_name, e.target_id, e.kind, e.file_path, e.line,
+                    e.target_kind, e.conditional
+             FROM edges e ORDER BY e.file_path, e.line, e.id",
+        )?;
+        let rows = stmt
+            .query_map([], row_to_edge)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// File-to-file dependencies: resolved edges whose target is defined in another
+    /// file, counted per `(source file, target file)` pair.
+    pub fn module_links(&self) -> Result<Vec<ModuleLink>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.file_path, t.file_path, COUNT(*)
+             FROM edges e
+             JOIN symbols t ON e.target_id = t.id
+             WHERE t.file_path != e.file_path
+             GROUP BY e.file_path, t.file_path
+             ORDER BY e.file_path, t.file_path",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(ModuleLink {
+                    source: row.get(0)?,
+                    target: row.get(1)?,
+                    edges: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    // ── RAG: Symbol Content ──
+
+    /// Insert or replace symbol content (raw source + metadata header for embedding).
+    ///
+    /// `symbol_name` is used to compute a normalized form (camelCase/snake_case split)
+    /// stored in the FTS5 index for better keyword matching.
+    pub fn upsert_symbol_content(
+        &self,
+        symbol_id: &str,
+        symbol_name: &str,
+        content: &str,
+        header: &str,
+    ) -> Result<()> {
+        let normalized = normalize_symbol_name(symbol_name);
+        self.conn.execute(
             "INSERT OR REPLACE INTO symbol_content (symbol_id, content, header, normalized_name)
              VALUES (?1, ?2, ?3, ?4)",
             params![symbol_id, content, header, normalized],
@@ -780,17 +2551,17 @@ impl Database {
     ///
     /// Tuples: `(symbol_id, symbol_name, content, header)`.
     pub fn insert_symbol_contents(&self, items: &[(String, String, String, String)]) -> Result<()> {
-        let tx = self.conn.unchecked_transaction()?;
-        let mut stmt = self.conn.prepare_cached(
-            "INSERT OR REPLACE INTO symbol_content (symbol_id, content, header, normalized_name)
-             VALUES (?1, ?2, ?3, ?4)",
-        )?;
-        for (symbol_id, name, content, header) in items {
-            let normalized = normalize_symbol_name(name);
-            stmt.execute(params![symbol_id, content, header, normalized])?;
-        }
-        tx.commit()?;
-        Ok(())
+        self.in_transaction(|| {
+            let mut stmt = self.conn.prepare_cached(
+                "INSERT OR REPLACE INTO symbol_content (symbol_id, content, header, normalized_name)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (symbol_id, name, content, header) in items {
+                let normalized = normalize_symbol_name(name);
+                stmt.execute(params![symbol_id, content, header, normalized])?;
+            }
+            Ok(())
+        })
     }
 
     /// Remove symbol content for all symbols in a file.
@@ -857,22 +2628,273 @@ impl Database {
 
     /// Full-text search over symbol names and content using BM25 ranking.
     ///
+    /// Columns are weighted with [`fts_weights`](Self::fts_weights), so a match in
+    /// the name outranks a passing mention in the body.
     /// Returns symbol IDs ordered by relevance (best match first).
     pub fn fts5_search(&self, query: &str, limit: u32) -> Result<Vec<String>> {
+        let w = self.fts_weights()?;
         let mut stmt = self.conn.prepare(
             "SELECT sc.symbol_id
              FROM symbol_fts f
              JOIN symbol_content sc ON sc.rowid = f.rowid
              WHERE symbol_fts MATCH ?1
-             ORDER BY rank
+             ORDER BY bm25(symbol_fts, ?3, ?4, ?5), sc.symbol_id
              LIMIT ?2",
         )?;
         let rows = stmt
-            .query_map(params![query, limit], |row| row.get(0))?
+            .query_map(
+                params![query, limit, w.name, w.normalized_name, w.content],
+                |row| row.get(0),
+            )?
             .collect::<std::result::Result<Vec<_>, _>>()?;
         Ok(rows)
     }
 
+    /// BM25 column weights used by [`fts5_search`](Self::fts5_search): the stored
+    /// setting, else [`FtsWeights::default`].
+    pub fn fts_weights(&self) -> Result<FtsWeights> {
+        match self.get_metadata(FTS_WEIGHTS_KEY)? {
+            Some(stored) => stored
+                .parse()
+                .map_err(|e| anyhow::anyhow!("invalid stored FTS weights '{stored}': {e}")),
+            None => Ok(FtsWeights::default()),
+        }
+    }
+
+    /// Store the BM25 column weights for this index; `None` restores the defaults.
+    pub fn set_fts_weights(&self, weights: Option<FtsWeights>) -> Result<()> {
+        match weights {
+            Some(w) => self.set_metadata(FTS_WEIGHTS_KEY, &w.to_string()),
+            None => {
+                self.conn.execute(
+                    "DELETE FROM metadata WHERE key = ?1",
+                    params![FTS_WEIGHTS_KEY],
+                )?;
+                Ok(())
+            }
+        }
+    }
+
+    /// How re-ranker scores become a relevance and which are dropped: the stored
+    /// setting, else [`RerankCalibration::default`].
+    pub fn rerank_calibration(&self) -> Result<RerankCalibration> {
+        match self.get_metadata(RERANK_CALIBRATION_KEY)? {
+            Some(stored) => stored.parse().map_err(|e| {
+                anyhow::anyhow!("invalid stored re-ranker calibration '{stored}': {e}")
+            }),
+            None => Ok(RerankCalibration::default()),
+        }
+    }
+
+    /// Store the re-ranker calibration for this index; `None` restores the defaults.
+    pub fn set_rerank_calibration(&self, calibration: Option<RerankCalibration>) -> Result<()> {
+        match calibration {
+            Some(c) => self.set_metadata(RERANK_CALIBRATION_KEY, &c.to_string()),
+            None => {
+                self.conn.execute(
+                    "DELETE FROM metadata WHERE key = ?1",
+                    params![RERANK_CALIBRATION_KEY],
+                )?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Compare `symbol_content` with its FTS5 index. The triggers keep them in sync,
+    /// but an interrupted write or a replaced row can leave them apart, and keyword
+    /// search then silently misses symbols or returns stale matches.
+    pub fn check_fts(&self) -> Result<FtsCheck> {
+        let count = |sql: &str| -> Result<u32> { Ok(self.conn.query_row(sql, [], |r| r.get(0))?) };
+        let corrupt = match self.conn.execute(
+            "INSERT INTO symbol_fts(symbol_fts) VALUES ('integrity-check')",
+            [],
+        ) {
+            Ok(_) => false,
+            Err(rusqlite::Error::SqliteFailure(e, _))
+                if e.code == rusqlite::ErrorCode::DatabaseCorrupt =>
+            {
+                true
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(FtsCheck {
+            content_rows: count("SELECT COUNT(*) FROM symbol_content")?,
+            // One docsize row per indexed document
+            indexed_rows: count("SELECT COUNT(*) FROM symbol_fts_docsize")?,
+            missing: count(
+                "SELECT COUNT(*) FROM symbol_content c
+                 WHERE NOT EXISTS (SELECT 1 FROM symbol_fts_docsize d WHERE d.id = c.rowid)",
+            )?,
+            orphaned: count(
+                "SELECT COUNT(*) FROM symbol_fts_docsize d
+                 WHERE NOT EXISTS (SELECT 1 FROM symbol_content c WHERE c.rowid = d.id)",
+            )?,
+            corrupt,
+        })
+    }
+
+    /// Rebuild the FTS5 index from `symbol_content`, returning the rows indexed.
+    ///
+    /// FTS5's own `rebuild` cannot be used: `symbol_name` comes from `symbols`,
+    /// not from the content table.
+    pub fn rebuild_fts(&self) -> Result<u32> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.conn.execute(
+            "INSERT INTO symbol_fts(symbol_fts) VALUES ('delete-all')",
+            [],
+        )?;
+        let rows = self.conn.execute(
+            "INSERT INTO symbol_fts(rowid, symbol_name, normalized_name, content)
+             SELECT c.rowid, s.name, c.normalized_name, c.content
+             FROM symbol_content c LEFT JOIN symbols s ON s.id = c.symbol_id",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(rows as u32)
+    }
+
+    /// Rows referring to symbols that no longer exist, left by an interrupted write
+    /// or an older cartog.
+    pub fn check_orphans(&self) -> Result<Orphans> {
+        let count = |sql: &str| -> Result<u32> { Ok(self.conn.query_row(sql, [], |r| r.get(0))?) };
+        let mut vectors = 0;
+        for table in self.stored_vector_tables()? {
+            vectors += count(&format!(
+                "SELECT COUNT(*) FROM {} WHERE rowid NOT IN ({LIVE_EMBEDDING_IDS})",
+                table.name()
+            ))?;
+        }
+        Ok(Orphans {
+            edges: count(
+                "SELECT COUNT(*) FROM edges e
+                 WHERE NOT EXISTS (SELECT 1 FROM symbols s WHERE s.id = e.source_id)",
+            )?,
+            contents: count(
+                "SELECT COUNT(*) FROM symbol_content c
+                 WHERE NOT EXISTS (SELECT 1 FROM symbols s WHERE s.id = c.symbol_id)",
+            )?,
+            embedding_ids: count(
+                "SELECT COUNT(*) FROM symbol_embedding_map em
+                 WHERE NOT EXISTS (SELECT 1 FROM symbols s WHERE s.id = em.symbol_id)",
+            )?,
+            vectors,
+        })
+    }
+
+    /// Delete the rows [`Self::check_orphans`] counts, returning how many went.
+    /// The delete trigger cannot find the symbol name of a deleted content row, so
+    /// rebuild the FTS index (see [`Self::rebuild_fts`]) after deleting contents.
+    pub fn delete_orphans(&self) -> Result<Orphans> {
+        self.in_transaction(|| {
+            let mut vectors = 0;
+            for table in self.stored_vector_tables()? {
+                vectors += self.conn.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE rowid NOT IN ({LIVE_EMBEDDING_IDS})",
+                        table.name()
+                    ),
+                    [],
+                )? as u32;
+            }
+            let delete = |sql: &str| -> Result<u32> { Ok(self.conn.execute(sql, [])? as u32) };
+            Ok(Orphans {
+                embedding_ids: delete(
+                    "DELETE FROM symbol_embedding_map
+                     WHERE symbol_id NOT IN (SELECT id FROM symbols)",
+                )?,
+                contents: delete(
+                    "DELETE FROM symbol_content WHERE symbol_id NOT IN (SELECT id FROM symbols)",
+                )?,
+                edges: delete("DELETE FROM edges WHERE source_id NOT IN (SELECT id FROM symbols)")?,
+                vectors,
+            })
+        })
+    }
+
+    // ── Maintenance ──
+
+    /// Size of the main database file, from its page count (the WAL excluded).
+    fn size_bytes(&self) -> Result<u64> {
+        let pages: u64 = self
+            .conn
+            .query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: u64 = self
+            .conn
+            .query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        Ok(pages * page_size)
+    }
+
+    /// Refresh query planner statistics and return free pages to the file system.
+    ///
+    /// Always runs `PRAGMA optimize` and an incremental vacuum (a no-op unless
+    /// `auto_vacuum` is `INCREMENTAL`, the default for databases created by this
+    /// version). `analyze` adds a full `ANALYZE` and merges the FTS5 index segments.
+    /// `vacuum` rebuilds the file with `VACUUM`, which also switches older databases
+    /// to incremental auto-vacuum. The WAL is checkpointed and truncated last.
+    pub fn maintain(&self, analyze: bool, vacuum: bool) -> Result<MaintenanceReport> {
+        let started = std::time::Instant::now();
+        let size_before = self.size_bytes()?;
+        if analyze {
+            self.conn.execute_batch(
+                "ANALYZE;
+                 INSERT INTO symbol_fts(symbol_fts) VALUES('optimize');",
+            )?;
+        }
+        self.conn.execute_batch("PRAGMA optimize;")?;
+        if vacuum {
+            self.conn
+                .execute_batch("PRAGMA auto_vacuum=INCREMENTAL; VACUUM;")
+                .context("VACUUM failed (is another process writing?)")?;
+        } else {
+            self.conn.execute_batch("PRAGMA incremental_vacuum;")?;
+        }
+        // Returns a (busy, log, checkpointed) row; an in-memory database has no WAL.
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        let size_after = self.size_bytes()?;
+        let _ = self.set_metadata(WRITES_SINCE_MAINTENANCE_KEY, "0");
+        Ok(MaintenanceReport {
+            databases: 1,
+            analyzed: analyze,
+            vacuumed: vacuum,
+            size_before,
+            size_after,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Count `files` re-indexed or removed towards the next automatic maintenance,
+    /// and run a light [`maintain`](Self::maintain) once [`AUTO_MAINTENANCE_FILES`]
+    /// have accumulated. Returns whether it ran.
+    pub fn record_writes(&self, files: u32) -> Result<bool> {
+        let pending = self
+            .get_metadata(WRITES_SINCE_MAINTENANCE_KEY)?
+            .and_then(|v| v.parse::<u32>().ok())
+            .unwrap_or(0)
+            .saturating_add(files);
+        if pending < AUTO_MAINTENANCE_FILES {
+            self.set_metadata(WRITES_SINCE_MAINTENANCE_KEY, &pending.to_string())?;
+            return Ok(false);
+        }
+        let report = self.maintain(false, false)?;
+        tracing::info!(
+            files = pending,
+            freed_bytes = report.size_before.saturating_sub(report.size_after),
+            duration_ms = report.duration_ms,
+            "ran automatic database maintenance"
+        );
+        Ok(true)
+    }
+
+    /// SQLite's `quick_check`: the problems found, empty when the file is sound.
+    pub fn quick_check(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("PRAGMA quick_check")?;
+        let rows = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows.into_iter().filter(|r| r != "ok").collect())
+    }
+
     // ── RAG: Embedding Map ──
 
     /// Get or create an integer ID for a symbol in the embedding map.
@@ -944,6 +2966,7 @@ impl Database {
     /// `embedding_id` is the integer key from `symbol_embedding_map`.
     /// `embedding` is a 384-dim f32 vector serialized as little-endian bytes.
     pub fn upsert_embedding(&self, embedding_id: i64, embedding: &[u8]) -> Result<()> {
+        self.require_vectors()?;
         // Delete existing entry if any (vec0 doesn't support REPLACE)
         self.conn.execute(
             "DELETE FROM symbol_vec WHERE rowid = ?1",
@@ -958,12 +2981,25 @@ impl Database {
 
     /// Insert multiple embeddings in a single transaction.
     pub fn insert_embeddings(&self, items: &[(i64, Vec<u8>)]) -> Result<()> {
+        self.insert_embeddings_into(VectorTable::Current, items)
+    }
+
+    /// Insert multiple embeddings into `table` in a single transaction.
+    pub fn insert_embeddings_into(
+        &self,
+        table: VectorTable,
+        items: &[(i64, Vec<u8>)],
+    ) -> Result<()> {
+        self.require_vectors()?;
+        let table = table.name();
         let tx = self.conn.unchecked_transaction()?;
         for (id, embedding) in items {
-            self.conn
-                .execute("DELETE FROM symbol_vec WHERE rowid = ?1", params![id])?;
             self.conn.execute(
-                "INSERT INTO symbol_vec (rowid, embedding) VALUES (?1, ?2)",
+                &format!("DELETE FROM {table} WHERE rowid = ?1"),
+                params![id],
+            )?;
+            self.conn.execute(
+                &format!("INSERT INTO {table} (rowid, embedding) VALUES (?1, ?2)"),
                 params![id, embedding],
             )?;
         }
@@ -975,13 +3011,25 @@ impl Database {
     ///
     /// Returns `(embedding_id, distance)` pairs ordered by distance (ascending).
     pub fn vector_search(&self, query_embedding: &[u8], limit: u32) -> Result<Vec<(i64, f64)>> {
-        let mut stmt = self.conn.prepare(
+        self.vector_search_in(VectorTable::Current, query_embedding, limit)
+    }
+
+    /// [`vector_search`](Self::vector_search) over the vectors of `table`.
+    pub fn vector_search_in(
+        &self,
+        table: VectorTable,
+        query_embedding: &[u8],
+        limit: u32,
+    ) -> Result<Vec<(i64, f64)>> {
+        self.require_vectors()?;
+        let mut stmt = self.conn.prepare(&format!(
             "SELECT rowid, distance
-             FROM symbol_vec
+             FROM {}
              WHERE embedding MATCH ?1
              ORDER BY distance
              LIMIT ?2",
-        )?;
+            table.name()
+        ))?;
         let rows = stmt
             .query_map(params![query_embedding, limit], |row| {
                 Ok((row.get(0)?, row.get(1)?))
@@ -999,8 +3047,23 @@ impl Database {
             })?)
     }
 
+    /// Count symbols eligible for embedding (content stored, variables excluded).
+    pub fn embeddable_symbol_count(&self) -> Result<u32> {
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) FROM symbol_content sc
+             JOIN symbols s ON s.id = sc.symbol_id
+             WHERE s.kind != ?1",
+            params![SymbolKind::Variable.as_str()],
+            |row| row.get(0),
+        )?)
+    }
+
     /// Check if a symbol already has an embedding.
     pub fn has_embedding(&self, symbol_id: &str) -> Result<bool> {
+        if !self.has_vectors() {
+            return Ok(false);
+        }
+        self.require_vectors()?;
         let map_id: Option<i64> = self
             .conn
             .query_row(
@@ -1024,31 +3087,104 @@ impl Database {
 
     /// Remove all RAG data (content, FTS, embeddings, embedding map) for symbols in a file.
     pub fn clear_rag_data_for_file(&self, file_path: &str) -> Result<()> {
-        // Delete embeddings via the map
-        self.conn.execute(
-            "DELETE FROM symbol_vec WHERE rowid IN
-             (SELECT em.id FROM symbol_embedding_map em
-              JOIN symbols s ON em.symbol_id = s.id
-              WHERE s.file_path = ?1)",
-            params![file_path],
-        )?;
-        // Delete embedding map entries
-        self.conn.execute(
-            "DELETE FROM symbol_embedding_map WHERE symbol_id IN
-             (SELECT id FROM symbols WHERE file_path = ?1)",
-            params![file_path],
-        )?;
+        self.clear_rag_data_keeping(file_path, &std::collections::HashSet::new())
+    }
+
+    /// [`Self::clear_rag_data_for_file`], but keep the embeddings of the symbols in `keep`.
+    fn clear_rag_data_keeping(
+        &self,
+        file_path: &str,
+        keep: &std::collections::HashSet<&str>,
+    ) -> Result<()> {
+        let dropped: Vec<String> = self
+            .conn
+            .prepare("SELECT id FROM symbols WHERE file_path = ?1")?
+            .query_map(params![file_path], |row| row.get::<_, String>(0))?
+            .filter(|id| id.as_ref().map_or(true, |id| !keep.contains(id.as_str())))
+            .collect::<std::result::Result<_, _>>()?;
+        let tables = self.stored_vector_tables()?;
+        for symbol_id in &dropped {
+            // Delete embeddings via the map, then the map entries
+            for table in &tables {
+                self.conn.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE rowid IN
+                         (SELECT id FROM symbol_embedding_map WHERE symbol_id = ?1)",
+                        table.name()
+                    ),
+                    params![symbol_id],
+                )?;
+            }
+            self.conn.execute(
+                "DELETE FROM symbol_embedding_map WHERE symbol_id = ?1",
+                params![symbol_id],
+            )?;
+        }
         // Delete content (triggers will clean up FTS)
         self.clear_symbol_content_for_file(file_path)?;
         Ok(())
     }
 
+    /// Stored symbols of a file with the hash of their content, for rename detection.
+    pub fn symbol_bodies(&self, file_path: &str) -> Result<Vec<SymbolBody>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.id, s.name, s.kind, c.content
+             FROM symbols s JOIN symbol_content c ON c.symbol_id = s.id
+             WHERE s.file_path = ?1",
+        )?;
+        let rows = stmt
+            .query_map(params![file_path], |row| {
+                let id: String = row.get(0)?;
+                let name: String = row.get(1)?;
+                let kind: String = row.get(2)?;
+                let content: String = row.get(3)?;
+                Ok(SymbolBody::new(
+                    &id,
+                    &name,
+                    kind.parse().unwrap_or(SymbolKind::Variable),
+                    &content,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Move the embeddings, scratch notes, reservations and pins of renamed symbols of a file to
+    /// their new IDs and names. Runs before [`Self::clear_file_data`], which would
+    /// otherwise drop the embeddings with the old symbols.
+    pub fn carry_over_renames(&self, file_path: &str, renames: &[Rename]) -> Result<()> {
+        for rename in renames {
+            self.conn.execute(
+                "UPDATE OR IGNORE symbol_embedding_map SET symbol_id = ?2 WHERE symbol_id = ?1",
+                params![rename.old_id, rename.new_id],
+            )?;
+            if !rename.moves_name {
+                continue;
+            }
+            self.conn.execute(
+                "UPDATE notes SET symbol_name = ?3 WHERE symbol_name = ?2 AND file_path = ?1",
+                params![file_path, rename.old_name, rename.new_name],
+            )?;
+            self.conn.execute(
+                "UPDATE OR IGNORE pins SET name = ?3 WHERE name = ?2 AND file_path = ?1",
+                params![file_path, rename.old_name, rename.new_name],
+            )?;
+            self.conn.execute(
+                "UPDATE OR IGNORE reservations SET symbol_name = ?3
+                 WHERE symbol_name = ?2 AND file_path = ?1",
+                params![file_path, rename.old_name, rename.new_name],
+            )?;
+        }
+        Ok(())
+    }
+
     /// Get a symbol by its ID.
     pub fn get_symbol(&self, id: &str) -> Result<Option<Symbol>> {
         self.conn
             .query_row(
                 "SELECT id, name, kind, file_path, start_line, end_line, start_byte, end_byte,
-                        parent_id, signature, visibility, is_async, docstring
+                        parent_id, signature, visibility, is_async, docstring, entrypoint,
+                        db_table, generation
                  FROM symbols WHERE id = ?1",
                 params![id],
                 row_to_symbol,
@@ -1075,16 +3211,24 @@ impl Database {
     ///
     /// Variables are excluded — they are too numerous and low-signal for embedding.
     pub fn symbols_needing_embeddings(&self) -> Result<Vec<String>> {
-        let mut stmt = self.conn.prepare(
+        self.symbols_needing_embeddings_in(VectorTable::Current)
+    }
+
+    /// [`symbols_needing_embeddings`](Self::symbols_needing_embeddings) for the
+    /// vectors of `table`.
+    pub fn symbols_needing_embeddings_in(&self, table: VectorTable) -> Result<Vec<String>> {
+        self.require_vectors()?;
+        let mut stmt = self.conn.prepare(&format!(
             "SELECT sc.symbol_id FROM symbol_content sc
              JOIN symbols s ON s.id = sc.symbol_id
              WHERE s.kind != ?1
              AND NOT EXISTS (
                  SELECT 1 FROM symbol_embedding_map em
-                 JOIN symbol_vec sv ON sv.rowid = em.id
+                 JOIN {} sv ON sv.rowid = em.id
                  WHERE em.symbol_id = sc.symbol_id
              )",
-        )?;
+            table.name()
+        ))?;
         let rows = stmt
             .query_map(params![SymbolKind::Variable.as_str()], |row| row.get(0))?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -1114,268 +3258,2971 @@ impl Database {
 
     /// Clear all embedding data (for force re-embed).
     pub fn clear_all_embeddings(&self) -> Result<()> {
-        self.conn.execute("DELETE FROM symbol_vec", [])?;
+        for table in self.stored_vector_tables()? {
+            self.conn
+                .execute(&format!("DELETE FROM {}", table.name()), [])?;
+        }
         self.conn.execute("DELETE FROM symbol_embedding_map", [])?;
         Ok(())
     }
-}
 
-#[derive(Debug, Clone, Serialize)]
-pub struct IndexStats {
-    pub num_files: u32,
-    pub num_symbols: u32,
-    pub num_edges: u32,
-    pub num_resolved: u32,
-    pub languages: Vec<(String, u32)>,
-    pub symbol_kinds: Vec<(String, u32)>,
-}
+    // ── RAG: Embedding Model Transition ──
 
-// ── Row Mapping Helpers ──
+    /// Name of the model the current vectors come from (`None` for the default model).
+    pub fn embedding_model(&self) -> Result<Option<String>> {
+        self.get_metadata(EMBEDDING_MODEL_KEY)
+    }
 
-fn row_to_symbol(row: &rusqlite::Row<'_>) -> rusqlite::Result<Symbol> {
-    row_to_symbol_offset(row, 0)
-}
+    /// Name of the model being migrated to, while its vectors fill `symbol_vec_next`.
+    pub fn next_embedding_model(&self) -> Result<Option<String>> {
+        self.get_metadata(EMBEDDING_MODEL_NEXT_KEY)
+    }
 
-fn row_to_symbol_offset(row: &rusqlite::Row<'_>, off: usize) -> rusqlite::Result<Symbol> {
-    let kind_str = row.get::<_, String>(off + 2)?;
-    let kind = kind_str.parse().unwrap_or_else(|_| {
-        warn!(kind = %kind_str, "unknown symbol kind, defaulting to variable");
-        SymbolKind::Variable
-    });
+    /// Embedding policy recorded by the last `serve --rag` or `watch --rag`.
+    pub fn embed_policy(&self) -> Result<Option<String>> {
+        self.get_metadata(EMBED_POLICY_KEY)
+    }
 
-    let vis_str = row.get::<_, Option<String>>(off + 10)?.unwrap_or_default();
+    /// Record the embedding policy serving this index.
+    pub fn set_embed_policy(&self, policy: &str) -> Result<()> {
+        self.set_metadata(EMBED_POLICY_KEY, policy)
+    }
 
-    Ok(Symbol {
-        id: row.get(off)?,
-        name: row.get(off + 1)?,
+    /// [`vector_tables`](Self::vector_tables) to delete vectors from: none without
+    /// sqlite-vec, whose tables can't be written then (their map entries still go).
+    fn stored_vector_tables(&self) -> Result<Vec<VectorTable>> {
+        if !self.has_vectors() {
+            return Ok(Vec::new());
+        }
+        self.require_vectors()?;
+        self.vector_tables()
+    }
+
+    /// The vector tables in use: the current one, and the next one during a transition.
+    fn vector_tables(&self) -> Result<Vec<VectorTable>> {
+        Ok(if self.next_embedding_model()?.is_some() {
+            vec![VectorTable::Current, VectorTable::Next]
+        } else {
+            vec![VectorTable::Current]
+        })
+    }
+
+    /// Start filling vectors of `model` (`dim` floats each) next to the current ones.
+    ///
+    /// Replaces any transition in progress, dropping its vectors. The embedding map
+    /// is shared, so a symbol has the same rowid in both tables.
+    pub fn start_embedding_transition(&self, model: &str, dim: usize) -> Result<()> {
+        self.require_vectors()?;
+        self.in_transaction(|| {
+            self.conn.execute_batch(&format!(
+                "DROP TABLE IF EXISTS symbol_vec_next;
+                 CREATE VIRTUAL TABLE symbol_vec_next USING vec0(embedding float[{dim}]);"
+            ))?;
+            self.set_metadata(EMBEDDING_MODEL_NEXT_KEY, model)
+        })
+    }
+
+    /// Drop the vectors of the transition in progress, keeping the current model.
+    pub fn cancel_embedding_transition(&self) -> Result<()> {
+        self.in_transaction(|| {
+            self.conn
+                .execute_batch("DROP TABLE IF EXISTS symbol_vec_next")?;
+            self.conn.execute(
+                "DELETE FROM metadata WHERE key = ?1",
+                params![EMBEDDING_MODEL_NEXT_KEY],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Make the next model current: its vectors (`dim` floats each) replace the
+    /// current table's, which is dropped.
+    pub fn finish_embedding_transition(&self, dim: usize) -> Result<()> {
+        let Some(next) = self.next_embedding_model()? else {
+            anyhow::bail!("no embedding model transition in progress");
+        };
+        self.require_vectors()?;
+        self.in_transaction(|| {
+            self.conn.execute_batch(&format!(
+                "DROP TABLE symbol_vec;
+                 CREATE VIRTUAL TABLE symbol_vec USING vec0(embedding float[{dim}]);
+                 INSERT INTO symbol_vec (rowid, embedding)
+                     SELECT rowid, embedding FROM symbol_vec_next;
+                 DROP TABLE symbol_vec_next;"
+            ))?;
+            self.set_metadata(EMBEDDING_MODEL_KEY, &next)?;
+            self.conn.execute(
+                "DELETE FROM metadata WHERE key = ?1",
+                params![EMBEDDING_MODEL_NEXT_KEY],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Number of vectors stored for the next model (0 outside a transition).
+    pub fn next_embedding_count(&self) -> Result<u32> {
+        if self.next_embedding_model()?.is_none() {
+            return Ok(0);
+        }
+        self.require_vectors()?;
+        Ok(self
+            .conn
+            .query_row("SELECT COUNT(*) FROM symbol_vec_next", [], |row| row.get(0))?)
+    }
+
+    // ── Anchors ──
+
+    /// Batch insert anchor comments in a single transaction.
+    pub fn insert_anchors(&self, anchors: &[Anchor]) -> Result<()> {
+        self.in_transaction(|| {
+            let mut stmt = self.conn.prepare_cached(
+                "INSERT INTO anchors (tag, text, file_path, line, symbol_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for a in anchors {
+                stmt.execute(params![a.tag, a.text, a.file_path, a.line, a.symbol_id])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Anchor comments, ordered by file and line.
+    ///
+    /// `path` is a prefix filter on file paths (a file or a directory);
+    /// `tag` is matched case-insensitively; `text` is a case-insensitive substring filter.
+    pub fn anchors(
+        &self,
+        path: Option<&str>,
+        tag: Option<&str>,
+        text: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<Anchor>> {
+        let escape = |s: &str| {
+            s.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        };
+        let path_pattern = path.map(|p| format!("{}%", escape(p)));
+        let text_pattern = text.map(|t| format!("%{}%", escape(t)));
+        let mut stmt = self.conn.prepare(
+            "SELECT a.tag, a.text, a.file_path, a.line, a.symbol_id, s.name
+             FROM anchors a LEFT JOIN symbols s ON s.id = a.symbol_id
+             WHERE (?1 IS NULL OR a.file_path LIKE ?1 ESCAPE '\\')
+               AND (?2 IS NULL OR a.tag = UPPER(?2))
+               AND (?3 IS NULL OR casefold(a.text) LIKE casefold(?3) ESCAPE '\\')
+             ORDER BY a.file_path, a.line
+             LIMIT ?4",
+        )?;
+        let rows = stmt
+            .query_map(params![path_pattern, tag, text_pattern, limit], |row| {
+                Ok(Anchor {
+                    tag: row.get(0)?,
+                    text: row.get(1)?,
+                    file_path: row.get(2)?,
+                    line: row.get(3)?,
+                    symbol_id: row.get(4)?,
+                    symbol_name: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    // ── Injections ──
+
+    /// Whether indexing scans string literals for injected languages.
+    pub fn scan_injections(&self) -> Result<bool> {
+        Ok(self.get_metadata(SCAN_INJECTIONS_KEY)?.as_deref() == Some("1"))
+    }
+
+    /// Turn string literal scanning on or off. Turning it off drops the stored
+    /// injections; files already indexed are only scanned when they are re-indexed.
+    pub fn set_scan_injections(&self, on: bool) -> Result<()> {
+        if on {
+            return self.set_metadata(SCAN_INJECTIONS_KEY, "1");
+        }
+        self.in_transaction(|| {
+            self.conn.execute(
+                "DELETE FROM metadata WHERE key = ?1",
+                params![SCAN_INJECTIONS_KEY],
+            )?;
+            self.conn.execute("DELETE FROM injections", [])?;
+            Ok(())
+        })
+    }
+
+    /// Batch insert injected-language literals in a single transaction.
+    pub fn insert_injections(&self, injections: &[Injection]) -> Result<()> {
+        self.in_transaction(|| {
+            let mut stmt = self.conn.prepare_cached(
+                "INSERT INTO injections (language, text, file_path, line, symbol_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for i in injections {
+                stmt.execute(params![
+                    i.language,
+                    i.text,
+                    i.file_path,
+                    i.line,
+                    i.symbol_id
+                ])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Store the import aliases of a file (see [`ImportAlias`]).
+    pub fn insert_import_aliases(&self, aliases: &[ImportAlias]) -> Result<()> {
+        self.in_transaction(|| {
+            let mut stmt = self.conn.prepare_cached(
+                "INSERT INTO import_aliases (file_path, alias, name, line)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for a in aliases {
+                stmt.execute(params![a.file_path, a.alias, a.name, a.line])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Import aliases of a file, in line order.
+    pub fn import_aliases(&self, file_path: &str) -> Result<Vec<ImportAlias>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT file_path, alias, name, line FROM import_aliases
+             WHERE file_path = ?1 ORDER BY line, rowid",
+        )?;
+        let aliases = stmt
+            .query_map(params![file_path], |row| {
+                Ok(ImportAlias {
+                    file_path: row.get(0)?,
+                    alias: row.get(1)?,
+                    name: row.get(2)?,
+                    line: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(aliases)
+    }
+
+    /// Injected-language literals containing every word of `query`
+    /// (case-insensitive), ordered by file and line.
+    ///
+    /// `language` is `sql`, `html` or `regex`; `path` is a prefix filter on file paths.
+    pub fn injections(
+        &self,
+        query: Option<&str>,
+        language: Option<&str>,
+        path: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<Injection>> {
+        let escape = |s: &str| {
+            s.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        };
+        let path_pattern = path.map(|p| format!("{}%", escape(p)));
+        let mut sql = String::from(
+            "SELECT i.language, i.text, i.file_path, i.line, i.symbol_id, s.name
+             FROM injections i LEFT JOIN symbols s ON s.id = i.symbol_id
+             WHERE (?1 IS NULL OR i.file_path LIKE ?1 ESCAPE '\\')
+               AND (?2 IS NULL OR i.language = LOWER(?2))",
+        );
+        let words: Vec<String> = query
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|w| format!("%{}%", escape(w)))
+            .collect();
+        for n in 0..words.len() {
+            sql.push_str(&format!(
+                " AND casefold(i.text) LIKE casefold(?{}) ESCAPE '\\'",
+                n + 4
+            ));
+        }
+        sql.push_str(" ORDER BY i.file_path, i.line LIMIT ?3");
+
+        let mut values: Vec<Box<dyn rusqlite::types::ToSql>> = vec![
+            Box::new(path_pattern),
+            Box::new(language.map(str::to_string)),
+            Box::new(limit),
+        ];
+        values.extend(
+            words
+                .into_iter()
+                .map(|w| Box::new(w) as Box<dyn rusqlite::types::ToSql>),
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(values.iter()), |row| {
+                Ok(Injection {
+                    language: row.get(0)?,
+                    text: row.get(1)?,
+                    file_path: row.get(2)?,
+                    line: row.get(3)?,
+                    symbol_id: row.get(4)?,
+                    symbol_name: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Whether the stored symbol IDs are stable ones. Indexes built before stable
+    /// IDs (with IDs embedding the line) need a full re-index; empty ones do not.
+    pub fn has_stable_ids(&self) -> Result<bool> {
+        if self.get_metadata(STABLE_IDS_KEY)?.is_some() {
+            return Ok(true);
+        }
+        let any_file: bool =
+            self.conn
+                .query_row("SELECT EXISTS(SELECT 1 FROM files)", [], |row| row.get(0))?;
+        Ok(!any_file)
+    }
+
+    /// Record that every stored symbol ID is a stable one.
+    pub fn set_stable_ids(&self) -> Result<()> {
+        self.set_metadata(STABLE_IDS_KEY, "1")
+    }
+
+    // ── Parse cache ──
+
+    /// Whether indexing caches extractions (see [`Self::cached_extraction`]).
+    pub fn parse_cache(&self) -> Result<bool> {
+        Ok(self.get_metadata(PARSE_CACHE_KEY)?.as_deref() == Some("1"))
+    }
+
+    /// Turn the extraction cache on or off. Turning it off drops the cached entries.
+    pub fn set_parse_cache(&self, on: bool) -> Result<()> {
+        if on {
+            return self.set_metadata(PARSE_CACHE_KEY, "1");
+        }
+        self.in_transaction(|| {
+            self.conn.execute(
+                "DELETE FROM metadata WHERE key = ?1",
+                params![PARSE_CACHE_KEY],
+            )?;
+            self.conn.execute("DELETE FROM parse_cache", [])?;
+            Ok(())
+        })
+    }
+
+    /// Extraction stored for `file_path` with content `hash`, parsed with `grammar`
+    /// (a grammar fingerprint and cartog version). Marks the entry as used at `now`.
+    pub fn cached_extraction(
+        &self,
+        file_path: &str,
+        hash: &str,
+        grammar: &str,
+        now: f64,
+    ) -> Result<Option<String>> {
+        let extraction: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT extraction FROM parse_cache
+                 WHERE file_path = ?1 AND hash = ?2 AND grammar = ?3",
+                params![file_path, hash, grammar],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if extraction.is_some() {
+            self.conn.execute(
+                "UPDATE parse_cache SET used = ?3 WHERE file_path = ?1 AND hash = ?2",
+                params![file_path, hash, now],
+            )?;
+        }
+        Ok(extraction)
+    }
+
+    /// Cache the serialized `extraction` of `file_path` with content `hash`, keeping
+    /// the file's [`PARSE_CACHE_PER_FILE`] most recently used entries.
+    pub fn store_extraction(
+        &self,
+        file_path: &str,
+        hash: &str,
+        grammar: &str,
+        extraction: &str,
+        now: f64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO parse_cache (file_path, hash, grammar, extraction, used)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![file_path, hash, grammar, extraction, now],
+        )?;
+        self.conn.execute(
+            "DELETE FROM parse_cache WHERE file_path = ?1 AND hash NOT IN
+             (SELECT hash FROM parse_cache WHERE file_path = ?1 ORDER BY used DESC LIMIT ?2)",
+            params![file_path, PARSE_CACHE_PER_FILE],
+        )?;
+        Ok(())
+    }
+
+    /// Grammar fingerprints per language recorded by the last full index, if any.
+    pub fn grammar_versions(&self) -> Result<Option<std::collections::BTreeMap<String, String>>> {
+        self.get_metadata(GRAMMAR_VERSIONS_KEY)?
+            .map(|json| serde_json::from_str(&json).context("invalid stored grammar versions"))
+            .transpose()
+    }
+
+    /// Record the grammar fingerprints the index is built with.
+    pub fn set_grammar_versions(
+        &self,
+        versions: &std::collections::BTreeMap<String, String>,
+    ) -> Result<()> {
+        self.set_metadata(GRAMMAR_VERSIONS_KEY, &serde_json::to_string(versions)?)
+    }
+
+    /// Fingerprint of the tree the last full index was built from, if recorded.
+    pub fn repo_fingerprint(&self) -> Result<Option<RepoFingerprint>> {
+        self.get_metadata(REPO_FINGERPRINT_KEY)?
+            .map(|json| serde_json::from_str(&json).context("invalid stored repo fingerprint"))
+            .transpose()
+    }
+
+    /// Record the tree the index was built from; `None` clears it.
+    pub fn set_repo_fingerprint(&self, fingerprint: Option<&RepoFingerprint>) -> Result<()> {
+        match fingerprint {
+            Some(f) => self.set_metadata(REPO_FINGERPRINT_KEY, &serde_json::to_string(f)?),
+            None => {
+                self.conn.execute(
+                    "DELETE FROM metadata WHERE key = ?1",
+                    params![REPO_FINGERPRINT_KEY],
+                )?;
+                Ok(())
+            }
+        }
+    }
+
+    // ── Scratch notes ──
+
+    /// Attach a scratch note to `symbol`, kept until `now + ttl_secs` (unix seconds).
+    /// Notes are keyed by symbol name and file, so they survive re-indexing
+    /// when the symbol moves within its file. Expired notes are pruned here.
+    pub fn add_note(&self, symbol: &Symbol, text: &str, now: f64, ttl_secs: f64) -> Result<Note> {
+        self.conn
+            .execute("DELETE FROM notes WHERE expires <= ?1", params![now])?;
+        self.conn.execute(
+            "INSERT INTO notes (symbol_name, file_path, text, created, expires)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![symbol.name, symbol.file_path, text, now, now + ttl_secs],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        let note = self.conn.query_row(
+            &format!("{NOTE_COLUMNS} WHERE id = ?1"),
+            params![id],
+            row_to_note,
+        )?;
+        Ok(note)
+    }
+
+    /// Notes that have not expired at `now`, oldest first, optionally for one symbol name.
+    pub fn notes(&self, name: Option<&str>, now: f64) -> Result<Vec<Note>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "{NOTE_COLUMNS} WHERE expires > ?1 AND (?2 IS NULL OR symbol_name = ?2) ORDER BY id"
+        ))?;
+        let rows = stmt
+            .query_map(params![now, name], row_to_note)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Non-empty docstring of the symbol `id`.
+    pub fn docstring(&self, id: &str) -> Result<Option<String>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT docstring FROM symbols WHERE id = ?1 AND docstring != ''")?;
+        let doc = stmt
+            .query_row(params![id], |row| row.get(0))
+            .optional()?
+            .flatten();
+        Ok(doc)
+    }
+
+    /// Fill in [`SymbolMatch::notes`] from the notes live at `now`.
+    pub fn attach_notes(&self, matches: &mut [SymbolMatch], now: f64) -> Result<()> {
+        let mut by_symbol: std::collections::HashMap<(String, String), Vec<Note>> =
+            std::collections::HashMap::new();
+        for note in self.notes(None, now)? {
+            by_symbol
+                .entry((note.symbol_name.clone(), note.file_path.clone()))
+                .or_default()
+                .push(note);
+        }
+        if by_symbol.is_empty() {
+            return Ok(());
+        }
+        for m in matches {
+            let key = (m.symbol.name.clone(), m.symbol.file_path.clone());
+            if let Some(notes) = by_symbol.get(&key) {
+                m.notes = notes.clone();
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete all scratch notes. Returns the number removed.
+    pub fn clear_notes(&self) -> Result<usize> {
+        Ok(self.conn.execute("DELETE FROM notes", [])?)
+    }
+
+    // ── Symbol reservations ──
+
+    /// Reserve `symbols` for `owner` until `now + ttl_secs` (unix seconds). A symbol
+    /// already reserved by another owner is left to them and reported as a conflict;
+    /// reserving it again as the same owner extends the reservation. Purely advisory:
+    /// nothing stops an agent from editing a reserved symbol.
+    pub fn reserve_symbols(
+        &self,
+        symbols: &[Symbol],
+        owner: &str,
+        reason: &str,
+        now: f64,
+        ttl_secs: f64,
+    ) -> Result<ReserveResult> {
+        self.conn
+            .execute("DELETE FROM reservations WHERE expires <= ?1", params![now])?;
+        let mut result = ReserveResult::default();
+        for symbol in symbols {
+            // One statement, so two agents reserving at once cannot both win.
+            self.conn.execute(
+                "INSERT INTO reservations (symbol_name, file_path, owner, reason, created, expires)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (symbol_name, file_path) DO UPDATE
+                 SET reason = excluded.reason, expires = excluded.expires
+                 WHERE owner = excluded.owner",
+                params![
+                    symbol.name,
+                    symbol.file_path,
+                    owner,
+                    reason,
+                    now,
+                    now + ttl_secs
+                ],
+            )?;
+            let reservation = self.conn.query_row(
+                &format!("{RESERVATION_COLUMNS} WHERE symbol_name = ?1 AND file_path = ?2"),
+                params![symbol.name, symbol.file_path],
+                row_to_reservation,
+            )?;
+            if reservation.owner == owner {
+                result.reserved.push(reservation);
+            } else {
+                result.conflicts.push(reservation);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Release the reservations of `owner`, only those on symbols named in `names`
+    /// when given. Returns the number released.
+    pub fn release_symbols(&self, owner: &str, names: Option<&[String]>) -> Result<usize> {
+        let Some(names) = names else {
+            return Ok(self
+                .conn
+                .execute("DELETE FROM reservations WHERE owner = ?1", params![owner])?);
+        };
+        let mut released = 0;
+        for name in names {
+            released += self.conn.execute(
+                "DELETE FROM reservations WHERE owner = ?1 AND symbol_name = ?2",
+                params![owner, name],
+            )?;
+        }
+        Ok(released)
+    }
+
+    /// Reservations live at `now`, oldest first, optionally of one owner.
+    pub fn reservations(&self, owner: Option<&str>, now: f64) -> Result<Vec<Reservation>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "{RESERVATION_COLUMNS} WHERE expires > ?1 AND (?2 IS NULL OR owner = ?2)
+             ORDER BY created, symbol_name, file_path"
+        ))?;
+        let rows = stmt
+            .query_map(params![now, owner], row_to_reservation)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Delete all reservations. Returns the number removed.
+    pub fn clear_reservations(&self) -> Result<usize> {
+        Ok(self.conn.execute("DELETE FROM reservations", [])?)
+    }
+
+    // ── Build-system packages ──
+
+    /// Replace the stored package graph with `packages` (from `cartog packages sync`).
+    pub fn replace_packages(&self, packages: &[Package]) -> Result<()> {
+        self.in_transaction(|| {
+            self.conn
+                .execute_batch("DELETE FROM package_deps; DELETE FROM packages;")?;
+            let mut insert_package = self
+                .conn
+                .prepare_cached("INSERT INTO packages (name, root, tool) VALUES (?1, ?2, ?3)")?;
+            let mut insert_dep = self.conn.prepare_cached(
+                "INSERT OR IGNORE INTO package_deps (package, dependency) VALUES (?1, ?2)",
+            )?;
+            for package in packages {
+                insert_package.execute(params![
+                    package.name,
+                    package.root,
+                    package.tool.to_string()
+                ])?;
+                for dep in &package.dependencies {
+                    insert_dep.execute(params![package.name, dep])?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Stored packages with their declared dependencies, by name.
+    pub fn packages(&self) -> Result<Vec<Package>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT name, root, tool FROM packages ORDER BY name")?;
+        let mut packages = stmt
+            .query_map([], |row| {
+                let tool: String = row.get(2)?;
+                Ok(Package {
+                    name: row.get(0)?,
+                    root: row.get(1)?,
+                    tool: tool.parse().unwrap_or_else(|_| {
+                        warn!(tool = %tool, "unknown build tool, defaulting to cargo");
+                        BuildTool::Cargo
+                    }),
+                    dependencies: Vec::new(),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut stmt = self
+            .conn
+            .prepare("SELECT package, dependency FROM package_deps ORDER BY package, dependency")?;
+        let deps = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        for (package, dep) in deps {
+            if let Ok(i) = packages.binary_search_by(|p| p.name.as_str().cmp(&package)) {
+                packages[i].dependencies.push(dep);
+            }
+        }
+        Ok(packages)
+    }
+
+    // ── Audit log ──
+
+    /// Append one MCP tool call to the audit log. `entry.time` is ignored.
+    pub fn insert_audit_entry(&self, entry: &AuditEntry) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO audit_log (ts, tool, params, result_count, duration_ms, ok)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                entry.timestamp,
+                entry.tool,
+                entry.params,
+                entry.result_count,
+                entry.duration_ms,
+                entry.ok,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The `limit` most recent audit entries, oldest first, optionally for one tool.
+    pub fn audit_tail(&self, limit: u32, tool: Option<&str>) -> Result<Vec<AuditEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT ts, strftime('%Y-%m-%dT%H:%M:%SZ', ts, 'unixepoch'),
+                    tool, params, result_count, duration_ms, ok
+             FROM audit_log
+             WHERE ?1 IS NULL OR tool = ?1
+             ORDER BY id DESC LIMIT ?2",
+        )?;
+        let mut rows = stmt
+            .query_map(params![tool, limit], |row| {
+                Ok(AuditEntry {
+                    timestamp: row.get(0)?,
+                    time: row.get(1)?,
+                    tool: row.get(2)?,
+                    params: row.get(3)?,
+                    result_count: row.get(4)?,
+                    duration_ms: row.get(5)?,
+                    ok: row.get(6)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        rows.reverse();
+        Ok(rows)
+    }
+
+    /// Record the symbols one audited tool call returned, once each.
+    pub fn insert_symbol_hits(&self, timestamp: f64, tool: &str, hits: &[SymbolHit]) -> Result<()> {
+        if hits.is_empty() {
+            return Ok(());
+        }
+        self.in_transaction(|| {
+            let mut stmt = self.conn.prepare_cached(
+                "INSERT INTO symbol_hits (ts, tool, name, file_path, kind)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for hit in hits {
+                stmt.execute(params![
+                    timestamp,
+                    tool,
+                    hit.name,
+                    hit.file_path,
+                    hit.kind.as_str()
+                ])?;
+            }
+            Ok(())
+        })
+    }
+
+    /// The `limit` symbols returned by the most audited tool calls since `since`
+    /// (unix seconds), most hits first, ties by name and file.
+    pub fn hot_symbols(&self, limit: u32, since: Option<f64>) -> Result<Vec<HotSymbol>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT name, file_path, kind, COUNT(*), GROUP_CONCAT(DISTINCT tool),
+                    strftime('%Y-%m-%dT%H:%M:%SZ', MAX(ts), 'unixepoch')
+             FROM symbol_hits
+             WHERE ?1 IS NULL OR ts >= ?1
+             GROUP BY name, file_path, kind
+             ORDER BY COUNT(*) DESC, name, file_path
+             LIMIT ?2",
+        )?;
+        let rows = stmt
+            .query_map(params![since, limit], |row| {
+                let kind: String = row.get(2)?;
+                let tools: String = row.get(4)?;
+                let mut tools: Vec<String> = tools.split(',').map(str::to_string).collect();
+                tools.sort();
+                Ok(HotSymbol {
+                    name: row.get(0)?,
+                    file_path: row.get(1)?,
+                    kind: kind.parse().unwrap_or(SymbolKind::Variable),
+                    hits: row.get(3)?,
+                    tools,
+                    last_hit: row.get(5)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Audit entries since `since` (unix seconds), oldest first, each with the
+    /// symbols the call returned in the order it returned them.
+    pub fn audit_calls(&self, since: Option<f64>) -> Result<Vec<AuditCall>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT ts, strftime('%Y-%m-%dT%H:%M:%SZ', ts, 'unixepoch'),
+                    tool, params, result_count, duration_ms, ok
+             FROM audit_log
+             WHERE ?1 IS NULL OR ts >= ?1
+             ORDER BY id",
+        )?;
+        let mut calls = stmt
+            .query_map(params![since], |row| {
+                Ok(AuditCall {
+                    entry: AuditEntry {
+                        timestamp: row.get(0)?,
+                        time: row.get(1)?,
+                        tool: row.get(2)?,
+                        params: row.get(3)?,
+                        result_count: row.get(4)?,
+                        duration_ms: row.get(5)?,
+                        ok: row.get(6)?,
+                    },
+                    hits: Vec::new(),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        // Hits carry no entry ID: they belong to the call with the same time and tool.
+        let mut by_call: std::collections::HashMap<(u64, String), usize> =
+            std::collections::HashMap::new();
+        for (i, call) in calls.iter().enumerate() {
+            by_call.insert((call.entry.timestamp.to_bits(), call.entry.tool.clone()), i);
+        }
+        let mut stmt = self.conn.prepare(
+            "SELECT ts, tool, name, file_path, kind FROM symbol_hits
+             WHERE ?1 IS NULL OR ts >= ?1
+             ORDER BY id",
+        )?;
+        let mut rows = stmt.query(params![since])?;
+        while let Some(row) = rows.next()? {
+            let ts: f64 = row.get(0)?;
+            let tool: String = row.get(1)?;
+            let kind: String = row.get(4)?;
+            if let Some(&i) = by_call.get(&(ts.to_bits(), tool)) {
+                calls[i].hits.push(SymbolHit {
+                    name: row.get(2)?,
+                    file_path: row.get(3)?,
+                    kind: kind.parse().unwrap_or(SymbolKind::Variable),
+                });
+            }
+        }
+        Ok(calls)
+    }
+
+    /// Delete audit entries and symbol hits recorded before `cutoff` (unix seconds).
+    /// Returns the number of audit entries removed.
+    pub fn prune_audit_log(&self, cutoff: f64) -> Result<usize> {
+        self.conn
+            .execute("DELETE FROM symbol_hits WHERE ts < ?1", params![cutoff])?;
+        Ok(self
+            .conn
+            .execute("DELETE FROM audit_log WHERE ts < ?1", params![cutoff])?)
+    }
+
+    /// Record a background index job (see [`crate::jobs`]) started at `started`
+    /// with `params` (a JSON object string). Returns its ID.
+    pub fn insert_job(&self, params: &str, phase: &str, started: f64) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO jobs (params, status, phase, started) VALUES (?1, ?2, ?3, ?4)",
+            params![params, JobStatus::Running.as_str(), phase, started],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Save the phase and progress of a running job.
+    pub fn update_job_progress(&self, id: i64, phase: &str, done: u32, total: u32) -> Result<()> {
+        self.conn.execute(
+            "UPDATE jobs SET phase = ?2, done = ?3, total = ?4 WHERE id = ?1",
+            params![id, phase, done, total],
+        )?;
+        Ok(())
+    }
+
+    /// Record how a job ended: its result (a JSON string) or error.
+    pub fn finish_job(
+        &self,
+        id: i64,
+        status: JobStatus,
+        result: Option<&str>,
+        error: Option<&str>,
+        finished: f64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE jobs SET status = ?2, result = ?3, error = ?4, finished = ?5 WHERE id = ?1",
+            params![id, status.as_str(), result, error, finished],
+        )?;
+        Ok(())
+    }
+
+    /// Mark jobs still running as interrupted, for a server starting after one that
+    /// stopped mid-job. Returns how many there were.
+    pub fn interrupt_running_jobs(&self, finished: f64) -> Result<usize> {
+        Ok(self.conn.execute(
+            "UPDATE jobs SET status = ?1, finished = ?2 WHERE status = ?3",
+            params![
+                JobStatus::Interrupted.as_str(),
+                finished,
+                JobStatus::Running.as_str()
+            ],
+        )?)
+    }
+
+    /// The job with ID `id`, if any.
+    pub fn get_job(&self, id: i64) -> Result<Option<Job>> {
+        let mut stmt = self.conn.prepare(&format!("{JOB_COLUMNS} WHERE id = ?1"))?;
+        Ok(stmt.query_row(params![id], row_to_job).optional()?)
+    }
+
+    /// The `limit` most recent jobs, newest first.
+    pub fn recent_jobs(&self, limit: u32) -> Result<Vec<Job>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("{JOB_COLUMNS} ORDER BY id DESC LIMIT ?1"))?;
+        let rows = stmt
+            .query_map(params![limit], row_to_job)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// All rows of `table` for `cartog export`, typed per [`ExportTable::columns`].
+    ///
+    /// Edges are joined to their source symbol and, when resolved, to their target.
+    pub fn export_rows(&self, table: ExportTable) -> Result<Vec<Vec<Cell>>> {
+        let sql = match table {
+            ExportTable::Symbols => {
+                "SELECT id, name, kind, file_path, start_line, end_line, start_byte, end_byte,
+                        parent_id, signature, visibility, is_async, docstring, entrypoint,
+                        db_table, generation
+                 FROM symbols ORDER BY file_path, start_line"
+            }
+            ExportTable::Edges => {
+                "SELECT e.kind, e.source_id, s.name, s.kind, e.file_path, e.line,
+                        e.target_name, e.target_id, t.kind, t.file_path, t.start_line
+                 FROM edges e
+                 LEFT JOIN symbols s ON s.id = e.source_id
+                 LEFT JOIN symbols t ON t.id = e.target_id
+                 ORDER BY e.file_path, e.line, e.id"
+            }
+            ExportTable::Files => {
+                "SELECT path, language, num_symbols, hash, last_modified
+                 FROM files ORDER BY path"
+            }
+        };
+        let columns = table.columns();
+        let mut stmt = self.conn.prepare(sql)?;
+        let rows = stmt
+            .query_map([], |row| {
+                columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, col)| Ok(Cell::from_sql(row.get_ref(i)?, col.ty)))
+                    .collect()
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Copy the whole database to `dest` with SQLite's online backup API.
+    ///
+    /// All pages are copied in a single step, i.e. within one read transaction, so the
+    /// copy is a consistent snapshot even while a watcher or server keeps writing
+    /// through another connection. An existing file at `dest` is replaced.
+    pub fn backup_to(&self, dest: impl AsRef<std::path::Path>) -> Result<BackupResult> {
+        let dest = dest.as_ref();
+        if let (Some(src), Ok(dst)) = (self.conn.path(), dest.canonicalize()) {
+            if std::path::Path::new(src).canonicalize().ok() == Some(dst) {
+                anyhow::bail!("backup destination is the live database");
+            }
+        }
+
+        let mut dst = Connection::open(dest)
+            .with_context(|| format!("Failed to open backup destination {}", dest.display()))?;
+        let pages = {
+            use rusqlite::backup::{Backup, StepResult};
+            let backup = Backup::new(&self.conn, &mut dst)?;
+            let mut attempts = 0;
+            // -1 copies every page in one step; only lock contention can interrupt it.
+            while backup.step(-1)? != StepResult::Done {
+                attempts += 1;
+                if attempts == 50 {
+                    anyhow::bail!("backup timed out waiting for a database lock");
+                }
+                std::thread::sleep(std::time::Duration::from_millis(100));
+            }
+            backup.progress().pagecount
+        };
+        dst.close().map_err(|(_, e)| e)?;
+
+        Ok(BackupResult {
+            path: dest.display().to_string(),
+            pages: pages.max(0) as u32,
+            size_bytes: std::fs::metadata(dest)?.len(),
+        })
+    }
+}
+
+/// Outcome of [`Database::maintain`], summed over databases when sharded.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct MaintenanceReport {
+    pub databases: u32,
+    /// `ANALYZE` and the FTS5 merge ran.
+    pub analyzed: bool,
+    /// The file was rebuilt with `VACUUM`.
+    pub vacuumed: bool,
+    pub size_before: u64,
+    pub size_after: u64,
+    pub duration_ms: u64,
+}
+
+/// Per-connection cache and memory-map sizes, scaled to the database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PragmaTuning {
+    /// `cache_size`, in KiB.
+    pub cache_kib: u64,
+    /// `mmap_size`, in bytes.
+    pub mmap_bytes: u64,
+}
+
+impl PragmaTuning {
+    /// Cache a quarter of the database (16–256 MiB) and map up to twice its size
+    /// (256 MiB–2 GiB), leaving most of `available` memory to the rest of the system:
+    /// the cache is capped at 1/16 and the map at 1/4 of it.
+    pub fn for_size(db_bytes: u64, available: Option<u64>) -> Self {
+        const MIB: u64 = 1024 * 1024;
+        let mut cache = (db_bytes / 4).clamp(16 * MIB, 256 * MIB);
+        let mut mmap = db_bytes.saturating_mul(2).clamp(256 * MIB, 2048 * MIB);
+        if let Some(available) = available {
+            cache = cache.min((available / 16).max(2 * MIB));
+            mmap = mmap.min(available / 4);
+        }
+        Self {
+            cache_kib: cache / 1024,
+            mmap_bytes: mmap,
+        }
+    }
+}
+
+/// Memory available to new allocations (`MemAvailable` in `/proc/meminfo`), where known.
+fn available_memory() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemAvailable:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Outcome of [`Database::backup_to`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct BackupResult {
+    pub path: String,
+    pub pages: u32,
+    pub size_bytes: u64,
+}
+
+/// A file-level dependency, see [`Database::module_links`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ModuleLink {
+    pub source: String,
+    pub target: String,
+    /// Number of resolved edges from `source` into `target`.
+    pub edges: u32,
+}
+
+/// Scratch note attached to a symbol by an agent, see [`Database::add_note`].
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Note {
+    pub id: i64,
+    pub symbol_name: String,
+    pub file_path: String,
+    pub text: String,
+    /// Creation time as an ISO-8601 UTC string.
+    pub created: String,
+    /// Expiry time as an ISO-8601 UTC string; the note is ignored afterwards.
+    pub expires: String,
+}
+
+const NOTE_COLUMNS: &str = "SELECT id, symbol_name, file_path, text,
+        strftime('%Y-%m-%dT%H:%M:%SZ', created, 'unixepoch'),
+        strftime('%Y-%m-%dT%H:%M:%SZ', expires, 'unixepoch')
+ FROM notes";
+
+fn row_to_note(row: &rusqlite::Row<'_>) -> rusqlite::Result<Note> {
+    Ok(Note {
+        id: row.get(0)?,
+        symbol_name: row.get(1)?,
+        file_path: row.get(2)?,
+        text: row.get(3)?,
+        created: row.get(4)?,
+        expires: row.get(5)?,
+    })
+}
+
+/// Advisory claim of an agent on a symbol, see [`Database::reserve_symbols`].
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Reservation {
+    pub symbol_name: String,
+    pub file_path: String,
+    /// Agent or worker holding the reservation, as it named itself.
+    pub owner: String,
+    pub reason: String,
+    /// Reservation time as an ISO-8601 UTC string.
+    pub created: String,
+    /// Expiry time as an ISO-8601 UTC string; the symbol is free afterwards.
+    pub expires: String,
+}
+
+/// Outcome of [`Database::reserve_symbols`].
+#[derive(Debug, Default, Serialize, JsonSchema)]
+pub struct ReserveResult {
+    /// Symbols now reserved by the caller.
+    pub reserved: Vec<Reservation>,
+    /// Symbols held by other owners, with their reservations.
+    pub conflicts: Vec<Reservation>,
+}
+
+const RESERVATION_COLUMNS: &str = "SELECT symbol_name, file_path, owner, reason,
+        strftime('%Y-%m-%dT%H:%M:%SZ', created, 'unixepoch'),
+        strftime('%Y-%m-%dT%H:%M:%SZ', expires, 'unixepoch')
+ FROM reservations";
+
+fn row_to_reservation(row: &rusqlite::Row<'_>) -> rusqlite::Result<Reservation> {
+    Ok(Reservation {
+        symbol_name: row.get(0)?,
+        file_path: row.get(1)?,
+        owner: row.get(2)?,
+        reason: row.get(3)?,
+        created: row.get(4)?,
+        expires: row.get(5)?,
+    })
+}
+
+const JOB_COLUMNS: &str = "SELECT id, status, phase, params, done, total,
+        strftime('%Y-%m-%dT%H:%M:%SZ', started, 'unixepoch'),
+        strftime('%Y-%m-%dT%H:%M:%SZ', finished, 'unixepoch'),
+        result, error
+ FROM jobs";
+
+fn row_to_job(row: &rusqlite::Row<'_>) -> rusqlite::Result<Job> {
+    let status: String = row.get(1)?;
+    let params: String = row.get(3)?;
+    let result: Option<String> = row.get(8)?;
+    Ok(Job {
+        id: row.get(0)?,
+        status: JobStatus::parse(&status).unwrap_or(JobStatus::Failed),
+        phase: row.get(2)?,
+        params: serde_json::from_str(&params).unwrap_or_default(),
+        done: row.get(4)?,
+        total: row.get(5)?,
+        started: row.get(6)?,
+        finished: row.get(7)?,
+        result: result.and_then(|r| serde_json::from_str(&r).ok()),
+        error: row.get(9)?,
+    })
+}
+
+/// Current time in unix seconds, as stored in the audit log and notes.
+pub fn unix_now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+/// A step of edge resolution, in the order [`Database::resolve_edges`] tries them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionStage {
+    /// Relative JS/TS import: the module file and its `export *` chain.
+    Module,
+    /// A definition in the edge's own file.
+    SameFile,
+    /// The definition pinned with `cartog pin`.
+    Pinned,
+    /// A definition under the edge file's directory.
+    SameDir,
+    /// The only definition in the project.
+    Unique,
+}
+
+impl ResolutionStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Module => "module",
+            Self::SameFile => "same file",
+            Self::Pinned => "pinned",
+            Self::SameDir => "same directory",
+            Self::Unique => "unique",
+        }
+    }
+}
+
+/// Edges one resolution step settled during the last [`Database::resolve_edges`] run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ResolutionPass {
+    pub stage: ResolutionStage,
+    pub resolved: u32,
+    pub duration_ms: f64,
+}
+
+impl ResolutionPass {
+    fn new(stage: ResolutionStage, resolved: u32, started: Instant) -> Self {
+        Self {
+            stage,
+            resolved,
+            duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// A definition considered by a resolution step.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ResolutionCandidate {
+    pub symbol: Symbol,
+    pub accepted: bool,
+    pub reason: String,
+}
+
+impl ResolutionCandidate {
+    fn new(symbol: Symbol, accepted: bool, reason: String) -> Self {
+        Self {
+            symbol,
+            accepted,
+            reason,
+        }
+    }
+}
+
+/// One step of [`Database::explain_resolution`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ResolutionStep {
+    pub stage: ResolutionStage,
+    pub candidates: Vec<ResolutionCandidate>,
+    /// What the step concluded.
+    pub outcome: String,
+}
+
+impl ResolutionStep {
+    fn new(stage: ResolutionStage, outcome: String) -> Self {
+        Self {
+            stage,
+            candidates: Vec::new(),
+            outcome,
+        }
+    }
+
+    /// A step that accepts `chosen` among `candidates`, or concludes `none`.
+    fn pick(
+        stage: ResolutionStage,
+        mut candidates: Vec<ResolutionCandidate>,
+        chosen: Option<&str>,
+        none: String,
+        accepted: &str,
+        rejected: &str,
+    ) -> Self {
+        let mut outcome = none;
+        for c in &mut candidates {
+            c.accepted = chosen == Some(c.symbol.id.as_str());
+            c.reason = if c.accepted { accepted } else { rejected }.to_string();
+            if c.accepted {
+                outcome = format!("resolved to {}:{}", c.symbol.file_path, c.symbol.start_line);
+            }
+        }
+        Self {
+            stage,
+            candidates,
+            outcome,
+        }
+    }
+}
+
+/// How one edge's target name resolves, step by step.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ResolutionExplanation {
+    /// Row ID of the edge, accepted by `cartog explain-resolution` until the next index.
+    pub edge_id: i64,
+    pub edge: Edge,
+    /// Module specifier of an import edge.
+    pub module: Option<String>,
+    /// Name looked up: the last segment of a dotted target name.
+    pub lookup_name: String,
+    /// Steps run, up to the one that accepted a definition.
+    pub steps: Vec<ResolutionStep>,
+    /// Symbol ID resolution picks today. Differs from `edge.target_id` when the
+    /// index changed since the edge was resolved.
+    pub resolved_to: Option<String>,
+}
+
+impl ResolutionExplanation {
+    /// Record a step; returns whether it accepted a definition.
+    fn push(&mut self, step: ResolutionStep) -> bool {
+        let accepted = step.candidates.iter().find(|c| c.accepted);
+        self.resolved_to = accepted.map(|c| c.symbol.id.clone());
+        self.steps.push(step);
+        self.resolved_to.is_some()
+    }
+}
+
+/// One recorded MCP tool call.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct AuditEntry {
+    /// Unix timestamp (seconds) of the call.
+    pub timestamp: f64,
+    /// `timestamp` as an ISO-8601 UTC string (filled in on read).
+    pub time: String,
+    pub tool: String,
+    /// Tool arguments as a JSON object string.
+    pub params: String,
+    /// Number of items in the result, when the response is a list or has a `results` list.
+    pub result_count: Option<u32>,
+    pub duration_ms: u64,
+    pub ok: bool,
+}
+
+/// A symbol returned by an audited tool call.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SymbolHit {
+    pub name: String,
+    pub file_path: String,
+    pub kind: SymbolKind,
+}
+
+/// An audited tool call with the symbols it returned ([`Database::audit_calls`]).
+#[derive(Debug, Clone)]
+pub struct AuditCall {
+    pub entry: AuditEntry,
+    pub hits: Vec<SymbolHit>,
+}
+
+/// How often audited tool calls returned a symbol (`stats --hot-symbols`).
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct HotSymbol {
+    pub name: String,
+    pub file_path: String,
+    pub kind: SymbolKind,
+    /// Tool calls that returned it.
+    pub hits: u32,
+    /// The tools that did, sorted.
+    pub tools: Vec<String>,
+    /// ISO-8601 UTC time of the latest such call.
+    pub last_hit: String,
+}
+
+/// State of a background index job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+    /// The server stopped before the job finished.
+    Interrupted,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "running",
+            Self::Done => "done",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+            Self::Interrupted => "interrupted",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        [
+            Self::Running,
+            Self::Done,
+            Self::Failed,
+            Self::Cancelled,
+            Self::Interrupted,
+        ]
+        .into_iter()
+        .find(|status| status.as_str() == s)
+    }
+}
+
+/// A background index job (see [`crate::jobs`]).
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Job {
+    pub id: i64,
+    pub status: JobStatus,
+    /// `index` (code graph), then `embed` when the job also builds embeddings.
+    pub phase: String,
+    /// What the job was started with.
+    pub params: serde_json::Value,
+    /// Files (`index`) or symbols (`embed`) processed so far in the current phase,
+    /// out of `total` known so far.
+    pub done: u32,
+    pub total: u32,
+    /// ISO-8601 UTC start time.
+    pub started: String,
+    /// ISO-8601 UTC end time, once the job is over.
+    pub finished: Option<String>,
+    /// The results of the phases, once done.
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+/// Documentation coverage report, see [`Database::doc_coverage`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct DocCoverage {
+    pub total: u32,
+    pub documented: u32,
+    /// `documented / total`, or 1.0 when there is nothing to document.
+    pub coverage: f64,
+    pub modules: Vec<ModuleDocCoverage>,
+    pub largest_undocumented: Vec<Symbol>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ModuleDocCoverage {
+    pub file_path: String,
+    pub total: u32,
+    pub documented: u32,
+    pub coverage: f64,
+}
+
+pub(crate) fn coverage_ratio(documented: u32, total: u32) -> f64 {
+    if total == 0 {
+        1.0
+    } else {
+        f64::from(documented) / f64::from(total)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct IndexStats {
+    pub num_files: u32,
+    pub num_symbols: u32,
+    pub num_edges: u32,
+    pub num_resolved: u32,
+    pub languages: Vec<(String, u32)>,
+    /// Lines of all indexed files, counted when each was indexed.
+    pub lines: LineCounts,
+    /// Lines per language, most code first.
+    pub language_lines: Vec<(String, LineCounts)>,
+    /// The [`LARGEST_FILES`] files adding the most symbols and edges, largest first.
+    pub largest_files: Vec<LargeFile>,
+    pub symbol_kinds: Vec<(String, u32)>,
+    /// Pinned canonical definitions as `(name, file_path)`.
+    pub pins: Vec<(String, String)>,
+    /// Edges resolved and time taken by each resolution step in the last index run.
+    pub resolution: Vec<ResolutionPass>,
+}
+
+/// Files listed in [`IndexStats::largest_files`].
+pub const LARGEST_FILES: u32 = 5;
+
+/// A file among those adding the most to the graph.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct LargeFile {
+    pub path: String,
+    pub symbols: u32,
+    pub edges: u32,
+    /// Extraction was cut at `max_symbols_per_file` or `max_edges_per_file`.
+    pub truncated: bool,
+}
+
+/// Extraction counts for one indexed file.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct FileStats {
+    pub path: String,
+    pub language: String,
+    /// Last line covered by an extracted symbol (0 if none): the lines the index "sees".
+    pub lines: u32,
+    pub symbols: u32,
+    pub edges: u32,
+    pub unresolved: u32,
+}
+
+/// BM25 weight of each FTS column (`symbol_name`, `normalized_name`, `content`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct FtsWeights {
+    pub name: f64,
+    pub normalized_name: f64,
+    pub content: f64,
+}
+
+impl Default for FtsWeights {
+    /// A term in the name counts ten times a term in the body; the split name
+    /// (`validate token` for `validateToken`) half as much as the exact one.
+    fn default() -> Self {
+        Self {
+            name: 10.0,
+            normalized_name: 5.0,
+            content: 1.0,
+        }
+    }
+}
+
+impl std::fmt::Display for FtsWeights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{}", self.name, self.normalized_name, self.content)
+    }
+}
+
+impl std::str::FromStr for FtsWeights {
+    type Err = String;
+
+    /// Parse `NAME,NORMALIZED,CONTENT`, e.g. `10,5,1`.
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        let weights: Vec<f64> = s
+            .split(',')
+            .map(|w| w.trim().parse::<f64>())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| format!("'{s}' is not three comma-separated numbers"))?;
+        match weights[..] {
+            [name, normalized_name, content]
+                if weights.iter().all(|w| w.is_finite() && *w >= 0.0) =>
+            {
+                Ok(Self {
+                    name,
+                    normalized_name,
+                    content,
+                })
+            }
+            _ => Err(format!(
+                "'{s}' is not three non-negative weights NAME,NORMALIZED,CONTENT"
+            )),
+        }
+    }
+}
+
+/// Mapping of cross-encoder logits to a relevance in `[0, 1]`, and the relevance
+/// below which re-ranked results are dropped when no `min_score` is given.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+pub struct RerankCalibration {
+    /// Temperature of the sigmoid: higher spreads relevance over more logits.
+    pub temperature: f64,
+    pub min_relevance: f64,
+}
+
+impl Default for RerankCalibration {
+    /// BGE-reranker-base emits logits from about -11 (unrelated) to +8 (exact
+    /// answer). At 2.0, a logit of 0 maps to 0.5 and the unrelated tail stays
+    /// below 0.01, where the cross-encoder is confident a result is unrelated.
+    fn default() -> Self {
+        Self {
+            temperature: 2.0,
+            min_relevance: 0.01,
+        }
+    }
+}
+
+impl RerankCalibration {
+    /// Check a calibration before storing it.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if !(self.temperature.is_finite() && self.temperature > 0.0) {
+            return Err(format!(
+                "temperature must be positive, got {}",
+                self.temperature
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.min_relevance) {
+            return Err(format!(
+                "min relevance must be between 0 and 1, got {}",
+                self.min_relevance
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for RerankCalibration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{}", self.temperature, self.min_relevance)
+    }
+}
+
+impl std::str::FromStr for RerankCalibration {
+    type Err = String;
+
+    /// Parse `TEMPERATURE,MIN_RELEVANCE`, e.g. `2,0.01`.
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        let (temperature, min_relevance) = s
+            .split_once(',')
+            .and_then(|(t, r)| Some((t.trim().parse().ok()?, r.trim().parse().ok()?)))
+            .ok_or_else(|| format!("'{s}' is not TEMPERATURE,MIN_RELEVANCE"))?;
+        let calibration = Self {
+            temperature,
+            min_relevance,
+        };
+        calibration.validate()?;
+        Ok(calibration)
+    }
+}
+
+/// Result of [`Database::check_fts`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct FtsCheck {
+    pub content_rows: u32,
+    pub indexed_rows: u32,
+    /// Content rows with no index entry: keyword search cannot find them.
+    pub missing: u32,
+    /// Index entries whose content row is gone: stale matches.
+    pub orphaned: u32,
+    /// FTS5's integrity check of the index structure failed.
+    pub corrupt: bool,
+}
+
+impl FtsCheck {
+    pub fn is_ok(&self) -> bool {
+        self.missing == 0 && self.orphaned == 0 && !self.corrupt
+    }
+}
+
+/// Result of [`Database::check_orphans`]: rows whose symbol is gone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Orphans {
+    /// Edges whose source symbol is gone.
+    pub edges: u32,
+    /// `symbol_content` rows (and so keyword search entries) of removed symbols.
+    pub contents: u32,
+    /// Embedding map entries of removed symbols.
+    pub embedding_ids: u32,
+    /// Vectors with no embedding map entry of an existing symbol.
+    pub vectors: u32,
+}
+
+impl Orphans {
+    pub fn total(&self) -> u32 {
+        self.edges + self.contents + self.embedding_ids + self.vectors
+    }
+}
+
+impl std::ops::AddAssign for Orphans {
+    fn add_assign(&mut self, other: Self) {
+        self.edges += other.edges;
+        self.contents += other.contents;
+        self.embedding_ids += other.embedding_ids;
+        self.vectors += other.vectors;
+    }
+}
+
+/// [`FileStats`] summed over a directory prefix (`stats --by-dir`).
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct DirStats {
+    pub dir: String,
+    pub files: u32,
+    pub lines: u32,
+    pub symbols: u32,
+    pub edges: u32,
+    pub unresolved: u32,
+    /// `unresolved / edges`, 0 when there are no edges.
+    pub unresolved_rate: f64,
+    /// `(language, files)`, most files first.
+    pub languages: Vec<(String, u32)>,
+}
+
+/// Group files by their first `depth` directory components, ordered by directory.
+/// Files above that depth are grouped under their own directory (`.` for the root).
+pub fn stats_by_dir(files: &[FileStats], depth: usize) -> Vec<DirStats> {
+    let mut dirs: std::collections::BTreeMap<
+        String,
+        (DirStats, std::collections::HashMap<String, u32>),
+    > = std::collections::BTreeMap::new();
+    for f in files {
+        let components: Vec<&str> = f.path.split('/').collect();
+        let dir_len = (components.len() - 1).min(depth);
+        let dir = if dir_len == 0 {
+            ".".to_string()
+        } else {
+            components[..dir_len].join("/")
+        };
+        let (stats, languages) = dirs.entry(dir.clone()).or_insert_with(|| {
+            let stats = DirStats {
+                dir,
+                files: 0,
+                lines: 0,
+                symbols: 0,
+                edges: 0,
+                unresolved: 0,
+                unresolved_rate: 0.0,
+                languages: Vec::new(),
+            };
+            (stats, std::collections::HashMap::new())
+        });
+        stats.files += 1;
+        stats.lines += f.lines;
+        stats.symbols += f.symbols;
+        stats.edges += f.edges;
+        stats.unresolved += f.unresolved;
+        *languages.entry(f.language.clone()).or_default() += 1;
+    }
+    dirs.into_values()
+        .map(|(mut stats, languages)| {
+            if stats.edges > 0 {
+                stats.unresolved_rate = stats.unresolved as f64 / stats.edges as f64;
+            }
+            stats.languages = by_count_desc(languages);
+            stats
+        })
+        .collect()
+}
+
+/// `(key, count)` pairs, highest count first, ties by key.
+pub(crate) fn by_count_desc(counts: std::collections::HashMap<String, u32>) -> Vec<(String, u32)> {
+    let mut counts: Vec<(String, u32)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+// ── Row Mapping Helpers ──
+
+fn row_to_file(row: &rusqlite::Row<'_>) -> rusqlite::Result<FileInfo> {
+    Ok(FileInfo {
+        path: row.get(0)?,
+        last_modified: row.get(1)?,
+        hash: row.get(2)?,
+        language: row.get(3)?,
+        num_symbols: row.get(4)?,
+        generation: row.get(5)?,
+        lines: LineCounts {
+            code: row.get(6)?,
+            comment: row.get(7)?,
+            blank: row.get(8)?,
+        },
+        truncated: row.get(9)?,
+    })
+}
+
+fn row_to_symbol(row: &rusqlite::Row<'_>) -> rusqlite::Result<Symbol> {
+    row_to_symbol_offset(row, 0)
+}
+
+fn row_to_symbol_offset(row: &rusqlite::Row<'_>, off: usize) -> rusqlite::Result<Symbol> {
+    let kind_str = row.get::<_, String>(off + 2)?;
+    let kind = kind_str.parse().unwrap_or_else(|_| {
+        warn!(kind = %kind_str, "unknown symbol kind, defaulting to variable");
+        SymbolKind::Variable
+    });
+
+    let vis_str = row.get::<_, Option<String>>(off + 10)?.unwrap_or_default();
+
+    Ok(Symbol {
+        id: row.get(off)?,
+        name: row.get(off + 1)?,
+        kind,
+        file_path: row.get(off + 3)?,
+        start_line: row.get(off + 4)?,
+        end_line: row.get(off + 5)?,
+        start_byte: row.get(off + 6)?,
+        end_byte: row.get(off + 7)?,
+        parent_id: row.get(off + 8)?,
+        signature: row.get(off + 9)?,
+        visibility: Visibility::from_str_lossy(&vis_str),
+        is_async: row.get(off + 11)?,
+        docstring: row.get(off + 12)?,
+        entrypoint: row.get(off + 13)?,
+        db_table: row.get(off + 14)?,
+        generation: row.get(off + 15)?,
+    })
+}
+
+/// Ids of the edges referencing the symbol name in `name_column`, matched like
+/// [`Database::refs_in`] does: by target name, by the name of the resolved
+/// target, or through an import alias of the referencing file.
+fn referencing_edge_ids(name_column: &str) -> String {
+    format!(
+        "SELECT id FROM edges WHERE target_name = {name_column}
+         UNION SELECT re.id FROM symbols rt JOIN edges re ON re.target_id = rt.id
+               WHERE rt.name = {name_column}
+         UNION SELECT re.id FROM import_aliases ra
+               JOIN edges re ON re.file_path = ra.file_path
+               LEFT JOIN symbols rt ON re.target_id = rt.id
+               WHERE ra.alias = {name_column} AND (ra.name = re.target_name OR ra.name = rt.name)"
+    )
+}
+
+/// Condition on an edge `e` followed by [`Database::impact_of`] from the name in
+/// `name_column`: the `target_kind` (`?3`) narrows references to the start name
+/// (`?1`), the language (`?4`) all of them.
+fn impact_edge_filter(name_column: &str) -> String {
+    format!(
+        "(?3 IS NULL OR {name_column} != ?1 OR e.target_kind = ?3) AND {language}",
+        language = language_condition("e.file_path", 4),
+    )
+}
+
+/// SQL condition: the file at `path_column` is of the language bound to `?{param}`,
+/// or any file when it is NULL. `typescript` covers `.tsx` files too, as in
+/// `watch --skip-lang`.
+pub(crate) fn language_condition(path_column: &str, param: u8) -> String {
+    format!(
+        "(?{param} IS NULL OR EXISTS (SELECT 1 FROM files lf WHERE lf.path = {path_column}
+             AND (lf.language = ?{param} OR (lf.language = 'tsx' AND ?{param} = 'typescript'))))"
+    )
+}
+
+fn row_to_edge(row: &rusqlite::Row<'_>) -> rusqlite::Result<Edge> {
+    let kind_str = row.get::<_, String>(4)?;
+    let kind = kind_str.parse().unwrap_or_else(|_| {
+        warn!(kind = %kind_str, "unknown edge kind, defaulting to references");
+        EdgeKind::References
+    });
+
+    Ok(Edge {
+        source_id: row.get(1)?,
+        target_name: row.get(2)?,
+        target_id: row.get(3)?,
+        target_kind: row
+            .get::<_, Option<String>>(7)?
+            .and_then(|k| k.parse().ok()),
         kind,
-        file_path: row.get(off + 3)?,
-        start_line: row.get(off + 4)?,
-        end_line: row.get(off + 5)?,
-        start_byte: row.get(off + 6)?,
-        end_byte: row.get(off + 7)?,
-        parent_id: row.get(off + 8)?,
-        signature: row.get(off + 9)?,
-        visibility: Visibility::from_str_lossy(&vis_str),
-        is_async: row.get(off + 11)?,
-        docstring: row.get(off + 12)?,
+        file_path: row.get(5)?,
+        line: row.get(6)?,
+        conditional: row.get(8)?,
     })
 }
 
-fn row_to_edge(row: &rusqlite::Row<'_>) -> rusqlite::Result<Edge> {
-    let kind_str = row.get::<_, String>(4)?;
-    let kind = kind_str.parse().unwrap_or_else(|_| {
-        warn!(kind = %kind_str, "unknown edge kind, defaulting to references");
-        EdgeKind::References
-    });
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_symbol(name: &str, kind: SymbolKind, file: &str, line: u32) -> Symbol {
+        Symbol::new(name, kind, file, line, line + 5, 0, 100)
+    }
+
+    // ── normalize_symbol_name tests ──
+
+    #[test]
+    fn test_normalize_snake_case() {
+        assert_eq!(normalize_symbol_name("validate_token"), "validate token");
+        assert_eq!(
+            normalize_symbol_name("get_current_user"),
+            "get current user"
+        );
+        assert_eq!(normalize_symbol_name("_private_method"), "private method");
+        assert_eq!(normalize_symbol_name("__init__"), "init");
+    }
+
+    #[test]
+    fn test_normalize_camel_case() {
+        assert_eq!(normalize_symbol_name("validateToken"), "validate token");
+        assert_eq!(normalize_symbol_name("getCurrentUser"), "get current user");
+        assert_eq!(normalize_symbol_name("findByToken"), "find by token");
+    }
+
+    #[test]
+    fn test_normalize_pascal_case() {
+        assert_eq!(
+            normalize_symbol_name("DatabaseConnection"),
+            "database connection"
+        );
+        assert_eq!(normalize_symbol_name("AuthService"), "auth service");
+        assert_eq!(normalize_symbol_name("TokenError"), "token error");
+    }
+
+    #[test]
+    fn test_normalize_screaming_snake() {
+        assert_eq!(normalize_symbol_name("TOKEN_EXPIRY"), "token expiry");
+        assert_eq!(normalize_symbol_name("MAX_RETRY_COUNT"), "max retry count");
+    }
+
+    #[test]
+    fn test_normalize_acronyms() {
+        assert_eq!(
+            normalize_symbol_name("getHTTPResponse"),
+            "get http response"
+        );
+        assert_eq!(normalize_symbol_name("parseJSON"), "parse json");
+        assert_eq!(normalize_symbol_name("HTMLParser"), "html parser");
+    }
+
+    #[test]
+    fn test_normalize_single_word() {
+        assert_eq!(normalize_symbol_name("validate"), "validate");
+        assert_eq!(normalize_symbol_name("Token"), "token");
+    }
+
+    #[test]
+    fn test_normalize_non_ascii() {
+        // Cased scripts follow camelCase rules
+        assert_eq!(normalize_symbol_name("получитьДанные"), "получить данные");
+        assert_eq!(normalize_symbol_name("ΣύνοψηΔεδομένων"), "σύνοψη δεδομένων");
+        // Script changes are word boundaries
+        assert_eq!(normalize_symbol_name("getユーザー"), "get ユーザー");
+        assert_eq!(normalize_symbol_name("ユーザー取得"), "ユーザー 取得");
+        assert_eq!(normalize_symbol_name("用户Service"), "用户 service");
+        assert_eq!(normalize_symbol_name("fetch_用户名2"), "fetch 用户名2");
+        // Han + Hiragana okurigana stays one word
+        assert_eq!(normalize_symbol_name("読み込む"), "読み込む");
+    }
+
+    #[test]
+    fn test_normalize_empty_and_special() {
+        assert_eq!(normalize_symbol_name(""), "");
+        assert_eq!(normalize_symbol_name("_"), "");
+        assert_eq!(normalize_symbol_name("___"), "");
+    }
+
+    #[test]
+    fn test_insert_and_query_symbols() {
+        let db = Database::open_memory().unwrap();
+        let sym = test_symbol("my_func", SymbolKind::Function, "test.py", 10);
+        db.insert_symbol(&sym).unwrap();
+
+        let outline = db.outline("test.py").unwrap();
+        assert_eq!(outline.len(), 1);
+        assert_eq!(outline[0].name, "my_func");
+    }
+
+    #[test]
+    fn test_insert_and_query_edges() {
+        let db = Database::open_memory().unwrap();
+        let caller = test_symbol("caller_fn", SymbolKind::Function, "a.py", 1);
+        let callee = test_symbol("callee_fn", SymbolKind::Function, "b.py", 1);
+        db.insert_symbol(&caller).unwrap();
+        db.insert_symbol(&callee).unwrap();
+
+        let edge = Edge {
+            source_id: caller.id.clone(),
+            target_name: "callee_fn".to_string(),
+            target_id: None,
+            target_kind: None,
+            kind: EdgeKind::Calls,
+            file_path: "a.py".to_string(),
+            line: 5,
+            conditional: false,
+        };
+        db.insert_edge(&edge).unwrap();
+
+        let refs = db.refs("callee_fn", None).unwrap();
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].0.source_id, caller.id);
+    }
+
+    #[test]
+    fn test_edge_resolution() {
+        let db = Database::open_memory().unwrap();
+        let sym_a = test_symbol("process", SymbolKind::Function, "a.py", 1);
+        let sym_b = test_symbol("helper", SymbolKind::Function, "a.py", 20);
+        db.insert_symbols(&[sym_a.clone(), sym_b.clone()]).unwrap();
+
+        let edge = Edge {
+            source_id: sym_a.id.clone(),
+            target_name: "helper".to_string(),
+            target_id: None,
+            target_kind: None,
+            kind: EdgeKind::Calls,
+            file_path: "a.py".to_string(),
+            line: 5,
+            conditional: false,
+        };
+        db.insert_edge(&edge).unwrap();
+
+        let resolved = db.resolve_edges().unwrap();
+        assert_eq!(resolved, 1);
+    }
+
+    #[test]
+    fn test_stats() {
+        let db = Database::open_memory().unwrap();
+        let file = FileInfo {
+            path: "test.py".to_string(),
+            last_modified: 0.0,
+            hash: "abc".to_string(),
+            language: "python".to_string(),
+            num_symbols: 2,
+            generation: 0,
+            lines: Default::default(),
+            truncated: false,
+        };
+        db.upsert_file(&file).unwrap();
+        let sym = test_symbol("foo", SymbolKind::Function, "test.py", 1);
+        db.insert_symbol(&sym).unwrap();
+
+        let stats = db.stats().unwrap();
+        assert_eq!(stats.num_files, 1);
+        assert_eq!(stats.num_symbols, 1);
+        assert_eq!(stats.largest_files[0].path, "test.py");
+        assert_eq!(stats.largest_files[0].symbols, 2);
+    }
+
+    #[test]
+    fn test_stats_by_dir() {
+        let db = Database::open_memory().unwrap();
+        for (path, language) in [
+            ("setup.py", "python"),
+            ("svc/api/routes.py", "python"),
+            ("svc/api/deep/models.py", "python"),
+            ("svc/web/app.ts", "typescript"),
+        ] {
+            db.upsert_file(&FileInfo {
+                path: path.to_string(),
+                last_modified: 0.0,
+                hash: String::new(),
+                language: language.to_string(),
+                num_symbols: 0,
+                generation: 0,
+                lines: Default::default(),
+                truncated: false,
+            })
+            .unwrap();
+        }
+        let routes = test_symbol("routes", SymbolKind::Function, "svc/api/routes.py", 1);
+        let mut models = test_symbol("Model", SymbolKind::Class, "svc/api/deep/models.py", 3);
+        models.end_line = 40;
+        db.insert_symbols(&[routes.clone(), models.clone()])
+            .unwrap();
+        db.insert_edges(&[
+            Edge::new(
+                &routes.id,
+                "Model",
+                EdgeKind::References,
+                "svc/api/routes.py",
+                2,
+            ),
+            Edge::new(&routes.id, "print", EdgeKind::Calls, "svc/api/routes.py", 3),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
+
+        let dirs = stats_by_dir(&db.file_stats().unwrap(), 2);
+        let names: Vec<&str> = dirs.iter().map(|d| d.dir.as_str()).collect();
+        assert_eq!(names, vec![".", "svc/api", "svc/web"]);
+
+        let api = &dirs[1];
+        assert_eq!((api.files, api.symbols, api.edges), (2, 2, 2));
+        assert_eq!(api.lines, routes.end_line + 40);
+        assert_eq!(api.unresolved, 1);
+        assert_eq!(api.unresolved_rate, 0.5);
+        assert_eq!(api.languages, vec![("python".to_string(), 2)]);
+
+        let web = &dirs[2];
+        assert_eq!((web.files, web.symbols, web.lines), (1, 0, 0));
+        assert_eq!(web.unresolved_rate, 0.0);
+
+        let top = stats_by_dir(&db.file_stats().unwrap(), 1);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[1].languages.len(), 2);
+    }
+
+    #[test]
+    fn test_resolve_edges_same_dir_priority() {
+        let db = Database::open_memory().unwrap();
+
+        // "helper" exists in same dir (src/utils.py) and elsewhere (lib/utils.py)
+        let caller = test_symbol("process", SymbolKind::Function, "src/main.py", 1);
+        let same_dir = test_symbol("helper", SymbolKind::Function, "src/utils.py", 1);
+        let other_dir = test_symbol("helper", SymbolKind::Function, "lib/utils.py", 1);
+        db.insert_symbols(&[caller.clone(), same_dir.clone(), other_dir.clone()])
+            .unwrap();
+
+        let edge = Edge {
+            source_id: caller.id.clone(),
+            target_name: "helper".to_string(),
+            target_id: None,
+            target_kind: None,
+            kind: EdgeKind::Calls,
+            file_path: "src/main.py".to_string(),
+            line: 5,
+            conditional: false,
+        };
+        db.insert_edge(&edge).unwrap();
+
+        let resolved = db.resolve_edges().unwrap();
+        assert_eq!(resolved, 1);
+
+        // Verify it resolved to the same-directory symbol
+        let refs = db.refs("helper", None).unwrap();
+        let call_edge = refs
+            .iter()
+            .find(|(e, _)| e.kind == EdgeKind::Calls)
+            .unwrap();
+        assert_eq!(call_edge.0.target_id.as_ref().unwrap(), &same_dir.id);
+    }
+
+    #[test]
+    fn test_resolve_edges_ambiguous_no_resolve() {
+        let db = Database::open_memory().unwrap();
+
+        // "helper" in two different directories, caller in a third
+        let caller = test_symbol("process", SymbolKind::Function, "app/main.py", 1);
+        let sym1 = test_symbol("helper", SymbolKind::Function, "pkg_a/utils.py", 1);
+        let sym2 = test_symbol("helper", SymbolKind::Function, "pkg_b/utils.py", 1);
+        db.insert_symbols(&[caller.clone(), sym1, sym2]).unwrap();
+
+        let edge = Edge {
+            source_id: caller.id.clone(),
+            target_name: "helper".to_string(),
+            target_id: None,
+            target_kind: None,
+            kind: EdgeKind::Calls,
+            file_path: "app/main.py".to_string(),
+            line: 5,
+            conditional: false,
+        };
+        db.insert_edge(&edge).unwrap();
+
+        let resolved = db.resolve_edges().unwrap();
+        // Should NOT resolve because "helper" is ambiguous (2 matches globally)
+        assert_eq!(resolved, 0);
+    }
+
+    #[test]
+    fn test_resolve_edges_records_passes() {
+        let db = Database::open_memory().unwrap();
+        assert!(db.last_resolution().unwrap().is_empty());
+
+        let caller = test_symbol("run", SymbolKind::Function, "app/main.py", 1);
+        let local = test_symbol("local", SymbolKind::Function, "app/main.py", 10);
+        let sibling = test_symbol("sibling", SymbolKind::Function, "app/util.py", 1);
+        let unique = test_symbol("unique", SymbolKind::Function, "lib/x.py", 1);
+        db.insert_symbols(&[caller.clone(), local, sibling, unique])
+            .unwrap();
+        for (name, line) in [
+            ("local", 2),
+            ("util.sibling", 3),
+            ("unique", 4),
+            ("unique", 5),
+            ("missing", 6),
+        ] {
+            db.insert_edge(&Edge::new(
+                &caller.id,
+                name,
+                EdgeKind::Calls,
+                "app/main.py",
+                line,
+            ))
+            .unwrap();
+        }
+
+        assert_eq!(db.resolve_edges().unwrap(), 4);
+        let counts: Vec<(ResolutionStage, u32)> = db
+            .last_resolution()
+            .unwrap()
+            .iter()
+            .map(|p| (p.stage, p.resolved))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                (ResolutionStage::Module, 0),
+                (ResolutionStage::SameFile, 1),
+                (ResolutionStage::Pinned, 0),
+                (ResolutionStage::SameDir, 1),
+                (ResolutionStage::Unique, 2),
+            ]
+        );
+        assert_eq!(db.stats().unwrap().resolution.len(), 5);
+
+        // Only the edge left unresolved is retried.
+        assert_eq!(db.resolve_edges().unwrap(), 0);
+        assert_eq!(db.stats().unwrap().num_resolved, 4);
+    }
+
+    #[test]
+    fn test_target_kind_stored_and_filtered() {
+        let db = Database::open_memory().unwrap();
+        let class = test_symbol("Config", SymbolKind::Class, "app/config.py", 1);
+        let func = test_symbol("Config", SymbolKind::Function, "app/legacy.py", 1);
+        let caller = test_symbol("run", SymbolKind::Function, "app/config.py", 10);
+        let other = test_symbol("main", SymbolKind::Function, "app/legacy.py", 10);
+        db.insert_symbols(&[class.clone(), func, caller.clone(), other.clone()])
+            .unwrap();
+        db.insert_edges(&[
+            Edge::new(&caller.id, "Config", EdgeKind::Calls, "app/config.py", 11),
+            Edge::new(&other.id, "Config", EdgeKind::Calls, "app/legacy.py", 11),
+            Edge::new(&other.id, "missing", EdgeKind::Calls, "app/legacy.py", 12),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
+
+        let refs = db.refs("Config", None).unwrap();
+        assert_eq!(refs.len(), 2);
+        let to_class = db
+            .refs_in(
+                "Config",
+                Some(EdgeKind::Calls),
+                None,
+                Some(SymbolKind::Class),
+                None,
+            )
+            .unwrap();
+        assert_eq!(to_class.len(), 1);
+        assert_eq!(to_class[0].0.target_id.as_deref(), Some(class.id.as_str()));
+        assert_eq!(to_class[0].0.target_kind, Some(SymbolKind::Class));
+        let json = serde_json::to_value(db.callees("main").unwrap()).unwrap();
+        assert_eq!(json[0]["target_kind"], "function");
+        assert!(json[1].get("target_kind").is_none(), "unresolved");
+
+        let impact = db
+            .impact_of("Config", 2, Some(SymbolKind::Class), None)
+            .unwrap();
+        let sources: Vec<&str> = impact.iter().map(|(e, _)| e.source_id.as_str()).collect();
+        assert_eq!(sources, [caller.id.as_str()]);
+        assert_eq!(db.impact("Config", 2).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_conditional_edges_stored_and_ranked_last() {
+        let db = Database::open_memory().unwrap();
+        let target = test_symbol("flush", SymbolKind::Function, "app/log.py", 1);
+        let debug = test_symbol("debug", SymbolKind::Function, "app/a.py", 1);
+        let save = test_symbol("save", SymbolKind::Function, "app/b.py", 1);
+        db.insert_symbols(&[target, debug.clone(), save.clone()])
+            .unwrap();
+        db.insert_edges(&[
+            Edge::new(&debug.id, "flush", EdgeKind::Calls, "app/a.py", 2).with_conditional(true),
+            Edge::new(&save.id, "flush", EdgeKind::Calls, "app/b.py", 2),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
 
-    Ok(Edge {
-        source_id: row.get(1)?,
-        target_name: row.get(2)?,
-        target_id: row.get(3)?,
-        kind,
-        file_path: row.get(5)?,
-        line: row.get(6)?,
-    })
-}
+        let refs = db.refs("flush", None).unwrap();
+        let conditional: Vec<&str> = refs
+            .iter()
+            .filter(|(e, _)| e.conditional)
+            .map(|(e, _)| e.source_id.as_str())
+            .collect();
+        assert_eq!(conditional, [debug.id.as_str()]);
+        let json = serde_json::to_value(db.callees("debug").unwrap()).unwrap();
+        assert_eq!(json[0]["conditional"], true);
+        let json = serde_json::to_value(db.callees("save").unwrap()).unwrap();
+        assert!(json[0].get("conditional").is_none());
+
+        // Same depth: the unconditional caller comes first despite its path
+        let impact = db.impact("flush", 1).unwrap();
+        let sources: Vec<&str> = impact.iter().map(|(e, _)| e.source_id.as_str()).collect();
+        assert_eq!(sources, [save.id.as_str(), debug.id.as_str()]);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_explain_resolution_steps() {
+        let db = Database::open_memory().unwrap();
+        let main_cfg = test_symbol("Config", SymbolKind::Class, "src/config.rs", 1);
+        let test_cfg = test_symbol("Config", SymbolKind::Class, "tests/config.rs", 1);
+        let helper = test_symbol("helper", SymbolKind::Function, "app/util.rs", 1);
+        let caller = test_symbol("run", SymbolKind::Function, "app/main.rs", 1);
+        db.insert_symbols(&[main_cfg.clone(), test_cfg, helper.clone(), caller.clone()])
+            .unwrap();
+        for (name, line) in [("Config", 2), ("Config", 3), ("util.helper", 4)] {
+            db.insert_edge(&Edge::new(
+                &caller.id,
+                name,
+                EdgeKind::Calls,
+                "app/main.rs",
+                line,
+            ))
+            .unwrap();
+        }
+        db.resolve_edges().unwrap();
 
-    fn test_symbol(name: &str, kind: SymbolKind, file: &str, line: u32) -> Symbol {
-        Symbol::new(name, kind, file, line, line + 5, 0, 100)
+        // Both Config edges resolve alike, so only the first is explained.
+        let explained = db.explain_resolution("Config", None, 10).unwrap();
+        assert_eq!(explained.len(), 1);
+        let stages: Vec<ResolutionStage> = explained[0].steps.iter().map(|s| s.stage).collect();
+        assert_eq!(
+            stages,
+            [
+                ResolutionStage::SameFile,
+                ResolutionStage::Pinned,
+                ResolutionStage::SameDir,
+                ResolutionStage::Unique
+            ]
+        );
+        let unique = &explained[0].steps[3];
+        assert_eq!(unique.candidates.len(), 2);
+        assert!(unique.candidates.iter().all(|c| !c.accepted));
+        assert!(unique.outcome.starts_with("2 definitions"));
+        assert_eq!(explained[0].resolved_to, None);
+
+        // A pin is accepted at its step, and the edge can be named by ID.
+        db.pin("Config", "src/config.rs").unwrap();
+        let edge_id = explained[0].edge_id.to_string();
+        let explained = db.explain_resolution(&edge_id, None, 10).unwrap();
+        let last = explained[0].steps.last().unwrap();
+        assert_eq!(last.stage, ResolutionStage::Pinned);
+        assert!(last.candidates[0].accepted);
+        assert_eq!(explained[0].resolved_to, Some(main_cfg.id));
+
+        // Dotted names match on their last segment; `_` is not a wildcard.
+        let explained = db
+            .explain_resolution("helper", Some("app/main.rs"), 10)
+            .unwrap();
+        assert_eq!(explained[0].lookup_name, "helper");
+        assert_eq!(
+            explained[0].steps.last().unwrap().stage,
+            ResolutionStage::SameDir
+        );
+        assert_eq!(explained[0].resolved_to, Some(helper.id));
+        assert!(db
+            .explain_resolution("h_lper", None, 10)
+            .unwrap()
+            .is_empty());
     }
 
-    // ── normalize_symbol_name tests ──
+    #[test]
+    fn test_pin_prefers_canonical_definition() {
+        let db = Database::open_memory().unwrap();
+        let main_cfg = test_symbol("Config", SymbolKind::Class, "src/config.rs", 1);
+        let test_cfg = test_symbol("Config", SymbolKind::Class, "tests/config.rs", 1);
+        let caller = test_symbol("run", SymbolKind::Function, "app/main.rs", 1);
+        db.insert_symbols(&[main_cfg.clone(), test_cfg.clone(), caller.clone()])
+            .unwrap();
+        db.insert_edge(&Edge::new(
+            &caller.id,
+            "Config",
+            EdgeKind::References,
+            "app/main.rs",
+            2,
+        ))
+        .unwrap();
+        db.resolve_edges().unwrap();
+        let target = |db: &Database| db.refs("Config", None).unwrap()[0].0.target_id.clone();
+        assert_eq!(target(&db), None, "ambiguous without a pin");
+
+        assert!(db.pin("Config", "lib/missing.rs").is_err());
+        let pinned = db.pin("Config", "tests/config.rs").unwrap();
+        assert_eq!(pinned.id, test_cfg.id);
+        assert_eq!(target(&db), Some(test_cfg.id.clone()));
+        let found = db.search("Config", None, None, 10).unwrap();
+        assert_eq!(found[0].id, test_cfg.id);
+        assert_eq!(
+            db.stats().unwrap().pins,
+            [("Config".to_string(), "tests/config.rs".to_string())]
+        );
+
+        // Re-pinning moves resolution to the new definition.
+        db.pin("Config", "src/config.rs").unwrap();
+        assert_eq!(target(&db), Some(main_cfg.id.clone()));
+
+        assert!(db.unpin("Config").unwrap());
+        assert!(!db.unpin("Config").unwrap());
+        assert_eq!(target(&db), None);
+        assert!(db.pins().unwrap().is_empty());
+    }
 
     #[test]
-    fn test_normalize_snake_case() {
-        assert_eq!(normalize_symbol_name("validate_token"), "validate token");
+    fn test_writer_version_is_recorded_and_checked() {
+        let dir = std::env::temp_dir().join("cartog_test_writer_version");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("index.db");
+        let ours = env!("CARGO_PKG_VERSION");
+
+        let db = Database::open(&path).unwrap();
         assert_eq!(
-            normalize_symbol_name("get_current_user"),
-            "get current user"
+            db.get_metadata(WRITER_VERSION_KEY).unwrap().as_deref(),
+            Some(ours)
         );
-        assert_eq!(normalize_symbol_name("_private_method"), "private method");
-        assert_eq!(normalize_symbol_name("__init__"), "init");
+        assert_eq!(
+            db.get_metadata(SCHEMA_VERSION_KEY).unwrap(),
+            Some(SCHEMA_VERSION.to_string())
+        );
+
+        // A newer writer with a compatible schema: open, but keep its version
+        db.set_metadata(WRITER_VERSION_KEY, "999.0.0").unwrap();
+        drop(db);
+        let db = Database::open(&path).unwrap();
+        assert_eq!(
+            db.get_metadata(WRITER_VERSION_KEY).unwrap().as_deref(),
+            Some("999.0.0")
+        );
+
+        // A newer schema: refuse, naming both versions
+        let newer = (SCHEMA_VERSION + 1).to_string();
+        db.set_metadata(SCHEMA_VERSION_KEY, &newer).unwrap();
+        drop(db);
+        let err = format!("{:#}", Database::open(&path).unwrap_err());
+        assert!(err.contains("cartog 999.0.0"), "{err}");
+        assert!(err.contains(&format!("cartog {ours}")), "{err}");
+        assert!(err.contains(&format!("schema {newer}")), "{err}");
+
+        let _ = std::fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn test_normalize_camel_case() {
-        assert_eq!(normalize_symbol_name("validateToken"), "validate token");
-        assert_eq!(normalize_symbol_name("getCurrentUser"), "get current user");
-        assert_eq!(normalize_symbol_name("findByToken"), "find by token");
+    fn test_entrypoint_column_added_to_older_database() {
+        let dir = std::env::temp_dir().join("cartog_test_added_columns");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("index.db");
+        {
+            // symbols table as created before `entrypoint` existed
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE symbols (
+                    id TEXT PRIMARY KEY, name TEXT NOT NULL, kind TEXT NOT NULL,
+                    file_path TEXT NOT NULL, start_line INTEGER, end_line INTEGER,
+                    start_byte INTEGER, end_byte INTEGER, parent_id TEXT, signature TEXT,
+                    visibility TEXT, is_async BOOLEAN DEFAULT FALSE, docstring TEXT
+                );
+                INSERT INTO symbols VALUES ('a.py:old:1', 'old', 'function', 'a.py', 1, 2, 0, 9,
+                                            NULL, NULL, 'public', 0, NULL);",
+            )
+            .unwrap();
+        }
+
+        let db = Database::open(&path).unwrap();
+        let main =
+            test_symbol("main", SymbolKind::Function, "a.py", 10).with_entrypoint(Some("script"));
+        db.insert_symbol(&main).unwrap();
+
+        assert_eq!(db.outline("a.py").unwrap()[0].entrypoint, None);
+        let entrypoints = db.entrypoints(None).unwrap();
+        assert_eq!(entrypoints.len(), 1);
+        assert_eq!(entrypoints[0].entrypoint.as_deref(), Some("script"));
+        assert!(db.entrypoints(Some("route")).unwrap().is_empty());
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
     }
 
     #[test]
-    fn test_normalize_pascal_case() {
+    fn test_parse_version() {
+        assert_eq!(parse_version("0.12.3"), Some((0, 12, 3)));
+        assert_eq!(parse_version("1.0.0-rc.1"), Some((1, 0, 0)));
+        assert_eq!(parse_version("1.0"), None);
+        assert!(parse_version("0.10.0") > parse_version("0.9.9"));
+    }
+
+    #[test]
+    fn test_backup_to_copies_live_database() {
+        let dir = std::env::temp_dir().join("cartog_test_backup");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let live_path = dir.join("live.db");
+        let backup_path = dir.join("backup.db");
+
+        let db = Database::open(&live_path).unwrap();
+        db.insert_symbols(&[test_symbol("login", SymbolKind::Function, "auth.py", 1)])
+            .unwrap();
+        // A second connection keeps the WAL busy, as a running watcher would.
+        let _writer = Database::open(&live_path).unwrap();
+
+        let result = db.backup_to(&backup_path).unwrap();
+        assert!(result.pages > 0);
+        assert!(result.size_bytes > 0);
+        let copy = Database::open(&backup_path).unwrap();
+        assert_eq!(copy.outline("auth.py").unwrap()[0].name, "login");
+
+        // Backing up again replaces the previous copy.
+        db.insert_symbols(&[test_symbol("logout", SymbolKind::Function, "auth.py", 5)])
+            .unwrap();
+        drop(copy);
+        db.backup_to(&backup_path).unwrap();
+        let copy = Database::open(&backup_path).unwrap();
+        assert_eq!(copy.outline("auth.py").unwrap().len(), 2);
+
+        assert!(db.backup_to(&live_path).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_doc_coverage() {
+        let db = Database::open_memory().unwrap();
+        let documented = test_symbol("load", SymbolKind::Function, "a.py", 1)
+            .with_docstring(Some("Load it.".to_string()));
+        let small = test_symbol("save", SymbolKind::Function, "a.py", 10);
+        let big = Symbol::new("Engine", SymbolKind::Class, "b.py", 1, 200, 0, 5000);
+        let private = test_symbol("_helper", SymbolKind::Function, "b.py", 300)
+            .with_visibility(Visibility::Private);
+        let var = test_symbol("MAX", SymbolKind::Variable, "b.py", 400);
+        db.insert_symbols(&[documented, small, big.clone(), private, var])
+            .unwrap();
+
+        let report = db.doc_coverage(Some(Visibility::Public), 10).unwrap();
+        assert_eq!(report.total, 3);
+        assert_eq!(report.documented, 1);
+        // b.py (0/1) sorts before a.py (1/2)
+        assert_eq!(report.modules[0].file_path, "b.py");
+        assert!((report.modules[1].coverage - 0.5).abs() < f64::EPSILON);
+        assert_eq!(report.largest_undocumented[0].id, big.id);
+        assert_eq!(report.largest_undocumented.len(), 2);
+
+        let all = db.doc_coverage(None, 1).unwrap();
+        assert_eq!(all.total, 4);
+        assert_eq!(all.largest_undocumented.len(), 1);
+    }
+
+    #[test]
+    fn test_anchors_filters_and_cleanup() {
+        let db = Database::open_memory().unwrap();
+        let func = test_symbol("login", SymbolKind::Function, "src/auth/login.py", 1);
+        db.insert_symbols(std::slice::from_ref(&func)).unwrap();
+
+        let anchor = |tag: &str, text: &str, file: &str, line: u32| Anchor {
+            tag: tag.to_string(),
+            text: text.to_string(),
+            file_path: file.to_string(),
+            line,
+            symbol_id: None,
+            symbol_name: None,
+        };
+        let mut linked = anchor("FIXME", "leaks timing", "src/auth/login.py", 3);
+        linked.symbol_id = Some(func.id.clone());
+        db.insert_anchors(&[
+            linked,
+            anchor("TODO", "add retries", "src/auth/login.py", 9),
+            anchor("FIXME", "flaky", "src/api/routes.py", 1),
+        ])
+        .unwrap();
+
+        let fixmes = db
+            .anchors(Some("src/auth"), Some("fixme"), None, 100)
+            .unwrap();
+        assert_eq!(fixmes.len(), 1);
+        assert_eq!(fixmes[0].symbol_name.as_deref(), Some("login"));
+
+        let by_text = db.anchors(None, None, Some("RETRIES"), 100).unwrap();
+        assert_eq!(by_text.len(), 1);
+        assert_eq!(by_text[0].line, 9);
+
+        assert_eq!(db.anchors(None, None, None, 100).unwrap().len(), 3);
+        db.clear_file_data("src/auth/login.py").unwrap();
+        assert_eq!(db.anchors(None, None, None, 100).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_injections_search_and_setting() {
+        let db = Database::open_memory().unwrap();
+        assert!(!db.scan_injections().unwrap());
+        db.set_scan_injections(true).unwrap();
+        assert!(db.scan_injections().unwrap());
+
+        let injection = |language: &str, text: &str, file: &str, line: u32| Injection {
+            language: language.to_string(),
+            text: text.to_string(),
+            file_path: file.to_string(),
+            line,
+            symbol_id: None,
+            symbol_name: None,
+        };
+        db.insert_injections(&[
+            injection(
+                "sql",
+                "SELECT id FROM users WHERE active = 1",
+                "src/users.py",
+                4,
+            ),
+            injection("sql", "DELETE FROM sessions", "src/auth.py", 9),
+            injection("regex", r"^users/(\d+)$", "src/routes.py", 2),
+        ])
+        .unwrap();
+
+        // Every word must match, in any case and order.
+        let hits = db.injections(Some("from USERS"), None, None, 100).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].file_path, "src/users.py");
         assert_eq!(
-            normalize_symbol_name("DatabaseConnection"),
-            "database connection"
+            db.injections(Some("users"), None, None, 100).unwrap().len(),
+            2
         );
-        assert_eq!(normalize_symbol_name("AuthService"), "auth service");
-        assert_eq!(normalize_symbol_name("TokenError"), "token error");
+        assert_eq!(
+            db.injections(Some("users"), Some("SQL"), None, 100)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            db.injections(None, None, Some("src/auth"), 100)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        db.clear_file_data("src/auth.py").unwrap();
+        assert_eq!(db.injections(None, None, None, 100).unwrap().len(), 2);
+        db.set_scan_injections(false).unwrap();
+        assert!(!db.scan_injections().unwrap());
+        assert!(db.injections(None, None, None, 100).unwrap().is_empty());
     }
 
     #[test]
-    fn test_normalize_screaming_snake() {
-        assert_eq!(normalize_symbol_name("TOKEN_EXPIRY"), "token expiry");
-        assert_eq!(normalize_symbol_name("MAX_RETRY_COUNT"), "max retry count");
+    fn test_audit_log_tail_and_prune() {
+        let db = Database::open_memory().unwrap();
+        for (i, tool) in ["cartog_refs", "cartog_search", "cartog_refs"]
+            .iter()
+            .enumerate()
+        {
+            db.insert_audit_entry(&AuditEntry {
+                timestamp: 1_000.0 + i as f64,
+                time: String::new(),
+                tool: tool.to_string(),
+                params: format!("{{\"n\":{i}}}"),
+                result_count: Some(i as u32),
+                duration_ms: 5,
+                ok: true,
+            })
+            .unwrap();
+        }
+
+        let tail = db.audit_tail(2, None).unwrap();
+        assert_eq!(tail.len(), 2);
+        assert_eq!(tail[0].tool, "cartog_search");
+        assert_eq!(tail[1].params, "{\"n\":2}");
+        assert_eq!(tail[1].time, "1970-01-01T00:16:42Z");
+
+        let refs = db.audit_tail(10, Some("cartog_refs")).unwrap();
+        assert_eq!(refs.len(), 2);
+
+        assert_eq!(db.prune_audit_log(1_001.5).unwrap(), 2);
+        assert_eq!(db.audit_tail(10, None).unwrap().len(), 1);
     }
 
     #[test]
-    fn test_normalize_acronyms() {
+    fn test_hot_symbols_count_hits_per_symbol() {
+        let db = Database::open_memory().unwrap();
+        let hit = |name: &str, file: &str| SymbolHit {
+            name: name.into(),
+            file_path: file.into(),
+            kind: SymbolKind::Function,
+        };
+        db.insert_symbol_hits(1_000.0, "cartog_search", &[hit("login", "auth.py")])
+            .unwrap();
+        db.insert_symbol_hits(
+            1_001.0,
+            "cartog_refs",
+            &[hit("login", "auth.py"), hit("logout", "auth.py")],
+        )
+        .unwrap();
+        db.insert_symbol_hits(1_002.0, "cartog_show", &[hit("login", "web.py")])
+            .unwrap();
+
+        let hot = db.hot_symbols(10, None).unwrap();
+        assert_eq!(hot.len(), 3);
+        assert_eq!((hot[0].name.as_str(), hot[0].hits), ("login", 2));
+        assert_eq!(hot[0].tools, ["cartog_refs", "cartog_search"]);
+        assert_eq!(hot[0].last_hit, "1970-01-01T00:16:41Z");
+        assert_eq!(db.hot_symbols(1, None).unwrap().len(), 1);
+
+        let recent = db.hot_symbols(10, Some(1_000.5)).unwrap();
+        assert!(recent.iter().all(|h| h.hits == 1));
+
+        db.prune_audit_log(1_001.5).unwrap();
+        let kept = db.hot_symbols(10, None).unwrap();
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].file_path, "web.py");
+    }
+
+    #[test]
+    fn test_audit_calls_pair_entries_with_hits() {
+        let db = Database::open_memory().unwrap();
+        let hit = |name: &str| SymbolHit {
+            name: name.into(),
+            file_path: "auth.py".into(),
+            kind: SymbolKind::Function,
+        };
+        for (ts, tool, hits) in [
+            (1_000.0, "cartog_search", vec![hit("logout"), hit("login")]),
+            (1_001.0, "cartog_stats", vec![]),
+            (1_002.0, "cartog_show", vec![hit("login")]),
+        ] {
+            db.insert_audit_entry(&AuditEntry {
+                timestamp: ts,
+                time: String::new(),
+                tool: tool.to_string(),
+                params: "{}".to_string(),
+                result_count: Some(hits.len() as u32),
+                duration_ms: 1,
+                ok: true,
+            })
+            .unwrap();
+            db.insert_symbol_hits(ts, tool, &hits).unwrap();
+        }
+
+        let calls = db.audit_calls(None).unwrap();
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0].hits, [hit("logout"), hit("login")]);
+        assert!(calls[1].hits.is_empty());
+        assert_eq!(calls[2].entry.tool, "cartog_show");
+
+        let recent = db.audit_calls(Some(1_001.5)).unwrap();
+        assert_eq!(recent.len(), 1);
+        assert_eq!(recent[0].hits, [hit("login")]);
+    }
+
+    #[test]
+    fn test_pragma_tuning_scales_with_size_and_memory() {
+        const MIB: u64 = 1024 * 1024;
+        let small = PragmaTuning::for_size(MIB, None);
+        assert_eq!((small.cache_kib, small.mmap_bytes), (16 * 1024, 256 * MIB));
+        let large = PragmaTuning::for_size(4096 * MIB, None);
         assert_eq!(
-            normalize_symbol_name("getHTTPResponse"),
-            "get http response"
+            (large.cache_kib, large.mmap_bytes),
+            (256 * 1024, 2048 * MIB)
         );
-        assert_eq!(normalize_symbol_name("parseJSON"), "parse json");
-        assert_eq!(normalize_symbol_name("HTMLParser"), "html parser");
+        let mid = PragmaTuning::for_size(400 * MIB, Some(16384 * MIB));
+        assert_eq!((mid.cache_kib, mid.mmap_bytes), (100 * 1024, 800 * MIB));
+        // A low-memory machine caps both.
+        let tight = PragmaTuning::for_size(4096 * MIB, Some(512 * MIB));
+        assert_eq!((tight.cache_kib, tight.mmap_bytes), (32 * 1024, 128 * MIB));
     }
 
     #[test]
-    fn test_normalize_single_word() {
-        assert_eq!(normalize_symbol_name("validate"), "validate");
-        assert_eq!(normalize_symbol_name("Token"), "token");
+    fn test_maintenance_runs_after_many_writes() {
+        let db = Database::open_memory().unwrap();
+        let report = db.maintain(true, false).unwrap();
+        assert_eq!(report.databases, 1);
+        assert!(report.analyzed && !report.vacuumed);
+        assert!(report.size_after > 0);
+
+        assert!(!db.record_writes(AUTO_MAINTENANCE_FILES - 1).unwrap());
+        assert!(db.record_writes(1).unwrap());
+        // The counter starts over.
+        assert!(!db.record_writes(AUTO_MAINTENANCE_FILES - 1).unwrap());
+        assert!(db.maintain(false, true).unwrap().vacuumed);
     }
 
     #[test]
-    fn test_normalize_empty_and_special() {
-        assert_eq!(normalize_symbol_name(""), "");
-        assert_eq!(normalize_symbol_name("_"), "");
-        assert_eq!(normalize_symbol_name("___"), "");
+    fn test_index_generation() {
+        let db = Database::open_memory().unwrap();
+        assert_eq!(db.index_generation().unwrap(), 0);
+        assert_eq!(db.bump_index_generation().unwrap(), 1);
+        assert_eq!(db.bump_index_generation().unwrap(), 2);
+        assert_eq!(db.index_generation().unwrap(), 2);
     }
 
     #[test]
-    fn test_insert_and_query_symbols() {
+    fn test_resolve_edges_follows_export_star_chain() {
         let db = Database::open_memory().unwrap();
-        let sym = test_symbol("my_func", SymbolKind::Function, "test.py", 10);
-        db.insert_symbol(&sym).unwrap();
+        for path in [
+            "src/app.js",
+            "src/decoy.ts",
+            "src/lib/index.ts",
+            "src/lib/impl.ts",
+        ] {
+            db.upsert_file(&FileInfo {
+                path: path.to_string(),
+                last_modified: 0.0,
+                hash: "h".to_string(),
+                language: "typescript".to_string(),
+                num_symbols: 1,
+                generation: 0,
+                lines: Default::default(),
+                truncated: false,
+            })
+            .unwrap();
+        }
 
-        let outline = db.outline("test.py").unwrap();
-        assert_eq!(outline.len(), 1);
-        assert_eq!(outline[0].name, "my_func");
+        // app.js: const { foo } = require('./lib')
+        let import = test_symbol("./lib", SymbolKind::Import, "src/app.js", 1)
+            .with_signature(Some("const { foo } = require('./lib');".to_string()));
+        // lib/index.ts: export * from './impl'
+        let reexport = test_symbol("./impl", SymbolKind::Import, "src/lib/index.ts", 1)
+            .with_signature(Some("export * from './impl';".to_string()));
+        // A same-directory "foo" would win the name-based fallback; the chain must beat it
+        let decoy = test_symbol("foo", SymbolKind::Function, "src/decoy.ts", 1);
+        let target = test_symbol("foo", SymbolKind::Function, "src/lib/impl.ts", 3);
+        db.insert_symbols(&[import.clone(), reexport, decoy, target.clone()])
+            .unwrap();
+
+        db.insert_edge(&Edge::new(
+            import.id.clone(),
+            "foo",
+            EdgeKind::Imports,
+            "src/app.js",
+            1,
+        ))
+        .unwrap();
+
+        assert_eq!(db.resolve_edges().unwrap(), 1);
+        let refs = db.refs("foo", Some(EdgeKind::Imports)).unwrap();
+        assert_eq!(refs[0].0.target_id.as_ref().unwrap(), &target.id);
     }
 
     #[test]
-    fn test_insert_and_query_edges() {
+    fn test_call_sites_with_target_and_line() {
         let db = Database::open_memory().unwrap();
-        let caller = test_symbol("caller_fn", SymbolKind::Function, "a.py", 1);
-        let callee = test_symbol("callee_fn", SymbolKind::Function, "b.py", 1);
-        db.insert_symbol(&caller).unwrap();
-        db.insert_symbol(&callee).unwrap();
+        let main = test_symbol("main", SymbolKind::Function, "app.py", 10);
+        let helper = test_symbol("helper", SymbolKind::Function, "util.py", 1)
+            .with_signature(Some("def helper(x: int) -> int".to_string()));
+        db.insert_symbols(&[main.clone(), helper.clone()]).unwrap();
+        db.upsert_symbol_content(
+            &main.id,
+            "main",
+            "def main():\n    x = 1\n    y = helper(x)\n    log(y)",
+            "header",
+        )
+        .unwrap();
+        db.insert_edges(&[
+            Edge::new(&main.id, "helper", EdgeKind::Calls, "app.py", 12),
+            Edge::new(&main.id, "log", EdgeKind::Calls, "app.py", 13),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
 
-        let edge = Edge {
-            source_id: caller.id.clone(),
-            target_name: "callee_fn".to_string(),
-            target_id: None,
-            kind: EdgeKind::Calls,
-            file_path: "a.py".to_string(),
-            line: 5,
-        };
-        db.insert_edge(&edge).unwrap();
+        let mut sites = db.call_sites("main", true, None).unwrap();
+        sites.sort_by_key(|s| s.edge.line);
+        assert_eq!(
+            sites[0].target.as_ref().map(|t| t.id.as_str()),
+            Some(helper.id.as_str())
+        );
+        assert_eq!(sites[0].line_text.as_deref(), Some("y = helper(x)"));
+        assert_eq!(sites[1].target, None);
+        assert_eq!(sites[1].line_text.as_deref(), Some("log(y)"));
 
-        let refs = db.refs("callee_fn", None).unwrap();
-        assert_eq!(refs.len(), 1);
-        assert_eq!(refs[0].0.source_id, caller.id);
+        let json = serde_json::to_value(&sites[0]).unwrap();
+        assert_eq!(
+            json["target_name"], "helper",
+            "edge fields stay at the top level"
+        );
+        assert_eq!(json["target"]["signature"], "def helper(x: int) -> int");
+
+        let sites = db.call_sites("main", false, None).unwrap();
+        assert!(sites.iter().all(|s| s.line_text.is_none()));
     }
 
     #[test]
-    fn test_edge_resolution() {
+    fn test_callers_only_calls_with_caller_symbol() {
         let db = Database::open_memory().unwrap();
-        let sym_a = test_symbol("process", SymbolKind::Function, "a.py", 1);
-        let sym_b = test_symbol("helper", SymbolKind::Function, "a.py", 20);
-        db.insert_symbols(&[sym_a.clone(), sym_b.clone()]).unwrap();
+        let main = test_symbol("main", SymbolKind::Function, "app.py", 10);
+        let run = test_symbol("run", SymbolKind::Method, "jobs.py", 3);
+        let helper = test_symbol("helper", SymbolKind::Function, "util.py", 1);
+        db.insert_symbols(&[main.clone(), run.clone(), helper])
+            .unwrap();
+        db.insert_edges(&[
+            Edge::new(&run.id, "helper", EdgeKind::Calls, "jobs.py", 5),
+            Edge::new(&main.id, "helper", EdgeKind::Calls, "app.py", 12),
+            Edge::new(&main.id, "helper", EdgeKind::References, "app.py", 14),
+            Edge::new(&main.id, "log", EdgeKind::Calls, "app.py", 13),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
 
-        let edge = Edge {
-            source_id: sym_a.id.clone(),
-            target_name: "helper".to_string(),
-            target_id: None,
-            kind: EdgeKind::Calls,
-            file_path: "a.py".to_string(),
-            line: 5,
-        };
-        db.insert_edge(&edge).unwrap();
+        let callers = db.callers("helper", None).unwrap();
+        let found: Vec<(&str, u32)> = callers
+            .iter()
+            .map(|c| (c.caller.name.as_str(), c.edge.line))
+            .collect();
+        assert_eq!(found, [("main", 12), ("run", 5)]);
 
-        let resolved = db.resolve_edges().unwrap();
-        assert_eq!(resolved, 1);
+        let json = serde_json::to_value(&callers[0]).unwrap();
+        assert_eq!(
+            json["file_path"], "app.py",
+            "edge fields stay at the top level"
+        );
+        assert_eq!(json["caller"]["name"], "main");
+        assert!(db.callers("missing", None).unwrap().is_empty());
     }
 
     #[test]
-    fn test_stats() {
+    fn test_callers_through_alias_and_by_language() {
         let db = Database::open_memory().unwrap();
-        let file = FileInfo {
-            path: "test.py".to_string(),
-            last_modified: 0.0,
-            hash: "abc".to_string(),
-            language: "python".to_string(),
-            num_symbols: 2,
+        for (path, language) in [
+            ("app.py", "python"),
+            ("util.py", "python"),
+            ("web.ts", "typescript"),
+        ] {
+            db.upsert_file(&FileInfo {
+                path: path.to_string(),
+                last_modified: 0.0,
+                hash: String::new(),
+                language: language.to_string(),
+                num_symbols: 1,
+                generation: 0,
+                lines: Default::default(),
+                truncated: false,
+            })
+            .unwrap();
+        }
+        let main = test_symbol("main", SymbolKind::Function, "app.py", 1);
+        let render = test_symbol("render", SymbolKind::Function, "web.ts", 1);
+        let helper = test_symbol("helper", SymbolKind::Function, "util.py", 1);
+        db.insert_symbols(&[main.clone(), render.clone(), helper])
+            .unwrap();
+        // `from util import helper as h` in app.py: the call targets the imported name.
+        db.insert_import_aliases(&[ImportAlias {
+            alias: "h".to_string(),
+            name: "helper".to_string(),
+            file_path: "app.py".to_string(),
+            line: 1,
+        }])
+        .unwrap();
+        db.insert_edges(&[
+            Edge::new(&main.id, "helper", EdgeKind::Calls, "app.py", 3),
+            Edge::new(&render.id, "helper", EdgeKind::Calls, "web.ts", 2),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
+
+        let callers = |name, lang| -> Vec<String> {
+            let found = db.callers(name, lang).unwrap();
+            found.into_iter().map(|c| c.caller.name).collect()
         };
-        db.upsert_file(&file).unwrap();
-        let sym = test_symbol("foo", SymbolKind::Function, "test.py", 1);
-        db.insert_symbol(&sym).unwrap();
+        assert_eq!(callers("helper", None), ["main", "render"]);
+        assert_eq!(callers("h", None), ["main"]);
+        assert_eq!(callers("helper", Some("python")), ["main"]);
+        assert_eq!(callers("helper", Some("typescript")), ["render"]);
+    }
+
+    #[test]
+    fn test_refs_in_path_glob() {
+        let db = Database::open_memory().unwrap();
+        db.insert_symbols(&[
+            test_symbol("login", SymbolKind::Function, "src/auth/login.py", 1),
+            test_symbol("check", SymbolKind::Function, "src/auth/jwt/check.py", 1),
+            test_symbol("submit", SymbolKind::Function, "src/api/form.py", 1),
+        ])
+        .unwrap();
+        db.insert_edges(&[
+            Edge::new(
+                "src/auth/login.py:login:1",
+                "validate",
+                EdgeKind::Calls,
+                "src/auth/login.py",
+                3,
+            ),
+            Edge::new(
+                "src/auth/jwt/check.py:check:1",
+                "validate",
+                EdgeKind::Calls,
+                "src/auth/jwt/check.py",
+                2,
+            ),
+            Edge::new(
+                "src/api/form.py:submit:1",
+                "validate",
+                EdgeKind::Calls,
+                "src/api/form.py",
+                8,
+            ),
+            Edge::new(
+                "src/auth/login.py:login:1",
+                "validate",
+                EdgeKind::Imports,
+                "src/auth/login.py",
+                1,
+            ),
+        ])
+        .unwrap();
 
-        let stats = db.stats().unwrap();
-        assert_eq!(stats.num_files, 1);
-        assert_eq!(stats.num_symbols, 1);
+        let files = |glob: Option<&str>, kind| -> Vec<String> {
+            let mut files: Vec<String> = db
+                .refs_in("validate", kind, glob, None, None)
+                .unwrap()
+                .into_iter()
+                .map(|(e, _)| format!("{}:{}", e.file_path, e.line))
+                .collect();
+            files.sort();
+            files
+        };
+        assert_eq!(files(None, None).len(), 4);
+        assert_eq!(
+            files(Some("./src/auth/**"), Some(EdgeKind::Calls)),
+            ["src/auth/jwt/check.py:2", "src/auth/login.py:3"]
+        );
+        assert_eq!(files(Some("*/form.py"), None), ["src/api/form.py:8"]);
+        assert!(files(Some("lib/**"), None).is_empty());
     }
 
     #[test]
-    fn test_resolve_edges_same_dir_priority() {
+    fn test_language_filter() {
         let db = Database::open_memory().unwrap();
-
-        // "helper" exists in same dir (src/utils.py) and elsewhere (lib/utils.py)
-        let caller = test_symbol("process", SymbolKind::Function, "src/main.py", 1);
-        let same_dir = test_symbol("helper", SymbolKind::Function, "src/utils.py", 1);
-        let other_dir = test_symbol("helper", SymbolKind::Function, "lib/utils.py", 1);
-        db.insert_symbols(&[caller.clone(), same_dir.clone(), other_dir.clone()])
+        for (path, language) in [("a.py", "python"), ("b.ts", "typescript"), ("c.tsx", "tsx")] {
+            db.upsert_file(&FileInfo {
+                path: path.to_string(),
+                last_modified: 0.0,
+                hash: String::new(),
+                language: language.to_string(),
+                num_symbols: 1,
+                generation: 0,
+                lines: Default::default(),
+                truncated: false,
+            })
             .unwrap();
+            db.insert_symbols(&[test_symbol("validate", SymbolKind::Function, path, 1)])
+                .unwrap();
+            db.insert_edges(&[Edge::new(
+                format!("{path}:validate:1"),
+                "check",
+                EdgeKind::Calls,
+                path,
+                2,
+            )])
+            .unwrap();
+        }
 
-        let edge = Edge {
-            source_id: caller.id.clone(),
-            target_name: "helper".to_string(),
-            target_id: None,
-            kind: EdgeKind::Calls,
-            file_path: "src/main.py".to_string(),
-            line: 5,
+        let search = |lang| -> Vec<String> {
+            let found = db.search_in("valid", None, None, lang, 10).unwrap();
+            found.into_iter().map(|s| s.file_path).collect()
         };
-        db.insert_edge(&edge).unwrap();
-
-        let resolved = db.resolve_edges().unwrap();
-        assert_eq!(resolved, 1);
-
-        // Verify it resolved to the same-directory symbol
-        let refs = db.refs("helper", None).unwrap();
-        let call_edge = refs
-            .iter()
-            .find(|(e, _)| e.kind == EdgeKind::Calls)
+        assert_eq!(search(None).len(), 3);
+        assert_eq!(search(Some("python")), ["a.py"]);
+        // `typescript` covers .tsx files
+        assert_eq!(search(Some("typescript")), ["b.ts", "c.tsx"]);
+        assert!(search(Some("go")).is_empty());
+
+        let refs = db
+            .refs_in("check", None, None, None, Some("python"))
             .unwrap();
-        assert_eq!(call_edge.0.target_id.as_ref().unwrap(), &same_dir.id);
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].0.file_path, "a.py");
+        let sites = db
+            .call_sites("validate", false, Some("typescript"))
+            .unwrap();
+        assert_eq!(sites.len(), 2);
+        assert_eq!(
+            db.impact_of("check", 2, None, Some("ruby")).unwrap().len(),
+            0
+        );
+        assert_eq!(db.impact_of("check", 2, None, None).unwrap().len(), 3);
     }
 
     #[test]
-    fn test_resolve_edges_ambiguous_no_resolve() {
-        let db = Database::open_memory().unwrap();
-
-        // "helper" in two different directories, caller in a third
-        let caller = test_symbol("process", SymbolKind::Function, "app/main.py", 1);
-        let sym1 = test_symbol("helper", SymbolKind::Function, "pkg_a/utils.py", 1);
-        let sym2 = test_symbol("helper", SymbolKind::Function, "pkg_b/utils.py", 1);
-        db.insert_symbols(&[caller.clone(), sym1, sym2]).unwrap();
-
-        let edge = Edge {
-            source_id: caller.id.clone(),
-            target_name: "helper".to_string(),
-            target_id: None,
-            kind: EdgeKind::Calls,
-            file_path: "app/main.py".to_string(),
-            line: 5,
-        };
-        db.insert_edge(&edge).unwrap();
-
-        let resolved = db.resolve_edges().unwrap();
-        // Should NOT resolve because "helper" is ambiguous (2 matches globally)
-        assert_eq!(resolved, 0);
+    fn test_join_module_path() {
+        assert_eq!(
+            join_module_path("src/app.js", "./lib/util").as_deref(),
+            Some("src/lib/util")
+        );
+        assert_eq!(
+            join_module_path("src/a/b.ts", "../c").as_deref(),
+            Some("src/c")
+        );
+        assert_eq!(join_module_path("app.js", "../x"), None);
     }
 
     #[test]
@@ -1393,9 +6240,11 @@ mod tests {
             source_id: caller.id.clone(),
             target_name: "helper".to_string(),
             target_id: None,
+            target_kind: None,
             kind: EdgeKind::Calls,
             file_path: "a.py".to_string(),
             line: 5,
+            conditional: false,
         };
         db.insert_edge(&edge).unwrap();
 
@@ -1426,17 +6275,21 @@ mod tests {
                 source_id: caller.id.clone(),
                 target_name: "fetch".to_string(),
                 target_id: None,
+                target_kind: None,
                 kind: EdgeKind::Calls,
                 file_path: "a.py".to_string(),
                 line: 5,
+                conditional: false,
             },
             Edge {
                 source_id: caller.id.clone(),
                 target_name: "save".to_string(),
                 target_id: None,
+                target_kind: None,
                 kind: EdgeKind::Calls,
                 file_path: "a.py".to_string(),
                 line: 6,
+                conditional: false,
             },
         ])
         .unwrap();
@@ -1464,17 +6317,21 @@ mod tests {
                 source_id: b.id.clone(),
                 target_name: "a".to_string(),
                 target_id: Some(a.id.clone()),
+                target_kind: None,
                 kind: EdgeKind::Calls,
                 file_path: "b.py".to_string(),
                 line: 5,
+                conditional: false,
             },
             Edge {
                 source_id: c.id.clone(),
                 target_name: "b".to_string(),
                 target_id: Some(b.id.clone()),
+                target_kind: None,
                 kind: EdgeKind::Calls,
                 file_path: "c.py".to_string(),
                 line: 5,
+                conditional: false,
             },
         ])
         .unwrap();
@@ -1486,6 +6343,87 @@ mod tests {
         assert_eq!(results[1].1, 2); // second hop
     }
 
+    #[test]
+    fn test_impact_query_matches_traversal() {
+        let db = Database::open_memory().unwrap();
+        let syms: Vec<Symbol> = ["a", "b", "c", "d", "run"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| test_symbol(name, SymbolKind::Function, "m.py", i as u32 * 10 + 1))
+            .collect();
+        db.insert_symbols(&syms).unwrap();
+        let call = |from: usize, to: &str, line| {
+            Edge::new(&syms[from].id, to, EdgeKind::Calls, "m.py", line)
+        };
+        // b -> a, c -> b, a -> c (a cycle), d -> a and d -> c, run -> x
+        // where `x` is an import alias of `d`.
+        db.insert_edges(&[
+            call(1, "a", 12),
+            call(2, "b", 22),
+            call(0, "c", 2),
+            call(3, "a", 32),
+            call(3, "c", 33),
+            call(4, "x", 42),
+        ])
+        .unwrap();
+        db.insert_import_aliases(&[ImportAlias {
+            alias: "x".to_string(),
+            name: "d".to_string(),
+            file_path: "m.py".to_string(),
+            line: 1,
+        }])
+        .unwrap();
+        db.resolve_edges().unwrap();
+
+        for start in ["a", "c", "d", "x", "missing"] {
+            for depth in 0..5 {
+                let bfs = transitive_impact(start, depth, |current| {
+                    db.refs_in(current, None, None, None, None)
+                })
+                .unwrap();
+                assert_eq!(
+                    db.impact(start, depth).unwrap(),
+                    bfs,
+                    "{start} at depth {depth}"
+                );
+            }
+        }
+        let depths: Vec<u32> = db
+            .impact("a", 10)
+            .unwrap()
+            .iter()
+            .map(|(_, d)| *d)
+            .collect();
+        assert_eq!(depths[..3], [1, 1, 2]);
+        assert_eq!(depths.last(), Some(&3));
+    }
+
+    #[test]
+    fn test_raises_follows_callers_of_raisers() {
+        let db = Database::open_memory().unwrap();
+        let load = test_symbol("load", SymbolKind::Function, "store.py", 1);
+        let handler = test_symbol("handler", SymbolKind::Function, "api.py", 1);
+        let other = test_symbol("other", SymbolKind::Function, "api.py", 10);
+        db.insert_symbols(&[load.clone(), handler.clone(), other.clone()])
+            .unwrap();
+        db.insert_edges(&[
+            Edge::new(&load.id, "NotFound", EdgeKind::Raises, "store.py", 3),
+            Edge::new(&handler.id, "load", EdgeKind::Calls, "api.py", 2),
+            // Not a call: must not make `other` a raiser
+            Edge::new(&other.id, "load", EdgeKind::References, "api.py", 11),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
+
+        let results = db.raises("NotFound", 3).unwrap();
+        let names: Vec<(&str, u32)> = results
+            .iter()
+            .map(|(e, depth)| (e.source_id.as_str(), *depth))
+            .collect();
+        assert_eq!(names, vec![(load.id.as_str(), 1), (handler.id.as_str(), 2)]);
+        assert_eq!(db.raises("NotFound", 1).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_hierarchy_query() {
         let db = Database::open_memory().unwrap();
@@ -1498,9 +6436,11 @@ mod tests {
             source_id: child.id.clone(),
             target_name: "Animal".to_string(),
             target_id: None,
+            target_kind: None,
             kind: EdgeKind::Inherits,
             file_path: "a.py".to_string(),
             line: 10,
+            conditional: false,
         })
         .unwrap();
 
@@ -1521,9 +6461,11 @@ mod tests {
             source_id: import_sym.id.clone(),
             target_name: "os".to_string(),
             target_id: None,
+            target_kind: None,
             kind: EdgeKind::Imports,
             file_path: "main.py".to_string(),
             line: 1,
+            conditional: false,
         })
         .unwrap();
 
@@ -1542,9 +6484,11 @@ mod tests {
             source_id: sym.id.clone(),
             target_name: "bar".to_string(),
             target_id: None,
+            target_kind: None,
             kind: EdgeKind::Calls,
             file_path: "test.py".to_string(),
             line: 5,
+            conditional: false,
         })
         .unwrap();
         db.upsert_file(&FileInfo {
@@ -1553,6 +6497,9 @@ mod tests {
             hash: "abc".to_string(),
             language: "python".to_string(),
             num_symbols: 1,
+            generation: 0,
+            lines: Default::default(),
+            truncated: false,
         })
         .unwrap();
 
@@ -1576,17 +6523,21 @@ mod tests {
                 source_id: child.id.clone(),
                 target_name: "AuthService".to_string(),
                 target_id: None,
+                target_kind: None,
                 kind: EdgeKind::Inherits,
                 file_path: "a.py".to_string(),
                 line: 20,
+                conditional: false,
             },
             Edge {
                 source_id: caller.id.clone(),
                 target_name: "AuthService".to_string(),
                 target_id: None,
+                target_kind: None,
                 kind: EdgeKind::Calls,
                 file_path: "b.py".to_string(),
                 line: 5,
+                conditional: false,
             },
         ])
         .unwrap();
@@ -1610,6 +6561,58 @@ mod tests {
         assert!(raises.is_empty());
     }
 
+    #[test]
+    fn test_refs_ordered_by_proximity() {
+        let db = Database::open_memory().unwrap();
+        let def = test_symbol("validate", SymbolKind::Function, "auth/tokens.py", 1);
+        let callers = [
+            test_symbol("handler", SymbolKind::Function, "web/app.py", 1),
+            test_symbol("check", SymbolKind::Function, "auth/sub/check.py", 1),
+            test_symbol("login", SymbolKind::Function, "auth/service.py", 1),
+            test_symbol("refresh", SymbolKind::Function, "auth/tokens.py", 20),
+        ];
+        db.insert_symbol(&def).unwrap();
+        db.insert_symbols(&callers).unwrap();
+        let edges: Vec<Edge> = callers
+            .iter()
+            .map(|c| {
+                Edge::new(
+                    &c.id,
+                    "validate",
+                    EdgeKind::Calls,
+                    &c.file_path,
+                    c.start_line + 1,
+                )
+            })
+            .collect();
+        db.insert_edges(&edges).unwrap();
+
+        let files: Vec<String> = db
+            .refs("validate", None)
+            .unwrap()
+            .into_iter()
+            .map(|(e, _)| e.file_path)
+            .collect();
+        assert_eq!(
+            files,
+            [
+                "auth/tokens.py",
+                "auth/service.py",
+                "auth/sub/check.py",
+                "web/app.py"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_path_proximity() {
+        assert_eq!(path_proximity("a/b.py", "a/b.py").0, 0);
+        assert_eq!(path_proximity("a/b.py", "a/c.py").0, 1);
+        assert_eq!(path_proximity("x.py", "y.py").0, 1);
+        assert_eq!(path_proximity("a/b/c.py", "a/d/e.py"), (2, Reverse(1)));
+        assert_eq!(path_proximity("a/c.py", "z/c.py"), (3, Reverse(0)));
+    }
+
     #[test]
     fn test_search_exact_match_ranks_first() {
         let db = Database::open_memory().unwrap();
@@ -1691,6 +6694,71 @@ mod tests {
         assert_eq!(results[0].name, "parse_config");
     }
 
+    #[test]
+    fn test_search_case_insensitive_non_ascii() {
+        let db = Database::open_memory().unwrap();
+        let greek = test_symbol("ΣύνοψηΔεδομένων", SymbolKind::Function, "a.py", 1);
+        let cyrillic = test_symbol("ПолучитьДанные", SymbolKind::Function, "a.py", 10);
+        db.insert_symbols(&[greek, cyrillic]).unwrap();
+
+        let results = db.search("σύνοψη", None, None, 20).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "ΣύνοψηΔεδομένων");
+
+        let results = db.search("данные", None, None, 20).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "ПолучитьДанные");
+    }
+
+    #[test]
+    fn test_search_collapse_generated_names() {
+        use crate::types::{collapse_similar, SymbolMatch};
+
+        let db = Database::open_memory().unwrap();
+        db.insert_symbols(&[
+            test_symbol("FooRequestBuilderImpl1", SymbolKind::Class, "gen.py", 1),
+            test_symbol("FooRequestBuilderImpl2", SymbolKind::Class, "gen.py", 10),
+            test_symbol("FooRequestBuilderImpl12", SymbolKind::Class, "gen.py", 20),
+            test_symbol(
+                "FooRequestBuilder_3fa9c1d2",
+                SymbolKind::Class,
+                "dist.py",
+                1,
+            ),
+            test_symbol("FooRequestBuilder_e5b7a0", SymbolKind::Class, "dist.py", 10),
+            test_symbol("FooRequestBuilder", SymbolKind::Class, "api.py", 1),
+            test_symbol("FooRequestBuilder", SymbolKind::Class, "legacy.py", 1),
+            test_symbol("FooRequestBuilderImpl3", SymbolKind::Function, "gen.py", 30),
+        ])
+        .unwrap();
+
+        let matches: Vec<SymbolMatch> = db
+            .search("FooRequestBuilder", None, None, 20)
+            .unwrap()
+            .into_iter()
+            .map(|s| SymbolMatch::new(s, "FooRequestBuilder"))
+            .collect();
+        let collapsed = collapse_similar(matches);
+        let summary: Vec<(&str, usize)> = collapsed
+            .iter()
+            .map(|m| (m.symbol.name.as_str(), m.similar.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                // Identical hand-written names are distinct definitions: never folded
+                ("FooRequestBuilder", 0),
+                ("FooRequestBuilder", 0),
+                ("FooRequestBuilderImpl3", 0),
+                ("FooRequestBuilder_3fa9c1d2", 1),
+                ("FooRequestBuilderImpl1", 2),
+            ]
+        );
+        assert!(collapsed[4]
+            .similar
+            .contains(&"gen.py:FooRequestBuilderImpl12:20".to_string()));
+    }
+
     #[test]
     fn test_search_kind_filter() {
         let db = Database::open_memory().unwrap();
@@ -1883,6 +6951,340 @@ mod tests {
         assert!(results.is_empty());
     }
 
+    #[test]
+    fn test_fts5_search_weights_name_over_content() {
+        let db = Database::open_memory().unwrap();
+        let mention = test_symbol("load", SymbolKind::Function, "app.py", 1);
+        let named = test_symbol("parse_config", SymbolKind::Function, "app.py", 20);
+        db.insert_symbols(&[mention.clone(), named.clone()])
+            .unwrap();
+        db.upsert_symbol_content(
+            &mention.id,
+            "load",
+            "def load():\n    config = read()\n    config.update(config.defaults)\n    return config",
+            "header",
+        )
+        .unwrap();
+        db.upsert_symbol_content(
+            &named.id,
+            "parse_config",
+            "def parse_config(text):\n    return toml.loads(text)",
+            "header",
+        )
+        .unwrap();
+
+        assert_eq!(db.fts_weights().unwrap(), FtsWeights::default());
+        let results = db.fts5_search("\"config\"", 10).unwrap();
+        assert_eq!(results, [named.id.clone(), mention.id.clone()]);
+
+        // Content-only weighting brings the repeated mention back on top
+        db.set_fts_weights(Some("0,0,1".parse().unwrap())).unwrap();
+        let results = db.fts5_search("\"config\"", 10).unwrap();
+        assert_eq!(results, [mention.id.clone(), named.id.clone()]);
+
+        db.set_fts_weights(None).unwrap();
+        assert_eq!(db.fts_weights().unwrap(), FtsWeights::default());
+        assert!("1,2".parse::<FtsWeights>().is_err());
+        assert!("1,-2,3".parse::<FtsWeights>().is_err());
+        assert_eq!(FtsWeights::default().to_string(), "10,5,1");
+    }
+
+    #[test]
+    fn test_query_order_independent_of_insert_order() {
+        let main = test_symbol("main", SymbolKind::Function, "app.py", 1);
+        let helper = test_symbol("helper", SymbolKind::Function, "app.py", 1);
+        let other = test_symbol("other", SymbolKind::Function, "lib.py", 1);
+        let mut symbols = vec![main.clone(), helper.clone(), other.clone()];
+        let mut edges = vec![
+            Edge::new(&main.id, "b", EdgeKind::Calls, "app.py", 2),
+            Edge::new(&main.id, "a", EdgeKind::Calls, "app.py", 2),
+            Edge::new(&helper.id, "a", EdgeKind::Calls, "app.py", 1),
+            Edge::new(&other.id, "main", EdgeKind::Calls, "lib.py", 3),
+            Edge::new(&helper.id, "main", EdgeKind::References, "app.py", 1),
+            Edge::new(&helper.id, "main", EdgeKind::Calls, "app.py", 1),
+        ];
+
+        let snapshot = |symbols: &[Symbol], edges: &[Edge]| {
+            let db = Database::open_memory().unwrap();
+            db.insert_symbols(symbols).unwrap();
+            db.insert_edges(edges).unwrap();
+            serde_json::to_string(&(
+                db.outline("app.py").unwrap(),
+                db.callees("main").unwrap(),
+                db.call_sites("main", false, None).unwrap(),
+                db.refs("main", None).unwrap(),
+                db.impact("a", 3).unwrap(),
+                db.search("ma", None, None, 10).unwrap(),
+            ))
+            .unwrap()
+        };
+
+        let forward = snapshot(&symbols, &edges);
+        symbols.reverse();
+        edges.reverse();
+        assert_eq!(forward, snapshot(&symbols, &edges));
+
+        let db = Database::open_memory().unwrap();
+        db.insert_symbols(&symbols).unwrap();
+        db.insert_edges(&edges).unwrap();
+        let targets: Vec<_> = db
+            .callees("main")
+            .unwrap()
+            .into_iter()
+            .map(|e| e.target_name)
+            .collect();
+        assert_eq!(targets, ["a", "b"]);
+        let outline: Vec<_> = db
+            .outline("app.py")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(outline, ["helper", "main"]);
+        // By depth, then file, line and kind; `main`'s referrers come at depth 2
+        let depths: Vec<_> = db
+            .impact("a", 3)
+            .unwrap()
+            .into_iter()
+            .map(|(e, d)| (e.file_path, e.line, e.kind, d))
+            .collect();
+        assert_eq!(
+            depths,
+            [
+                ("app.py".into(), 1, EdgeKind::Calls, 1),
+                ("app.py".into(), 2, EdgeKind::Calls, 1),
+                ("app.py".into(), 1, EdgeKind::Calls, 2),
+                ("app.py".into(), 1, EdgeKind::References, 2),
+                ("lib.py".into(), 3, EdgeKind::Calls, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_notes_expire_and_attach_to_search_matches() {
+        let db = Database::open_memory().unwrap();
+        let login = test_symbol("login", SymbolKind::Function, "auth.py", 1);
+        let other = test_symbol("login", SymbolKind::Function, "admin.py", 1);
+        db.insert_symbols(&[login.clone(), other.clone()]).unwrap();
+
+        let note = db
+            .add_note(&login, "checks the password twice", 1000.0, 60.0)
+            .unwrap();
+        assert_eq!(note.created, "1970-01-01T00:16:40Z");
+        assert_eq!(note.expires, "1970-01-01T00:17:40Z");
+        db.add_note(&login, "short-lived", 1000.0, 10.0).unwrap();
+
+        assert_eq!(db.notes(Some("login"), 1005.0).unwrap().len(), 2);
+        let live = db.notes(None, 1030.0).unwrap();
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].text, "checks the password twice");
+
+        let mut matches: Vec<SymbolMatch> = [login, other]
+            .into_iter()
+            .map(|s| SymbolMatch::new(s, "login"))
+            .collect();
+        db.attach_notes(&mut matches, 1030.0).unwrap();
+        assert_eq!(matches[0].notes, live);
+        assert!(matches[1].notes.is_empty());
+
+        assert_eq!(db.clear_notes().unwrap(), 2);
+        assert!(db.notes(None, 0.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reservations_conflict_extend_and_expire() {
+        let db = Database::open_memory().unwrap();
+        let login = test_symbol("login", SymbolKind::Function, "auth.py", 1);
+        let logout = test_symbol("logout", SymbolKind::Function, "auth.py", 5);
+
+        let first = db
+            .reserve_symbols(
+                std::slice::from_ref(&login),
+                "agent-a",
+                "refactor",
+                1000.0,
+                60.0,
+            )
+            .unwrap();
+        assert_eq!(first.reserved.len(), 1);
+        assert_eq!(first.reserved[0].expires, "1970-01-01T00:17:40Z");
+
+        let second = db
+            .reserve_symbols(
+                &[login.clone(), logout.clone()],
+                "agent-b",
+                "",
+                1010.0,
+                60.0,
+            )
+            .unwrap();
+        assert_eq!(second.reserved.len(), 1);
+        assert_eq!(second.reserved[0].symbol_name, "logout");
+        assert_eq!(second.conflicts.len(), 1);
+        assert_eq!(second.conflicts[0].owner, "agent-a");
+        assert_eq!(second.conflicts[0].reason, "refactor");
+
+        // The owner extends its own reservation.
+        let again = db
+            .reserve_symbols(
+                std::slice::from_ref(&login),
+                "agent-a",
+                "refactor",
+                1050.0,
+                60.0,
+            )
+            .unwrap();
+        assert_eq!(again.reserved[0].expires, "1970-01-01T00:18:30Z");
+        assert_eq!(db.reservations(Some("agent-a"), 1100.0).unwrap().len(), 1);
+        assert_eq!(db.reservations(None, 1100.0).unwrap().len(), 1);
+
+        // Expired reservations are free to take.
+        let late = db
+            .reserve_symbols(&[login], "agent-b", "", 1200.0, 60.0)
+            .unwrap();
+        assert!(late.conflicts.is_empty());
+        assert_eq!(db.reservations(Some("agent-b"), 1200.0).unwrap().len(), 1);
+
+        assert_eq!(
+            db.release_symbols("agent-b", Some(&["login".to_string()]))
+                .unwrap(),
+            1
+        );
+        assert_eq!(db.release_symbols("agent-a", None).unwrap(), 0);
+        assert!(db.reservations(None, 1200.0).unwrap().is_empty());
+        assert_eq!(db.clear_reservations().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_attach_parent_docstrings() {
+        let db = Database::open_memory().unwrap();
+        let mut store = test_symbol("SessionStore", SymbolKind::Class, "auth.py", 1);
+        store.docstring = Some("Server-side sessions with sliding expiry.".to_string());
+        let mut refresh = test_symbol("refresh", SymbolKind::Method, "auth.py", 5);
+        refresh.parent_id = Some(store.id.clone());
+        let mut helper = test_symbol("helper", SymbolKind::Function, "auth.py", 20);
+        helper.parent_id = Some("auth.py:undocumented:30".to_string());
+        let top = test_symbol("refresh_all", SymbolKind::Function, "auth.py", 40);
+        db.insert_symbols(&[store.clone(), refresh.clone(), helper.clone(), top.clone()])
+            .unwrap();
+
+        let mut matches: Vec<SymbolMatch> = [refresh, helper, top]
+            .into_iter()
+            .map(|s| SymbolMatch::new(s, "refresh"))
+            .collect();
+        let db = crate::shard::ShardedDatabase::from(db);
+        db.attach_parent_docs(&mut matches).unwrap();
+        assert_eq!(matches[0].parent_docstring, store.docstring);
+        assert!(matches[1].parent_docstring.is_none());
+        assert!(matches[2].parent_docstring.is_none());
+        let json = serde_json::to_value(&matches[2]).unwrap();
+        assert!(json.get("parent_docstring").is_none());
+    }
+
+    #[test]
+    fn test_in_transaction_rolls_back_nested_writes() {
+        let db = Database::open_memory().unwrap();
+        let err: Result<()> = db.in_transaction(|| {
+            db.insert_symbols(&[test_symbol("a", SymbolKind::Function, "a.py", 1)])?;
+            anyhow::bail!("stop")
+        });
+        assert!(err.is_err());
+        assert!(db.outline("a.py").unwrap().is_empty());
+
+        db.in_transaction(|| {
+            db.insert_symbols(&[test_symbol("a", SymbolKind::Function, "a.py", 1)])
+        })
+        .unwrap();
+        assert_eq!(db.outline("a.py").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rerank_calibration_stored_per_index() {
+        let db = Database::open_memory().unwrap();
+        assert_eq!(
+            db.rerank_calibration().unwrap(),
+            RerankCalibration::default()
+        );
+        let custom = RerankCalibration {
+            temperature: 3.0,
+            min_relevance: 0.2,
+        };
+        db.set_rerank_calibration(Some(custom)).unwrap();
+        assert_eq!(db.rerank_calibration().unwrap(), custom);
+        db.set_rerank_calibration(None).unwrap();
+        assert_eq!(
+            db.rerank_calibration().unwrap(),
+            RerankCalibration::default()
+        );
+        assert_eq!(RerankCalibration::default().to_string(), "2,0.01");
+        assert!("0,0.01".parse::<RerankCalibration>().is_err());
+        assert!("2,1.5".parse::<RerankCalibration>().is_err());
+        assert!("2".parse::<RerankCalibration>().is_err());
+    }
+
+    #[test]
+    fn test_check_and_rebuild_fts() {
+        let db = Database::open_memory().unwrap();
+        for name in ["validate_token", "refresh_token"] {
+            let sym = test_symbol(name, SymbolKind::Function, "auth.py", 1);
+            db.insert_symbol(&sym).unwrap();
+            db.upsert_symbol_content(&sym.id, name, &format!("def {name}(): pass"), "header")
+                .unwrap();
+        }
+        assert!(db.check_fts().unwrap().is_ok());
+
+        // Drift: the index lost its entries behind the triggers' back
+        db.conn
+            .execute(
+                "INSERT INTO symbol_fts(symbol_fts) VALUES ('delete-all')",
+                [],
+            )
+            .unwrap();
+        let check = db.check_fts().unwrap();
+        assert_eq!((check.content_rows, check.indexed_rows), (2, 0));
+        assert_eq!((check.missing, check.orphaned), (2, 0));
+        assert!(db.fts5_search("\"validate\"", 10).unwrap().is_empty());
+
+        assert_eq!(db.rebuild_fts().unwrap(), 2);
+        assert!(db.check_fts().unwrap().is_ok());
+        assert_eq!(db.fts5_search("validate_token", 10).unwrap().len(), 1);
+        assert!(db.quick_check().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_and_delete_orphans() {
+        let db = Database::open_memory().unwrap();
+        let kept = test_symbol("kept", SymbolKind::Function, "auth.py", 1);
+        db.insert_symbol(&kept).unwrap();
+        db.upsert_symbol_content(&kept.id, "kept", "def kept(): pass", "")
+            .unwrap();
+        db.get_or_create_embedding_id(&kept.id).unwrap();
+        db.insert_edge(&Edge::new(&kept.id, "gone", EdgeKind::Calls, "auth.py", 1))
+            .unwrap();
+        assert_eq!(db.check_orphans().unwrap(), Orphans::default());
+
+        // Rows of a symbol deleted behind the foreign keys' back
+        db.conn.execute_batch("PRAGMA foreign_keys=OFF").unwrap();
+        let gone = "auth.py:gone:abc";
+        db.upsert_symbol_content(gone, "gone", "def gone(): pass", "")
+            .unwrap();
+        db.get_or_create_embedding_id(gone).unwrap();
+        db.insert_edge(&Edge::new(gone, "kept", EdgeKind::Calls, "auth.py", 5))
+            .unwrap();
+        db.conn.execute_batch("PRAGMA foreign_keys=ON").unwrap();
+
+        let orphans = db.check_orphans().unwrap();
+        assert_eq!(
+            (orphans.edges, orphans.contents, orphans.embedding_ids),
+            (1, 1, 1)
+        );
+        assert_eq!(db.delete_orphans().unwrap(), orphans);
+        assert_eq!(db.check_orphans().unwrap().total(), 0);
+        assert!(db.check_fts().unwrap().is_ok());
+        assert_eq!(db.symbol_content_count().unwrap(), 1);
+        assert_eq!(db.callees("kept").unwrap().len(), 1);
+    }
+
     // ── RAG: Embedding Map Tests ──
 
     #[test]
@@ -1980,6 +7382,49 @@ mod tests {
         assert!(db.has_embedding("a:foo:1").unwrap());
     }
 
+    #[test]
+    fn test_vector_table_is_created_on_first_use() {
+        let db = Database::open_memory().unwrap();
+        assert!(db.has_vectors());
+        let tables = |db: &Database| -> u32 {
+            db.conn
+                .query_row(
+                    "SELECT COUNT(*) FROM sqlite_master WHERE name = 'symbol_vec'",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        assert_eq!(tables(&db), 0);
+        assert!(db.symbols_needing_embeddings().unwrap().is_empty());
+        assert_eq!(tables(&db), 1);
+        assert!(vector_support().available);
+    }
+
+    #[test]
+    fn test_without_vectors_graph_and_cleanup_still_work() {
+        let db = Database::open_memory().unwrap();
+        let db = Database {
+            vec_error: Some("no such module: vec0".to_string()),
+            ..db
+        };
+        let sym = test_symbol("login", SymbolKind::Function, "auth.py", 1);
+        db.insert_symbols(std::slice::from_ref(&sym)).unwrap();
+        db.upsert_symbol_content(&sym.id, "login", "def login(): pass", "header")
+            .unwrap();
+        db.get_or_create_embedding_id(&sym.id).unwrap();
+
+        assert!(!db.has_embedding(&sym.id).unwrap());
+        let err = db.insert_embeddings(&[(1, vec![0; 16])]).unwrap_err();
+        assert!(err.to_string().contains("no such module: vec0"));
+        assert!(db.symbols_needing_embeddings().is_err());
+
+        db.clear_rag_data_for_file("auth.py").unwrap();
+        db.clear_all_embeddings().unwrap();
+        assert_eq!(db.embedding_count().unwrap(), 0);
+        assert_eq!(db.definitions("login", None).unwrap().len(), 1);
+    }
+
     #[test]
     fn test_clear_all_embeddings() {
         let db = Database::open_memory().unwrap();
@@ -1998,6 +7443,55 @@ mod tests {
         assert_eq!(db.embedding_count().unwrap(), 0);
     }
 
+    #[test]
+    fn test_embedding_model_transition() {
+        let db = Database::open_memory().unwrap();
+        let sym = test_symbol("foo", SymbolKind::Function, "a.py", 1);
+        db.insert_symbols(std::slice::from_ref(&sym)).unwrap();
+        db.upsert_symbol_content(&sym.id, "foo", "def foo(): pass", "header")
+            .unwrap();
+        let eid = db.get_or_create_embedding_id(&sym.id).unwrap();
+        let vector = |dim: usize| -> Vec<u8> {
+            vec![0.5f32; dim]
+                .iter()
+                .flat_map(|f| f.to_le_bytes())
+                .collect()
+        };
+        db.insert_embeddings(&[(eid, vector(384))]).unwrap();
+        assert_eq!(db.embedding_model().unwrap(), None);
+
+        db.start_embedding_transition("wide", 8).unwrap();
+        assert_eq!(db.next_embedding_model().unwrap().as_deref(), Some("wide"));
+        assert_eq!(
+            db.symbols_needing_embeddings_in(VectorTable::Next).unwrap(),
+            [sym.id.as_str()]
+        );
+        db.insert_embeddings_into(VectorTable::Next, &[(eid, vector(8))])
+            .unwrap();
+        assert_eq!(db.next_embedding_count().unwrap(), 1);
+        // Both tables answer queries in their own dimension.
+        assert_eq!(db.vector_search(&vector(384), 5).unwrap()[0].0, eid);
+        assert_eq!(
+            db.vector_search_in(VectorTable::Next, &vector(8), 5)
+                .unwrap()[0]
+                .0,
+            eid
+        );
+
+        db.finish_embedding_transition(8).unwrap();
+        assert_eq!(db.embedding_model().unwrap().as_deref(), Some("wide"));
+        assert_eq!(db.next_embedding_model().unwrap(), None);
+        assert_eq!(db.vector_search(&vector(8), 5).unwrap()[0].0, eid);
+        assert!(db.finish_embedding_transition(8).is_err());
+
+        db.start_embedding_transition("other", 4).unwrap();
+        db.clear_rag_data_for_file("a.py").unwrap();
+        assert_eq!(db.next_embedding_count().unwrap(), 0);
+        db.cancel_embedding_transition().unwrap();
+        assert_eq!(db.next_embedding_model().unwrap(), None);
+        assert_eq!(db.next_embedding_count().unwrap(), 0);
+    }
+
     #[test]
     fn test_symbols_needing_embeddings() {
         let db = Database::open_memory().unwrap();
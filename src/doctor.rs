@@ -0,0 +1,187 @@
+//! Index integrity diagnostics (`cartog doctor`): everything `cartog verify` checks,
+//! plus rows left behind by removed symbols, files changed since they were indexed
+//! and the ONNX models and vector support semantic search needs, with `--fix` for
+//! what can be repaired.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::db::{self, FtsCheck, Orphans, VectorSupport};
+use crate::indexer;
+use crate::rag::embeddings::EmbeddingModelId;
+use crate::rag::reranker;
+use crate::shard::ShardedDatabase;
+
+/// Result of [`diagnose`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct DoctorReport {
+    /// Schema of the index. Older ones are upgraded when opened and newer ones
+    /// refused, so this always matches the running cartog.
+    pub schema_version: u32,
+    /// Problems reported by SQLite's `quick_check`.
+    pub database: Vec<String>,
+    pub orphans: Orphans,
+    pub fts: FtsCheck,
+    /// Indexed files whose content no longer hashes to the stored hash, or that
+    /// are gone.
+    pub stale_files: Vec<String>,
+    /// Symbols with an embedding: without them, missing models are no problem.
+    pub embeddings: u32,
+    /// The ONNX models semantic search loads.
+    pub models: Vec<ModelCheck>,
+    /// Whether the sqlite-vec extension loads, for vector search.
+    pub vectors: VectorSupport,
+    /// What `--fix` repaired, when it ran.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fixed: Option<Repairs>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ModelCheck {
+    pub name: String,
+    pub downloaded: bool,
+}
+
+/// Repairs made by [`diagnose`] with `fix`.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct Repairs {
+    /// Orphaned rows deleted.
+    pub orphans: Orphans,
+    pub files_reindexed: u32,
+    /// Rows indexed, when the FTS index was rebuilt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fts_rebuilt: Option<u32>,
+}
+
+impl DoctorReport {
+    /// Embedded symbols cannot be searched semantically: a model is missing.
+    pub fn models_missing(&self) -> bool {
+        self.embeddings > 0 && self.models.iter().any(|m| !m.downloaded)
+    }
+
+    /// Embedded symbols cannot be searched semantically: sqlite-vec does not load.
+    pub fn vectors_unavailable(&self) -> bool {
+        self.embeddings > 0 && !self.vectors.available
+    }
+
+    /// No problem found (or left after `--fix`).
+    pub fn is_ok(&self) -> bool {
+        self.database.is_empty()
+            && self.orphans.total() == 0
+            && self.fts.is_ok()
+            && self.stale_files.is_empty()
+            && !self.models_missing()
+            && !self.vectors_unavailable()
+    }
+}
+
+/// Check the index of `root`. With `fix`, delete orphaned rows, re-index stale
+/// files and rebuild a drifted FTS index, then report what is left. A database
+/// failing `quick_check` is left alone: it needs a fresh index.
+pub fn diagnose(db: &mut ShardedDatabase, root: &Path, fix: bool) -> Result<DoctorReport> {
+    let mut report = check(db, root)?;
+    if !fix || !report.database.is_empty() {
+        return Ok(report);
+    }
+
+    let mut repairs = Repairs::default();
+    if report.orphans.total() > 0 {
+        repairs.orphans = db.delete_orphans()?;
+    }
+    if !report.stale_files.is_empty() {
+        let files: Vec<PathBuf> = report.stale_files.iter().map(PathBuf::from).collect();
+        let result = db.index_files(root, &files)?;
+        repairs.files_reindexed = result.files_indexed + result.files_removed;
+    }
+    // Deleted contents leave their symbol name in the FTS index
+    if repairs.orphans.contents > 0 || !db.check_fts()?.is_ok() {
+        repairs.fts_rebuilt = Some(db.rebuild_fts()?);
+    }
+
+    report = check(db, root)?;
+    report.fixed = Some(repairs);
+    Ok(report)
+}
+
+fn check(db: &ShardedDatabase, root: &Path) -> Result<DoctorReport> {
+    let model = db
+        .embedding_model()?
+        .and_then(|name| name.parse::<EmbeddingModelId>().ok())
+        .unwrap_or_default();
+    Ok(DoctorReport {
+        schema_version: db.schema_version()?,
+        database: db.quick_check()?,
+        orphans: db.check_orphans()?,
+        fts: db.check_fts()?,
+        stale_files: stale_files(db, root)?,
+        embeddings: db.embedding_count()?,
+        models: vec![
+            ModelCheck {
+                name: model.name().to_string(),
+                downloaded: model.is_downloaded(),
+            },
+            ModelCheck {
+                name: reranker::MODEL_NAME.to_string(),
+                downloaded: reranker::is_downloaded(),
+            },
+        ],
+        vectors: db::vector_support(),
+        fixed: None,
+    })
+}
+
+/// Indexed files whose content on disk differs from what was indexed. Unlike
+/// [`indexer::staleness`], reads every file, so touched files don't count.
+fn stale_files(db: &ShardedDatabase, root: &Path) -> Result<Vec<String>> {
+    let mut stale = Vec::new();
+    for file in db.files_changed_since(0)? {
+        let changed = match std::fs::read_to_string(root.join(&file.path)) {
+            Ok(content) => indexer::file_hash(&content) != file.hash,
+            Err(_) => true,
+        };
+        if changed {
+            stale.push(file.path);
+        }
+    }
+    Ok(stale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnose_and_fix_stale_files() {
+        let dir = std::env::temp_dir().join("cartog_test_doctor");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.py"), "def a():\n    pass\n").unwrap();
+        std::fs::write(dir.join("b.py"), "def b():\n    a()\n").unwrap();
+
+        let mut db = ShardedDatabase::open(dir.join(crate::db::DB_FILE)).unwrap();
+        db.index(&dir, false).unwrap();
+        let report = diagnose(&mut db, &dir, false).unwrap();
+        assert!(report.is_ok(), "{report:?}");
+        assert_eq!(report.schema_version, crate::db::SCHEMA_VERSION);
+        assert_eq!(report.embeddings, 0);
+        assert!(report.vectors.available);
+
+        std::fs::write(dir.join("a.py"), "def renamed():\n    pass\n").unwrap();
+        std::fs::remove_file(dir.join("b.py")).unwrap();
+        let report = diagnose(&mut db, &dir, false).unwrap();
+        assert_eq!(report.stale_files, ["a.py", "b.py"]);
+        assert!(!report.is_ok());
+        assert!(report.fixed.is_none());
+
+        let report = diagnose(&mut db, &dir, true).unwrap();
+        assert!(report.is_ok(), "{report:?}");
+        assert_eq!(report.fixed.unwrap().files_reindexed, 2);
+        assert_eq!(db.search("renamed", None, None, 10).unwrap().len(), 1);
+        assert!(db.outline("b.py").unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,693 @@
+//! `cartog export`: dump the graph tables to CSV or Parquet for offline analysis
+//! (pandas, duckdb, polars), or the symbol graph to Graphviz DOT for diagrams.
+//!
+//! Edges are exported joined to their source symbol and, when resolved, to their
+//! target symbol, so the files can be analyzed without re-implementing resolution.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use rusqlite::types::ValueRef;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::shard::ShardedDatabase;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Parquet,
+    /// One Graphviz digraph of the symbol graph (see [`export_dot`]).
+    Dot,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Dot => "dot",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportTable {
+    Symbols,
+    Edges,
+    Files,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Text,
+    Int,
+    Float,
+    Bool,
+}
+
+/// One exported column: name, type, and whether it may be null.
+pub struct Column {
+    pub name: &'static str,
+    pub ty: ColumnType,
+    pub nullable: bool,
+}
+
+const fn col(name: &'static str, ty: ColumnType, nullable: bool) -> Column {
+    Column { name, ty, nullable }
+}
+
+const SYMBOL_COLUMNS: &[Column] = &[
+    col("id", ColumnType::Text, false),
+    col("name", ColumnType::Text, false),
+    col("kind", ColumnType::Text, false),
+    col("file_path", ColumnType::Text, false),
+    col("start_line", ColumnType::Int, true),
+    col("end_line", ColumnType::Int, true),
+    col("start_byte", ColumnType::Int, true),
+    col("end_byte", ColumnType::Int, true),
+    col("parent_id", ColumnType::Text, true),
+    col("signature", ColumnType::Text, true),
+    col("visibility", ColumnType::Text, true),
+    col("is_async", ColumnType::Bool, true),
+    col("docstring", ColumnType::Text, true),
+    col("entrypoint", ColumnType::Text, true),
+    col("db_table", ColumnType::Text, true),
+    col("generation", ColumnType::Int, false),
+];
+
+const EDGE_COLUMNS: &[Column] = &[
+    col("kind", ColumnType::Text, false),
+    col("source_id", ColumnType::Text, false),
+    col("source_name", ColumnType::Text, true),
+    col("source_kind", ColumnType::Text, true),
+    col("file_path", ColumnType::Text, false),
+    col("line", ColumnType::Int, true),
+    col("target_name", ColumnType::Text, false),
+    col("target_id", ColumnType::Text, true),
+    col("target_kind", ColumnType::Text, true),
+    col("target_file", ColumnType::Text, true),
+    col("target_line", ColumnType::Int, true),
+];
+
+const FILE_COLUMNS: &[Column] = &[
+    col("path", ColumnType::Text, false),
+    col("language", ColumnType::Text, true),
+    col("num_symbols", ColumnType::Int, true),
+    col("hash", ColumnType::Text, true),
+    col("last_modified", ColumnType::Float, true),
+];
+
+impl ExportTable {
+    pub const ALL: [ExportTable; 3] =
+        [ExportTable::Symbols, ExportTable::Edges, ExportTable::Files];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExportTable::Symbols => "symbols",
+            ExportTable::Edges => "edges",
+            ExportTable::Files => "files",
+        }
+    }
+
+    /// Columns in the order of [`crate::db::Database::export_rows`].
+    pub fn columns(self) -> &'static [Column] {
+        match self {
+            ExportTable::Symbols => SYMBOL_COLUMNS,
+            ExportTable::Edges => EDGE_COLUMNS,
+            ExportTable::Files => FILE_COLUMNS,
+        }
+    }
+}
+
+/// A typed cell value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Null,
+    Text(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl Cell {
+    /// Convert a SQLite value to the column's type (SQLite stores booleans as integers).
+    pub(crate) fn from_sql(value: ValueRef<'_>, ty: ColumnType) -> Cell {
+        match (value, ty) {
+            (ValueRef::Null, _) => Cell::Null,
+            (ValueRef::Integer(i), ColumnType::Bool) => Cell::Bool(i != 0),
+            (ValueRef::Integer(i), ColumnType::Float) => Cell::Float(i as f64),
+            (ValueRef::Integer(i), _) => Cell::Int(i),
+            (ValueRef::Real(f), _) => Cell::Float(f),
+            (ValueRef::Text(t) | ValueRef::Blob(t), _) => {
+                Cell::Text(String::from_utf8_lossy(t).into_owned())
+            }
+        }
+    }
+}
+
+/// One file written by [`export`].
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ExportedTable {
+    pub table: &'static str,
+    pub path: String,
+    pub rows: usize,
+}
+
+/// Write each of `tables` to `<out_dir>/<table>.<csv|parquet>`.
+pub fn export(
+    db: &ShardedDatabase,
+    format: ExportFormat,
+    tables: &[ExportTable],
+    out_dir: &Path,
+) -> Result<Vec<ExportedTable>> {
+    anyhow::ensure!(
+        format != ExportFormat::Dot,
+        "DOT export writes one graph, not tables: use export_dot"
+    );
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create {}", out_dir.display()))?;
+    let mut written = Vec::new();
+    for &table in tables {
+        let rows = db.export_rows(table)?;
+        let path: PathBuf = out_dir.join(format!("{}.{}", table.as_str(), format.extension()));
+        let file =
+            File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        match format {
+            ExportFormat::Csv => write_csv(BufWriter::new(file), table.columns(), &rows)?,
+            ExportFormat::Parquet => write_parquet(file, table, &rows)?,
+            ExportFormat::Dot => unreachable!("rejected above"),
+        }
+        written.push(ExportedTable {
+            table: table.as_str(),
+            path: path.display().to_string(),
+            rows: rows.len(),
+        });
+    }
+    Ok(written)
+}
+
+/// RFC 4180 CSV with a header row. Nulls are empty fields.
+fn write_csv(mut out: impl Write, columns: &[Column], rows: &[Vec<Cell>]) -> Result<()> {
+    let header: Vec<&str> = columns.iter().map(|c| c.name).collect();
+    writeln!(out, "{}", header.join(","))?;
+    for row in rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|cell| match cell {
+                Cell::Null => String::new(),
+                Cell::Text(s) => csv_field(s),
+                Cell::Int(i) => i.to_string(),
+                Cell::Float(f) => f.to_string(),
+                Cell::Bool(b) => b.to_string(),
+            })
+            .collect();
+        writeln!(out, "{}", fields.join(","))?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Node or edge color per edge kind in DOT output.
+fn edge_color(kind: &str) -> &'static str {
+    match kind {
+        "calls" => "#1f77b4",
+        "inherits" => "#2ca02c",
+        "imports" => "#ff7f0e",
+        "raises" => "#d62728",
+        _ => "#7f7f7f",
+    }
+}
+
+/// A graph node: a symbol, a file (the source of its imports) or an unresolved name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum DotNode {
+    Symbol(String),
+    Unresolved(String),
+}
+
+/// Write the symbol graph as a Graphviz digraph to `<out_dir>/graph.dot`.
+///
+/// Symbols are grouped into one cluster per file and edges are colored by kind;
+/// unresolved targets are dashed nodes outside the clusters, so resolution gaps stand
+/// out. With `root`, only the symbols within `depth` edges of the symbols named
+/// `root` are kept, following edges in both directions.
+pub fn export_dot(
+    db: &ShardedDatabase,
+    root: Option<&str>,
+    depth: u32,
+    out_dir: &Path,
+) -> Result<ExportedTable> {
+    let symbols = db.export_rows(ExportTable::Symbols)?;
+    let edges = db.export_rows(ExportTable::Edges)?;
+    let dot = render_dot(&symbols, &edges, root, depth)?;
+
+    std::fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create {}", out_dir.display()))?;
+    let path = out_dir.join(format!("graph.{}", ExportFormat::Dot.extension()));
+    std::fs::write(&path, dot.text)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(ExportedTable {
+        table: "graph",
+        path: path.display().to_string(),
+        rows: dot.edges,
+    })
+}
+
+struct Dot {
+    text: String,
+    edges: usize,
+}
+
+/// Render [`ExportTable::Symbols`] and [`ExportTable::Edges`] rows as DOT.
+fn render_dot(
+    symbols: &[Vec<Cell>],
+    edges: &[Vec<Cell>],
+    root: Option<&str>,
+    depth: u32,
+) -> Result<Dot> {
+    // id → (name, kind, file, line)
+    let by_id: HashMap<&str, (&str, &str, &str, i64)> = symbols
+        .iter()
+        .filter_map(|row| {
+            Some((
+                text(&row[0])?,
+                (
+                    text(&row[1])?,
+                    text(&row[2])?,
+                    text(&row[3])?,
+                    int(&row[4]).unwrap_or(0),
+                ),
+            ))
+        })
+        .collect();
+    // (kind, source, target)
+    let links: Vec<(&str, DotNode, DotNode)> = edges
+        .iter()
+        .filter_map(|row| {
+            let kind = text(&row[0])?;
+            let source = DotNode::Symbol(text(&row[1])?.to_string());
+            let target = match text(&row[7]) {
+                Some(id) => DotNode::Symbol(id.to_string()),
+                None => DotNode::Unresolved(text(&row[6])?.to_string()),
+            };
+            Some((kind, source, target))
+        })
+        .collect();
+
+    let keep = root
+        .map(|root| neighborhood(&by_id, &links, root, depth))
+        .transpose()?;
+    let kept = |node: &DotNode| keep.as_ref().map_or(true, |k| k.contains(node));
+
+    let mut nodes: BTreeSet<DotNode> = BTreeSet::new();
+    if let Some(keep) = &keep {
+        nodes.extend(keep.iter().cloned());
+    }
+    let mut lines: Vec<String> = Vec::new();
+    for (kind, source, target) in &links {
+        if !kept(source) || !kept(target) {
+            continue;
+        }
+        nodes.insert(source.clone());
+        nodes.insert(target.clone());
+        let style = if matches!(target, DotNode::Unresolved(_)) {
+            ", style=dashed"
+        } else {
+            ""
+        };
+        lines.push(format!(
+            "  {} -> {} [color=\"{}\", label=\"{kind}\"{style}];",
+            node_id(source),
+            node_id(target),
+            edge_color(kind)
+        ));
+    }
+    lines.sort();
+    lines.dedup();
+
+    let mut files: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut loose: Vec<String> = Vec::new();
+    for node in &nodes {
+        match node {
+            DotNode::Symbol(id) => match by_id.get(id.as_str()) {
+                Some((name, kind, file, line)) => {
+                    let shape = match *kind {
+                        "class" => "box",
+                        "variable" | "import" => "plaintext",
+                        _ => "ellipse",
+                    };
+                    files.entry(*file).or_default().push(format!(
+                        "    {} [label=\"{}\\n{kind} :{line}\", shape={shape}];",
+                        node_id(node),
+                        escape(name)
+                    ));
+                }
+                // Edges from a whole file (its imports) have the path as source.
+                None => loose.push(format!(
+                    "  {} [label=\"{}\", shape=note];",
+                    node_id(node),
+                    escape(id)
+                )),
+            },
+            DotNode::Unresolved(name) => loose.push(format!(
+                "  {} [label=\"{}\", style=dashed, color=\"#7f7f7f\"];",
+                node_id(node),
+                escape(name)
+            )),
+        }
+    }
+
+    let mut out = String::from(
+        "digraph cartog {\n  rankdir=LR;\n  node [fontsize=10];\n  edge [fontsize=8];\n",
+    );
+    for (i, (file, members)) in files.iter().enumerate() {
+        out.push_str(&format!(
+            "  subgraph cluster_{i} {{\n    label=\"{}\";\n",
+            escape(file)
+        ));
+        for member in members {
+            out.push_str(member);
+            out.push('\n');
+        }
+        out.push_str("  }\n");
+    }
+    for line in loose.iter().chain(&lines) {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str("}\n");
+    Ok(Dot {
+        text: out,
+        edges: lines.len(),
+    })
+}
+
+/// Nodes within `depth` edges of the symbols named `root` (or its unresolved
+/// references), in either direction.
+fn neighborhood(
+    by_id: &HashMap<&str, (&str, &str, &str, i64)>,
+    links: &[(&str, DotNode, DotNode)],
+    root: &str,
+    depth: u32,
+) -> Result<BTreeSet<DotNode>> {
+    let mut frontier: BTreeSet<DotNode> = by_id
+        .iter()
+        .filter(|(_, (name, ..))| *name == root)
+        .map(|(id, _)| DotNode::Symbol(id.to_string()))
+        .collect();
+    let unresolved = DotNode::Unresolved(root.to_string());
+    if links.iter().any(|(_, _, target)| *target == unresolved) {
+        frontier.insert(unresolved);
+    }
+    anyhow::ensure!(
+        !frontier.is_empty(),
+        "no symbol named '{root}' in the index"
+    );
+
+    let mut adjacent: HashMap<&DotNode, Vec<&DotNode>> = HashMap::new();
+    for (_, source, target) in links {
+        adjacent.entry(source).or_default().push(target);
+        adjacent.entry(target).or_default().push(source);
+    }
+    let mut seen = frontier.clone();
+    for _ in 0..depth {
+        let mut next = BTreeSet::new();
+        for node in &frontier {
+            for &neighbor in adjacent.get(node).into_iter().flatten() {
+                if seen.insert(neighbor.clone()) {
+                    next.insert(neighbor.clone());
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+    Ok(seen)
+}
+
+/// Quoted DOT identifier of a node.
+fn node_id(node: &DotNode) -> String {
+    match node {
+        DotNode::Symbol(id) => format!("\"{}\"", escape(id)),
+        DotNode::Unresolved(name) => format!("\"?{}\"", escape(name)),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn text(cell: &Cell) -> Option<&str> {
+    match cell {
+        Cell::Text(s) => Some(s),
+        _ => None,
+    }
+}
+
+fn int(cell: &Cell) -> Option<i64> {
+    match cell {
+        Cell::Int(i) => Some(*i),
+        _ => None,
+    }
+}
+
+#[cfg(not(feature = "parquet"))]
+fn write_parquet(_file: File, _table: ExportTable, _rows: &[Vec<Cell>]) -> Result<()> {
+    anyhow::bail!(
+        "Parquet export is not available: cartog was built without the `parquet` feature \
+         (rebuild with `cargo install cartog --features parquet`)"
+    )
+}
+
+/// One row group, uncompressed, with each column typed per [`ExportTable::columns`].
+#[cfg(feature = "parquet")]
+fn write_parquet(file: File, table: ExportTable, rows: &[Vec<Cell>]) -> Result<()> {
+    use std::sync::Arc;
+
+    use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    let columns = table.columns();
+    let fields: Vec<String> = columns
+        .iter()
+        .map(|c| {
+            let repetition = if c.nullable { "OPTIONAL" } else { "REQUIRED" };
+            let ty = match c.ty {
+                ColumnType::Text => "BYTE_ARRAY",
+                ColumnType::Int => "INT64",
+                ColumnType::Float => "DOUBLE",
+                ColumnType::Bool => "BOOLEAN",
+            };
+            let logical = if c.ty == ColumnType::Text {
+                " (UTF8)"
+            } else {
+                ""
+            };
+            format!("{repetition} {ty} {}{logical};", c.name)
+        })
+        .collect();
+    let message = format!("message {} {{ {} }}", table.as_str(), fields.join(" "));
+    let schema = Arc::new(parse_message_type(&message)?);
+    let props = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(file, schema, props)?;
+
+    let mut row_group = writer.next_row_group()?;
+    let mut index = 0;
+    while let Some(mut column) = row_group.next_column()? {
+        let spec = &columns[index];
+        let cells = rows.iter().map(|row| &row[index]);
+        // Definition levels: 1 = value present, 0 = null (optional columns only).
+        let mut defs: Vec<i16> = Vec::with_capacity(rows.len());
+        for cell in cells.clone() {
+            if *cell == Cell::Null && !spec.nullable {
+                anyhow::bail!("{}.{} has a null value", table.as_str(), spec.name);
+            }
+            defs.push(i16::from(*cell != Cell::Null));
+        }
+        let defs = spec.nullable.then_some(defs.as_slice());
+
+        match spec.ty {
+            ColumnType::Text => {
+                let values: Vec<ByteArray> = cells
+                    .filter_map(|c| match c {
+                        Cell::Text(s) => Some(ByteArray::from(s.as_str())),
+                        _ => None,
+                    })
+                    .collect();
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, defs, None)?;
+            }
+            ColumnType::Int => {
+                let values: Vec<i64> = cells
+                    .filter_map(|c| match c {
+                        Cell::Int(i) => Some(*i),
+                        _ => None,
+                    })
+                    .collect();
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&values, defs, None)?;
+            }
+            ColumnType::Float => {
+                let values: Vec<f64> = cells
+                    .filter_map(|c| match c {
+                        Cell::Float(f) => Some(*f),
+                        _ => None,
+                    })
+                    .collect();
+                column
+                    .typed::<DoubleType>()
+                    .write_batch(&values, defs, None)?;
+            }
+            ColumnType::Bool => {
+                let values: Vec<bool> = cells
+                    .filter_map(|c| match c {
+                        Cell::Bool(b) => Some(*b),
+                        _ => None,
+                    })
+                    .collect();
+                column
+                    .typed::<BoolType>()
+                    .write_batch(&values, defs, None)?;
+            }
+        }
+        column.close()?;
+        index += 1;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::types::{Edge, EdgeKind, Symbol, SymbolKind};
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_export_csv_joins_edge_targets() {
+        let db = Database::open_memory().unwrap();
+        let validate = Symbol::new("validate", SymbolKind::Function, "auth.py", 1, 3, 0, 40)
+            .with_signature(Some("(token, strict=True)".to_string()));
+        let login = Symbol::new("login", SymbolKind::Function, "api.py", 1, 5, 0, 60);
+        db.insert_symbols(&[validate.clone(), login.clone()])
+            .unwrap();
+        db.insert_edges(&[
+            Edge::new(&login.id, "validate", EdgeKind::Calls, "api.py", 2),
+            Edge::new(&login.id, "print", EdgeKind::Calls, "api.py", 3),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
+        let db = ShardedDatabase::from(db);
+
+        let out = std::env::temp_dir().join("cartog_test_export");
+        let _ = std::fs::remove_dir_all(&out);
+        let written = export(&db, ExportFormat::Csv, &ExportTable::ALL, &out).unwrap();
+        assert_eq!(
+            written
+                .iter()
+                .map(|w| (w.table, w.rows))
+                .collect::<Vec<_>>(),
+            [("symbols", 2), ("edges", 2), ("files", 0)]
+        );
+
+        let edges = std::fs::read_to_string(out.join("edges.csv")).unwrap();
+        let lines: Vec<&str> = edges.lines().collect();
+        assert_eq!(
+            lines[0],
+            "kind,source_id,source_name,source_kind,file_path,line,\
+             target_name,target_id,target_kind,target_file,target_line"
+        );
+        assert_eq!(
+            lines[1],
+            format!(
+                "calls,{},login,function,api.py,2,validate,{},function,auth.py,1",
+                login.id, validate.id
+            )
+        );
+        // Unresolved target: the joined columns are empty.
+        assert!(lines[2].ends_with(",print,,,,"));
+
+        let symbols = std::fs::read_to_string(out.join("symbols.csv")).unwrap();
+        assert!(symbols.contains(",\"(token, strict=True)\","));
+        assert!(symbols.lines().nth(1).unwrap().contains(",false,"));
+
+        let _ = std::fs::remove_dir_all(&out);
+    }
+
+    #[test]
+    fn test_export_dot_colors_edges_and_scopes_to_root() {
+        let db = Database::open_memory().unwrap();
+        let base = Symbol::new("Base", SymbolKind::Class, "auth.py", 1, 2, 0, 20);
+        let validate = Symbol::new("validate", SymbolKind::Function, "auth.py", 4, 6, 30, 60);
+        let login = Symbol::new("login", SymbolKind::Function, "api.py", 1, 5, 0, 60);
+        let handler = Symbol::new("handler", SymbolKind::Function, "api.py", 7, 9, 70, 99);
+        db.insert_symbols(&[
+            base.clone(),
+            validate.clone(),
+            login.clone(),
+            handler.clone(),
+        ])
+        .unwrap();
+        db.insert_edges(&[
+            Edge::new(&login.id, "validate", EdgeKind::Calls, "api.py", 2),
+            Edge::new(&login.id, "print", EdgeKind::Calls, "api.py", 3),
+            Edge::new(&handler.id, "login", EdgeKind::Calls, "api.py", 8),
+            Edge::new(&validate.id, "Base", EdgeKind::Inherits, "auth.py", 4),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
+        let db = ShardedDatabase::from(db);
+
+        let out = std::env::temp_dir().join("cartog_test_export_dot");
+        let _ = std::fs::remove_dir_all(&out);
+        let written = export_dot(&db, None, 0, &out).unwrap();
+        assert_eq!((written.table, written.rows), ("graph", 4));
+        let dot = std::fs::read_to_string(out.join("graph.dot")).unwrap();
+        assert!(dot.starts_with("digraph cartog {"));
+        assert!(dot.contains("label=\"api.py\""));
+        assert!(dot.contains(&format!(
+            "\"{}\" -> \"{}\" [color=\"#1f77b4\", label=\"calls\"];",
+            login.id, validate.id
+        )));
+        assert!(dot.contains("[color=\"#2ca02c\", label=\"inherits\"]"));
+        // The unresolved call stands out as a dashed node.
+        assert!(dot.contains("\"?print\" [label=\"print\", style=dashed"));
+
+        // One hop from `validate`: its caller and base class, not the caller's caller.
+        let written = export_dot(&db, Some("validate"), 1, &out).unwrap();
+        assert_eq!(written.rows, 2);
+        let dot = std::fs::read_to_string(out.join("graph.dot")).unwrap();
+        assert!(dot.contains(&login.id));
+        assert!(!dot.contains(&handler.id));
+        assert!(export_dot(&db, Some("missing"), 1, &out).is_err());
+
+        let _ = std::fs::remove_dir_all(&out);
+    }
+}
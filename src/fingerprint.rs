@@ -0,0 +1,257 @@
+//! Content-defined repository fingerprints, to reuse an index built in another clone.
+//!
+//! A fingerprint is the `HEAD` commit plus a SHA-256 digest of every file that differs
+//! from it (modified, staged or untracked). Two clones with the same fingerprint have
+//! the same sources wherever they are, so an index built in one is valid in the other.
+//! When only the commit matches, the files whose digests differ are re-indexed.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::db::{Database, DB_FILE};
+use crate::indexer::{git_cmd, git_head_commit, in_ignored_dir, parse_git_lines};
+use crate::shard::ShardedDatabase;
+
+/// Digest recorded for a tracked file deleted from the working tree.
+const DELETED: &str = "deleted";
+
+/// Sources of a working tree: the checked-out commit and its uncommitted changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct RepoFingerprint {
+    /// Full hash of the `HEAD` commit.
+    pub commit: String,
+    /// SHA-256 of each file differing from `commit` (relative to the indexed root),
+    /// or `"deleted"`.
+    pub dirty: BTreeMap<String, String>,
+}
+
+impl RepoFingerprint {
+    /// Fingerprint the working tree at `root`; `None` outside a git repository or
+    /// before the first commit.
+    pub fn compute(root: &Path) -> Option<Self> {
+        let commit = git_head_commit(root)?;
+        let mut paths: Vec<String> = Vec::new();
+        for args in [
+            &["diff", "--name-only", "--no-renames", "--relative", "HEAD"][..],
+            &["ls-files", "--others", "--exclude-standard"][..],
+        ] {
+            let output = git_cmd(root, args)?;
+            if !output.status.success() {
+                return None;
+            }
+            // The index itself and ignored directories never reach the index.
+            paths.extend(
+                parse_git_lines(&output.stdout)
+                    .filter(|path| !path.starts_with(DB_FILE) && !in_ignored_dir(Path::new(path))),
+            );
+        }
+
+        let dirty = paths
+            .into_iter()
+            .map(|path| {
+                let digest = match std::fs::read(root.join(&path)) {
+                    Ok(bytes) => format!("{:x}", Sha256::digest(&bytes)),
+                    Err(_) => DELETED.to_string(),
+                };
+                (path, digest)
+            })
+            .collect();
+        Some(Self { commit, dirty })
+    }
+
+    /// Short form for display: the abbreviated commit, plus a digest of the dirty
+    /// files when there are any (`1a2b3c4d5e6f+9f8e7d6c`).
+    pub fn id(&self) -> String {
+        let commit = &self.commit[..self.commit.len().min(12)];
+        if self.dirty.is_empty() {
+            return commit.to_string();
+        }
+        let mut hasher = Sha256::new();
+        for (path, digest) in &self.dirty {
+            hasher.update(path.as_bytes());
+            hasher.update([0]);
+            hasher.update(digest.as_bytes());
+            hasher.update([0]);
+        }
+        let digest = format!("{:x}", hasher.finalize());
+        format!("{commit}+{}", &digest[..8])
+    }
+
+    /// Files whose contents differ between two trees of the same commit: dirty in
+    /// either, with different digests.
+    pub fn differing(&self, other: &Self) -> Vec<String> {
+        let mut paths: Vec<String> = self
+            .dirty
+            .iter()
+            .filter(|(path, digest)| other.dirty.get(*path) != Some(digest))
+            .map(|(path, _)| path.clone())
+            .chain(
+                other
+                    .dirty
+                    .keys()
+                    .filter(|path| !self.dirty.contains_key(*path))
+                    .cloned(),
+            )
+            .collect();
+        paths.sort();
+        paths
+    }
+}
+
+/// An index taken over from another clone by [`adopt_index`].
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Adoption {
+    /// Fingerprint of the tree the adopted index was built from.
+    pub fingerprint: String,
+    /// Files that differ from that tree and must be re-indexed.
+    pub stale_files: Vec<String>,
+}
+
+/// Copy the index at `artifact` to `dest` if it was built from the commit checked
+/// out at `root`, and return the files to re-index. `Ok(None)` when the artifact
+/// was built elsewhere (or records no fingerprint), leaving `dest` untouched.
+pub fn adopt_index(artifact: &Path, root: &Path, dest: &Path) -> Result<Option<Adoption>> {
+    anyhow::ensure!(artifact.is_file(), "no index at {}", artifact.display());
+    let local = RepoFingerprint::compute(root)
+        .context("--reuse needs a git repository with at least one commit")?;
+
+    let source = ShardedDatabase::open(artifact)
+        .with_context(|| format!("Failed to open {}", artifact.display()))?;
+    anyhow::ensure!(
+        !source.is_sharded()?,
+        "{} is sharded; reuse a `cartog backup` of an unsharded index",
+        artifact.display()
+    );
+    let Some(built) = source.repo_fingerprint()? else {
+        return Ok(None);
+    };
+    if built.commit != local.commit {
+        return Ok(None);
+    }
+
+    source.backup_to(dest)?;
+    Ok(Some(Adoption {
+        fingerprint: built.id(),
+        stale_files: local.differing(&built),
+    }))
+}
+
+/// [`Adoption::stale_files`] as paths for [`crate::indexer::index_files`].
+pub fn stale_paths(adoption: &Adoption) -> Vec<PathBuf> {
+    adoption.stale_files.iter().map(PathBuf::from).collect()
+}
+
+/// Record the fingerprint of `root` as the tree `db` was built from, or clear it
+/// outside a git repository.
+pub fn record(db: &Database, root: &Path) -> Result<()> {
+    db.set_repo_fingerprint(RepoFingerprint::compute(root).as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(root: &Path, args: &[&str]) {
+        let output = git_cmd(root, args).expect("git is available");
+        assert!(output.status.success(), "git {args:?} failed");
+    }
+
+    fn fingerprint(dirty: &[(&str, &str)]) -> RepoFingerprint {
+        RepoFingerprint {
+            commit: "0123456789abcdef0123".to_string(),
+            dirty: dirty
+                .iter()
+                .map(|(path, digest)| (path.to_string(), digest.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_differing_and_id() {
+        let clean = fingerprint(&[]);
+        let ours = fingerprint(&[("a.py", "1"), ("b.py", "2")]);
+        let theirs = fingerprint(&[("b.py", "2"), ("c.py", "3")]);
+
+        assert_eq!(ours.differing(&theirs), vec!["a.py", "c.py"]);
+        assert_eq!(theirs.differing(&ours), vec!["a.py", "c.py"]);
+        assert!(ours.differing(&ours.clone()).is_empty());
+        assert_eq!(clean.differing(&ours), vec!["a.py", "b.py"]);
+
+        assert_eq!(clean.id(), "0123456789ab");
+        assert!(ours.id().starts_with("0123456789ab+"));
+        assert_ne!(ours.id(), theirs.id());
+    }
+
+    #[test]
+    fn test_adopt_index_reindexes_differing_files() {
+        let tmp = std::env::temp_dir().join("cartog_test_fingerprint");
+        let _ = std::fs::remove_dir_all(&tmp);
+        let (ours, theirs) = (tmp.join("ours"), tmp.join("theirs"));
+        std::fs::create_dir_all(&ours).unwrap();
+        git(&ours, &["init", "-q"]);
+        std::fs::write(ours.join("app.py"), "def committed():\n    pass\n").unwrap();
+        git(&ours, &["add", "-A"]);
+        git(
+            &ours,
+            &[
+                "-c",
+                "user.name=cartog",
+                "-c",
+                "user.email=cartog@example.com",
+                "commit",
+                "-q",
+                "-m",
+                "init",
+            ],
+        );
+        git(&tmp, &["clone", "-q", "ours", "theirs"]);
+
+        // Build the artifact from a dirty tree.
+        std::fs::write(ours.join("app.py"), "def edited():\n    pass\n").unwrap();
+        let artifact = tmp.join("artifact.db");
+        let db = Database::open(&artifact).unwrap();
+        crate::indexer::index_directory(&db, &ours, false).unwrap();
+        let built = db.repo_fingerprint().unwrap().unwrap();
+        assert_eq!(built.dirty.len(), 1);
+        drop(db);
+
+        let dest = theirs.join(".cartog.db");
+        let adoption = adopt_index(&artifact, &theirs, &dest).unwrap().unwrap();
+        assert_eq!(adoption.fingerprint, built.id());
+        assert_eq!(adoption.stale_files, vec!["app.py"]);
+
+        let db = Database::open(&dest).unwrap();
+        crate::indexer::index_files(&db, &theirs, &stale_paths(&adoption)).unwrap();
+        let outline = db.outline("app.py").unwrap();
+        assert!(outline.iter().any(|s| s.name == "committed"));
+        assert!(!outline.iter().any(|s| s.name == "edited"));
+        // The index no longer matches one tree exactly.
+        assert!(db.repo_fingerprint().unwrap().is_none());
+        drop(db);
+
+        // A different commit is not adopted.
+        std::fs::write(theirs.join("app.py"), "def later():\n    pass\n").unwrap();
+        git(
+            &theirs,
+            &[
+                "-c",
+                "user.name=cartog",
+                "-c",
+                "user.email=cartog@example.com",
+                "commit",
+                "-qam",
+                "later",
+            ],
+        );
+        let other = tmp.join("other.db");
+        assert!(adopt_index(&artifact, &theirs, &other).unwrap().is_none());
+        assert!(!other.exists());
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+}
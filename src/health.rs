@@ -0,0 +1,125 @@
+//! Index and server readiness in one report (`cartog health`, MCP `cartog_health`),
+//! so an orchestrator can decide whether to index before querying.
+
+use std::path::Path;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::db;
+use crate::indexer::{self, Staleness};
+use crate::rag::search::{self as rag_search, ModelsStatus};
+use crate::shard::ShardedDatabase;
+
+/// Result of [`check`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Health {
+    pub version: &'static str,
+    /// The index database, or `None` when it does not exist yet.
+    pub index: Option<IndexHealth>,
+    /// Load state of the semantic search models in this process.
+    pub models: ModelsStatus,
+    /// Whether sqlite-vec loads, for embeddings and vector search.
+    pub vectors: db::VectorSupport,
+    /// Another process (an index run, a watcher) holds the database write lock.
+    pub locked: bool,
+    /// The index is missing, empty or stale: run `cartog index` before querying.
+    pub index_recommended: bool,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct IndexHealth {
+    pub schema_version: u32,
+    /// Index runs that changed the graph.
+    pub generation: u64,
+    pub files: usize,
+    pub staleness: Staleness,
+    /// Agreement of the keyword search index with the symbol contents.
+    pub fts: db::FtsCheck,
+}
+
+/// Report on the index at `db_path` covering `root`. `db` is the already open
+/// database, if any; otherwise the database is opened only when it exists.
+pub fn check(db_path: &Path, root: &Path, db: Option<&ShardedDatabase>) -> Result<Health> {
+    let opened;
+    let db = match db {
+        Some(db) => Some(db),
+        None if db_path.exists() => {
+            opened = ShardedDatabase::open(db_path)?;
+            Some(&opened)
+        }
+        None => None,
+    };
+
+    let index = match db {
+        Some(db) => {
+            let files = db.file_mtimes()?;
+            Some(IndexHealth {
+                schema_version: db.schema_version()?,
+                generation: db.index_generation()?,
+                files: files.len(),
+                staleness: indexer::staleness(root, &files)?,
+                fts: db.check_fts()?,
+            })
+        }
+        None => None,
+    };
+    let locked = db_path.exists() && db::is_write_locked(db_path)?;
+    let index_recommended = index
+        .as_ref()
+        .map_or(true, |i| i.files == 0 || i.staleness.is_stale());
+
+    Ok(Health {
+        version: env!("CARGO_PKG_VERSION"),
+        index,
+        models: rag_search::models_status(),
+        vectors: db::vector_support(),
+        locked,
+        index_recommended,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_missing_index_and_staleness() {
+        let dir = std::env::temp_dir().join("cartog_test_health");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.py"), "def a():\n    pass\n").unwrap();
+        std::fs::write(dir.join("b.py"), "def b():\n    pass\n").unwrap();
+        let db_path = dir.join(crate::db::DB_FILE);
+
+        let health = check(&db_path, &dir, None).unwrap();
+        assert!(health.index.is_none());
+        assert!(!db_path.exists(), "check does not create the database");
+        assert!(health.index_recommended);
+
+        let mut db = ShardedDatabase::open(&db_path).unwrap();
+        db.index(&dir, false).unwrap();
+        let health = check(&db_path, &dir, Some(&db)).unwrap();
+        let index = health.index.unwrap();
+        assert_eq!(index.files, 2);
+        assert_eq!(index.generation, 1);
+        assert!(!index.staleness.is_stale());
+        assert!(index.fts.is_ok());
+        assert!(!health.locked);
+        assert!(!health.index_recommended);
+        assert!(health.vectors.available && health.vectors.fix.is_none());
+
+        std::fs::remove_file(dir.join("b.py")).unwrap();
+        std::fs::write(dir.join("c.py"), "def c():\n    pass\n").unwrap();
+        let staleness = check(&db_path, &dir, Some(&db))
+            .unwrap()
+            .index
+            .unwrap()
+            .staleness;
+        assert_eq!((staleness.new_files, staleness.deleted_files), (1, 1));
+
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,294 @@
+//! User commands run on index events, configured under `[hooks]` in `.cartog.toml`.
+//!
+//! ```toml
+//! [hooks]
+//! on_index_complete = "curl -s -d @- http://localhost:9000/cartog"
+//! on_file_indexed = "./scripts/invalidate-cache.sh"
+//! on_resolution_drop = "notify-send 'cartog: edge resolution dropped'"
+//! resolution_drop = 0.02
+//! ```
+//!
+//! Each command runs through the shell in the project root, with the event as one
+//! JSON object on stdin and its name in `CARTOG_EVENT`. Its stdout is discarded, so
+//! it cannot corrupt `--json` output or the MCP stream; a failing command is logged
+//! and never fails the index run.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+use anyhow::Result;
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::config::HooksConfig;
+use crate::indexer::{IndexResult, IndexedFile};
+use crate::shard::ShardedDatabase;
+
+/// Default drop in the resolved edge share that fires `on_resolution_drop`.
+pub const DEFAULT_RESOLUTION_DROP: f64 = 0.01;
+
+/// Edge resolution at one point in time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Resolution {
+    pub edges: u32,
+    pub resolved: u32,
+    /// `resolved / edges`, 1 for an index without edges.
+    pub rate: f64,
+}
+
+impl Resolution {
+    fn of(db: &ShardedDatabase) -> Result<Self> {
+        let stats = db.stats()?;
+        Ok(Self {
+            edges: stats.num_edges,
+            resolved: stats.num_resolved,
+            rate: if stats.num_edges == 0 {
+                1.0
+            } else {
+                f64::from(stats.num_resolved) / f64::from(stats.num_edges)
+            },
+        })
+    }
+}
+
+/// An event passed to a hook on stdin.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum HookEvent {
+    IndexComplete {
+        root: String,
+        duration_ms: u64,
+        result: IndexResult,
+    },
+    FileIndexed {
+        root: String,
+        #[serde(flatten)]
+        file: IndexedFile,
+    },
+    ResolutionDrop {
+        root: String,
+        before: Resolution,
+        after: Resolution,
+    },
+}
+
+impl HookEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::IndexComplete { .. } => "index_complete",
+            Self::FileIndexed { .. } => "file_indexed",
+            Self::ResolutionDrop { .. } => "resolution_drop",
+        }
+    }
+}
+
+/// A hook command ready to run with its event.
+#[derive(Debug, Clone)]
+pub struct HookCall {
+    command: String,
+    event: HookEvent,
+}
+
+/// The hooks of one project.
+#[derive(Debug, Clone)]
+pub struct Hooks {
+    config: HooksConfig,
+    /// Working directory of the commands: the project root.
+    dir: PathBuf,
+}
+
+/// State captured before an index run, see [`Hooks::start`].
+pub struct IndexRun {
+    started: Instant,
+    before: Option<Resolution>,
+}
+
+impl Hooks {
+    pub fn new(config: HooksConfig, dir: &Path) -> Self {
+        Self {
+            config,
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Call before an index run of `db`. Counts the resolved edges only when
+    /// `on_resolution_drop` is set.
+    pub fn start(&self, db: &ShardedDatabase) -> Result<IndexRun> {
+        let before = match self.config.on_resolution_drop {
+            Some(_) => Some(Resolution::of(db)?),
+            None => None,
+        };
+        Ok(IndexRun {
+            started: Instant::now(),
+            before,
+        })
+    }
+
+    /// The hook calls an index run of `root` triggers, in order: one per indexed
+    /// file, the resolution drop, then the completion.
+    pub fn finish(
+        &self,
+        run: IndexRun,
+        db: &ShardedDatabase,
+        root: &Path,
+        result: &IndexResult,
+    ) -> Result<Vec<HookCall>> {
+        let root = root.display().to_string();
+        let mut calls = Vec::new();
+        if let Some(command) = &self.config.on_file_indexed {
+            calls.extend(result.indexed.iter().map(|file| HookCall {
+                command: command.clone(),
+                event: HookEvent::FileIndexed {
+                    root: root.clone(),
+                    file: file.clone(),
+                },
+            }));
+        }
+        if let (Some(command), Some(before)) = (&self.config.on_resolution_drop, run.before) {
+            let after = Resolution::of(db)?;
+            let threshold = self
+                .config
+                .resolution_drop
+                .unwrap_or(DEFAULT_RESOLUTION_DROP);
+            if before.rate - after.rate >= threshold {
+                calls.push(HookCall {
+                    command: command.clone(),
+                    event: HookEvent::ResolutionDrop {
+                        root: root.clone(),
+                        before,
+                        after,
+                    },
+                });
+            }
+        }
+        if let Some(command) = &self.config.on_index_complete {
+            calls.push(HookCall {
+                command: command.clone(),
+                event: HookEvent::IndexComplete {
+                    root,
+                    duration_ms: run.started.elapsed().as_millis() as u64,
+                    result: result.clone(),
+                },
+            });
+        }
+        Ok(calls)
+    }
+
+    /// Run `calls` one after the other, waiting for each.
+    pub fn run(&self, calls: &[HookCall]) {
+        for call in calls {
+            if let Err(e) = self.run_one(call) {
+                warn!(event = call.event.name(), command = %call.command, error = %e, "hook failed");
+            }
+        }
+    }
+
+    /// [`run`](Self::run) on a background thread, so a slow hook does not hold up
+    /// the watcher or server.
+    pub fn spawn(&self, calls: Vec<HookCall>) {
+        if calls.is_empty() {
+            return;
+        }
+        let hooks = self.clone();
+        let spawned = std::thread::Builder::new()
+            .name("cartog-hooks".into())
+            .spawn(move || hooks.run(&calls));
+        if let Err(e) = spawned {
+            warn!(error = %e, "cannot start hook thread");
+        }
+    }
+
+    fn run_one(&self, call: &HookCall) -> Result<()> {
+        let payload = serde_json::to_string(&call.event)?;
+        debug!(event = call.event.name(), command = %call.command, "running hook");
+        let mut child = shell(&call.command)
+            .current_dir(&self.dir)
+            .env("CARTOG_EVENT", call.event.name())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A hook may exit without reading its input; that is not an error.
+            let _ = stdin.write_all(payload.as_bytes());
+        }
+        let status = child.wait()?;
+        anyhow::ensure!(status.success(), "exited with {status}");
+        Ok(())
+    }
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(command);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hooks_receive_events_on_stdin() {
+        let tmp = std::env::temp_dir().join("cartog_test_hooks");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(tmp.join("src")).unwrap();
+        std::fs::write(tmp.join("src/a.py"), "def a():\n    b()\n").unwrap();
+
+        let config = HooksConfig {
+            on_index_complete: Some("cat > complete.json".into()),
+            on_file_indexed: Some("cat >> files.jsonl; echo >> files.jsonl".into()),
+            on_resolution_drop: Some("echo $CARTOG_EVENT > drop.txt".into()),
+            resolution_drop: None,
+        };
+        let hooks = Hooks::new(config, &tmp);
+        let db = ShardedDatabase::from(Database::open_memory().unwrap());
+        let run = hooks.start(&db).unwrap();
+        let result = crate::indexer::index_directory(&db, &tmp.join("src"), false).unwrap();
+        let calls = hooks.finish(run, &db, &tmp, &result).unwrap();
+        let events: Vec<&str> = calls.iter().map(|c| c.event.name()).collect();
+        // `b` stays unresolved: the share of resolved edges fell from 1 to 0.
+        assert_eq!(
+            events,
+            ["file_indexed", "resolution_drop", "index_complete"]
+        );
+        hooks.run(&calls);
+
+        let file: serde_json::Value = serde_json::from_str(
+            std::fs::read_to_string(tmp.join("files.jsonl"))
+                .unwrap()
+                .trim(),
+        )
+        .unwrap();
+        assert_eq!(file["event"], "file_indexed");
+        assert_eq!(file["path"], "a.py");
+        let complete: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(tmp.join("complete.json")).unwrap())
+                .unwrap();
+        assert_eq!(complete["result"]["files_indexed"], 1);
+        assert_eq!(
+            std::fs::read_to_string(tmp.join("drop.txt"))
+                .unwrap()
+                .trim(),
+            "resolution_drop"
+        );
+
+        // Without a drop, only the other hooks fire.
+        let run = hooks.start(&db).unwrap();
+        let result = crate::indexer::index_directory(&db, &tmp.join("src"), false).unwrap();
+        let calls = hooks.finish(run, &db, &tmp, &result).unwrap();
+        let events: Vec<&str> = calls.iter().map(|c| c.event.name()).collect();
+        assert_eq!(events, ["index_complete"]);
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+}
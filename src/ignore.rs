@@ -0,0 +1,451 @@
+//! Files left out of the index: `.gitignore` files, `.cartogignore` and the
+//! `[index]` globs of `.cartog.toml`, the last two at the indexed root.
+//!
+//! ```text
+//! # .cartogignore
+//! third_party/
+//! generated/
+//! *.min.js
+//! !generated/schema.py
+//! ```
+//!
+//! ```toml
+//! [index]
+//! include = ["src/", "lib/"]   # only these (default: every supported file)
+//! exclude = ["**/fixtures/"]
+//!
+//! [index.languages]            # index matching files as this language
+//! "bin/*" = "python"
+//! ```
+//!
+//! Patterns follow gitignore rules (see [`pattern_matches`]). In `.cartogignore`, a
+//! later `!pattern` takes back an earlier exclusion, except below an excluded
+//! directory, which is never walked. `[index] exclude` always wins. Paths no
+//! `.cartogignore` or `[index]` pattern matches are left out when git ignores them:
+//! the `.gitignore` of each directory from the repository's top level down to the
+//! path, and `.git/info/exclude`, read with the `ignore` crate (unless `[index]
+//! gitignore = false` or `index --no-ignore`). Like git, a root outside any
+//! repository reads no `.gitignore` file. All of this comes on top of the built-in
+//! skipped directories (`.git`, `node_modules`, ...).
+//!
+//! The filter also decides which language a file is indexed as
+//! ([`IndexFilter::language`]): its `[index.languages]` entry, else what
+//! [`detect_file_language`] makes of its extension and first lines.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use ::ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ::ignore::Match;
+use anyhow::{Context, Result};
+use tracing::warn;
+
+use crate::config::{IndexConfig, ProjectConfig};
+use crate::languages::{detect_file_language, indexed_language};
+
+/// File name of the ignore list, at the indexed root.
+pub const IGNORE_FILE: &str = ".cartogignore";
+/// File name of git's ignore lists, in any directory.
+pub const GITIGNORE_FILE: &str = ".gitignore";
+
+/// Which files under a root are indexed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexFilter {
+    include: Vec<String>,
+    /// `.cartogignore` lines, then `[index] exclude`. The last match decides.
+    rules: Vec<Rule>,
+    /// `[index.languages]`, longest pattern first.
+    languages: Vec<(String, &'static str)>,
+    /// Consulted for paths no rule matches; none when `.gitignore` files are not read.
+    gitignore: Option<GitIgnores>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    pattern: String,
+    negated: bool,
+}
+
+impl IndexFilter {
+    /// The filter of the project indexed at `root`; keeps everything when it has
+    /// neither file. `gitignore` is false to keep the files git ignores for this
+    /// run (`index --no-ignore`).
+    pub fn load(root: &Path, gitignore: bool) -> Result<Self> {
+        let path = root.join(IGNORE_FILE);
+        let ignore = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).with_context(|| format!("failed to read {}", path.display())),
+        };
+        let config = ProjectConfig::load(root)?.index;
+        let mut filter = Self::new(&config, &ignore);
+        if gitignore && config.gitignore != Some(false) {
+            filter.gitignore = GitIgnores::new(root);
+        }
+        Ok(filter)
+    }
+
+    /// A filter from `[index]` settings and `.cartogignore` text (one pattern per
+    /// line, `#` comments, `!` negation).
+    pub fn new(config: &IndexConfig, ignore: &str) -> Self {
+        let mut rules: Vec<Rule> = ignore
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.strip_prefix('!') {
+                Some(pattern) => Rule {
+                    pattern: pattern.to_string(),
+                    negated: true,
+                },
+                None => Rule {
+                    pattern: line.to_string(),
+                    negated: false,
+                },
+            })
+            .collect();
+        rules.extend(config.exclude.iter().map(|pattern| Rule {
+            pattern: pattern.clone(),
+            negated: false,
+        }));
+        let mut languages: Vec<(String, &'static str)> = config
+            .languages
+            .iter()
+            .filter_map(|(pattern, lang)| Some((pattern.clone(), indexed_language(lang)?)))
+            .collect();
+        languages.sort_by_key(|(pattern, _)| std::cmp::Reverse(pattern.len()));
+        Self {
+            include: config.include.clone(),
+            rules,
+            languages,
+            gitignore: None,
+        }
+    }
+
+    /// Whether an `[index.languages]` entry sets the language of `rel_path`.
+    pub fn overrides_language(&self, rel_path: &str) -> bool {
+        self.language_override(rel_path).is_some()
+    }
+
+    fn language_override(&self, rel_path: &str) -> Option<&'static str> {
+        let rel = rel_path.replace('\\', "/");
+        self.languages
+            .iter()
+            .find(|(pattern, _)| pattern_matches(pattern, &rel, false))
+            .map(|(_, lang)| *lang)
+    }
+
+    /// Language the file at `rel_path` (relative to the root, on disk at `path`)
+    /// is indexed as, `None` for a file no extractor handles.
+    pub fn language(&self, rel_path: &str, path: &Path) -> Option<&'static str> {
+        self.language_override(rel_path)
+            .or_else(|| detect_file_language(path))
+    }
+
+    /// Whether the file at `rel_path` (relative to the root) is left out: it
+    /// matches no `include`, or it or one of its directories is excluded.
+    pub fn excludes(&self, rel_path: &str) -> bool {
+        let path = rel_path.replace('\\', "/");
+        if !self.include.is_empty()
+            && !self
+                .include
+                .iter()
+                .any(|p| pattern_matches(p, &path, false))
+        {
+            return true;
+        }
+        let dirs = path.match_indices('/').map(|(i, _)| &path[..i]);
+        dirs.into_iter().any(|dir| self.excluded(dir, true)) || self.excluded(&path, false)
+    }
+
+    /// Whether the directory `rel_dir` is excluded as a whole, so a walk can skip it.
+    pub fn excludes_dir(&self, rel_dir: &str) -> bool {
+        self.excluded(&rel_dir.replace('\\', "/"), true)
+    }
+
+    fn excluded(&self, path: &str, is_dir: bool) -> bool {
+        match self
+            .rules
+            .iter()
+            .rev()
+            .find(|r| pattern_matches(&r.pattern, path, is_dir))
+        {
+            Some(rule) => !rule.negated,
+            None => self
+                .gitignore
+                .as_ref()
+                .is_some_and(|git| git.ignores(path, is_dir)),
+        }
+    }
+}
+
+/// The `.gitignore` files applying to a root, each parsed the first time a path
+/// below its directory is checked.
+#[derive(Debug, Clone)]
+struct GitIgnores {
+    root: PathBuf,
+    /// The directories above the root up to the repository's top level, deepest
+    /// first; their `.gitignore` files apply below the root too.
+    parents: Vec<PathBuf>,
+    /// `.git/info/exclude` of the repository, applying everywhere below the root.
+    exclude: Arc<Gitignore>,
+    /// Directory → its `.gitignore`.
+    dirs: Arc<Mutex<HashMap<PathBuf, Option<Arc<Gitignore>>>>>,
+}
+
+impl PartialEq for GitIgnores {
+    fn eq(&self, other: &Self) -> bool {
+        self.root == other.root
+    }
+}
+
+impl GitIgnores {
+    /// `None` when `root` is not inside a git repository, which git would not
+    /// apply any `.gitignore` to either.
+    fn new(root: &Path) -> Option<Self> {
+        let top = root.ancestors().find(|dir| dir.join(".git").exists())?;
+        let mut exclude = GitignoreBuilder::new(top);
+        let exclude_file = top.join(".git").join("info").join("exclude");
+        if exclude_file.is_file() {
+            if let Some(e) = exclude.add(&exclude_file) {
+                warn!(file = %exclude_file.display(), error = %e, "skipping git exclude patterns");
+            }
+        }
+        let parents = root
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(top))
+            .map(Path::to_path_buf)
+            .collect();
+        Some(Self {
+            root: root.to_path_buf(),
+            parents,
+            exclude: Arc::new(exclude.build().unwrap_or_else(|_| Gitignore::empty())),
+            dirs: Arc::default(),
+        })
+    }
+
+    /// Whether git ignores `path` (relative to the root, a directory when `is_dir`)
+    /// itself: the deepest `.gitignore` with a matching pattern decides. Directories
+    /// above `path` are not checked; [`IndexFilter::excludes`] does that.
+    fn ignores(&self, path: &str, is_dir: bool) -> bool {
+        let full = self.root.join(path);
+        let mut dirs: Vec<PathBuf> = path
+            .match_indices('/')
+            .map(|(i, _)| self.root.join(&path[..i]))
+            .rev()
+            .collect();
+        dirs.push(self.root.clone());
+        dirs.extend(self.parents.iter().cloned());
+        for dir in &dirs {
+            let Some(gitignore) = self.load(dir) else {
+                continue;
+            };
+            match gitignore.matched(&full, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        self.exclude.matched(&full, is_dir).is_ignore()
+    }
+
+    /// The `.gitignore` of `dir`, read on first use; `None` when it has none.
+    fn load(&self, dir: &Path) -> Option<Arc<Gitignore>> {
+        let mut dirs = self.dirs.lock().unwrap_or_else(|e| e.into_inner());
+        dirs.entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let file = dir.join(GITIGNORE_FILE);
+                if !file.is_file() {
+                    return None;
+                }
+                let (gitignore, error) = Gitignore::new(&file);
+                if let Some(e) = error {
+                    warn!(file = %file.display(), error = %e, "skipping invalid .gitignore patterns");
+                }
+                Some(Arc::new(gitignore))
+            })
+            .clone()
+    }
+}
+
+/// Match a gitignore-style pattern (`.cartogignore`, CODEOWNERS) against a path
+/// relative to the root, naming a directory when `is_dir`.
+///
+/// - a leading `/` or an inner `/` anchors the pattern to the root;
+///   otherwise it matches at any depth
+/// - a pattern naming a directory matches everything below it,
+///   except `dir/*`, which matches direct children only
+/// - a trailing `/` only matches directories
+/// - `*` and `?` match within one path segment, `**` across segments
+pub(crate) fn pattern_matches(pattern: &str, path: &str, is_dir: bool) -> bool {
+    let dir_only = pattern.ends_with('/');
+    let pat = pattern.trim_end_matches('/');
+    let anchored = pat.starts_with('/') || pat.contains('/');
+    let pat = pat.trim_start_matches('/');
+    if pat.is_empty() {
+        return false;
+    }
+    let allow_prefix = !pat.ends_with("/*");
+
+    let pat_segs: Vec<&str> = pat.split('/').collect();
+    let path_segs: Vec<&str> = path.split('/').collect();
+    let starts = if anchored { 0..1 } else { 0..path_segs.len() };
+
+    starts.into_iter().any(|start| {
+        let rest = &path_segs[start..];
+        (1..=rest.len()).any(|n| {
+            let is_full = n == rest.len();
+            (!is_full || !dir_only || is_dir)
+                && (is_full || allow_prefix)
+                && segs_match(&pat_segs, &rest[..n])
+        })
+    })
+}
+
+fn segs_match(pat: &[&str], path: &[&str]) -> bool {
+    match pat.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|i| segs_match(rest, &path[i..])),
+        Some((seg, rest)) => {
+            !path.is_empty()
+                && wildcard_match(seg.as_bytes(), path[0].as_bytes())
+                && segs_match(rest, &path[1..])
+        }
+    }
+}
+
+/// `*` / `?` wildcard match within a single segment.
+fn wildcard_match(pat: &[u8], text: &[u8]) -> bool {
+    match pat.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|i| wildcard_match(rest, &text[i..])),
+        Some((b'?', rest)) => !text.is_empty() && wildcard_match(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && wildcard_match(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_file_and_config_globs() {
+        let config = IndexConfig {
+            include: Vec::new(),
+            exclude: vec!["**/fixtures/".into()],
+            ..Default::default()
+        };
+        let filter = IndexFilter::new(
+            &config,
+            "# third-party\nthird_party/\n/generated/\n*.min.js\n!/generated/keep.py\n\n!tests/fixtures/\n",
+        );
+
+        assert!(filter.excludes("third_party/lib/a.py"));
+        assert!(filter.excludes("web/third_party/b.js"));
+        assert!(filter.excludes_dir("third_party"));
+        assert!(!filter.excludes("src/third_party.py"));
+        assert!(filter.excludes("generated/api.py"));
+        assert!(!filter.excludes("src/generated/api.py"));
+        assert!(filter.excludes("static/app.min.js"));
+        assert!(!filter.excludes("static/app.js"));
+        // A negation re-includes a file, but not below an excluded directory.
+        assert!(filter.excludes("generated/keep.py"));
+        // `[index] exclude` wins over `.cartogignore` negations.
+        assert!(filter.excludes("tests/fixtures/data.py"));
+        assert!(!filter.excludes("tests/test_app.py"));
+
+        let only_src = IndexFilter::new(
+            &IndexConfig {
+                include: vec!["src/".into(), "*.go".into()],
+                ..Default::default()
+            },
+            "",
+        );
+        assert!(!only_src.excludes("src/app.py"));
+        assert!(!only_src.excludes("cmd/main.go"));
+        assert!(only_src.excludes("scripts/build.py"));
+        assert!(!only_src.excludes_dir("scripts"));
+        assert_eq!(
+            IndexFilter::default(),
+            IndexFilter::new(&Default::default(), "# none\n")
+        );
+    }
+
+    #[test]
+    fn test_gitignore_files() {
+        let tmp = std::env::temp_dir().join("cartog_test_gitignore");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(tmp.join("sub")).unwrap();
+        std::fs::create_dir_all(tmp.join(".git/info")).unwrap();
+        std::fs::write(tmp.join(".gitignore"), "build/\n*.log\n*.gen.py\n").unwrap();
+        std::fs::write(tmp.join("sub/.gitignore"), "!keep.log\nlocal.py\n").unwrap();
+        std::fs::write(tmp.join(".git/info/exclude"), "scratch.py\n").unwrap();
+        std::fs::write(tmp.join(IGNORE_FILE), "!schema.gen.py\n").unwrap();
+
+        let filter = IndexFilter::load(&tmp, true).unwrap();
+        assert!(filter.excludes_dir("build"));
+        assert!(filter.excludes("build/out.py"));
+        assert!(filter.excludes("a.log"));
+        assert!(filter.excludes("sub/other.log"));
+        // A deeper `.gitignore` overrides the root's.
+        assert!(!filter.excludes("sub/keep.log"));
+        assert!(filter.excludes("sub/local.py"));
+        assert!(!filter.excludes("local.py"));
+        assert!(filter.excludes("scratch.py"));
+        assert!(filter.excludes("api.gen.py"));
+        // `.cartogignore` takes precedence over git.
+        assert!(!filter.excludes("schema.gen.py"));
+        assert!(!filter.excludes("src/app.py"));
+
+        // The repository's `.gitignore` files above a root apply below it too.
+        let sub = IndexFilter::load(&tmp.join("sub"), true).unwrap();
+        assert!(sub.excludes("other.log"));
+        assert!(!sub.excludes("keep.log"));
+        assert!(sub.excludes("scratch.py"));
+
+        let no_ignore = IndexFilter::load(&tmp, false).unwrap();
+        assert!(!no_ignore.excludes("build/out.py"));
+        assert!(!no_ignore.excludes("a.log"));
+
+        std::fs::write(tmp.join(".cartog.toml"), "[index]\ngitignore = false\n").unwrap();
+        let filter = IndexFilter::load(&tmp, true).unwrap();
+        assert!(!filter.excludes("build/out.py"));
+        assert!(!filter.excludes("a.log"));
+
+        // Outside a git repository, `.gitignore` files are not read.
+        std::fs::remove_file(tmp.join(".cartog.toml")).unwrap();
+        std::fs::remove_dir_all(tmp.join(".git")).unwrap();
+        let filter = IndexFilter::load(&tmp, true).unwrap();
+        assert!(!filter.excludes("a.log"));
+        assert!(!filter.excludes_dir("build"));
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_language_overrides() {
+        let config = IndexConfig {
+            languages: [
+                ("bin/*".to_string(), "python".to_string()),
+                ("bin/serve".to_string(), "ruby".to_string()),
+                ("*.mts".to_string(), "typescript".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let filter = IndexFilter::new(&config, "");
+        let missing = Path::new("/nonexistent/cartog");
+        assert_eq!(filter.language("bin/deploy", missing), Some("python"));
+        assert_eq!(filter.language("bin/serve", missing), Some("ruby"));
+        assert_eq!(filter.language("web/api.mts", missing), Some("typescript"));
+        // Without an override, the extension decides.
+        assert_eq!(
+            filter.language("src/app.go", Path::new("/nonexistent/app.go")),
+            Some("go")
+        );
+        assert_eq!(filter.language("tools/deploy", missing), None);
+        assert!(filter.overrides_language("bin/deploy"));
+        assert!(!filter.overrides_language("src/app.go"));
+    }
+}
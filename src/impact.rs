@@ -0,0 +1,247 @@
+//! Grouped views of `impact` results: `impact --tree` and `impact --summary`.
+//!
+//! The flat list repeats the file and symbol of every referencing edge. The tree
+//! groups edges by depth, then file, then referencing symbol, merging its lines;
+//! the summary only counts the affected symbols per file. Symbols reached only
+//! through conditional calls (see [`Edge::conditional`]) are marked, and weigh
+//! less in the summary's ranking.
+
+use std::collections::{HashMap, HashSet};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::types::{Edge, EdgeKind};
+
+/// Impacted symbols at one distance from the queried symbol.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ImpactLevel {
+    pub depth: u32,
+    /// Files in path order.
+    pub files: Vec<ImpactFile>,
+}
+
+/// Symbols of one file at one depth.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ImpactFile {
+    pub file_path: String,
+    /// Symbols in order of their first referencing line.
+    pub symbols: Vec<ImpactSymbol>,
+}
+
+/// A symbol referencing the previous level, with all its referencing lines.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ImpactSymbol {
+    pub source_id: String,
+    /// Name part of `source_id`.
+    pub name: String,
+    /// Edge kinds of its references, in order of first use.
+    pub kinds: Vec<EdgeKind>,
+    pub lines: Vec<u32>,
+    /// All its referencing edges are conditional.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub conditional: bool,
+}
+
+/// `impact --summary`: how many symbols each file has in the impact set.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ImpactSummary {
+    /// Distinct impacted symbols across all files.
+    pub total_symbols: u32,
+    pub files: Vec<ImpactFileCount>,
+}
+
+/// Impacted symbols of one file.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ImpactFileCount {
+    pub file_path: String,
+    pub symbols: u32,
+    /// Of `symbols`, those only impacted through conditional edges.
+    pub conditional: u32,
+    /// Smallest depth at which the file is impacted.
+    pub nearest_depth: u32,
+}
+
+/// Group impact `results` into a tree.
+pub fn impact_tree(results: &[(Edge, u32)]) -> Vec<ImpactLevel> {
+    // Results list conditional edges last at each depth; the tree goes by path and line
+    let mut ordered: Vec<&(Edge, u32)> = results.iter().collect();
+    ordered.sort_by_key(|(edge, depth)| (*depth, edge.file_path.as_str(), edge.line));
+    let mut levels: Vec<ImpactLevel> = Vec::new();
+    for (edge, depth) in ordered {
+        if levels.last().map_or(true, |l| l.depth != *depth) {
+            levels.push(ImpactLevel {
+                depth: *depth,
+                files: Vec::new(),
+            });
+        }
+        let files = &mut levels.last_mut().expect("level pushed above").files;
+        let file = match files.iter().position(|f| f.file_path == edge.file_path) {
+            Some(i) => &mut files[i],
+            None => {
+                files.push(ImpactFile {
+                    file_path: edge.file_path.clone(),
+                    symbols: Vec::new(),
+                });
+                files.last_mut().expect("file pushed above")
+            }
+        };
+        match file
+            .symbols
+            .iter_mut()
+            .find(|s| s.source_id == edge.source_id)
+        {
+            Some(sym) => {
+                if !sym.kinds.contains(&edge.kind) {
+                    sym.kinds.push(edge.kind);
+                }
+                if !sym.lines.contains(&edge.line) {
+                    sym.lines.push(edge.line);
+                }
+                sym.conditional &= edge.conditional;
+            }
+            None => file.symbols.push(ImpactSymbol {
+                name: source_name(&edge.source_id, &edge.file_path).to_string(),
+                source_id: edge.source_id.clone(),
+                kinds: vec![edge.kind],
+                lines: vec![edge.line],
+                conditional: edge.conditional,
+            }),
+        }
+    }
+    levels
+}
+
+/// Count the distinct impacted symbols per file, most impacted files first.
+pub fn impact_summary(results: &[(Edge, u32)]) -> ImpactSummary {
+    let unconditional: HashSet<&str> = results
+        .iter()
+        .filter(|(edge, _)| !edge.conditional)
+        .map(|(edge, _)| edge.source_id.as_str())
+        .collect();
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut counts: HashMap<&str, (u32, u32, u32)> = HashMap::new();
+    for (edge, depth) in results {
+        let entry = counts
+            .entry(edge.file_path.as_str())
+            .or_insert((0, 0, *depth));
+        entry.2 = entry.2.min(*depth);
+        if seen.insert(edge.source_id.as_str()) {
+            entry.0 += 1;
+            if !unconditional.contains(edge.source_id.as_str()) {
+                entry.1 += 1;
+            }
+        }
+    }
+
+    let mut files: Vec<ImpactFileCount> = counts
+        .into_iter()
+        .map(
+            |(file_path, (symbols, conditional, nearest_depth))| ImpactFileCount {
+                file_path: file_path.to_string(),
+                symbols,
+                conditional,
+                nearest_depth,
+            },
+        )
+        .collect();
+    // Files with the most symbols impacted unconditionally first
+    files.sort_by(|a, b| {
+        (b.symbols - b.conditional)
+            .cmp(&(a.symbols - a.conditional))
+            .then(b.symbols.cmp(&a.symbols))
+            .then(a.nearest_depth.cmp(&b.nearest_depth))
+            .then_with(|| a.file_path.cmp(&b.file_path))
+    });
+    ImpactSummary {
+        total_symbols: seen.len() as u32,
+        files,
+    }
+}
+
+/// Qualified name of a `file:qualified.name:hash` symbol ID in `file_path`, or the
+/// whole ID when it has another form.
+fn source_name<'a>(source_id: &'a str, file_path: &str) -> &'a str {
+    source_id
+        .strip_prefix(file_path)
+        .and_then(|rest| rest.strip_prefix(':'))
+        .and_then(|rest| rest.rsplit_once(':'))
+        .map_or(source_id, |(name, _hash)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hop(source_id: &str, file: &str, line: u32, kind: EdgeKind, depth: u32) -> (Edge, u32) {
+        (Edge::new(source_id, "target", kind, file, line), depth)
+    }
+
+    fn sample() -> Vec<(Edge, u32)> {
+        vec![
+            hop("a.py:Service:1", "a.py", 3, EdgeKind::References, 1),
+            hop("a.py:login:10", "a.py", 12, EdgeKind::Calls, 1),
+            hop("a.py:login:10", "a.py", 15, EdgeKind::Calls, 1),
+            hop("b.py", "b.py", 1, EdgeKind::Imports, 1),
+            hop("a.py:login:10", "a.py", 20, EdgeKind::Calls, 2),
+            hop("c.py:handle:4", "c.py", 6, EdgeKind::Calls, 2),
+        ]
+    }
+
+    #[test]
+    fn test_impact_tree_groups_by_depth_file_and_symbol() {
+        let tree = impact_tree(&sample());
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[0].depth, 1);
+        let files: Vec<&str> = tree[0].files.iter().map(|f| f.file_path.as_str()).collect();
+        assert_eq!(files, ["a.py", "b.py"]);
+
+        assert_eq!(tree[0].files[0].symbols[0].name, "Service");
+        let login = &tree[0].files[0].symbols[1];
+        assert_eq!(login.name, "login");
+        assert_eq!(login.lines, [12, 15]);
+        assert_eq!(login.kinds, [EdgeKind::Calls]);
+        assert_eq!(tree[0].files[1].symbols[0].name, "b.py");
+        assert_eq!(tree[1].files.len(), 2);
+    }
+
+    #[test]
+    fn test_impact_summary_counts_distinct_symbols() {
+        let summary = impact_summary(&sample());
+        assert_eq!(summary.total_symbols, 4);
+        let counts: Vec<(&str, u32, u32)> = summary
+            .files
+            .iter()
+            .map(|f| (f.file_path.as_str(), f.symbols, f.nearest_depth))
+            .collect();
+        assert_eq!(counts, [("a.py", 2, 1), ("b.py", 1, 1), ("c.py", 1, 2)]);
+    }
+
+    #[test]
+    fn test_conditional_edges_weigh_less() {
+        let conditional = |(edge, depth): (Edge, u32)| (edge.with_conditional(true), depth);
+        let results = vec![
+            conditional(hop("d.py:debug:1", "d.py", 2, EdgeKind::Calls, 1)),
+            conditional(hop("d.py:trace:5", "d.py", 6, EdgeKind::Calls, 1)),
+            hop("e.py:run:1", "e.py", 2, EdgeKind::Calls, 1),
+            conditional(hop("e.py:run:1", "e.py", 4, EdgeKind::Calls, 1)),
+        ];
+        let summary = impact_summary(&results);
+        let counts: Vec<(&str, u32, u32)> = summary
+            .files
+            .iter()
+            .map(|f| (f.file_path.as_str(), f.symbols, f.conditional))
+            .collect();
+        // One unconditional caller outranks two conditional ones
+        assert_eq!(counts, [("e.py", 1, 0), ("d.py", 2, 2)]);
+
+        let tree = impact_tree(&results);
+        let symbols: Vec<(&str, bool)> = tree[0]
+            .files
+            .iter()
+            .flat_map(|f| &f.symbols)
+            .map(|s| (s.name.as_str(), s.conditional))
+            .collect();
+        assert_eq!(symbols, [("debug", true), ("trace", true), ("run", false)]);
+    }
+}
@@ -1,17 +1,24 @@
-use std::path::Path;
-use std::time::SystemTime;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 
 use anyhow::{Context, Result};
+use rayon::prelude::*;
 use sha2::{Digest, Sha256};
 use tracing::warn;
 use walkdir::WalkDir;
 
+use crate::anchors::extract_anchors;
+use crate::config::{FileLimits, ProjectConfig};
 use crate::db::Database;
-use crate::languages::{detect_language, get_extractor, Extractor};
-use crate::types::FileInfo;
+use crate::ignore::IndexFilter;
+use crate::injections::extract_injections;
+use crate::languages::{count_lines, get_extractor, grammar_versions, ExtractionResult, Extractor};
+use crate::orm::link_models;
+use crate::renames::{match_renames, Rename, SymbolBody};
+use crate::types::{FileInfo, LineCounts};
 
 /// Summary of an indexing operation.
-#[derive(Debug, Default, serde::Serialize)]
+#[derive(Debug, Clone, Default, serde::Serialize, schemars::JsonSchema)]
 pub struct IndexResult {
     pub files_indexed: u32,
     pub files_skipped: u32,
@@ -19,6 +26,37 @@ pub struct IndexResult {
     pub symbols_added: u32,
     pub edges_added: u32,
     pub edges_resolved: u32,
+    /// Symbols found again under a new ID (renamed), which kept their embeddings,
+    /// notes and pins.
+    pub symbols_renamed: u32,
+    /// Files whose extraction came from the parse cache (`index --parse-cache`).
+    pub files_from_cache: u32,
+    /// Each file (re-)indexed, for the `on_file_indexed` hook (see [`crate::hooks`]).
+    #[serde(skip)]
+    pub indexed: Vec<IndexedFile>,
+    /// Files over the per-file thresholds or caps of `[index]`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub oversized: Vec<OversizedFile>,
+}
+
+/// A file extracting more symbols or edges than `[index]` allows or warns about.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, schemars::JsonSchema)]
+pub struct OversizedFile {
+    pub path: String,
+    /// Symbols and edges extracted, before any truncation.
+    pub symbols: u32,
+    pub edges: u32,
+    /// Extraction was cut at `max_symbols_per_file` or `max_edges_per_file`.
+    pub truncated: bool,
+}
+
+/// One file stored by an index run.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct IndexedFile {
+    pub path: String,
+    pub language: String,
+    pub symbols: u32,
+    pub edges: u32,
 }
 
 /// Index a directory, updating the database incrementally.
@@ -28,16 +66,69 @@ pub struct IndexResult {
 /// 2. Git-based → diff `last_commit..HEAD` to find changed files, skip the rest without reading
 /// 3. SHA-256 fallback → read file, hash it, compare to stored hash
 pub fn index_directory(db: &Database, root: &Path, force: bool) -> Result<IndexResult> {
+    let options = IndexOptions {
+        force,
+        ..Default::default()
+    };
+    index_scoped(db, root, IndexScope::All, options)
+}
+
+/// Settings of one index run.
+#[derive(Debug, Clone, Copy)]
+pub struct IndexOptions {
+    /// Re-index every file, skipping change detection (`index --force`).
+    pub force: bool,
+    /// Leave out the files `.gitignore` files ignore; false for `index --no-ignore`.
+    pub gitignore: bool,
+}
+
+impl Default for IndexOptions {
+    fn default() -> Self {
+        Self {
+            force: false,
+            gitignore: true,
+        }
+    }
+}
+
+/// The part of a root that one database covers (see [`crate::shard`]).
+#[derive(Debug, Clone, Copy)]
+pub enum IndexScope<'a> {
+    /// Everything under the root.
+    All,
+    /// One top-level directory of the root. Paths stay relative to the root.
+    Dir(&'a str),
+    /// Only the files directly in the root.
+    RootFiles,
+}
+
+/// [`index_directory`] restricted to `scope`. Indexed files outside the scope are
+/// removed from `db`, like deleted files.
+pub fn index_scoped(
+    db: &Database,
+    root: &Path,
+    scope: IndexScope<'_>,
+    run: IndexOptions,
+) -> Result<IndexResult> {
     let mut result = IndexResult::default();
+    // An index from before stable IDs is rebuilt once; its IDs change shape, which
+    // rename detection bridges for embeddings, notes and pins.
+    let force = run.force || !db.has_stable_ids()?;
 
     let root = root.canonicalize().context("Failed to resolve root path")?;
+    let (walk_root, max_depth) = match scope {
+        IndexScope::All => (root.clone(), usize::MAX),
+        IndexScope::Dir(dir) => (root.join(dir), usize::MAX),
+        IndexScope::RootFiles => (root.clone(), 1),
+    };
 
-    // Cache one extractor (with its Parser) per language to avoid recreating parsers per file.
-    let mut extractors: std::collections::HashMap<&'static str, Box<dyn Extractor>> =
-        std::collections::HashMap::new();
+    let options = FileOptions::load(db, &root)?;
+    let stored_grammars = check_grammars(db, &options.grammars)?;
+    let filter = IndexFilter::load(&root, run.gitignore)?;
 
     // Collect files that should be indexed
     let mut current_files = std::collections::HashSet::new();
+    let mut candidates = Vec::new();
 
     // Git-based change detection: get set of files changed since last indexed commit
     let last_commit = if force {
@@ -51,10 +142,11 @@ pub fn index_directory(db: &Database, root: &Path, force: bool) -> Result<IndexR
         git_changed_files(&root, last_commit.as_deref())
     };
 
-    for entry in WalkDir::new(&root)
+    for entry in WalkDir::new(&walk_root)
         .follow_links(true)
+        .max_depth(max_depth)
         .into_iter()
-        .filter_entry(|e| !is_ignored(e))
+        .filter_entry(|e| !is_ignored(e) && !excluded_dir(&filter, &root, e))
     {
         let entry = match entry {
             Ok(e) => e,
@@ -74,7 +166,11 @@ pub fn index_directory(db: &Database, root: &Path, force: bool) -> Result<IndexR
             Err(_) => continue,
         };
 
-        let lang = match detect_language(Path::new(&rel_path)) {
+        // Not added to `current_files`: an excluded file indexed earlier is removed.
+        if filter.excludes(&rel_path) {
+            continue;
+        }
+        let lang = match filter.language(&rel_path, path) {
             Some(l) => l,
             None => continue,
         };
@@ -92,98 +188,719 @@ pub fn index_directory(db: &Database, root: &Path, force: bool) -> Result<IndexR
             }
         }
 
-        let source = match std::fs::read_to_string(path) {
-            Ok(s) => s,
-            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => continue, // binary file
-            Err(e) => {
-                warn!(file = %rel_path, error = %e, "cannot read file");
-                continue;
-            }
-        };
+        candidates.push(Candidate {
+            path: path.to_path_buf(),
+            rel_path,
+            lang,
+        });
+    }
+    index_candidates(db, &candidates, force, &options, &mut result)?;
 
-        let hash = file_hash(&source);
+    // Remove files that no longer exist
+    let all_indexed = db.all_files()?;
+    for indexed_path in all_indexed {
+        if !current_files.contains(&indexed_path) {
+            db.remove_file(&indexed_path)?;
+            result.files_removed += 1;
+        }
+    }
 
-        // Hash-based check: even for git-detected changes, skip if content is identical
-        // (handles touched-but-not-modified files)
-        if !force {
-            if let Ok(Some(existing)) = db.get_file(&rel_path) {
-                if existing.hash == hash {
-                    result.files_skipped += 1;
-                    continue;
-                }
-            }
+    // Resolve edges
+    result.edges_resolved = db.resolve_edges()?;
+
+    if result.files_indexed > 0 || result.files_removed > 0 {
+        db.bump_index_generation()?;
+        db.record_writes(result.files_indexed + result.files_removed)?;
+    }
+
+    // Store the current git commit as last indexed
+    if let Some(commit) = git_head_commit(&root) {
+        db.set_metadata("last_commit", &commit)?;
+    }
+    // Shards are never reused on their own, see `fingerprint::adopt_index`.
+    if matches!(scope, IndexScope::All) {
+        crate::fingerprint::record(db, &root)?;
+    }
+    // Every file now comes from the current grammars.
+    if force || stored_grammars.is_none() {
+        db.set_grammar_versions(&options.grammars)?;
+    }
+    db.set_stable_ids()?;
+
+    Ok(result)
+}
+
+/// Re-index only `files` (paths relative to `root`), e.g. on an editor's save notification.
+///
+/// Deleted and newly excluded files are removed from the index; files in ignored
+/// directories or in unsupported languages are skipped. Unlike [`index_directory`], the last indexed
+/// commit is left untouched, so the next full index still diffs from it. The repository
+/// fingerprint is cleared: the index no longer matches one tree.
+pub fn index_files(db: &Database, root: &Path, files: &[PathBuf]) -> Result<IndexResult> {
+    let mut result = IndexResult::default();
+    let root = root.canonicalize().context("Failed to resolve root path")?;
+    let options = FileOptions::load(db, &root)?;
+    check_grammars(db, &options.grammars)?;
+    let filter = IndexFilter::load(&root, true)?;
+
+    let mut candidates = Vec::new();
+    for file in files {
+        if in_ignored_dir(file) {
+            result.files_skipped += 1;
+            continue;
         }
+        let rel_path = file.to_string_lossy().to_string();
+        let path = root.join(file);
+        let lang = if path.is_file() && !filter.excludes(&rel_path) {
+            filter.language(&rel_path, &path)
+        } else {
+            None
+        };
 
-        let modified = file_modified(path);
+        if let Some(lang) = lang {
+            candidates.push(Candidate {
+                path,
+                rel_path,
+                lang,
+            });
+        } else if db.get_file(&rel_path)?.is_some() {
+            db.remove_file(&rel_path)?;
+            result.files_removed += 1;
+        } else {
+            result.files_skipped += 1;
+        }
+    }
+    index_candidates(db, &candidates, false, &options, &mut result)?;
 
-        // Extract symbols and edges — reuse the cached extractor for this language
-        // so the tree-sitter Parser inside is allocated only once per language.
-        let extractor = extractors
-            .entry(lang)
-            .or_insert_with(|| get_extractor(lang).expect("lang was validated by detect_language"))
-            .as_mut();
+    if result.files_indexed > 0 || result.files_removed > 0 {
+        db.set_repo_fingerprint(None)?;
+        result.edges_resolved = db.resolve_edges()?;
+        db.bump_index_generation()?;
+        db.record_writes(result.files_indexed + result.files_removed)?;
+    }
 
-        let extraction = match extractor.extract(&source, &rel_path) {
-            Ok(e) => e,
-            Err(err) => {
-                warn!(file = %rel_path, error = %err, "extraction failed");
-                continue;
+    Ok(result)
+}
+
+/// A file entry removed by [`prune_missing`] (or that would be, on a dry run).
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct PrunedFile {
+    pub path: String,
+    /// Modification time recorded when the file was last indexed (Unix seconds).
+    pub last_modified: f64,
+    /// Rows stored for the file: its entry, symbols, contents, edges, anchors and injections.
+    pub rows: u64,
+}
+
+/// Summary of a [`prune_missing`] run.
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
+pub struct PruneResult {
+    /// Nothing was removed; `files` lists what would have been.
+    pub dry_run: bool,
+    pub files_checked: u32,
+    pub files: Vec<PrunedFile>,
+    pub rows_removed: u64,
+    pub edges_resolved: u32,
+}
+
+/// Remove the entries of indexed files that no longer exist under `root`.
+///
+/// Unlike [`index_directory`], which also drops deleted files, nothing is parsed:
+/// each indexed path is only checked for existence. With `older_than`, only files
+/// whose recorded modification time is at least that old are removed, so entries
+/// for files a slow network filesystem briefly fails to list are kept.
+pub fn prune_missing(
+    db: &Database,
+    root: &Path,
+    older_than: Option<Duration>,
+    dry_run: bool,
+) -> Result<PruneResult> {
+    let cutoff = older_than.map(|age| crate::db::unix_now() - age.as_secs_f64());
+    let mut result = PruneResult {
+        dry_run,
+        ..Default::default()
+    };
+
+    for (path, last_modified) in db.file_mtimes()? {
+        result.files_checked += 1;
+        if root.join(&path).exists() || cutoff.is_some_and(|cutoff| last_modified > cutoff) {
+            continue;
+        }
+        let rows = db.file_row_count(&path)?;
+        result.rows_removed += rows;
+        result.files.push(PrunedFile {
+            path,
+            last_modified,
+            rows,
+        });
+    }
+
+    if !dry_run && !result.files.is_empty() {
+        db.in_transaction(|| {
+            for file in &result.files {
+                db.remove_file(&file.path)?;
             }
-        };
+            Ok(())
+        })?;
+        result.edges_resolved = db.resolve_edges()?;
+        db.bump_index_generation()?;
+        db.record_writes(result.files.len() as u32)?;
+    }
+    Ok(result)
+}
 
-        // Clear old data and insert new
-        db.clear_file_data(&rel_path)?;
+/// Whether any directory component of the relative path `file` is ignored.
+pub(crate) fn in_ignored_dir(file: &Path) -> bool {
+    file.parent().is_some_and(|parent| {
+        parent.components().any(|c| match c {
+            std::path::Component::Normal(name) => is_ignored_dirname(&name.to_string_lossy()),
+            std::path::Component::CurDir => false,
+            // `..` or an absolute path escapes the indexed root.
+            _ => true,
+        })
+    })
+}
 
-        let num_symbols = extraction.symbols.len() as u32;
-        let num_edges = extraction.edges.len() as u32;
+/// Whether the walk entry `entry` is a directory excluded by `.cartogignore` or
+/// `[index] exclude`, so its whole subtree is skipped.
+fn excluded_dir(filter: &IndexFilter, root: &Path, entry: &walkdir::DirEntry) -> bool {
+    entry.depth() > 0
+        && entry.file_type().is_dir()
+        && entry
+            .path()
+            .strip_prefix(root)
+            .is_ok_and(|rel| filter.excludes_dir(&rel.to_string_lossy()))
+}
 
-        db.insert_symbols(&extraction.symbols)?;
-        db.insert_edges(&extraction.edges)?;
+/// Per-index settings that apply to every file.
+struct FileOptions {
+    /// Store string literals holding SQL, HTML or regexes (`index --injections`).
+    scan_injections: bool,
+    /// Reuse cached extractions of unchanged content (`index --parse-cache`).
+    parse_cache: bool,
+    /// Current grammar fingerprint per language (see [`grammar_versions`]).
+    grammars: std::collections::BTreeMap<String, String>,
+    /// Per-file warning thresholds and caps from `[index]`.
+    limits: FileLimits,
+}
 
-        // Store symbol content for RAG/semantic search
-        let contents: Vec<(String, String, String, String)> = extraction
-            .symbols
+impl FileOptions {
+    fn load(db: &Database, root: &Path) -> Result<Self> {
+        Ok(Self {
+            scan_injections: db.scan_injections()?,
+            parse_cache: db.parse_cache()?,
+            grammars: grammar_versions(),
+            limits: ProjectConfig::load(root)?.index.file_limits(),
+        })
+    }
+
+    /// Parse cache key of `lang`: its grammar fingerprint and the cartog version,
+    /// since extractors change between releases too. `None` when the cache is off
+    /// or the language has no grammar (parsing it is cheap).
+    fn cache_key(&self, lang: &str) -> Option<String> {
+        if !self.parse_cache {
+            return None;
+        }
+        let grammar = self.grammars.get(lang)?;
+        Some(format!("{grammar}/{}", env!("CARGO_PKG_VERSION")))
+    }
+}
+
+/// Warn once per process when the grammars differ from those the index was last
+/// fully built with. Returns the recorded fingerprints.
+fn check_grammars(
+    db: &Database,
+    current: &std::collections::BTreeMap<String, String>,
+) -> Result<Option<std::collections::BTreeMap<String, String>>> {
+    static WARNED: std::sync::Once = std::sync::Once::new();
+    let stored = db.grammar_versions()?;
+    if let Some(stored) = &stored {
+        let changed: Vec<&str> = current
             .iter()
-            .filter(|sym| sym.kind != crate::types::SymbolKind::Import)
-            .filter_map(|sym| {
-                extract_symbol_content(&source, sym)
-                    .map(|(content, header)| (sym.id.clone(), sym.name.clone(), content, header))
-            })
+            .filter(|(lang, version)| stored.get(*lang) != Some(*version))
+            .map(|(lang, _)| lang.as_str())
             .collect();
-        if !contents.is_empty() {
-            db.insert_symbol_contents(&contents)?;
+        if !changed.is_empty() {
+            WARNED.call_once(|| {
+                warn!(
+                    languages = %changed.join(", "),
+                    "tree-sitter grammars changed since the index was built; \
+                     run `cartog index --force` to re-extract every file with them"
+                );
+            });
         }
+    }
+    Ok(stored)
+}
+
+/// A supported source file picked for (re-)indexing.
+struct Candidate {
+    path: PathBuf,
+    rel_path: String,
+    lang: &'static str,
+}
+
+/// A candidate whose content changed (or `force`), read and ready to extract.
+struct LoadedFile {
+    rel_path: String,
+    lang: &'static str,
+    source: String,
+    hash: String,
+    modified: f64,
+    /// Extraction found in the parse cache for this content.
+    cached: Option<ExtractionResult>,
+    cache_key: Option<String>,
+    /// Time spent on the file so far, counted against [`FILE_TIME_BUDGET`].
+    spent: Duration,
+}
+
+/// A file extracted on a worker thread, waiting to be stored.
+struct ExtractedFile {
+    file: LoadedFile,
+    extraction: ExtractionResult,
+    from_cache: bool,
+    /// Set when the file is over a threshold or cap of `[index]`.
+    oversized: Option<OversizedFile>,
+    anchors: Vec<crate::types::Anchor>,
+    injections: Vec<crate::types::Injection>,
+    lines: LineCounts,
+}
+
+/// Files per batch: a batch is read, extracted in parallel, then stored in one
+/// transaction, which bounds memory and the number of commits.
+const BATCH_FILES: usize = 256;
+
+thread_local! {
+    /// One extractor per language and thread, so the tree-sitter Parser inside is
+    /// allocated once per worker rather than once per file.
+    static EXTRACTORS: std::cell::RefCell<std::collections::HashMap<&'static str, Box<dyn Extractor>>> =
+        std::cell::RefCell::new(std::collections::HashMap::new());
+}
+
+/// Use `jobs` threads to extract files for the rest of the process (`index --jobs`).
+/// Without it, indexing uses one thread per CPU.
+pub fn set_jobs(jobs: usize) -> Result<()> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .thread_name(|i| format!("cartog-index-{i}"))
+        .build_global()
+        .context("indexing thread pool already started")
+}
+
+/// Extract and store `candidates`, skipping files whose content hash is unchanged
+/// (unless `force`). Parsing runs on the rayon pool; reads and writes stay on the
+/// calling thread, which owns the database connection.
+fn index_candidates(
+    db: &Database,
+    candidates: &[Candidate],
+    force: bool,
+    options: &FileOptions,
+    result: &mut IndexResult,
+) -> Result<()> {
+    crate::jobs::checkpoint(candidates.len(), 0)?;
+    for batch in candidates.chunks(BATCH_FILES) {
+        let mut loaded = Vec::with_capacity(batch.len());
+        for candidate in batch {
+            if let Some(file) = load_file(db, candidate, force, options, result)? {
+                loaded.push(file);
+            }
+        }
+        let extracted: Vec<ExtractedFile> = loaded
+            .into_par_iter()
+            .filter_map(|file| extract_file(file, options))
+            .collect();
+        db.in_transaction(|| {
+            for file in extracted {
+                store_file(db, file, result)?;
+            }
+            Ok(())
+        })?;
+        crate::jobs::checkpoint(0, batch.len())?;
+    }
+    Ok(())
+}
+
+/// Read a candidate and look its content up in the parse cache. `None` when its
+/// hash is unchanged (unless `force`) or it cannot be read, which is logged.
+fn load_file(
+    db: &Database,
+    candidate: &Candidate,
+    force: bool,
+    options: &FileOptions,
+    result: &mut IndexResult,
+) -> Result<Option<LoadedFile>> {
+    let rel_path = &candidate.rel_path;
+    let mut budget = FileBudget::resume(rel_path, Duration::ZERO);
+    let source = match std::fs::read_to_string(&candidate.path) {
+        Ok(s) => s,
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidData => return Ok(None), // binary file
+        Err(e) => {
+            warn!(file = %rel_path, error = %e, "cannot read file");
+            return Ok(None);
+        }
+    };
+
+    let hash = file_hash(&source);
+
+    // Hash-based check: even for git-detected changes, skip if content is identical
+    // (handles touched-but-not-modified files)
+    if !force {
+        if let Ok(Some(existing)) = db.get_file(rel_path) {
+            if existing.hash == hash {
+                result.files_skipped += 1;
+                return Ok(None);
+            }
+        }
+    }
+
+    let cache_key = options.cache_key(candidate.lang);
+    let cached = match &cache_key {
+        Some(key) => db
+            .cached_extraction(rel_path, &hash, key, crate::db::unix_now())?
+            .and_then(|json| serde_json::from_str::<ExtractionResult>(&json).ok()),
+        None => None,
+    };
+
+    budget.check("reading");
+    Ok(Some(LoadedFile {
+        rel_path: rel_path.clone(),
+        lang: candidate.lang,
+        modified: file_modified(&candidate.path),
+        source,
+        hash,
+        cached,
+        cache_key,
+        spent: budget.spent(),
+    }))
+}
+
+/// Worker side of indexing: extract symbols and edges (unless cached), then the
+/// anchors and injections that depend on them. Unparseable files are logged and
+/// dropped.
+fn extract_file(mut file: LoadedFile, options: &FileOptions) -> Option<ExtractedFile> {
+    let mut budget = FileBudget::resume(&file.rel_path, file.spent);
+    let from_cache = file.cached.is_some();
+    let mut extraction = match file.cached.take() {
+        Some(extraction) => extraction,
+        None => {
+            let extracted = EXTRACTORS.with(|extractors| {
+                extractors
+                    .borrow_mut()
+                    .entry(file.lang)
+                    .or_insert_with(|| {
+                        get_extractor(file.lang)
+                            .expect("lang was validated by IndexFilter::language")
+                    })
+                    .extract(&file.source, &file.rel_path)
+            });
+            match extracted {
+                Ok(e) => e,
+                Err(err) => {
+                    warn!(file = %file.rel_path, error = %err, "extraction failed");
+                    return None;
+                }
+            }
+        }
+    };
+
+    // Idempotent, so extractions cached before IDs were stable are re-keyed too.
+    extraction.assign_stable_ids(&file.source);
+    link_models(&file.source, file.lang, &mut extraction.symbols);
+    let oversized = limit_extraction(&file.rel_path, &mut extraction, &options.limits);
+    budget.check("extraction");
+    let anchors = extract_anchors(&file.source, &file.rel_path, file.lang, &extraction.symbols);
+    let injections = if options.scan_injections {
+        extract_injections(&file.source, &file.rel_path, file.lang, &extraction.symbols)
+    } else {
+        Vec::new()
+    };
+    let lines = count_lines(&file.source, file.lang);
+    file.spent = budget.spent();
+    Some(ExtractedFile {
+        file,
+        extraction,
+        from_cache,
+        oversized,
+        anchors,
+        injections,
+        lines,
+    })
+}
+
+/// Replace the stored data of one extracted file, carrying state over renamed
+/// symbols and filling the parse cache.
+fn store_file(db: &Database, extracted: ExtractedFile, result: &mut IndexResult) -> Result<()> {
+    let ExtractedFile {
+        file,
+        mut extraction,
+        from_cache,
+        oversized,
+        anchors,
+        injections,
+        lines,
+    } = extracted;
+    let rel_path = &file.rel_path;
+    let source = &file.source;
+    let mut budget = FileBudget::resume(rel_path, file.spent);
+
+    let truncated = oversized.as_ref().is_some_and(|o| o.truncated);
+    if from_cache {
+        result.files_from_cache += 1;
+    } else if let (Some(key), false) = (&file.cache_key, truncated) {
+        // A truncated extraction is not cached, so raising a cap re-parses the file.
+        let json = serde_json::to_string(&extraction)?;
+        db.store_extraction(rel_path, &file.hash, key, &json, crate::db::unix_now())?;
+    }
+
+    // The generation this run will bump the index to once it is done.
+    let generation = db.index_generation()? + 1;
+    for sym in &mut extraction.symbols {
+        sym.generation = generation;
+    }
+
+    let num_symbols = extraction.symbols.len() as u32;
+    let num_edges = extraction.edges.len() as u32;
+    let renames = detect_renames(db, rel_path, source, &extraction.symbols)?;
+
+    // Replace the file's data in one transaction, so readers never see it half
+    // indexed, flushing in chunks so symbol bodies are copied a chunk at a time.
+    db.in_transaction(|| {
+        db.carry_over_renames(rel_path, &renames)?;
+        let kept: std::collections::HashSet<&str> =
+            extraction.symbols.iter().map(|s| s.id.as_str()).collect();
+        db.clear_file_data_keeping(rel_path, &kept)?;
+
+        for chunk in extraction.symbols.chunks(FLUSH_CHUNK) {
+            db.insert_symbols(chunk)?;
+            // Store symbol content for RAG/semantic search
+            let contents: Vec<(String, String, String, String)> = chunk
+                .iter()
+                .filter(|sym| sym.kind != crate::types::SymbolKind::Import)
+                .filter_map(|sym| {
+                    extract_symbol_content(source, sym).map(|(content, header)| {
+                        (sym.id.clone(), sym.name.clone(), content, header)
+                    })
+                })
+                .collect();
+            if !contents.is_empty() {
+                db.insert_symbol_contents(&contents)?;
+            }
+            budget.check("storing symbols");
+        }
+        for chunk in extraction.edges.chunks(FLUSH_CHUNK) {
+            db.insert_edges(chunk)?;
+            budget.check("storing edges");
+        }
+        db.insert_anchors(&anchors)?;
+        db.insert_injections(&injections)?;
+        db.insert_import_aliases(&extraction.aliases)?;
 
         db.upsert_file(&FileInfo {
-            path: rel_path,
-            last_modified: modified,
-            hash,
-            language: lang.to_string(),
+            path: rel_path.clone(),
+            last_modified: file.modified,
+            hash: file.hash.clone(),
+            language: file.lang.to_string(),
             num_symbols,
-        })?;
+            generation,
+            lines,
+            truncated,
+        })
+    })?;
+
+    result.files_indexed += 1;
+    result.symbols_added += num_symbols;
+    result.edges_added += num_edges;
+    result.symbols_renamed += renames.len() as u32;
+    result.indexed.push(IndexedFile {
+        path: rel_path.clone(),
+        language: file.lang.to_string(),
+        symbols: num_symbols,
+        edges: num_edges,
+    });
+    result.oversized.extend(oversized);
+
+    Ok(())
+}
+
+/// Apply the per-file caps of `limits` to `extraction`: symbols past
+/// `max_symbols` in source order are dropped with the edges they are the source
+/// of, then edges past `max_edges`. Logs and returns the file when it is over a
+/// warning threshold or was cut.
+fn limit_extraction(
+    rel_path: &str,
+    extraction: &mut ExtractionResult,
+    limits: &FileLimits,
+) -> Option<OversizedFile> {
+    let symbols = extraction.symbols.len() as u32;
+    let edges = extraction.edges.len() as u32;
+    let mut truncated = false;
+    if let Some(max) = limits.max_symbols.filter(|&max| symbols > max) {
+        // Parents start before their children, so no kept symbol loses its parent.
+        extraction.symbols.sort_by_key(|s| s.start_byte);
+        let dropped: std::collections::HashSet<String> = extraction
+            .symbols
+            .drain(max as usize..)
+            .map(|s| s.id)
+            .collect();
+        extraction.edges.retain(|e| !dropped.contains(&e.source_id));
+        truncated = true;
+    }
+    if let Some(max) = limits.max_edges {
+        truncated |= extraction.edges.len() > max as usize;
+        extraction.edges.truncate(max as usize);
+    }
 
-        result.files_indexed += 1;
-        result.symbols_added += num_symbols;
-        result.edges_added += num_edges;
+    if truncated {
+        warn!(
+            file = %rel_path,
+            symbols,
+            edges,
+            kept_symbols = extraction.symbols.len(),
+            kept_edges = extraction.edges.len(),
+            "file over the per-file cap of [index], extraction truncated"
+        );
+    } else if limits.exceeded(symbols, edges) {
+        warn!(
+            file = %rel_path,
+            symbols,
+            edges,
+            "file adds an unusually large part of the graph; exclude it under [index] \
+             or cap it with max_symbols_per_file / max_edges_per_file"
+        );
+    } else {
+        return None;
     }
+    Some(OversizedFile {
+        path: rel_path.to_string(),
+        symbols,
+        edges,
+        truncated,
+    })
+}
 
-    // Remove files that no longer exist
-    let all_indexed = db.all_files()?;
-    for indexed_path in all_indexed {
-        if !current_files.contains(&indexed_path) {
-            db.remove_file(&indexed_path)?;
-            result.files_removed += 1;
+/// Symbols of a re-indexed file whose ID changed while their body did not (see
+/// [`crate::renames`]). Nothing for a file indexed for the first time.
+fn detect_renames(
+    db: &Database,
+    rel_path: &str,
+    source: &str,
+    symbols: &[crate::types::Symbol],
+) -> Result<Vec<Rename>> {
+    let old = db.symbol_bodies(rel_path)?;
+    if old.is_empty() {
+        return Ok(Vec::new());
+    }
+    let new: Vec<SymbolBody> = symbols
+        .iter()
+        .filter_map(|sym| {
+            extract_symbol_content(source, sym)
+                .map(|(content, _)| SymbolBody::new(&sym.id, &sym.name, sym.kind, &content))
+        })
+        .collect();
+    Ok(match_renames(&old, &new))
+}
+
+/// Symbols or edges written per flush while storing one file.
+const FLUSH_CHUNK: usize = 2_000;
+
+/// Time one file may take to read, parse and store before it is reported as slow.
+const FILE_TIME_BUDGET: Duration = Duration::from_secs(5);
+
+/// Warns once when a file runs past [`FILE_TIME_BUDGET`], naming the stage it was in,
+/// so a pathological file that stalls indexing can be found and excluded.
+///
+/// A file is read, extracted and stored in separate passes over its batch, so the
+/// budget resumes from the time the file took in the previous passes.
+struct FileBudget<'a> {
+    file: &'a str,
+    started: Instant,
+    /// Time spent before `started`.
+    before: Duration,
+    warned: bool,
+}
+
+impl<'a> FileBudget<'a> {
+    fn resume(file: &'a str, spent: Duration) -> Self {
+        Self {
+            file,
+            started: Instant::now(),
+            before: spent,
+            // An earlier pass past the budget already warned.
+            warned: spent > FILE_TIME_BUDGET,
         }
     }
 
-    // Resolve edges
-    result.edges_resolved = db.resolve_edges()?;
+    fn spent(&self) -> Duration {
+        self.before + self.started.elapsed()
+    }
 
-    // Store the current git commit as last indexed
-    if let Some(commit) = git_head_commit(&root) {
-        db.set_metadata("last_commit", &commit)?;
+    fn check(&mut self, stage: &str) {
+        let elapsed = self.spent();
+        if !self.warned && elapsed > FILE_TIME_BUDGET {
+            warn!(
+                file = %self.file,
+                stage,
+                elapsed_ms = elapsed.as_millis() as u64,
+                budget_ms = FILE_TIME_BUDGET.as_millis() as u64,
+                "file exceeded its indexing time budget"
+            );
+            self.warned = true;
+        }
+    }
+}
+
+/// Source files that changed on disk since they were indexed, by modification time.
+#[derive(Debug, Default, Clone, PartialEq, Eq, serde::Serialize, schemars::JsonSchema)]
+pub struct Staleness {
+    /// Supported source files not in the index.
+    pub new_files: u32,
+    /// Indexed files modified after they were indexed.
+    pub modified_files: u32,
+    /// Indexed files no longer on disk.
+    pub deleted_files: u32,
+}
+
+impl Staleness {
+    pub fn is_stale(&self) -> bool {
+        self.new_files + self.modified_files + self.deleted_files > 0
     }
+}
 
+/// Estimate what an incremental index run of `root` would pick up, comparing file
+/// modification times with the `indexed` ones. Reads no file contents, so a file
+/// touched without changes counts as modified.
+pub fn staleness(root: &Path, indexed: &[(String, f64)]) -> Result<Staleness> {
+    let root = root.canonicalize().context("Failed to resolve root path")?;
+    let mut remaining: std::collections::HashMap<&str, f64> = indexed
+        .iter()
+        .map(|(path, modified)| (path.as_str(), *modified))
+        .collect();
+    let mut result = Staleness::default();
+    let filter = IndexFilter::load(&root, true)?;
+
+    for entry in WalkDir::new(&root)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|e| !is_ignored(e) && !excluded_dir(&filter, &root, e))
+        .filter_map(|e| e.ok())
+    {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(rel_path) = entry.path().strip_prefix(&root) else {
+            continue;
+        };
+        let rel = rel_path.to_string_lossy();
+        if filter.excludes(&rel) || filter.language(&rel, entry.path()).is_none() {
+            continue;
+        }
+        match remaining.remove(rel.as_ref()) {
+            Some(modified) if file_modified(entry.path()) > modified => result.modified_files += 1,
+            Some(_) => {}
+            None => result.new_files += 1,
+        }
+    }
+    result.deleted_files = remaining.len() as u32;
     Ok(result)
 }
 
@@ -225,7 +942,7 @@ pub fn is_ignored_dirname(name: &str) -> bool {
     ) || name.starts_with('.')
 }
 
-fn file_hash(content: &str) -> String {
+pub(crate) fn file_hash(content: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(content.as_bytes());
     format!("{:x}", hasher.finalize())
@@ -291,7 +1008,7 @@ fn git_changed_files(
 }
 
 /// Get the current HEAD commit hash.
-fn git_head_commit(root: &Path) -> Option<String> {
+pub(crate) fn git_head_commit(root: &Path) -> Option<String> {
     let output = git_cmd(root, &["rev-parse", "HEAD"])?;
     if output.status.success() {
         Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
@@ -300,8 +1017,25 @@ fn git_head_commit(root: &Path) -> Option<String> {
     }
 }
 
+/// Repository top-level directory and the path of `root` within it (`""` or `sub/dir/`).
+pub fn repo_location(root: &Path) -> Result<(PathBuf, String)> {
+    let git_line = |args: &[&str]| -> Result<String> {
+        let output = git_cmd(root, args).context("Failed to run git")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "not a git repository: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let toplevel = git_line(&["rev-parse", "--show-toplevel"])?;
+    let prefix = git_line(&["rev-parse", "--show-prefix"])?;
+    Ok((PathBuf::from(toplevel), prefix))
+}
+
 /// Run a git command with stdin suppressed to prevent interactive prompts.
-fn git_cmd(root: &Path, args: &[&str]) -> Option<std::process::Output> {
+pub(crate) fn git_cmd(root: &Path, args: &[&str]) -> Option<std::process::Output> {
     std::process::Command::new("git")
         .args(args)
         .current_dir(root)
@@ -311,7 +1045,7 @@ fn git_cmd(root: &Path, args: &[&str]) -> Option<std::process::Output> {
 }
 
 /// Parse lines from git command output, filtering empty lines.
-fn parse_git_lines(stdout: &[u8]) -> impl Iterator<Item = String> + '_ {
+pub(crate) fn parse_git_lines(stdout: &[u8]) -> impl Iterator<Item = String> + '_ {
     String::from_utf8_lossy(stdout)
         .lines()
         .filter(|l| !l.is_empty())
@@ -512,6 +1246,432 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_index_files_updates_only_notified_files() {
+        use crate::db::Database;
+
+        let tmp = std::env::temp_dir().join("cartog_test_index_files");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(tmp.join("node_modules")).unwrap();
+        std::fs::write(tmp.join("a.py"), "def alpha():\n    pass\n").unwrap();
+        std::fs::write(tmp.join("b.py"), "def beta():\n    pass\n").unwrap();
+
+        let db = Database::open_memory().unwrap();
+        index_directory(&db, &tmp, false).unwrap();
+
+        std::fs::write(tmp.join("a.py"), "def alpha2():\n    pass\n").unwrap();
+        std::fs::write(tmp.join("b.py"), "def beta2():\n    pass\n").unwrap();
+        std::fs::write(tmp.join("node_modules/c.py"), "def gamma():\n    pass\n").unwrap();
+        let r = index_files(
+            &db,
+            &tmp,
+            &[PathBuf::from("a.py"), PathBuf::from("node_modules/c.py")],
+        )
+        .unwrap();
+        assert_eq!((r.files_indexed, r.files_skipped), (1, 1));
+        assert_eq!(db.outline("a.py").unwrap()[0].name, "alpha2");
+        // b.py was not notified, so it keeps its old symbols and their generation.
+        assert_eq!(db.outline("b.py").unwrap()[0].name, "beta");
+        assert_eq!(db.outline("a.py").unwrap()[0].generation, 2);
+        assert_eq!(db.outline("b.py").unwrap()[0].generation, 1);
+        assert!(db.outline("node_modules/c.py").unwrap().is_empty());
+
+        // Unchanged content is skipped, a deleted file is removed.
+        std::fs::remove_file(tmp.join("b.py")).unwrap();
+        let r = index_files(&db, &tmp, &[PathBuf::from("a.py"), PathBuf::from("b.py")]).unwrap();
+        assert_eq!(
+            (r.files_indexed, r.files_skipped, r.files_removed),
+            (0, 1, 1)
+        );
+        assert!(db.outline("b.py").unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_excluded_files_are_not_indexed() {
+        use crate::db::Database;
+
+        let tmp = std::env::temp_dir().join("cartog_test_index_exclude");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(tmp.join("third_party/lib")).unwrap();
+        std::fs::create_dir_all(tmp.join("static")).unwrap();
+        std::fs::write(tmp.join("a.py"), "def alpha():\n    pass\n").unwrap();
+        std::fs::write(tmp.join("third_party/lib/b.py"), "def beta():\n    pass\n").unwrap();
+        std::fs::write(tmp.join("static/app.min.js"), "function m(){}\n").unwrap();
+
+        let db = Database::open_memory().unwrap();
+        index_directory(&db, &tmp, false).unwrap();
+        assert_eq!(db.all_files().unwrap().len(), 3);
+
+        // Excluding files drops them from an existing index.
+        std::fs::write(tmp.join(crate::ignore::IGNORE_FILE), "third_party/\n").unwrap();
+        std::fs::write(
+            tmp.join(crate::config::CONFIG_FILE),
+            "[index]\nexclude = [\"*.min.js\"]\n",
+        )
+        .unwrap();
+        let r = index_directory(&db, &tmp, false).unwrap();
+        assert_eq!(r.files_removed, 2);
+        assert_eq!(db.all_files().unwrap(), ["a.py"]);
+        assert!(!staleness(&tmp, &db.file_mtimes().unwrap())
+            .unwrap()
+            .is_stale());
+
+        let r = index_files(&db, &tmp, &[PathBuf::from("third_party/lib/b.py")]).unwrap();
+        assert_eq!((r.files_indexed, r.files_skipped), (0, 1));
+        assert_eq!(db.all_files().unwrap(), ["a.py"]);
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_language_from_content_and_overrides() {
+        use crate::db::Database;
+
+        let tmp = std::env::temp_dir().join("cartog_test_index_sniff");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(tmp.join("bin")).unwrap();
+        std::fs::write(
+            tmp.join("bin/deploy"),
+            "#!/usr/bin/env python3\ndef run():\n    pass\n",
+        )
+        .unwrap();
+        std::fs::write(tmp.join("bin/build"), "#!/bin/sh\nmake\n").unwrap();
+        std::fs::write(
+            tmp.join("app_fr.ts"),
+            "<?xml version=\"1.0\"?>\n<TS version=\"2.1\"></TS>\n",
+        )
+        .unwrap();
+        std::fs::write(tmp.join("tasks.rake"), "def migrate\nend\n").unwrap();
+        std::fs::write(
+            tmp.join(crate::config::CONFIG_FILE),
+            "[index.languages]\n\"*.rake\" = \"ruby\"\n",
+        )
+        .unwrap();
+
+        let db = Database::open_memory().unwrap();
+        index_directory(&db, &tmp, false).unwrap();
+        assert_eq!(db.all_files().unwrap(), ["bin/deploy", "tasks.rake"]);
+        assert_eq!(db.outline("bin/deploy").unwrap()[0].name, "run");
+        assert_eq!(db.outline("tasks.rake").unwrap()[0].name, "migrate");
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_index_files_carries_state_over_renames() {
+        use crate::db::Database;
+
+        let tmp = std::env::temp_dir().join("cartog_test_index_renames");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        // Bodies of at least MIN_CONTENT_BYTES, so their content is stored and hashed.
+        let body = "(user):\n    return user.session.refresh_token(scope=\"read\").value\n";
+        std::fs::write(tmp.join("a.py"), format!("def login{body}")).unwrap();
+
+        let db = Database::open_memory().unwrap();
+        index_directory(&db, &tmp, false).unwrap();
+        let login = db.outline("a.py").unwrap().remove(0);
+        let eid = db.get_or_create_embedding_id(&login.id).unwrap();
+        let bytes: Vec<u8> = [0.5f32; 384].iter().flat_map(|f| f.to_le_bytes()).collect();
+        db.upsert_embedding(eid, &bytes).unwrap();
+        db.add_note(&login, "token may be stale", 1000.0, 3600.0)
+            .unwrap();
+        db.pin("login", "a.py").unwrap();
+
+        std::fs::write(tmp.join("a.py"), format!("import os\n\ndef sign_in{body}")).unwrap();
+        let r = index_files(&db, &tmp, &[PathBuf::from("a.py")]).unwrap();
+        assert_eq!(r.symbols_renamed, 1);
+
+        let sign_in = db.outline("a.py").unwrap().pop().unwrap();
+        assert_eq!(sign_in.name, "sign_in");
+        assert!(db.has_embedding(&sign_in.id).unwrap());
+        assert_eq!(db.notes(Some("sign_in"), 1000.0).unwrap().len(), 1);
+        assert_eq!(db.pins().unwrap(), [("sign_in".into(), "a.py".into())]);
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_symbol_ids_survive_line_shifts() {
+        use crate::db::Database;
+
+        let tmp = std::env::temp_dir().join("cartog_test_stable_ids");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        let code = "class Auth:\n    def login(self, user):\n        return user.token\n";
+        std::fs::write(tmp.join("a.py"), code).unwrap();
+
+        let db = Database::open_memory().unwrap();
+        index_directory(&db, &tmp, false).unwrap();
+        assert!(db.has_stable_ids().unwrap());
+        let login = db.outline("a.py").unwrap().pop().unwrap();
+        assert!(login.id.starts_with("a.py:Auth.login:"), "{}", login.id);
+        let eid = db.get_or_create_embedding_id(&login.id).unwrap();
+        let bytes: Vec<u8> = [0.5f32; 384].iter().flat_map(|f| f.to_le_bytes()).collect();
+        db.upsert_embedding(eid, &bytes).unwrap();
+
+        std::fs::write(tmp.join("a.py"), format!("import os\n\n{code}")).unwrap();
+        let r = index_files(&db, &tmp, &[PathBuf::from("a.py")]).unwrap();
+        assert_eq!(r.symbols_renamed, 0);
+        let moved = db.outline("a.py").unwrap().pop().unwrap();
+        assert_eq!(
+            (moved.id.as_str(), moved.start_line),
+            (login.id.as_str(), 4)
+        );
+        assert!(db.has_embedding(&moved.id).unwrap());
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_parallel_index_matches_single_thread() {
+        use crate::db::Database;
+
+        let tmp = std::env::temp_dir().join("cartog_test_parallel_index");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        // More files than one batch, calling across files.
+        for i in 0..BATCH_FILES + 20 {
+            let source = format!("def f{i}():\n    f{}()\n", (i + 1) % (BATCH_FILES + 20));
+            std::fs::write(tmp.join(format!("m{i}.py")), source).unwrap();
+        }
+
+        let index_with = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            // The connection is not Sync: open and use it on the pool's thread, then
+            // hand it back (it is Send).
+            pool.install(|| {
+                let db = Database::open_memory().unwrap();
+                let r = index_directory(&db, &tmp, false).unwrap();
+                (db, r)
+            })
+        };
+        let (single, r1) = index_with(1);
+        let (parallel, r4) = index_with(4);
+        assert_eq!(r1.files_indexed, BATCH_FILES as u32 + 20);
+        assert_eq!(
+            (r1.files_indexed, r1.symbols_added, r1.edges_resolved),
+            (r4.files_indexed, r4.symbols_added, r4.edges_resolved)
+        );
+        let counts = |db: &Database| {
+            let stats = db.stats().unwrap();
+            (stats.num_symbols, stats.num_edges, stats.num_resolved)
+        };
+        assert_eq!(counts(&single), counts(&parallel));
+        assert_eq!(parallel.callees("f7").unwrap()[0].target_name, "f8");
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_parse_cache_reuses_extractions() {
+        use crate::db::Database;
+
+        let tmp = std::env::temp_dir().join("cartog_test_parse_cache");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        std::fs::write(tmp.join("a.py"), "def alpha():\n    beta()\n").unwrap();
+
+        let db = Database::open_memory().unwrap();
+        db.set_parse_cache(true).unwrap();
+        let r = index_directory(&db, &tmp, false).unwrap();
+        assert_eq!((r.files_indexed, r.files_from_cache), (1, 0));
+        assert_eq!(db.grammar_versions().unwrap(), Some(grammar_versions()));
+
+        // A forced re-index of unchanged content reads the cached extraction.
+        let r = index_directory(&db, &tmp, true).unwrap();
+        assert_eq!((r.files_indexed, r.files_from_cache), (1, 1));
+        assert_eq!(db.outline("a.py").unwrap()[0].name, "alpha");
+        assert_eq!(db.callees("alpha").unwrap().len(), 1);
+
+        // Reverting an edit hits the cache too; turning it off drops the entries.
+        std::fs::write(tmp.join("a.py"), "def alpha():\n    gamma()\n").unwrap();
+        index_files(&db, &tmp, &[PathBuf::from("a.py")]).unwrap();
+        std::fs::write(tmp.join("a.py"), "def alpha():\n    beta()\n").unwrap();
+        let r = index_files(&db, &tmp, &[PathBuf::from("a.py")]).unwrap();
+        assert_eq!(r.files_from_cache, 1);
+        db.set_parse_cache(false).unwrap();
+        let r = index_directory(&db, &tmp, true).unwrap();
+        assert_eq!(r.files_from_cache, 0);
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_file_caps_truncate_extraction() {
+        use crate::db::Database;
+
+        let tmp = std::env::temp_dir().join("cartog_test_file_caps");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        std::fs::write(
+            tmp.join("big.py"),
+            "def a():\n    x()\n\ndef b():\n    y()\n\ndef c():\n    z()\n",
+        )
+        .unwrap();
+        std::fs::write(tmp.join("small.py"), "def d():\n    pass\n").unwrap();
+        std::fs::write(
+            tmp.join(crate::config::CONFIG_FILE),
+            "[index]\nwarn_edges_per_file = 1\nmax_symbols_per_file = 2\n",
+        )
+        .unwrap();
+
+        let db = Database::open_memory().unwrap();
+        let r = index_directory(&db, &tmp, false).unwrap();
+        assert_eq!(
+            r.oversized,
+            [OversizedFile {
+                path: "big.py".to_string(),
+                symbols: 3,
+                edges: 3,
+                truncated: true,
+            }]
+        );
+        let names: Vec<String> = db
+            .outline("big.py")
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(names, ["a", "b"], "the first symbols in the file are kept");
+        assert!(
+            db.callees("c").unwrap().is_empty(),
+            "edges of dropped symbols go too"
+        );
+        assert!(db.get_file("big.py").unwrap().unwrap().truncated);
+        assert!(!db.get_file("small.py").unwrap().unwrap().truncated);
+        assert!(db.stats().unwrap().largest_files[0].truncated);
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_aliased_references_count_for_imported_symbol() {
+        use crate::db::Database;
+        use crate::types::EdgeKind;
+
+        let tmp = std::env::temp_dir().join("cartog_test_import_aliases");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        std::fs::write(tmp.join("auth.py"), "def validate(token):\n    pass\n").unwrap();
+        std::fs::write(
+            tmp.join("api.py"),
+            "from auth import validate as check\n\ndef handle(t):\n    check(t)\n",
+        )
+        .unwrap();
+
+        let db = Database::open_memory().unwrap();
+        index_directory(&db, &tmp, false).unwrap();
+        assert_eq!(db.import_aliases("api.py").unwrap()[0].alias, "check");
+
+        // The aliased call resolves to `validate` and is one of its references.
+        let calls = db.refs("validate", Some(EdgeKind::Calls)).unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0.file_path, "api.py");
+        assert!(calls[0]
+            .0
+            .target_id
+            .as_deref()
+            .unwrap()
+            .starts_with("auth.py:"));
+        // Querying the alias finds it too.
+        assert_eq!(db.refs("check", Some(EdgeKind::Calls)).unwrap().len(), 1);
+
+        // Re-indexing without the alias drops it.
+        std::fs::write(tmp.join("api.py"), "from auth import validate\n").unwrap();
+        index_directory(&db, &tmp, false).unwrap();
+        assert!(db.import_aliases("api.py").unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_prune_missing_removes_deleted_files() {
+        use crate::db::Database;
+
+        let tmp = std::env::temp_dir().join("cartog_test_prune_missing");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        std::fs::write(tmp.join("a.py"), "def alpha():\n    pass\n").unwrap();
+        std::fs::write(tmp.join("b.py"), "def beta():\n    alpha()\n").unwrap();
+
+        let db = Database::open_memory().unwrap();
+        index_directory(&db, &tmp, false).unwrap();
+        std::fs::remove_file(tmp.join("b.py")).unwrap();
+
+        let dry = prune_missing(&db, &tmp, None, true).unwrap();
+        assert_eq!(dry.files_checked, 2);
+        assert_eq!(dry.files.len(), 1);
+        assert_eq!(dry.files[0].path, "b.py");
+        // Entry, symbol, content and call edge.
+        assert!(dry.rows_removed >= 3);
+        assert!(db.get_file("b.py").unwrap().is_some());
+
+        // The file was indexed moments ago: too recent to prune.
+        let recent = prune_missing(&db, &tmp, Some(Duration::from_secs(3600)), false).unwrap();
+        assert!(recent.files.is_empty());
+
+        let pruned = prune_missing(&db, &tmp, None, false).unwrap();
+        assert_eq!(pruned.rows_removed, dry.rows_removed);
+        assert!(db.get_file("b.py").unwrap().is_none());
+        assert!(db.outline("b.py").unwrap().is_empty());
+        assert_eq!(db.files_removed_since(0).unwrap(), ["b.py"]);
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_query_order_stable_after_reindex() {
+        use crate::db::Database;
+
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/auth");
+        let db = Database::open_memory().unwrap();
+        let snapshot = || {
+            serde_json::to_string(&(
+                db.outline("service.py").unwrap(),
+                db.call_sites("login", true, None).unwrap(),
+                db.refs("AuthService", None).unwrap(),
+                db.impact("validate_token", 3).unwrap(),
+                db.search("service", None, None, 20).unwrap(),
+            ))
+            .unwrap()
+        };
+
+        index_directory(&db, &fixtures, false).unwrap();
+        let first = snapshot();
+        index_directory(&db, &fixtures, true).unwrap();
+        // Only the generation of the rewritten symbols may differ.
+        let second = snapshot().replace("\"generation\":2", "\"generation\":1");
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_index_file_flushes_in_chunks() {
+        use crate::db::Database;
+
+        let tmp = std::env::temp_dir().join("cartog_test_flush_chunks");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        let count = FLUSH_CHUNK * 2 + 7;
+        let source: String = (0..count)
+            .map(|i| format!("def f{i}():\n    return g{i}()\n"))
+            .collect();
+        std::fs::write(tmp.join("big.py"), source).unwrap();
+
+        let db = Database::open_memory().unwrap();
+        let r = index_directory(&db, &tmp, false).unwrap();
+        assert_eq!(r.symbols_added as usize, count);
+        assert_eq!(r.edges_added as usize, count);
+        assert_eq!(db.outline("big.py").unwrap().len(), count);
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
     #[test]
     fn test_floor_char_boundary_ascii() {
         let s = "hello world";
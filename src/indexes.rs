@@ -0,0 +1,173 @@
+//! Named indexes: one database per git branch (or any name) besides the default
+//! `.cartog.db`, so switching branches doesn't leave incremental indexing comparing
+//! one branch's files with another branch's index.
+//!
+//! A named index lives in `.cartog-indexes/<name>/.cartog.db`, each in a directory of
+//! its own so a sharded one keeps its shards apart. Commands pick one with the global
+//! `--index <name>`, or, with `per_branch = true` under `[index]` in `.cartog.toml`,
+//! the one named after the checked-out branch. On a detached `HEAD` or outside git,
+//! `per_branch` falls back to the default index.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::db::DB_FILE;
+use crate::indexer::git_cmd;
+
+/// Directory of the named indexes, at the project root.
+pub const INDEXES_DIR: &str = ".cartog-indexes";
+
+/// A named index found by [`list`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct NamedIndex {
+    pub name: String,
+    pub path: String,
+    pub size_bytes: u64,
+}
+
+/// Database path of the index `name` of the project at `root`.
+pub fn db_path(root: &Path, name: &str) -> Result<PathBuf> {
+    anyhow::ensure!(!name.trim().is_empty(), "index name is empty");
+    Ok(root.join(INDEXES_DIR).join(encode(name)).join(DB_FILE))
+}
+
+/// The database to use instead of the default one: the index `name`, else the
+/// current branch's with `per_branch`. Creates its directory. `None` for the default.
+pub fn resolve(root: &Path, name: Option<&str>, per_branch: bool) -> Result<Option<PathBuf>> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None if per_branch => match current_branch(root) {
+            Some(branch) => branch,
+            None => return Ok(None),
+        },
+        None => return Ok(None),
+    };
+    let path = db_path(root, &name)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    Ok(Some(path))
+}
+
+/// Short name of the checked-out branch; `None` on a detached `HEAD` or outside git.
+pub fn current_branch(root: &Path) -> Option<String> {
+    let output = git_cmd(root, &["symbolic-ref", "--quiet", "--short", "HEAD"])?;
+    if !output.status.success() {
+        return None;
+    }
+    let branch = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!branch.is_empty()).then_some(branch)
+}
+
+/// The named indexes of the project at `root`, by name.
+pub fn list(root: &Path) -> Result<Vec<NamedIndex>> {
+    let Ok(entries) = std::fs::read_dir(root.join(INDEXES_DIR)) else {
+        return Ok(Vec::new());
+    };
+    let mut indexes = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path().join(DB_FILE);
+        let Ok(metadata) = path.metadata() else {
+            continue;
+        };
+        indexes.push(NamedIndex {
+            name: decode(&entry.file_name().to_string_lossy()),
+            path: path.display().to_string(),
+            size_bytes: metadata.len(),
+        });
+    }
+    indexes.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(indexes)
+}
+
+/// Directory name of an index: branch names keep their letters, digits, `-`, `_`
+/// and inner `.`; other bytes (`/` in `feature/login`) become `%XX`.
+fn encode(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for (i, byte) in name.bytes().enumerate() {
+        let plain =
+            byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' || (byte == b'.' && i > 0);
+        if plain {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// Inverse of [`encode`].
+fn decode(dir: &str) -> String {
+    let bytes = dir.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| dir.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names_encode_to_safe_directories() {
+        for (name, dir) in [
+            ("main", "main"),
+            ("feature/login", "feature%2Flogin"),
+            ("release-1.2", "release-1.2"),
+            ("..", "%2E."),
+            ("ünï", "%C3%BCn%C3%AF"),
+        ] {
+            assert_eq!(encode(name), dir);
+            assert_eq!(decode(dir), name);
+        }
+        assert!(db_path(Path::new("."), " ").is_err());
+    }
+
+    #[test]
+    fn test_resolve_and_list() {
+        let root = std::env::temp_dir().join("cartog_test_indexes");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+
+        assert!(resolve(&root, None, false).unwrap().is_none());
+        // Outside git there is no branch to key by.
+        assert!(resolve(&root, None, true).unwrap().is_none());
+
+        let path = resolve(&root, Some("feature/login"), false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            path,
+            root.join(INDEXES_DIR).join("feature%2Flogin").join(DB_FILE)
+        );
+        assert!(list(&root).unwrap().is_empty(), "nothing indexed yet");
+
+        crate::db::Database::open(&path).unwrap();
+        let indexes = list(&root).unwrap();
+        assert_eq!(indexes.len(), 1);
+        assert_eq!(indexes[0].name, "feature/login");
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
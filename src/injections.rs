@@ -0,0 +1,384 @@
+//! Injected languages: SQL, HTML and regexes inside string literals.
+//!
+//! Code in another language often lives in string literals (a `SELECT ... FROM
+//! users` query, an HTML template, a `re.compile` pattern), where the symbol
+//! extractors and name search cannot see it. When enabled with `cartog index
+//! --injections`, the indexer lexes each file's string literals (a language-agnostic
+//! scan, like [`crate::anchors`]) and keeps those that look like SQL or HTML, or that
+//! are passed to a regex constructor, linked to their enclosing symbol.
+
+use crate::anchors::enclosing_symbol;
+use crate::types::{Injection, Symbol};
+
+/// Injected languages recognised.
+pub const INJECTION_LANGUAGES: &[&str] = &["sql", "html", "regex"];
+
+/// Longest literal text stored; longer ones are cut.
+const MAX_TEXT_LEN: usize = 1_000;
+
+/// Calls whose first argument is a regex pattern (`RegExp(` also covers `new RegExp(`).
+const REGEX_CALLS: &[&str] = &[
+    "re.compile(",
+    "re.match(",
+    "re.fullmatch(",
+    "re.search(",
+    "re.sub(",
+    "re.subn(",
+    "re.split(",
+    "re.findall(",
+    "re.finditer(",
+    "RegExp(",
+    "Regex::new(",
+    "RegexBuilder::new(",
+    "RegexSet::new(",
+    "regexp.MustCompile(",
+    "regexp.Compile(",
+    "regexp.MatchString(",
+    "Regexp.new(",
+];
+
+/// Scan `source` for string literals holding SQL, HTML or a regex, linking each to
+/// its innermost enclosing symbol.
+pub fn extract_injections(
+    source: &str,
+    file_path: &str,
+    language: &str,
+    symbols: &[Symbol],
+) -> Vec<Injection> {
+    string_literals(source, language)
+        .into_iter()
+        .filter_map(|lit| {
+            let language = classify(lit.text, lit.before)?;
+            let enclosing = enclosing_symbol(symbols, lit.line);
+            Some(Injection {
+                language: language.to_string(),
+                text: clean_text(lit.text),
+                file_path: file_path.to_string(),
+                line: lit.line,
+                symbol_id: enclosing.map(|s| s.id.clone()),
+                symbol_name: enclosing.map(|s| s.name.clone()),
+            })
+        })
+        .collect()
+}
+
+/// A string literal found by [`string_literals`].
+#[derive(Debug, PartialEq)]
+struct Literal<'a> {
+    /// Contents between the delimiters, escapes left as written.
+    text: &'a str,
+    /// Line of the opening delimiter.
+    line: u32,
+    /// Source from the start of that line up to the literal (prefix included).
+    before: &'a str,
+}
+
+/// String literals of `source`, skipping comments.
+///
+/// Handles `"..."`, `'...'` where the language has single-quoted strings, Python's
+/// triple quotes, JS/TS template and Go raw strings in backticks, and Rust raw strings.
+fn string_literals<'a>(source: &'a str, language: &str) -> Vec<Literal<'a>> {
+    let bytes = source.as_bytes();
+    let hash_comments = matches!(language, "python" | "ruby");
+    let single_quoted = matches!(
+        language,
+        "python" | "ruby" | "javascript" | "typescript" | "tsx"
+    );
+    let backticks = matches!(language, "javascript" | "typescript" | "tsx" | "go");
+    let multiline_quotes = matches!(language, "rust" | "ruby");
+
+    let mut literals = Vec::new();
+    let (mut i, mut line, mut line_start) = (0, 1u32, 0);
+    // Move past `bytes[from..to]`, counting its newlines.
+    let skip = |from: usize, to: usize, line: &mut u32, line_start: &mut usize| {
+        for (offset, &b) in bytes[from..to].iter().enumerate() {
+            if b == b'\n' {
+                *line += 1;
+                *line_start = from + offset + 1;
+            }
+        }
+        to
+    };
+
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        let (open, close, escapes, multiline): (usize, &[u8], bool, bool) = match bytes[i] {
+            b'#' if hash_comments => {
+                i = find(bytes, i, b"\n").unwrap_or(bytes.len());
+                continue;
+            }
+            b'/' if !hash_comments && rest.starts_with(b"//") => {
+                i = find(bytes, i, b"\n").unwrap_or(bytes.len());
+                continue;
+            }
+            b'/' if !hash_comments && rest.starts_with(b"/*") => {
+                let end = find(bytes, i + 2, b"*/").map_or(bytes.len(), |e| e + 2);
+                i = skip(i, end, &mut line, &mut line_start);
+                continue;
+            }
+            b'"' | b'\''
+                if language == "python"
+                    && (rest.starts_with(b"\"\"\"") || rest.starts_with(b"'''")) =>
+            {
+                (3, &rest[..3], true, true)
+            }
+            b'"' => (1, b"\"", true, multiline_quotes),
+            b'\'' if single_quoted => (1, b"'", true, multiline_quotes),
+            b'\'' => {
+                i = skip_char_literal(bytes, i);
+                continue;
+            }
+            b'`' if backticks => (1, b"`", language != "go", true),
+            b'r' if language == "rust" && !is_ident_byte(i.checked_sub(1).map(|p| bytes[p])) => {
+                match raw_string_opener(rest) {
+                    Some((open, hashes)) => (open, &rest[open - 1 - hashes..open - 1], false, true),
+                    None => {
+                        i += 1;
+                        continue;
+                    }
+                }
+            }
+            b'\n' => {
+                line += 1;
+                i += 1;
+                line_start = i;
+                continue;
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        // A raw string closes with `"` and as many `#` as it opened with.
+        let close: Vec<u8> = if bytes[i] == b'r' {
+            std::iter::once(b'"').chain(close.iter().copied()).collect()
+        } else {
+            close.to_vec()
+        };
+
+        let start = i + open;
+        let mut j = start;
+        let end = loop {
+            match bytes.get(j) {
+                None => break None,
+                Some(b'\\') if escapes => j += 2,
+                Some(b'\n') if !multiline => break None,
+                Some(_) if bytes[j..].starts_with(&close) => break Some(j),
+                Some(_) => j += 1,
+            }
+        };
+        match end {
+            Some(end) => {
+                literals.push(Literal {
+                    text: &source[start..end],
+                    line,
+                    before: &source[line_start..i],
+                });
+                i = skip(i, end + close.len(), &mut line, &mut line_start);
+            }
+            // Unterminated: step over the delimiter and carry on.
+            None => i += 1,
+        }
+    }
+    literals
+}
+
+/// Index of the first `needle` in `bytes` at or after `from`.
+fn find(bytes: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    bytes[from..]
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|p| from + p)
+}
+
+fn is_ident_byte(b: Option<u8>) -> bool {
+    b.is_some_and(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+/// For `r"`, `r#"`, `r##"`...: the opener length and the number of `#`.
+fn raw_string_opener(rest: &[u8]) -> Option<(usize, usize)> {
+    let hashes = rest[1..].iter().take_while(|&&b| b == b'#').count();
+    (rest.get(1 + hashes) == Some(&b'"')).then_some((hashes + 2, hashes))
+}
+
+/// Past a char literal (`'x'`, `'\n'`, `'"'`) at `i`, or past a lone `'` (a Rust
+/// lifetime or label).
+fn skip_char_literal(bytes: &[u8], i: usize) -> usize {
+    let window = &bytes[i + 1..bytes.len().min(i + 12)];
+    let close = if window.first() == Some(&b'\\') {
+        window
+            .iter()
+            .skip(2)
+            .position(|&b| b == b'\'')
+            .map(|p| p + 2)
+    } else {
+        // One char, up to 4 bytes of UTF-8.
+        window.iter().take(5).position(|&b| b == b'\'')
+    };
+    match close {
+        Some(p) if !window[..p].contains(&b'\n') && p > 0 => i + p + 2,
+        _ => i + 1,
+    }
+}
+
+/// The injected language of a literal with contents `text`, preceded by `before`.
+fn classify(text: &str, before: &str) -> Option<&'static str> {
+    // Strip string prefixes such as Python's `r`/`rb` or Rust's `r#`.
+    let call = before
+        .trim_end_matches(|c: char| "rRbBfFuU#".contains(c))
+        .trim_end();
+    if REGEX_CALLS.iter().any(|c| call.ends_with(c)) {
+        Some("regex")
+    } else if looks_like_sql(text) {
+        Some("sql")
+    } else if looks_like_html(text) {
+        Some("html")
+    } else {
+        None
+    }
+}
+
+/// A statement keyword followed by its companion (`SELECT ... FROM`, `UPDATE ... SET`).
+///
+/// Prose such as "Select a file from the list" passes that test, so a statement
+/// whose keyword is not upper case also needs a sign of SQL: `*`, `,`, `=`, `(`, a
+/// placeholder, or a clause keyword.
+fn looks_like_sql(text: &str) -> bool {
+    let words: Vec<&str> = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty())
+        .take(64)
+        .collect();
+    let has = |word: &str| words.iter().skip(1).any(|w| w.eq_ignore_ascii_case(word));
+    let Some(first) = words.first() else {
+        return false;
+    };
+    let statement = match first.to_ascii_uppercase().as_str() {
+        "SELECT" | "DELETE" => has("FROM"),
+        "INSERT" => has("INTO"),
+        "UPDATE" => has("SET"),
+        "WITH" => has("SELECT"),
+        "CREATE" | "ALTER" | "DROP" => words.get(1).is_some_and(|w| {
+            ["TABLE", "INDEX", "VIEW", "UNIQUE", "TRIGGER", "SCHEMA"]
+                .iter()
+                .any(|k| w.eq_ignore_ascii_case(k))
+        }),
+        _ => false,
+    };
+    statement
+        && (first.chars().all(|c| c.is_ascii_uppercase())
+            || text.contains(['*', ',', '=', '(', '?', '$', ';'])
+            || ["WHERE", "JOIN", "VALUES", "LIMIT", "ORDER", "GROUP"]
+                .iter()
+                .any(|k| has(k)))
+}
+
+/// Starts with a tag and closes one (`</p>`, `<br/>`), or is a doctype.
+fn looks_like_html(text: &str) -> bool {
+    let text = text.trim_start();
+    let mut chars = text.chars();
+    chars.next() == Some('<')
+        && chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '!')
+        && (text.contains("</")
+            || text.contains("/>")
+            || text
+                .get(..9)
+                .is_some_and(|t| t.eq_ignore_ascii_case("<!doctype")))
+}
+
+/// Whitespace runs collapsed to one space, cut to [`MAX_TEXT_LEN`].
+fn clean_text(text: &str) -> String {
+    let mut cleaned = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if cleaned.len() > MAX_TEXT_LEN {
+        let mut cut = MAX_TEXT_LEN;
+        while !cleaned.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        cleaned.truncate(cut);
+    }
+    cleaned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SymbolKind;
+
+    #[test]
+    fn test_extract_injections_python() {
+        let source = r#"
+PATTERN = re.compile(r"^\d+-(\w+)$")
+
+def active_users(db):
+    # "SELECT in a comment" is not a literal
+    label = "Select a file from the list"
+    return db.execute("""
+        SELECT id, name
+        FROM users
+        WHERE active = 1
+    """)
+
+PAGE = '<div class="card"><p>{body}</p></div>'
+"#;
+        let func = Symbol::new("active_users", SymbolKind::Function, "q.py", 4, 11, 0, 100);
+        let found = extract_injections(source, "q.py", "python", std::slice::from_ref(&func));
+        let summary: Vec<(&str, u32, Option<&str>)> = found
+            .iter()
+            .map(|i| (i.language.as_str(), i.line, i.symbol_name.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("regex", 2, None),
+                ("sql", 7, Some("active_users")),
+                ("html", 13, None),
+            ]
+        );
+        assert_eq!(found[0].text, r"^\d+-(\w+)$");
+        assert_eq!(found[1].text, "SELECT id, name FROM users WHERE active = 1");
+    }
+
+    #[test]
+    fn test_extract_injections_rust_and_go() {
+        let rust = r##"
+fn load<'a>(conn: &'a Connection) -> Vec<User> {
+    let quote = '"';
+    let re = Regex::new(r"\bfoo\b").unwrap();
+    conn.query(r#"select * from "users" where id = ?1"#)
+}
+"##;
+        let found = extract_injections(rust, "db.rs", "rust", &[]);
+        let langs: Vec<(&str, &str)> = found
+            .iter()
+            .map(|i| (i.language.as_str(), i.text.as_str()))
+            .collect();
+        assert_eq!(
+            langs,
+            [
+                ("regex", r"\bfoo\b"),
+                ("sql", r#"select * from "users" where id = ?1"#),
+            ]
+        );
+        assert_eq!(found[1].line, 5);
+
+        let go = "var idRe = regexp.MustCompile(`^[a-z]+\\d*$`)\n// `DROP TABLE x` in a comment\n";
+        let found = extract_injections(go, "ids.go", "go", &[]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].text, r"^[a-z]+\d*$");
+    }
+
+    #[test]
+    fn test_looks_like_sql_and_html() {
+        assert!(looks_like_sql("UPDATE accounts SET balance = 0"));
+        assert!(looks_like_sql("insert into t values (1)"));
+        assert!(looks_like_sql("CREATE TABLE IF NOT EXISTS t (id INTEGER)"));
+        assert!(!looks_like_sql("update the settings"));
+        assert!(!looks_like_sql("Delete it from the cache"));
+        assert!(looks_like_html("<ul><li>one</li></ul>"));
+        assert!(looks_like_html("<!DOCTYPE html>"));
+        assert!(!looks_like_html("<T> generic"));
+        assert!(!looks_like_html("a < b and c > d"));
+    }
+}
@@ -0,0 +1,422 @@
+//! Index runs that outlive the tool call starting them (`cartog_start_index`), so
+//! an MCP client can trigger a full re-index or embedding run, which takes minutes
+//! on a large repository, and poll it (`cartog_job_status`) or stop it
+//! (`cartog_cancel_job`) instead of blocking on it.
+//!
+//! A job runs on its own thread with its own database connections, and is kept in
+//! the `jobs` table of the project's index: its phase (`index`, then `embed`),
+//! progress, and in the end its result or error. Index and embedding runs call
+//! [`checkpoint`] between batches, which counts their progress and stops them once
+//! the job is cancelled. Files stored before that stay indexed; the next run picks
+//! up the rest.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::db::{unix_now, Database, Job, JobStatus};
+use crate::hooks::Hooks;
+use crate::shard::ShardedDatabase;
+
+/// Phase building the code graph.
+pub const PHASE_INDEX: &str = "index";
+/// Phase embedding symbols for semantic search.
+pub const PHASE_EMBED: &str = "embed";
+
+/// How often the progress of a job is saved to its row.
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What a job does.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexJob {
+    /// Directory to index, absolute.
+    pub path: PathBuf,
+    /// Re-index every file (and re-embed every symbol), bypassing change detection.
+    pub force: bool,
+    /// Embed symbols for semantic search once the code graph is indexed.
+    pub rag: bool,
+}
+
+/// Running jobs by project root, with their ID.
+type Running = HashMap<PathBuf, (i64, Arc<Control>)>;
+
+/// The jobs running in this process, at most one per project root.
+#[derive(Debug, Default)]
+pub struct Jobs {
+    running: Mutex<Running>,
+}
+
+impl Jobs {
+    /// Start `job` on the project at `root`, indexed into `db_path`, and return it
+    /// as recorded, without waiting for it. Fails when a job is already running there.
+    pub fn start(
+        self: &Arc<Self>,
+        root: &Path,
+        db_path: &Path,
+        job: IndexJob,
+        hooks: Hooks,
+    ) -> Result<Job> {
+        let mut running = self.lock()?;
+        if let Some((id, _)) = running.get(root) {
+            anyhow::bail!("job {id} is already indexing this project; wait for it or cancel it");
+        }
+
+        let mut db = ShardedDatabase::open(db_path)?;
+        if job.rag {
+            db.ensure_rag_supported()?;
+        }
+        // Progress is saved from inside the run, through a connection of its own.
+        let progress_db = Database::open(db_path)?;
+        let id = db.insert_job(&serde_json::to_string(&job)?, PHASE_INDEX, unix_now())?;
+        let recorded = db.get_job(id)?.context("job not recorded")?;
+
+        let control = Arc::new(Control::default());
+        running.insert(root.to_path_buf(), (id, Arc::clone(&control)));
+        let jobs = Arc::clone(self);
+        let root_buf = root.to_path_buf();
+        let spawned = std::thread::Builder::new()
+            .name(format!("cartog-job-{id}"))
+            .spawn(move || {
+                let current = Current {
+                    id,
+                    control: Arc::clone(&control),
+                    db: progress_db,
+                    saved: Instant::now(),
+                };
+                let outcome = with_current(current, || run(&mut db, &job, &hooks));
+                let (status, result, error) = match outcome {
+                    Ok(result) => (JobStatus::Done, Some(result.to_string()), None),
+                    Err(e) if e.chain().any(|c| c.is::<Cancelled>()) => {
+                        (JobStatus::Cancelled, None, None)
+                    }
+                    Err(e) => (JobStatus::Failed, None, Some(format!("{e:#}"))),
+                };
+                info!(job = id, status = status.as_str(), "job finished");
+                // Out of the registry first, so once the row says it is over, a new
+                // job can start.
+                if let Ok(mut running) = jobs.running.lock() {
+                    running.remove(&root_buf);
+                }
+                let (phase, done, total) = control.progress();
+                let saved = db
+                    .update_job_progress(id, phase, done, total)
+                    .and_then(|()| {
+                        db.finish_job(id, status, result.as_deref(), error.as_deref(), unix_now())
+                    });
+                if let Err(e) = saved {
+                    warn!(job = id, error = %e, "failed to record the end of the job");
+                }
+            });
+        if let Err(e) = spawned {
+            running.remove(root);
+            Database::open(db_path)?.finish_job(
+                id,
+                JobStatus::Failed,
+                None,
+                Some(&e.to_string()),
+                unix_now(),
+            )?;
+            return Err(e).context("failed to start the job thread");
+        }
+        Ok(recorded)
+    }
+
+    /// The job `id` of the project at `root`, with its live progress when it runs
+    /// in this process.
+    pub fn status(&self, db: &Database, root: &Path, id: i64) -> Result<Option<Job>> {
+        let mut job = db.get_job(id)?;
+        if let Some(job) = &mut job {
+            self.overlay(root, job)?;
+        }
+        Ok(job)
+    }
+
+    /// The `limit` most recent jobs of the project at `root`, newest first.
+    pub fn recent(&self, db: &Database, root: &Path, limit: u32) -> Result<Vec<Job>> {
+        let mut jobs = db.recent_jobs(limit)?;
+        for job in &mut jobs {
+            self.overlay(root, job)?;
+        }
+        Ok(jobs)
+    }
+
+    /// Ask the running job `id` of the project at `root` to stop. It does at its
+    /// next batch; until then its status stays `running`.
+    pub fn cancel(&self, db: &Database, root: &Path, id: i64) -> Result<Job> {
+        let job = self
+            .status(db, root, id)?
+            .with_context(|| format!("no job {id}"))?;
+        match self.lock()?.get(root) {
+            Some((running, control)) if *running == id => {
+                control.cancelled.store(true, Ordering::Relaxed);
+                Ok(job)
+            }
+            _ => anyhow::bail!("job {id} is not running ({})", job.status.as_str()),
+        }
+    }
+
+    fn overlay(&self, root: &Path, job: &mut Job) -> Result<()> {
+        if let Some((id, control)) = self.lock()?.get(root) {
+            if *id == job.id {
+                let (phase, done, total) = control.progress();
+                job.phase = phase.to_string();
+                job.done = done;
+                job.total = total;
+            }
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Running>> {
+        self.running
+            .lock()
+            .map_err(|_| anyhow::anyhow!("job registry lock poisoned"))
+    }
+}
+
+fn run(db: &mut ShardedDatabase, job: &IndexJob, hooks: &Hooks) -> Result<serde_json::Value> {
+    let run = hooks.start(db)?;
+    let index = db.index(&job.path, job.force)?;
+    hooks.spawn(hooks.finish(run, db, &job.path, &index)?);
+    let mut result = serde_json::json!({ "index": index });
+    if job.rag {
+        enter_phase(PHASE_EMBED);
+        let embed = crate::rag::indexer::index_embeddings(db, job.force, Default::default())?;
+        result["embed"] = serde_json::to_value(embed)?;
+    }
+    Ok(result)
+}
+
+/// Progress and cancellation of a running job, shared with the server.
+#[derive(Debug)]
+struct Control {
+    cancelled: AtomicBool,
+    phase: Mutex<&'static str>,
+    done: AtomicU32,
+    total: AtomicU32,
+}
+
+impl Default for Control {
+    fn default() -> Self {
+        Self {
+            cancelled: AtomicBool::new(false),
+            phase: Mutex::new(PHASE_INDEX),
+            done: AtomicU32::new(0),
+            total: AtomicU32::new(0),
+        }
+    }
+}
+
+impl Control {
+    fn progress(&self) -> (&'static str, u32, u32) {
+        let phase = self.phase.lock().map_or(PHASE_INDEX, |p| *p);
+        (
+            phase,
+            self.done.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// The job running on this thread.
+struct Current {
+    id: i64,
+    control: Arc<Control>,
+    /// Connection to the coordinator database, for saving progress.
+    db: Database,
+    saved: Instant,
+}
+
+impl Current {
+    fn save(&mut self) {
+        let (phase, done, total) = self.control.progress();
+        // Progress is informative: a busy database must not fail the job.
+        if let Err(e) = self.db.update_job_progress(self.id, phase, done, total) {
+            warn!(job = self.id, error = %e, "failed to save job progress");
+        }
+        self.saved = Instant::now();
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Current>> = const { RefCell::new(None) };
+}
+
+fn with_current<T>(current: Current, f: impl FnOnce() -> T) -> T {
+    CURRENT.with(|c| *c.borrow_mut() = Some(current));
+    let result = f();
+    CURRENT.with(|c| *c.borrow_mut() = None);
+    result
+}
+
+/// Error ending a cancelled job.
+#[derive(Debug)]
+struct Cancelled;
+
+impl std::fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("job cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Count `total` more items (files or symbols) to process and `done` more processed
+/// by the job running on this thread, and fail once it is cancelled. Does nothing
+/// outside jobs.
+pub fn checkpoint(total: usize, done: usize) -> Result<()> {
+    CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        let Some(current) = current.as_mut() else {
+            return Ok(());
+        };
+        let control = &current.control;
+        control.total.fetch_add(total as u32, Ordering::Relaxed);
+        control.done.fetch_add(done as u32, Ordering::Relaxed);
+        if control.cancelled.load(Ordering::Relaxed) {
+            return Err(Cancelled.into());
+        }
+        if current.saved.elapsed() >= SAVE_INTERVAL {
+            current.save();
+        }
+        Ok(())
+    })
+}
+
+/// Move the job running on this thread to `phase`, restarting its progress count.
+fn enter_phase(phase: &'static str) {
+    CURRENT.with(|current| {
+        if let Some(current) = current.borrow_mut().as_mut() {
+            if let Ok(mut p) = current.control.phase.lock() {
+                *p = phase;
+            }
+            current.control.done.store(0, Ordering::Relaxed);
+            current.control.total.store(0, Ordering::Relaxed);
+            current.save();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::DB_FILE;
+
+    fn wait_for_end(jobs: &Jobs, db: &Database, root: &Path, id: i64) -> Job {
+        let deadline = Instant::now() + Duration::from_secs(30);
+        loop {
+            let job = jobs.status(db, root, id).unwrap().unwrap();
+            if job.status != JobStatus::Running {
+                return job;
+            }
+            assert!(Instant::now() < deadline, "job {id} did not finish");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn test_index_job_runs_in_background_and_records_result() {
+        let root = std::env::temp_dir().join("cartog_test_jobs");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("a.py"), "def login():\n    check()\n").unwrap();
+        let root = root.canonicalize().unwrap();
+        let db = Database::open(root.join(DB_FILE)).unwrap();
+        let jobs = Arc::new(Jobs::default());
+        let job = IndexJob {
+            path: root.clone(),
+            force: false,
+            rag: false,
+        };
+
+        let started = jobs
+            .start(
+                &root,
+                &root.join(DB_FILE),
+                job.clone(),
+                Hooks::new(Default::default(), &root),
+            )
+            .unwrap();
+        assert_eq!(started.status, JobStatus::Running);
+        assert_eq!(started.phase, PHASE_INDEX);
+        let done = wait_for_end(&jobs, &db, &root, started.id);
+        assert_eq!(done.status, JobStatus::Done, "{:?}", done.error);
+        assert_eq!(done.result.unwrap()["index"]["files_indexed"], 1);
+        assert!(done.total >= 1 && done.done == done.total);
+        assert!(done.finished.is_some());
+        assert_eq!(db.stats().unwrap().num_files, 1);
+
+        // Over: it can no longer be cancelled, and a new one can start.
+        let err = jobs.cancel(&db, &root, started.id).unwrap_err();
+        assert!(err.to_string().contains("not running (done)"));
+        assert!(jobs.cancel(&db, &root, 999).is_err());
+        let second = jobs
+            .start(
+                &root,
+                &root.join(DB_FILE),
+                job,
+                Hooks::new(Default::default(), &root),
+            )
+            .unwrap();
+        wait_for_end(&jobs, &db, &root, second.id);
+        let recent = jobs.recent(&db, &root, 10).unwrap();
+        assert_eq!(
+            recent.iter().map(|j| j.id).collect::<Vec<_>>(),
+            [second.id, started.id]
+        );
+
+        // A server stopping mid-job leaves it running in the table.
+        let orphan = db.insert_job("{}", PHASE_INDEX, unix_now()).unwrap();
+        assert_eq!(db.interrupt_running_jobs(unix_now()).unwrap(), 1);
+        assert_eq!(
+            db.get_job(orphan).unwrap().unwrap().status,
+            JobStatus::Interrupted
+        );
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_checkpoint_stops_cancelled_job() {
+        let dir = std::env::temp_dir().join("cartog_test_jobs_cancel");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let db = Database::open(dir.join(DB_FILE)).unwrap();
+        let id = db.insert_job("{}", PHASE_INDEX, unix_now()).unwrap();
+        let control = Arc::new(Control::default());
+
+        // Outside a job, checkpoints do nothing.
+        checkpoint(10, 0).unwrap();
+        let current = Current {
+            id,
+            control: Arc::clone(&control),
+            db: Database::open(dir.join(DB_FILE)).unwrap(),
+            saved: Instant::now(),
+        };
+        let outcome = with_current(current, || -> Result<()> {
+            checkpoint(10, 0)?;
+            checkpoint(0, 4)?;
+            enter_phase(PHASE_EMBED);
+            checkpoint(3, 1)?;
+            control.cancelled.store(true, Ordering::Relaxed);
+            checkpoint(0, 1)?;
+            unreachable!("the job was cancelled")
+        });
+        let err = outcome.unwrap_err();
+        assert!(err.is::<Cancelled>());
+        assert_eq!(control.progress(), (PHASE_EMBED, 2, 3));
+        // The phase change was saved.
+        let job = db.get_job(id).unwrap().unwrap();
+        assert_eq!(job.phase, PHASE_EMBED);
+        checkpoint(0, 1).unwrap();
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,1016 @@
+use anyhow::Result;
+use tree_sitter::{Language, Node, Parser};
+
+use crate::types::{symbol_id, Edge, EdgeKind, ImportAlias, Symbol, SymbolKind, Visibility};
+
+use super::{descendants_of_kind, is_conditional, node_text, ExtractionResult, Extractor};
+
+/// Extracts symbols and edges from C# source files.
+pub struct CSharpExtractor {
+    parser: Parser,
+}
+
+impl CSharpExtractor {
+    pub fn new() -> Self {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&Language::new(tree_sitter_c_sharp::LANGUAGE))
+            .expect("C# grammar should always load");
+        Self { parser }
+    }
+}
+
+impl Default for CSharpExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Extractor for CSharpExtractor {
+    fn extract(&mut self, source: &str, file_path: &str) -> Result<ExtractionResult> {
+        let tree = self
+            .parser
+            .parse(source, None)
+            .ok_or_else(|| anyhow::anyhow!("Failed to parse {file_path}"))?;
+
+        let mut symbols = Vec::new();
+        let mut edges = Vec::new();
+
+        let root = tree.root_node();
+        let mut scope = Scope {
+            parent_id: None,
+            member_default: Visibility::Public,
+        };
+        for child in root.named_children(&mut root.walk()) {
+            if child.kind() == "file_scoped_namespace_declaration" {
+                // `namespace Acme.Web;` holds the rest of the file
+                scope.parent_id =
+                    extract_namespace(child, root, source, file_path, &scope, &mut symbols);
+                continue;
+            }
+            extract_node(child, source, file_path, &scope, &mut symbols, &mut edges);
+        }
+        let aliases = extract_aliases(root, source, file_path);
+
+        Ok(ExtractionResult::new(symbols, edges, aliases))
+    }
+}
+
+/// Where a declaration sits: its parent symbol, and the visibility of members
+/// declared without an access modifier.
+struct Scope {
+    parent_id: Option<String>,
+    member_default: Visibility,
+}
+
+fn extract_node(
+    node: Node,
+    source: &str,
+    file_path: &str,
+    scope: &Scope,
+    symbols: &mut Vec<Symbol>,
+    edges: &mut Vec<Edge>,
+) {
+    match node.kind() {
+        "namespace_declaration" => {
+            let namespace_id = extract_namespace(node, node, source, file_path, scope, symbols);
+            if let Some(body) = node.child_by_field_name("body") {
+                let inner = Scope {
+                    parent_id: namespace_id,
+                    member_default: Visibility::Public,
+                };
+                for child in body.named_children(&mut body.walk()) {
+                    extract_node(child, source, file_path, &inner, symbols, edges);
+                }
+            }
+        }
+        "class_declaration"
+        | "record_declaration"
+        | "struct_declaration"
+        | "interface_declaration"
+        | "enum_declaration" => {
+            extract_type(node, source, file_path, scope, symbols, edges);
+        }
+        "method_declaration" | "constructor_declaration" | "operator_declaration" => {
+            extract_method(node, source, file_path, scope, symbols, edges);
+        }
+        "property_declaration" => {
+            extract_property(node, source, file_path, scope, symbols, edges);
+        }
+        "using_directive" => {
+            extract_using(node, source, file_path, scope, symbols, edges);
+        }
+        // Fields, events, indexers, delegates and top-level statements are not tracked
+        _ => {}
+    }
+}
+
+// ── Namespaces ──
+
+/// A namespace symbol spanning `node` up to the end of `extent` (the file, for a
+/// file-scoped namespace). Returns its ID.
+fn extract_namespace(
+    node: Node,
+    extent: Node,
+    source: &str,
+    file_path: &str,
+    scope: &Scope,
+    symbols: &mut Vec<Symbol>,
+) -> Option<String> {
+    let name = node_text(node.child_by_field_name("name")?, source);
+    let start_line = node.start_position().row as u32 + 1;
+    let sym_id = symbol_id(file_path, name, start_line);
+    symbols.push(
+        Symbol::new(
+            name,
+            SymbolKind::Class,
+            file_path,
+            start_line,
+            extent.end_position().row as u32 + 1,
+            node.start_byte() as u32,
+            extent.end_byte() as u32,
+        )
+        .with_parent(scope.parent_id.as_deref())
+        .with_docstring(extract_doc_comment(node, source)),
+    );
+    Some(sym_id)
+}
+
+// ── Types ──
+
+fn extract_type(
+    node: Node,
+    source: &str,
+    file_path: &str,
+    scope: &Scope,
+    symbols: &mut Vec<Symbol>,
+    edges: &mut Vec<Edge>,
+) {
+    let name = match node.child_by_field_name("name") {
+        Some(n) => node_text(n, source).to_string(),
+        None => return,
+    };
+
+    let start_line = node.start_position().row as u32 + 1;
+    let sym_id = symbol_id(file_path, &name, start_line);
+    symbols.push(
+        Symbol::new(
+            name,
+            SymbolKind::Class,
+            file_path,
+            start_line,
+            node.end_position().row as u32 + 1,
+            node.start_byte() as u32,
+            node.end_byte() as u32,
+        )
+        .with_parent(scope.parent_id.as_deref())
+        .with_visibility(csharp_visibility(node, source, scope.member_default))
+        .with_docstring(extract_doc_comment(node, source)),
+    );
+
+    // Base class and interfaces → inherits edges (C# syntax doesn't tell them apart)
+    let bases: Vec<Node> = node
+        .named_children(&mut node.walk())
+        .find(|child| child.kind() == "base_list")
+        .map(|list| list.named_children(&mut list.walk()).collect())
+        .unwrap_or_default();
+    for base in bases {
+        let base_type = match base.kind() {
+            // `record Person(string Name) : Entity(Name)`
+            "primary_constructor_base_type" => base.child_by_field_name("type"),
+            _ => Some(base),
+        };
+        let base_name = base_type.map_or(String::new(), |t| type_name(t, source));
+        if !base_name.is_empty() {
+            edges.push(Edge::new(
+                &sym_id,
+                base_name,
+                EdgeKind::Inherits,
+                file_path,
+                base.start_position().row as u32 + 1,
+            ));
+        }
+    }
+
+    if let Some(body) = node.child_by_field_name("body") {
+        let inner = Scope {
+            parent_id: Some(sym_id),
+            // Interface members are public; class, struct and record members private
+            member_default: if node.kind() == "interface_declaration" {
+                Visibility::Public
+            } else {
+                Visibility::Private
+            },
+        };
+        for child in body.named_children(&mut body.walk()) {
+            extract_node(child, source, file_path, &inner, symbols, edges);
+        }
+    }
+}
+
+// ── Methods ──
+
+fn extract_method(
+    node: Node,
+    source: &str,
+    file_path: &str,
+    scope: &Scope,
+    symbols: &mut Vec<Symbol>,
+    edges: &mut Vec<Edge>,
+) {
+    let name = match node.kind() {
+        "operator_declaration" => match node.child_by_field_name("operator") {
+            Some(op) => format!("operator {}", node_text(op, source)),
+            None => return,
+        },
+        _ => match node.child_by_field_name("name") {
+            Some(n) => node_text(n, source).to_string(),
+            None => return,
+        },
+    };
+
+    let start_line = node.start_position().row as u32 + 1;
+    let end_line = node.end_position().row as u32 + 1;
+    let modifiers = modifiers(node, source);
+    let is_static = modifiers.contains(&"static");
+    let entrypoint = if name == "Main" && is_static {
+        Some("main")
+    } else {
+        attribute_names(node, source)
+            .iter()
+            .find_map(|attr| attribute_entrypoint(attr))
+    };
+
+    let sym_id = symbol_id(file_path, &name, start_line);
+    symbols.push(
+        Symbol::new(
+            name,
+            SymbolKind::Method,
+            file_path,
+            start_line,
+            end_line,
+            node.start_byte() as u32,
+            node.end_byte() as u32,
+        )
+        .with_parent(scope.parent_id.as_deref())
+        .with_signature(extract_signature(node, source))
+        .with_visibility(csharp_visibility(node, source, scope.member_default))
+        .with_async(modifiers.contains(&"async"))
+        .with_docstring(extract_doc_comment(node, source))
+        .with_entrypoint(entrypoint),
+    );
+
+    // Parameter and return types → references, minus the method's type parameters
+    let type_params: Vec<&str> = node
+        .child_by_field_name("type_parameters")
+        .map(|list| {
+            descendants_of_kind(list, &["type_parameter"])
+                .into_iter()
+                .filter_map(|p| p.child_by_field_name("name"))
+                .map(|n| node_text(n, source))
+                .collect()
+        })
+        .unwrap_or_default();
+    let returns = node
+        .child_by_field_name("returns")
+        .or_else(|| node.child_by_field_name("type"));
+    let params: Vec<Node> = node
+        .child_by_field_name("parameters")
+        .map(|list| list.named_children(&mut list.walk()).collect())
+        .unwrap_or_default();
+    let param_types = params.iter().filter_map(|p| p.child_by_field_name("type"));
+    for type_node in returns.into_iter().chain(param_types) {
+        collect_type_refs(type_node, source, file_path, &sym_id, &type_params, edges);
+    }
+
+    if let Some(body) = node.child_by_field_name("body") {
+        walk_for_calls(body, source, file_path, &sym_id, edges);
+    }
+}
+
+// ── Properties ──
+
+fn extract_property(
+    node: Node,
+    source: &str,
+    file_path: &str,
+    scope: &Scope,
+    symbols: &mut Vec<Symbol>,
+    edges: &mut Vec<Edge>,
+) {
+    let name = match node.child_by_field_name("name") {
+        Some(n) => node_text(n, source).to_string(),
+        None => return,
+    };
+
+    let start_line = node.start_position().row as u32 + 1;
+    // `int Count { get; set; }`, without modifiers, attributes or accessor bodies
+    let signature = node.child_by_field_name("type").map(|t| {
+        let accessors = node
+            .child_by_field_name("accessors")
+            .map(|list| {
+                let names: Vec<String> = list
+                    .named_children(&mut list.walk())
+                    .map(|a| accessor_keyword(a, source))
+                    .collect();
+                format!(" {{ {}; }}", names.join("; "))
+            })
+            .unwrap_or_default();
+        format!("{} {name}{accessors}", node_text(t, source))
+    });
+
+    let sym_id = symbol_id(file_path, &name, start_line);
+    symbols.push(
+        Symbol::new(
+            name,
+            SymbolKind::Variable,
+            file_path,
+            start_line,
+            node.end_position().row as u32 + 1,
+            node.start_byte() as u32,
+            node.end_byte() as u32,
+        )
+        .with_parent(scope.parent_id.as_deref())
+        .with_signature(signature)
+        .with_visibility(csharp_visibility(node, source, scope.member_default))
+        .with_docstring(extract_doc_comment(node, source)),
+    );
+
+    if let Some(type_node) = node.child_by_field_name("type") {
+        collect_type_refs(type_node, source, file_path, &sym_id, &[], edges);
+    }
+    // Accessor bodies, `=> expr` and initializers
+    for field in ["accessors", "value"] {
+        if let Some(body) = node.child_by_field_name(field) {
+            walk_for_calls(body, source, file_path, &sym_id, edges);
+        }
+    }
+}
+
+/// `get`, `set`, `init`, ... of an accessor declaration.
+fn accessor_keyword(node: Node, source: &str) -> String {
+    let text = node_text(node, source);
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .find(|word| matches!(*word, "get" | "set" | "init" | "add" | "remove"))
+        .unwrap_or(text)
+        .to_string()
+}
+
+// ── Using directives ──
+
+fn extract_using(
+    node: Node,
+    source: &str,
+    file_path: &str,
+    scope: &Scope,
+    symbols: &mut Vec<Symbol>,
+    edges: &mut Vec<Edge>,
+) {
+    let Some(target) = using_target(node) else {
+        return;
+    };
+    let path = node_text(target, source);
+    let line = node.start_position().row as u32 + 1;
+
+    let sym_id = symbol_id(file_path, path, line);
+    symbols.push(
+        Symbol::new(
+            path,
+            SymbolKind::Import,
+            file_path,
+            line,
+            line,
+            node.start_byte() as u32,
+            node.end_byte() as u32,
+        )
+        .with_parent(scope.parent_id.as_deref())
+        .with_signature(Some(node_text(node, source).to_string())),
+    );
+
+    edges.push(Edge::new(
+        sym_id,
+        type_name(target, source),
+        EdgeKind::Imports,
+        file_path,
+        line,
+    ));
+}
+
+/// The namespace or type a using directive names (not its alias).
+fn using_target(node: Node) -> Option<Node> {
+    let alias = node.child_by_field_name("name").map(|n| n.id());
+    node.named_children(&mut node.walk())
+        .find(|child| Some(child.id()) != alias && child.kind() != "comment")
+}
+
+/// `using Json = Newtonsoft.Json;` (Json → Json, dropped) and
+/// `using Repo = Acme.Data.Repository<User>;` (Repo → Repository), at any depth.
+fn extract_aliases(root: Node, source: &str, file_path: &str) -> Vec<ImportAlias> {
+    descendants_of_kind(root, &["using_directive"])
+        .into_iter()
+        .filter_map(|node| {
+            let alias = node_text(node.child_by_field_name("name")?, source);
+            let name = type_name(using_target(node)?, source);
+            Some(ImportAlias {
+                alias: alias.to_string(),
+                name: name.rsplit('.').next().unwrap_or(&name).to_string(),
+                file_path: file_path.to_string(),
+                line: node.start_position().row as u32 + 1,
+            })
+        })
+        .collect()
+}
+
+// ── Call walking ──
+
+fn walk_for_calls(
+    node: Node,
+    source: &str,
+    file_path: &str,
+    context_id: &str,
+    edges: &mut Vec<Edge>,
+) {
+    let mut cursor = node.walk();
+    let mut did_visit_children = false;
+
+    loop {
+        let current = cursor.node();
+
+        if !did_visit_children {
+            let line = current.start_position().row as u32 + 1;
+            match current.kind() {
+                "invocation_expression" => {
+                    let callee = current
+                        .child_by_field_name("function")
+                        .map_or(String::new(), |f| callee_name(f, source));
+                    if !callee.is_empty() {
+                        edges.push(
+                            Edge::new(context_id, callee, EdgeKind::Calls, file_path, line)
+                                .with_conditional(is_conditional(current, node, source)),
+                        );
+                    }
+                }
+                "object_creation_expression" => {
+                    // new Invoice(...) → calls the Invoice constructor
+                    let type_name = current
+                        .child_by_field_name("type")
+                        .map_or(String::new(), |t| type_name(t, source));
+                    if !type_name.is_empty() {
+                        edges.push(
+                            Edge::new(context_id, type_name, EdgeKind::Calls, file_path, line)
+                                .with_conditional(is_conditional(current, node, source)),
+                        );
+                    }
+                }
+                "throw_statement" | "throw_expression" => {
+                    // throw new InvalidOperationException(...); a bare `throw;` rethrows
+                    let thrown = current
+                        .named_child(0)
+                        .filter(|e| e.kind() == "object_creation_expression")
+                        .and_then(|e| e.child_by_field_name("type"))
+                        .map_or(String::new(), |t| type_name(t, source));
+                    if !thrown.is_empty() {
+                        edges.push(Edge::new(
+                            context_id,
+                            thrown,
+                            EdgeKind::Raises,
+                            file_path,
+                            line,
+                        ));
+                    }
+                }
+                "catch_declaration" => {
+                    // catch (NotFoundException ex) → references the exception type
+                    let caught = current
+                        .child_by_field_name("type")
+                        .map_or(String::new(), |t| type_name(t, source));
+                    if !caught.is_empty() {
+                        edges.push(Edge::new(
+                            context_id,
+                            caught,
+                            EdgeKind::References,
+                            file_path,
+                            line,
+                        ));
+                    }
+                }
+                // Don't descend into nested type declarations
+                "class_declaration"
+                | "record_declaration"
+                | "struct_declaration"
+                | "interface_declaration"
+                | "enum_declaration" => {
+                    did_visit_children = true;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        if !did_visit_children && cursor.goto_first_child() {
+            did_visit_children = false;
+            continue;
+        }
+        did_visit_children = false;
+        if cursor.goto_next_sibling() {
+            continue;
+        }
+        loop {
+            if !cursor.goto_parent() {
+                return;
+            }
+            if cursor.node() == node {
+                return;
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+        }
+    }
+}
+
+/// Callee of an invocation: `Validate`, `_repo.Save`, `this.Ok`, `a.Go` for
+/// `a?.Go()`, `Run` for `Run<int>()`. A receiver that is itself a call or
+/// spans lines is dropped, keeping only the method name.
+fn callee_name(node: Node, source: &str) -> String {
+    match node.kind() {
+        "identifier" => node_text(node, source).to_string(),
+        "generic_name" => generic_base(node, source),
+        "member_binding_expression" => node
+            .child_by_field_name("name")
+            .map_or(String::new(), |n| callee_name(n, source)),
+        "member_access_expression" | "conditional_access_expression" => {
+            let Some(name) = descendants_of_kind(node, &["identifier", "generic_name"])
+                .into_iter()
+                .rev()
+                .find(|n| {
+                    n.parent()
+                        .is_some_and(|p| p.child_by_field_name("name") == Some(*n))
+                })
+            else {
+                return String::new();
+            };
+            let method = callee_name(name, source);
+            let receiver = source
+                .get(node.start_byte()..name.start_byte())
+                .unwrap_or("")
+                .trim_end_matches(['.', '?']);
+            if receiver.is_empty()
+                || receiver.contains(|c: char| c == '(' || c == '[' || c.is_whitespace())
+            {
+                method
+            } else {
+                format!("{receiver}.{method}")
+            }
+        }
+        _ => String::new(),
+    }
+}
+
+// ── Type references ──
+
+fn collect_type_refs(
+    node: Node,
+    source: &str,
+    file_path: &str,
+    sym_id: &str,
+    type_params: &[&str],
+    edges: &mut Vec<Edge>,
+) {
+    match node.kind() {
+        "identifier" | "qualified_name" => {
+            let name = type_name(node, source);
+            if !name.is_empty() && !type_params.contains(&name.as_str()) {
+                edges.push(Edge::new(
+                    sym_id,
+                    name,
+                    EdgeKind::References,
+                    file_path,
+                    node.start_position().row as u32 + 1,
+                ));
+            }
+            // Type arguments of a qualified generic (Acme.Page<User>)
+            if node.kind() == "qualified_name" {
+                if let Some(name) = node.child_by_field_name("name") {
+                    if name.kind() == "generic_name" {
+                        for args in descendants_of_kind(name, &["type_argument_list"])
+                            .into_iter()
+                            .take(1)
+                        {
+                            collect_type_refs(args, source, file_path, sym_id, type_params, edges);
+                        }
+                    }
+                }
+            }
+        }
+        // int, string, bool, ...
+        "predefined_type" | "implicit_type" => {}
+        _ => {
+            for child in node.named_children(&mut node.walk()) {
+                collect_type_refs(child, source, file_path, sym_id, type_params, edges);
+            }
+        }
+    }
+}
+
+// ── Helpers ──
+
+/// Name of a type as written, without type arguments, nullability or array
+/// ranks: `List<Order>` → `List`, `Acme.Data.Repo<T>` → `Acme.Data.Repo`,
+/// `Order?` → `Order`, `Order[]` → `Order`.
+fn type_name(node: Node, source: &str) -> String {
+    match node.kind() {
+        "identifier" => node_text(node, source).to_string(),
+        "generic_name" => generic_base(node, source),
+        "qualified_name" => {
+            let qualifier = node
+                .child_by_field_name("qualifier")
+                .map_or(String::new(), |q| type_name(q, source));
+            let name = node
+                .child_by_field_name("name")
+                .map_or(String::new(), |n| type_name(n, source));
+            if qualifier.is_empty() {
+                name
+            } else {
+                format!("{qualifier}.{name}")
+            }
+        }
+        "nullable_type" | "array_type" => node
+            .child_by_field_name("type")
+            .map_or(String::new(), |t| type_name(t, source)),
+        _ => String::new(),
+    }
+}
+
+/// `List` of `List<Order>`.
+fn generic_base(node: Node, source: &str) -> String {
+    node.named_child(0)
+        .filter(|n| n.kind() == "identifier")
+        .map_or(String::new(), |n| node_text(n, source).to_string())
+}
+
+/// Modifier keywords of a declaration (`public`, `static`, `async`, ...).
+fn modifiers<'a>(node: Node, source: &'a str) -> Vec<&'a str> {
+    node.named_children(&mut node.walk())
+        .filter(|child| child.kind() == "modifier")
+        .map(|m| node_text(m, source))
+        .collect()
+}
+
+/// Visibility from access modifiers, `default` without any. `internal` counts as
+/// public, like `pub(crate)` in Rust; `private protected` as private.
+fn csharp_visibility(node: Node, source: &str, default: Visibility) -> Visibility {
+    let modifiers = modifiers(node, source);
+    if modifiers.contains(&"private") {
+        Visibility::Private
+    } else if modifiers.contains(&"protected") {
+        Visibility::Protected
+    } else if modifiers.contains(&"public") || modifiers.contains(&"internal") {
+        Visibility::Public
+    } else {
+        default
+    }
+}
+
+/// Attribute names on a declaration, as written (`HttpGet`, `Route`).
+fn attribute_names<'a>(node: Node, source: &'a str) -> Vec<&'a str> {
+    node.named_children(&mut node.walk())
+        .filter(|child| child.kind() == "attribute_list")
+        .flat_map(|list| descendants_of_kind(list, &["attribute"]))
+        .filter_map(|attr| attr.child_by_field_name("name"))
+        .map(|name| node_text(name, source))
+        .collect()
+}
+
+/// ASP.NET route attributes: `[HttpGet]`, `[HttpPost("{id}")]`, `[Route(...)]`.
+fn attribute_entrypoint(attr: &str) -> Option<&'static str> {
+    let name = attr.rsplit('.').next().unwrap_or(attr);
+    let name = name.strip_suffix("Attribute").unwrap_or(name);
+    (name == "Route" || (name.starts_with("Http") && name.len() > 4)).then_some("route")
+}
+
+/// `Task<Invoice> CreateAsync(Order order)`: the declaration from its return type
+/// (or name) through its parameter list, without modifiers or attributes.
+fn extract_signature(node: Node, source: &str) -> Option<String> {
+    let params = node.child_by_field_name("parameters")?;
+    let start = node
+        .child_by_field_name("returns")
+        .or_else(|| node.child_by_field_name("type"))
+        .or_else(|| node.child_by_field_name("name"))?;
+    let text = source.get(start.start_byte()..params.end_byte())?;
+    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Preceding `///` XML doc comments, with the tags stripped.
+fn extract_doc_comment(node: Node, source: &str) -> Option<String> {
+    let mut lines = Vec::new();
+    let mut prev = node.prev_named_sibling();
+
+    while let Some(p) = prev {
+        if p.kind() != "comment" {
+            break;
+        }
+        let Some(stripped) = node_text(p, source).strip_prefix("///") else {
+            break;
+        };
+        lines.push(strip_xml_tags(stripped));
+        prev = p.prev_named_sibling();
+    }
+
+    lines.reverse();
+    let text = lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!text.is_empty()).then_some(text)
+}
+
+/// `<summary>Computes <see cref="Invoice"/>s.</summary>` → `Computes s.`
+/// keeps the text between tags only.
+fn strip_xml_tags(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut in_tag = false;
+    for c in line.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(source: &str) -> ExtractionResult {
+        let mut ext = CSharpExtractor::new();
+        ext.extract(source, "Billing.cs").unwrap()
+    }
+
+    fn symbol<'a>(result: &'a ExtractionResult, name: &str) -> &'a Symbol {
+        result
+            .symbols
+            .iter()
+            .find(|s| s.name == name)
+            .unwrap_or_else(|| panic!("no symbol {name}"))
+    }
+
+    fn targets(result: &ExtractionResult, kind: EdgeKind) -> Vec<&str> {
+        result
+            .edges
+            .iter()
+            .filter(|e| e.kind == kind)
+            .map(|e| e.target_name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_namespace_class_and_members() {
+        let result = extract(
+            r#"
+namespace Acme.Billing
+{
+    /// <summary>
+    /// Computes invoices.
+    /// </summary>
+    public class InvoiceService : BaseService, IInvoiceService
+    {
+        public int Count { get; private set; }
+        string Label => "invoices";
+
+        public InvoiceService(IRepository repo) : base(repo) { }
+
+        public async Task<Invoice> CreateAsync(Order order) { return null; }
+
+        private static void Validate(Invoice i) { }
+    }
+}
+"#,
+        );
+        let namespace = symbol(&result, "Acme.Billing");
+        assert_eq!(namespace.kind, SymbolKind::Class);
+        assert_eq!((namespace.start_line, namespace.end_line), (2, 18));
+
+        let class = symbol(&result, "InvoiceService");
+        assert_eq!(class.kind, SymbolKind::Class);
+        assert_eq!(class.parent_id.as_deref(), Some(namespace.id.as_str()));
+        assert_eq!(class.docstring.as_deref(), Some("Computes invoices."));
+        assert_eq!(class.visibility, Visibility::Public);
+
+        let count = symbol(&result, "Count");
+        assert_eq!(count.kind, SymbolKind::Variable);
+        assert_eq!(count.signature.as_deref(), Some("int Count { get; set; }"));
+        assert_eq!(count.parent_id.as_deref(), Some(class.id.as_str()));
+        // Members default to private
+        assert_eq!(symbol(&result, "Label").visibility, Visibility::Private);
+
+        let create = symbol(&result, "CreateAsync");
+        assert_eq!(create.kind, SymbolKind::Method);
+        assert!(create.is_async);
+        assert_eq!(
+            create.signature.as_deref(),
+            Some("Task<Invoice> CreateAsync(Order order)")
+        );
+        let ctor = result
+            .symbols
+            .iter()
+            .filter(|s| s.name == "InvoiceService")
+            .find(|s| s.kind == SymbolKind::Method)
+            .unwrap();
+        assert_eq!(
+            ctor.signature.as_deref(),
+            Some("InvoiceService(IRepository repo)")
+        );
+        assert_eq!(symbol(&result, "Validate").visibility, Visibility::Private);
+
+        assert_eq!(
+            targets(&result, EdgeKind::Inherits),
+            ["BaseService", "IInvoiceService"]
+        );
+        assert_eq!(
+            targets(&result, EdgeKind::References),
+            ["IRepository", "Task", "Invoice", "Order", "Invoice"]
+        );
+    }
+
+    #[test]
+    fn test_records_structs_interfaces_enums() {
+        let result = extract(
+            r#"
+namespace Acme.Models;
+
+public record Person(string Name) : Entity(Name), IComparable<Person>;
+public record struct Point(int X, int Y);
+internal interface IRepo<T> : Acme.Data.IReadable<T> { T Find(int id); }
+public struct Money { }
+enum Status { Open, Paid }
+"#,
+        );
+        let namespace = symbol(&result, "Acme.Models");
+        // A file-scoped namespace holds the rest of the file
+        assert_eq!(namespace.end_line, 9);
+        for name in ["Person", "Point", "IRepo", "Money", "Status"] {
+            let sym = symbol(&result, name);
+            assert_eq!(sym.kind, SymbolKind::Class, "{name}");
+            assert_eq!(sym.parent_id.as_deref(), Some(namespace.id.as_str()));
+        }
+        // Interface members are public; the method's own T is not a reference
+        let find = symbol(&result, "Find");
+        assert_eq!(find.visibility, Visibility::Public);
+        assert_eq!(
+            targets(&result, EdgeKind::Inherits),
+            ["Entity", "IComparable", "Acme.Data.IReadable"]
+        );
+    }
+
+    #[test]
+    fn test_using_directives() {
+        let result = extract(
+            r#"
+using System;
+using System.Collections.Generic;
+using static System.Math;
+using Repo = Acme.Data.Repository<User>;
+global using Json = Newtonsoft.Json;
+"#,
+        );
+        let imports: Vec<&str> = result
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Import)
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(
+            imports,
+            [
+                "System",
+                "System.Collections.Generic",
+                "System.Math",
+                "Acme.Data.Repository<User>",
+                "Newtonsoft.Json",
+            ]
+        );
+        assert_eq!(
+            targets(&result, EdgeKind::Imports),
+            [
+                "System",
+                "System.Collections.Generic",
+                "System.Math",
+                "Acme.Data.Repository",
+                "Newtonsoft.Json",
+            ]
+        );
+        assert_eq!(
+            symbol(&result, "System.Math").signature.as_deref(),
+            Some("using static System.Math;")
+        );
+        let aliases: Vec<(&str, &str)> = result
+            .aliases
+            .iter()
+            .map(|a| (a.alias.as_str(), a.name.as_str()))
+            .collect();
+        assert_eq!(aliases, [("Repo", "Repository"), ("Json", "Json")]);
+    }
+
+    #[test]
+    fn test_calls_and_raises() {
+        let result = extract(
+            r#"
+class Service
+{
+    public Invoice Create(Order order)
+    {
+        var inv = new Invoice(order.Id);
+        _repo.Save(inv);
+        Validate(inv);
+        this.Log(Run<int>(inv));
+        repo.Find(1).Close();
+        cache?.Evict(inv);
+        try { Load(); } catch (NotFoundException) { throw; }
+        if (inv == null) throw new InvalidOperationException("missing");
+        Func<int> f = () => Helper.Compute();
+        return inv;
+    }
+
+    class Nested { void Inner() { Hidden(); } }
+}
+"#,
+        );
+        let create = symbol(&result, "Create");
+        let calls: Vec<&str> = result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Calls && e.source_id == create.id)
+            .map(|e| e.target_name.as_str())
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                "Invoice",
+                "_repo.Save",
+                "Validate",
+                "this.Log",
+                "Run",
+                "Close",
+                "repo.Find",
+                "cache.Evict",
+                "Load",
+                "InvalidOperationException",
+                "Helper.Compute",
+            ]
+        );
+        assert_eq!(
+            targets(&result, EdgeKind::Raises),
+            ["InvalidOperationException"]
+        );
+        assert!(targets(&result, EdgeKind::References).contains(&"NotFoundException"));
+        // Nested types own their calls
+        let hidden = result
+            .edges
+            .iter()
+            .find(|e| e.target_name == "Hidden")
+            .unwrap();
+        assert_eq!(hidden.source_id, symbol(&result, "Inner").id);
+    }
+
+    #[test]
+    fn test_entrypoints_and_visibility() {
+        let result = extract(
+            r#"
+public static class Program
+{
+    public static void Main(string[] args) { }
+}
+
+[ApiController]
+public class UsersController : ControllerBase
+{
+    [HttpGet("{id}")]
+    public IActionResult Get(int id) => Ok();
+
+    protected internal void Audit() { }
+    private protected void Trace() { }
+    internal void Sync() { }
+    public static Money operator +(Money a, Money b) => a;
+}
+"#,
+        );
+        assert_eq!(symbol(&result, "Main").entrypoint.as_deref(), Some("main"));
+        let get = symbol(&result, "Get");
+        assert_eq!(get.entrypoint.as_deref(), Some("route"));
+        assert_eq!(get.signature.as_deref(), Some("IActionResult Get(int id)"));
+        assert_eq!(symbol(&result, "Audit").visibility, Visibility::Protected);
+        assert_eq!(symbol(&result, "Trace").visibility, Visibility::Private);
+        assert_eq!(symbol(&result, "Sync").visibility, Visibility::Public);
+        assert_eq!(
+            symbol(&result, "operator +").signature.as_deref(),
+            Some("Money operator +(Money a, Money b)")
+        );
+    }
+}
@@ -1,9 +1,9 @@
 use anyhow::Result;
 use tree_sitter::{Language, Node, Parser};
 
-use crate::types::{symbol_id, Edge, EdgeKind, Symbol, SymbolKind, Visibility};
+use crate::types::{symbol_id, Edge, EdgeKind, ImportAlias, Symbol, SymbolKind, Visibility};
 
-use super::{node_text, ExtractionResult, Extractor};
+use super::{descendants_of_kind, is_conditional, node_text, ExtractionResult, Extractor};
 
 pub struct GoExtractor {
     parser: Parser,
@@ -43,8 +43,9 @@ impl Extractor for GoExtractor {
             &mut symbols,
             &mut edges,
         );
+        let aliases = extract_aliases(tree.root_node(), source, file_path);
 
-        Ok(ExtractionResult { symbols, edges })
+        Ok(ExtractionResult::new(symbols, edges, aliases))
     }
 }
 
@@ -103,6 +104,12 @@ fn extract_function(
     let visibility = go_visibility(&name);
     let signature = extract_fn_signature(node, source);
     let docstring = extract_doc_comment(node, source);
+    // Called by the runtime: `main` of package main, and every package's `init`
+    let entrypoint = match name.as_str() {
+        "main" => Some("main"),
+        "init" => Some("init"),
+        _ => None,
+    };
 
     let sym_id = symbol_id(file_path, &name, start_line);
     let mut sym = Symbol::new(
@@ -116,7 +123,8 @@ fn extract_function(
     )
     .with_parent(parent_id)
     .with_signature(signature)
-    .with_docstring(docstring);
+    .with_docstring(docstring)
+    .with_entrypoint(entrypoint);
     if visibility != Visibility::Public {
         sym = sym.with_visibility(visibility);
     }
@@ -146,8 +154,8 @@ fn extract_method(
 
     // Extract receiver type for parent linkage.
     // NOTE: parent_id uses format "file_path:type_name" which doesn't match the
-    // symbol_id format "file_path:name:line". This means parent linkage for methods
-    // won't resolve to the struct symbol via direct id match. Edge resolution by
+    // symbol ID format (see `types::stable_symbol_id`). This means parent linkage
+    // for methods won't resolve to the struct symbol via direct id match. Edge resolution by
     // name (db.resolve_edges) handles cross-symbol references instead.
     let receiver_type = extract_receiver_type(node, source);
     let parent_id = receiver_type.as_ref().map(|rt| format!("{file_path}:{rt}"));
@@ -383,6 +391,26 @@ fn extract_import_spec(
     ));
 }
 
+/// `import f "fmt"` (f → fmt, the last path segment). Blank and dot imports bind no name.
+fn extract_aliases(root: Node, source: &str, file_path: &str) -> Vec<ImportAlias> {
+    root.named_children(&mut root.walk())
+        .filter(|node| node.kind() == "import_declaration")
+        .flat_map(|decl| descendants_of_kind(decl, &["import_spec"]))
+        .filter_map(|spec| {
+            let alias = spec
+                .child_by_field_name("name")
+                .filter(|name| name.kind() == "package_identifier")?;
+            let path = extract_import_path(spec, source);
+            Some(ImportAlias {
+                alias: node_text(alias, source).to_string(),
+                name: path.rsplit('/').next().unwrap_or(&path).to_string(),
+                file_path: file_path.to_string(),
+                line: spec.start_position().row as u32 + 1,
+            })
+        })
+        .collect()
+}
+
 /// Extract the path string from an import spec, stripping quotes.
 fn extract_import_path(node: Node, source: &str) -> String {
     let path_node = node.child_by_field_name("path");
@@ -556,6 +584,62 @@ fn extract_var_spec(
 
 // ── Call walking ──
 
+fn returned_values(ret: Node) -> Vec<Node> {
+    let mut values = Vec::new();
+    for child in ret.named_children(&mut ret.walk()) {
+        if child.kind() == "expression_list" {
+            values.extend(child.named_children(&mut child.walk()));
+        } else {
+            values.push(child);
+        }
+    }
+    values
+}
+
+/// Error named by a returned value: `&NotFoundError{..}`, `ErrNotFound`, `io.EOF`,
+/// `fmt.Errorf("..: %w", ErrNotFound)` (the wrapped sentinel), or `error` for an
+/// anonymous `errors.New(..)` / `fmt.Errorf(..)`. `None` for `err`, `nil` and non-errors.
+fn go_error_value(value: Node, source: &str) -> Option<String> {
+    match value.kind() {
+        "unary_expression" => go_error_value(value.child_by_field_name("operand")?, source),
+        "composite_literal" => {
+            let type_name = extract_type_name(value.child_by_field_name("type")?, source);
+            type_name.ends_with("Error").then_some(type_name)
+        }
+        "identifier" => is_sentinel_error(node_text(value, source))
+            .then(|| node_text(value, source).to_string()),
+        "selector_expression" => {
+            let field = node_text(value.child_by_field_name("field")?, source);
+            (is_sentinel_error(field) || field == "EOF").then(|| field.to_string())
+        }
+        "call_expression" => {
+            let callee = node_text(value.child_by_field_name("function")?, source);
+            if !matches!(
+                callee,
+                "errors.New" | "errors.Join" | "errors.Wrap" | "errors.Wrapf" | "fmt.Errorf"
+            ) {
+                return None;
+            }
+            let args = value.child_by_field_name("arguments")?;
+            let wrapped = args
+                .named_children(&mut args.walk())
+                .filter(|arg| arg.kind() != "identifier" || node_text(*arg, source) != "err")
+                .find_map(|arg| go_error_value(arg, source));
+            Some(wrapped.unwrap_or_else(|| "error".to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Go sentinel naming convention: `ErrNotFound`, `errClosed`.
+fn is_sentinel_error(name: &str) -> bool {
+    ["Err", "err"].iter().any(|prefix| {
+        name.strip_prefix(prefix)
+            .and_then(|rest| rest.chars().next())
+            .is_some_and(|c| c.is_ascii_uppercase())
+    })
+}
+
 fn walk_for_calls(
     node: Node,
     source: &str,
@@ -575,13 +659,16 @@ fn walk_for_calls(
                     if let Some(func) = current.child_by_field_name("function") {
                         let callee_name = node_text(func, source).to_string();
                         if !callee_name.is_empty() {
-                            edges.push(Edge::new(
-                                context_id,
-                                callee_name,
-                                EdgeKind::Calls,
-                                file_path,
-                                current.start_position().row as u32 + 1,
-                            ));
+                            edges.push(
+                                Edge::new(
+                                    context_id,
+                                    callee_name,
+                                    EdgeKind::Calls,
+                                    file_path,
+                                    current.start_position().row as u32 + 1,
+                                )
+                                .with_conditional(is_conditional(current, node, source)),
+                            );
                         }
                     }
                 }
@@ -602,6 +689,19 @@ fn walk_for_calls(
                         }
                     }
                 }
+                "return_statement" => {
+                    for value in returned_values(current) {
+                        if let Some(error_name) = go_error_value(value, source) {
+                            edges.push(Edge::new(
+                                context_id,
+                                error_name,
+                                EdgeKind::Raises,
+                                file_path,
+                                current.start_position().row as u32 + 1,
+                            ));
+                        }
+                    }
+                }
                 // Don't descend into nested function literals
                 "func_literal" => {
                     did_visit_children = true;
@@ -991,6 +1091,39 @@ import "fmt"
         assert_eq!(imports[0].name, "fmt");
     }
 
+    #[test]
+    fn test_named_imports_are_aliases() {
+        let result = extract(
+            r#"package main
+
+import (
+    f "fmt"
+    _ "net/http/pprof"
+    authpkg "example.com/app/internal/auth"
+)
+
+func main() {
+    f.Println(authpkg.Login())
+}
+"#,
+        );
+
+        let aliases: Vec<(&str, &str)> = result
+            .aliases
+            .iter()
+            .map(|a| (a.alias.as_str(), a.name.as_str()))
+            .collect();
+        assert_eq!(aliases, [("f", "fmt"), ("authpkg", "auth")]);
+        let calls: Vec<&str> = result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Calls)
+            .map(|e| e.target_name.as_str())
+            .collect();
+        assert!(calls.contains(&"fmt.Println"));
+        assert!(calls.contains(&"auth.Login"));
+    }
+
     #[test]
     fn test_function_calls() {
         let result = extract(
@@ -1269,6 +1402,84 @@ const DefaultErr = errors.New("default")
         assert_eq!(calls[0].target_name, "errors.New");
     }
 
+    #[test]
+    fn test_returned_errors_are_raises() {
+        let result = extract(
+            r#"package store
+
+var ErrNotFound = errors.New("not found")
+
+func Load(id string) (*Item, error) {
+	if id == "" {
+		return nil, &ValidationError{Field: "id"}
+	}
+	if missing(id) {
+		return nil, fmt.Errorf("load %s: %w", id, ErrNotFound)
+	}
+	if eof() {
+		return nil, io.EOF
+	}
+	item, err := read(id)
+	if err != nil {
+		return nil, err
+	}
+	return item, errors.New("unreachable")
+}
+"#,
+        );
+
+        let raises: Vec<(&str, u32)> = result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Raises)
+            .map(|e| (e.target_name.as_str(), e.line))
+            .collect();
+        assert_eq!(
+            raises,
+            vec![
+                ("ValidationError", 7),
+                ("ErrNotFound", 10),
+                ("EOF", 13),
+                ("error", 19)
+            ]
+        );
+        assert!(result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Raises)
+            .all(|e| e.source_id.contains(":Load:")));
+    }
+
+    #[test]
+    fn test_main_and_init_are_entrypoints() {
+        let result = extract(
+            r#"package main
+
+func init() {}
+
+func main() {
+	run()
+}
+
+func run() {}
+"#,
+        );
+
+        let entries: Vec<(&str, Option<&str>)> = result
+            .symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.entrypoint.as_deref()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("init", Some("init")),
+                ("main", Some("main")),
+                ("run", None)
+            ]
+        );
+    }
+
     #[test]
     fn test_empty_file() {
         let result = extract("");
@@ -120,6 +120,68 @@ import path from 'path';
         assert_eq!(imports.len(), 2);
     }
 
+    #[test]
+    fn test_commonjs_require() {
+        let result = extract_js(
+            r#"
+const { readFile, join: joinPath } = require('./utils');
+const express = require("express");
+require('./setup');
+"#,
+        );
+
+        let imports: Vec<&str> = result
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Import)
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(imports, vec!["./utils", "express", "./setup"]);
+        assert!(!result
+            .symbols
+            .iter()
+            .any(|s| s.kind == SymbolKind::Variable));
+
+        let imported: Vec<&str> = result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Imports)
+            .map(|e| e.target_name.as_str())
+            .collect();
+        assert_eq!(imported, vec!["readFile", "join", "express"]);
+    }
+
+    #[test]
+    fn test_reexports() {
+        let result = extract_js(
+            r#"
+export * from './bar';
+export { a, b as c } from './baz';
+"#,
+        );
+
+        let imports: Vec<_> = result
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Import)
+            .collect();
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[0].name, "./bar");
+        assert!(imports[0]
+            .signature
+            .as_deref()
+            .unwrap()
+            .starts_with("export * from"));
+
+        let imported: Vec<&str> = result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Imports)
+            .map(|e| e.target_name.as_str())
+            .collect();
+        assert_eq!(imported, vec!["a", "b"]);
+    }
+
     #[test]
     fn test_function_calls() {
         let result = extract_js(
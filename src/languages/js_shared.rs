@@ -7,9 +7,9 @@
 use anyhow::Result;
 use tree_sitter::{Node, Parser};
 
-use crate::types::{symbol_id, Edge, EdgeKind, Symbol, SymbolKind, Visibility};
+use crate::types::{symbol_id, Edge, EdgeKind, ImportAlias, Symbol, SymbolKind, Visibility};
 
-use super::{node_text, ExtractionResult};
+use super::{descendants_of_kind, is_conditional, node_text, ExtractionResult};
 
 /// Parse source and extract symbols + edges. Works for JS, TS, and TSX.
 pub fn extract(parser: &mut Parser, source: &str, file_path: &str) -> Result<ExtractionResult> {
@@ -29,7 +29,9 @@ pub fn extract(parser: &mut Parser, source: &str, file_path: &str) -> Result<Ext
         &mut edges,
     );
 
-    Ok(ExtractionResult { symbols, edges })
+    let aliases = extract_aliases(tree.root_node(), source, file_path);
+
+    Ok(ExtractionResult::new(symbols, edges, aliases))
 }
 
 fn extract_node(
@@ -57,12 +59,36 @@ fn extract_node(
         "import_statement" => {
             extract_import(node, source, file_path, parent_id, symbols, edges);
         }
+        // Re-exports (`export * from './bar'`, `export { a } from './bar'`) act as imports
+        "export_statement" if node.child_by_field_name("source").is_some() => {
+            extract_import(node, source, file_path, parent_id, symbols, edges);
+        }
         // Exports that wrap declarations
         "export_statement" => {
             for child in node.named_children(&mut node.walk()) {
                 extract_node(child, source, file_path, parent_id, symbols, edges);
             }
         }
+        // Side-effect CommonJS import: require('./setup');
+        "expression_statement"
+            if node
+                .named_child(0)
+                .and_then(|c| require_source(c, source))
+                .is_some() =>
+        {
+            if let Some(module_name) = node.named_child(0).and_then(|c| require_source(c, source)) {
+                push_import(
+                    node,
+                    source,
+                    file_path,
+                    parent_id,
+                    module_name,
+                    Vec::new(),
+                    symbols,
+                    edges,
+                );
+            }
+        }
         // Expression statements — scan for calls
         "expression_statement" => {
             walk_for_calls_and_throws(node, source, file_path, parent_id, edges);
@@ -155,6 +181,27 @@ fn extract_variable_declaration(
             continue;
         }
 
+        let value = child.child_by_field_name("value");
+
+        // CommonJS: const x = require('./x') / const { a, b } = require('./x')
+        if let Some(module_name) = value.and_then(|v| require_source(v, source)) {
+            let names = child
+                .child_by_field_name("name")
+                .map(|n| collect_binding_names(n, source))
+                .unwrap_or_default();
+            push_import(
+                node,
+                source,
+                file_path,
+                parent_id,
+                module_name,
+                names,
+                symbols,
+                edges,
+            );
+            continue;
+        }
+
         let name_node = match child.child_by_field_name("name") {
             Some(n) if n.kind() == "identifier" => n,
             _ => continue,
@@ -163,8 +210,6 @@ fn extract_variable_declaration(
         let start_line = node.start_position().row as u32 + 1;
         let end_line = node.end_position().row as u32 + 1;
 
-        let value = child.child_by_field_name("value");
-
         let is_function = value.as_ref().is_some_and(|v| is_function_like(v.kind()));
 
         if is_function {
@@ -414,14 +459,43 @@ fn extract_import(
     symbols: &mut Vec<Symbol>,
     edges: &mut Vec<Edge>,
 ) {
-    let line = node.start_position().row as u32 + 1;
-    let import_text = node_text(node, source).to_string();
-
     let module_name = extract_import_source(node, source);
     if module_name.is_empty() {
         return;
     }
 
+    // Collect imported names
+    let names = collect_imported_names(node, source);
+    push_import(
+        node,
+        source,
+        file_path,
+        parent_id,
+        module_name,
+        names,
+        symbols,
+        edges,
+    );
+}
+
+/// Emit an import symbol for `module_name` plus an `imports` edge per imported name.
+///
+/// The full statement text is kept as the signature; edge resolution relies on it
+/// to recognise `export * from` re-exports.
+#[allow(clippy::too_many_arguments)]
+fn push_import(
+    node: Node,
+    source: &str,
+    file_path: &str,
+    parent_id: Option<&str>,
+    module_name: String,
+    names: Vec<String>,
+    symbols: &mut Vec<Symbol>,
+    edges: &mut Vec<Edge>,
+) {
+    let line = node.start_position().row as u32 + 1;
+    let import_text = node_text(node, source).to_string();
+
     let sym_id = symbol_id(file_path, &module_name, line);
     symbols.push(
         Symbol::new(
@@ -437,8 +511,6 @@ fn extract_import(
         .with_signature(Some(import_text)),
     );
 
-    // Collect imported names
-    let names = collect_imported_names(node, source);
     for imported in names {
         edges.push(Edge::new(
             sym_id.clone(),
@@ -450,6 +522,52 @@ fn extract_import(
     }
 }
 
+/// If `node` is a `require('module')` call with a literal string argument, return the module.
+fn require_source(node: Node, source: &str) -> Option<String> {
+    if node.kind() != "call_expression" {
+        return None;
+    }
+    let func = node.child_by_field_name("function")?;
+    if node_text(func, source) != "require" {
+        return None;
+    }
+    let arg = node.child_by_field_name("arguments")?.named_child(0)?;
+    if arg.kind() != "string" {
+        return None;
+    }
+    let module = node_text(arg, source)
+        .trim_matches('\'')
+        .trim_matches('"')
+        .to_string();
+    if module.is_empty() {
+        None
+    } else {
+        Some(module)
+    }
+}
+
+/// Names bound by a `require` destructuring pattern.
+///
+/// `x` → `["x"]`, `{ a, b: c }` → `["a", "b"]` (the exported name, not the local alias).
+fn collect_binding_names(node: Node, source: &str) -> Vec<String> {
+    match node.kind() {
+        "identifier" => vec![node_text(node, source).to_string()],
+        "object_pattern" => node
+            .named_children(&mut node.walk())
+            .filter_map(|prop| match prop.kind() {
+                "shorthand_property_identifier_pattern" => {
+                    Some(node_text(prop, source).to_string())
+                }
+                "pair_pattern" => prop
+                    .child_by_field_name("key")
+                    .map(|k| node_text(k, source).to_string()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
 fn extract_import_source(node: Node, source: &str) -> String {
     // import ... from 'module'  —  the source is a string child
     node.child_by_field_name("source")
@@ -463,10 +581,37 @@ fn extract_import_source(node: Node, source: &str) -> String {
         .unwrap_or_default()
 }
 
+/// `import { a as b } from './mod'` (b → a). Default and namespace imports name
+/// no exported symbol.
+fn extract_aliases(root: Node, source: &str, file_path: &str) -> Vec<ImportAlias> {
+    root.named_children(&mut root.walk())
+        .filter(|node| node.kind() == "import_statement")
+        .flat_map(|stmt| descendants_of_kind(stmt, &["import_specifier"]))
+        .filter_map(|spec| {
+            Some(ImportAlias {
+                alias: node_text(spec.child_by_field_name("alias")?, source).to_string(),
+                name: node_text(spec.child_by_field_name("name")?, source).to_string(),
+                file_path: file_path.to_string(),
+                line: spec.start_position().row as u32 + 1,
+            })
+        })
+        .collect()
+}
+
 fn collect_imported_names(node: Node, source: &str) -> Vec<String> {
     let mut names = Vec::new();
 
     for child in node.named_children(&mut node.walk()) {
+        // export { a, b as c } from './mod' — record the original names
+        if child.kind() == "export_clause" {
+            for spec in child.named_children(&mut child.walk()) {
+                if spec.kind() == "export_specifier" {
+                    if let Some(n) = spec.child_by_field_name("name") {
+                        names.push(node_text(n, source).to_string());
+                    }
+                }
+            }
+        }
         if child.kind() == "import_clause" {
             for inner in child.named_children(&mut child.walk()) {
                 match inner.kind() {
@@ -631,13 +776,16 @@ fn walk_for_calls_and_throws(
                         if let Some(func) = current.child_by_field_name("function") {
                             let callee_name = node_text(func, source).to_string();
                             if !callee_name.is_empty() {
-                                edges.push(Edge::new(
-                                    ctx.to_string(),
-                                    callee_name,
-                                    EdgeKind::Calls,
-                                    file_path,
-                                    current.start_position().row as u32 + 1,
-                                ));
+                                edges.push(
+                                    Edge::new(
+                                        ctx.to_string(),
+                                        callee_name,
+                                        EdgeKind::Calls,
+                                        file_path,
+                                        current.start_position().row as u32 + 1,
+                                    )
+                                    .with_conditional(is_conditional(current, node, source)),
+                                );
                             }
                         }
                     }
@@ -647,13 +795,16 @@ fn walk_for_calls_and_throws(
                         if let Some(ctor) = current.child_by_field_name("constructor") {
                             let ctor_name = node_text(ctor, source).to_string();
                             if !ctor_name.is_empty() {
-                                edges.push(Edge::new(
-                                    ctx.to_string(),
-                                    ctor_name,
-                                    EdgeKind::Calls,
-                                    file_path,
-                                    current.start_position().row as u32 + 1,
-                                ));
+                                edges.push(
+                                    Edge::new(
+                                        ctx.to_string(),
+                                        ctor_name,
+                                        EdgeKind::Calls,
+                                        file_path,
+                                        current.start_position().row as u32 + 1,
+                                    )
+                                    .with_conditional(is_conditional(current, node, source)),
+                                );
                             }
                         }
                     }
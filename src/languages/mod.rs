@@ -1,22 +1,119 @@
+pub mod csharp;
 pub mod go;
 pub mod javascript;
 mod js_shared;
+pub mod prisma;
 pub mod python;
 pub mod ruby;
 pub mod rust_lang;
 pub mod typescript;
 
-use crate::types::{Edge, Symbol};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::types::{stable_symbol_id, Edge, EdgeKind, ImportAlias, LineCounts, Symbol};
 use anyhow::Result;
-use tree_sitter::Node;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tree_sitter::{Language, Node};
 
 /// Result of extracting symbols and edges from a source file.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ExtractionResult {
     pub symbols: Vec<Symbol>,
     pub edges: Vec<Edge>,
+    /// Names the file imports under another name, already applied to `edges`.
+    #[serde(default)]
+    pub aliases: Vec<ImportAlias>,
+}
+
+impl ExtractionResult {
+    /// Assemble a result, rewriting edge targets written with an import alias to
+    /// the imported name (`np.array` → `numpy.array`, `Baz::new` → `Bar::new`) so
+    /// they resolve to, and count as references of, the real symbol. Import edges
+    /// already name the imported symbol. An alias applies to its whole file, even
+    /// when imported inside a function.
+    pub fn new(symbols: Vec<Symbol>, mut edges: Vec<Edge>, aliases: Vec<ImportAlias>) -> Self {
+        let canonical: HashMap<&str, &str> = aliases
+            .iter()
+            .filter(|a| a.alias != a.name)
+            .map(|a| (a.alias.as_str(), a.name.as_str()))
+            .collect();
+        if !canonical.is_empty() {
+            for edge in edges.iter_mut().filter(|e| e.kind != EdgeKind::Imports) {
+                let head_len = edge
+                    .target_name
+                    .find(['.', ':'])
+                    .unwrap_or(edge.target_name.len());
+                let (head, rest) = edge.target_name.split_at(head_len);
+                if let Some(name) = canonical.get(head) {
+                    edge.target_name = format!("{name}{rest}");
+                }
+            }
+        }
+        Self {
+            symbols,
+            edges,
+            aliases,
+        }
+    }
+
+    /// Replace the provisional `file:name:line` IDs of the extractors with
+    /// [`stable_symbol_id`]s of each symbol's qualified name (`Class.method`) and
+    /// code in `source`, rewriting parent links and edge sources to match. Symbols
+    /// with the same qualified name and identical code get `-2`, `-3`, ... suffixes
+    /// in extraction order.
+    pub fn assign_stable_ids(&mut self, source: &str) {
+        let by_id: HashMap<String, (String, Option<String>)> = self
+            .symbols
+            .iter()
+            .map(|s| (s.id.clone(), (s.name.clone(), s.parent_id.clone())))
+            .collect();
+        let mut seen: HashMap<String, u32> = HashMap::new();
+        let mut new_ids: HashMap<String, String> = HashMap::with_capacity(self.symbols.len());
+        for sym in &mut self.symbols {
+            let mut qualified = vec![sym.name.as_str()];
+            let mut parent = sym.parent_id.as_deref();
+            // Bounded, in case an extractor ever links a symbol to itself.
+            while let Some((name, grandparent)) = parent
+                .and_then(|id| by_id.get(id))
+                .filter(|_| qualified.len() < MAX_NESTING)
+            {
+                qualified.push(name);
+                parent = grandparent.as_deref();
+            }
+            qualified.reverse();
+            let code = source
+                .get(sym.start_byte as usize..sym.end_byte as usize)
+                .unwrap_or_default();
+            let mut id = stable_symbol_id(&sym.file_path, &qualified.join("."), code);
+            let count = seen.entry(id.clone()).or_insert(0);
+            *count += 1;
+            if *count > 1 {
+                id = format!("{id}-{count}");
+            }
+            let old = std::mem::replace(&mut sym.id, id);
+            new_ids.entry(old).or_insert_with(|| sym.id.clone());
+        }
+
+        let rename = |id: &mut String| {
+            if let Some(new) = new_ids.get(id.as_str()) {
+                id.clone_from(new);
+            }
+        };
+        for sym in &mut self.symbols {
+            if let Some(parent) = &mut sym.parent_id {
+                rename(parent);
+            }
+        }
+        for edge in &mut self.edges {
+            rename(&mut edge.source_id);
+        }
+    }
 }
 
+/// Deepest parent chain followed to qualify a symbol name.
+const MAX_NESTING: usize = 32;
+
 /// Trait implemented by each language extractor.
 ///
 /// `extract` takes `&mut self` so implementations can reuse an internal
@@ -31,6 +128,137 @@ pub(crate) fn node_text<'a>(node: Node, source: &'a str) -> &'a str {
     source.get(node.start_byte()..node.end_byte()).unwrap_or("")
 }
 
+/// Nodes of one of `kinds` in the tree under `root`, in document order.
+pub(crate) fn descendants_of_kind<'a>(root: Node<'a>, kinds: &[&str]) -> Vec<Node<'a>> {
+    let mut found = Vec::new();
+    let mut cursor = root.walk();
+    loop {
+        let node = cursor.node();
+        if kinds.contains(&node.kind()) {
+            found.push(node);
+        }
+        if cursor.goto_first_child() {
+            continue;
+        }
+        loop {
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            if !cursor.goto_parent() {
+                return found;
+            }
+        }
+    }
+}
+
+/// Entry point kind implied by a decorator or attribute path (`app.route`,
+/// `tokio::main`, `click.command`), judged by its last segment.
+pub(crate) fn decorator_entrypoint(path: &str) -> Option<&'static str> {
+    let last = path.rsplit(['.', ':']).next().unwrap_or(path);
+    match last {
+        "main" if path != last => Some("main"),
+        "route" | "get" | "post" | "put" | "patch" | "delete" | "head" | "options"
+        | "websocket" | "api_view" => Some("route"),
+        "command" | "group" => Some("cli"),
+        "task" | "shared_task" | "periodic_task" => Some("task"),
+        _ => None,
+    }
+}
+
+/// Node kinds whose children only run under a condition: `if`/`else` branches,
+/// ternaries, `switch`/`match` arms and `#if` blocks, across the grammars. Their
+/// `condition` and `initializer` always run.
+const GUARD_KINDS: &[&str] = &[
+    "if_statement",
+    "if_expression",
+    "if",
+    "if_modifier",
+    "unless",
+    "unless_modifier",
+    "elif_clause",
+    "elsif",
+    "else_clause",
+    "else",
+    "ternary_expression",
+    "conditional_expression",
+    "conditional",
+    "switch_case",
+    "switch_default",
+    "switch_section",
+    "switch_expression_arm",
+    "expression_case",
+    "type_case",
+    "communication_case",
+    "default_case",
+    "case_clause",
+    "when",
+    "match_arm",
+    "preproc_if",
+    "preproc_elif",
+    "preproc_else",
+];
+
+/// Whether `node` (a call site) sits under a condition or feature gate within
+/// `scope`, the body being walked: a branch or arm of [`GUARD_KINDS`], the right
+/// operand of `&&`/`||`/`and`/`or`/`??`, or a statement behind `#[cfg(..)]`.
+pub(crate) fn is_conditional(node: Node, scope: Node, source: &str) -> bool {
+    let mut child = node;
+    while child != scope {
+        if is_cfg_gated(child, source) {
+            return true;
+        }
+        let Some(parent) = child.parent() else {
+            break;
+        };
+        if guards(parent, child) {
+            return true;
+        }
+        child = parent;
+    }
+    false
+}
+
+/// Whether `child` runs only when its `parent` decides so.
+fn guards(parent: Node, child: Node) -> bool {
+    let kind = parent.kind();
+    if GUARD_KINDS.contains(&kind) {
+        // Python's `a if cond else b` has no fields: the condition is in the middle
+        if kind == "conditional_expression" && parent.child_by_field_name("condition").is_none() {
+            return parent.named_child(1) != Some(child);
+        }
+        return ["condition", "initializer"]
+            .iter()
+            .all(|field| parent.child_by_field_name(field) != Some(child));
+    }
+    matches!(kind, "binary_expression" | "boolean_operator" | "binary")
+        && parent.child_by_field_name("right") == Some(child)
+        && parent
+            .child_by_field_name("operator")
+            .is_some_and(|op| matches!(op.kind(), "&&" | "||" | "and" | "or" | "??"))
+}
+
+/// A Rust statement or item preceded by a `#[cfg(..)]` attribute (not
+/// `#[cfg_attr(..)]`, which only gates other attributes).
+fn is_cfg_gated(node: Node, source: &str) -> bool {
+    let mut prev = node.prev_sibling();
+    while let Some(p) = prev {
+        match p.kind() {
+            "attribute_item" => {}
+            "line_comment" | "block_comment" => {
+                prev = p.prev_sibling();
+                continue;
+            }
+            _ => break,
+        }
+        let path = p.named_child(0).and_then(|attr| attr.named_child(0));
+        if path.is_some_and(|path| node_text(path, source) == "cfg") {
+            return true;
+        }
+        prev = p.prev_sibling();
+    }
+    false
+}
+
 /// Map file extension to language name.
 pub fn detect_language(path: &std::path::Path) -> Option<&'static str> {
     let ext = path.extension()?.to_str()?;
@@ -42,10 +270,98 @@ pub fn detect_language(path: &std::path::Path) -> Option<&'static str> {
         "rs" => Some("rust"),
         "go" => Some("go"),
         "rb" => Some("ruby"),
+        "cs" => Some("csharp"),
+        "prisma" => Some("prisma"),
         _ => None,
     }
 }
 
+/// Bytes read from the start of a file by [`detect_file_language`].
+const SNIFF_BYTES: u64 = 512;
+
+/// Language of the file at `path` on disk: [`detect_language`], with the start of
+/// the file breaking ties where an extension alone can't tell.
+///
+/// - `.ts` holding Qt Linguist XML and `.rs` holding RenderScript (`#pragma
+///   version`) are not source files
+/// - a file without an extension goes by its shebang (`#!/usr/bin/env python3`,
+///   `node`, `deno`, `ruby`, ...)
+///
+/// A file that can't be read is judged by its extension alone.
+pub fn detect_file_language(path: &std::path::Path) -> Option<&'static str> {
+    use std::io::Read;
+
+    let ext = path.extension().and_then(|e| e.to_str());
+    if !matches!(ext, None | Some("ts" | "rs")) {
+        return detect_language(path);
+    }
+    let mut head = Vec::new();
+    if let Ok(file) = std::fs::File::open(path) {
+        let _ = file.take(SNIFF_BYTES).read_to_end(&mut head);
+    }
+    sniff_language(path, &String::from_utf8_lossy(&head))
+}
+
+/// [`detect_file_language`] given the first bytes of the file.
+fn sniff_language(path: &std::path::Path, head: &str) -> Option<&'static str> {
+    let start = head.trim_start_matches('\u{feff}').trim_start();
+    match detect_language(path) {
+        Some("typescript") if start.starts_with("<?xml") || start.starts_with("<TS") => None,
+        Some("rust")
+            if start.lines().any(|line| {
+                let line = line.trim_start();
+                line.starts_with("#pragma version") || line.starts_with("#pragma rs ")
+            }) =>
+        {
+            None
+        }
+        Some(lang) => Some(lang),
+        None if path.extension().is_none() => shebang_language(head.lines().next()?),
+        None => None,
+    }
+}
+
+/// Language of a script from its `#!` line: the interpreter, or the command
+/// `env` runs.
+fn shebang_language(line: &str) -> Option<&'static str> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|w| !w.starts_with('-'))?;
+    }
+    let name = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    match name {
+        "python" | "pypy" => Some("python"),
+        "node" | "nodejs" | "bun" => Some("javascript"),
+        "deno" | "ts-node" | "tsx" => Some("typescript"),
+        "ruby" => Some("ruby"),
+        _ => None,
+    }
+}
+
+/// The name of a language files can be indexed as (as stored per file), `None`
+/// when `name` isn't one.
+pub fn indexed_language(name: &str) -> Option<&'static str> {
+    QUERY_LANGUAGES
+        .iter()
+        .chain(&["tsx"])
+        .copied()
+        .find(|lang| *lang == name)
+}
+
+/// Languages a query can be scoped to (`--lang`). `typescript` covers `.tsx` files
+/// too, which are indexed as `tsx`.
+pub const QUERY_LANGUAGES: &[&str] = &[
+    "python",
+    "typescript",
+    "javascript",
+    "rust",
+    "go",
+    "ruby",
+    "csharp",
+    "prisma",
+];
+
 /// Get the extractor for a language name.
 pub fn get_extractor(language: &str) -> Option<Box<dyn Extractor>> {
     match language {
@@ -56,14 +372,266 @@ pub fn get_extractor(language: &str) -> Option<Box<dyn Extractor>> {
         "rust" => Some(Box::new(rust_lang::RustExtractor::new())),
         "go" => Some(Box::new(go::GoExtractor::new())),
         "ruby" => Some(Box::new(ruby::RubyExtractor::new())),
+        "csharp" => Some(Box::new(csharp::CSharpExtractor::new())),
+        "prisma" => Some(Box::new(prisma::PrismaExtractor::new())),
         _ => None,
     }
 }
 
+/// Languages parsed with a tree-sitter grammar (Prisma is scanned by line).
+const GRAMMAR_LANGUAGES: &[&str] = &[
+    "python",
+    "typescript",
+    "tsx",
+    "javascript",
+    "rust",
+    "go",
+    "ruby",
+    "csharp",
+];
+
+fn grammar(language: &str) -> Option<Language> {
+    let lang = match language {
+        "python" => tree_sitter_python::LANGUAGE,
+        "typescript" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+        "tsx" => tree_sitter_typescript::LANGUAGE_TSX,
+        "javascript" => tree_sitter_javascript::LANGUAGE,
+        "rust" => tree_sitter_rust::LANGUAGE,
+        "go" => tree_sitter_go::LANGUAGE,
+        "ruby" => tree_sitter_ruby::LANGUAGE,
+        "csharp" => tree_sitter_c_sharp::LANGUAGE,
+        _ => return None,
+    };
+    Some(Language::new(lang))
+}
+
+/// Fingerprint of the tree-sitter grammar `language` is parsed with: its ABI
+/// version and a hash of its node kinds, fields and parse states, e.g.
+/// `abi14-3f2a9c01d4e7`. Grammar crates don't expose their version, but any update
+/// that could change what the extractors see changes this. `None` without a grammar.
+pub fn grammar_version(language: &str) -> Option<String> {
+    let lang = grammar(language)?;
+    let mut hasher = Sha256::new();
+    hasher.update(lang.parse_state_count().to_le_bytes());
+    for id in 0..lang.node_kind_count() as u16 {
+        hasher.update(lang.node_kind_for_id(id).unwrap_or("").as_bytes());
+        hasher.update([u8::from(lang.node_kind_is_named(id)), 0]);
+    }
+    for id in 1..=lang.field_count() as u16 {
+        hasher.update(lang.field_name_for_id(id).unwrap_or("").as_bytes());
+        hasher.update([0]);
+    }
+    let hash = format!("{:x}", hasher.finalize());
+    Some(format!("abi{}-{}", lang.version(), &hash[..12]))
+}
+
+/// [`grammar_version`] of every language parsed with a grammar.
+pub fn grammar_versions() -> BTreeMap<String, String> {
+    GRAMMAR_LANGUAGES
+        .iter()
+        .filter_map(|lang| Some((lang.to_string(), grammar_version(lang)?)))
+        .collect()
+}
+
+/// How a language writes comments and strings, as far as [`count_lines`] cares.
+struct CommentSyntax {
+    line: &'static [u8],
+    /// Opening and closing markers of block comments.
+    block: Option<(&'static [u8], &'static [u8])>,
+    /// Bytes opening a string that runs to the same byte.
+    quotes: &'static [u8],
+}
+
+impl CommentSyntax {
+    fn of(language: &str) -> Self {
+        let (line, block, quotes): (&'static [u8], _, &'static [u8]) = match language {
+            "python" => (b"#", None, b"\"'"),
+            "ruby" => (b"#", Some((&b"=begin"[..], &b"=end"[..])), b"\"'`"),
+            "javascript" | "typescript" | "tsx" => (b"//", Some((&b"/*"[..], &b"*/"[..])), b"\"'`"),
+            "prisma" => (b"//", None, b"\""),
+            // Rust, Go and C#: `'` opens char literals and lifetimes, not strings.
+            _ => (b"//", Some((&b"/*"[..], &b"*/"[..])), b"\""),
+        };
+        Self {
+            line,
+            block,
+            quotes,
+        }
+    }
+}
+
+/// Count the code, comment and blank lines of `source`, written in `language`.
+///
+/// A lexical scan, not a parse: line comments, block comments (`/* */`, Ruby's
+/// `=begin`/`=end`) and quoted strings on one line are recognized, so a `//` inside
+/// a string stays code. A line with any code on it counts as code; docstrings are
+/// code. Blank lines inside a block comment count as blank.
+pub fn count_lines(source: &str, language: &str) -> LineCounts {
+    let CommentSyntax {
+        line: line_comment,
+        block,
+        quotes,
+    } = CommentSyntax::of(language);
+
+    let mut counts = LineCounts::default();
+    let mut in_block = false;
+    for line in source.lines() {
+        let line = line.trim().as_bytes();
+        if line.is_empty() {
+            counts.blank += 1;
+            continue;
+        }
+        // Ruby's block comment markers only count at the start of a line.
+        if language == "ruby" {
+            if in_block {
+                in_block = !line.starts_with(b"=end");
+                counts.comment += 1;
+                continue;
+            }
+            if line.starts_with(b"=begin") {
+                in_block = true;
+                counts.comment += 1;
+                continue;
+            }
+        }
+
+        let (mut code, mut comment) = (false, false);
+        let mut quote = None;
+        let mut i = 0;
+        while i < line.len() {
+            let rest = &line[i..];
+            if in_block {
+                comment = true;
+                let (_, close) = block.expect("only languages with block comments enter one");
+                match rest.windows(close.len()).position(|w| w == close) {
+                    Some(end) => {
+                        in_block = false;
+                        i += end + close.len();
+                    }
+                    None => break,
+                }
+            } else if let Some(q) = quote {
+                match line[i] {
+                    b'\\' => i += 2,
+                    b if b == q => {
+                        quote = None;
+                        i += 1;
+                    }
+                    _ => i += 1,
+                }
+            } else if rest.starts_with(line_comment) {
+                comment = true;
+                break;
+            } else if let Some((open, _)) =
+                block.filter(|(open, _)| language != "ruby" && rest.starts_with(open))
+            {
+                in_block = true;
+                i += open.len();
+            } else {
+                let b = line[i];
+                code |= !b.is_ascii_whitespace();
+                if quotes.contains(&b) {
+                    quote = Some(b);
+                }
+                i += 1;
+            }
+        }
+
+        if code {
+            counts.code += 1;
+        } else if comment {
+            counts.comment += 1;
+        } else {
+            counts.blank += 1;
+        }
+    }
+    counts
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_count_lines() {
+        let rust = "/// Doc\nfn main() { // entry\n\n    /* block\n\n       end */ let s = \"// not a comment\";\n    /* one */\n}\n";
+        assert_eq!(
+            count_lines(rust, "rust"),
+            LineCounts {
+                code: 3,
+                comment: 3,
+                blank: 2
+            }
+        );
+
+        let python = "# comment\nimport os\n\nx = '# not a comment'  # trailing\n";
+        assert_eq!(
+            count_lines(python, "python"),
+            LineCounts {
+                code: 2,
+                comment: 1,
+                blank: 1
+            }
+        );
+
+        let ruby = "=begin\nnotes\n=end\nputs 'hi' # greet\n";
+        assert_eq!(
+            count_lines(ruby, "ruby"),
+            LineCounts {
+                code: 1,
+                comment: 3,
+                blank: 0
+            }
+        );
+        assert_eq!(count_lines("", "go"), LineCounts::default());
+    }
+
+    #[test]
+    fn test_stable_ids_survive_line_shifts() {
+        let code = "class Auth:\n    def login(self):\n        check()\n\ndef noop():\n    pass\n\ndef noop():\n    pass\n";
+        let extract = |source: &str| {
+            let mut result = get_extractor("python")
+                .unwrap()
+                .extract(source, "a.py")
+                .unwrap();
+            result.assign_stable_ids(source);
+            result
+        };
+        let before = extract(code);
+        let after = extract(&format!("import os\n\n{code}"));
+
+        let ids = |r: &ExtractionResult| -> Vec<String> {
+            r.symbols
+                .iter()
+                .filter(|s| s.name != "os")
+                .map(|s| s.id.clone())
+                .collect()
+        };
+        assert_eq!(ids(&before), ids(&after));
+        let login = before.symbols.iter().find(|s| s.name == "login").unwrap();
+        assert!(login.id.starts_with("a.py:Auth.login:"), "{}", login.id);
+        let auth = before.symbols.iter().find(|s| s.name == "Auth").unwrap();
+        assert_eq!(login.parent_id.as_deref(), Some(auth.id.as_str()));
+        let call = before
+            .edges
+            .iter()
+            .find(|e| e.target_name == "check")
+            .unwrap();
+        assert_eq!(call.source_id, login.id);
+        // Identical definitions are told apart by order.
+        let noops: Vec<&str> = before
+            .symbols
+            .iter()
+            .filter(|s| s.name == "noop")
+            .map(|s| s.id.as_str())
+            .collect();
+        assert_eq!(noops[1], format!("{}-2", noops[0]));
+
+        let edited = extract(&code.replace("check()", "check(self)"));
+        let edited_login = edited.symbols.iter().find(|s| s.name == "login").unwrap();
+        assert_ne!(edited_login.id, login.id, "editing the code changes the ID");
+    }
+
     #[test]
     fn test_detect_language() {
         use std::path::Path;
@@ -76,11 +644,66 @@ mod tests {
         assert_eq!(detect_language(Path::new("main.rs")), Some("rust"));
         assert_eq!(detect_language(Path::new("server.go")), Some("go"));
         assert_eq!(detect_language(Path::new("app.rb")), Some("ruby"));
+        assert_eq!(detect_language(Path::new("Program.cs")), Some("csharp"));
+        assert_eq!(
+            detect_language(Path::new("prisma/schema.prisma")),
+            Some("prisma")
+        );
         assert_eq!(detect_language(Path::new("README.md")), None);
         assert_eq!(detect_language(Path::new("Makefile")), None);
         assert_eq!(detect_language(Path::new("Main.java")), None); // java not supported yet
     }
 
+    #[test]
+    fn test_sniff_language() {
+        use std::path::Path;
+        let ts = Path::new("i18n/app_fr.ts");
+        assert_eq!(
+            sniff_language(ts, "export const a = 1;\n"),
+            Some("typescript")
+        );
+        assert_eq!(
+            sniff_language(
+                ts,
+                "<?xml version=\"1.0\"?>\n<!DOCTYPE TS>\n<TS version=\"2.1\">"
+            ),
+            None
+        );
+        let rs = Path::new("gfx/blur.rs");
+        assert_eq!(sniff_language(rs, "fn main() {}\n"), Some("rust"));
+        assert_eq!(
+            sniff_language(
+                rs,
+                "// blur\n#pragma version(1)\n#pragma rs java_package_name(a)\n"
+            ),
+            None
+        );
+        let script = Path::new("bin/deploy");
+        assert_eq!(
+            sniff_language(script, "#!/usr/bin/env python3\nimport os\n"),
+            Some("python")
+        );
+        assert_eq!(
+            sniff_language(script, "#!/usr/bin/env -S deno run -A\n"),
+            Some("typescript")
+        );
+        assert_eq!(
+            sniff_language(script, "#!/usr/local/bin/node\n"),
+            Some("javascript")
+        );
+        assert_eq!(sniff_language(script, "#!/bin/sh\nset -e\n"), None);
+        assert_eq!(sniff_language(Path::new("Makefile"), "all:\n"), None);
+        // Shebangs only decide for files without an extension.
+        assert_eq!(
+            sniff_language(Path::new("run.sh"), "#!/usr/bin/env python\n"),
+            None
+        );
+
+        assert_eq!(indexed_language("tsx"), Some("tsx"));
+        assert_eq!(indexed_language("ruby"), Some("ruby"));
+        assert_eq!(indexed_language("c"), None);
+    }
+
     #[test]
     fn test_get_extractor() {
         assert!(get_extractor("python").is_some());
@@ -90,7 +713,94 @@ mod tests {
         assert!(get_extractor("rust").is_some());
         assert!(get_extractor("go").is_some());
         assert!(get_extractor("ruby").is_some());
+        assert!(get_extractor("csharp").is_some());
+        assert!(get_extractor("prisma").is_some());
         assert!(get_extractor("java").is_none());
         assert!(get_extractor("unknown").is_none());
     }
+
+    #[test]
+    fn test_conditional_calls() {
+        let conditional_calls = |language: &str, source: &str| -> Vec<(String, bool)> {
+            let mut extractor = get_extractor(language).unwrap();
+            let result = extractor.extract(source, "f").unwrap();
+            result
+                .edges
+                .into_iter()
+                .filter(|e| e.kind == EdgeKind::Calls)
+                .map(|e| (e.target_name, e.conditional))
+                .collect()
+        };
+        let calls = |pairs: &[(&str, bool)]| -> Vec<(String, bool)> {
+            pairs.iter().map(|(n, c)| (n.to_string(), *c)).collect()
+        };
+
+        let python = "def f():\n    setup()\n    if check():\n        log()\n    else:\n        fail()\n    x = a() if ok() else b()\n    debug and trace()\n";
+        assert_eq!(
+            conditional_calls("python", python),
+            calls(&[
+                ("setup", false),
+                ("check", false),
+                ("log", true),
+                ("fail", true),
+                ("a", true),
+                ("ok", false),
+                ("b", true),
+                ("trace", true),
+            ])
+        );
+
+        let rust = "fn f() {\n    #[cfg(feature = \"metrics\")]\n    record();\n    match v { 1 => one(), _ => other() }\n    if let Some(x) = get() { use_it(x); }\n    run();\n}\n";
+        assert_eq!(
+            conditional_calls("rust", rust),
+            calls(&[
+                ("record", true),
+                ("one", true),
+                ("other", true),
+                ("get", false),
+                ("use_it", true),
+                ("run", false),
+            ])
+        );
+
+        let go = "package m\nfunc f() {\n\tif err := load(); err != nil {\n\t\thandle(err)\n\t}\n\tswitch v {\n\tcase 1:\n\t\tone()\n\t}\n}\n";
+        assert_eq!(
+            conditional_calls("go", go),
+            calls(&[("load", false), ("handle", true), ("one", true)])
+        );
+
+        let ruby = "def f\n  notify(user) if user.admin?\n  save(user)\nend\n";
+        assert_eq!(
+            conditional_calls("ruby", ruby),
+            calls(&[("notify", true), ("user.admin?", false), ("save", false)])
+        );
+
+        let typescript =
+            "function f() {\n  debug && log();\n  const x = ok ? make() : null;\n  run();\n}\n";
+        assert_eq!(
+            conditional_calls("typescript", typescript),
+            calls(&[("log", true), ("make", true), ("run", false)])
+        );
+
+        let csharp = "class C {\n  void F() {\n#if DEBUG\n    Trace();\n#endif\n    var s = v switch { 1 => One(), _ => Other() };\n    Run();\n  }\n}\n";
+        assert_eq!(
+            conditional_calls("csharp", csharp),
+            calls(&[
+                ("Trace", true),
+                ("One", true),
+                ("Other", true),
+                ("Run", false)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_grammar_versions() {
+        let versions = grammar_versions();
+        assert_eq!(versions.len(), GRAMMAR_LANGUAGES.len());
+        assert!(versions["python"].starts_with("abi"));
+        assert_ne!(versions["typescript"], versions["tsx"]);
+        assert_eq!(grammar_version("go"), grammar_version("go"));
+        assert_eq!(grammar_version("prisma"), None);
+    }
 }
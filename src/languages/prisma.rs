@@ -0,0 +1,157 @@
+use anyhow::Result;
+
+use crate::types::{Symbol, SymbolKind};
+
+use super::{ExtractionResult, Extractor};
+
+/// Extracts models from Prisma schema files (`schema.prisma`).
+///
+/// A line scan rather than a grammar: each `model Name { ... }` block becomes a
+/// class symbol whose [`Symbol::db_table`] is its `@@map("table")`, or the model
+/// name, Prisma's default. `///` comments above a model are its docstring.
+#[derive(Default)]
+pub struct PrismaExtractor;
+
+impl PrismaExtractor {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Extractor for PrismaExtractor {
+    fn extract(&mut self, source: &str, file_path: &str) -> Result<ExtractionResult> {
+        let mut symbols = Vec::new();
+        let mut docs: Vec<&str> = Vec::new();
+        let mut open: Option<OpenModel> = None;
+        let mut offset = 0;
+
+        for (idx, line) in source.split_inclusive('\n').enumerate() {
+            let line_no = idx as u32 + 1;
+            let trimmed = line.trim();
+            match &mut open {
+                None => {
+                    if let Some(doc) = trimmed.strip_prefix("///") {
+                        docs.push(doc.trim());
+                    } else if let Some(name) = model_name(trimmed) {
+                        let doc = (!docs.is_empty()).then(|| docs.join("\n"));
+                        open = Some(OpenModel {
+                            name: name.to_string(),
+                            start_line: line_no,
+                            start_byte: offset,
+                            doc,
+                            table: None,
+                        });
+                        docs.clear();
+                    } else {
+                        docs.clear();
+                    }
+                }
+                Some(model) => {
+                    if let Some(args) = trimmed.strip_prefix("@@map(") {
+                        model.table = map_argument(args);
+                    } else if trimmed == "}" {
+                        let model = open.take().unwrap();
+                        let end_byte = offset + line.trim_end().len();
+                        let table = model.table.unwrap_or_else(|| model.name.clone());
+                        symbols.push(
+                            Symbol::new(
+                                model.name,
+                                SymbolKind::Class,
+                                file_path,
+                                model.start_line,
+                                line_no,
+                                model.start_byte as u32,
+                                end_byte as u32,
+                            )
+                            .with_docstring(model.doc)
+                            .with_db_table(Some(table)),
+                        );
+                    }
+                }
+            }
+            offset += line.len();
+        }
+
+        Ok(ExtractionResult {
+            symbols,
+            ..Default::default()
+        })
+    }
+}
+
+/// A `model` block whose closing brace is not reached yet.
+struct OpenModel {
+    name: String,
+    start_line: u32,
+    start_byte: usize,
+    doc: Option<String>,
+    /// `@@map` table, if seen.
+    table: Option<String>,
+}
+
+/// `Name` from a `model Name {` line.
+fn model_name(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix("model")?;
+    let name = rest.trim().strip_suffix('{')?.trim();
+    (rest.starts_with(char::is_whitespace) && !name.is_empty() && !name.contains(' '))
+        .then_some(name)
+}
+
+/// Table of `@@map("table")` / `@@map(name: "table")`, given what follows `@@map(`.
+fn map_argument(args: &str) -> Option<String> {
+    let args = args.trim_start();
+    let args = args.strip_prefix("name:").unwrap_or(args).trim_start();
+    let value = args.strip_prefix('"')?;
+    Some(value[..value.find('"')?].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_models() {
+        let source = r#"datasource db {
+  provider = "postgresql"
+}
+
+/// A registered user.
+model User {
+  id    Int    @id
+  posts Post[]
+
+  @@map("users")
+}
+
+model Post {
+  id Int @id
+}
+"#;
+        let result = PrismaExtractor::new()
+            .extract(source, "schema.prisma")
+            .unwrap();
+        let models: Vec<(&str, u32, u32, Option<&str>)> = result
+            .symbols
+            .iter()
+            .map(|s| {
+                (
+                    s.name.as_str(),
+                    s.start_line,
+                    s.end_line,
+                    s.db_table.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            models,
+            [
+                ("User", 6, 11, Some("users")),
+                ("Post", 13, 15, Some("Post"))
+            ]
+        );
+        let user = &result.symbols[0];
+        assert_eq!(user.docstring.as_deref(), Some("A registered user."));
+        let body = &source[user.start_byte as usize..user.end_byte as usize];
+        assert!(body.starts_with("model User {") && body.ends_with('}'));
+    }
+}
@@ -1,9 +1,12 @@
 use anyhow::Result;
 use tree_sitter::{Language, Node, Parser};
 
-use crate::types::{symbol_id, Edge, EdgeKind, Symbol, SymbolKind, Visibility};
+use crate::types::{symbol_id, Edge, EdgeKind, ImportAlias, Symbol, SymbolKind, Visibility};
 
-use super::{node_text, ExtractionResult, Extractor};
+use super::{
+    decorator_entrypoint, descendants_of_kind, is_conditional, node_text, ExtractionResult,
+    Extractor,
+};
 
 pub struct PythonExtractor {
     parser: Parser,
@@ -44,8 +47,12 @@ impl Extractor for PythonExtractor {
             &mut symbols,
             &mut edges,
         );
+        extract_dynamic_imports(root, source, file_path, &mut symbols, &mut edges);
+        apply_dunder_all(root, source, file_path, &mut symbols, &mut edges);
+        mark_main_guard_calls(root, source, &mut symbols);
+        let aliases = extract_aliases(root, source, file_path);
 
-        Ok(ExtractionResult { symbols, edges })
+        Ok(ExtractionResult::new(symbols, edges, aliases))
     }
 }
 
@@ -77,15 +84,27 @@ fn extract_node(
                 }
             }
             // Extract decorator references using the decorated symbol as source
+            let first_symbol = symbols.len();
+            let mut entrypoint = None;
             for child in node.named_children(&mut node.walk()) {
                 if child.kind() == "decorator" {
                     extract_decorator_ref(child, source, file_path, def_sym_id.as_deref(), edges);
+                    entrypoint = entrypoint.or_else(|| {
+                        decorator_name(child, source).and_then(|n| decorator_entrypoint(&n))
+                    });
                 } else if child.kind() == "function_definition"
                     || child.kind() == "class_definition"
                 {
                     extract_node(child, source, file_path, parent_id, symbols, edges);
                 }
             }
+            // `@app.route(...)`, `@click.command()`, `@shared_task`, ...
+            if let Some(sym) = symbols[first_symbol..]
+                .iter_mut()
+                .find(|s| Some(&s.id) == def_sym_id.as_ref())
+            {
+                sym.entrypoint = entrypoint.map(str::to_string);
+            }
         }
         "import_statement" | "import_from_statement" => {
             extract_import(node, source, file_path, parent_id, symbols, edges);
@@ -353,13 +372,16 @@ fn walk_for_calls_and_raises(
                         if let Some(func) = current.child_by_field_name("function") {
                             let callee_name = node_text(func, source);
                             if !callee_name.is_empty() {
-                                edges.push(Edge::new(
-                                    ctx,
-                                    callee_name,
-                                    EdgeKind::Calls,
-                                    file_path,
-                                    current.start_position().row as u32 + 1,
-                                ));
+                                edges.push(
+                                    Edge::new(
+                                        ctx,
+                                        callee_name,
+                                        EdgeKind::Calls,
+                                        file_path,
+                                        current.start_position().row as u32 + 1,
+                                    )
+                                    .with_conditional(is_conditional(current, node, source)),
+                                );
                             }
                         }
                     }
@@ -453,6 +475,175 @@ fn walk_for_calls_and_raises(
     }
 }
 
+// ── Module exports and dynamic imports ──
+
+/// Callees that import a module named by their first (string) argument.
+const DYNAMIC_IMPORT_FUNCS: &[&str] = &["importlib.import_module", "import_module", "__import__"];
+
+/// Emit import symbols + edges for `importlib.import_module("pkg.mod")` / `__import__("pkg")`.
+///
+/// Only literal string arguments are recorded; computed module names are skipped.
+/// The import symbol is parented to the enclosing function/class, if any.
+fn extract_dynamic_imports(
+    node: Node,
+    source: &str,
+    file_path: &str,
+    symbols: &mut Vec<Symbol>,
+    edges: &mut Vec<Edge>,
+) {
+    if node.kind() == "call" {
+        let is_dynamic_import = node
+            .child_by_field_name("function")
+            .is_some_and(|f| DYNAMIC_IMPORT_FUNCS.contains(&node_text(f, source)));
+        if is_dynamic_import {
+            if let Some(module_name) = node
+                .child_by_field_name("arguments")
+                .and_then(|args| args.named_child(0))
+                .and_then(|arg| string_literal_value(arg, source))
+            {
+                let line = node.start_position().row as u32 + 1;
+                let parent_id = enclosing_definition_id(node, source, file_path);
+                let sym_id = symbol_id(file_path, &module_name, line);
+                symbols.push(
+                    Symbol::new(
+                        &module_name,
+                        SymbolKind::Import,
+                        file_path,
+                        line,
+                        line,
+                        node.start_byte() as u32,
+                        node.end_byte() as u32,
+                    )
+                    .with_parent(parent_id.as_deref())
+                    .with_signature(Some(node_text(node, source).to_string())),
+                );
+                edges.push(Edge::new(
+                    sym_id,
+                    module_name,
+                    EdgeKind::Imports,
+                    file_path,
+                    line,
+                ));
+            }
+        }
+    }
+
+    for child in node.named_children(&mut node.walk()) {
+        extract_dynamic_imports(child, source, file_path, symbols, edges);
+    }
+}
+
+/// Apply module-level `__all__` to symbol visibility.
+///
+/// Names listed in `__all__` are public (even `_prefixed` ones); other top-level
+/// public definitions become private since they are not part of the exported API.
+/// Each listed name also gets a references edge from the `__all__` variable so
+/// re-exports in `__init__.py` show up in `refs`.
+fn apply_dunder_all(
+    root: Node,
+    source: &str,
+    file_path: &str,
+    symbols: &mut [Symbol],
+    edges: &mut Vec<Edge>,
+) {
+    let mut exported: Vec<(String, u32)> = Vec::new();
+    let mut found = false;
+    for stmt in root.named_children(&mut root.walk()) {
+        if stmt.kind() != "expression_statement" {
+            continue;
+        }
+        for expr in stmt.named_children(&mut stmt.walk()) {
+            if !matches!(expr.kind(), "assignment" | "augmented_assignment") {
+                continue;
+            }
+            let is_all = expr
+                .child_by_field_name("left")
+                .is_some_and(|l| node_text(l, source) == "__all__");
+            let Some(right) = expr.child_by_field_name("right") else {
+                continue;
+            };
+            if !is_all || !matches!(right.kind(), "list" | "tuple") {
+                continue;
+            }
+            found = true;
+            for item in right.named_children(&mut right.walk()) {
+                if let Some(name) = string_literal_value(item, source) {
+                    exported.push((name, item.start_position().row as u32 + 1));
+                }
+            }
+        }
+    }
+
+    if !found {
+        return;
+    }
+
+    let all_id = symbols
+        .iter()
+        .find(|s| s.name == "__all__" && s.parent_id.is_none())
+        .map(|s| s.id.clone());
+
+    for sym in symbols.iter_mut() {
+        if sym.parent_id.is_some() || sym.kind == SymbolKind::Import || sym.name == "__all__" {
+            continue;
+        }
+        if exported.iter().any(|(name, _)| *name == sym.name) {
+            sym.visibility = Visibility::Public;
+        } else if sym.visibility == Visibility::Public && !sym.name.starts_with("__") {
+            sym.visibility = Visibility::Private;
+        }
+    }
+
+    if let Some(all_id) = all_id {
+        for (name, line) in exported {
+            edges.push(Edge::new(
+                all_id.as_str(),
+                name,
+                EdgeKind::References,
+                file_path,
+                line,
+            ));
+        }
+    }
+}
+
+/// Value of a plain string literal (`"pkg.mod"`), or `None` for f-strings / non-strings.
+fn string_literal_value(node: Node, source: &str) -> Option<String> {
+    if node.kind() != "string" {
+        return None;
+    }
+    let mut value = String::new();
+    for child in node.named_children(&mut node.walk()) {
+        match child.kind() {
+            "string_content" => value.push_str(node_text(child, source)),
+            "interpolation" => return None,
+            _ => {}
+        }
+    }
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Symbol ID of the nearest enclosing function or class definition.
+fn enclosing_definition_id(node: Node, source: &str, file_path: &str) -> Option<String> {
+    let mut current = node.parent();
+    while let Some(n) = current {
+        if matches!(n.kind(), "function_definition" | "class_definition") {
+            let name = node_text(n.child_by_field_name("name")?, source);
+            return Some(symbol_id(
+                file_path,
+                name,
+                n.start_position().row as u32 + 1,
+            ));
+        }
+        current = n.parent();
+    }
+    None
+}
+
 // ── Reference helpers ──
 
 /// Extract type annotation references from function parameters and return type.
@@ -532,29 +723,81 @@ fn extract_decorator_ref(
     edges: &mut Vec<Edge>,
 ) {
     let Some(ctx) = context_id else { return };
+    if let Some(name) = decorator_name(node, source) {
+        edges.push(Edge::new(
+            ctx,
+            name,
+            EdgeKind::References,
+            file_path,
+            node.start_position().row as u32 + 1,
+        ));
+    }
+}
+
+/// Dotted name of a decorator: `@app.route("/")` → `app.route`.
+fn decorator_name(node: Node, source: &str) -> Option<String> {
     // Decorator node children: "@" + expression
     // The expression can be identifier, attribute, or call
-    for child in node.named_children(&mut node.walk()) {
-        let name = match child.kind() {
-            "identifier" | "attribute" => node_text(child, source).to_string(),
+    let name = node
+        .named_children(&mut node.walk())
+        .find_map(|child| match child.kind() {
+            "identifier" | "attribute" => Some(node_text(child, source)),
             "call" => child
                 .child_by_field_name("function")
-                .map(|f| node_text(f, source).to_string())
-                .unwrap_or_default(),
-            _ => continue,
+                .map(|f| node_text(f, source)),
+            _ => None,
+        })?;
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Mark module-level functions called under `if __name__ == "__main__":` as
+/// `script` entry points.
+fn mark_main_guard_calls(root: Node, source: &str, symbols: &mut [Symbol]) {
+    for stmt in root.named_children(&mut root.walk()) {
+        if stmt.kind() != "if_statement" {
+            continue;
+        }
+        let Some(condition) = stmt.child_by_field_name("condition") else {
+            continue;
         };
-        if !name.is_empty() {
-            edges.push(Edge::new(
-                ctx,
-                name,
-                EdgeKind::References,
-                file_path,
-                node.start_position().row as u32 + 1,
-            ));
+        let condition: String = node_text(condition, source)
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| if c == '\'' { '"' } else { c })
+            .collect();
+        if condition != r#"__name__=="__main__""# && condition != r#""__main__"==__name__"# {
+            continue;
+        }
+        let Some(body) = stmt.child_by_field_name("consequence") else {
+            continue;
+        };
+        let mut called = Vec::new();
+        collect_called_names(body, source, &mut called);
+        for sym in symbols.iter_mut() {
+            if sym.parent_id.is_none()
+                && sym.kind == SymbolKind::Function
+                && sym.entrypoint.is_none()
+                && called.contains(&sym.name.as_str())
+            {
+                sym.entrypoint = Some("script".to_string());
+            }
         }
     }
 }
 
+fn collect_called_names<'a>(node: Node, source: &'a str, names: &mut Vec<&'a str>) {
+    if node.kind() == "call" {
+        if let Some(f) = node.child_by_field_name("function") {
+            if f.kind() == "identifier" {
+                names.push(node_text(f, source));
+            }
+        }
+    }
+    for child in node.named_children(&mut node.walk()) {
+        collect_called_names(child, source, names);
+    }
+}
+
 // ── Helpers ──
 
 fn node_text_slice(source: &str, start: usize, end: usize) -> &str {
@@ -613,6 +856,21 @@ fn extract_docstring(node: Node, source: &str) -> Option<String> {
     }
 }
 
+/// `import numpy as np` (np → numpy) and `from x import A as B` (B → A), at any depth.
+fn extract_aliases(root: Node, source: &str, file_path: &str) -> Vec<ImportAlias> {
+    descendants_of_kind(root, &["aliased_import"])
+        .into_iter()
+        .filter_map(|node| {
+            Some(ImportAlias {
+                alias: node_text(node.child_by_field_name("alias")?, source).to_string(),
+                name: node_text(node.child_by_field_name("name")?, source).to_string(),
+                file_path: file_path.to_string(),
+                line: node.start_position().row as u32 + 1,
+            })
+        })
+        .collect()
+}
+
 fn extract_import_module(node: Node, source: &str) -> String {
     match node.kind() {
         "import_statement" => {
@@ -932,19 +1190,139 @@ __private_lock = None
             r#"
 import numpy as np
 from collections import OrderedDict as ODict
+
+def build():
+    import os.path as osp
+    return ODict(np.zeros(3), osp.join("a", "b"))
 "#,
         );
 
         let imports: Vec<_> = result
             .symbols
             .iter()
-            .filter(|s| s.kind == SymbolKind::Import)
+            .filter(|s| s.kind == SymbolKind::Import && s.parent_id.is_none())
             .collect();
         assert_eq!(imports.len(), 2);
 
         let names: Vec<&str> = imports.iter().map(|s| s.name.as_str()).collect();
         assert!(names.contains(&"numpy"));
         assert!(names.contains(&"collections"));
+
+        let aliases: Vec<(&str, &str)> = result
+            .aliases
+            .iter()
+            .map(|a| (a.alias.as_str(), a.name.as_str()))
+            .collect();
+        assert_eq!(
+            aliases,
+            [
+                ("np", "numpy"),
+                ("ODict", "OrderedDict"),
+                ("osp", "os.path")
+            ]
+        );
+        // Calls through an alias target the imported name.
+        let calls: Vec<&str> = result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Calls)
+            .map(|e| e.target_name.as_str())
+            .collect();
+        assert_eq!(calls, ["OrderedDict", "numpy.zeros", "os.path.join"]);
+    }
+
+    #[test]
+    fn test_dunder_all_controls_visibility() {
+        let result = extract(
+            r#"
+__all__ = ["load", "_compat"]
+
+def load():
+    pass
+
+def helper():
+    pass
+
+def _compat():
+    pass
+
+class Loader:
+    def run(self):
+        pass
+"#,
+        );
+
+        let vis = |name: &str| {
+            result
+                .symbols
+                .iter()
+                .find(|s| s.name == name)
+                .unwrap()
+                .visibility
+        };
+        assert_eq!(vis("load"), Visibility::Public);
+        assert_eq!(vis("_compat"), Visibility::Public);
+        assert_eq!(vis("helper"), Visibility::Private);
+        assert_eq!(vis("Loader"), Visibility::Private);
+        // Methods are untouched — __all__ only governs module-level names
+        assert_eq!(vis("run"), Visibility::Public);
+
+        let exported: Vec<&str> = result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::References && e.source_id.contains("__all__"))
+            .map(|e| e.target_name.as_str())
+            .collect();
+        assert_eq!(exported, vec!["load", "_compat"]);
+    }
+
+    #[test]
+    fn test_no_dunder_all_keeps_default_visibility() {
+        let result = extract("def helper():\n    pass\n");
+        let helper = result.symbols.iter().find(|s| s.name == "helper").unwrap();
+        assert_eq!(helper.visibility, Visibility::Public);
+    }
+
+    #[test]
+    fn test_dynamic_imports() {
+        let result = extract(
+            r#"
+import importlib
+
+def load_plugin(name):
+    mod = importlib.import_module("plugins.auth")
+    legacy = __import__("legacy_mod")
+    computed = importlib.import_module(name)
+    return mod
+"#,
+        );
+
+        let imports: Vec<&str> = result
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Import)
+            .map(|s| s.name.as_str())
+            .collect();
+        assert!(imports.contains(&"plugins.auth"));
+        assert!(imports.contains(&"legacy_mod"));
+        assert_eq!(imports.len(), 3); // importlib + 2 literal dynamic imports
+
+        let dynamic = result
+            .symbols
+            .iter()
+            .find(|s| s.name == "plugins.auth")
+            .unwrap();
+        assert!(dynamic
+            .parent_id
+            .as_deref()
+            .unwrap()
+            .contains("load_plugin"));
+
+        let edge = result
+            .edges
+            .iter()
+            .find(|e| e.kind == EdgeKind::Imports && e.target_name == "plugins.auth");
+        assert!(edge.is_some());
     }
 
     #[test]
@@ -1008,6 +1386,52 @@ def endpoint():
         assert!(targets.contains(&"app.route"));
     }
 
+    #[test]
+    fn test_entrypoints() {
+        let result = extract(
+            r#"
+@app.route("/api")
+def endpoint():
+    pass
+
+@click.command()
+def cli():
+    main()
+
+@shared_task
+def cleanup():
+    pass
+
+@login_required
+def protected():
+    pass
+
+def main():
+    pass
+
+def helper():
+    pass
+
+if __name__ == '__main__':
+    main()
+"#,
+        );
+
+        let entry = |name: &str| {
+            result
+                .symbols
+                .iter()
+                .find(|s| s.name == name)
+                .and_then(|s| s.entrypoint.as_deref())
+        };
+        assert_eq!(entry("endpoint"), Some("route"));
+        assert_eq!(entry("cli"), Some("cli"));
+        assert_eq!(entry("cleanup"), Some("task"));
+        assert_eq!(entry("main"), Some("script"));
+        assert_eq!(entry("protected"), None);
+        assert_eq!(entry("helper"), None);
+    }
+
     #[test]
     fn test_except_clause_refs() {
         let result = extract(
@@ -3,7 +3,7 @@ use tree_sitter::{Language, Node, Parser};
 
 use crate::types::{symbol_id, Edge, EdgeKind, Symbol, SymbolKind, Visibility};
 
-use super::{node_text, ExtractionResult, Extractor};
+use super::{is_conditional, node_text, ExtractionResult, Extractor};
 
 /// Extracts symbols and edges from Ruby source files.
 pub struct RubyExtractor {
@@ -45,7 +45,7 @@ impl Extractor for RubyExtractor {
             &mut edges,
         );
 
-        Ok(ExtractionResult { symbols, edges })
+        Ok(ExtractionResult::new(symbols, edges, Vec::new()))
     }
 }
 
@@ -500,13 +500,16 @@ fn walk_for_calls_and_raises(
                             } else {
                                 method_name.to_string()
                             };
-                            edges.push(Edge::new(
-                                context_id,
-                                callee,
-                                EdgeKind::Calls,
-                                file_path,
-                                current.start_position().row as u32 + 1,
-                            ));
+                            edges.push(
+                                Edge::new(
+                                    context_id,
+                                    callee,
+                                    EdgeKind::Calls,
+                                    file_path,
+                                    current.start_position().row as u32 + 1,
+                                )
+                                .with_conditional(is_conditional(current, node, source)),
+                            );
                         }
                         _ => {}
                     }
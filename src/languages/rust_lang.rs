@@ -1,9 +1,12 @@
 use anyhow::Result;
 use tree_sitter::{Language, Node, Parser};
 
-use crate::types::{symbol_id, Edge, EdgeKind, Symbol, SymbolKind, Visibility};
+use crate::types::{symbol_id, Edge, EdgeKind, ImportAlias, Symbol, SymbolKind, Visibility};
 
-use super::{node_text, ExtractionResult, Extractor};
+use super::{
+    decorator_entrypoint, descendants_of_kind, is_conditional, node_text, ExtractionResult,
+    Extractor,
+};
 
 pub struct RustExtractor {
     parser: Parser,
@@ -43,8 +46,9 @@ impl Extractor for RustExtractor {
             &mut symbols,
             &mut edges,
         );
+        let aliases = extract_aliases(tree.root_node(), source, file_path);
 
-        Ok(ExtractionResult { symbols, edges })
+        Ok(ExtractionResult::new(symbols, edges, aliases))
     }
 }
 
@@ -61,10 +65,10 @@ fn extract_node(
             extract_function(node, source, file_path, parent_id, symbols, edges);
         }
         "struct_item" => {
-            extract_struct(node, source, file_path, parent_id, symbols);
+            extract_struct(node, source, file_path, parent_id, symbols, edges);
         }
         "enum_item" => {
-            extract_enum(node, source, file_path, parent_id, symbols);
+            extract_enum(node, source, file_path, parent_id, symbols, edges);
         }
         "trait_item" => {
             extract_trait(node, source, file_path, parent_id, symbols);
@@ -84,6 +88,12 @@ fn extract_node(
         "type_item" => {
             extract_type_alias(node, source, file_path, parent_id, symbols);
         }
+        "macro_definition" => {
+            extract_macro_definition(node, source, file_path, parent_id, symbols);
+        }
+        "macro_invocation" => {
+            extract_static_macro(node, source, file_path, parent_id, symbols, edges);
+        }
         "attribute_item" | "inner_attribute_item" => {
             // Skip attributes, but process the next sibling
         }
@@ -123,6 +133,11 @@ fn extract_function(
     let is_async = has_child_kind(node, "async");
     let signature = extract_fn_signature(node, source);
     let docstring = extract_doc_comment(node, source);
+    let attributes = attribute_paths(node, source);
+    let entrypoint = attributes
+        .iter()
+        .find_map(|attr| decorator_entrypoint(attr))
+        .or_else(|| (name == "main" && !is_method).then_some("main"));
 
     let sym_id = symbol_id(file_path, &name, start_line);
     symbols.push(
@@ -139,15 +154,49 @@ fn extract_function(
         .with_signature(signature)
         .with_visibility(visibility)
         .with_async(is_async)
-        .with_docstring(docstring),
+        .with_docstring(docstring)
+        .with_entrypoint(entrypoint),
     );
 
+    // Well-known attribute macros that rewrite the function (e.g. #[tokio::main])
+    for attr in attributes {
+        if FN_ATTRIBUTE_MACROS.contains(&attr.as_str()) {
+            edges.push(Edge::new(
+                sym_id.clone(),
+                attr,
+                EdgeKind::References,
+                file_path,
+                start_line,
+            ));
+        }
+    }
+
     // Extract type references from parameter and return types
     extract_fn_type_refs(node, source, file_path, &sym_id, edges);
 
     // Walk body for calls
     if let Some(body) = node.child_by_field_name("body") {
+        let first_edge = edges.len();
         walk_for_calls(body, source, file_path, &sym_id, edges);
+
+        // `?` converts any propagated error into the declared `Result<_, E>` error type
+        let declared = node
+            .child_by_field_name("return_type")
+            .and_then(|ret| result_error_type(ret, source));
+        if let Some(error_type) = declared {
+            let already_raised = edges[first_edge..]
+                .iter()
+                .any(|e| e.kind == EdgeKind::Raises && e.target_name == error_type);
+            if !already_raised && uses_try_operator(body) {
+                edges.push(Edge::new(
+                    sym_id.clone(),
+                    error_type,
+                    EdgeKind::Raises,
+                    file_path,
+                    start_line,
+                ));
+            }
+        }
     }
 }
 
@@ -159,6 +208,7 @@ fn extract_struct(
     file_path: &str,
     parent_id: Option<&str>,
     symbols: &mut Vec<Symbol>,
+    edges: &mut Vec<Edge>,
 ) {
     let name = match node.child_by_field_name("name") {
         Some(n) => node_text(n, source).to_string(),
@@ -168,10 +218,11 @@ fn extract_struct(
     let start_line = node.start_position().row as u32 + 1;
     let visibility = rust_visibility(node, source);
     let docstring = extract_doc_comment(node, source);
+    let sym_id = symbol_id(file_path, &name, start_line);
 
     symbols.push(
         Symbol::new(
-            name,
+            name.clone(),
             SymbolKind::Class,
             file_path,
             start_line,
@@ -183,6 +234,26 @@ fn extract_struct(
         .with_visibility(visibility)
         .with_docstring(docstring),
     );
+
+    let derives = extract_derives(node, source);
+    emit_derive_refs(&derives, &sym_id, file_path, start_line, edges);
+
+    // derive_builder generates a `{Name}Builder` type next to the struct
+    if derives.iter().any(|d| d == "Builder") {
+        symbols.push(
+            Symbol::new(
+                format!("{name}Builder"),
+                SymbolKind::Class,
+                file_path,
+                start_line,
+                node.end_position().row as u32 + 1,
+                node.start_byte() as u32,
+                node.end_byte() as u32,
+            )
+            .with_parent(parent_id)
+            .with_visibility(visibility),
+        );
+    }
 }
 
 // ── Enums ──
@@ -193,6 +264,7 @@ fn extract_enum(
     file_path: &str,
     parent_id: Option<&str>,
     symbols: &mut Vec<Symbol>,
+    edges: &mut Vec<Edge>,
 ) {
     let name = match node.child_by_field_name("name") {
         Some(n) => node_text(n, source).to_string(),
@@ -202,6 +274,7 @@ fn extract_enum(
     let start_line = node.start_position().row as u32 + 1;
     let visibility = rust_visibility(node, source);
     let docstring = extract_doc_comment(node, source);
+    let sym_id = symbol_id(file_path, &name, start_line);
 
     symbols.push(
         Symbol::new(
@@ -217,6 +290,38 @@ fn extract_enum(
         .with_visibility(visibility)
         .with_docstring(docstring),
     );
+
+    let derives = extract_derives(node, source);
+    emit_derive_refs(&derives, &sym_id, file_path, start_line, edges);
+
+    // thiserror enums: variants are the error values callers construct and match on,
+    // so expose them as symbols (e.g. `AppError::NotFound` → `NotFound`).
+    if derives.iter().any(|d| d == "Error") {
+        if let Some(body) = node.child_by_field_name("body") {
+            for variant in body.named_children(&mut body.walk()) {
+                if variant.kind() != "enum_variant" {
+                    continue;
+                }
+                let Some(variant_name) = variant.child_by_field_name("name") else {
+                    continue;
+                };
+                symbols.push(
+                    Symbol::new(
+                        node_text(variant_name, source),
+                        SymbolKind::Variable,
+                        file_path,
+                        variant.start_position().row as u32 + 1,
+                        variant.end_position().row as u32 + 1,
+                        variant.start_byte() as u32,
+                        variant.end_byte() as u32,
+                    )
+                    .with_parent(Some(&sym_id))
+                    .with_visibility(visibility)
+                    .with_docstring(extract_doc_comment(variant, source)),
+                );
+            }
+        }
+    }
 }
 
 // ── Traits ──
@@ -455,6 +560,23 @@ fn collect_use_names_recursive(node: Node, source: &str, names: &mut Vec<String>
     }
 }
 
+/// `use foo::Bar as Baz` (Baz → Bar), at any depth. `as _` imports stay unnamed.
+fn extract_aliases(root: Node, source: &str, file_path: &str) -> Vec<ImportAlias> {
+    descendants_of_kind(root, &["use_as_clause"])
+        .into_iter()
+        .filter_map(|node| {
+            let alias = node_text(node.child_by_field_name("alias")?, source);
+            let name = last_path_segment(node.child_by_field_name("path")?, source);
+            (alias != "_").then(|| ImportAlias {
+                alias: alias.to_string(),
+                name,
+                file_path: file_path.to_string(),
+                line: node.start_position().row as u32 + 1,
+            })
+        })
+        .collect()
+}
+
 fn last_path_segment(node: Node, source: &str) -> String {
     let text = node_text(node, source);
     text.rsplit("::").next().unwrap_or(text).to_string()
@@ -575,6 +697,213 @@ fn extract_type_alias(
     );
 }
 
+// ── Macros ──
+
+/// Attribute macros that wrap or rewrite the annotated function.
+/// Recorded as references so entry points like `#[tokio::main]` stay visible.
+const FN_ATTRIBUTE_MACROS: &[&str] = &[
+    "tokio::main",
+    "tokio::test",
+    "async_std::main",
+    "async_std::test",
+    "actix_web::main",
+    "actix_rt::main",
+];
+
+/// Function-like macros whose body declares statics (`static ref FOO: T = ...;`).
+const STATIC_DECLARING_MACROS: &[&str] = &["lazy_static", "thread_local"];
+
+/// `macro_rules! name { ... }` → a function symbol named `name!`.
+///
+/// Macro invocations are recorded as call edges to `name!`, so naming the
+/// definition the same way lets those edges resolve.
+fn extract_macro_definition(
+    node: Node,
+    source: &str,
+    file_path: &str,
+    parent_id: Option<&str>,
+    symbols: &mut Vec<Symbol>,
+) {
+    let name = match node.child_by_field_name("name") {
+        Some(n) => node_text(n, source).to_string(),
+        None => return,
+    };
+
+    let visibility = if preceding_attributes(node)
+        .iter()
+        .any(|a| node_text(*a, source).contains("macro_export"))
+    {
+        Visibility::Public
+    } else {
+        Visibility::Private
+    };
+
+    symbols.push(
+        Symbol::new(
+            format!("{name}!"),
+            SymbolKind::Function,
+            file_path,
+            node.start_position().row as u32 + 1,
+            node.end_position().row as u32 + 1,
+            node.start_byte() as u32,
+            node.end_byte() as u32,
+        )
+        .with_parent(parent_id)
+        .with_visibility(visibility)
+        .with_docstring(extract_doc_comment(node, source)),
+    );
+}
+
+/// Item-level `lazy_static! { static ref FOO: T = ...; }` / `thread_local!` → variable symbols.
+fn extract_static_macro(
+    node: Node,
+    source: &str,
+    file_path: &str,
+    parent_id: Option<&str>,
+    symbols: &mut Vec<Symbol>,
+    edges: &mut Vec<Edge>,
+) {
+    let macro_name = node
+        .child_by_field_name("macro")
+        .map(|m| last_path_segment(m, source))
+        .unwrap_or_default();
+    if !STATIC_DECLARING_MACROS.contains(&macro_name.as_str()) {
+        return;
+    }
+
+    let Some(tokens) = node
+        .named_children(&mut node.walk())
+        .find(|c| c.kind() == "token_tree")
+    else {
+        return;
+    };
+
+    // Token trees are flat: scan for `static [ref] <identifier>`.
+    let children: Vec<Node> = tokens.children(&mut tokens.walk()).collect();
+    let mut i = 0;
+    while i < children.len() {
+        if node_text(children[i], source) != "static" {
+            i += 1;
+            continue;
+        }
+        let mut j = i + 1;
+        if children
+            .get(j)
+            .is_some_and(|c| node_text(*c, source) == "ref")
+        {
+            j += 1;
+        }
+        if let Some(ident) = children.get(j).filter(|c| c.kind() == "identifier") {
+            let line = ident.start_position().row as u32 + 1;
+            let name = node_text(*ident, source).to_string();
+            let sym_id = symbol_id(file_path, &name, line);
+            symbols.push(
+                Symbol::new(
+                    name,
+                    SymbolKind::Variable,
+                    file_path,
+                    line,
+                    line,
+                    ident.start_byte() as u32,
+                    ident.end_byte() as u32,
+                )
+                .with_parent(parent_id)
+                .with_visibility(
+                    if i > 0 && node_text(children[i - 1], source) == "pub" {
+                        Visibility::Public
+                    } else {
+                        Visibility::Private
+                    },
+                ),
+            );
+            edges.push(Edge::new(
+                sym_id,
+                format!("{macro_name}!"),
+                EdgeKind::References,
+                file_path,
+                line,
+            ));
+        }
+        i = j + 1;
+    }
+}
+
+/// Collect the derive names from `#[derive(...)]` attributes preceding an item.
+///
+/// Paths are reduced to their last segment: `serde::Serialize` → `Serialize`.
+fn extract_derives(node: Node, source: &str) -> Vec<String> {
+    let mut derives = Vec::new();
+    for attr in preceding_attributes(node) {
+        let text = node_text(attr, source);
+        let inner = text.trim_start_matches("#[").trim_end_matches(']').trim();
+        let Some(args) = inner
+            .strip_prefix("derive")
+            .map(str::trim_start)
+            .and_then(|a| a.strip_prefix('('))
+            .and_then(|a| a.strip_suffix(')'))
+        else {
+            continue;
+        };
+        for name in args.split(',') {
+            let name = name.trim();
+            let name = name.rsplit("::").next().unwrap_or(name).trim();
+            if !name.is_empty() {
+                derives.push(name.to_string());
+            }
+        }
+    }
+    derives
+}
+
+/// Record each derive macro as a reference from the deriving type.
+fn emit_derive_refs(
+    derives: &[String],
+    sym_id: &str,
+    file_path: &str,
+    line: u32,
+    edges: &mut Vec<Edge>,
+) {
+    for derive in derives {
+        edges.push(Edge::new(
+            sym_id,
+            derive.clone(),
+            EdgeKind::References,
+            file_path,
+            line,
+        ));
+    }
+}
+
+/// Paths of the attributes preceding an item: `#[tokio::main]` → `tokio::main`.
+fn attribute_paths(node: Node, source: &str) -> Vec<String> {
+    preceding_attributes(node)
+        .into_iter()
+        .filter_map(|attr| {
+            let attribute = attr
+                .named_children(&mut attr.walk())
+                .find(|c| c.kind() == "attribute")?;
+            let path = attribute.named_child(0)?;
+            Some(node_text(path, source).to_string())
+        })
+        .collect()
+}
+
+/// `attribute_item` siblings directly preceding an item (doc comments are skipped).
+fn preceding_attributes(node: Node) -> Vec<Node> {
+    let mut attrs = Vec::new();
+    let mut prev = node.prev_sibling();
+    while let Some(p) = prev {
+        match p.kind() {
+            "attribute_item" => attrs.push(p),
+            "line_comment" | "block_comment" => {}
+            _ => break,
+        }
+        prev = p.prev_sibling();
+    }
+    attrs.reverse();
+    attrs
+}
+
 // ── Call walking ──
 
 fn walk_for_calls(
@@ -596,10 +925,22 @@ fn walk_for_calls(
                     if let Some(func) = current.child_by_field_name("function") {
                         let callee_name = node_text(func, source);
                         if !callee_name.is_empty() {
+                            edges.push(
+                                Edge::new(
+                                    context_id.to_string(),
+                                    callee_name.to_string(),
+                                    EdgeKind::Calls,
+                                    file_path,
+                                    current.start_position().row as u32 + 1,
+                                )
+                                .with_conditional(is_conditional(current, node, source)),
+                            );
+                        }
+                        if let Some(error_type) = raised_error_type(current, func, source) {
                             edges.push(Edge::new(
                                 context_id.to_string(),
-                                callee_name.to_string(),
-                                EdgeKind::Calls,
+                                error_type,
+                                EdgeKind::Raises,
                                 file_path,
                                 current.start_position().row as u32 + 1,
                             ));
@@ -611,13 +952,16 @@ fn walk_for_calls(
                     if let Some(mac) = current.child_by_field_name("macro") {
                         let macro_name = node_text(mac, source);
                         if !macro_name.is_empty() {
-                            edges.push(Edge::new(
-                                context_id.to_string(),
-                                format!("{macro_name}!"),
-                                EdgeKind::Calls,
-                                file_path,
-                                current.start_position().row as u32 + 1,
-                            ));
+                            edges.push(
+                                Edge::new(
+                                    context_id.to_string(),
+                                    format!("{macro_name}!"),
+                                    EdgeKind::Calls,
+                                    file_path,
+                                    current.start_position().row as u32 + 1,
+                                )
+                                .with_conditional(is_conditional(current, node, source)),
+                            );
                         }
                     }
                 }
@@ -649,6 +993,82 @@ fn walk_for_calls(
     }
 }
 
+// ── Error propagation ──
+
+/// Error type constructed by `Err(..)`, `.map_err(..)`, `.ok_or(..)` or `.ok_or_else(..)`.
+fn raised_error_type(call: Node, func: Node, source: &str) -> Option<String> {
+    let is_error_site = match func.kind() {
+        "identifier" => node_text(func, source) == "Err",
+        "field_expression" => func
+            .child_by_field_name("field")
+            .is_some_and(|f| matches!(node_text(f, source), "map_err" | "ok_or" | "ok_or_else")),
+        _ => false,
+    };
+    if !is_error_site {
+        return None;
+    }
+    let args = call.child_by_field_name("arguments")?;
+    let arg = args.named_child(0)?;
+    error_type_of_expr(arg, source)
+}
+
+/// Type named by an error value expression: `FooError::NotFound`, `FooError::Io(e)`,
+/// `FooError::new(..)`, `FooError { .. }`, `|e| FooError::Io(e)`. `None` for values
+/// whose type is not spelled out (`e`, `e.into()`, `anyhow!(..)`).
+fn error_type_of_expr(expr: Node, source: &str) -> Option<String> {
+    match expr.kind() {
+        "closure_expression" => error_type_of_expr(expr.child_by_field_name("body")?, source),
+        "call_expression" => {
+            error_type_of_path(node_text(expr.child_by_field_name("function")?, source))
+        }
+        "struct_expression" => {
+            error_type_of_path(node_text(expr.child_by_field_name("name")?, source))
+        }
+        "scoped_identifier" | "identifier" => error_type_of_path(node_text(expr, source)),
+        _ => None,
+    }
+}
+
+/// `FooError::Variant` and `FooError::new` → `FooError`; `mod::FooError` → `FooError`.
+fn error_type_of_path(path: &str) -> Option<String> {
+    let is_type = |s: &str| s.starts_with(|c: char| c.is_ascii_uppercase()) && s != "Self";
+    let segments: Vec<&str> = path.split("::").map(str::trim).collect();
+    let (last, init) = segments.split_last()?;
+    match init.last() {
+        Some(parent) if is_type(parent) => Some(parent.to_string()),
+        _ => is_type(last).then(|| last.to_string()),
+    }
+}
+
+/// Error type `E` of a `Result<T, E>` return type, when `E` is a named type.
+fn result_error_type(return_type: Node, source: &str) -> Option<String> {
+    if return_type.kind() != "generic_type" {
+        return None;
+    }
+    let base = return_type.child_by_field_name("type")?;
+    if node_text(base, source) != "Result" {
+        return None;
+    }
+    let args = return_type.child_by_field_name("type_arguments")?;
+    let error = args.named_children(&mut args.walk()).nth(1)?;
+    match error.kind() {
+        "type_identifier" | "scoped_type_identifier" => {
+            error_type_of_path(node_text(error, source))
+        }
+        _ => None,
+    }
+}
+
+/// Whether `node` contains a `?` outside nested functions and closures.
+fn uses_try_operator(node: Node) -> bool {
+    node.named_children(&mut node.walk())
+        .any(|child| match child.kind() {
+            "try_expression" => true,
+            "function_item" | "closure_expression" | "impl_item" | "mod_item" => false,
+            _ => uses_try_operator(child),
+        })
+}
+
 // ── Type reference extraction ──
 
 /// Extract type references from function parameter types and return type.
@@ -995,6 +1415,53 @@ fn process() {
         assert!(targets.contains(&"save"));
     }
 
+    #[test]
+    fn test_error_construction_is_raises() {
+        let result = extract(
+            r#"
+fn parse(input: &str) -> Result<Ast, ParseError> {
+    if input.is_empty() {
+        return Err(ParseError::Empty);
+    }
+    let n = input.parse::<u32>().map_err(|e| ParseError::Number(e))?;
+    let cfg = lookup(n).ok_or(ConfigError::new("missing"))?;
+    Err(anyhow!("untyped"))
+}
+
+fn load(path: &str) -> Result<String, io::Error> {
+    let text = std::fs::read_to_string(path)?;
+    Ok(text)
+}
+
+fn passthrough(r: Result<(), Error>) -> Result<(), Error> {
+    r.map_err(|e| e)
+}
+"#,
+        );
+
+        let raises: Vec<(&str, &str)> = result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Raises)
+            .map(|e| {
+                (
+                    e.source_id.split(':').nth(1).unwrap(),
+                    e.target_name.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            raises,
+            vec![
+                ("parse", "ParseError"),
+                ("parse", "ParseError"),
+                ("parse", "ConfigError"),
+                // `?` propagation into the declared error type
+                ("load", "Error"),
+            ]
+        );
+    }
+
     #[test]
     fn test_macro_calls() {
         let result = extract(
@@ -1222,6 +1689,34 @@ pub mod auth {
         // `use_as_clause` collects the original name from the path
         assert_eq!(edges.len(), 1);
         assert_eq!(edges[0].target_name, "HashMap");
+        assert_eq!(result.aliases.len(), 1);
+        assert_eq!(
+            (
+                result.aliases[0].alias.as_str(),
+                result.aliases[0].name.as_str()
+            ),
+            ("Map", "HashMap")
+        );
+    }
+
+    #[test]
+    fn test_aliased_calls_target_imported_name() {
+        let result = extract(
+            r#"
+use crate::auth::Service as Auth;
+use std::fmt::Write as _;
+
+fn run() {
+    let auth = Auth::new();
+    auth.login();
+}
+"#,
+        );
+        assert_eq!(result.aliases.len(), 1);
+        assert!(result
+            .edges
+            .iter()
+            .any(|e| e.kind == EdgeKind::Calls && e.target_name == "Service::new"));
     }
 
     #[test]
@@ -1245,6 +1740,193 @@ fn connect(cfg: &crate::Config) -> io::Result<Connection> {
         assert!(refs.contains(&"Connection"));
     }
 
+    #[test]
+    fn test_derive_macros_recorded_as_references() {
+        let result = extract(
+            r#"
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Config {
+    name: String,
+}
+"#,
+        );
+
+        let config = result.symbols.iter().find(|s| s.name == "Config").unwrap();
+        let derives: Vec<&str> = result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::References && e.source_id == config.id)
+            .map(|e| e.target_name.as_str())
+            .collect();
+        assert_eq!(derives, vec!["Debug", "Clone", "Serialize"]);
+    }
+
+    #[test]
+    fn test_derive_builder_emits_builder_type() {
+        let result = extract(
+            r#"
+#[derive(Builder)]
+pub struct Request {
+    url: String,
+}
+"#,
+        );
+
+        let builder = result
+            .symbols
+            .iter()
+            .find(|s| s.name == "RequestBuilder")
+            .unwrap();
+        assert_eq!(builder.kind, SymbolKind::Class);
+        assert_eq!(builder.visibility, Visibility::Public);
+    }
+
+    #[test]
+    fn test_thiserror_variants_are_symbols() {
+        let result = extract(
+            r#"
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("not found")]
+    NotFound,
+    #[error("io: {0}")]
+    Io(std::io::Error),
+}
+
+pub enum Plain {
+    A,
+}
+"#,
+        );
+
+        let app_error = result
+            .symbols
+            .iter()
+            .find(|s| s.name == "AppError")
+            .unwrap();
+        let not_found = result
+            .symbols
+            .iter()
+            .find(|s| s.name == "NotFound")
+            .unwrap();
+        assert_eq!(not_found.kind, SymbolKind::Variable);
+        assert_eq!(not_found.parent_id.as_deref(), Some(app_error.id.as_str()));
+        assert!(result.symbols.iter().any(|s| s.name == "Io"));
+
+        // Enums without derive(Error) keep their variants out of the index
+        assert!(!result.symbols.iter().any(|s| s.name == "A"));
+    }
+
+    #[test]
+    fn test_macro_rules_definition_resolves_macro_calls() {
+        let result = extract(
+            r#"
+#[macro_export]
+macro_rules! ensure_ok {
+    ($e:expr) => { $e.unwrap() };
+}
+
+fn run() {
+    ensure_ok!(work());
+}
+"#,
+        );
+
+        let mac = result
+            .symbols
+            .iter()
+            .find(|s| s.name == "ensure_ok!")
+            .unwrap();
+        assert_eq!(mac.kind, SymbolKind::Function);
+        assert_eq!(mac.visibility, Visibility::Public);
+
+        let call = result
+            .edges
+            .iter()
+            .find(|e| e.kind == EdgeKind::Calls && e.target_name == "ensure_ok!");
+        assert!(call.is_some());
+    }
+
+    #[test]
+    fn test_lazy_static_declares_variables() {
+        let result = extract(
+            r#"
+lazy_static! {
+    pub static ref REGISTRY: Registry = Registry::new();
+    static ref CACHE: Cache = Cache::default();
+}
+"#,
+        );
+
+        let registry = result
+            .symbols
+            .iter()
+            .find(|s| s.name == "REGISTRY")
+            .unwrap();
+        assert_eq!(registry.kind, SymbolKind::Variable);
+        assert_eq!(registry.visibility, Visibility::Public);
+
+        let cache = result.symbols.iter().find(|s| s.name == "CACHE").unwrap();
+        assert_eq!(cache.visibility, Visibility::Private);
+    }
+
+    #[test]
+    fn test_tokio_main_attribute_recorded() {
+        let result = extract(
+            r#"
+#[tokio::main]
+async fn main() {
+    serve().await;
+}
+"#,
+        );
+
+        let refs: Vec<&str> = result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::References)
+            .map(|e| e.target_name.as_str())
+            .collect();
+        assert!(refs.contains(&"tokio::main"));
+    }
+
+    #[test]
+    fn test_entrypoints() {
+        let result = extract(
+            r#"
+#[tokio::main]
+async fn main() {}
+
+#[get("/users/<id>")]
+fn user(id: u32) -> String { id.to_string() }
+
+#[tokio::test]
+async fn it_works() {}
+
+struct Server;
+impl Server {
+    fn main(&self) {}
+}
+"#,
+        );
+
+        let entries: Vec<(&str, Option<&str>)> = result
+            .symbols
+            .iter()
+            .filter(|s| matches!(s.kind, SymbolKind::Function | SymbolKind::Method))
+            .map(|s| (s.name.as_str(), s.entrypoint.as_deref()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("main", Some("main")),
+                ("user", Some("route")),
+                ("it_works", None),
+                ("main", None),
+            ]
+        );
+    }
+
     #[test]
     fn test_empty_file() {
         let result = extract("");
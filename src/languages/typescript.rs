@@ -204,6 +204,62 @@ import fs from 'fs';
         assert!(import_edges.len() >= 3); // Router, Request, Response, path, fs
     }
 
+    #[test]
+    fn test_aliased_import_specifiers() {
+        let result = extract_ts(
+            r#"
+import { validateToken as check, Session } from './auth';
+
+function guard(token: string): Session {
+    return check(token);
+}
+"#,
+        );
+
+        assert_eq!(result.aliases.len(), 1);
+        assert_eq!(result.aliases[0].alias, "check");
+        assert_eq!(result.aliases[0].name, "validateToken");
+        let calls: Vec<&str> = result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Calls)
+            .map(|e| e.target_name.as_str())
+            .collect();
+        assert_eq!(calls, ["validateToken"]);
+    }
+
+    #[test]
+    fn test_reexports_and_require() {
+        let result = extract_ts(
+            r#"
+export * from './models';
+export * as helpers from './helpers';
+export { UserService } from './services/user';
+const { config } = require('./config');
+"#,
+        );
+
+        let imports: Vec<&str> = result
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Import)
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(
+            imports,
+            vec!["./models", "./helpers", "./services/user", "./config"]
+        );
+
+        let imported: Vec<&str> = result
+            .edges
+            .iter()
+            .filter(|e| e.kind == EdgeKind::Imports)
+            .map(|e| e.target_name.as_str())
+            .collect();
+        assert!(imported.contains(&"UserService"));
+        assert!(imported.contains(&"config"));
+    }
+
     #[test]
     fn test_function_calls() {
         let result = extract_ts(
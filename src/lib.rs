@@ -1,6 +1,40 @@
+pub mod anchors;
+pub mod callpath;
+pub mod config;
+pub mod context;
+pub mod cycles;
 pub mod db;
+pub mod doctor;
+pub mod export;
+pub mod fingerprint;
+pub mod health;
+pub mod hooks;
+pub mod ignore;
+pub mod impact;
 pub mod indexer;
+pub mod indexes;
+pub mod injections;
+pub mod jobs;
 pub mod languages;
+pub mod orm;
+pub mod overloads;
+pub mod owners;
+pub mod packages;
 pub mod rag;
+pub mod reach;
+pub mod renames;
+pub mod selftest;
+pub mod setops;
+pub mod shard;
+#[cfg(feature = "watch")]
+pub mod simulate;
+pub mod skeleton;
+pub mod snapshot;
+pub mod source;
+pub mod sync;
+pub mod testmap;
+pub mod tokens;
+pub mod training;
 pub mod types;
+#[cfg(feature = "watch")]
 pub mod watch;
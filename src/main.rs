@@ -1,25 +1,68 @@
+mod api;
 mod cli;
 mod commands;
+#[cfg(feature = "mcp")]
 mod mcp;
+#[cfg(feature = "mcp")]
+mod metrics;
+#[cfg(feature = "mcp")]
+mod policy;
+mod repl;
+mod schema;
+#[cfg(feature = "tui")]
+mod tui;
+mod viz;
 
 // Re-export lib modules as crate-level so commands/cli/mcp can use crate::db, etc.
+pub use cartog::callpath;
+pub use cartog::config;
+pub use cartog::context;
+pub use cartog::cycles;
 pub use cartog::db;
+pub use cartog::doctor;
+pub use cartog::export;
+pub use cartog::fingerprint;
+pub use cartog::health;
+pub use cartog::hooks;
+pub use cartog::ignore;
+pub use cartog::impact;
 pub use cartog::indexer;
+pub use cartog::indexes;
+pub use cartog::injections;
+pub use cartog::jobs;
 pub use cartog::languages;
+pub use cartog::orm;
+pub use cartog::overloads;
+pub use cartog::owners;
+pub use cartog::packages;
 pub use cartog::rag;
+pub use cartog::reach;
+pub use cartog::selftest;
+pub use cartog::setops;
+pub use cartog::shard;
+#[cfg(feature = "watch")]
+pub use cartog::simulate;
+pub use cartog::skeleton;
+pub use cartog::snapshot;
+pub use cartog::source;
+pub use cartog::sync;
+pub use cartog::testmap;
+pub use cartog::tokens;
+pub use cartog::training;
 pub use cartog::types;
+#[cfg(feature = "watch")]
 pub use cartog::watch;
 
 use anyhow::Result;
 use clap::Parser;
 
-use cli::{Cli, Command, RagCommand};
+use cli::{AuditCommand, Cli, Command, PackagesCommand, RagCommand};
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     let is_serve = matches!(cli.command, Command::Serve { .. });
-    let is_watch = matches!(cli.command, Command::Watch { .. });
+    let is_watch = matches!(cli.command, Command::Watch { .. } | Command::Viz { .. });
     let is_rag = matches!(
         cli.command,
         Command::Rag(RagCommand::Index { .. }) | Command::Rag(RagCommand::Setup)
@@ -32,7 +75,7 @@ fn main() -> Result<()> {
 
     // Initialize tracing to stderr for all commands.
     // - CLI mode: only warnings (e.g., unparseable files) show by default
-    // - Serve / RAG index / Watch mode: info-level for progress
+    // - Serve / RAG index / Watch / Viz mode: info-level for progress
     // Stdout stays clean for CLI output and MCP protocol.
     tracing_subscriber::fmt()
         .with_writer(std::io::stderr)
@@ -42,37 +85,411 @@ fn main() -> Result<()> {
         )
         .init();
 
+    if let Some(rev) = &cli.as_of {
+        let is_query = matches!(
+            cli.command,
+            Command::Outline { .. }
+                | Command::Show { .. }
+                | Command::Callees { .. }
+                | Command::Callers { .. }
+                | Command::Impact { .. }
+                | Command::Raises { .. }
+                | Command::Path { .. }
+                | Command::Refs { .. }
+                | Command::Hierarchy { .. }
+                | Command::Deps { .. }
+                | Command::Stats { .. }
+                | Command::Search { .. }
+                | Command::DocCoverage { .. }
+                | Command::Todos { .. }
+                | Command::Injections { .. }
+                | Command::TestsFor { .. }
+                | Command::Untested { .. }
+                | Command::Entrypoints { .. }
+                | Command::Reachable { .. }
+                | Command::Cycles { .. }
+                | Command::TableUsages { .. }
+                | Command::ExplainResolution { .. }
+                | Command::Sync { .. }
+                | Command::Packages(PackagesCommand::List)
+                | Command::Packages(PackagesCommand::Deps { .. })
+                | Command::SelectTests { .. }
+                | Command::Rag(RagCommand::Search { .. })
+        );
+        if !is_query {
+            anyhow::bail!("--as-of is only supported by query commands");
+        }
+        commands::use_db_path(snapshot::snapshot_db(std::path::Path::new("."), rev)?);
+    }
+
+    let config = config::ProjectConfig::load(std::path::Path::new("."))?;
+    if cli.as_of.is_none() {
+        let named = indexes::resolve(
+            std::path::Path::new("."),
+            cli.index.as_deref(),
+            config.index.per_branch,
+        )?;
+        if let Some(path) = named {
+            commands::use_db_path(path);
+        }
+    }
+    commands::use_config(config);
+
     match cli.command {
-        Command::Index { path, force } => commands::cmd_index(&path, force, cli.json),
-        Command::Outline { file } => commands::cmd_outline(&file, cli.json),
-        Command::Callees { name } => commands::cmd_callees(&name, cli.json),
-        Command::Impact { name, depth } => commands::cmd_impact(&name, depth, cli.json),
-        Command::Refs { name, kind } => commands::cmd_refs(&name, kind, cli.json),
+        Command::Index {
+            path,
+            force,
+            shard,
+            injections,
+            no_injections,
+            parse_cache,
+            no_parse_cache,
+            no_ignore,
+            jobs,
+            reuse,
+        } => {
+            let injections = if injections {
+                Some(true)
+            } else {
+                no_injections.then_some(false)
+            };
+            let parse_cache = if parse_cache {
+                Some(true)
+            } else {
+                no_parse_cache.then_some(false)
+            };
+            if let Some(jobs) = jobs {
+                indexer::set_jobs(jobs.into())?;
+            }
+            commands::cmd_index(
+                &path,
+                force,
+                shard,
+                injections,
+                parse_cache,
+                !no_ignore,
+                reuse.as_deref(),
+                cli.json,
+            )
+        }
+        Command::Outline {
+            file,
+            public_only,
+            signature_only,
+        } => commands::cmd_outline(&file, public_only, signature_only, cli.json),
+        Command::Show {
+            name,
+            file,
+            context,
+        } => commands::cmd_show(&name, file.as_deref(), context, cli.json),
+        Command::Context {
+            query,
+            budget,
+            keyword_only,
+        } => commands::cmd_context(&query, budget, keyword_only, cli.json),
+        Command::Callees {
+            name,
+            lines,
+            lang,
+            limit,
+        } => commands::cmd_callees(&name, lines, lang.as_deref(), limit, cli.json),
+        Command::Callers { name, lang, limit } => {
+            commands::cmd_callers(&name, lang.as_deref(), limit, cli.json)
+        }
+        Command::Impact {
+            name,
+            depth,
+            target_kind,
+            lang,
+            tree,
+            summary,
+            limit,
+        } => commands::cmd_impact(
+            &name,
+            depth,
+            target_kind,
+            lang.as_deref(),
+            tree,
+            summary,
+            limit,
+            cli.json,
+        ),
+        Command::Raises { name, depth, limit } => {
+            commands::cmd_raises(&name, depth, limit, cli.json)
+        }
+        Command::Path {
+            from,
+            to,
+            depth,
+            limit,
+        } => commands::cmd_path(&from, &to, depth, limit, cli.json),
+        Command::Refs {
+            name,
+            kind,
+            path_glob,
+            target_kind,
+            lang,
+            group,
+            limit,
+        } => commands::cmd_refs(
+            &name,
+            kind,
+            path_glob.as_deref(),
+            target_kind,
+            lang.as_deref(),
+            group,
+            limit,
+            cli.json,
+        ),
         Command::Hierarchy { name } => commands::cmd_hierarchy(&name, cli.json),
-        Command::Deps { file } => commands::cmd_deps(&file, cli.json),
-        Command::Stats => commands::cmd_stats(cli.json),
+        Command::Deps { file, limit } => commands::cmd_deps(&file, limit, cli.json),
+        Command::Stats {
+            by_dir: true,
+            depth,
+            ..
+        } => commands::cmd_stats_by_dir(depth, cli.json),
+        Command::Stats {
+            hot_symbols: true,
+            since,
+            limit,
+            ..
+        } => commands::cmd_stats_hot_symbols(limit, since, cli.json),
+        Command::Stats { .. } => commands::cmd_stats(cli.json),
+        Command::Health => commands::cmd_health(cli.json),
+        Command::Verify { fix } => commands::cmd_verify(fix, cli.json),
+        Command::Doctor { fix } => commands::cmd_doctor(fix, cli.json),
+        Command::Selftest { dir, update } => {
+            commands::cmd_selftest(dir.as_deref(), update, cli.json)
+        }
         Command::Search {
             query,
             kind,
             file,
+            lang,
+            limit,
+            anchors,
+            collapse,
+            group,
+            context,
+        } => commands::cmd_search(
+            &query,
+            kind,
+            file.as_deref(),
+            lang.as_deref(),
+            limit,
+            anchors,
+            collapse,
+            group,
+            context,
+            cli.json,
+        ),
+        Command::DocCoverage { visibility, limit } => {
+            commands::cmd_doc_coverage(visibility.into(), limit, cli.json)
+        }
+        Command::Todos { path, tag, limit } => {
+            commands::cmd_todos(path.as_deref(), tag.as_deref(), limit, cli.json)
+        }
+        Command::Injections {
+            query,
+            lang,
+            path,
+            limit,
+        } => commands::cmd_injections(
+            query.as_deref(),
+            lang.as_deref(),
+            path.as_deref(),
+            limit,
+            cli.json,
+        ),
+        Command::TestsFor { name, depth } => commands::cmd_tests_for(&name, depth, cli.json),
+        Command::SelectTests { rev, format, depth } => {
+            commands::cmd_select_tests(&rev, format.map(Into::into), depth, cli.json)
+        }
+        Command::Untested { dir, limit } => commands::cmd_untested(dir.as_deref(), limit, cli.json),
+        Command::Entrypoints { kind, limit } => {
+            commands::cmd_entrypoints(kind.as_deref(), limit, cli.json)
+        }
+        Command::Reachable { from, dir, limit } => {
+            commands::cmd_reachable(from.as_deref(), dir.as_deref(), limit, cli.json)
+        }
+        Command::Cycles { lang, scope, check } => {
+            commands::cmd_cycles(lang.as_deref(), scope.into(), check, cli.json)
+        }
+        Command::TableUsages { table } => commands::cmd_table_usages(&table, cli.json),
+        Command::ExplainResolution {
+            target,
+            file,
             limit,
-        } => commands::cmd_search(&query, kind, file.as_deref(), limit, cli.json),
+        } => commands::cmd_explain_resolution(&target, file.as_deref(), limit, cli.json),
+        Command::Pin { name, file } => commands::cmd_pin(&name, &file, cli.json),
+        Command::Unpin { name } => commands::cmd_unpin(&name, cli.json),
+        Command::Clean => commands::cmd_clean(cli.json),
+        Command::Schema { command } => commands::cmd_schema(&command.join("-")),
+        Command::Export {
+            format,
+            tables,
+            out,
+            root,
+            depth,
+        } => {
+            let tables: Vec<_> = tables.into_iter().map(Into::into).collect();
+            commands::cmd_export(
+                format.into(),
+                &tables,
+                &out,
+                root.as_deref(),
+                depth,
+                cli.json,
+            )
+        }
+        Command::Maintain { vacuum } => commands::cmd_maintain(vacuum, cli.json),
+        Command::Prune {
+            path,
+            older_than,
+            dry_run,
+        } => commands::cmd_prune(&path, older_than, dry_run, cli.json),
+        Command::Indexes => commands::cmd_indexes(cli.json),
+        Command::Backup { to } => commands::cmd_backup(&to, cli.json),
+        Command::Sync {
+            since,
+            files,
+            outlines,
+        } => commands::cmd_sync(since, &files, outlines, cli.json),
+        Command::OwnersImpact { range, depth } => {
+            commands::cmd_owners_impact(&range, depth, cli.json)
+        }
+        #[cfg(feature = "watch")]
         Command::Watch {
             path,
             debounce,
             rag,
             rag_delay,
-        } => commands::cmd_watch(&path, debounce, rag, rag_delay),
-        Command::Serve { watch, rag } => {
+            skip_lang,
+            skip_tests,
+            rag_skip_tests,
+            rag_public_only,
+            simulate,
+        } => commands::cmd_watch(
+            &path,
+            debounce,
+            rag,
+            rag_delay,
+            watch::WatchFilter {
+                skip_languages: skip_lang,
+                skip_tests,
+                embed: rag::indexer::EmbedFilter {
+                    skip_tests: rag_skip_tests,
+                    public_only: rag_public_only,
+                },
+            },
+            simulate.as_deref(),
+            cli.json,
+        ),
+        #[cfg(not(feature = "watch"))]
+        Command::Watch { .. } => Err(compiled_out("watch")),
+        Command::Viz { port, watch } => commands::cmd_viz(port, watch),
+        #[cfg(feature = "tui")]
+        Command::Tui { query } => commands::cmd_tui(query.as_deref()),
+        #[cfg(not(feature = "tui"))]
+        Command::Tui { .. } => Err(compiled_out("tui")),
+        Command::Repl => commands::cmd_repl(),
+        Command::Set { op, operands } => commands::cmd_set(op.into(), &operands, cli.json),
+        Command::Serve {
+            api: Some(addr),
+            cors_origins,
+            watch,
+            ..
+        } => commands::cmd_serve_api(api::ApiConfig { addr, cors_origins }, watch),
+        #[cfg(feature = "mcp")]
+        Command::Serve {
+            watch,
+            rag,
+            metrics_addr,
+            audit,
+            audit_retention_days,
+            projects,
+            preload_models,
+            allow_roots,
+            max_read_bytes,
+            ..
+        } => {
             let runtime = tokio::runtime::Runtime::new()?;
-            runtime.block_on(mcp::run_server(watch, rag))
+            runtime.block_on(mcp::run_server(mcp::ServeConfig {
+                watch,
+                rag,
+                metrics_addr,
+                audit_retention_days: audit.then_some(audit_retention_days),
+                projects,
+                preload_models,
+                index: cli.index,
+                allow_roots,
+                max_read_bytes,
+            }))
         }
+        #[cfg(not(feature = "mcp"))]
+        Command::Serve { .. } => Err(compiled_out("mcp")),
         Command::Rag(rag_cmd) => match rag_cmd {
             RagCommand::Setup => commands::cmd_rag_setup(cli.json),
             RagCommand::Index { path, force } => commands::cmd_rag_index(&path, force, cli.json),
-            RagCommand::Search { query, kind, limit } => {
-                commands::cmd_rag_search(&query, kind, limit, cli.json)
+            RagCommand::Migrate { model, cancel } => {
+                commands::cmd_rag_migrate(model, cancel, cli.json)
+            }
+            RagCommand::Status => commands::cmd_rag_status(cli.json),
+            RagCommand::RebuildFts => commands::cmd_rag_rebuild_fts(cli.json),
+            RagCommand::FtsWeights { weights, reset } => {
+                commands::cmd_rag_fts_weights(weights, reset, cli.json)
             }
+            RagCommand::Calibration {
+                temperature,
+                min_relevance,
+                reset,
+            } => commands::cmd_rag_calibration(temperature, min_relevance, reset, cli.json),
+            RagCommand::Search {
+                query,
+                kind,
+                path,
+                lang,
+                exclude,
+                limit,
+                keyword_only,
+                min_score,
+                budget,
+            } => commands::cmd_rag_search(
+                &query,
+                rag::search::SearchFilter {
+                    kind: kind.map(Into::into),
+                    path: path.as_deref(),
+                    lang: lang.as_deref(),
+                    exclude: exclude.as_deref(),
+                },
+                limit,
+                keyword_only,
+                min_score,
+                budget,
+                cli.json,
+            ),
+        },
+        Command::Audit(AuditCommand::Tail { limit, tool }) => {
+            commands::cmd_audit_tail(limit, tool.as_deref(), cli.json)
+        }
+        Command::EmitTrainingData { to, since } => {
+            commands::cmd_emit_training_data(&to, since, cli.json)
+        }
+        Command::Packages(packages_cmd) => match packages_cmd {
+            PackagesCommand::Sync { tool } => {
+                commands::cmd_packages_sync(tool.map(Into::into), cli.json)
+            }
+            PackagesCommand::List => commands::cmd_packages_list(cli.json),
+            PackagesCommand::Deps { name } => commands::cmd_packages_deps(&name, cli.json),
         },
     }
 }
+
+/// Error for a subcommand whose cargo feature was not enabled at build time.
+#[cfg(not(all(feature = "mcp", feature = "watch", feature = "tui")))]
+fn compiled_out(feature: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "this command is not available: cartog was built without the `{feature}` feature \
+         (rebuild with `cargo install cartog --features {feature}`)"
+    )
+}
@@ -1,26 +1,60 @@
+use std::collections::HashSet;
 use std::future::Future;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
 use rmcp::schemars;
 use rmcp::{
-    handler::server::{router::tool::ToolRouter, tool::Parameters},
+    handler::server::{
+        router::tool::ToolRouter,
+        tool::{Parameters, ToolCallContext},
+    },
     model::*,
-    tool, tool_handler, tool_router,
+    service::RequestContext,
+    tool, tool_router,
     transport::stdio,
-    ErrorData as McpError, ServerHandler, ServiceExt,
+    ErrorData as McpError, RoleServer, ServerHandler, ServiceExt,
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, info};
 
-use crate::db::{Database, DB_FILE, MAX_SEARCH_LIMIT};
-use crate::indexer;
+use crate::callpath;
+use crate::config::ProjectConfig;
+use crate::context;
+use crate::db::{unix_now, AuditEntry, SymbolHit, DB_FILE, MAX_SEARCH_LIMIT};
+use crate::health;
+use crate::hooks::Hooks;
+use crate::impact;
+use crate::indexes;
+use crate::jobs::{IndexJob, Jobs};
+use crate::languages::QUERY_LANGUAGES;
+use crate::metrics::{self, Metrics};
+use crate::overloads::{self, Reference};
+use crate::policy::PathPolicy;
 use crate::rag;
-use crate::types::EdgeKind;
+use crate::rag::freshness::EmbedPolicy;
+use crate::schema;
+use crate::setops::{self, SetOp, SetQuery};
+use crate::shard::ShardedDatabase;
+use crate::skeleton;
+use crate::source;
+use crate::sync;
+use crate::testmap;
+use crate::tokens;
+use crate::types::{collapse_similar, EdgeKind, HierarchyEntry, ImpactEntry, SymbolMatch};
+#[cfg(feature = "watch")]
 use crate::watch::{self, WatchConfig, WatchHandle};
 
 const MAX_IMPACT_DEPTH: u32 = 10;
+const MAX_NOTE_CHARS: usize = 2000;
+const MAX_NOTE_TTL_HOURS: u32 = 720;
+const MAX_RESERVATION_TTL_MINUTES: u32 = 1440;
+const MAX_BATCH_QUERIES: usize = 32;
+/// MIME type of the `cartog://schema/<command>` resources.
+const SCHEMA_MIME: &str = "application/schema+json";
 
 // ── Parameter types ──
 
@@ -32,6 +66,8 @@ pub struct IndexParams {
     /// Force full re-index, bypassing change detection
     #[serde(default)]
     pub force: bool,
+    /// Project to update (see cartog_list_projects); required when several are served
+    pub project: Option<String>,
 }
 
 fn default_dot() -> String {
@@ -42,6 +78,49 @@ fn default_dot() -> String {
 pub struct OutlineParams {
     /// File path relative to project root
     pub file: String,
+    /// Only public symbols (no private helpers, no imports), for an API skeleton
+    #[serde(default)]
+    pub public_only: bool,
+    /// Only kind, name and signature per symbol: no variables, no line or byte ranges
+    #[serde(default)]
+    pub signature_only: bool,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ShowParams {
+    /// Symbol name to show the source of
+    pub name: String,
+    /// Only the definition in this file (relative to project root)
+    pub file: Option<String>,
+    /// Lines of context before and after each definition (default 0, at most 50)
+    #[serde(default)]
+    pub context: u32,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ContextParams {
+    /// Symbol name, or a search query whose best hit is the subject
+    pub query: String,
+    /// Token budget of the bundle (default 4000)
+    pub budget: Option<u64>,
+    /// Find related code by keyword only, without loading any model (default false)
+    pub keyword_only: Option<bool>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ValidateRangeParams {
+    /// Symbol ID whose byte range is about to be edited (`file:qualified.name:hash`)
+    pub symbol_id: String,
+    /// `hash` of the symbol's code as returned by cartog_show
+    pub expected_hash: String,
+    /// Project to query (see cartog_list_projects); required with several projects
+    pub project: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -50,12 +129,40 @@ pub struct RefsParams {
     pub name: String,
     /// Filter by edge kind: calls, imports, inherits, references, raises
     pub kind: Option<String>,
+    /// Only references in files matching this glob, e.g. "src/auth/**" (`*` also matches `/`)
+    #[serde(rename = "in")]
+    pub path_glob: Option<String>,
+    /// Only references resolved to a symbol of this kind: function, class, method, variable, import
+    pub target_kind: Option<String>,
+    /// Only references made in files of this language: python, typescript (with .tsx), javascript, rust, go, ruby, csharp, prisma
+    pub lang: Option<String>,
+    /// Group references by the qualified name of their target, with its `definitions`
+    #[serde(default)]
+    pub group: bool,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct CalleesParams {
     /// Symbol name to find callees of
     pub name: String,
+    /// Include the source text of each call line (default false)
+    pub lines: Option<bool>,
+    /// Only calls made in files of this language: python, typescript (with .tsx), javascript, rust, go, ruby, csharp, prisma
+    pub lang: Option<String>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CallersParams {
+    /// Symbol name to find callers of
+    pub name: String,
+    /// Only calls made in files of this language: python, typescript (with .tsx), javascript, rust, go, ruby, csharp, prisma
+    pub lang: Option<String>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -64,18 +171,68 @@ pub struct ImpactParams {
     pub name: String,
     /// Maximum traversal depth (default 3, max 10)
     pub depth: Option<u32>,
+    /// Only start from references resolved to a symbol of this kind: function, class, method, variable, import
+    pub target_kind: Option<String>,
+    /// Only follow references made in files of this language: python, typescript (with .tsx), javascript, rust, go, ruby, csharp, prisma
+    pub lang: Option<String>,
+    /// Group results by depth, then file, then referencing symbol (default false)
+    #[serde(default)]
+    pub tree: bool,
+    /// Only count the impacted symbols per file (default false; overrides tree)
+    #[serde(default)]
+    pub summary: bool,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RaisesParams {
+    /// Exception or error type name (e.g. ValueError, ParseError, ErrNotFound)
+    pub name: String,
+    /// Maximum depth of transitive callers (default 3, max 10)
+    pub depth: Option<u32>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PathParams {
+    /// Symbol the paths start from: name, ID, or name qualified by its class (e.g. Database.open)
+    pub from: String,
+    /// Symbol the paths lead to, given the same way
+    pub to: String,
+    /// Maximum number of hops (default 6, max 10)
+    pub depth: Option<u32>,
+    /// Maximum paths to return (default 5)
+    pub limit: Option<u32>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetParams {
+    /// How to combine the queries: intersect, union, or diff (symbols of the first query in none of the others)
+    pub op: SetOp,
+    /// At least two queries, each one of {"search": text}, {"refs": name}, {"callers": name}, {"callees": name}, {"impact": name}, {"outline": file} or {"ids": [symbol IDs]}
+    pub queries: Vec<SetQuery>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct HierarchyParams {
     /// Class name to show hierarchy for
     pub name: String,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct DepsParams {
     /// File path to show import dependencies for
     pub file: String,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -86,8 +243,98 @@ pub struct SearchParams {
     pub kind: Option<String>,
     /// Filter to a specific file path relative to project root
     pub file: Option<String>,
+    /// Only symbols in files of this language: python, typescript (with .tsx), javascript, rust, go, ruby, csharp, prisma
+    pub lang: Option<String>,
     /// Maximum results to return (default 30, max 100)
     pub limit: Option<u32>,
+    /// Also return TODO/FIXME/HACK/XXX/SAFETY comments whose text contains the query
+    #[serde(default)]
+    pub include_anchors: bool,
+    /// Fold names differing only by numbers or generated suffixes (FooImpl1, FooImpl2)
+    /// into one result whose `similar` lists the folded symbol IDs
+    #[serde(default)]
+    pub collapse: bool,
+    /// Group overloads and same-name definitions by qualified name (`Parser::parse`)
+    /// into one result with a `definitions` array
+    #[serde(default)]
+    pub group: bool,
+    /// Include the docstring of each hit's parent (a method's class) as `parent_docstring`
+    #[serde(default)]
+    pub context: bool,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TodosParams {
+    /// Only comments in this file or directory (path prefix relative to project root)
+    pub path: Option<String>,
+    /// Only this tag: todo, fixme, hack, xxx, safety
+    pub tag: Option<String>,
+    /// Maximum results to return (default 100)
+    pub limit: Option<u32>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct InjectionsParams {
+    /// Words the string literal must contain (case-insensitive)
+    pub query: Option<String>,
+    /// Only this embedded language: sql, html, regex
+    pub lang: Option<String>,
+    /// Only literals in this file or directory (path prefix relative to project root)
+    pub path: Option<String>,
+    /// Maximum results to return (default 100)
+    pub limit: Option<u32>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TestsForParams {
+    /// Symbol name to find tests for
+    pub name: String,
+    /// Maximum hops from a test to the symbol (default 3, max 10)
+    pub depth: Option<u32>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UntestedParams {
+    /// Only symbols in this file or directory (path prefix relative to project root)
+    pub dir: Option<String>,
+    /// Maximum results to return (default 100)
+    pub limit: Option<u32>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StatsParams {
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SyncParams {
+    /// `generation` of the previous cartog_sync response; every file when omitted
+    pub since: Option<u64>,
+    /// Only these files (relative to project root), e.g. the files open in the editor
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// Include the outline of each listed file
+    #[serde(default)]
+    pub outlines: bool,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct HealthParams {
+    /// Project to check (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -98,6 +345,39 @@ pub struct RagIndexParams {
     /// Force re-embed all symbols (ignore existing embeddings)
     #[serde(default)]
     pub force: bool,
+    /// Project to update (see cartog_list_projects); required when several are served
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StartIndexParams {
+    /// Directory to index relative to project root (defaults to ".")
+    #[serde(default = "default_dot")]
+    pub path: String,
+    /// Force full re-index (and re-embedding with `rag`), bypassing change detection
+    #[serde(default)]
+    pub force: bool,
+    /// Also build the embedding index for semantic search once the graph is indexed
+    #[serde(default)]
+    pub rag: bool,
+    /// Project to index (see cartog_list_projects); required when several are served
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct JobStatusParams {
+    /// Job ID returned by cartog_start_index; the 10 most recent jobs when omitted
+    pub job_id: Option<i64>,
+    /// Project of the job (see cartog_list_projects); required when several are served
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CancelJobParams {
+    /// Job ID returned by cartog_start_index
+    pub job_id: i64,
+    /// Project of the job (see cartog_list_projects); required when several are served
+    pub project: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -106,28 +386,120 @@ pub struct RagSearchParams {
     pub query: String,
     /// Filter by symbol kind: function, class, method, variable
     pub kind: Option<String>,
+    /// Only symbols in files matching this gitignore-style glob relative to project root (e.g. 'src/auth/**')
+    pub path: Option<String>,
+    /// Only symbols in files of this language: python, typescript (with .tsx), javascript, rust, go, ruby, csharp, prisma
+    pub lang: Option<String>,
+    /// Leave out symbols in files matching this glob (e.g. '*.test.ts')
+    pub exclude: Option<String>,
     /// Maximum results to return (default 10)
     pub limit: Option<u32>,
+    /// Keyword-only search (FTS5/BM25), loading no embedding or re-ranker model
+    #[serde(default)]
+    pub keyword_only: bool,
+    /// Drop re-ranked results whose calibrated `relevance` (0-1) is below this (default 0.01, or the index's `rag calibration`)
+    pub min_score: Option<f64>,
+    /// Fit result contents into about this many tokens; lower-value results are cut to their signature line
+    pub budget: Option<u64>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
 }
 
-// ── Response wrappers for JSON serialization ──
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RagSearchBatchParams {
+    /// Natural language queries, searched together (max 32)
+    pub queries: Vec<String>,
+    /// Filter by symbol kind: function, class, method, variable
+    pub kind: Option<String>,
+    /// Only symbols in files matching this gitignore-style glob relative to project root (e.g. 'src/auth/**')
+    pub path: Option<String>,
+    /// Only symbols in files of this language: python, typescript (with .tsx), javascript, rust, go, ruby, csharp, prisma
+    pub lang: Option<String>,
+    /// Leave out symbols in files matching this glob (e.g. '*.test.ts')
+    pub exclude: Option<String>,
+    /// Maximum results per query (default 10)
+    pub limit: Option<u32>,
+    /// Keyword-only search (FTS5/BM25), loading no embedding or re-ranker model
+    #[serde(default)]
+    pub keyword_only: bool,
+    /// Drop re-ranked results whose calibrated `relevance` (0-1) is below this (default 0.01, or the index's `rag calibration`)
+    pub min_score: Option<f64>,
+    /// Fit each query's result contents into about this many tokens
+    pub budget: Option<u64>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
+}
 
-#[derive(Debug, Serialize)]
-struct RefEntry {
-    edge: crate::types::Edge,
-    source: Option<crate::types::Symbol>,
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct NotifyFileChangedParams {
+    /// Saved, created or deleted files relative to project root
+    pub paths: Vec<String>,
+    /// Project to update (see cartog_list_projects); required when several are served
+    pub project: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
-struct ImpactEntry {
-    edge: crate::types::Edge,
-    depth: u32,
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BackupParams {
+    /// Destination file relative to project root (replaced if it exists)
+    pub to: String,
+    /// Project to update (see cartog_list_projects); required when several are served
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AnnotateSymbolParams {
+    /// Symbol name to attach the note to
+    pub name: String,
+    /// File defining the symbol, relative to project root; required when several files define the name
+    pub file: Option<String>,
+    /// Note text, e.g. an intermediate finding (max 2000 characters)
+    pub note: String,
+    /// Hours until the note expires (default 24, max 720)
+    pub ttl_hours: Option<u32>,
+    /// Project to update (see cartog_list_projects); required when several are served
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReserveSymbolsParams {
+    /// Symbol names to reserve
+    pub names: Vec<String>,
+    /// File defining the symbols, relative to project root; required when several files define a name
+    pub file: Option<String>,
+    /// Name of the agent or worker taking the reservation
+    pub owner: String,
+    /// What the symbols are reserved for, shown to other agents (max 2000 characters)
+    pub reason: Option<String>,
+    /// Minutes until the reservation expires (default 30, max 1440)
+    pub ttl_minutes: Option<u32>,
+    /// Project to update (see cartog_list_projects); required when several are served
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ReleaseSymbolsParams {
+    /// Owner whose reservations to release
+    pub owner: String,
+    /// Symbol names to release (default: every reservation of `owner`)
+    pub names: Option<Vec<String>>,
+    /// Project to update (see cartog_list_projects); required when several are served
+    pub project: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ListReservationsParams {
+    /// Only the reservations of this owner
+    pub owner: Option<String>,
+    /// Project to query (see cartog_list_projects); all projects when omitted
+    pub project: Option<String>,
 }
 
+// ── Response wrappers for JSON serialization ──
+
 #[derive(Debug, Serialize)]
-struct HierarchyEntry {
-    child: String,
-    parent: String,
+struct SearchWithAnchors {
+    symbols: serde_json::Value,
+    anchors: Vec<crate::types::Anchor>,
 }
 
 // ── Path validation ──
@@ -163,6 +535,13 @@ fn validate_path_within_cwd_canonical(
     Ok(resolved)
 }
 
+/// [`validate_path_within_cwd_canonical`], relative to `root` as the index stores paths.
+fn validate_relative_path(input: &str, root: &Path) -> Result<String, McpError> {
+    let validated = validate_path_within_cwd_canonical(input, root).map_err(mcp_err)?;
+    let relative = validated.strip_prefix(root).unwrap_or(&validated);
+    Ok(relative.to_string_lossy().into_owned())
+}
+
 /// Validate that a path is within the current working directory subtree.
 /// Returns the canonicalized path on success, or an error if the path escapes CWD.
 #[cfg(test)]
@@ -175,7 +554,7 @@ fn validate_path_within_cwd(input: &str) -> Result<PathBuf, String> {
 }
 
 /// Normalize a path by resolving `.` and `..` components without requiring the path to exist.
-fn normalize_path(path: &Path) -> PathBuf {
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
     let mut components = Vec::new();
     for component in path.components() {
         match component {
@@ -193,47 +572,375 @@ fn mcp_err(msg: impl std::fmt::Display) -> McpError {
     McpError::internal_error(msg.to_string(), None)
 }
 
+/// The one non-import definition of `name`, in `file` when given.
+fn find_definition(
+    db: &ShardedDatabase,
+    name: &str,
+    file: Option<&str>,
+) -> Result<crate::types::Symbol, McpError> {
+    let mut definitions: Vec<_> = db
+        .search(name, None, file, MAX_SEARCH_LIMIT)
+        .map_err(|e| mcp_err(format!("symbol lookup failed: {e}")))?
+        .into_iter()
+        .filter(|s| s.name == name && s.kind != crate::types::SymbolKind::Import)
+        .collect();
+    let mut files: Vec<&str> = definitions.iter().map(|s| s.file_path.as_str()).collect();
+    files.sort_unstable();
+    files.dedup();
+    match files.len() {
+        0 => Err(mcp_err(format!("no definition of '{name}' found"))),
+        1 => Ok(definitions.swap_remove(0)),
+        _ => Err(mcp_err(format!(
+            "'{name}' is defined in several files, pass `file`: {}",
+            files.join(", ")
+        ))),
+    }
+}
+
+/// The `[hooks]` of the project at `root`; none when its `.cartog.toml` is invalid.
+fn project_hooks(root: &Path) -> Hooks {
+    let config = ProjectConfig::load(root).unwrap_or_else(|e| {
+        tracing::warn!(error = %e, "ignoring hooks");
+        ProjectConfig::default()
+    });
+    Hooks::new(config.hooks, root)
+}
+
+/// Parse an optional `target_kind` tool parameter.
+fn parse_target_kind(kind: Option<&str>) -> Result<Option<crate::types::SymbolKind>, McpError> {
+    kind.map(|s| {
+        s.parse().map_err(|_| {
+            mcp_err(format!(
+                "invalid target kind '{s}'. Valid: function, class, method, variable, import"
+            ))
+        })
+    })
+    .transpose()
+}
+
+/// Check an optional `lang` tool parameter.
+fn parse_lang(lang: Option<String>) -> Result<Option<String>, McpError> {
+    match lang {
+        Some(l) if !QUERY_LANGUAGES.contains(&l.as_str()) => Err(mcp_err(format!(
+            "invalid language '{l}'. Valid: {}",
+            QUERY_LANGUAGES.join(", ")
+        ))),
+        lang => Ok(lang),
+    }
+}
+
+/// Embed the backlog before a semantic search under `serve --rag lazy`. A failure
+/// (no model downloaded, say) is logged and the search runs on the vectors it has.
+fn catch_up_embeddings(db: &ShardedDatabase) {
+    match rag::freshness::catch_up(db) {
+        Ok(Some(r)) => info!(embedded = r.symbols_embedded, "lazy embedding complete"),
+        Ok(None) => {}
+        Err(e) => tracing::warn!(error = %e, "lazy embedding failed, searching stale vectors"),
+    }
+}
+
 /// Build a JSON text response, appending a hint if the DB has no indexed files.
-fn json_response(db: &Database, json: String) -> Result<CallToolResult, McpError> {
+fn json_response(db: &ShardedDatabase, json: String) -> Result<CallToolResult, McpError> {
     // Single lightweight check instead of full stats() (which runs 4 COUNT queries).
     let is_empty = !db
         .has_indexed_files()
         .map_err(|e| mcp_err(format!("stats check failed: {e}")))?;
+    text_response(json, is_empty)
+}
+
+/// JSON text response with token estimates, or the empty-index hint.
+fn text_response(json: String, is_empty: bool) -> Result<CallToolResult, McpError> {
     if is_empty {
         let hint = "\n\n(Index is empty. Run cartog_index first to build the code graph.)";
         Ok(CallToolResult::success(vec![Content::text(format!(
             "{json}{hint}"
         ))]))
     } else {
-        Ok(CallToolResult::success(vec![Content::text(json)]))
+        Ok(CallToolResult::success(vec![Content::text(
+            with_token_estimates(json),
+        )]))
+    }
+}
+
+/// Add `estimated_tokens` to each result and a trailing total, so the agent can
+/// judge the cost of reading result bodies before fetching them.
+fn with_token_estimates(json: String) -> String {
+    let Ok(mut value) = serde_json::from_str::<serde_json::Value>(&json) else {
+        return json;
+    };
+    let total = tokens::annotate(&mut value);
+    if total == 0 {
+        return json;
     }
+    let json = serde_json::to_string_pretty(&value).unwrap_or(json);
+    format!(
+        "{json}\n\n({} to read these results)",
+        tokens::format_total(total)
+    )
 }
 
 // ── MCP Server ──
 
+/// An indexed repository served by this server.
+#[derive(Clone)]
+struct Project {
+    /// Label used by the `project` tool parameter and in merged results.
+    name: String,
+    /// Canonicalized project root captured at server start; tool paths are
+    /// validated against it. Wrapped in `Arc` so clones are cheap.
+    root: Arc<Path>,
+    /// Database served: `.cartog.db` under the root, or a named index
+    /// (`serve --index`, or the branch's with `per_branch`).
+    db_path: Arc<Path>,
+    /// Shared database connection, opened once at server start.
+    db: Arc<Mutex<ShardedDatabase>>,
+}
+
+impl Project {
+    fn open(name: String, root: &Path, index: Option<&str>) -> anyhow::Result<Self> {
+        let root = root
+            .canonicalize()
+            .map_err(|e| anyhow::anyhow!("cannot resolve project root {}: {e}", root.display()))?;
+        let per_branch = ProjectConfig::load(&root)?.index.per_branch;
+        let db_path =
+            indexes::resolve(&root, index, per_branch)?.unwrap_or_else(|| root.join(DB_FILE));
+        let db = ShardedDatabase::open(&db_path)
+            .map_err(|e| anyhow::anyhow!("failed to open database of project '{name}': {e}"))?;
+        // Jobs left running belong to a previous server that stopped mid-run.
+        let interrupted = db.interrupt_running_jobs(unix_now())?;
+        if interrupted > 0 {
+            info!(project = %name, interrupted, "marked unfinished index jobs as interrupted");
+        }
+        Ok(Self {
+            name,
+            root: Arc::from(root),
+            db_path: Arc::from(db_path),
+            db: Arc::new(Mutex::new(db)),
+        })
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, ShardedDatabase>, McpError> {
+        self.db
+            .lock()
+            .map_err(|_| mcp_err("database lock poisoned"))
+    }
+}
+
+/// `[NAME=]PATH` from `serve --project`; the name defaults to the directory name.
+pub fn parse_project_spec(spec: &str) -> (String, PathBuf) {
+    match spec.split_once('=') {
+        Some((name, path)) if !name.is_empty() && !name.contains(['/', '\\']) => {
+            (name.to_string(), PathBuf::from(path))
+        }
+        _ => {
+            let path = PathBuf::from(spec);
+            (project_name(&path), path)
+        }
+    }
+}
+
+/// Last component of `root`, made absolute first so `.` names the current directory.
+fn project_name(root: &Path) -> String {
+    let absolute = match std::env::current_dir() {
+        Ok(cwd) if root.is_relative() => normalize_path(&cwd.join(root)),
+        _ => normalize_path(root),
+    };
+    absolute
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// Add `"project": name` to each result of `value` (array items, or the value
+/// itself), appending them to `merged`. Non-object results are wrapped as
+/// `{"project": name, "value": ...}`.
+fn label_results(value: serde_json::Value, name: &str, merged: &mut Vec<serde_json::Value>) {
+    let items = match value {
+        serde_json::Value::Array(items) => items,
+        other => vec![other],
+    };
+    merged.extend(items.into_iter().map(|item| match item {
+        serde_json::Value::Object(mut obj) => {
+            obj.insert("project".to_string(), name.into());
+            serde_json::Value::Object(obj)
+        }
+        other => serde_json::json!({ "project": name, "value": other }),
+    }));
+}
+
+#[derive(Debug, Serialize)]
+struct ProjectEntry {
+    name: String,
+    root: String,
+    num_files: u32,
+    num_symbols: u32,
+    num_edges: u32,
+}
+
 #[derive(Clone)]
 pub struct CartogServer {
     tool_router: ToolRouter<Self>,
-    /// Shared database connection, opened once at server start.
-    db: Arc<Mutex<Database>>,
-    /// Canonicalized CWD captured at server start to avoid repeated syscalls.
-    /// Wrapped in `Arc` so clones (required by `#[derive(Clone)]`) are cheap.
-    cwd: Arc<Path>,
+    /// Projects in configuration order: the working directory, or the
+    /// `serve --project` list when federating several repositories.
+    projects: Arc<[Project]>,
+    /// Tool-call counters, shared with the optional `/metrics` listener.
+    metrics: Arc<Metrics>,
+    /// Opt-in audit log of tool calls (`serve --audit`).
+    audit: Option<Arc<AuditLog>>,
+    /// Where tools returning file content may read from (`serve --allow-root`).
+    policy: Arc<PathPolicy>,
+    /// When embeddings catch up with index updates (`serve --rag <POLICY>`).
+    embed_policy: EmbedPolicy,
+    /// Index runs started by `cartog_start_index`.
+    jobs: Arc<Jobs>,
 }
 
 #[tool_router]
 impl CartogServer {
-    pub fn new() -> anyhow::Result<Self> {
-        let db =
-            Database::open(DB_FILE).map_err(|e| anyhow::anyhow!("failed to open database: {e}"))?;
-        let cwd = std::env::current_dir()
-            .and_then(|p| p.canonicalize())
-            .map_err(|e| anyhow::anyhow!("cannot determine CWD: {e}"))?;
+    /// Serve the index in the current directory, or its named index `index`.
+    pub fn new(index: Option<&str>) -> anyhow::Result<Self> {
+        let cwd =
+            std::env::current_dir().map_err(|e| anyhow::anyhow!("cannot determine CWD: {e}"))?;
+        Self::federated(&[(project_name(&cwd), cwd)], index)
+    }
+
+    /// Serve several projects, each with its own index under its root (the one
+    /// named `index` when given).
+    pub fn federated(projects: &[(String, PathBuf)], index: Option<&str>) -> anyhow::Result<Self> {
+        anyhow::ensure!(!projects.is_empty(), "no project to serve");
+        let mut opened: Vec<Project> = Vec::with_capacity(projects.len());
+        for (name, root) in projects {
+            anyhow::ensure!(
+                opened.iter().all(|p| &p.name != name),
+                "duplicate project name '{name}' (name them with --project NAME=PATH)"
+            );
+            opened.push(Project::open(name.clone(), root, index)?);
+        }
         Ok(Self {
             tool_router: Self::tool_router(),
-            db: Arc::new(Mutex::new(db)),
-            cwd: Arc::from(cwd),
+            projects: opened.into(),
+            metrics: Arc::new(Metrics::new()),
+            audit: None,
+            policy: Arc::new(PathPolicy::default()),
+            embed_policy: EmbedPolicy::default(),
+            jobs: Arc::new(Jobs::default()),
+        })
+    }
+
+    /// Record every tool call in the `audit_log` table, keeping `retention_days` of history.
+    pub fn with_audit(mut self, retention_days: u32) -> Self {
+        self.audit = Some(Arc::new(AuditLog::new(retention_days)));
+        self
+    }
+
+    /// Check the files of returned content against `policy` instead of the default
+    /// (project roots only, [`crate::policy::DEFAULT_MAX_READ_BYTES`] per result).
+    pub fn with_path_policy(mut self, policy: PathPolicy) -> Self {
+        self.policy = Arc::new(policy);
+        self
+    }
+
+    /// Keep embeddings fresh by `policy`, recording it in each project's index for
+    /// `cartog rag status`. Only `lazy` changes what the server itself does: semantic
+    /// searches embed the backlog first.
+    pub fn with_embed_policy(mut self, policy: EmbedPolicy) -> anyhow::Result<Self> {
+        for project in self.projects.iter() {
+            let db = project
+                .db
+                .lock()
+                .map_err(|_| anyhow::anyhow!("database lock poisoned"))?;
+            db.set_embed_policy(policy.as_str())?;
+        }
+        self.embed_policy = policy;
+        Ok(self)
+    }
+
+    /// Counters recorded for every tool call.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    fn project_names(&self) -> String {
+        let names: Vec<&str> = self.projects.iter().map(|p| p.name.as_str()).collect();
+        names.join(", ")
+    }
+
+    /// The named project, or every project when `name` is `None`.
+    fn select(&self, name: Option<&str>) -> Result<Vec<Project>, McpError> {
+        match name {
+            None => Ok(self.projects.to_vec()),
+            Some(name) => match self.projects.iter().find(|p| p.name == name) {
+                Some(project) => Ok(vec![project.clone()]),
+                None => Err(mcp_err(format!(
+                    "unknown project '{name}'. Known: {}",
+                    self.project_names()
+                ))),
+            },
+        }
+    }
+
+    /// The project a write applies to: the named one, or the only one served.
+    fn target(&self, name: Option<&str>) -> Result<Project, McpError> {
+        if name.is_none() && self.projects.len() > 1 {
+            return Err(mcp_err(format!(
+                "project is required when several are served: {}",
+                self.project_names()
+            )));
+        }
+        Ok(self.select(name)?.swap_remove(0))
+    }
+
+    /// Run a read-only query returning JSON against the selected projects.
+    ///
+    /// With one project the JSON is returned as is; with several, results are
+    /// concatenated in project order, each labeled with a `project` field.
+    async fn query<F>(&self, project: Option<String>, f: F) -> Result<CallToolResult, McpError>
+    where
+        F: Fn(&ShardedDatabase, &Path) -> Result<String, McpError> + Send + 'static,
+    {
+        let projects = self.select(project.as_deref())?;
+
+        tokio::task::spawn_blocking(move || {
+            if let [project] = projects.as_slice() {
+                let db = project.lock()?;
+                let json = f(&db, &project.root)?;
+                return json_response(&db, json);
+            }
+
+            let mut merged = Vec::new();
+            let mut any_indexed = false;
+            for project in &projects {
+                let db = project.lock()?;
+                any_indexed |= db
+                    .has_indexed_files()
+                    .map_err(|e| mcp_err(format!("stats check failed: {e}")))?;
+                let json = f(&db, &project.root)?;
+                let value = serde_json::from_str(&json)
+                    .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
+                label_results(value, &project.name, &mut merged);
+            }
+            let json = serde_json::to_string_pretty(&merged)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
+            text_response(json, !any_indexed)
+        })
+        .await
+        .map_err(|e| mcp_err(format!("task join failed: {e}")))?
+    }
+
+    /// Run a write (indexing, backup) against one project.
+    async fn update<F>(&self, project: Option<String>, f: F) -> Result<CallToolResult, McpError>
+    where
+        F: FnOnce(&mut ShardedDatabase, &Path) -> Result<String, McpError> + Send + 'static,
+    {
+        let project = self.target(project.as_deref())?;
+
+        tokio::task::spawn_blocking(move || {
+            let mut db = project.lock()?;
+            let json = f(&mut db, &project.root)?;
+            Ok(CallToolResult::success(vec![Content::text(json)]))
         })
+        .await
+        .map_err(|e| mcp_err(format!("task join failed: {e}")))?
     }
 
     /// Build or rebuild the code graph index for a directory.
@@ -246,124 +953,312 @@ impl CartogServer {
     ) -> Result<CallToolResult, McpError> {
         let path = params.path;
         let force = params.force;
-        let db = Arc::clone(&self.db);
-        let cwd = Arc::clone(&self.cwd);
 
-        tokio::task::spawn_blocking(move || {
-            let validated = validate_path_within_cwd_canonical(&path, &cwd).map_err(mcp_err)?;
+        self.update(params.project, move |db, root| {
+            let validated = validate_path_within_cwd_canonical(&path, root).map_err(mcp_err)?;
             debug!(path = %validated.display(), force, "indexing directory");
 
-            let db = db.lock().map_err(|_| mcp_err("database lock poisoned"))?;
-            let result = indexer::index_directory(&db, &validated, force)
+            let hooks = project_hooks(root);
+            let run = hooks.start(db).map_err(mcp_err)?;
+            let result = db
+                .index(&validated, force)
+                .map_err(|e| mcp_err(format!("indexing failed: {e}")))?;
+            hooks.spawn(
+                hooks
+                    .finish(run, db, &validated, &result)
+                    .map_err(mcp_err)?,
+            );
+
+            serde_json::to_string_pretty(&result)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Re-index files an editor reports as changed.
+    #[tool(
+        description = "Re-index specific files right after an editor saves, creates or deletes them. Deterministic alternative to filesystem watching where file events are unreliable (Docker volumes, network mounts). Deleted files are removed from the index."
+    )]
+    async fn cartog_notify_file_changed(
+        &self,
+        Parameters(params): Parameters<NotifyFileChangedParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let paths = params.paths;
+
+        self.update(params.project, move |db, root| {
+            let files = paths
+                .iter()
+                .map(|p| {
+                    let validated = validate_path_within_cwd_canonical(p, root).map_err(mcp_err)?;
+                    Ok(validated
+                        .strip_prefix(root)
+                        .map(Path::to_path_buf)
+                        .unwrap_or(validated))
+                })
+                .collect::<Result<Vec<_>, McpError>>()?;
+            debug!(files = files.len(), "notify file changed");
+
+            let hooks = project_hooks(root);
+            let run = hooks.start(db).map_err(mcp_err)?;
+            let result = db
+                .index_files(root, &files)
                 .map_err(|e| mcp_err(format!("indexing failed: {e}")))?;
+            hooks.spawn(hooks.finish(run, db, root, &result).map_err(mcp_err)?);
 
-            let json = serde_json::to_string_pretty(&result)
-                .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
-            Ok(CallToolResult::success(vec![Content::text(json)]))
+            serde_json::to_string_pretty(&result)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
         })
         .await
-        .map_err(|e| mcp_err(format!("task join failed: {e}")))?
     }
 
     /// Show symbols and structure of a file without reading its content.
     #[tool(
-        description = "Show symbols and structure of a file (functions, classes, methods, imports with line ranges). Use instead of reading the file when you need structure, not content."
+        description = "Show symbols and structure of a file (functions, classes, methods, imports with line ranges). Use instead of reading the file when you need structure, not content. public_only and signature_only cut it down to a minimal API skeleton."
     )]
     async fn cartog_outline(
         &self,
         Parameters(params): Parameters<OutlineParams>,
     ) -> Result<CallToolResult, McpError> {
         let file = params.file;
-        let db = Arc::clone(&self.db);
+        let (public_only, signature_only) = (params.public_only, params.signature_only);
 
-        tokio::task::spawn_blocking(move || {
-            debug!(file = %file, "outline");
-            let db = db.lock().map_err(|_| mcp_err("database lock poisoned"))?;
-            let symbols = db
+        self.query(params.project, move |db, _| {
+            debug!(file = %file, public_only, signature_only, "outline");
+            let mut symbols = db
                 .outline(&file)
                 .map_err(|e| mcp_err(format!("outline query failed: {e}")))?;
+            if public_only {
+                symbols = skeleton::public_only(symbols);
+            }
 
-            let json = serde_json::to_string_pretty(&symbols)
-                .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
-            json_response(&db, json)
+            let json = if signature_only {
+                serde_json::to_string_pretty(&skeleton::signature_lines(&symbols))
+            } else {
+                serde_json::to_string_pretty(&symbols)
+            };
+            json.map_err(|e| mcp_err(format!("serialization failed: {e}")))
         })
         .await
-        .map_err(|e| mcp_err(format!("task join failed: {e}")))?
     }
 
-    /// Find all references to a symbol (calls, imports, inherits, type references, raises).
+    /// Return the source of a symbol's definitions.
     #[tool(
-        description = "Find all references to a symbol. Returns call sites, imports, inheritance, type annotations, and raise/rescue usages. Optionally filter by kind: calls, imports, inherits, references, raises."
+        description = "Return the exact source of a symbol's definitions (one per matching name, or only the one in `file`), with signature, docstring and line range, plus `context` lines around it. \
+                       Use instead of reading a whole file when you need one function or class. \
+                       `stale: true` means the file changed since indexing: `code` is the indexed version, without context."
     )]
-    async fn cartog_refs(
+    async fn cartog_show(
         &self,
-        Parameters(params): Parameters<RefsParams>,
+        Parameters(params): Parameters<ShowParams>,
     ) -> Result<CallToolResult, McpError> {
-        let name = params.name;
-        let kind_str = params.kind;
-        let db = Arc::clone(&self.db);
-
-        tokio::task::spawn_blocking(move || {
-            let kind_filter = kind_str
-                .as_deref()
-                .map(|s| {
-                    s.parse::<EdgeKind>().map_err(|_| {
-                        mcp_err(format!(
-                            "invalid edge kind '{s}'. \
-                             Valid: calls, imports, inherits, references, raises"
-                        ))
-                    })
-                })
-                .transpose()?;
-
-            debug!(name = %name, kind = ?kind_filter, "refs");
-            let db = db.lock().map_err(|_| mcp_err("database lock poisoned"))?;
-            let results = db
-                .refs(&name, kind_filter)
-                .map_err(|e| mcp_err(format!("refs query failed: {e}")))?;
+        let (name, file, context) = (params.name, params.file, params.context);
+        let policy = Arc::clone(&self.policy);
+
+        self.query(params.project, move |db, root| {
+            debug!(name = %name, file = ?file, context, "show");
+            let mut sources = source::symbol_sources(db, root, &name, file.as_deref(), context)
+                .map_err(|e| mcp_err(format!("show query failed: {e}")))?;
+            policy.enforce(
+                "cartog_show",
+                root,
+                &mut sources,
+                |s| s.symbol.file_path.as_str(),
+                |s| s.code.as_mut(),
+            );
+
+            serde_json::to_string_pretty(&sources)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
 
-            let entries: Vec<RefEntry> = results
-                .into_iter()
-                .map(|(edge, sym)| RefEntry { edge, source: sym })
-                .collect();
+    /// Bundle a symbol with its callers, callees and related code under a token budget.
+    #[tool(
+        description = "Assemble the context to work on a symbol in one call, fitted to a token budget (default 4000): \
+                       its signature and code, one line per caller and callee, and related code from search. \
+                       `query` is a symbol name, or a search query whose best hit becomes the subject. \
+                       Code that doesn't fit is cut by lines (`truncated_lines`) and entries that don't fit are counted under `omitted`."
+    )]
+    async fn cartog_context(
+        &self,
+        Parameters(params): Parameters<ContextParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let query = params.query;
+        let budget = params.budget.unwrap_or(4000);
+        let mode = if params.keyword_only.unwrap_or(false) {
+            rag::search::SearchMode::Keyword
+        } else {
+            rag::search::SearchMode::Hybrid
+        };
+        let policy = Arc::clone(&self.policy);
+        let lazy = self.embed_policy == EmbedPolicy::Lazy;
+
+        self.query(params.project, move |db, root| {
+            debug!(query = %query, budget, ?mode, "context");
+            if lazy && mode == rag::search::SearchMode::Hybrid && db.ensure_rag_supported().is_ok()
+            {
+                catch_up_embeddings(db);
+            }
+            let mut pack = context::build(db, root, &query, budget, mode)
+                .map_err(|e| mcp_err(format!("context query failed: {e}")))?;
+            if let Some(pack) = &mut pack {
+                policy
+                    .check(root, &pack.target.symbol.file_path)
+                    .map_err(mcp_err)?;
+                policy.cap(&mut pack.target.content);
+                policy.enforce(
+                    "cartog_context",
+                    root,
+                    &mut pack.related,
+                    |item| item.symbol.file_path.as_str(),
+                    |item| Some(&mut item.content),
+                );
+            }
 
-            let json = serde_json::to_string_pretty(&entries)
-                .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
-            json_response(&db, json)
+            serde_json::to_string_pretty(&pack)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
         })
         .await
-        .map_err(|e| mcp_err(format!("task join failed: {e}")))?
     }
 
-    /// Find what a symbol calls.
+    /// Check a symbol's byte offsets against the file before editing it.
     #[tool(
-        description = "Find what a symbol calls. Returns all outgoing call edges from functions/methods matching the given name."
+        description = "Check that a symbol's byte offsets still hold the code you read before applying an edit by offset. \
+                       Pass the symbol ID and the `hash` cartog_show returned. `status`: valid (edit at `range`), \
+                       moved (the code is intact but shifted: edit at the updated `range`), changed (the code differs: re-read it first) \
+                       or missing (the symbol or its file is gone)."
     )]
-    async fn cartog_callees(
+    async fn cartog_validate_range(
         &self,
-        Parameters(params): Parameters<CalleesParams>,
+        Parameters(params): Parameters<ValidateRangeParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let (symbol_id, expected_hash) = (params.symbol_id, params.expected_hash);
+
+        self.update(params.project, move |db, root| {
+            debug!(symbol_id = %symbol_id, "validate range");
+            let check = source::validate_range(db, root, &symbol_id, &expected_hash)
+                .map_err(|e| mcp_err(format!("validate_range failed: {e}")))?;
+            serde_json::to_string_pretty(&check)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Find all references to a symbol (calls, imports, inherits, type references, raises).
+    #[tool(
+        description = "Find all references to a symbol. Returns call sites, imports, inheritance, type annotations, and raise/rescue usages, nearest first (same file, same directory, same package, elsewhere). Optionally filter by kind: calls, imports, inherits, references, raises."
+    )]
+    async fn cartog_refs(
+        &self,
+        Parameters(params): Parameters<RefsParams>,
     ) -> Result<CallToolResult, McpError> {
         let name = params.name;
-        let db = Arc::clone(&self.db);
+        let kind_filter = params
+            .kind
+            .as_deref()
+            .map(|s| {
+                s.parse::<EdgeKind>().map_err(|_| {
+                    mcp_err(format!(
+                        "invalid edge kind '{s}'. \
+                         Valid: calls, imports, inherits, references, raises"
+                    ))
+                })
+            })
+            .transpose()?;
 
-        tokio::task::spawn_blocking(move || {
-            debug!(name = %name, "callees");
-            let db = db.lock().map_err(|_| mcp_err("database lock poisoned"))?;
-            let edges = db
-                .callees(&name)
+        let target_kind = parse_target_kind(params.target_kind.as_deref())?;
+        let lang = parse_lang(params.lang)?;
+        let path_glob = params.path_glob;
+        let group = params.group;
+
+        self.query(params.project, move |db, _| {
+            debug!(name = %name, kind = ?kind_filter, path_glob = ?path_glob, target_kind = ?target_kind, lang = ?lang, group, "refs");
+            let results = db
+                .refs_in(&name, kind_filter, path_glob.as_deref(), target_kind, lang.as_deref())
+                .map_err(|e| mcp_err(format!("refs query failed: {e}")))?;
+
+            if group {
+                let groups = overloads::group_refs(db, results)
+                    .map_err(|e| mcp_err(format!("refs query failed: {e}")))?;
+                return serde_json::to_string_pretty(&groups)
+                    .map_err(|e| mcp_err(format!("serialization failed: {e}")));
+            }
+            let entries: Vec<Reference> = results
+                .into_iter()
+                .map(|(edge, source)| Reference { edge, source })
+                .collect();
+
+            serde_json::to_string_pretty(&entries)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Find what a symbol calls.
+    #[tool(
+        description = "Find what a symbol calls. Returns all outgoing call edges from functions/methods matching the given name, \
+                       each with the resolved `target` symbol (file, line, signature) when known. \
+                       Set lines=true to include the source text of each call line."
+    )]
+    async fn cartog_callees(
+        &self,
+        Parameters(params): Parameters<CalleesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let name = params.name;
+        let lines = params.lines.unwrap_or(false);
+        let lang = parse_lang(params.lang)?;
+        let policy = Arc::clone(&self.policy);
+
+        self.query(params.project, move |db, root| {
+            debug!(name = %name, lines, lang = ?lang, "callees");
+            let mut sites = db
+                .call_sites(&name, lines, lang.as_deref())
                 .map_err(|e| mcp_err(format!("callees query failed: {e}")))?;
+            if lines {
+                policy.enforce(
+                    "cartog_callees",
+                    root,
+                    &mut sites,
+                    |s| s.edge.file_path.as_str(),
+                    |s| s.line_text.as_mut(),
+                );
+            }
 
-            let json = serde_json::to_string_pretty(&edges)
-                .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
-            json_response(&db, json)
+            serde_json::to_string_pretty(&sites)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Find what calls a symbol.
+    #[tool(
+        description = "Find what calls a symbol (reverse of cartog_callees). Returns each call edge to the given name, \
+                       with the `caller` function or method making it (file, line range, signature). \
+                       Narrower than cartog_refs: only calls, and only from resolved caller symbols."
+    )]
+    async fn cartog_callers(
+        &self,
+        Parameters(params): Parameters<CallersParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let (name, lang) = (params.name, params.lang);
+
+        self.query(params.project, move |db, _| {
+            debug!(name = %name, lang = ?lang, "callers");
+            let callers = db
+                .callers(&name, lang.as_deref())
+                .map_err(|e| mcp_err(format!("callers query failed: {e}")))?;
+
+            serde_json::to_string_pretty(&callers)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
         })
         .await
-        .map_err(|e| mcp_err(format!("task join failed: {e}")))?
     }
 
     /// Transitive impact analysis — what breaks if this symbol changes?
     #[tool(
-        description = "Transitive impact analysis. Shows everything that transitively depends on a symbol up to N hops. Use before refactoring to assess blast radius."
+        description = "Transitive impact analysis. Shows everything that transitively depends on a symbol up to N hops. Use before refactoring to assess blast radius. \
+                       Calls made under a condition (an if branch, a match arm, a feature gate) are marked conditional and listed after the others at each depth. \
+                       Set tree=true to group the hops by depth, file and symbol, or summary=true for only the count of impacted symbols per file (smallest output)."
     )]
     async fn cartog_impact(
         &self,
@@ -371,26 +1266,112 @@ impl CartogServer {
     ) -> Result<CallToolResult, McpError> {
         let name = params.name;
         let depth = params.depth.unwrap_or(3).min(MAX_IMPACT_DEPTH);
-        let db = Arc::clone(&self.db);
+        let target_kind = parse_target_kind(params.target_kind.as_deref())?;
+        let lang = parse_lang(params.lang)?;
+        let (tree, summary) = (params.tree, params.summary);
 
-        tokio::task::spawn_blocking(move || {
-            debug!(name = %name, depth, "impact");
-            let db = db.lock().map_err(|_| mcp_err("database lock poisoned"))?;
+        self.query(params.project, move |db, _| {
+            debug!(name = %name, depth, target_kind = ?target_kind, lang = ?lang, tree, summary, "impact");
             let results = db
-                .impact(&name, depth)
+                .impact_of(&name, depth, target_kind, lang.as_deref())
                 .map_err(|e| mcp_err(format!("impact query failed: {e}")))?;
 
+            let json = if summary {
+                serde_json::to_string_pretty(&impact::impact_summary(&results))
+            } else if tree {
+                serde_json::to_string_pretty(&impact::impact_tree(&results))
+            } else {
+                let entries: Vec<ImpactEntry> = results
+                    .into_iter()
+                    .map(|(edge, d)| ImpactEntry { edge, depth: d })
+                    .collect();
+                serde_json::to_string_pretty(&entries)
+            };
+            json.map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Find symbols that can raise an exception or error type.
+    #[tool(
+        description = "Who can raise an exception or error type: symbols that raise/throw/return it directly (depth 1), then their callers up to N hops. Covers Python/Ruby raise, TS/JS and C# throw, Rust Err/map_err/? and Go returned errors."
+    )]
+    async fn cartog_raises(
+        &self,
+        Parameters(params): Parameters<RaisesParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let name = params.name;
+        let depth = params.depth.unwrap_or(3).min(MAX_IMPACT_DEPTH);
+
+        self.query(params.project, move |db, _| {
+            debug!(name = %name, depth, "raises");
+            let results = db
+                .raises(&name, depth)
+                .map_err(|e| mcp_err(format!("raises query failed: {e}")))?;
+
             let entries: Vec<ImpactEntry> = results
                 .into_iter()
                 .map(|(edge, d)| ImpactEntry { edge, depth: d })
                 .collect();
 
-            let json = serde_json::to_string_pretty(&entries)
-                .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
-            json_response(&db, json)
+            serde_json::to_string_pretty(&entries)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Call and reference paths between two symbols.
+    #[tool(
+        description = "How one symbol reaches another: call and reference paths from `from` to `to`, shortest first, up to a depth limit. Each step lists the symbol and the edge (kind, line) leading to it; by_name marks unresolved calls followed because a single symbol has the name. Replaces chains of cartog_callees calls."
+    )]
+    async fn cartog_path(
+        &self,
+        Parameters(params): Parameters<PathParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let depth = params
+            .depth
+            .unwrap_or(callpath::DEFAULT_DEPTH)
+            .min(MAX_IMPACT_DEPTH);
+        let limit = params.limit.unwrap_or(5) as usize;
+
+        self.query(params.project, move |db, _| {
+            debug!(from = %params.from, to = %params.to, depth, "path");
+            let paths = callpath::find_paths(db, &params.from, &params.to, depth, limit)
+                .map_err(|e| mcp_err(format!("path query failed: {e}")))?;
+
+            serde_json::to_string_pretty(&paths)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Combine the symbols of several queries.
+    #[tool(
+        description = "Intersect, union or diff the symbols of several queries in one call, e.g. symbols matching `login` that also reference `check`: op=intersect, queries=[{\"search\": \"login\"}, {\"refs\": \"check\"}]. Use {\"ids\": [...]} to reuse symbol IDs from earlier results. Results keep the first query's order."
+    )]
+    async fn cartog_set(
+        &self,
+        Parameters(params): Parameters<SetParams>,
+    ) -> Result<CallToolResult, McpError> {
+        if params.queries.len() < 2 {
+            return Err(mcp_err("cartog_set needs at least two queries"));
+        }
+
+        self.query(params.project, move |db, _| {
+            debug!(op = ?params.op, queries = params.queries.len(), "set");
+            let sets = params
+                .queries
+                .iter()
+                .map(|query| query.symbol_ids(db))
+                .collect::<anyhow::Result<Vec<_>>>()
+                .map_err(|e| mcp_err(format!("set query failed: {e}")))?;
+            let result = setops::evaluate(db, params.op, &sets)
+                .map_err(|e| mcp_err(format!("set lookup failed: {e}")))?;
+
+            serde_json::to_string_pretty(&result)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
         })
         .await
-        .map_err(|e| mcp_err(format!("task join failed: {e}")))?
     }
 
     /// Show inheritance hierarchy for a class.
@@ -402,11 +1383,9 @@ impl CartogServer {
         Parameters(params): Parameters<HierarchyParams>,
     ) -> Result<CallToolResult, McpError> {
         let name = params.name;
-        let db = Arc::clone(&self.db);
 
-        tokio::task::spawn_blocking(move || {
+        self.query(params.project, move |db, _| {
             debug!(name = %name, "hierarchy");
-            let db = db.lock().map_err(|_| mcp_err("database lock poisoned"))?;
             let pairs = db
                 .hierarchy(&name)
                 .map_err(|e| mcp_err(format!("hierarchy query failed: {e}")))?;
@@ -416,12 +1395,10 @@ impl CartogServer {
                 .map(|(child, parent)| HierarchyEntry { child, parent })
                 .collect();
 
-            let json = serde_json::to_string_pretty(&entries)
-                .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
-            json_response(&db, json)
+            serde_json::to_string_pretty(&entries)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
         })
         .await
-        .map_err(|e| mcp_err(format!("task join failed: {e}")))?
     }
 
     /// File-level import dependencies.
@@ -433,21 +1410,17 @@ impl CartogServer {
         Parameters(params): Parameters<DepsParams>,
     ) -> Result<CallToolResult, McpError> {
         let file = params.file;
-        let db = Arc::clone(&self.db);
 
-        tokio::task::spawn_blocking(move || {
+        self.query(params.project, move |db, _| {
             debug!(file = %file, "deps");
-            let db = db.lock().map_err(|_| mcp_err("database lock poisoned"))?;
             let edges = db
                 .file_deps(&file)
                 .map_err(|e| mcp_err(format!("deps query failed: {e}")))?;
 
-            let json = serde_json::to_string_pretty(&edges)
-                .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
-            json_response(&db, json)
+            serde_json::to_string_pretty(&edges)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
         })
         .await
-        .map_err(|e| mcp_err(format!("task join failed: {e}")))?
     }
 
     /// Search for symbols by name — use this to discover exact names before calling refs/callees/impact.
@@ -462,66 +1435,280 @@ impl CartogServer {
         Parameters(params): Parameters<SearchParams>,
     ) -> Result<CallToolResult, McpError> {
         let query = params.query;
-        let kind_str = params.kind;
         let file = params.file;
+        let lang = parse_lang(params.lang)?;
         let limit = params.limit.unwrap_or(30).min(MAX_SEARCH_LIMIT);
-        let db = Arc::clone(&self.db);
-        let cwd = Arc::clone(&self.cwd);
-
-        tokio::task::spawn_blocking(move || {
-            if query.is_empty() {
-                return Err(mcp_err("query cannot be empty"));
-            }
+        let include_anchors = params.include_anchors;
+        let collapse = params.collapse;
+        let group = params.group;
+        let context = params.context;
 
-            let kind_filter = kind_str
-                .as_deref()
-                .map(|s| {
-                    s.parse::<crate::types::SymbolKind>().map_err(|_| {
-                        mcp_err(
-                            "invalid symbol kind. Valid: function, class, method, variable, import",
-                        )
-                    })
+        if query.is_empty() {
+            return Err(mcp_err("query cannot be empty"));
+        }
+        let kind_filter = params
+            .kind
+            .as_deref()
+            .map(|s| {
+                s.parse::<crate::types::SymbolKind>().map_err(|_| {
+                    mcp_err("invalid symbol kind. Valid: function, class, method, variable, import")
                 })
-                .transpose()?;
+            })
+            .transpose()?;
 
-            // Validate file path is within CWD — consistent with cartog_outline / cartog_deps.
+        self.query(params.project, move |db, root| {
+            // Validate file path is within the project — consistent with cartog_outline / cartog_deps.
             let validated_file: Option<String> = file
+                .as_deref()
                 .map(|f| {
-                    validate_path_within_cwd_canonical(&f, &cwd)
+                    validate_path_within_cwd_canonical(f, root)
                         .map_err(mcp_err)
                         .map(|p| p.to_string_lossy().into_owned())
                 })
                 .transpose()?;
             let file_filter = validated_file.as_deref();
-            debug!(query = %query, kind = ?kind_filter, limit, "search");
-            let db = db.lock().map_err(|_| mcp_err("database lock poisoned"))?;
-            let symbols = db
-                .search(&query, kind_filter, file_filter, limit)
-                .map_err(|e| mcp_err(format!("search failed: {e}")))?;
+            debug!(query = %query, kind = ?kind_filter, lang = ?lang, limit, "search");
+            let fetch = if collapse || group {
+                MAX_SEARCH_LIMIT
+            } else {
+                limit
+            };
+            let mut matches: Vec<SymbolMatch> = db
+                .search_in(&query, kind_filter, file_filter, lang.as_deref(), fetch)
+                .map_err(|e| mcp_err(format!("search failed: {e}")))?
+                .into_iter()
+                .map(|sym| SymbolMatch::new(sym, &query))
+                .collect();
+            if collapse {
+                matches = collapse_similar(matches);
+                matches.truncate(limit as usize);
+            }
+            db.attach_notes(&mut matches, unix_now())
+                .map_err(|e| mcp_err(format!("note lookup failed: {e}")))?;
+            if context {
+                db.attach_parent_docs(&mut matches)
+                    .map_err(|e| mcp_err(format!("docstring lookup failed: {e}")))?;
+            }
+            let symbols = if group {
+                let mut groups = overloads::group_matches(db, matches)
+                    .map_err(|e| mcp_err(format!("search failed: {e}")))?;
+                groups.truncate(limit as usize);
+                serde_json::to_value(groups)
+            } else {
+                serde_json::to_value(matches)
+            }
+            .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
+
+            if include_anchors {
+                let anchors = db
+                    .anchors(file_filter, None, Some(&query), limit)
+                    .map_err(|e| mcp_err(format!("anchor query failed: {e}")))?;
+                serde_json::to_string_pretty(&SearchWithAnchors { symbols, anchors })
+            } else {
+                serde_json::to_string_pretty(&symbols)
+            }
+            .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
 
-            let json = serde_json::to_string_pretty(&symbols)
-                .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
-            json_response(&db, json)
+    /// List anchor comments (TODO/FIXME/HACK/XXX/SAFETY).
+    #[tool(
+        description = "List TODO/FIXME/HACK/XXX/SAFETY comments with file, line, text and enclosing symbol. \
+                       Filter by path prefix (file or directory) and tag. \
+                       Use to answer questions like 'unresolved FIXMEs in the auth module'."
+    )]
+    async fn cartog_todos(
+        &self,
+        Parameters(params): Parameters<TodosParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let limit = params.limit.unwrap_or(100);
+        let path = params.path;
+        let tag = params.tag;
+        let policy = Arc::clone(&self.policy);
+
+        self.query(params.project, move |db, root| {
+            debug!(path = ?path, tag = ?tag, "todos");
+            let mut anchors = db
+                .anchors(path.as_deref(), tag.as_deref(), None, limit)
+                .map_err(|e| mcp_err(format!("anchor query failed: {e}")))?;
+            policy.enforce(
+                "cartog_todos",
+                root,
+                &mut anchors,
+                |a| a.file_path.as_str(),
+                |a| Some(&mut a.text),
+            );
+
+            serde_json::to_string_pretty(&anchors)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Search string literals holding SQL, HTML or regexes.
+    #[tool(
+        description = "Search string literals that hold SQL, HTML or regexes, with file, line, language and enclosing symbol. \
+                       Every word of query must appear in the literal. Only available once the index was built with \
+                       `cartog index --injections`. Use to answer questions like 'which functions query the users table'."
+    )]
+    async fn cartog_injections(
+        &self,
+        Parameters(params): Parameters<InjectionsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let limit = params.limit.unwrap_or(100);
+        let query = params.query;
+        let lang = params.lang;
+        let path = params.path;
+        let policy = Arc::clone(&self.policy);
+
+        self.query(params.project, move |db, root| {
+            debug!(query = ?query, lang = ?lang, path = ?path, "injections");
+            if !db
+                .scan_injections()
+                .map_err(|e| mcp_err(format!("failed to read index settings: {e}")))?
+            {
+                return Err(mcp_err(
+                    "string literals are not scanned; run `cartog index --injections` first",
+                ));
+            }
+            let mut injections = db
+                .injections(query.as_deref(), lang.as_deref(), path.as_deref(), limit)
+                .map_err(|e| mcp_err(format!("injection query failed: {e}")))?;
+            policy.enforce(
+                "cartog_injections",
+                root,
+                &mut injections,
+                |i| i.file_path.as_str(),
+                |i| Some(&mut i.text),
+            );
+
+            serde_json::to_string_pretty(&injections)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Tests exercising a symbol.
+    #[tool(
+        description = "List the tests that exercise a symbol, directly or through non-test helpers, with the hop count. \
+                       Tests are recognized by file (tests/, test_*.py, *_test.go, *.spec.ts) and name (test_*, Test*, mod tests). \
+                       Use after a change to pick which tests to run."
+    )]
+    async fn cartog_tests_for(
+        &self,
+        Parameters(params): Parameters<TestsForParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let depth = params.depth.unwrap_or(3).min(MAX_IMPACT_DEPTH);
+        let name = params.name;
+
+        self.query(params.project, move |db, _| {
+            debug!(name = %name, depth, "tests_for");
+            let hits = testmap::tests_for(db, &name, depth)
+                .map_err(|e| mcp_err(format!("tests-for query failed: {e}")))?;
+
+            serde_json::to_string_pretty(&hits)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Public symbols no test references.
+    #[tool(
+        description = "List public functions, methods and classes with no incoming edge from test code. \
+                       Filter by path prefix (file or directory)."
+    )]
+    async fn cartog_untested(
+        &self,
+        Parameters(params): Parameters<UntestedParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let limit = params.limit.unwrap_or(100) as usize;
+        let dir = params.dir;
+
+        self.query(params.project, move |db, _| {
+            debug!(dir = ?dir, "untested");
+            let mut symbols = testmap::untested(db, dir.as_deref())
+                .map_err(|e| mcp_err(format!("untested query failed: {e}")))?;
+            symbols.truncate(limit);
+
+            serde_json::to_string_pretty(&symbols)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
         })
         .await
-        .map_err(|e| mcp_err(format!("task join failed: {e}")))?
     }
 
     /// Index statistics summary.
     #[tool(
         description = "Show index statistics: file count, symbol count, edge count, resolution rate, breakdown by language and symbol kind."
     )]
-    async fn cartog_stats(&self) -> Result<CallToolResult, McpError> {
-        let db = Arc::clone(&self.db);
-
-        tokio::task::spawn_blocking(move || {
+    async fn cartog_stats(
+        &self,
+        Parameters(params): Parameters<StatsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        self.query(params.project, |db, _| {
             debug!("stats");
-            let db = db.lock().map_err(|_| mcp_err("database lock poisoned"))?;
             let stats = db
                 .stats()
                 .map_err(|e| mcp_err(format!("stats query failed: {e}")))?;
 
-            let json = serde_json::to_string_pretty(&stats)
+            serde_json::to_string_pretty(&stats)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Outline digests of files changed since a generation, for client-side mirrors.
+    #[tool(
+        description = "List per-file outline digests of the files (re)indexed since an index generation, \
+                       and the files removed since. Pass the returned `generation` as `since` next time \
+                       and refetch only outlines whose digest changed (or set `outlines`)."
+    )]
+    async fn cartog_sync(
+        &self,
+        Parameters(params): Parameters<SyncParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let (since, files, outlines) = (params.since, params.files, params.outlines);
+
+        self.query(params.project, move |db, _| {
+            debug!(since = ?since, files = files.len(), "sync");
+            let delta = sync::sync(db, since, &files, outlines)
+                .map_err(|e| mcp_err(format!("sync query failed: {e}")))?;
+
+            serde_json::to_string_pretty(&delta)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Projects served by this server.
+    #[tool(
+        description = "List the projects this server answers for, with their root and index size. \
+                       Pass a project's name as `project` to any tool to query only that repository; \
+                       queries without it cover every project and label each result with its `project`."
+    )]
+    async fn cartog_list_projects(&self) -> Result<CallToolResult, McpError> {
+        let projects = Arc::clone(&self.projects);
+
+        tokio::task::spawn_blocking(move || {
+            debug!("list projects");
+            let entries = projects
+                .iter()
+                .map(|project| {
+                    let stats = project
+                        .lock()?
+                        .stats()
+                        .map_err(|e| mcp_err(format!("stats query failed: {e}")))?;
+                    Ok(ProjectEntry {
+                        name: project.name.clone(),
+                        root: project.root.display().to_string(),
+                        num_files: stats.num_files,
+                        num_symbols: stats.num_symbols,
+                        num_edges: stats.num_edges,
+                    })
+                })
+                .collect::<Result<Vec<_>, McpError>>()?;
+
+            let json = serde_json::to_string_pretty(&entries)
                 .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
             Ok(CallToolResult::success(vec![Content::text(json)]))
         })
@@ -529,6 +1716,210 @@ impl CartogServer {
         .map_err(|e| mcp_err(format!("task join failed: {e}")))?
     }
 
+    /// Index and model readiness.
+    #[tool(
+        description = "Check whether the index is ready before querying: index presence, schema version, \
+                       generation, file count, files new/modified/deleted since indexing (by modification time), \
+                       whether another process holds the write lock, and semantic search model state \
+                       (not_loaded, loading, ready, unavailable). `index_recommended` is true when the index is \
+                       missing, empty or stale."
+    )]
+    async fn cartog_health(
+        &self,
+        Parameters(params): Parameters<HealthParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let projects = Arc::clone(&self.projects);
+        self.query(params.project, move |db, root| {
+            debug!("health");
+            let db_path = projects
+                .iter()
+                .find(|p| *p.root == *root)
+                .map_or_else(|| root.join(DB_FILE), |p| p.db_path.to_path_buf());
+            let report = health::check(&db_path, root, Some(db))
+                .map_err(|e| mcp_err(format!("health check failed: {e}")))?;
+
+            serde_json::to_string_pretty(&report)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Online backup of the index database.
+    #[tool(
+        description = "Back up the index database to a file inside the project using SQLite's online backup API. Safe while the server or a watcher keeps writing: the copy is a consistent snapshot."
+    )]
+    async fn cartog_backup(
+        &self,
+        Parameters(params): Parameters<BackupParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let to = params.to;
+
+        self.update(params.project, move |db, root| {
+            let validated = validate_path_within_cwd_canonical(&to, root).map_err(mcp_err)?;
+            debug!(to = %validated.display(), "backup");
+
+            let result = db
+                .backup_to(&validated)
+                .map_err(|e| mcp_err(format!("backup failed: {e}")))?;
+
+            serde_json::to_string_pretty(&result)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Attach a scratch note to a symbol.
+    #[tool(
+        description = "Attach a short scratch note to a symbol, e.g. an intermediate finding in a multi-step task. \
+                       Notes appear in cartog_search results (`notes`) until they expire (ttl_hours, default 24). \
+                       They stay in the local index database, are never written to source files, and `cartog clean` deletes them."
+    )]
+    async fn cartog_annotate_symbol(
+        &self,
+        Parameters(params): Parameters<AnnotateSymbolParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let AnnotateSymbolParams {
+            name,
+            file,
+            note,
+            ttl_hours,
+            project,
+        } = params;
+        if note.trim().is_empty() {
+            return Err(mcp_err("note cannot be empty"));
+        }
+        if note.chars().count() > MAX_NOTE_CHARS {
+            return Err(mcp_err(format!(
+                "note is longer than {MAX_NOTE_CHARS} characters"
+            )));
+        }
+        let ttl_hours = ttl_hours.unwrap_or(24).clamp(1, MAX_NOTE_TTL_HOURS);
+
+        self.update(project, move |db, root| {
+            let file = file
+                .as_deref()
+                .map(|f| validate_relative_path(f, root))
+                .transpose()?;
+            let symbol = find_definition(db, &name, file.as_deref())?;
+            debug!(name = %name, file = %symbol.file_path, ttl_hours, "annotate symbol");
+
+            let note = db
+                .add_note(&symbol, &note, unix_now(), f64::from(ttl_hours) * 3600.0)
+                .map_err(|e| mcp_err(format!("failed to store note: {e}")))?;
+            serde_json::to_string_pretty(&note)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Reserve symbols for one agent.
+    #[tool(
+        description = "Reserve symbols before editing them, so agents working the same repository can split the work. \
+                       Returns `reserved` (now held by `owner`) and `conflicts` (held by another owner, with their reason and expiry). \
+                       Reservations are advisory: nothing blocks edits. They expire after ttl_minutes (default 30); \
+                       reserving again as the same owner extends them. Release them with cartog_release_symbols when done."
+    )]
+    async fn cartog_reserve_symbols(
+        &self,
+        Parameters(params): Parameters<ReserveSymbolsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let ReserveSymbolsParams {
+            names,
+            file,
+            owner,
+            reason,
+            ttl_minutes,
+            project,
+        } = params;
+        if names.is_empty() {
+            return Err(mcp_err("names cannot be empty"));
+        }
+        if owner.trim().is_empty() {
+            return Err(mcp_err("owner cannot be empty"));
+        }
+        let reason = reason.unwrap_or_default();
+        if reason.chars().count() > MAX_NOTE_CHARS {
+            return Err(mcp_err(format!(
+                "reason is longer than {MAX_NOTE_CHARS} characters"
+            )));
+        }
+        let ttl_minutes = ttl_minutes
+            .unwrap_or(30)
+            .clamp(1, MAX_RESERVATION_TTL_MINUTES);
+
+        self.update(project, move |db, root| {
+            let file = file
+                .as_deref()
+                .map(|f| validate_relative_path(f, root))
+                .transpose()?;
+            let symbols = names
+                .iter()
+                .map(|name| find_definition(db, name, file.as_deref()))
+                .collect::<Result<Vec<_>, _>>()?;
+            debug!(owner = %owner, symbols = symbols.len(), ttl_minutes, "reserve symbols");
+
+            let result = db
+                .reserve_symbols(
+                    &symbols,
+                    &owner,
+                    &reason,
+                    unix_now(),
+                    f64::from(ttl_minutes) * 60.0,
+                )
+                .map_err(|e| mcp_err(format!("failed to reserve symbols: {e}")))?;
+            serde_json::to_string_pretty(&result)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Release symbols reserved by an agent.
+    #[tool(
+        description = "Release symbols reserved with cartog_reserve_symbols by `owner`: those named in `names`, or all of them when omitted. \
+                       Returns the number of reservations released."
+    )]
+    async fn cartog_release_symbols(
+        &self,
+        Parameters(params): Parameters<ReleaseSymbolsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let ReleaseSymbolsParams {
+            owner,
+            names,
+            project,
+        } = params;
+
+        self.update(project, move |db, _| {
+            let released = db
+                .release_symbols(&owner, names.as_deref())
+                .map_err(|e| mcp_err(format!("failed to release symbols: {e}")))?;
+            debug!(owner = %owner, released, "release symbols");
+            serde_json::to_string_pretty(&serde_json::json!({ "released": released }))
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// List live symbol reservations.
+    #[tool(
+        description = "List the symbols currently reserved with cartog_reserve_symbols, oldest first, with owner, reason and expiry. \
+                       Pass `owner` to see one agent's reservations."
+    )]
+    async fn cartog_list_reservations(
+        &self,
+        Parameters(params): Parameters<ListReservationsParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let owner = params.owner;
+
+        self.query(params.project, move |db, _| {
+            let reservations = db
+                .reservations(owner.as_deref(), unix_now())
+                .map_err(|e| mcp_err(format!("reservation lookup failed: {e}")))?;
+            serde_json::to_string_pretty(&reservations)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
     /// Build embedding index for semantic code search.
     #[tool(
         description = "Build embedding index for semantic code search. Requires the embedding model to be downloaded first (run 'cartog rag setup' from CLI). Embeds all code symbols for vector similarity search."
@@ -539,81 +1930,375 @@ impl CartogServer {
     ) -> Result<CallToolResult, McpError> {
         let path = params.path;
         let force = params.force;
-        let db = Arc::clone(&self.db);
-        let cwd = Arc::clone(&self.cwd);
 
-        tokio::task::spawn_blocking(move || {
-            let validated = validate_path_within_cwd_canonical(&path, &cwd).map_err(mcp_err)?;
+        self.update(params.project, move |db, root| {
+            let validated = validate_path_within_cwd_canonical(&path, root).map_err(mcp_err)?;
             debug!(path = %validated.display(), force, "rag index");
 
-            let db = db.lock().map_err(|_| mcp_err("database lock poisoned"))?;
+            db.ensure_rag_supported().map_err(mcp_err)?;
 
             // Ensure the code graph index is up to date first
-            let _ = indexer::index_directory(&db, &validated, false)
+            let _ = db
+                .index(&validated, false)
                 .map_err(|e| mcp_err(format!("code graph indexing failed: {e}")))?;
 
-            let result = rag::indexer::index_embeddings(&db, force)
+            let result = rag::indexer::index_embeddings(db, force, Default::default())
                 .map_err(|e| mcp_err(format!("embedding indexing failed: {e}")))?;
 
-            let json = serde_json::to_string_pretty(&result)
-                .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
-            Ok(CallToolResult::success(vec![Content::text(json)]))
+            serde_json::to_string_pretty(&result)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Start an index run in the background and return its job right away.
+    #[tool(
+        description = "Start indexing in the background and return a job right away, for runs too long to wait on (full re-index with force, or rag=true to also build the embedding index). Poll it with cartog_job_status, stop it with cartog_cancel_job. One job runs per project at a time."
+    )]
+    async fn cartog_start_index(
+        &self,
+        Parameters(params): Parameters<StartIndexParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let path = params.path;
+        let force = params.force;
+        let rag = params.rag;
+        let jobs = Arc::clone(&self.jobs);
+        let db_path = self.target(params.project.as_deref())?.db_path;
+
+        self.update(params.project, move |_db, root| {
+            let validated = validate_path_within_cwd_canonical(&path, root).map_err(mcp_err)?;
+            debug!(path = %validated.display(), force, rag, "start index job");
+
+            let job = IndexJob {
+                path: validated,
+                force,
+                rag,
+            };
+            let job = jobs
+                .start(root, &db_path, job, project_hooks(root))
+                .map_err(|e| mcp_err(format!("failed to start job: {e}")))?;
+            serde_json::to_string_pretty(&job)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Status of a background index job, or of the recent ones.
+    #[tool(
+        description = "Status of a job started by cartog_start_index: `status` (running, done, failed, cancelled, interrupted), `phase` (index, then embed), `done`/`total` progress of the phase, and once over its `result` or `error`. Without job_id, lists the 10 most recent jobs."
+    )]
+    async fn cartog_job_status(
+        &self,
+        Parameters(params): Parameters<JobStatusParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let job_id = params.job_id;
+        let jobs = Arc::clone(&self.jobs);
+
+        self.update(params.project, move |db, root| {
+            let json = match job_id {
+                Some(id) => {
+                    let job = jobs
+                        .status(db, root, id)
+                        .map_err(|e| mcp_err(format!("failed to read job: {e}")))?
+                        .ok_or_else(|| mcp_err(format!("no job {id}")))?;
+                    serde_json::to_string_pretty(&job)
+                }
+                None => {
+                    let recent = jobs
+                        .recent(db, root, 10)
+                        .map_err(|e| mcp_err(format!("failed to read jobs: {e}")))?;
+                    serde_json::to_string_pretty(&recent)
+                }
+            };
+            json.map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Stop a running background index job.
+    #[tool(
+        description = "Cancel a running job started by cartog_start_index. It stops at its next batch of files or symbols (status becomes cancelled); what it stored so far stays indexed and the next index run completes it."
+    )]
+    async fn cartog_cancel_job(
+        &self,
+        Parameters(params): Parameters<CancelJobParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let job_id = params.job_id;
+        let jobs = Arc::clone(&self.jobs);
+
+        self.update(params.project, move |db, root| {
+            debug!(job_id, "cancel job");
+            let job = jobs.cancel(db, root, job_id).map_err(mcp_err)?;
+            serde_json::to_string_pretty(&job)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
         })
         .await
-        .map_err(|e| mcp_err(format!("task join failed: {e}")))?
     }
 
     /// Semantic search over code symbols using hybrid FTS5 + vector search.
     #[tool(
-        description = "Semantic search over code symbols. Combines keyword (FTS5/BM25) and vector similarity search with Reciprocal Rank Fusion. Returns ranked code symbols with content. Use for natural language queries about code functionality."
+        description = "Semantic search over code symbols. Combines keyword (FTS5/BM25) and vector similarity search with Reciprocal Rank Fusion. Falls back to keyword-only when no embedding model is available (result `mode` tells which was used). Returns ranked code symbols with content; re-ranked results carry a calibrated `relevance` (0-1) and those the re-ranker finds irrelevant are dropped, so fewer than `limit` may come back. Use `path`, `lang` and `exclude` to scope the search to a subsystem, `min_score` to keep only confident matches, and `budget` (tokens) to get full bodies only for the results worth their size and signature lines for the rest. Use for natural language queries about code functionality."
     )]
     async fn cartog_rag_search(
         &self,
         Parameters(params): Parameters<RagSearchParams>,
     ) -> Result<CallToolResult, McpError> {
         let query = params.query;
-        let kind_str = params.kind;
         let limit = params.limit.unwrap_or(10).min(MAX_SEARCH_LIMIT);
-        let db = Arc::clone(&self.db);
+        let mode = if params.keyword_only {
+            rag::search::SearchMode::Keyword
+        } else {
+            rag::search::SearchMode::Hybrid
+        };
+        let min_score = params.min_score;
+        let budget = params.budget;
 
-        tokio::task::spawn_blocking(move || {
-            if query.is_empty() {
-                return Err(mcp_err("query cannot be empty"));
+        if query.is_empty() {
+            return Err(mcp_err("query cannot be empty"));
+        }
+        if min_score.is_some_and(|m| !(0.0..=1.0).contains(&m)) {
+            return Err(mcp_err("min_score must be between 0 and 1"));
+        }
+        let kind_filter = params
+            .kind
+            .as_deref()
+            .map(|s| {
+                s.parse::<crate::types::SymbolKind>().map_err(|_| {
+                    mcp_err("invalid symbol kind. Valid: function, class, method, variable, import")
+                })
+            })
+            .transpose()?;
+        let lang = parse_lang(params.lang)?;
+        let (path, exclude) = (params.path, params.exclude);
+
+        let policy = Arc::clone(&self.policy);
+        let lazy = self.embed_policy == EmbedPolicy::Lazy;
+
+        self.query(params.project, move |db, root| {
+            debug!(query = %query, kind = ?kind_filter, path = ?path, lang = ?lang, exclude = ?exclude, limit, ?mode, "rag search");
+            db.ensure_rag_supported().map_err(mcp_err)?;
+            if lazy && mode == rag::search::SearchMode::Hybrid {
+                catch_up_embeddings(db);
             }
 
-            debug!(query = %query, kind = ?kind_str, limit, "rag search");
-            let db = db.lock().map_err(|_| mcp_err("database lock poisoned"))?;
-
-            let kind_filter = match kind_str {
-                Some(kind_s) => {
-                    let kind = kind_s.parse::<crate::types::SymbolKind>().map_err(|_| {
-                        mcp_err(
-                            "invalid symbol kind. Valid: function, class, method, variable, import",
-                        )
-                    })?;
-                    Some(kind)
-                }
-                None => None,
+            let filter = rag::search::SearchFilter {
+                kind: kind_filter,
+                path: path.as_deref(),
+                lang: lang.as_deref(),
+                exclude: exclude.as_deref(),
             };
+            let mut result = rag::search::search(db, &query, limit, filter, mode, min_score)
+                .map_err(|e| mcp_err(format!("semantic search failed: {e}")))?;
+            policy.enforce(
+                "cartog_rag_search",
+                root,
+                &mut result.results,
+                |r| r.symbol.file_path.as_str(),
+                |r| r.content.as_mut(),
+            );
+            if let Some(budget) = budget {
+                rag::pack::pack(&mut result, budget);
+            }
 
-            let result = rag::search::hybrid_search(&db, &query, limit, kind_filter)
+            serde_json::to_string_pretty(&result)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
+        })
+        .await
+    }
+
+    /// Semantic search for several queries at once, embedded in one model call.
+    #[tool(
+        description = "Run cartog_rag_search for several queries in one call. The queries are embedded together in a single model pass, which is much faster than separate calls when exploring a topic from several angles. Returns one entry per query, in order: `query` plus the same fields as cartog_rag_search. The filters, `limit`, `min_score` and `budget` apply to each query."
+    )]
+    async fn cartog_rag_search_batch(
+        &self,
+        Parameters(params): Parameters<RagSearchBatchParams>,
+    ) -> Result<CallToolResult, McpError> {
+        let queries = params.queries;
+        let limit = params.limit.unwrap_or(10).min(MAX_SEARCH_LIMIT);
+        let mode = if params.keyword_only {
+            rag::search::SearchMode::Keyword
+        } else {
+            rag::search::SearchMode::Hybrid
+        };
+        let min_score = params.min_score;
+        let budget = params.budget;
+
+        if queries.is_empty() || queries.iter().any(|q| q.is_empty()) {
+            return Err(mcp_err("queries must be non-empty"));
+        }
+        if queries.len() > MAX_BATCH_QUERIES {
+            return Err(mcp_err(format!(
+                "at most {MAX_BATCH_QUERIES} queries per batch"
+            )));
+        }
+        if min_score.is_some_and(|m| !(0.0..=1.0).contains(&m)) {
+            return Err(mcp_err("min_score must be between 0 and 1"));
+        }
+        let kind_filter = params
+            .kind
+            .as_deref()
+            .map(|s| {
+                s.parse::<crate::types::SymbolKind>().map_err(|_| {
+                    mcp_err("invalid symbol kind. Valid: function, class, method, variable, import")
+                })
+            })
+            .transpose()?;
+        let lang = parse_lang(params.lang)?;
+        let (path, exclude) = (params.path, params.exclude);
+
+        let policy = Arc::clone(&self.policy);
+        let lazy = self.embed_policy == EmbedPolicy::Lazy;
+
+        self.query(params.project, move |db, root| {
+            debug!(queries = queries.len(), kind = ?kind_filter, path = ?path, lang = ?lang, exclude = ?exclude, limit, ?mode, "rag search batch");
+            db.ensure_rag_supported().map_err(mcp_err)?;
+            if lazy && mode == rag::search::SearchMode::Hybrid {
+                catch_up_embeddings(db);
+            }
+
+            let refs: Vec<&str> = queries.iter().map(String::as_str).collect();
+            let filter = rag::search::SearchFilter {
+                kind: kind_filter,
+                path: path.as_deref(),
+                lang: lang.as_deref(),
+                exclude: exclude.as_deref(),
+            };
+            let results = rag::search::search_batch(db, &refs, limit, filter, mode, min_score)
                 .map_err(|e| mcp_err(format!("semantic search failed: {e}")))?;
+            let results: Vec<rag::search::QueryResults> = queries
+                .iter()
+                .zip(results)
+                .map(|(query, mut result)| {
+                    policy.enforce(
+                        "cartog_rag_search_batch",
+                        root,
+                        &mut result.results,
+                        |r| r.symbol.file_path.as_str(),
+                        |r| r.content.as_mut(),
+                    );
+                    if let Some(budget) = budget {
+                        rag::pack::pack(&mut result, budget);
+                    }
+                    rag::search::QueryResults {
+                        query: query.clone(),
+                        result,
+                    }
+                })
+                .collect();
 
-            let json = serde_json::to_string_pretty(&result)
-                .map_err(|e| mcp_err(format!("serialization failed: {e}")))?;
-            json_response(&db, json)
+            serde_json::to_string_pretty(&results)
+                .map_err(|e| mcp_err(format!("serialization failed: {e}")))
         })
         .await
-        .map_err(|e| mcp_err(format!("task join failed: {e}")))?
     }
 }
 
-#[tool_handler]
 impl ServerHandler for CartogServer {
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let tool = request.name.to_string();
+        let params = self.audit.as_ref().map(|_| {
+            request
+                .arguments
+                .as_ref()
+                .and_then(|args| serde_json::to_string(args).ok())
+                .unwrap_or_else(|| "{}".to_string())
+        });
+        let started = Instant::now();
+        let tcc = ToolCallContext::new(self, request, context);
+        let result = self.tool_router.call(tcc).await;
+        let elapsed = started.elapsed();
+        let ok = matches!(&result, Ok(r) if r.is_error != Some(true));
+        self.metrics.record(&tool, elapsed, ok);
+
+        if let (Some(audit), Some(params)) = (&self.audit, params) {
+            let hits = result.as_ref().map(returned_symbols).unwrap_or_default();
+            let entry = AuditEntry {
+                timestamp: unix_now(),
+                time: String::new(),
+                tool,
+                params,
+                result_count: result.as_ref().ok().and_then(result_count),
+                duration_ms: elapsed.as_millis() as u64,
+                ok,
+            };
+            let audit = Arc::clone(audit);
+            // Federated servers log to the first project's database.
+            let db = Arc::clone(&self.projects[0].db);
+            if let Err(e) =
+                tokio::task::spawn_blocking(move || audit.record(&db, &entry, &hits)).await
+            {
+                tracing::warn!(error = %e, "audit task failed");
+            }
+        }
+        result
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        Ok(ListToolsResult::with_all_items(self.tool_router.list_all()))
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let resources = schema::COMMANDS
+            .iter()
+            .map(|command| {
+                let mut resource = RawResource::new(
+                    schema::resource_uri(command),
+                    format!("{command} output schema"),
+                );
+                resource.description = Some(format!(
+                    "JSON Schema of `cartog {} --json` and the matching tool's result",
+                    schema::subcommand(command)
+                ));
+                resource.mime_type = Some(SCHEMA_MIME.to_string());
+                resource.no_annotation()
+            })
+            .collect();
+        Ok(ListResourcesResult::with_all_items(resources))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        let schema = request
+            .uri
+            .strip_prefix("cartog://schema/")
+            .and_then(schema::schema)
+            .ok_or_else(|| {
+                McpError::resource_not_found(format!("unknown resource: {}", request.uri), None)
+            })?;
+        let text = serde_json::to_string_pretty(&schema)
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: request.uri,
+                mime_type: Some(SCHEMA_MIME.to_string()),
+                text,
+            }],
+        })
+    }
+
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::LATEST,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_resources()
+                .build(),
             server_info: Implementation {
                 name: "cartog".into(),
                 version: env!("CARGO_PKG_VERSION").into(),
@@ -623,55 +2308,264 @@ impl ServerHandler for CartogServer {
                  (functions, classes, methods, imports) and edges (calls, imports, inherits, \
                  type references, raises) using tree-sitter, stored in SQLite.\n\n\
                   Workflow:\n\
+                  0. cartog_health tells whether the index exists and is current (index_recommended).\n\
                   1. Run cartog_index first to build/update the graph (use force=true if results seem stale).\n\
-                  2. Use cartog_search to discover symbol names by partial match before calling refs/callees/impact.\n\
+                  2. Use cartog_search to discover symbol names by partial match before calling refs/callees/callers/impact.\n\
                   3. Use cartog_outline instead of reading a file when you need structure, not content.\n\
-                  4. Use cartog_refs to find all usages of a symbol (filter with kind param).\n\
+                  4. Use cartog_refs to find all usages of a symbol (filter with kind param), cartog_callers for just its callers.\n\
                   5. Use cartog_impact before refactoring to assess blast radius.\n\
+                  Use cartog_raises to see which code paths can raise a given exception or error type.\n\
+                  Use cartog_path to see how one symbol reaches another instead of chaining cartog_callees.\n\
+                  Use cartog_set to intersect, union or diff the symbols of several queries in one call.\n\
                   6. Re-run cartog_index after making code changes to keep the graph current.\n\
-                  7. Only fall back to reading files when you need actual implementation logic.\n\n\
+                  7. Use cartog_show for one symbol's code; only fall back to reading files when you need more.\n\
+                  Use cartog_context to get a symbol's code, callers, callees and related code in one budgeted call.\n\
+                  Before editing a symbol by byte offset, pass its cartog_show `hash` to cartog_validate_range.\n\
+                  Use cartog_todos to list TODO/FIXME/HACK/SAFETY comments by path and tag.\n\
+                  Use cartog_injections to find SQL, HTML or regexes written in string literals.\n\
+                  Use cartog_tests_for after a change to find the tests worth running; cartog_untested lists untested public symbols.\n\
+                  Editor plugins can call cartog_notify_file_changed after saves instead of relying on --watch.\n\
+                  Use cartog_backup to snapshot the index database without stopping the server.\n\
+                  For long runs (force re-index, embeddings), cartog_start_index returns a job to poll with cartog_job_status.\n\
+                  Use cartog_annotate_symbol to keep intermediate findings on a symbol; cartog_search shows them as `notes`.\n\
+                  When several agents share the repository, cartog_reserve_symbols claims symbols before editing them \
+                  (advisory, with a TTL); cartog_list_reservations shows who holds what, cartog_release_symbols frees them.\n\
+                  If cartog_list_projects shows several projects, pass `project` to target one; \
+                  queries without it cover all projects and label each result with its `project`.\n\
+                  The resources cartog://schema/<command> hold the JSON Schema of each tool's result.\n\
+                  Each symbol carries the `generation` of the index run that last wrote it. Results mixing \
+                  generations were read while the watcher was re-indexing; retry once cartog_health's generation stops changing.\n\n\
                   Semantic search (if embedding model is installed):\n\
                   - Run cartog_rag_index to build the embedding index (after cartog_index).\n\
                   - Use cartog_rag_search for natural language queries about code functionality.\n\
-                  - Combines keyword (BM25) and vector similarity search for best results.\n\n\
-                 Supports: Python, TypeScript/JavaScript, Rust, Go, Ruby."
+                  - Use cartog_rag_search_batch to run several such queries in one call.\n\
+                  - Combines keyword (BM25) and vector similarity search for best results.\n\
+                  - Without a model it runs keyword-only (BM25); set keyword_only to force this.\n\
+                  - cartog_health shows whether the models have finished loading.\n\n\
+                 Supports: Python, TypeScript/JavaScript, Rust, Go, Ruby, C#."
                     .into(),
             ),
         }
     }
 }
 
+// ── Audit log ──
+
+/// Settings and pruning state for the opt-in tool-call audit log.
+struct AuditLog {
+    retention: Duration,
+    /// When entries older than `retention` were last deleted.
+    last_prune: Mutex<Option<Instant>>,
+}
+
+impl AuditLog {
+    /// How often expired entries are pruned while the server runs.
+    const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+    fn new(retention_days: u32) -> Self {
+        Self {
+            retention: Duration::from_secs(u64::from(retention_days) * 86_400),
+            last_prune: Mutex::new(None),
+        }
+    }
+
+    /// Insert `entry` and the symbols the call returned, pruning expired entries at
+    /// most once per `PRUNE_INTERVAL`. Failures are logged, never surfaced to the
+    /// MCP client.
+    fn record(&self, db: &Mutex<ShardedDatabase>, entry: &AuditEntry, hits: &[SymbolHit]) {
+        let Ok(db) = db.lock() else {
+            tracing::warn!("database lock poisoned, audit entry dropped");
+            return;
+        };
+        if let Err(e) = db.insert_audit_entry(entry) {
+            tracing::warn!(error = %e, "failed to write audit entry");
+        }
+        if let Err(e) = db.insert_symbol_hits(entry.timestamp, &entry.tool, hits) {
+            tracing::warn!(error = %e, "failed to write symbol hits");
+        }
+
+        let Ok(mut last_prune) = self.last_prune.lock() else {
+            return;
+        };
+        if last_prune.is_some_and(|t| t.elapsed() < Self::PRUNE_INTERVAL) {
+            return;
+        }
+        *last_prune = Some(Instant::now());
+        let cutoff = unix_now() - self.retention.as_secs_f64();
+        match db.prune_audit_log(cutoff) {
+            Ok(0) => {}
+            Ok(n) => debug!(removed = n, "pruned audit log"),
+            Err(e) => tracing::warn!(error = %e, "failed to prune audit log"),
+        }
+    }
+}
+
+/// The JSON value of a tool response.
+fn response_json(result: &CallToolResult) -> Option<serde_json::Value> {
+    let text = &result.content.as_ref()?.first()?.as_text()?.text;
+    // Responses may carry a trailing plain-text hint after the JSON value.
+    serde_json::Deserializer::from_str(text)
+        .into_iter::<serde_json::Value>()
+        .next()?
+        .ok()
+}
+
+/// Number of items in a tool response: the length of a top-level JSON array,
+/// or of a `results` array. `None` for other shapes.
+fn result_count(result: &CallToolResult) -> Option<u32> {
+    let value = response_json(result)?;
+    let items = match &value {
+        serde_json::Value::Array(items) => items,
+        serde_json::Value::Object(obj) => obj.get("results")?.as_array()?,
+        _ => return None,
+    };
+    Some(items.len() as u32)
+}
+
+/// Symbols anywhere in a tool response, once each: objects with the `id`,
+/// `name`, `kind` and `file_path` of a serialized [`crate::types::Symbol`].
+fn returned_symbols(result: &CallToolResult) -> Vec<SymbolHit> {
+    fn walk(value: &serde_json::Value, seen: &mut HashSet<SymbolHit>, hits: &mut Vec<SymbolHit>) {
+        match value {
+            serde_json::Value::Array(items) => items.iter().for_each(|v| walk(v, seen, hits)),
+            serde_json::Value::Object(obj) => {
+                let field = |key: &str| obj.get(key).and_then(serde_json::Value::as_str);
+                if let (Some(_), Some(name), Some(kind), Some(file_path)) = (
+                    field("id"),
+                    field("name"),
+                    field("kind"),
+                    field("file_path"),
+                ) {
+                    if let Ok(kind) = kind.parse() {
+                        let hit = SymbolHit {
+                            name: name.to_string(),
+                            file_path: file_path.to_string(),
+                            kind,
+                        };
+                        if seen.insert(hit.clone()) {
+                            hits.push(hit);
+                        }
+                    }
+                }
+                obj.values().for_each(|v| walk(v, seen, hits));
+            }
+            _ => {}
+        }
+    }
+    let mut hits = Vec::new();
+    if let Some(value) = response_json(result) {
+        walk(&value, &mut HashSet::new(), &mut hits);
+    }
+    hits
+}
+
+/// Options for [`run_server`].
+pub struct ServeConfig {
+    /// Keep the index fresh with a background file watcher.
+    pub watch: bool,
+    /// When embeddings catch up with index updates; `eager` needs `watch`.
+    pub rag: Option<EmbedPolicy>,
+    /// Serve Prometheus metrics on this address.
+    pub metrics_addr: Option<SocketAddr>,
+    /// Record tool calls in the audit log, keeping this many days of history.
+    pub audit_retention_days: Option<u32>,
+    /// `[NAME=]PATH` of each project to serve; empty serves the current directory.
+    pub projects: Vec<String>,
+    /// Load the embedding and re-ranker models in the background at startup, with `rag`.
+    pub preload_models: bool,
+    /// Named index to serve instead of each project's default (`--index`).
+    pub index: Option<String>,
+    /// Roots besides the projects' own that returned content may come from.
+    pub allow_roots: Vec<PathBuf>,
+    /// Cap on the content bytes of one returned result.
+    pub max_read_bytes: usize,
+}
+
+impl ServeConfig {
+    /// Whether to start the model preload thread: only for semantic search (`rag`).
+    fn preloads_models(&self) -> bool {
+        self.preload_models && self.rag.is_some()
+    }
+}
+
 /// Start the MCP server over stdio.
-///
-/// When `watch` is true, a background file watcher keeps the index fresh.
-/// When `rag` is true (requires `watch`), embeddings are also auto-updated.
-pub async fn run_server(watch: bool, rag: bool) -> anyhow::Result<()> {
+pub async fn run_server(config: ServeConfig) -> anyhow::Result<()> {
     info!("starting cartog MCP server v{}", env!("CARGO_PKG_VERSION"));
+    let embed_policy = config.rag.unwrap_or_default();
+    let rag = embed_policy == EmbedPolicy::Eager && config.watch;
+    if rag || embed_policy == EmbedPolicy::Lazy {
+        rag::ensure_models_enabled()?;
+    }
+    if embed_policy == EmbedPolicy::Eager && !config.watch {
+        tracing::warn!(
+            "`--rag eager` embeds after watcher updates: add --watch, or use `--rag lazy`"
+        );
+    }
+    #[cfg(not(feature = "watch"))]
+    if config.watch {
+        anyhow::bail!(
+            "`serve --watch` is not available: cartog was built without the `watch` feature \
+             (rebuild with `cargo install cartog --features watch`)"
+        );
+    }
 
-    // Optionally spawn a background file watcher
-    let _watch_handle: Option<WatchHandle> = if watch {
-        let cwd = std::env::current_dir()?;
-        let mut config = WatchConfig::new(cwd);
-        config.rag = rag;
-        match watch::spawn_watch(config, DB_FILE) {
-            Ok(handle) => {
-                info!(rag, "background file watcher started");
-                Some(handle)
-            }
-            Err(e) => {
-                tracing::warn!(error = %e, "failed to start background watcher, continuing without it");
-                None
+    if config.preloads_models() {
+        std::thread::Builder::new()
+            .name("cartog-preload".into())
+            .spawn(rag::search::preload_models)?;
+    }
+
+    let mut server = if config.projects.is_empty() {
+        CartogServer::new(config.index.as_deref())?
+    } else {
+        let projects: Vec<(String, PathBuf)> = config
+            .projects
+            .iter()
+            .map(|spec| parse_project_spec(spec))
+            .collect();
+        info!(projects = %projects.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(", "), "federating projects");
+        CartogServer::federated(&projects, config.index.as_deref())?
+    };
+
+    // Optionally spawn a background file watcher per project
+    #[cfg(feature = "watch")]
+    let _watch_handles: Vec<WatchHandle> = if config.watch {
+        let mut handles = Vec::new();
+        for project in server.projects.iter() {
+            let mut watch_config = WatchConfig::new(project.root.to_path_buf());
+            watch_config.rag = rag;
+            match watch::spawn_watch(watch_config, &project.db_path.to_string_lossy()) {
+                Ok(handle) => {
+                    info!(project = %project.name, rag, "background file watcher started");
+                    handles.push(handle);
+                }
+                Err(e) => {
+                    tracing::warn!(project = %project.name, error = %e, "failed to start background watcher, continuing without it");
+                }
             }
         }
+        handles
     } else {
-        None
+        Vec::new()
     };
 
-    let server = CartogServer::new()?;
+    server = server.with_path_policy(PathPolicy::new(&config.allow_roots, config.max_read_bytes)?);
+    if let Some(policy) = config.rag {
+        info!(policy = %policy, "embedding policy");
+        server = server.with_embed_policy(policy)?;
+    }
+    if let Some(days) = config.audit_retention_days {
+        info!(retention_days = days, "audit log enabled");
+        server = server.with_audit(days);
+    }
+    if let Some(addr) = config.metrics_addr {
+        let db_path = server.projects[0].db_path.to_path_buf();
+        metrics::spawn_metrics_server(addr, server.metrics(), db_path)?;
+    }
     let service = server.serve(stdio()).await?;
     service.waiting().await?;
 
-    // WatchHandle is dropped here, signaling the watcher thread to stop.
+    // WatchHandles are dropped here, signaling the watcher threads to stop.
     info!("cartog MCP server stopped");
     Ok(())
 }
@@ -679,6 +2573,242 @@ pub async fn run_server(watch: bool, rag: bool) -> anyhow::Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::Database;
+
+    // ── Audit log tests ──
+
+    #[test]
+    fn result_count_reads_array_and_results_list() {
+        let array = CallToolResult::success(vec![Content::text("[1, 2, 3]")]);
+        assert_eq!(result_count(&array), Some(3));
+
+        let hinted = CallToolResult::success(vec![Content::text(
+            "[]\n\n(Index is empty. Run cartog_index first to build the code graph.)",
+        )]);
+        assert_eq!(result_count(&hinted), Some(0));
+
+        let nested = CallToolResult::success(vec![Content::text(r#"{"results": [{}, {}]}"#)]);
+        assert_eq!(result_count(&nested), Some(2));
+
+        let stats = CallToolResult::success(vec![Content::text(r#"{"num_files": 4}"#)]);
+        assert_eq!(result_count(&stats), None);
+    }
+
+    #[test]
+    fn returned_symbols_are_found_at_any_depth_once() {
+        let response = CallToolResult::success(vec![Content::text(
+            r#"[
+                {"id": "a.py:login:1", "name": "login", "kind": "function", "file_path": "a.py"},
+                {"edge": {"target_name": "login"},
+                 "source": {"id": "b.py:main:3", "name": "main", "kind": "function", "file_path": "b.py"}},
+                {"id": "a.py:login:1", "name": "login", "kind": "function", "file_path": "a.py"},
+                {"name": "not a symbol", "file_path": "c.py"}
+            ]
+
+(~40 tokens to read these results)"#,
+        )]);
+        let hits = returned_symbols(&response);
+        let names: Vec<&str> = hits.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, ["login", "main"]);
+        assert_eq!(hits[1].file_path, "b.py");
+
+        let stats = CallToolResult::success(vec![Content::text(r#"{"num_files": 4}"#)]);
+        assert!(returned_symbols(&stats).is_empty());
+    }
+
+    #[test]
+    fn token_estimates_are_added_and_totaled() {
+        let json = r#"[{"name": "a", "start_byte": 0, "end_byte": 40}]"#.to_string();
+        let text = with_token_estimates(json);
+        assert!(text.contains(r#""estimated_tokens": 10"#));
+        assert!(text.ends_with("\n\n(~10 tokens to read these results)"));
+
+        let response = CallToolResult::success(vec![Content::text(text)]);
+        assert_eq!(result_count(&response), Some(1));
+
+        let stats = r#"{"num_files": 4}"#.to_string();
+        assert_eq!(with_token_estimates(stats.clone()), stats);
+    }
+
+    // ── Federation tests ──
+
+    #[test]
+    fn project_spec_parses_name_and_path() {
+        assert_eq!(
+            parse_project_spec("api=../services/api"),
+            ("api".to_string(), PathBuf::from("../services/api"))
+        );
+        assert_eq!(
+            parse_project_spec("/src/web-app"),
+            ("web-app".to_string(), PathBuf::from("/src/web-app"))
+        );
+        // `=` inside a path is not a name separator
+        assert_eq!(
+            parse_project_spec("/tmp/a=b/repo"),
+            ("repo".to_string(), PathBuf::from("/tmp/a=b/repo"))
+        );
+    }
+
+    #[test]
+    fn merged_results_are_labeled_with_their_project() {
+        let mut merged = Vec::new();
+        label_results(
+            serde_json::json!([{"name": "a"}, {"name": "b"}]),
+            "api",
+            &mut merged,
+        );
+        label_results(serde_json::json!({"num_files": 3}), "web", &mut merged);
+        label_results(serde_json::json!(["x"]), "cli", &mut merged);
+        assert_eq!(
+            serde_json::Value::Array(merged),
+            serde_json::json!([
+                {"name": "a", "project": "api"},
+                {"name": "b", "project": "api"},
+                {"num_files": 3, "project": "web"},
+                {"value": "x", "project": "cli"},
+            ])
+        );
+    }
+
+    #[test]
+    fn federated_server_selects_projects_by_name() {
+        let base = std::env::temp_dir().join(format!("cartog_federation_{}", std::process::id()));
+        let (api, web) = (base.join("api"), base.join("web"));
+        std::fs::create_dir_all(&api).unwrap();
+        std::fs::create_dir_all(&web).unwrap();
+        let server = CartogServer::federated(
+            &[
+                ("api".to_string(), api.clone()),
+                ("web".to_string(), web.clone()),
+            ],
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(server.select(None).unwrap().len(), 2);
+        assert_eq!(server.select(Some("web")).unwrap()[0].name, "web");
+        let unknown = server.select(Some("cli")).err().unwrap();
+        assert!(unknown.message.contains("Known: api, web"));
+        assert!(server.target(None).is_err(), "writes must name a project");
+        assert_eq!(server.target(Some("api")).unwrap().name, "api");
+        assert!(api.join(DB_FILE).exists(), "each project has its own index");
+
+        let named =
+            CartogServer::federated(&[("api".to_string(), api.clone())], Some("next")).unwrap();
+        assert!(named.projects[0].db_path.ends_with("next/.cartog.db"));
+        assert!(named.projects[0].db_path.exists());
+
+        let duplicate =
+            CartogServer::federated(&[("x".to_string(), api), ("x".to_string(), web)], None);
+        assert!(duplicate.is_err());
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn serve_preloads_models_only_with_rag() {
+        let config = |preload_models, rag| ServeConfig {
+            watch: false,
+            rag,
+            metrics_addr: None,
+            audit_retention_days: None,
+            projects: Vec::new(),
+            preload_models,
+            index: None,
+            allow_roots: Vec::new(),
+            max_read_bytes: crate::policy::DEFAULT_MAX_READ_BYTES,
+        };
+        assert!(!config(false, None).preloads_models());
+        assert!(!config(true, None).preloads_models());
+        assert!(!config(false, Some(EmbedPolicy::Lazy)).preloads_models());
+        assert!(config(true, Some(EmbedPolicy::Lazy)).preloads_models());
+    }
+
+    /// A served project whose `vendor/` links to a directory outside it, both
+    /// holding a TODO comment and a SQL literal, indexed with `--injections`.
+    #[cfg(unix)]
+    fn server_with_outside_symlink(name: &str) -> (CartogServer, PathBuf) {
+        let base = std::env::temp_dir().join(format!("cartog_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let (project, outside) = (base.join("project"), base.join("outside"));
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        let source = "def load(db):\n    # TODO: paginate\n    return db.execute(\"SELECT id FROM users WHERE active = 1\")\n";
+        std::fs::write(project.join("app.py"), source).unwrap();
+        std::fs::write(outside.join("secret.py"), source).unwrap();
+        std::os::unix::fs::symlink(&outside, project.join("vendor")).unwrap();
+
+        let server =
+            CartogServer::federated(&[("app".to_string(), project.clone())], None).unwrap();
+        let mut db = server.projects[0].lock().unwrap();
+        db.set_scan_injections(true).unwrap();
+        db.index(&project, false).unwrap();
+        assert_eq!(db.anchors(None, None, None, 10).unwrap().len(), 2);
+        drop(db);
+        (
+            server.with_path_policy(PathPolicy::new(&[], 4).unwrap()),
+            base,
+        )
+    }
+
+    /// File paths and texts of the array a tool returned.
+    #[cfg(unix)]
+    fn returned_texts(result: &CallToolResult) -> Vec<(String, String)> {
+        let value = response_json(result).unwrap();
+        value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| {
+                let field = |key: &str| item[key].as_str().unwrap().to_string();
+                (field("file_path"), field("text"))
+            })
+            .collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn todos_withhold_files_outside_the_roots_and_cap_text() {
+        let (server, base) = server_with_outside_symlink("policy_todos");
+        let params = TodosParams {
+            path: None,
+            tag: None,
+            limit: None,
+            project: None,
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime
+            .block_on(server.cartog_todos(Parameters(params)))
+            .unwrap();
+        let texts = returned_texts(&result);
+        assert_eq!(texts.len(), 1, "{texts:?}");
+        assert_eq!(texts[0].0, "app.py");
+        assert!(texts[0]
+            .1
+            .ends_with("(truncated at the server's read limit)"));
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn injections_withhold_files_outside_the_roots_and_cap_text() {
+        let (server, base) = server_with_outside_symlink("policy_injections");
+        let params = InjectionsParams {
+            query: Some("users".to_string()),
+            lang: None,
+            path: None,
+            limit: None,
+            project: None,
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let result = runtime
+            .block_on(server.cartog_injections(Parameters(params)))
+            .unwrap();
+        let texts = returned_texts(&result);
+        assert_eq!(texts.len(), 1, "{texts:?}");
+        assert_eq!(texts[0].0, "app.py");
+        assert!(texts[0].1.starts_with("SELE\n"));
+        let _ = std::fs::remove_dir_all(&base);
+    }
 
     // ── Path validation tests ──
 
@@ -735,6 +2865,96 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn validate_relative_path_strips_the_root() {
+        let root = std::env::current_dir().unwrap().canonicalize().unwrap();
+        assert_eq!(
+            validate_relative_path("src/mcp.rs", &root).unwrap(),
+            "src/mcp.rs"
+        );
+        let absolute = root.join("src/./mcp.rs");
+        let absolute = absolute.to_str().unwrap();
+        assert_eq!(
+            validate_relative_path(absolute, &root).unwrap(),
+            "src/mcp.rs"
+        );
+        assert!(validate_relative_path("../outside.rs", &root).is_err());
+    }
+
+    #[test]
+    fn annotate_symbol_finds_the_definition_in_file() {
+        let dir = std::env::temp_dir().join(format!("cartog_annotate_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.py"), "def login():\n    pass\n").unwrap();
+        std::fs::write(dir.join("b.py"), "def login():\n    pass\n").unwrap();
+        let server = CartogServer::federated(&[("app".to_string(), dir.clone())], None).unwrap();
+        server.projects[0]
+            .lock()
+            .unwrap()
+            .index(&dir, false)
+            .unwrap();
+
+        let annotate = |file: Option<&str>| {
+            let params = AnnotateSymbolParams {
+                name: "login".to_string(),
+                file: file.map(str::to_string),
+                note: "checked".to_string(),
+                ttl_hours: None,
+                project: None,
+            };
+            let runtime = tokio::runtime::Runtime::new().unwrap();
+            runtime.block_on(server.cartog_annotate_symbol(Parameters(params)))
+        };
+        let ambiguous = annotate(None).err().unwrap();
+        assert!(ambiguous.message.contains("pass `file`"));
+        annotate(Some("b.py")).unwrap();
+
+        let notes = server.projects[0]
+            .lock()
+            .unwrap()
+            .notes(None, unix_now())
+            .unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].file_path, "b.py");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reserve_symbols_finds_the_definitions_in_file() {
+        let dir = std::env::temp_dir().join(format!("cartog_reserve_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.py"), "def login():\n    pass\n").unwrap();
+        std::fs::write(dir.join("b.py"), "def login():\n    pass\n").unwrap();
+        let server = CartogServer::federated(&[("app".to_string(), dir.clone())], None).unwrap();
+        server.projects[0]
+            .lock()
+            .unwrap()
+            .index(&dir, false)
+            .unwrap();
+
+        let params = ReserveSymbolsParams {
+            names: vec!["login".to_string()],
+            file: Some(dir.join("a.py").to_string_lossy().into_owned()),
+            owner: "agent-a".to_string(),
+            reason: None,
+            ttl_minutes: None,
+            project: None,
+        };
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime
+            .block_on(server.cartog_reserve_symbols(Parameters(params)))
+            .unwrap();
+
+        let db = server.projects[0].lock().unwrap();
+        let reservations = db.reservations(Some("agent-a"), unix_now()).unwrap();
+        assert_eq!(reservations.len(), 1);
+        assert_eq!(reservations[0].file_path, "a.py");
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     // ── Normalize path tests ──
 
     #[test]
@@ -866,7 +3086,7 @@ mod tests {
 
     #[test]
     fn ref_entry_serializes() {
-        let entry = RefEntry {
+        let entry = Reference {
             edge: crate::types::Edge::new("src:foo:1", "bar", EdgeKind::Calls, "src/main.py", 10),
             source: None,
         };
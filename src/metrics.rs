@@ -0,0 +1,304 @@
+//! Prometheus metrics for `cartog serve`.
+//!
+//! Tool-call counters and latencies are recorded in-process by the MCP server;
+//! index gauges (size, generation, embedding coverage) are read from the database
+//! at scrape time. Exposed over a minimal HTTP listener at `GET /metrics`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write as _};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use tracing::{debug, info, warn};
+
+use crate::shard::ShardedDatabase;
+
+/// Upper bounds (seconds) of the tool latency histogram buckets.
+const LATENCY_BUCKETS: [f64; 9] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0, 5.0, 30.0];
+
+#[derive(Debug, Default, Clone)]
+struct ToolStats {
+    calls: u64,
+    errors: u64,
+    seconds_sum: f64,
+    /// Non-cumulative counts per bucket; the `+Inf` bucket is `calls`.
+    buckets: [u64; LATENCY_BUCKETS.len()],
+}
+
+/// In-process counters shared between the MCP server and the metrics listener.
+#[derive(Debug)]
+pub struct Metrics {
+    started: Instant,
+    tools: Mutex<BTreeMap<String, ToolStats>>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            tools: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Record one tool call.
+    pub fn record(&self, tool: &str, elapsed: Duration, ok: bool) {
+        let Ok(mut tools) = self.tools.lock() else {
+            return;
+        };
+        let stats = tools.entry(tool.to_string()).or_default();
+        let secs = elapsed.as_secs_f64();
+        stats.calls += 1;
+        if !ok {
+            stats.errors += 1;
+        }
+        stats.seconds_sum += secs;
+        if let Some(i) = LATENCY_BUCKETS.iter().position(|&le| secs <= le) {
+            stats.buckets[i] += 1;
+        }
+    }
+
+    /// Render all metrics in the Prometheus text exposition format.
+    ///
+    /// `db_path` is only used for the on-disk size gauge.
+    pub fn render(&self, db: &ShardedDatabase, db_path: &Path) -> Result<String> {
+        let mut out = String::new();
+        let tools = self
+            .tools
+            .lock()
+            .map_err(|_| anyhow::anyhow!("metrics lock poisoned"))?
+            .clone();
+
+        header(
+            &mut out,
+            "cartog_tool_calls_total",
+            "counter",
+            "MCP tool calls.",
+        );
+        for (tool, s) in &tools {
+            let _ = writeln!(
+                out,
+                "cartog_tool_calls_total{{tool=\"{tool}\"}} {}",
+                s.calls
+            );
+        }
+        header(
+            &mut out,
+            "cartog_tool_errors_total",
+            "counter",
+            "MCP tool calls that returned an error.",
+        );
+        for (tool, s) in &tools {
+            let _ = writeln!(
+                out,
+                "cartog_tool_errors_total{{tool=\"{tool}\"}} {}",
+                s.errors
+            );
+        }
+        header(
+            &mut out,
+            "cartog_tool_duration_seconds",
+            "histogram",
+            "MCP tool call latency.",
+        );
+        for (tool, s) in &tools {
+            let mut cumulative = 0;
+            for (le, count) in LATENCY_BUCKETS.iter().zip(s.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "cartog_tool_duration_seconds_bucket{{tool=\"{tool}\",le=\"{le}\"}} {cumulative}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "cartog_tool_duration_seconds_bucket{{tool=\"{tool}\",le=\"+Inf\"}} {}",
+                s.calls
+            );
+            let _ = writeln!(
+                out,
+                "cartog_tool_duration_seconds_sum{{tool=\"{tool}\"}} {}",
+                s.seconds_sum
+            );
+            let _ = writeln!(
+                out,
+                "cartog_tool_duration_seconds_count{{tool=\"{tool}\"}} {}",
+                s.calls
+            );
+        }
+
+        let stats = db.stats()?;
+        let generation = db.index_generation()?;
+        let embeddable = db.embeddable_symbol_count()?;
+        let embedded = db.embedding_count()?;
+        let coverage = if embeddable == 0 {
+            0.0
+        } else {
+            f64::from(embedded) / f64::from(embeddable)
+        };
+        let db_size = std::fs::metadata(db_path).map(|m| m.len()).unwrap_or(0);
+
+        gauge(
+            &mut out,
+            "cartog_index_generation",
+            "Number of index runs that changed the graph.",
+            generation,
+        );
+        gauge(&mut out, "cartog_files", "Indexed files.", stats.num_files);
+        gauge(
+            &mut out,
+            "cartog_symbols",
+            "Indexed symbols.",
+            stats.num_symbols,
+        );
+        gauge(
+            &mut out,
+            "cartog_edges",
+            "Extracted edges.",
+            stats.num_edges,
+        );
+        gauge(
+            &mut out,
+            "cartog_edges_resolved",
+            "Edges resolved to a target symbol.",
+            stats.num_resolved,
+        );
+        gauge(
+            &mut out,
+            "cartog_db_size_bytes",
+            "Size of the SQLite database file.",
+            db_size,
+        );
+        gauge(
+            &mut out,
+            "cartog_embeddings",
+            "Symbols with a stored embedding.",
+            embedded,
+        );
+        gauge(
+            &mut out,
+            "cartog_embedding_coverage_ratio",
+            "Fraction of embeddable symbols that have an embedding.",
+            coverage,
+        );
+        gauge(
+            &mut out,
+            "cartog_uptime_seconds",
+            "Seconds since the server started.",
+            self.started.elapsed().as_secs_f64(),
+        );
+
+        Ok(out)
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: impl std::fmt::Display) {
+    header(out, name, "gauge", help);
+    let _ = writeln!(out, "{name} {value}");
+}
+
+/// Serve `GET /metrics` on `addr` from a background thread.
+///
+/// Uses its own read connection to `db_path` so scrapes never wait on the
+/// MCP server's database lock (WAL mode allows concurrent readers).
+pub fn spawn_metrics_server(
+    addr: SocketAddr,
+    metrics: Arc<Metrics>,
+    db_path: impl Into<PathBuf>,
+) -> Result<()> {
+    let db_path = db_path.into();
+    let db = ShardedDatabase::open(&db_path).context("failed to open database for metrics")?;
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("failed to bind metrics to {addr}"))?;
+    info!(%addr, "metrics endpoint listening on /metrics");
+
+    std::thread::Builder::new()
+        .name("cartog-metrics".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(e) = handle_connection(stream, &metrics, &db, &db_path) {
+                            debug!(error = %e, "metrics request failed");
+                        }
+                    }
+                    Err(e) => warn!(error = %e, "metrics connection failed"),
+                }
+            }
+        })
+        .context("failed to spawn metrics thread")?;
+    Ok(())
+}
+
+fn handle_connection(
+    mut stream: TcpStream,
+    metrics: &Metrics,
+    db: &ShardedDatabase,
+    db_path: &Path,
+) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+
+    let (status, body) = if method == "GET" && path == "/metrics" {
+        match metrics.render(db, db_path) {
+            Ok(body) => ("200 OK", body),
+            Err(e) => ("500 Internal Server Error", format!("{e}\n")),
+        }
+    } else {
+        ("404 Not Found", "not found\n".to_string())
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_counts_and_histogram() {
+        let db = ShardedDatabase::from(crate::db::Database::open_memory().unwrap());
+        let metrics = Metrics::new();
+        metrics.record("cartog_refs", Duration::from_millis(3), true);
+        metrics.record("cartog_refs", Duration::from_millis(200), false);
+
+        let out = metrics.render(&db, Path::new("/nonexistent")).unwrap();
+        assert!(out.contains("cartog_tool_calls_total{tool=\"cartog_refs\"} 2"));
+        assert!(out.contains("cartog_tool_errors_total{tool=\"cartog_refs\"} 1"));
+        assert!(out
+            .contains("cartog_tool_duration_seconds_bucket{tool=\"cartog_refs\",le=\"0.005\"} 1"));
+        assert!(
+            out.contains("cartog_tool_duration_seconds_bucket{tool=\"cartog_refs\",le=\"0.25\"} 2")
+        );
+        assert!(
+            out.contains("cartog_tool_duration_seconds_bucket{tool=\"cartog_refs\",le=\"+Inf\"} 2")
+        );
+        assert!(out.contains("cartog_index_generation 0"));
+        assert!(out.contains("cartog_db_size_bytes 0"));
+        assert!(out.contains("# TYPE cartog_tool_duration_seconds histogram"));
+    }
+}
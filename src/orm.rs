@@ -0,0 +1,396 @@
+//! Heuristic ORM model-to-table linkage and table usage lookup (`cartog table-usages`).
+//!
+//! Run by the indexer after the symbol extractor, like anchor extraction. A model's
+//! table is stored in [`Symbol::db_table`]:
+//!
+//! - Python: SQLAlchemy `__tablename__ = "x"`, Django `class Meta: db_table = "x"`, or
+//!   `<app>_<model>` for a `models.Model` subclass without one.
+//! - Ruby: ActiveRecord models (`< ApplicationRecord`, `< ActiveRecord::Base`) use
+//!   `self.table_name = "x"`, else the pluralized snake_case class name.
+//! - Go: GORM structs with a `TableName()` method returning a literal, else structs
+//!   embedding `gorm.Model` get the pluralized snake_case struct name.
+//! - Prisma: set by the schema extractor (`@@map("x")`, else the model name).
+//!
+//! Raw SQL is found by a line scan of stored symbol content for the table name right
+//! after `FROM`, `JOIN`, `INTO`, `UPDATE`, `TABLE`, `EXISTS` or `TRUNCATE`.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::shard::ShardedDatabase;
+use crate::types::{Symbol, SymbolKind};
+
+/// SQL keywords that a table name follows.
+const SQL_KEYWORDS: &[&str] = &[
+    "from", "join", "into", "update", "table", "exists", "truncate",
+];
+
+/// Set [`Symbol::db_table`] on the ORM models among a file's symbols.
+pub fn link_models(source: &str, language: &str, symbols: &mut [Symbol]) {
+    let text = |sym: &Symbol| {
+        source
+            .get(sym.start_byte as usize..sym.end_byte as usize)
+            .unwrap_or("")
+    };
+    match language {
+        "python" => {
+            for sym in symbols.iter_mut() {
+                if sym.kind == SymbolKind::Class && sym.name != "Meta" {
+                    sym.db_table = python_table(sym, text(sym));
+                }
+            }
+        }
+        "ruby" => {
+            for sym in symbols.iter_mut() {
+                if sym.kind == SymbolKind::Class {
+                    sym.db_table = ruby_table(&sym.name, text(sym));
+                }
+            }
+        }
+        "go" => {
+            // `func (User) TableName() string { return "people" }`, keyed by receiver type.
+            let overrides: HashMap<String, String> = symbols
+                .iter()
+                .filter(|s| s.kind == SymbolKind::Method && s.name == "TableName")
+                .filter_map(|s| {
+                    let receiver = s.parent_id.as_deref()?.rsplit(':').next()?;
+                    let table = text(s).split("return").nth(1).and_then(quoted)?;
+                    Some((receiver.to_string(), table.to_string()))
+                })
+                .collect();
+            for sym in symbols.iter_mut() {
+                if sym.kind != SymbolKind::Class {
+                    continue;
+                }
+                sym.db_table = match overrides.get(&sym.name) {
+                    Some(table) => Some(table.clone()),
+                    None => text(sym)
+                        .lines()
+                        .any(|l| l.trim() == "gorm.Model")
+                        .then(|| pluralize(&snake_case(&sym.name))),
+                };
+            }
+        }
+        _ => {}
+    }
+}
+
+fn python_table(sym: &Symbol, body: &str) -> Option<String> {
+    if let Some(table) = assigned(body, "__tablename__").or_else(|| assigned(body, "db_table")) {
+        return Some(table.to_string());
+    }
+    let header = body.lines().next().unwrap_or("");
+    if !header.contains("models.Model") {
+        return None;
+    }
+    // Django's default: `<app label>_<model name>`, the app being the package
+    // holding `models.py` (or the `models/` package).
+    let mut dirs = sym.file_path.rsplit('/').skip(1);
+    let app = match dirs.next()? {
+        "models" => dirs.next()?,
+        dir => dir,
+    };
+    Some(format!("{app}_{}", sym.name.to_lowercase()))
+}
+
+fn ruby_table(name: &str, body: &str) -> Option<String> {
+    let header = body.lines().next().unwrap_or("");
+    let base = header.split_once('<')?.1.trim();
+    if !matches!(base, "ApplicationRecord" | "ActiveRecord::Base") {
+        return None;
+    }
+    Some(match assigned(body, "self.table_name") {
+        Some(table) => table.to_string(),
+        None => pluralize(&snake_case(name)),
+    })
+}
+
+/// String literal assigned to `key` on a line of its own: `key = "value"`.
+fn assigned<'a>(body: &'a str, key: &str) -> Option<&'a str> {
+    body.lines().find_map(|line| {
+        let rest = line.trim_start().strip_prefix(key)?.trim_start();
+        quoted(rest.strip_prefix('=')?)
+    })
+}
+
+/// Contents of the string literal at the start of `s`, after whitespace.
+fn quoted(s: &str) -> Option<&str> {
+    let s = s.trim_start();
+    let quote = s.chars().next().filter(|c| matches!(c, '"' | '\'' | '`'))?;
+    let rest = &s[1..];
+    let value = &rest[..rest.find(quote)?];
+    (!value.is_empty()).then_some(value)
+}
+
+/// `UserProfile` → `user_profile`, `HTTPLog` → `http_log`.
+pub fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+/// English plural of a snake_case name, as Rails and GORM name tables:
+/// `category` → `categories`, `address` → `addresses`, `user` → `users`.
+pub fn pluralize(word: &str) -> String {
+    let consonant_y =
+        word.ends_with('y') && !word[..word.len() - 1].ends_with(['a', 'e', 'i', 'o', 'u']);
+    if consonant_y {
+        format!("{}ies", &word[..word.len() - 1])
+    } else if word.ends_with(['s', 'x', 'z']) || word.ends_with("ch") || word.ends_with("sh") {
+        format!("{word}es")
+    } else {
+        format!("{word}s")
+    }
+}
+
+/// A raw SQL mention of a table.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SqlReference {
+    /// Innermost symbol holding the SQL.
+    pub symbol: Symbol,
+    pub line: u32,
+    /// The source line, trimmed.
+    pub snippet: String,
+}
+
+/// Result of [`table_usages`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TableUsages {
+    pub table: String,
+    /// ORM models mapped to the table.
+    pub models: Vec<Symbol>,
+    /// Raw SQL naming the table, by file and line.
+    pub sql_references: Vec<SqlReference>,
+}
+
+/// Models mapped to `table` and raw SQL strings naming it, across languages.
+/// Table names match case-insensitively.
+pub fn table_usages(db: &ShardedDatabase, table: &str) -> Result<TableUsages> {
+    let models = db.models_for_table(table)?;
+
+    // (file, line) → innermost symbol mentioning the table there.
+    let mut found: HashMap<(String, u32), SqlReference> = HashMap::new();
+    for (symbol, content) in db.symbols_mentioning(table)? {
+        for (offset, line) in content.lines().enumerate() {
+            if !names_table(line, table) {
+                continue;
+            }
+            let line_no = symbol.start_line + offset as u32;
+            let span = symbol.end_line - symbol.start_line;
+            let key = (symbol.file_path.clone(), line_no);
+            if found
+                .get(&key)
+                .is_some_and(|r| r.symbol.end_line - r.symbol.start_line <= span)
+            {
+                continue;
+            }
+            found.insert(
+                key,
+                SqlReference {
+                    symbol: symbol.clone(),
+                    line: line_no,
+                    snippet: line.trim().to_string(),
+                },
+            );
+        }
+    }
+    let mut sql_references: Vec<SqlReference> = found.into_values().collect();
+    sql_references
+        .sort_by(|a, b| (&a.symbol.file_path, a.line).cmp(&(&b.symbol.file_path, b.line)));
+
+    Ok(TableUsages {
+        table: table.to_string(),
+        models,
+        sql_references,
+    })
+}
+
+/// Whether `line` names `table` right after a SQL keyword, allowing quoting
+/// (`"users"`, `` `users` ``, `[users]`) and a schema prefix (`public.users`).
+fn names_table(line: &str, table: &str) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let lower = line.to_lowercase();
+    let table = table.to_lowercase();
+    lower.match_indices(&table).any(|(pos, _)| {
+        let after = lower[pos + table.len()..].chars().next();
+        if after.is_some_and(is_word) || lower[..pos].chars().next_back().is_some_and(is_word) {
+            return false;
+        }
+        let quotes: &[char] = &['"', '`', '['];
+        let mut before = lower[..pos].trim_end_matches(quotes);
+        if let Some(schema) = before.strip_suffix('.') {
+            before = schema
+                .trim_end_matches(['"', '`', ']'])
+                .trim_end_matches(is_word)
+                .trim_end_matches(quotes);
+        }
+        let keyword = before.trim_end();
+        let keyword = &keyword[keyword.trim_end_matches(is_word).len()..];
+        SQL_KEYWORDS.contains(&keyword)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn class(name: &str, file: &str, source: &str) -> Symbol {
+        Symbol::new(
+            name,
+            SymbolKind::Class,
+            file,
+            1,
+            source.lines().count() as u32,
+            0,
+            source.len() as u32,
+        )
+    }
+
+    #[test]
+    fn test_link_models_per_language() {
+        let sa = "class User(Base):\n    __tablename__ = \"app_users\"\n";
+        let mut syms = vec![class("User", "app/models.py", sa)];
+        link_models(sa, "python", &mut syms);
+        assert_eq!(syms[0].db_table.as_deref(), Some("app_users"));
+
+        let dj = "class BlogPost(models.Model):\n    title = models.CharField()\n";
+        let mut syms = vec![class("BlogPost", "blog/models.py", dj)];
+        link_models(dj, "python", &mut syms);
+        assert_eq!(syms[0].db_table.as_deref(), Some("blog_blogpost"));
+
+        let rb = "class LineItem < ApplicationRecord\nend\n";
+        let mut syms = vec![class("LineItem", "app/models/line_item.rb", rb)];
+        link_models(rb, "ruby", &mut syms);
+        assert_eq!(syms[0].db_table.as_deref(), Some("line_items"));
+
+        let rb = "class Person < ActiveRecord::Base\n  self.table_name = 'people'\nend\n";
+        let mut syms = vec![class("Person", "person.rb", rb)];
+        link_models(rb, "ruby", &mut syms);
+        assert_eq!(syms[0].db_table.as_deref(), Some("people"));
+
+        let plain = "class Service\nend\n";
+        let mut syms = vec![class("Service", "service.rb", plain)];
+        link_models(plain, "ruby", &mut syms);
+        assert_eq!(syms[0].db_table, None);
+    }
+
+    #[test]
+    fn test_link_gorm_models() {
+        let go = "Category struct {\n\tgorm.Model\n}\nfunc (Category) TableName() string { return \"cats\" }\nAddress struct {\n\tgorm.Model\n}\n";
+        let category_end = go.find("\nfunc").unwrap();
+        let method_start = category_end + 1;
+        let address_start = go.find("Address").unwrap();
+        let mut syms = vec![
+            Symbol::new(
+                "Category",
+                SymbolKind::Class,
+                "m.go",
+                1,
+                3,
+                0,
+                category_end as u32,
+            ),
+            Symbol::new(
+                "TableName",
+                SymbolKind::Method,
+                "m.go",
+                4,
+                4,
+                method_start as u32,
+                address_start as u32 - 1,
+            )
+            .with_parent(Some("m.go:Category")),
+            Symbol::new(
+                "Address",
+                SymbolKind::Class,
+                "m.go",
+                5,
+                7,
+                address_start as u32,
+                go.len() as u32,
+            ),
+        ];
+        link_models(go, "go", &mut syms);
+        assert_eq!(syms[0].db_table.as_deref(), Some("cats"));
+        assert_eq!(syms[2].db_table.as_deref(), Some("addresses"));
+    }
+
+    #[test]
+    fn test_inflection() {
+        assert_eq!(snake_case("UserProfile"), "user_profile");
+        assert_eq!(snake_case("HTTPLog"), "http_log");
+        assert_eq!(pluralize("category"), "categories");
+        assert_eq!(pluralize("day"), "days");
+        assert_eq!(pluralize("box"), "boxes");
+    }
+
+    #[test]
+    fn test_names_table() {
+        assert!(names_table("SELECT * FROM users WHERE id = ?", "users"));
+        assert!(names_table("select u.id from public.\"Users\" u", "users"));
+        assert!(names_table(
+            "INSERT INTO `users` (name) VALUES (?)",
+            "users"
+        ));
+        assert!(names_table("  LEFT JOIN users ON", "USERS"));
+        assert!(!names_table("SELECT * FROM users_archive", "users"));
+        assert!(!names_table("let users = load_users();", "users"));
+    }
+
+    #[test]
+    fn test_table_usages() {
+        let db = Database::open_memory().unwrap();
+        let model = Symbol::new("User", SymbolKind::Class, "models.py", 1, 3, 0, 60)
+            .with_db_table(Some("users".to_string()));
+        let query = Symbol::new(
+            "count_users",
+            SymbolKind::Function,
+            "repo.go",
+            10,
+            14,
+            0,
+            90,
+        );
+        let unrelated = Symbol::new("load", SymbolKind::Function, "load.rs", 1, 2, 0, 30);
+        db.insert_symbols(&[model, query.clone(), unrelated.clone()])
+            .unwrap();
+        db.insert_symbol_contents(&[
+            (
+                query.id.clone(),
+                query.name.clone(),
+                "func count_users() {\n\trow := db.QueryRow(`\n\t\tSELECT count(*) FROM users`)\n}"
+                    .to_string(),
+                String::new(),
+            ),
+            (
+                unrelated.id.clone(),
+                unrelated.name.clone(),
+                "fn load() { users.len() }".to_string(),
+                String::new(),
+            ),
+        ])
+        .unwrap();
+
+        let usages = table_usages(&ShardedDatabase::from(db), "Users").unwrap();
+        assert_eq!(usages.models.len(), 1);
+        assert_eq!(usages.models[0].name, "User");
+        assert_eq!(usages.sql_references.len(), 1);
+        let r = &usages.sql_references[0];
+        assert_eq!((r.symbol.name.as_str(), r.line), ("count_users", 12));
+        assert_eq!(r.snippet, "SELECT count(*) FROM users`)");
+    }
+}
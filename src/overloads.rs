@@ -0,0 +1,226 @@
+//! Overload grouping: fold `search` and `refs` results that share a fully-qualified
+//! name into one entry with a `definitions` array.
+//!
+//! The qualified name is built from the enclosing symbols recorded at index time
+//! (`AuthService::login`, `tests::logs_in`), so it works the same for every language:
+//! Java/C# overloads in one class, Python functions redefined per platform, or the same
+//! top-level `main` in several files all end up in a single group.
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::shard::ShardedDatabase;
+use crate::types::{Edge, Symbol, SymbolKind, SymbolMatch};
+
+/// Search hits sharing a qualified name and kind, best-ranked definition first.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SymbolGroup {
+    pub qualified_name: String,
+    pub kind: SymbolKind,
+    pub definitions: Vec<SymbolMatch>,
+}
+
+/// A reference, with the symbol it comes from (same shape as ungrouped `refs`).
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Reference {
+    pub edge: Edge,
+    pub source: Option<Symbol>,
+}
+
+/// References whose targets share a qualified name.
+///
+/// `definitions` lists the resolved targets; it is empty for references that did
+/// not resolve, which are grouped under the name they use.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RefGroup {
+    pub qualified_name: String,
+    pub definitions: Vec<Symbol>,
+    pub references: Vec<Reference>,
+}
+
+/// `sym`'s name prefixed by its enclosing symbols, joined with `::`.
+pub fn qualified_name(db: &ShardedDatabase, sym: &Symbol) -> Result<String> {
+    let mut names = vec![sym.name.clone()];
+    let mut parent_id = sym.parent_id.clone();
+    while let Some(id) = parent_id {
+        let Some(parent) = db.get_symbol(&id)? else {
+            break;
+        };
+        parent_id = parent.parent_id;
+        names.push(parent.name);
+    }
+    names.reverse();
+    Ok(names.join("::"))
+}
+
+/// Group search hits by qualified name and kind. Groups keep the rank of their
+/// best hit, and definitions keep their relative order.
+pub fn group_matches(db: &ShardedDatabase, matches: Vec<SymbolMatch>) -> Result<Vec<SymbolGroup>> {
+    let mut groups: Vec<SymbolGroup> = Vec::new();
+    let mut index: HashMap<(String, SymbolKind), usize> = HashMap::new();
+    for m in matches {
+        let key = (qualified_name(db, &m.symbol)?, m.symbol.kind);
+        match index.get(&key) {
+            Some(&i) => groups[i].definitions.push(m),
+            None => {
+                index.insert(key.clone(), groups.len());
+                groups.push(SymbolGroup {
+                    qualified_name: key.0,
+                    kind: key.1,
+                    definitions: vec![m],
+                });
+            }
+        }
+    }
+    Ok(groups)
+}
+
+/// Group references by the qualified name of their target. Groups appear in the
+/// order of their first reference, so the nearest-first order of `refs` carries over.
+pub fn group_refs(
+    db: &ShardedDatabase,
+    refs: Vec<(Edge, Option<Symbol>)>,
+) -> Result<Vec<RefGroup>> {
+    let mut targets: HashMap<String, Option<(Symbol, String)>> = HashMap::new();
+    let mut groups: Vec<RefGroup> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for (edge, source) in refs {
+        let target = match edge.target_id.as_deref() {
+            Some(id) => {
+                if !targets.contains_key(id) {
+                    let resolved = match db.get_symbol(id)? {
+                        Some(sym) => {
+                            let name = qualified_name(db, &sym)?;
+                            Some((sym, name))
+                        }
+                        None => None,
+                    };
+                    targets.insert(id.to_string(), resolved);
+                }
+                targets[id].as_ref()
+            }
+            None => None,
+        };
+        let key = target.map_or_else(|| edge.target_name.clone(), |(_, name)| name.clone());
+        let i = *index.entry(key.clone()).or_insert_with(|| {
+            groups.push(RefGroup {
+                qualified_name: key,
+                definitions: Vec::new(),
+                references: Vec::new(),
+            });
+            groups.len() - 1
+        });
+        let group = &mut groups[i];
+        if let Some((sym, _)) = target {
+            if !group.definitions.iter().any(|d| d.id == sym.id) {
+                group.definitions.push(sym.clone());
+            }
+        }
+        group.references.push(Reference { edge, source });
+    }
+    Ok(groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::types::EdgeKind;
+
+    /// Two `Parser::parse` overloads, a second `parse` in another class, and the
+    /// same top-level `main` in two files.
+    fn setup() -> (ShardedDatabase, Vec<Symbol>) {
+        let db = Database::open_memory().unwrap();
+        let sym = |name: &str, kind, file: &str, line| {
+            Symbol::new(name, kind, file, line, line + 2, 0, 10)
+        };
+        let parser = sym("Parser", SymbolKind::Class, "Parser.java", 1);
+        let lexer = sym("Lexer", SymbolKind::Class, "Lexer.java", 1);
+        let symbols = vec![
+            sym("parse", SymbolKind::Method, "Parser.java", 3).with_parent(Some(&parser.id)),
+            sym("parse", SymbolKind::Method, "Parser.java", 8).with_parent(Some(&parser.id)),
+            sym("parse", SymbolKind::Method, "Lexer.java", 3).with_parent(Some(&lexer.id)),
+            sym("main", SymbolKind::Function, "a.py", 1),
+            sym("main", SymbolKind::Function, "b.py", 1),
+            parser,
+            lexer,
+        ];
+        db.insert_symbols(&symbols).unwrap();
+        (ShardedDatabase::from(db), symbols)
+    }
+
+    #[test]
+    fn test_group_matches_by_qualified_name() {
+        let (db, _) = setup();
+        let matches = ["parse", "main"]
+            .iter()
+            .flat_map(|q| db.search(q, None, None, 10).unwrap())
+            .map(|sym| SymbolMatch::new(sym, "parse"))
+            .collect();
+        let groups = group_matches(&db, matches).unwrap();
+        let summary: Vec<(&str, Vec<&str>)> = groups
+            .iter()
+            .map(|g| {
+                let files = g
+                    .definitions
+                    .iter()
+                    .map(|d| d.symbol.file_path.as_str())
+                    .collect();
+                (g.qualified_name.as_str(), files)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Lexer::parse", vec!["Lexer.java"]),
+                ("Parser::parse", vec!["Parser.java", "Parser.java"]),
+                ("Parser", vec!["Parser.java"]),
+                ("main", vec!["a.py", "b.py"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_refs_by_target() {
+        let (db, symbols) = setup();
+        let (first, second, other) = (&symbols[0], &symbols[1], &symbols[2]);
+        let caller = &symbols[3];
+        let edge = |target: &Symbol, line| {
+            let mut e = Edge::new(&caller.id, "parse", EdgeKind::Calls, "a.py", line);
+            e.target_id = Some(target.id.clone());
+            e
+        };
+        let refs = vec![
+            (edge(first, 2), None),
+            (edge(other, 3), None),
+            (edge(second, 4), None),
+            (edge(first, 5), None),
+            (
+                Edge::new(&caller.id, "parse", EdgeKind::Calls, "a.py", 6),
+                None,
+            ),
+        ];
+        let groups = group_refs(&db, refs).unwrap();
+        let summary: Vec<(&str, usize, usize)> = groups
+            .iter()
+            .map(|g| {
+                (
+                    g.qualified_name.as_str(),
+                    g.definitions.len(),
+                    g.references.len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Parser::parse", 2, 3),
+                ("Lexer::parse", 1, 1),
+                ("parse", 0, 1)
+            ]
+        );
+    }
+}
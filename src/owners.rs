@@ -0,0 +1,452 @@
+//! Change impact grouped by code owner.
+//!
+//! Combines three inputs: the line ranges touched by a git revision range, the
+//! symbol graph (transitive `impact` of every changed symbol), and a
+//! `CODEOWNERS` file. The result is one entry per owner listing the changed and
+//! impacted symbols in files they own — enough to decide who to ping for review.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::ignore::pattern_matches;
+use crate::indexer::git_cmd;
+use crate::shard::ShardedDatabase;
+use crate::types::{Symbol, SymbolKind};
+
+/// Locations searched for a CODEOWNERS file, in GitHub's order of precedence.
+pub const CODEOWNERS_PATHS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Owner label for files that no CODEOWNERS rule matches.
+pub const UNOWNED: &str = "(unowned)";
+
+/// Parsed CODEOWNERS rules. The last matching rule wins.
+#[derive(Debug, Default)]
+pub struct CodeOwners {
+    rules: Vec<OwnerRule>,
+    /// Prepended to looked-up paths when the index root is a repo subdirectory.
+    prefix: String,
+}
+
+#[derive(Debug)]
+struct OwnerRule {
+    pattern: String,
+    owners: Vec<String>,
+}
+
+impl CodeOwners {
+    /// Load the first CODEOWNERS file found under `root`, if any.
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        for rel in CODEOWNERS_PATHS {
+            let path = root.join(rel);
+            if path.is_file() {
+                let text = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                return Ok(Some(Self::parse(&text)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse CODEOWNERS text: `pattern owner...` per line, `#` comments.
+    pub fn parse(text: &str) -> Self {
+        let rules = text
+            .lines()
+            .filter_map(|line| {
+                let line = line.split(" #").next().unwrap_or("").trim();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+                let mut parts = line.split_whitespace();
+                let pattern = parts.next()?.to_string();
+                Some(OwnerRule {
+                    pattern,
+                    owners: parts.map(str::to_string).collect(),
+                })
+            })
+            .collect();
+        Self {
+            rules,
+            prefix: String::new(),
+        }
+    }
+
+    /// Look up paths relative to `prefix` (a repo subdirectory, e.g. `backend/`).
+    pub fn with_path_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    /// Owners of `path` (relative to the index root). Empty if unowned.
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        let path = format!("{}{path}", self.prefix);
+        self.rules
+            .iter()
+            .rev()
+            .find(|r| pattern_matches(&r.pattern, &path, false))
+            .map(|r| r.owners.as_slice())
+            .unwrap_or(&[])
+    }
+}
+
+// ── Diff parsing ──
+
+/// Changed line ranges (inclusive, new-side line numbers) per file.
+pub type ChangedLines = BTreeMap<String, Vec<(u32, u32)>>;
+
+/// Run `git diff -U0 <range>` in `root` and collect changed line ranges.
+///
+/// Paths are relative to `root` (`--relative`), matching how the index stores them.
+/// Deleted files are skipped: their symbols are no longer in the index.
+pub fn changed_lines(root: &Path, range: &str) -> Result<ChangedLines> {
+    let output = git_cmd(
+        root,
+        &[
+            "-c",
+            "core.quotepath=off",
+            "diff",
+            "-U0",
+            "--no-color",
+            "--no-ext-diff",
+            "--relative",
+            range,
+            "--",
+        ],
+    )
+    .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git diff {range} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(parse_unified_diff(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `+++ b/<path>` headers and `@@ -a,b +c,d @@` hunk headers.
+fn parse_unified_diff(diff: &str) -> ChangedLines {
+    let mut changes = ChangedLines::new();
+    let mut current: Option<String> = None;
+
+    for line in diff.lines() {
+        if let Some(target) = line.strip_prefix("+++ ") {
+            current = target.strip_prefix("b/").map(str::to_string);
+            continue;
+        }
+        let (Some(file), Some(hunk)) = (&current, line.strip_prefix("@@ ")) else {
+            continue;
+        };
+        let Some(new_side) = hunk.split_whitespace().find_map(|p| p.strip_prefix('+')) else {
+            continue;
+        };
+        let mut nums = new_side.splitn(2, ',');
+        let start: u32 = nums.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+        let count: u32 = nums.next().and_then(|n| n.parse().ok()).unwrap_or(1);
+        let range = if count == 0 {
+            // Pure deletion after line `start`: attribute it to the surrounding lines.
+            (start.max(1), start + 1)
+        } else {
+            (start, start + count - 1)
+        };
+        changes.entry(file.clone()).or_default().push(range);
+    }
+    changes
+}
+
+// ── Impact by owner ──
+
+/// A symbol touched by the diff.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ChangedSymbol {
+    pub id: String,
+    pub name: String,
+    pub kind: SymbolKind,
+    pub file_path: String,
+    pub line: u32,
+}
+
+/// A symbol that transitively depends on a changed symbol.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ImpactedSymbol {
+    pub id: String,
+    pub name: String,
+    pub kind: SymbolKind,
+    pub file_path: String,
+    pub line: u32,
+    /// Hops from the nearest changed symbol.
+    pub depth: u32,
+    /// Changed symbol this impact was reached from.
+    pub via: String,
+}
+
+/// Everything one owner should look at.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct OwnerImpact {
+    pub owner: String,
+    pub files: Vec<String>,
+    pub changed: Vec<ChangedSymbol>,
+    pub impacted: Vec<ImpactedSymbol>,
+}
+
+/// Result of [`owners_impact`].
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct OwnersImpactReport {
+    pub range: String,
+    pub changed_files: u32,
+    pub changed_symbols: u32,
+    pub impacted_symbols: u32,
+    pub owners: Vec<OwnerImpact>,
+}
+
+/// Changed symbols: the innermost non-import symbols overlapping each changed range.
+pub fn changed_symbols(db: &ShardedDatabase, changes: &ChangedLines) -> Result<Vec<Symbol>> {
+    let mut result = Vec::new();
+    for (file, ranges) in changes {
+        let symbols = db.outline(file)?;
+        let overlapping: Vec<&Symbol> = symbols
+            .iter()
+            .filter(|s| s.kind != SymbolKind::Import)
+            .filter(|s| {
+                ranges
+                    .iter()
+                    .any(|&(start, end)| s.start_line <= end && start <= s.end_line)
+            })
+            .collect();
+        // Drop enclosing symbols (a class) when a nested one (its method) is also hit.
+        for sym in &overlapping {
+            let has_inner = overlapping.iter().any(|o| {
+                o.id != sym.id && sym.start_line <= o.start_line && o.end_line <= sym.end_line
+            });
+            if !has_inner {
+                result.push((*sym).clone());
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Group changed and transitively impacted symbols by CODEOWNERS owner.
+///
+/// Files without a matching rule are reported under [`UNOWNED`], sorted last.
+pub fn owners_impact(
+    db: &ShardedDatabase,
+    owners: &CodeOwners,
+    range: &str,
+    changes: &ChangedLines,
+    depth: u32,
+) -> Result<OwnersImpactReport> {
+    let changed = changed_symbols(db, changes)?;
+    let changed_ids: BTreeSet<&str> = changed.iter().map(|s| s.id.as_str()).collect();
+
+    // Impacted symbol id → (depth, via), keeping the shortest path.
+    let mut impacted: HashMap<String, (u32, String)> = HashMap::new();
+    for sym in &changed {
+        for (edge, d) in db.impact(&sym.name, depth)? {
+            if changed_ids.contains(edge.source_id.as_str()) {
+                continue;
+            }
+            let entry = impacted
+                .entry(edge.source_id)
+                .or_insert((d, sym.name.clone()));
+            if d < entry.0 {
+                *entry = (d, sym.name.clone());
+            }
+        }
+    }
+    let impacted_ids: Vec<String> = impacted.keys().cloned().collect();
+    // Import statements add noise: the symbols using the import are listed anyway.
+    let impacted_symbols: Vec<Symbol> = db
+        .get_symbols_by_ids(&impacted_ids)?
+        .into_iter()
+        .filter(|s| s.kind != SymbolKind::Import)
+        .collect();
+
+    let mut by_owner: BTreeMap<String, OwnerImpact> = BTreeMap::new();
+    for sym in &changed {
+        let item = ChangedSymbol {
+            id: sym.id.clone(),
+            name: sym.name.clone(),
+            kind: sym.kind,
+            file_path: sym.file_path.clone(),
+            line: sym.start_line,
+        };
+        for entry in owner_entries(&mut by_owner, owners, &sym.file_path) {
+            entry.changed.push(item.clone());
+        }
+    }
+    for sym in &impacted_symbols {
+        let Some((d, via)) = impacted.get(&sym.id) else {
+            continue;
+        };
+        let item = ImpactedSymbol {
+            id: sym.id.clone(),
+            name: sym.name.clone(),
+            kind: sym.kind,
+            file_path: sym.file_path.clone(),
+            line: sym.start_line,
+            depth: *d,
+            via: via.clone(),
+        };
+        for entry in owner_entries(&mut by_owner, owners, &sym.file_path) {
+            entry.impacted.push(item.clone());
+        }
+    }
+
+    let mut owners_list: Vec<OwnerImpact> = by_owner.into_values().collect();
+    for o in &mut owners_list {
+        o.files.sort();
+        o.changed
+            .sort_by(|a, b| (&a.file_path, a.line).cmp(&(&b.file_path, b.line)));
+        o.impacted
+            .sort_by(|a, b| (a.depth, &a.file_path, a.line).cmp(&(b.depth, &b.file_path, b.line)));
+    }
+    owners_list.sort_by_key(|o| o.owner == UNOWNED);
+
+    Ok(OwnersImpactReport {
+        range: range.to_string(),
+        changed_files: changes.len() as u32,
+        changed_symbols: changed.len() as u32,
+        impacted_symbols: impacted_symbols.len() as u32,
+        owners: owners_list,
+    })
+}
+
+/// Entries for every owner of `file` (or [`UNOWNED`]), created on first use.
+fn owner_entries<'a>(
+    by_owner: &'a mut BTreeMap<String, OwnerImpact>,
+    owners: &CodeOwners,
+    file: &'a str,
+) -> impl Iterator<Item = &'a mut OwnerImpact> {
+    let names: BTreeSet<String> = match owners.owners_of(file) {
+        [] => BTreeSet::from([UNOWNED.to_string()]),
+        names => names.iter().cloned().collect(),
+    };
+    for name in &names {
+        by_owner.entry(name.clone()).or_insert_with(|| OwnerImpact {
+            owner: name.clone(),
+            files: Vec::new(),
+            changed: Vec::new(),
+            impacted: Vec::new(),
+        });
+    }
+    by_owner
+        .iter_mut()
+        .filter(move |(name, _)| names.contains(*name))
+        .map(move |(_, entry)| {
+            if !entry.files.iter().any(|f| f == file) {
+                entry.files.push(file.to_string());
+            }
+            entry
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Edge, EdgeKind};
+
+    #[test]
+    fn test_codeowners_last_match_wins() {
+        let owners = CodeOwners::parse(
+            "\
+# default
+*           @org/core
+*.py        @org/python   # inline comment
+/docs/      @org/docs
+src/auth/** @alice @bob
+apps/       @org/apps
+docs/*      @org/docs-flat
+",
+        );
+        assert_eq!(owners.owners_of("README.md"), ["@org/core"]);
+        assert_eq!(owners.owners_of("lib/util.py"), ["@org/python"]);
+        assert_eq!(owners.owners_of("src/auth/tokens.py"), ["@alice", "@bob"]);
+        assert_eq!(owners.owners_of("nested/apps/web/main.ts"), ["@org/apps"]);
+        assert_eq!(owners.owners_of("docs/intro.md"), ["@org/docs-flat"]);
+        // `docs/*` only covers direct children; `/docs/` still covers nested files.
+        assert_eq!(owners.owners_of("docs/guide/setup.md"), ["@org/docs"]);
+        assert!(CodeOwners::parse("/src/ @x")
+            .owners_of("lib/src/a.rs")
+            .is_empty());
+
+        let scoped = CodeOwners::parse("/backend/api/ @team-api").with_path_prefix("backend/");
+        assert_eq!(scoped.owners_of("api/routes.py"), ["@team-api"]);
+    }
+
+    #[test]
+    fn test_parse_unified_diff() {
+        let diff = "\
+diff --git a/auth/tokens.py b/auth/tokens.py
+--- a/auth/tokens.py
++++ b/auth/tokens.py
+@@ -10,2 +10,3 @@ def validate_token(token):
+@@ -40 +41 @@ class Session:
+@@ -50,3 +51,0 @@
+diff --git a/old.py b/old.py
+--- a/old.py
++++ /dev/null
+@@ -1,5 +0,0 @@
+";
+        let changes = parse_unified_diff(diff);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes["auth/tokens.py"],
+            vec![(10, 12), (41, 41), (51, 52)]
+        );
+    }
+
+    #[test]
+    fn test_owners_impact_groups_by_owner() {
+        let db = ShardedDatabase::from(crate::db::Database::open_memory().unwrap());
+        let validate = Symbol::new(
+            "validate",
+            SymbolKind::Function,
+            "auth/tokens.py",
+            1,
+            10,
+            0,
+            100,
+        );
+        let login = Symbol::new(
+            "login",
+            SymbolKind::Function,
+            "api/routes.py",
+            1,
+            10,
+            0,
+            100,
+        );
+        let handler = Symbol::new("handler", SymbolKind::Function, "web/app.py", 1, 10, 0, 100);
+        db.insert_symbols(&[validate.clone(), login.clone(), handler.clone()])
+            .unwrap();
+        db.insert_edges(&[
+            Edge::new(&login.id, "validate", EdgeKind::Calls, "api/routes.py", 5),
+            Edge::new(&handler.id, "login", EdgeKind::Calls, "web/app.py", 5),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
+
+        let owners = CodeOwners::parse("/auth/ @team-auth\n/api/ @team-api\n");
+        let changes = ChangedLines::from([("auth/tokens.py".to_string(), vec![(3, 4)])]);
+        let report = owners_impact(&db, &owners, "main..HEAD", &changes, 3).unwrap();
+
+        assert_eq!(report.changed_symbols, 1);
+        assert_eq!(report.impacted_symbols, 2);
+        let owner_names: Vec<&str> = report.owners.iter().map(|o| o.owner.as_str()).collect();
+        assert_eq!(owner_names, ["@team-api", "@team-auth", UNOWNED]);
+
+        let api = &report.owners[0];
+        assert!(api.changed.is_empty());
+        assert_eq!(api.impacted[0].name, "login");
+        assert_eq!(api.impacted[0].depth, 1);
+        assert_eq!(api.impacted[0].via, "validate");
+
+        assert_eq!(report.owners[1].changed[0].name, "validate");
+        assert_eq!(report.owners[2].impacted[0].name, "handler");
+        assert_eq!(report.owners[2].impacted[0].depth, 2);
+        assert_eq!(report.owners[2].files, ["web/app.py"]);
+    }
+}
@@ -0,0 +1,509 @@
+//! Package boundaries from the build system (`cartog packages`).
+//!
+//! Monorepo build tools already know which directories form a package and which
+//! packages may depend on which: `cargo metadata` for Cargo workspaces, the Nx
+//! project graph, `bazel query` for Bazel. `sync` reads that graph and stores it
+//! next to the index; queries then map files to packages by their root directory
+//! and compare the declared dependencies with the imports cartog resolved.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::db::Database;
+use crate::shard::ShardedDatabase;
+use crate::types::EdgeKind;
+
+/// Build system a package graph was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildTool {
+    /// Cargo workspace members (`cargo metadata`).
+    Cargo,
+    /// Nx projects (`nx graph --file`).
+    Nx,
+    /// Bazel packages (`bazel query --output=graph`).
+    Bazel,
+}
+
+impl BuildTool {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::Nx => "nx",
+            Self::Bazel => "bazel",
+        }
+    }
+}
+
+impl std::fmt::Display for BuildTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for BuildTool {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "cargo" => Ok(Self::Cargo),
+            "nx" => Ok(Self::Nx),
+            "bazel" => Ok(Self::Bazel),
+            _ => Err(anyhow::anyhow!("unknown build tool: '{s}'")),
+        }
+    }
+}
+
+/// A package as the build system defines it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Package {
+    pub name: String,
+    /// Directory relative to the project root (`.` for the root itself).
+    pub root: String,
+    pub tool: BuildTool,
+    /// Packages of the same graph this one declares a dependency on, by name.
+    pub dependencies: Vec<String>,
+}
+
+/// Result of [`sync`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SyncResult {
+    pub tool: BuildTool,
+    pub packages: usize,
+    pub dependencies: usize,
+}
+
+/// Imports from one package into another, as resolved by cartog.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PackageImport {
+    pub package: String,
+    /// Resolved import edges from files of the importing package.
+    pub imports: u32,
+    /// Whether the build system declares this dependency.
+    pub declared: bool,
+}
+
+/// Result of [`package_deps`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PackageDeps {
+    pub package: Package,
+    /// Packages its files import, most imports first.
+    pub imports: Vec<PackageImport>,
+    /// Declared dependencies none of its resolved imports point into.
+    pub unused: Vec<String>,
+    /// Packages declaring a dependency on it.
+    pub dependents: Vec<String>,
+}
+
+/// The build tool whose marker file is at `root`: `MODULE.bazel`/`WORKSPACE`,
+/// then `nx.json`, then `Cargo.toml`.
+pub fn detect(root: &Path) -> Option<BuildTool> {
+    let has = |file: &str| root.join(file).is_file();
+    if has("MODULE.bazel") || has("WORKSPACE") || has("WORKSPACE.bazel") {
+        Some(BuildTool::Bazel)
+    } else if has("nx.json") {
+        Some(BuildTool::Nx)
+    } else if has("Cargo.toml") {
+        Some(BuildTool::Cargo)
+    } else {
+        None
+    }
+}
+
+/// Read the package graph of `root` from its build tool (detected when `tool` is
+/// `None`) and replace the stored one.
+pub fn sync(db: &Database, root: &Path, tool: Option<BuildTool>) -> Result<SyncResult> {
+    let Some(tool) = tool.or_else(|| detect(root)) else {
+        bail!(
+            "no build system found in {} (looked for MODULE.bazel, WORKSPACE, nx.json, Cargo.toml); \
+             pass --tool",
+            root.display()
+        );
+    };
+    let packages = match tool {
+        BuildTool::Cargo => {
+            let out = run(
+                root,
+                "cargo",
+                &["metadata", "--format-version", "1", "--no-deps"],
+            )?;
+            parse_cargo_metadata(&out, &root.canonicalize()?)?
+        }
+        BuildTool::Nx => {
+            let file = std::env::temp_dir().join(format!("cartog-nx-{}.json", std::process::id()));
+            let arg = format!("--file={}", file.display());
+            run(root, "npx", &["--no-install", "nx", "graph", &arg])?;
+            let out = std::fs::read_to_string(&file)
+                .with_context(|| format!("nx graph wrote no {}", file.display()));
+            let _ = std::fs::remove_file(&file);
+            parse_nx_graph(&out?)?
+        }
+        BuildTool::Bazel => {
+            let out = run(
+                root,
+                "bazel",
+                &[
+                    "query",
+                    "deps(//...)",
+                    "--output=graph",
+                    "--nograph:factored",
+                    "--noimplicit_deps",
+                ],
+            )?;
+            parse_bazel_graph(&out)
+        }
+    };
+    db.replace_packages(&packages)?;
+    Ok(SyncResult {
+        tool,
+        packages: packages.len(),
+        dependencies: packages.iter().map(|p| p.dependencies.len()).sum(),
+    })
+}
+
+/// Run a build tool in `root` and return its stdout.
+fn run(root: &Path, program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run {program}"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Workspace members of `cargo metadata --no-deps` output, with their path
+/// dependencies on each other. Members outside `root` are skipped.
+pub fn parse_cargo_metadata(json: &str, root: &Path) -> Result<Vec<Package>> {
+    let metadata: Value = serde_json::from_str(json).context("invalid cargo metadata output")?;
+    let mut packages = Vec::new();
+    for package in metadata["packages"].as_array().into_iter().flatten() {
+        let (Some(name), Some(manifest)) =
+            (package["name"].as_str(), package["manifest_path"].as_str())
+        else {
+            continue;
+        };
+        let Some(dir) = Path::new(manifest).parent() else {
+            continue;
+        };
+        let Ok(relative) = dir.strip_prefix(root) else {
+            continue;
+        };
+        let deps = package["dependencies"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|d| d["path"].is_string())
+            .filter_map(|d| d["name"].as_str().map(String::from))
+            .collect();
+        packages.push(package_at(
+            name,
+            &relative.to_string_lossy(),
+            BuildTool::Cargo,
+            deps,
+        ));
+    }
+    Ok(finish(packages))
+}
+
+/// Projects of an Nx project graph (`nx graph --file=graph.json`). External
+/// nodes (`npm:...`) are not packages.
+pub fn parse_nx_graph(json: &str) -> Result<Vec<Package>> {
+    let graph: Value = serde_json::from_str(json).context("invalid nx graph output")?;
+    let graph = &graph["graph"];
+    let Some(nodes) = graph["nodes"].as_object() else {
+        bail!("nx graph output has no graph.nodes");
+    };
+    let packages = nodes
+        .iter()
+        .map(|(name, node)| {
+            let deps = graph["dependencies"][name]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|d| d["target"].as_str())
+                .filter(|target| nodes.contains_key(*target))
+                .map(String::from)
+                .collect();
+            let root = node["data"]["root"].as_str().unwrap_or_default();
+            package_at(name, root, BuildTool::Nx, deps)
+        })
+        .collect();
+    Ok(finish(packages))
+}
+
+/// Bazel packages of `bazel query --output=graph --nograph:factored` output: one
+/// package per `//path` of the targets, depending on the packages its targets
+/// depend on. External repositories (`@repo//...`) are skipped.
+pub fn parse_bazel_graph(dot: &str) -> Vec<Package> {
+    let package_of = |label: &str| -> Option<String> {
+        let label = label.trim().trim_matches('"');
+        let path = label.strip_prefix("//")?;
+        Some(path.split(':').next().unwrap_or(path).to_string())
+    };
+    let mut graph: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for line in dot.lines() {
+        let line = line.trim();
+        match line.split_once("->") {
+            Some((from, to)) => {
+                if let Some(from) = package_of(from) {
+                    let to = package_of(to);
+                    let deps = graph.entry(from.clone()).or_default();
+                    if let Some(to) = to.filter(|to| *to != from) {
+                        deps.insert(to.clone());
+                        graph.entry(to).or_default();
+                    }
+                }
+            }
+            None if line.starts_with('"') => {
+                if let Some(package) = package_of(line) {
+                    graph.entry(package).or_default();
+                }
+            }
+            None => {}
+        }
+    }
+    graph
+        .into_iter()
+        .map(|(path, deps)| {
+            let deps = deps.into_iter().map(|d| format!("//{d}")).collect();
+            package_at(&format!("//{path}"), &path, BuildTool::Bazel, deps)
+        })
+        .collect()
+}
+
+fn package_at(name: &str, root: &str, tool: BuildTool, dependencies: Vec<String>) -> Package {
+    let root = root.trim_matches('/');
+    Package {
+        name: name.to_string(),
+        root: if root.is_empty() { "." } else { root }.to_string(),
+        tool,
+        dependencies,
+    }
+}
+
+/// Sort by name and drop dependencies on names outside the graph.
+fn finish(mut packages: Vec<Package>) -> Vec<Package> {
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    let names: BTreeSet<String> = packages.iter().map(|p| p.name.clone()).collect();
+    for package in &mut packages {
+        package.dependencies.retain(|d| names.contains(d));
+        package.dependencies.sort();
+        package.dependencies.dedup();
+    }
+    packages
+}
+
+/// The package containing `file`: the one with the longest matching root.
+pub fn package_of<'a>(packages: &'a [Package], file: &str) -> Option<&'a Package> {
+    packages
+        .iter()
+        .filter(|p| {
+            p.root == "."
+                || file
+                    .strip_prefix(p.root.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        })
+        .max_by_key(|p| if p.root == "." { 0 } else { p.root.len() })
+}
+
+/// What `name` imports, declares and is depended on by.
+pub fn package_deps(db: &ShardedDatabase, name: &str) -> Result<PackageDeps> {
+    let packages = db.packages()?;
+    if packages.is_empty() {
+        bail!("No packages stored; run `cartog packages sync` first");
+    }
+    let Some(package) = packages.iter().find(|p| p.name == name).cloned() else {
+        bail!("No package named '{name}'; `cartog packages list` shows them");
+    };
+
+    let symbols = db.all_symbols()?;
+    let file_of: HashMap<&str, &str> = symbols
+        .iter()
+        .map(|s| (s.id.as_str(), s.file_path.as_str()))
+        .collect();
+    let mut counts: HashMap<&str, u32> = HashMap::new();
+    for edge in db.all_edges()? {
+        if edge.kind != EdgeKind::Imports {
+            continue;
+        }
+        if package_of(&packages, &edge.file_path).map(|p| p.name.as_str()) != Some(name) {
+            continue;
+        }
+        let Some(target_file) = edge.target_id.as_deref().and_then(|id| file_of.get(id)) else {
+            continue;
+        };
+        match package_of(&packages, target_file) {
+            Some(target) if target.name != name => *counts.entry(&target.name).or_default() += 1,
+            _ => {}
+        }
+    }
+
+    let mut imports: Vec<PackageImport> = counts
+        .iter()
+        .map(|(target, &imports)| PackageImport {
+            package: target.to_string(),
+            imports,
+            declared: package.dependencies.iter().any(|d| d == target),
+        })
+        .collect();
+    imports.sort_by(|a, b| {
+        b.imports
+            .cmp(&a.imports)
+            .then_with(|| a.package.cmp(&b.package))
+    });
+    let unused = package
+        .dependencies
+        .iter()
+        .filter(|d| !counts.contains_key(d.as_str()))
+        .cloned()
+        .collect();
+    let dependents = packages
+        .iter()
+        .filter(|p| p.dependencies.iter().any(|d| d == name))
+        .map(|p| p.name.clone())
+        .collect();
+
+    Ok(PackageDeps {
+        package,
+        imports,
+        unused,
+        dependents,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Edge, Symbol, SymbolKind};
+
+    #[test]
+    fn test_parse_cargo_metadata() {
+        let json = r#"{"packages": [
+            {"name": "app", "manifest_path": "/repo/Cargo.toml",
+             "dependencies": [{"name": "core", "path": "/repo/crates/core"}, {"name": "serde"}]},
+            {"name": "core", "manifest_path": "/repo/crates/core/Cargo.toml", "dependencies": []},
+            {"name": "vendored", "manifest_path": "/elsewhere/Cargo.toml", "dependencies": []}
+        ]}"#;
+        let packages = parse_cargo_metadata(json, Path::new("/repo")).unwrap();
+        assert_eq!(
+            packages,
+            [
+                package_at("app", "", BuildTool::Cargo, vec!["core".into()]),
+                package_at("core", "crates/core", BuildTool::Cargo, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_nx_graph() {
+        let json = r#"{"graph": {
+            "nodes": {
+                "web": {"name": "web", "data": {"root": "apps/web"}},
+                "ui": {"name": "ui", "data": {"root": "libs/ui"}}
+            },
+            "dependencies": {
+                "web": [{"source": "web", "target": "ui", "type": "static"},
+                        {"source": "web", "target": "npm:react", "type": "static"}],
+                "ui": []
+            }
+        }}"#;
+        let packages = parse_nx_graph(json).unwrap();
+        assert_eq!(
+            packages[0],
+            package_at("ui", "libs/ui", BuildTool::Nx, vec![])
+        );
+        assert_eq!(packages[1].root, "apps/web");
+        assert_eq!(packages[1].dependencies, ["ui"]);
+    }
+
+    #[test]
+    fn test_parse_bazel_graph() {
+        let dot = r#"digraph mygraph {
+  node [shape=box];
+  "//app:main"
+  "//app:main" -> "//lib/auth:auth"
+  "//app:main" -> "//app:util"
+  "//lib/auth:auth" -> "@maven//:guava"
+}"#;
+        let packages = parse_bazel_graph(dot);
+        assert_eq!(
+            packages,
+            [
+                package_at("//app", "app", BuildTool::Bazel, vec!["//lib/auth".into()]),
+                package_at("//lib/auth", "lib/auth", BuildTool::Bazel, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_package_deps_compares_imports_with_declared() {
+        let db = Database::open_memory().unwrap();
+        db.replace_packages(&[
+            package_at(
+                "app",
+                ".",
+                BuildTool::Cargo,
+                vec!["auth".into(), "log".into()],
+            ),
+            package_at("auth", "crates/auth", BuildTool::Cargo, vec![]),
+            package_at("db", "crates/db", BuildTool::Cargo, vec![]),
+            package_at("log", "crates/log", BuildTool::Cargo, vec![]),
+        ])
+        .unwrap();
+        let sym = |file: &str| Symbol::new("f", SymbolKind::Function, file, 1, 2, 0, 10);
+        let (main, login, query) = (
+            sym("src/main.rs"),
+            sym("crates/auth/src/lib.rs"),
+            sym("crates/db/src/lib.rs"),
+        );
+        db.insert_symbols(&[main.clone(), login.clone(), query.clone()])
+            .unwrap();
+        let import = |target: &Symbol, line| {
+            let mut e = Edge::new(&main.id, "f", EdgeKind::Imports, "src/main.rs", line);
+            e.target_id = Some(target.id.clone());
+            e
+        };
+        db.insert_edges(&[import(&login, 1), import(&query, 2), import(&query, 3)])
+            .unwrap();
+
+        let deps = package_deps(&ShardedDatabase::from(db), "app").unwrap();
+        let imports: Vec<(&str, u32, bool)> = deps
+            .imports
+            .iter()
+            .map(|i| (i.package.as_str(), i.imports, i.declared))
+            .collect();
+        assert_eq!(imports, [("db", 2, false), ("auth", 1, true)]);
+        assert_eq!(deps.unused, ["log"]);
+        assert!(deps.dependents.is_empty());
+    }
+
+    #[test]
+    fn test_package_of_longest_root() {
+        let packages = [
+            package_at("root", ".", BuildTool::Nx, vec![]),
+            package_at("ui", "libs/ui", BuildTool::Nx, vec![]),
+        ];
+        assert_eq!(
+            package_of(&packages, "libs/ui/button.ts").unwrap().name,
+            "ui"
+        );
+        assert_eq!(
+            package_of(&packages, "libs/uikit/a.ts").unwrap().name,
+            "root"
+        );
+    }
+}
@@ -0,0 +1,171 @@
+//! Path access policy for MCP tools that return file content.
+//!
+//! Bodies come from the index rather than from disk, but the index can hold files
+//! reached through a symlink that points out of the project. Before a tool returns
+//! content, each result's file must resolve inside the project root or an extra
+//! allowed root (`serve --allow-root`), and its content is capped at
+//! `serve --max-read-bytes`. Results that fail the check are withheld and logged.
+
+use std::path::{Path, PathBuf};
+
+use tracing::warn;
+
+use crate::mcp::normalize_path;
+
+/// Default cap on the content bytes one result returns.
+pub const DEFAULT_MAX_READ_BYTES: usize = 64 * 1024;
+
+/// Appended to content cut at the read limit.
+const TRUNCATED_MARKER: &str = "\n… (truncated at the server's read limit)";
+
+/// Where content-returning tools may read from, and how much.
+#[derive(Debug, Clone)]
+pub struct PathPolicy {
+    /// Canonical roots allowed besides each project's own root.
+    extra_roots: Vec<PathBuf>,
+    max_read_bytes: usize,
+}
+
+impl Default for PathPolicy {
+    fn default() -> Self {
+        Self {
+            extra_roots: Vec::new(),
+            max_read_bytes: DEFAULT_MAX_READ_BYTES,
+        }
+    }
+}
+
+impl PathPolicy {
+    /// Policy allowing `extra_roots` besides the project roots. Each must exist.
+    pub fn new(extra_roots: &[PathBuf], max_read_bytes: usize) -> anyhow::Result<Self> {
+        anyhow::ensure!(max_read_bytes > 0, "--max-read-bytes must be at least 1");
+        let extra_roots = extra_roots
+            .iter()
+            .map(|root| {
+                root.canonicalize().map_err(|e| {
+                    anyhow::anyhow!("cannot resolve allowed root {}: {e}", root.display())
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            extra_roots,
+            max_read_bytes,
+        })
+    }
+
+    /// Check that `file_path` (relative to the canonical project `root`) resolves,
+    /// symlinks followed, inside `root` or an extra allowed root.
+    pub fn check(&self, root: &Path, file_path: &str) -> Result<(), String> {
+        let candidate = root.join(file_path);
+        // A file deleted since it was indexed can only be checked lexically.
+        let resolved = candidate
+            .canonicalize()
+            .unwrap_or_else(|_| normalize_path(&candidate));
+        if resolved.starts_with(root) || self.extra_roots.iter().any(|r| resolved.starts_with(r)) {
+            Ok(())
+        } else {
+            Err(format!(
+                "'{file_path}' resolves to {} outside the allowed roots",
+                resolved.display()
+            ))
+        }
+    }
+
+    /// Withhold the `items` whose file fails [`check`](Self::check) and cap the
+    /// content of the others. Violations are logged under `tool`.
+    pub fn enforce<T>(
+        &self,
+        tool: &str,
+        root: &Path,
+        items: &mut Vec<T>,
+        file_path: impl Fn(&T) -> &str,
+        content: impl Fn(&mut T) -> Option<&mut String>,
+    ) {
+        items.retain_mut(|item| {
+            if let Err(reason) = self.check(root, file_path(item)) {
+                warn!(tool, reason = %reason, "path policy violation, result withheld");
+                return false;
+            }
+            if let Some(text) = content(item) {
+                self.cap(text);
+            }
+            true
+        });
+    }
+
+    /// Cut `text` to the read limit, on a character boundary.
+    pub fn cap(&self, text: &mut String) {
+        if text.len() <= self.max_read_bytes {
+            return;
+        }
+        let mut end = self.max_read_bytes;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        text.push_str(TRUNCATED_MARKER);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let base = std::env::temp_dir().join(format!("cartog_policy_{name}"));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("project/src")).unwrap();
+        std::fs::create_dir_all(base.join("outside")).unwrap();
+        std::fs::write(base.join("project/src/a.py"), "def a(): pass\n").unwrap();
+        std::fs::write(base.join("outside/secret.py"), "TOKEN = 1\n").unwrap();
+        base.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn test_check_rejects_escapes_and_allows_extra_roots() {
+        let base = temp_root("check");
+        let root = base.join("project");
+        let policy = PathPolicy::default();
+        assert!(policy.check(&root, "src/a.py").is_ok());
+        assert!(policy.check(&root, "src/deleted.py").is_ok());
+        assert!(policy.check(&root, "../outside/secret.py").is_err());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(base.join("outside"), root.join("vendor")).unwrap();
+            assert!(policy.check(&root, "vendor/secret.py").is_err());
+            let allowed = PathPolicy::new(&[base.join("outside")], 100).unwrap();
+            assert!(allowed.check(&root, "vendor/secret.py").is_ok());
+        }
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_enforce_withholds_and_caps() {
+        let base = temp_root("enforce");
+        let root = base.join("project");
+        let policy = PathPolicy::new(&[], 2).unwrap();
+        let mut items = vec![
+            ("src/a.py".to_string(), Some("déf a(): pass".to_string())),
+            (
+                "../outside/secret.py".to_string(),
+                Some("TOKEN = 1".to_string()),
+            ),
+            ("src/a.py".to_string(), None),
+        ];
+        policy.enforce(
+            "test",
+            &root,
+            &mut items,
+            |i| i.0.as_str(),
+            |i| i.1.as_mut(),
+        );
+        assert_eq!(items.len(), 2);
+        // `é` spans bytes 1-2, so the cut falls back to before it.
+        let capped = format!("d{TRUNCATED_MARKER}");
+        assert_eq!(items[0].1.as_deref(), Some(capped.as_str()));
+        assert_eq!(items[1].1, None);
+        assert!(PathPolicy::new(&[], 0).is_err());
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
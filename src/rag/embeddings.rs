@@ -1,46 +1,138 @@
-use anyhow::{Context, Result};
+#[cfg(feature = "rag")]
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(feature = "rag")]
 use fastembed::{EmbeddingModel, TextEmbedding, TextInitOptions};
 
-use super::{model_cache_dir, EMBEDDING_DIM};
+#[cfg(feature = "rag")]
+use super::model_cache_dir;
+use super::EMBEDDING_DIM;
 
 /// Batch size for fastembed internal sub-batching.
 /// Smaller batches reduce padding waste when text lengths vary widely.
+#[cfg(feature = "rag")]
 const EMBED_BATCH_SIZE: usize = 64;
 
+/// Embedding models an index can use, named as stored in the database.
+///
+/// Vectors of different models are not comparable, so an index uses one at a time;
+/// `cartog rag migrate` switches between them (see [`super::migrate`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum EmbeddingModelId {
+    /// BGE-small-en-v1.5, quantized (384 dims).
+    #[default]
+    BgeSmallEnV15,
+    /// BGE-base-en-v1.5, quantized (768 dims): better recall, about 3x slower to embed.
+    BgeBaseEnV15,
+    /// all-MiniLM-L6-v2, quantized (384 dims): the fastest to embed.
+    AllMiniLmL6V2,
+}
+
+impl EmbeddingModelId {
+    pub const ALL: &'static [Self] =
+        &[Self::BgeSmallEnV15, Self::BgeBaseEnV15, Self::AllMiniLmL6V2];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::BgeSmallEnV15 => "bge-small-en-v1.5",
+            Self::BgeBaseEnV15 => "bge-base-en-v1.5",
+            Self::AllMiniLmL6V2 => "all-minilm-l6-v2",
+        }
+    }
+
+    /// Length of the vectors the model produces.
+    pub fn dim(self) -> usize {
+        match self {
+            Self::BgeSmallEnV15 | Self::AllMiniLmL6V2 => EMBEDDING_DIM,
+            Self::BgeBaseEnV15 => 768,
+        }
+    }
+
+    /// Whether the model's ONNX file was downloaded, checked without loading it.
+    #[cfg(feature = "rag")]
+    pub fn is_downloaded(self) -> bool {
+        TextEmbedding::get_model_info(&self.fastembed())
+            .is_ok_and(|info| super::is_cached(&info.model_code, &info.model_file))
+    }
+
+    /// Models are never downloaded without the `rag` feature.
+    #[cfg(not(feature = "rag"))]
+    pub fn is_downloaded(self) -> bool {
+        false
+    }
+
+    #[cfg(feature = "rag")]
+    fn fastembed(self) -> EmbeddingModel {
+        match self {
+            Self::BgeSmallEnV15 => EmbeddingModel::BGESmallENV15Q,
+            Self::BgeBaseEnV15 => EmbeddingModel::BGEBaseENV15Q,
+            Self::AllMiniLmL6V2 => EmbeddingModel::AllMiniLML6V2Q,
+        }
+    }
+}
+
+impl std::fmt::Display for EmbeddingModelId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for EmbeddingModelId {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|m| m.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|m| m.name()).collect();
+                format!(
+                    "unknown embedding model '{s}' (expected one of: {})",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
 /// Embedding engine wrapping a fastembed ONNX model.
 ///
 /// Uses ONNX Runtime for inference with SIMD and graph-level optimizations.
 /// The quantized model (BGESmallENV15Q) is ~2-3x faster than full precision
 /// with negligible quality loss.
+#[cfg(feature = "rag")]
 pub struct EmbeddingEngine {
     model: TextEmbedding,
+    dim: usize,
 }
 
+#[cfg(feature = "rag")]
 impl EmbeddingEngine {
-    /// Create a new embedding engine using the quantized BGE-small-en-v1.5 model.
+    /// Create a new embedding engine using the default model (quantized BGE-small-en-v1.5).
     ///
     /// Models are cached in the shared directory (see [`super::model_cache_dir`]).
     pub fn new() -> Result<Self> {
-        let model = TextEmbedding::try_new(
-            TextInitOptions::new(EmbeddingModel::BGESmallENV15Q)
-                .with_cache_dir(model_cache_dir())
-                .with_show_download_progress(false),
-        )
-        .context("Failed to initialize embedding model")?;
-
-        Ok(Self { model })
+        Self::load(EmbeddingModelId::default(), false)
     }
 
     /// Create a new embedding engine, showing download progress on stdout.
     pub fn new_with_progress() -> Result<Self> {
-        let model = TextEmbedding::try_new(
-            TextInitOptions::new(EmbeddingModel::BGESmallENV15Q)
+        Self::load(EmbeddingModelId::default(), true)
+    }
+
+    /// Create an embedding engine for `model`, downloading it on first use.
+    pub fn load(model: EmbeddingModelId, show_progress: bool) -> Result<Self> {
+        let engine = TextEmbedding::try_new(
+            TextInitOptions::new(model.fastembed())
                 .with_cache_dir(model_cache_dir())
-                .with_show_download_progress(true),
+                .with_show_download_progress(show_progress),
         )
-        .context("Failed to initialize embedding model")?;
+        .with_context(|| format!("Failed to initialize embedding model {model}"))?;
 
-        Ok(Self { model })
+        Ok(Self {
+            model: engine,
+            dim: model.dim(),
+        })
     }
 
     /// Embed a single text string, returning a normalized vector.
@@ -57,8 +149,9 @@ impl EmbeddingEngine {
 
         debug_assert_eq!(
             vec.len(),
-            EMBEDDING_DIM,
-            "Expected {EMBEDDING_DIM}-dim embedding, got {}",
+            self.dim,
+            "Expected {}-dim embedding, got {}",
+            self.dim,
             vec.len()
         );
 
@@ -79,14 +172,44 @@ impl EmbeddingEngine {
             .context("Batch embedding failed")?;
 
         debug_assert!(
-            results.iter().all(|v| v.len() == EMBEDDING_DIM),
-            "All embeddings should be {EMBEDDING_DIM}-dim"
+            results.iter().all(|v| v.len() == self.dim),
+            "All embeddings should be {}-dim",
+            self.dim
         );
 
         Ok(results)
     }
 }
 
+/// Stand-in when the `rag` feature is compiled out: never constructible.
+#[cfg(not(feature = "rag"))]
+pub struct EmbeddingEngine {
+    never: std::convert::Infallible,
+}
+
+#[cfg(not(feature = "rag"))]
+impl EmbeddingEngine {
+    pub fn new() -> Result<Self> {
+        Err(super::models_disabled())
+    }
+
+    pub fn new_with_progress() -> Result<Self> {
+        Self::new()
+    }
+
+    pub fn load(_model: EmbeddingModelId, _show_progress: bool) -> Result<Self> {
+        Self::new()
+    }
+
+    pub fn embed(&mut self, _text: &str) -> Result<Vec<f32>> {
+        match self.never {}
+    }
+
+    pub fn embed_batch(&mut self, _texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        match self.never {}
+    }
+}
+
 /// Serialize a Vec<f32> to little-endian bytes for sqlite-vec storage.
 pub fn embedding_to_bytes(embedding: &[f32]) -> Vec<u8> {
     let mut bytes = Vec::with_capacity(embedding.len() * 4);
@@ -124,6 +247,19 @@ mod tests {
         assert_eq!(bytes.len(), EMBEDDING_DIM * 4);
     }
 
+    #[test]
+    fn test_embedding_model_names_roundtrip() {
+        for &model in EmbeddingModelId::ALL {
+            assert_eq!(model.name().parse::<EmbeddingModelId>(), Ok(model));
+        }
+        assert_eq!(
+            "BGE-Base-EN-v1.5".parse::<EmbeddingModelId>(),
+            Ok(EmbeddingModelId::BgeBaseEnV15)
+        );
+        assert_eq!(EmbeddingModelId::default().dim(), EMBEDDING_DIM);
+        assert!("ada-002".parse::<EmbeddingModelId>().is_err());
+    }
+
     #[test]
     fn test_empty_bytes_roundtrip() {
         let original: Vec<f32> = vec![];
@@ -0,0 +1,129 @@
+//! When embeddings catch up with the code index, and how far behind they are.
+//!
+//! Re-indexing stores symbol contents right away, but embedding them needs the model
+//! and takes a while, so `serve --rag` picks a policy: `eager` embeds in the
+//! background after every index update (like `watch --rag`), `lazy` waits for the
+//! first semantic query and embeds the backlog before answering it, and `manual`
+//! leaves it to `cartog rag index`. The policy is recorded in the index, so
+//! `cartog rag status` can report it next to the backlog size.
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tracing::info;
+
+use crate::db::Database;
+
+use super::indexer::{index_embeddings, pending_embeddings, RagIndexResult};
+use super::migrate::{self, MigrationStatus};
+
+/// When symbols are embedded after the code index changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbedPolicy {
+    /// Embed in the background after every index update.
+    Eager,
+    /// Embed the backlog on the first semantic query after an update.
+    Lazy,
+    /// Only embed on `cartog rag index`.
+    #[default]
+    Manual,
+}
+
+impl EmbedPolicy {
+    pub const ALL: [Self; 3] = [Self::Eager, Self::Lazy, Self::Manual];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Eager => "eager",
+            Self::Lazy => "lazy",
+            Self::Manual => "manual",
+        }
+    }
+}
+
+impl std::fmt::Display for EmbedPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for EmbedPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.as_str().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!("unknown embedding policy '{s}' (expected one of: eager, lazy, manual)")
+            })
+    }
+}
+
+/// The policy recorded in `db`; `manual` when none was.
+pub fn policy(db: &Database) -> Result<EmbedPolicy> {
+    Ok(db
+        .embed_policy()?
+        .and_then(|stored| stored.parse().ok())
+        .unwrap_or_default())
+}
+
+/// Where the embeddings of an index stand (`cartog rag status`).
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RagStatus {
+    /// Policy of the last `serve --rag` or `watch --rag` on this index.
+    pub policy: EmbedPolicy,
+    /// Symbols with an embedding.
+    pub embedded: u32,
+    /// Symbols eligible for embedding (content stored, variables excluded).
+    pub embeddable: u32,
+    /// Eligible symbols still waiting for an embedding.
+    pub backlog: u32,
+    /// The embedding models and the migration in progress, if any.
+    pub models: MigrationStatus,
+}
+
+/// The policy, counts and models of the index.
+pub fn status(db: &Database) -> Result<RagStatus> {
+    Ok(RagStatus {
+        policy: policy(db)?,
+        embedded: db.embedding_count()?,
+        embeddable: db.embeddable_symbol_count()?,
+        backlog: pending_embeddings(db, Default::default())?.len() as u32,
+        models: migrate::status(db)?,
+    })
+}
+
+/// Embed the backlog, if any, before a semantic query (the `lazy` policy). Nothing
+/// to do without sqlite-vec, where the query runs keyword-only.
+pub fn catch_up(db: &Database) -> Result<Option<RagIndexResult>> {
+    if !db.has_vectors() {
+        return Ok(None);
+    }
+    let backlog = pending_embeddings(db, Default::default())?.len();
+    if backlog == 0 {
+        return Ok(None);
+    }
+    info!(backlog, "embedding backlog before semantic query");
+    index_embeddings(db, false, Default::default()).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_defaults_to_manual_and_round_trips() {
+        let db = Database::open_memory().unwrap();
+        assert_eq!(policy(&db).unwrap(), EmbedPolicy::Manual);
+        db.set_embed_policy(EmbedPolicy::Lazy.as_str()).unwrap();
+        assert_eq!(policy(&db).unwrap(), EmbedPolicy::Lazy);
+        assert_eq!("EAGER".parse::<EmbedPolicy>(), Ok(EmbedPolicy::Eager));
+        assert!("sometimes".parse::<EmbedPolicy>().is_err());
+
+        let status = status(&db).unwrap();
+        assert_eq!((status.embedded, status.backlog), (0, 0));
+        assert!(catch_up(&db).unwrap().is_none());
+    }
+}
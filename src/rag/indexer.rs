@@ -1,16 +1,23 @@
 use anyhow::{Context, Result};
 use tracing::info;
 
-use crate::db::Database;
+use crate::db::{Database, VectorTable};
+use crate::testmap::is_test_file;
+use crate::types::{Symbol, Visibility};
 
-use super::embeddings::{embedding_to_bytes, EmbeddingEngine};
+use super::embeddings::{embedding_to_bytes, EmbeddingEngine, EmbeddingModelId};
+use super::migrate::{self, IndexModels, MigrationStatus};
 
 /// Result of a RAG indexing operation.
-#[derive(Debug, Default, serde::Serialize)]
+#[derive(Debug, Default, serde::Serialize, schemars::JsonSchema)]
 pub struct RagIndexResult {
     pub symbols_embedded: u32,
     pub symbols_skipped: u32,
     pub total_content_symbols: u32,
+    /// Progress of the embedding model migration in progress, if any (see
+    /// [`super::migrate`]): this run also embedded symbols with the next model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub migration: Option<MigrationStatus>,
 }
 
 /// Maximum number of texts sent to the embedding engine in one call.
@@ -26,6 +33,7 @@ const DB_BATCH_LIMIT: usize = 256;
 fn flush_embedding_batch(
     engine: &mut EmbeddingEngine,
     db: &Database,
+    table: VectorTable,
     texts: &[String],
     symbol_ids: &[String],
     db_batch: &mut Vec<(i64, Vec<u8>)>,
@@ -41,7 +49,7 @@ fn flush_embedding_batch(
                 result.symbols_embedded += 1;
 
                 if db_batch.len() >= DB_BATCH_LIMIT {
-                    db.insert_embeddings(db_batch)?;
+                    db.insert_embeddings_into(table, db_batch)?;
                     db_batch.clear();
                 }
             }
@@ -61,7 +69,7 @@ fn flush_embedding_batch(
                         count += 1;
 
                         if db_batch.len() >= DB_BATCH_LIMIT {
-                            db.insert_embeddings(db_batch)?;
+                            db.insert_embeddings_into(table, db_batch)?;
                             db_batch.clear();
                         }
                     }
@@ -86,14 +94,60 @@ pub fn compact_embedding_text(header: &str, content: &str) -> String {
     format!("{}\n{}", header, first_line)
 }
 
+/// Which symbols to embed, so a watcher on a busy repo only embeds what gets searched.
+///
+/// The default embeds every symbol with content (variables excepted).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EmbedFilter {
+    /// Skip symbols in test files (see [`is_test_file`]).
+    pub skip_tests: bool,
+    /// Only embed public symbols.
+    pub public_only: bool,
+}
+
+impl EmbedFilter {
+    fn is_noop(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn accepts(&self, sym: &Symbol) -> bool {
+        !(self.skip_tests && is_test_file(&sym.file_path)
+            || self.public_only && sym.visibility != Visibility::Public)
+    }
+
+    /// Keep the IDs of symbols the filter accepts, in order.
+    fn retain(&self, db: &Database, ids: Vec<String>) -> Result<Vec<String>> {
+        if self.is_noop() {
+            return Ok(ids);
+        }
+        Ok(db
+            .get_symbols_by_ids(&ids)?
+            .into_iter()
+            .filter(|sym| self.accepts(sym))
+            .map(|sym| sym.id)
+            .collect())
+    }
+}
+
+/// Symbols with content but no embedding yet that `filter` lets through.
+pub fn pending_embeddings(db: &Database, filter: EmbedFilter) -> Result<Vec<String>> {
+    filter.retain(db, db.symbols_needing_embeddings()?)
+}
+
 /// Embed all symbols that have content but no embedding yet.
 ///
 /// Requires the embedding model to be available (downloaded via `cartog rag setup`
 /// or auto-downloaded on first use by fastembed).
 /// When `force` is true, clears all existing embeddings and re-embeds everything.
-pub fn index_embeddings(db: &Database, force: bool) -> Result<RagIndexResult> {
-    info!("Loading embedding model...");
-    let mut engine = EmbeddingEngine::new()
+/// Symbols `filter` rejects are left without an embedding.
+///
+/// During an embedding model migration, symbols are also embedded with the next
+/// model; once they all are, the migration completes.
+pub fn index_embeddings(db: &Database, force: bool, filter: EmbedFilter) -> Result<RagIndexResult> {
+    super::ensure_models_enabled()?;
+    let models = IndexModels::of(db)?;
+    info!("Loading embedding model {}...", models.current);
+    let mut engine = EmbeddingEngine::load(models.current, false)
         .context("Failed to load embedding model. Run 'cartog rag setup' to download it.")?;
 
     let total_content_symbols = db.symbol_content_count()?;
@@ -104,9 +158,9 @@ pub fn index_embeddings(db: &Database, force: bool) -> Result<RagIndexResult> {
     }
 
     let symbol_ids = if force {
-        db.all_content_symbol_ids()?
+        filter.retain(db, db.all_content_symbol_ids()?)?
     } else {
-        db.symbols_needing_embeddings()?
+        pending_embeddings(db, filter)?
     };
 
     let mut result = RagIndexResult {
@@ -116,9 +170,60 @@ pub fn index_embeddings(db: &Database, force: bool) -> Result<RagIndexResult> {
 
     if symbol_ids.is_empty() {
         info!("No symbols need embedding");
-        return Ok(result);
+    } else {
+        embed_symbols(
+            &mut engine,
+            db,
+            VectorTable::Current,
+            &symbol_ids,
+            &mut result,
+        )?;
+    }
+
+    if let Some(next) = models.next {
+        // Free the current model before loading the next one
+        #[cfg(feature = "rag")]
+        drop(engine);
+        result.migration = Some(migrate_embeddings(db, next, filter)?);
     }
 
+    Ok(result)
+}
+
+/// Embed the symbols the next model has no vector for, then complete the migration.
+fn migrate_embeddings(
+    db: &Database,
+    next: EmbeddingModelId,
+    filter: EmbedFilter,
+) -> Result<MigrationStatus> {
+    let symbol_ids = filter.retain(db, db.symbols_needing_embeddings_in(VectorTable::Next)?)?;
+    if !symbol_ids.is_empty() {
+        info!("Loading embedding model {next} (migration)...");
+        let mut engine = EmbeddingEngine::load(next, false).with_context(|| {
+            format!("Failed to load embedding model {next} to migrate the index to")
+        })?;
+        let mut next_result = RagIndexResult::default();
+        embed_symbols(
+            &mut engine,
+            db,
+            VectorTable::Next,
+            &symbol_ids,
+            &mut next_result,
+        )?;
+    }
+    // Symbols that failed to embed are left out rather than blocking the switch
+    // forever, as they are with the current model.
+    migrate::finish(db)
+}
+
+/// Embed `symbol_ids` in chunks, writing the vectors to `table`.
+fn embed_symbols(
+    engine: &mut EmbeddingEngine,
+    db: &Database,
+    table: VectorTable,
+    symbol_ids: &[String],
+    result: &mut RagIndexResult,
+) -> Result<()> {
     info!("Embedding {} symbols...", symbol_ids.len());
 
     let mut db_batch: Vec<(i64, Vec<u8>)> = Vec::with_capacity(DB_BATCH_LIMIT);
@@ -128,6 +233,8 @@ pub fn index_embeddings(db: &Database, force: bool) -> Result<RagIndexResult> {
     let total = symbol_ids.len();
     let mut processed = 0usize;
 
+    crate::jobs::checkpoint(total, 0)?;
+
     // Process in chunks, batch-fetching content for each chunk
     for chunk in symbol_ids.chunks(CHUNK_SIZE) {
         let chunk_vec: Vec<String> = chunk.to_vec();
@@ -147,12 +254,13 @@ pub fn index_embeddings(db: &Database, force: bool) -> Result<RagIndexResult> {
 
             if texts.len() >= CHUNK_SIZE {
                 let count = flush_embedding_batch(
-                    &mut engine,
+                    engine,
                     db,
+                    table,
                     &texts,
                     &text_symbol_ids,
                     &mut db_batch,
-                    &mut result,
+                    result,
                 )?;
                 processed += count;
                 texts.clear();
@@ -163,24 +271,26 @@ pub fn index_embeddings(db: &Database, force: bool) -> Result<RagIndexResult> {
                 }
             }
         }
+        crate::jobs::checkpoint(0, chunk.len())?;
     }
 
     // Flush remaining texts
     if !texts.is_empty() {
         let count = flush_embedding_batch(
-            &mut engine,
+            engine,
             db,
+            table,
             &texts,
             &text_symbol_ids,
             &mut db_batch,
-            &mut result,
+            result,
         )?;
         processed += count;
     }
 
     // Flush remaining DB writes
     if !db_batch.is_empty() {
-        db.insert_embeddings(&db_batch)?;
+        db.insert_embeddings_into(table, &db_batch)?;
     }
 
     info!(
@@ -188,12 +298,45 @@ pub fn index_embeddings(db: &Database, force: bool) -> Result<RagIndexResult> {
         result.symbols_embedded, result.symbols_skipped
     );
 
-    Ok(result)
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::SymbolKind;
+
+    #[test]
+    fn test_embed_filter_accepts() {
+        let mut sym = Symbol::new("helper", SymbolKind::Function, "src/util.py", 1, 2, 0, 10);
+        let test_sym = Symbol::new(
+            "check",
+            SymbolKind::Function,
+            "tests/test_util.py",
+            1,
+            2,
+            0,
+            10,
+        );
+
+        let all = EmbedFilter::default();
+        assert!(all.accepts(&sym) && all.accepts(&test_sym));
+
+        let no_tests = EmbedFilter {
+            skip_tests: true,
+            ..Default::default()
+        };
+        assert!(no_tests.accepts(&sym));
+        assert!(!no_tests.accepts(&test_sym));
+
+        let public_only = EmbedFilter {
+            public_only: true,
+            ..Default::default()
+        };
+        assert!(public_only.accepts(&sym));
+        sym.visibility = Visibility::Private;
+        assert!(!public_only.accepts(&sym));
+    }
 
     #[test]
     fn test_compact_embedding_text_header_plus_first_line() {
@@ -0,0 +1,149 @@
+//! Switching embedding models without a search outage (`cartog rag migrate`).
+//!
+//! Re-embedding a large index takes a while, and clearing the old vectors first
+//! leaves semantic search degraded until it is done. A migration instead keeps the
+//! current model's vectors in `symbol_vec` while the new model's fill
+//! `symbol_vec_next` (on `cartog rag index`, or in the background under
+//! `cartog watch --rag`). Searches meanwhile query both and merge the hits with
+//! normalized scores. Once every symbol has a new vector, the next table replaces
+//! the current one.
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::db::Database;
+
+use super::embeddings::EmbeddingModelId;
+
+/// Embedding models of an index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexModels {
+    /// Model the index's vectors come from.
+    pub current: EmbeddingModelId,
+    /// Model being migrated to, if a migration is in progress.
+    pub next: Option<EmbeddingModelId>,
+}
+
+impl IndexModels {
+    /// The models recorded in `db`.
+    pub fn of(db: &Database) -> Result<Self> {
+        let parse = |stored: String| {
+            stored
+                .parse::<EmbeddingModelId>()
+                .map_err(|e| anyhow::anyhow!("{e}: the index was embedded by a newer cartog"))
+        };
+        Ok(Self {
+            current: db
+                .embedding_model()?
+                .map(parse)
+                .transpose()?
+                .unwrap_or_default(),
+            next: db.next_embedding_model()?.map(parse).transpose()?,
+        })
+    }
+}
+
+/// Progress of an embedding model migration.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct MigrationStatus {
+    /// Model the index's vectors come from.
+    pub current: String,
+    /// Model being migrated to; absent when no migration is in progress.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    /// Symbols with a vector from the next model.
+    pub next_embedded: u32,
+    /// Symbols eligible for embedding (content stored, variables excluded).
+    pub embeddable: u32,
+    /// Whether the migration completed during this call: `current` is the new model.
+    pub completed: bool,
+}
+
+/// Where the index's embedding models stand.
+pub fn status(db: &Database) -> Result<MigrationStatus> {
+    let models = IndexModels::of(db)?;
+    Ok(MigrationStatus {
+        current: models.current.to_string(),
+        next: models.next.map(|m| m.to_string()),
+        next_embedded: db.next_embedding_count()?,
+        embeddable: db.embeddable_symbol_count()?,
+        completed: false,
+    })
+}
+
+/// Start migrating the index to `model`.
+///
+/// Starting again with the model already being migrated to keeps its vectors;
+/// another model restarts the migration from scratch.
+pub fn start(db: &Database, model: EmbeddingModelId) -> Result<MigrationStatus> {
+    super::ensure_models_enabled()?;
+    let models = IndexModels::of(db)?;
+    if models.next == Some(model) {
+        return status(db);
+    }
+    if models.current == model {
+        match models.next {
+            Some(next) => anyhow::bail!(
+                "the index already uses {model}; run `cartog rag migrate --cancel` to abandon \
+                 the migration to {next}"
+            ),
+            None => anyhow::bail!("the index already uses {model}"),
+        }
+    }
+    db.start_embedding_transition(model.name(), model.dim())
+        .context("Failed to create the vector table of the new model")?;
+    status(db)
+}
+
+/// Abandon the migration in progress, dropping the new model's vectors.
+pub fn cancel(db: &Database) -> Result<MigrationStatus> {
+    if IndexModels::of(db)?.next.is_none() {
+        anyhow::bail!("no embedding model migration in progress");
+    }
+    db.cancel_embedding_transition()?;
+    status(db)
+}
+
+/// Make the next model current once its vectors are filled in.
+pub(super) fn finish(db: &Database) -> Result<MigrationStatus> {
+    let mut done = status(db)?;
+    let next = IndexModels::of(db)?
+        .next
+        .context("no embedding model migration in progress")?;
+    db.finish_embedding_transition(next.dim())?;
+    tracing::info!(model = %next, "embedding model migration complete");
+    done.current = next.to_string();
+    done.next = None;
+    done.completed = true;
+    Ok(done)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_models_default_and_stored() {
+        let db = Database::open_memory().unwrap();
+        let models = IndexModels::of(&db).unwrap();
+        assert_eq!(models.current, EmbeddingModelId::BgeSmallEnV15);
+        assert_eq!(models.next, None);
+
+        let next = EmbeddingModelId::BgeBaseEnV15;
+        db.start_embedding_transition(next.name(), next.dim())
+            .unwrap();
+        assert_eq!(IndexModels::of(&db).unwrap().next, Some(next));
+        let status = status(&db).unwrap();
+        assert_eq!(status.next.as_deref(), Some("bge-base-en-v1.5"));
+        assert_eq!(status.next_embedded, 0);
+
+        let done = finish(&db).unwrap();
+        assert!(done.completed);
+        assert_eq!(IndexModels::of(&db).unwrap().current, next);
+        assert!(cancel(&db).is_err());
+
+        db.start_embedding_transition("ada-002", 4).unwrap();
+        assert!(IndexModels::of(&db).is_err());
+    }
+}
@@ -1,12 +1,37 @@
 pub mod embeddings;
+pub mod freshness;
 pub mod indexer;
+pub mod migrate;
+pub mod pack;
 pub mod reranker;
 pub mod search;
 pub mod setup;
 
-/// Embedding dimension for the bge-small-en-v1.5 model.
+/// Embedding dimension of the default bge-small-en-v1.5 model.
 pub const EMBEDDING_DIM: usize = 384;
 
+/// Whether the embedding/re-ranker models were compiled in (the `rag` cargo feature).
+pub const MODELS_ENABLED: bool = cfg!(feature = "rag");
+
+/// Fail with a clear message when the `rag` feature was compiled out.
+///
+/// Keyword (FTS5) search keeps working without it; only model-backed
+/// operations (setup, embedding, vector search, re-ranking) call this.
+pub fn ensure_models_enabled() -> anyhow::Result<()> {
+    if MODELS_ENABLED {
+        Ok(())
+    } else {
+        Err(models_disabled())
+    }
+}
+
+fn models_disabled() -> anyhow::Error {
+    anyhow::anyhow!(
+        "embedding models are not available: cartog was built without the `rag` feature \
+         (rebuild with `cargo install cartog --features rag`)"
+    )
+}
+
 /// Shared model cache directory for ONNX models (embedding + reranker).
 ///
 /// Precedence:
@@ -36,6 +61,20 @@ pub fn model_cache_dir() -> std::path::PathBuf {
     std::path::PathBuf::from(".fastembed_cache")
 }
 
+/// Whether `file` of the Hugging Face repository `repo` is in [`model_cache_dir`],
+/// laid out as hf-hub downloads it: `models--ORG--NAME/snapshots/REVISION/FILE`.
+#[cfg(feature = "rag")]
+fn is_cached(repo: &str, file: &str) -> bool {
+    let snapshots = model_cache_dir()
+        .join(format!("models--{}", repo.replace('/', "--")))
+        .join("snapshots");
+    std::fs::read_dir(snapshots).is_ok_and(|revisions| {
+        revisions
+            .flatten()
+            .any(|revision| revision.path().join(file).is_file())
+    })
+}
+
 /// Get the user's home directory (no external dependency needed).
 fn home_dir() -> Option<std::path::PathBuf> {
     std::env::var("HOME")
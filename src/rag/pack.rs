@@ -0,0 +1,161 @@
+//! Fit `rag search` results into a token budget (`--budget`).
+//!
+//! Every result keeps its symbol metadata and the first line of its content
+//! (the signature), so the agent still sees the whole ranking. The remaining
+//! budget goes to full bodies, greedily by score per extra token: a short,
+//! relevant function is worth more than a long one that barely matched.
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use super::search::{HybridSearchResult, SearchResult};
+use crate::tokens;
+
+/// What [`pack`] did, reported as `packing` in the search result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct PackSummary {
+    pub budget: u64,
+    /// Estimated tokens of all result contents after packing. Exceeds `budget`
+    /// only when the signatures alone do.
+    pub tokens: u64,
+    pub full_bodies: u32,
+    pub signatures_only: u32,
+}
+
+/// Cut bodies from `result` until its contents fit in `budget` tokens.
+///
+/// Results are not dropped or reordered. A result whose body is cut keeps the
+/// first non-blank line of its content and is flagged `body_omitted`. The
+/// summary is also stored in `result.packing`.
+pub fn pack(result: &mut HybridSearchResult, budget: u64) -> PackSummary {
+    let results = &mut result.results;
+    let costs: Vec<(u64, u64)> = results
+        .iter()
+        .map(|r| {
+            let content = r.content.as_deref().unwrap_or_default();
+            (
+                tokens::estimate(content),
+                tokens::estimate(signature_line(content)),
+            )
+        })
+        .collect();
+
+    let mut used: u64 = costs.iter().map(|&(_, sig)| sig).sum();
+    let mut order: Vec<usize> = (0..results.len()).collect();
+    let value = |i: usize| score(&results[i]) / (costs[i].0 - costs[i].1).max(1) as f64;
+    order.sort_by(|&a, &b| value(b).total_cmp(&value(a)));
+
+    let mut keep = vec![false; results.len()];
+    for i in order {
+        let extra = costs[i].0 - costs[i].1;
+        if used + extra <= budget {
+            used += extra;
+            keep[i] = true;
+        }
+    }
+
+    let mut full_bodies = 0;
+    for (r, keep) in results.iter_mut().zip(keep) {
+        if keep {
+            full_bodies += 1;
+        } else {
+            omit_body(r);
+        }
+    }
+    let summary = PackSummary {
+        budget,
+        tokens: used,
+        full_bodies,
+        signatures_only: results.len() as u32 - full_bodies,
+    };
+    result.packing = Some(summary);
+    summary
+}
+
+/// Calibrated relevance when re-ranked, else the fused rank score.
+fn score(r: &SearchResult) -> f64 {
+    r.relevance.unwrap_or(r.rrf_score)
+}
+
+/// First non-blank line of `content`, with any leading blank lines.
+fn signature_line(content: &str) -> &str {
+    let start = content.len() - content.trim_start().len();
+    let end = content[start..]
+        .find('\n')
+        .map_or(content.len(), |i| start + i);
+    content[..end].trim_end()
+}
+
+fn omit_body(r: &mut SearchResult) {
+    let Some(content) = r.content.as_mut() else {
+        return;
+    };
+    let kept = signature_line(content).len();
+    if kept == content.len() {
+        return;
+    }
+    content.truncate(kept);
+    r.content_matches.retain(|m| m.end as usize <= kept);
+    r.body_omitted = true;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rag::search::SearchMode;
+    use crate::types::{Symbol, SymbolKind};
+
+    fn result(name: &str, relevance: f64, content: &str) -> SearchResult {
+        SearchResult {
+            symbol: Symbol::new(name, SymbolKind::Function, "a.py", 1, 10, 0, 100),
+            content: Some(content.to_string()),
+            rrf_score: 0.0,
+            rerank_score: None,
+            relevance: Some(relevance),
+            sources: vec!["fts5".to_string()],
+            name_matches: Vec::new(),
+            content_matches: Vec::new(),
+            why: "name:test".to_string(),
+            body_omitted: false,
+        }
+    }
+
+    #[test]
+    fn test_pack_prefers_score_per_token() {
+        let long_body = format!("def long():\n{}", "    x = 1\n".repeat(40));
+        let mut search = HybridSearchResult {
+            results: vec![
+                result("long", 0.9, &long_body),
+                result("short", 0.8, "def short():\n    return 1\n"),
+                result("weak", 0.1, "def weak():\n    return 2\n"),
+            ],
+            fts_count: 3,
+            vec_count: 0,
+            merged_count: 3,
+            mode: SearchMode::Keyword,
+            packing: None,
+        };
+        let summary = pack(&mut search, 20);
+
+        let names: Vec<_> = search
+            .results
+            .iter()
+            .map(|r| r.symbol.name.as_str())
+            .collect();
+        assert_eq!(names, ["long", "short", "weak"], "ranking is kept");
+        assert!(search.results[0].body_omitted);
+        assert_eq!(search.results[0].content.as_deref(), Some("def long():"));
+        assert!(!search.results[1].body_omitted);
+        assert!(!search.results[2].body_omitted);
+        assert_eq!((summary.full_bodies, summary.signatures_only), (2, 1));
+        assert!(summary.tokens <= 20);
+        assert_eq!(search.packing, Some(summary));
+    }
+
+    #[test]
+    fn test_signature_line() {
+        assert_eq!(signature_line("\n\nfn a() {\n}"), "\n\nfn a() {");
+        assert_eq!(signature_line("x = 1"), "x = 1");
+        assert_eq!(signature_line(""), "");
+    }
+}
@@ -1,17 +1,46 @@
-use anyhow::{Context, Result};
+#[cfg(feature = "rag")]
+use anyhow::Context;
+use anyhow::Result;
+#[cfg(feature = "rag")]
 use fastembed::{RerankInitOptions, RerankerModel, TextRerank};
 
+#[cfg(feature = "rag")]
 use super::model_cache_dir;
 
+/// Map a raw cross-encoder logit to a relevance in `[0, 1]` with a sigmoid at
+/// `temperature` (see [`crate::db::RerankCalibration`]), so scores read as a
+/// probability of relevance.
+pub fn calibrate(logit: f64, temperature: f64) -> f64 {
+    1.0 / (1.0 + (-logit / temperature).exp())
+}
+
+/// Whether the re-ranker's ONNX file was downloaded, checked without loading it.
+#[cfg(feature = "rag")]
+pub fn is_downloaded() -> bool {
+    let info = TextRerank::get_model_info(&RerankerModel::BGERerankerBase);
+    super::is_cached(&info.model_code, &info.model_file)
+}
+
+/// Models are never downloaded without the `rag` feature.
+#[cfg(not(feature = "rag"))]
+pub fn is_downloaded() -> bool {
+    false
+}
+
+/// Name of the re-ranker model, as shown in reports.
+pub const MODEL_NAME: &str = "bge-reranker-base";
+
 /// Cross-encoder re-ranker for scoring (query, document) pairs.
 ///
 /// Uses ONNX Runtime via fastembed for inference. The BGE-reranker-base model
 /// processes query and document jointly through all transformer layers,
 /// producing a relevance score for each pair.
+#[cfg(feature = "rag")]
 pub struct CrossEncoderEngine {
     model: TextRerank,
 }
 
+#[cfg(feature = "rag")]
 impl CrossEncoderEngine {
     /// Load the cross-encoder re-ranker model.
     ///
@@ -62,3 +91,40 @@ impl CrossEncoderEngine {
         Ok(scores)
     }
 }
+
+/// Stand-in when the `rag` feature is compiled out: never constructible.
+#[cfg(not(feature = "rag"))]
+pub struct CrossEncoderEngine {
+    never: std::convert::Infallible,
+}
+
+#[cfg(not(feature = "rag"))]
+impl CrossEncoderEngine {
+    pub fn load() -> Result<Self> {
+        Err(super::models_disabled())
+    }
+
+    pub fn load_with_progress() -> Result<Self> {
+        Self::load()
+    }
+
+    pub fn score_batch(&mut self, _query: &str, _documents: &[&str]) -> Result<Vec<f32>> {
+        match self.never {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calibrate_maps_logits_to_unit_interval() {
+        let t = crate::db::RerankCalibration::default().temperature;
+        assert!((calibrate(0.0, t) - 0.5).abs() < f64::EPSILON);
+        assert!(calibrate(8.0, t) > 0.95);
+        assert!(calibrate(-11.0, t) < 0.01);
+        assert!(calibrate(-2.0, t) < calibrate(-1.0, t));
+        // A higher temperature flattens the curve.
+        assert!(calibrate(-11.0, 4.0) > calibrate(-11.0, t));
+    }
+}
@@ -1,40 +1,144 @@
 use std::collections::HashMap;
 
 use anyhow::Result;
+use schemars::JsonSchema;
 use serde::Serialize;
 
+use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Mutex;
 
-use crate::db::Database;
-use crate::types::{Symbol, SymbolKind};
+use crate::db::{normalize_symbol_name, Database, RerankCalibration, VectorTable};
+use crate::ignore::pattern_matches;
+use crate::types::{match_spans, MatchSpan, Symbol, SymbolKind};
 
-use super::embeddings::{embedding_to_bytes, EmbeddingEngine};
-use super::reranker::CrossEncoderEngine;
+use super::embeddings::{embedding_to_bytes, EmbeddingEngine, EmbeddingModelId};
+use super::migrate::IndexModels;
+use super::reranker::{self, CrossEncoderEngine};
 
-/// Cached embedding engine — loaded once, reused across search calls.
-static EMBEDDING_ENGINE: Mutex<Option<EmbeddingEngine>> = Mutex::new(None);
+/// Cached embedding engines, one per model — loaded once, reused across search calls.
+/// A model is absent until a load is attempted; `None` = load failed, `Some(_)` = ready.
+/// An index uses one model, two while it migrates to another.
+static EMBEDDING_ENGINES: Mutex<Vec<(EmbeddingModelId, Option<EmbeddingEngine>)>> =
+    Mutex::new(Vec::new());
 
 /// Cached cross-encoder engine — loaded once, reused across search calls.
 /// Uses tri-state: None = not attempted, Some(None) = load failed, Some(Some(_)) = ready.
 static RERANKER_ENGINE: Mutex<Option<Option<CrossEncoderEngine>>> = Mutex::new(None);
 
-/// Get or initialize the cached embedding engine.
+/// Load state of each cached engine, readable without waiting on its lock
+/// (which is held for the whole load and for every inference).
+static EMBEDDING_STATUS: AtomicU8 = AtomicU8::new(ModelStatus::NotLoaded as u8);
+static RERANKER_STATUS: AtomicU8 = AtomicU8::new(ModelStatus::NotLoaded as u8);
+
+/// Load state of a model engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelStatus {
+    /// No search or preload has needed it yet.
+    NotLoaded = 0,
+    Loading = 1,
+    Ready = 2,
+    /// Loading failed (not downloaded, or the `rag` feature is compiled out); not retried.
+    Unavailable = 3,
+}
+
+impl std::fmt::Display for ModelStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::NotLoaded => "not loaded",
+            Self::Loading => "loading",
+            Self::Ready => "ready",
+            Self::Unavailable => "unavailable",
+        })
+    }
+}
+
+impl ModelStatus {
+    fn load(status: &AtomicU8) -> Self {
+        if !super::MODELS_ENABLED {
+            return Self::Unavailable;
+        }
+        match status.load(Ordering::Relaxed) {
+            1 => Self::Loading,
+            2 => Self::Ready,
+            3 => Self::Unavailable,
+            _ => Self::NotLoaded,
+        }
+    }
+}
+
+/// Load state of the embedding and re-ranker engines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct ModelsStatus {
+    pub embedding: ModelStatus,
+    pub reranker: ModelStatus,
+}
+
+/// Current load state of both engines, without blocking.
+pub fn models_status() -> ModelsStatus {
+    ModelsStatus {
+        embedding: ModelStatus::load(&EMBEDDING_STATUS),
+        reranker: ModelStatus::load(&RERANKER_STATUS),
+    }
+}
+
+/// Load both engines now instead of on the first hybrid search, so the first
+/// query after `cartog serve` starts does not pay for model initialization.
+/// Models missing from the cache are left alone rather than downloaded.
+/// Blocks until both are loaded or have failed.
+pub fn preload_models() {
+    let model = EmbeddingModelId::default();
+    if !model.is_downloaded() {
+        tracing::info!(model = %model, "embedding model not downloaded, not preloading it");
+    } else if let Err(e) = with_embedding_engine(model, |_| Ok(())) {
+        tracing::warn!(error = %e, "embedding model preload failed");
+    }
+    if reranker::is_downloaded() {
+        with_reranker_engine(|_| ());
+    } else {
+        tracing::info!("re-ranker not downloaded, not preloading it");
+    }
+    let status = models_status();
+    tracing::info!(embedding = ?status.embedding, reranker = ?status.reranker, "models preloaded");
+}
+
+/// Get or initialize the cached embedding engine of `model`.
+///
+/// Returns `Ok(None)` if the model is not available (not downloaded, or the
+/// `rag` feature is compiled out); the failure is cached like the re-ranker's,
+/// so keyword-only fallback does not re-probe the model on every call.
 ///
-/// NOTE: The Mutex is held for the entire duration of model inference.
-/// This is fine for single-threaded CLI and MCP usage (one query at a time).
-/// If the MCP server becomes multi-threaded with concurrent queries,
-/// this should be replaced with a pool or per-thread engine.
-fn with_embedding_engine<F, R>(f: F) -> Result<R>
+/// NOTE: The Mutex is held for the entire duration of model inference, so
+/// concurrent searches embed one at a time. Workloads with several queries
+/// should go through [`embed_queries`] / [`search_batch`], which take the lock
+/// once and run a single batched inference.
+fn with_embedding_engine<F, R>(model: EmbeddingModelId, f: F) -> Result<Option<R>>
 where
     F: FnOnce(&mut EmbeddingEngine) -> Result<R>,
 {
-    let mut guard = EMBEDDING_ENGINE
+    let mut guard = EMBEDDING_ENGINES
         .lock()
         .map_err(|_| anyhow::anyhow!("embedding engine lock poisoned"))?;
-    if guard.is_none() {
-        *guard = Some(EmbeddingEngine::new()?);
-    }
-    f(guard.as_mut().unwrap())
+    let index = match guard.iter().position(|(m, _)| *m == model) {
+        Some(index) => index,
+        None => {
+            EMBEDDING_STATUS.store(ModelStatus::Loading as u8, Ordering::Relaxed);
+            let engine = match EmbeddingEngine::load(model, false) {
+                Ok(engine) => {
+                    EMBEDDING_STATUS.store(ModelStatus::Ready as u8, Ordering::Relaxed);
+                    Some(engine)
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, %model, "Embedding model not available, falling back to keyword-only search");
+                    EMBEDDING_STATUS.store(ModelStatus::Unavailable as u8, Ordering::Relaxed);
+                    None
+                }
+            };
+            guard.push((model, engine));
+            guard.len() - 1
+        }
+    };
+    guard[index].1.as_mut().map(f).transpose()
 }
 
 /// Get or initialize the cached cross-encoder engine.
@@ -49,11 +153,16 @@ where
     let mut guard = RERANKER_ENGINE.lock().ok()?;
     if guard.is_none() {
         // First attempt: try to load, cache the result either way
+        RERANKER_STATUS.store(ModelStatus::Loading as u8, Ordering::Relaxed);
         match CrossEncoderEngine::load() {
-            Ok(engine) => *guard = Some(Some(engine)),
+            Ok(engine) => {
+                *guard = Some(Some(engine));
+                RERANKER_STATUS.store(ModelStatus::Ready as u8, Ordering::Relaxed);
+            }
             Err(e) => {
                 tracing::debug!(error = %e, "Cross-encoder not available, skipping re-ranking");
                 *guard = Some(None); // Cache the failure — don't retry
+                RERANKER_STATUS.store(ModelStatus::Unavailable as u8, Ordering::Relaxed);
                 return None;
             }
         }
@@ -62,7 +171,7 @@ where
 }
 
 /// A search result combining symbol metadata with relevance info.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct SearchResult {
     pub symbol: Symbol,
     pub content: Option<String>,
@@ -70,17 +179,65 @@ pub struct SearchResult {
     /// Cross-encoder re-ranking score (higher = more relevant). Present only when
     /// the cross-encoder model is available.
     pub rerank_score: Option<f64>,
+    /// `rerank_score` calibrated to `[0, 1]`, comparable across queries.
+    pub relevance: Option<f64>,
     /// Which retrieval methods found this result.
     pub sources: Vec<String>,
+    /// Byte spans of query terms found in `symbol.name`.
+    pub name_matches: Vec<MatchSpan>,
+    /// Byte spans of query terms found in `content`.
+    pub content_matches: Vec<MatchSpan>,
+    /// What matched, per source: query terms in the name, normalized name and
+    /// content, and the vector distance, e.g. `name:token, content:"expired", vector:0.12`.
+    pub why: String,
+    /// `content` was cut to its first line to fit a `--budget` (see [`super::pack`]).
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub body_omitted: bool,
+}
+
+/// Retrieval strategy for a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// FTS5 + vector KNN, re-ranked by the cross-encoder when available.
+    Hybrid,
+    /// FTS5 only (BM25 over names, normalized names and content). Loads no models.
+    Keyword,
+}
+
+impl std::fmt::Display for SearchMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchMode::Hybrid => write!(f, "hybrid"),
+            SearchMode::Keyword => write!(f, "keyword"),
+        }
+    }
 }
 
 /// Result of a hybrid search operation.
-#[derive(Debug, Serialize)]
+///
+/// The shape is the same in both modes: keyword-only results report
+/// `vec_count: 0`, `rerank_score: null` and `sources: ["fts5"]`.
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct HybridSearchResult {
     pub results: Vec<SearchResult>,
     pub fts_count: u32,
     pub vec_count: u32,
     pub merged_count: u32,
+    /// Mode actually used: `keyword` when requested, or when no model or no vector
+    /// storage (sqlite-vec) could be used.
+    pub mode: SearchMode,
+    /// Set when the results were fitted to a token budget.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub packing: Option<super::pack::PackSummary>,
+}
+
+/// One query's results in a [`search_batch`] response.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct QueryResults {
+    pub query: String,
+    #[serde(flatten)]
+    pub result: HybridSearchResult,
 }
 
 /// Reciprocal Rank Fusion: merge multiple ranked lists into a single ranking.
@@ -106,33 +263,232 @@ fn rrf_merge(ranked_lists: &[(&str, Vec<String>)], k: f64) -> Vec<(String, f64,
         .into_iter()
         .map(|(id, (score, sources))| (id, score, sources))
         .collect();
-    results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    // Ties (e.g. one hit per list at the same rank) fall back to the symbol ID,
+    // so map iteration order never leaks into the ranking.
+    results.sort_by(|a, b| {
+        b.1.partial_cmp(&a.1)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.0.cmp(&b.0))
+    });
     results
 }
 
+/// Which symbols a search returns. The default keeps every match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchFilter<'a> {
+    pub kind: Option<SymbolKind>,
+    /// Only symbols whose file matches this gitignore-style glob (`src/auth/**`, `*.ts`).
+    pub path: Option<&'a str>,
+    /// Only symbols in files of this language (`typescript` covers `.tsx`).
+    pub lang: Option<&'a str>,
+    /// Drop symbols whose file matches this glob.
+    pub exclude: Option<&'a str>,
+}
+
+impl SearchFilter<'_> {
+    /// Whether the filter restricts files, so candidates are dropped before ranking.
+    fn is_scoped(&self) -> bool {
+        self.path.is_some() || self.lang.is_some() || self.exclude.is_some()
+    }
+
+    /// Whether symbols of `file_path` are in scope, judged by its path and the
+    /// language it was indexed as.
+    fn accepts_file(&self, db: &Database, file_path: &str) -> Result<bool> {
+        let path_ok = self
+            .path
+            .map_or(true, |glob| pattern_matches(glob, file_path, false));
+        let excluded = self
+            .exclude
+            .is_some_and(|glob| pattern_matches(glob, file_path, false));
+        if !path_ok || excluded {
+            return Ok(false);
+        }
+        Ok(match self.lang {
+            None => true,
+            Some(lang) => db.get_file(file_path)?.is_some_and(|f| {
+                f.language == lang || (f.language == "tsx" && lang == "typescript")
+            }),
+        })
+    }
+}
+
 /// Run hybrid search: FTS5 keyword + vector KNN, merged with RRF.
 ///
-/// When `kind_filter` is set, results are filtered before applying `limit`,
-/// so the caller always gets up to `limit` results of the requested kind.
+/// Degrades to keyword-only when the embedding model or re-ranker is unavailable.
+/// `filter` applies before `limit`, so the caller always gets up to `limit`
+/// results of the requested kind and files.
 pub fn hybrid_search(
     db: &Database,
     query: &str,
     limit: u32,
-    kind_filter: Option<SymbolKind>,
+    filter: SearchFilter<'_>,
+) -> Result<HybridSearchResult> {
+    search(db, query, limit, filter, SearchMode::Hybrid, None)
+}
+
+/// Run keyword-only search (FTS5/BM25), without loading any model.
+pub fn keyword_search(
+    db: &Database,
+    query: &str,
+    limit: u32,
+    filter: SearchFilter<'_>,
+) -> Result<HybridSearchResult> {
+    search(db, query, limit, filter, SearchMode::Keyword, None)
+}
+
+/// Search in the requested mode. See [`hybrid_search`] and [`keyword_search`].
+///
+/// Re-ranked results whose relevance is below `min_score` (default: the stored
+/// [`RerankCalibration::min_relevance`]) are dropped, so fewer than `limit` may be
+/// returned.
+/// Results without a relevance (keyword mode, or no content to re-rank) are kept.
+pub fn search(
+    db: &Database,
+    query: &str,
+    limit: u32,
+    filter: SearchFilter<'_>,
+    mode: SearchMode,
+    min_score: Option<f64>,
 ) -> Result<HybridSearchResult> {
-    let retrieval_limit = (limit * 3).max(20); // Over-retrieve for better merge
+    run_search(
+        db,
+        query,
+        limit,
+        filter,
+        mode,
+        min_score,
+        QueryVector::Embed,
+    )
+}
+
+/// A query embedded with the index's model and, during a model migration, with
+/// the model being migrated to (see [`super::migrate`]).
+#[derive(Debug, Clone)]
+pub struct QueryEmbedding {
+    pub current: Vec<f32>,
+    /// `None` outside a migration, before the next model has vectors, or when it
+    /// cannot be loaded.
+    pub next: Option<Vec<f32>>,
+}
+
+/// Embed several queries with one batched call per model, taking each engine lock once.
+///
+/// Returns `Ok(None)` when the index's embedding model is not available. Embeddings
+/// are in the order of `queries`.
+pub fn embed_queries(db: &Database, queries: &[&str]) -> Result<Option<Vec<QueryEmbedding>>> {
+    let models = IndexModels::of(db)?;
+    let Some(current) = with_embedding_engine(models.current, |e| e.embed_batch(queries))? else {
+        return Ok(None);
+    };
+    let next = match models.next {
+        Some(next) if db.next_embedding_count()? > 0 => {
+            with_embedding_engine(next, |e| e.embed_batch(queries))?
+        }
+        _ => None,
+    };
+    let mut next = next.map(Vec::into_iter);
+    Ok(Some(
+        current
+            .into_iter()
+            .map(|current| QueryEmbedding {
+                current,
+                next: next.as_mut().and_then(Iterator::next),
+            })
+            .collect(),
+    ))
+}
+
+/// Run [`search`] for each query, embedding all of them up front with
+/// [`embed_queries`] instead of one model call per query.
+///
+/// Results are in the order of `queries` and identical to separate searches.
+pub fn search_batch(
+    db: &Database,
+    queries: &[&str],
+    limit: u32,
+    filter: SearchFilter<'_>,
+    mode: SearchMode,
+    min_score: Option<f64>,
+) -> Result<Vec<HybridSearchResult>> {
+    let embeddings = if mode == SearchMode::Hybrid
+        && super::MODELS_ENABLED
+        && db.has_vectors()
+        && db.embedding_count()? > 0
+    {
+        embed_queries(db, queries)?
+    } else {
+        None
+    };
+    queries
+        .iter()
+        .enumerate()
+        .map(|(i, query)| {
+            let vector = match &embeddings {
+                Some(embeddings) => QueryVector::Given(&embeddings[i]),
+                None => QueryVector::Skip,
+            };
+            run_search(db, query, limit, filter, mode, min_score, vector)
+        })
+        .collect()
+}
+
+/// Query embedding for the vector half of a search.
+enum QueryVector<'a> {
+    /// Embed the query when vector search runs.
+    Embed,
+    /// Already embedded (batch searches).
+    Given(&'a QueryEmbedding),
+    /// The model is unavailable: keyword results only.
+    Skip,
+}
+
+fn run_search(
+    db: &Database,
+    query: &str,
+    limit: u32,
+    filter: SearchFilter<'_>,
+    mode: SearchMode,
+    min_score: Option<f64>,
+    vector: QueryVector,
+) -> Result<HybridSearchResult> {
+    // Over-retrieve for better merge, and more so when most candidates may be out of scope
+    let retrieval_limit = if filter.is_scoped() {
+        (limit * 10).max(100)
+    } else {
+        (limit * 3).max(20)
+    };
+    let use_models = mode == SearchMode::Hybrid && super::MODELS_ENABLED;
+    let mut used_models = false;
 
     // 1. FTS5 keyword search
     let fts_results = fts5_search_safe(db, query, retrieval_limit)?;
     let fts_count = fts_results.len() as u32;
 
-    // 2. Vector search (if embeddings exist in the DB)
-    let vec_results = if db.embedding_count()? > 0 {
-        vector_search(db, query, retrieval_limit)?
+    // 2. Vector search (if sqlite-vec loaded, embeddings exist and the model loads)
+    let vec_hits = if use_models && db.has_vectors() && db.embedding_count()? > 0 {
+        let ids = match vector {
+            QueryVector::Embed => vector_search(db, query, retrieval_limit)?,
+            QueryVector::Given(embedding) => Some(nearest_symbols(
+                db,
+                &embedding.current,
+                embedding.next.as_deref(),
+                retrieval_limit,
+            )?),
+            QueryVector::Skip => None,
+        };
+        match ids {
+            Some(ids) => {
+                used_models = true;
+                ids
+            }
+            None => Vec::new(),
+        }
     } else {
         Vec::new()
     };
-    let vec_count = vec_results.len() as u32;
+    let vec_count = vec_hits.len() as u32;
+    let distances: HashMap<&str, f64> = vec_hits.iter().map(|(id, d)| (id.as_str(), *d)).collect();
+    let vec_results = vec_hits.iter().map(|(id, _)| id.clone()).collect();
 
     // 3. RRF merge
     let ranked_lists: Vec<(&str, Vec<String>)> =
@@ -140,7 +496,8 @@ pub fn hybrid_search(
     let merged = rrf_merge(&ranked_lists, 60.0);
     let merged_count = merged.len() as u32;
 
-    // 4. Hydrate all merged candidates with symbol data + content.
+    // 4. Hydrate all merged candidates with symbol data + content,
+    //    dropping those outside the path/language scope before re-ranking.
     let candidate_ids: Vec<String> = merged.iter().map(|(id, _, _)| id.clone()).collect();
 
     let symbols = db.get_symbols_by_ids(&candidate_ids)?;
@@ -152,25 +509,47 @@ pub fn hybrid_search(
 
     let symbol_map: HashMap<&str, &Symbol> = symbols.iter().map(|s| (s.id.as_str(), s)).collect();
 
+    let terms: Vec<&str> = query.split_whitespace().collect();
     let empty_sources = Vec::new();
     let mut candidates: Vec<SearchResult> = Vec::new();
     for id in &candidate_ids {
-        if let Some(sym) = symbol_map.get(id.as_str()) {
-            let (score, sources) = score_map
-                .get(id.as_str())
-                .copied()
-                .unwrap_or((0.0, &empty_sources));
-
-            let content = db.get_symbol_content(id)?.map(|(c, _)| c);
-
-            candidates.push(SearchResult {
-                symbol: (*sym).clone(),
-                content,
-                rrf_score: score,
-                rerank_score: None,
-                sources: sources.clone(),
-            });
+        let Some(sym) = symbol_map.get(id.as_str()) else {
+            continue;
+        };
+        if !filter.accepts_file(db, &sym.file_path)? {
+            continue;
         }
+        let (score, sources) = score_map
+            .get(id.as_str())
+            .copied()
+            .unwrap_or((0.0, &empty_sources));
+
+        let content = db.get_symbol_content(id)?.map(|(c, _)| c);
+        let content_matches = content
+            .as_deref()
+            .map(|c| match_spans(c, &terms))
+            .unwrap_or_default();
+
+        let why = explain_match(
+            &sym.name,
+            content.as_deref(),
+            &terms,
+            sources.iter().any(|s| s == "fts5"),
+            distances.get(id.as_str()).copied(),
+        );
+
+        candidates.push(SearchResult {
+            symbol: (*sym).clone(),
+            content,
+            rrf_score: score,
+            rerank_score: None,
+            relevance: None,
+            sources: sources.clone(),
+            name_matches: match_spans(&sym.name, &terms),
+            content_matches,
+            why,
+            body_omitted: false,
+        });
     }
 
     // 5. Cross-encoder re-ranking (if model is available).
@@ -181,20 +560,31 @@ pub fn hybrid_search(
     } else {
         &mut candidates[..]
     };
-    with_reranker_engine(|engine| {
-        rerank_candidates(engine, query, rerank_slice);
-    });
+    let calibration = db.rerank_calibration()?;
+    if use_models
+        && with_reranker_engine(|engine| {
+            rerank_candidates(engine, query, rerank_slice, calibration.temperature)
+        })
+        .is_some()
+    {
+        used_models = true;
+    }
 
-    // 6. Apply kind filter + limit on (re-ranked) candidates.
+    // 6. Apply kind filter, relevance threshold + limit on (re-ranked) candidates.
+    let min_score = min_score.unwrap_or(calibration.min_relevance);
     let mut results = Vec::new();
     for candidate in candidates {
         if results.len() >= limit as usize {
             break;
         }
-        if let Some(ref filter) = kind_filter {
-            if &candidate.symbol.kind != filter {
-                continue;
-            }
+        if filter
+            .kind
+            .is_some_and(|kind| candidate.symbol.kind != kind)
+        {
+            continue;
+        }
+        if candidate.relevance.is_some_and(|r| r < min_score) {
+            continue;
         }
         results.push(candidate);
     }
@@ -204,18 +594,78 @@ pub fn hybrid_search(
         fts_count,
         vec_count,
         merged_count,
+        packing: None,
+        mode: if used_models {
+            SearchMode::Hybrid
+        } else {
+            SearchMode::Keyword
+        },
     })
 }
 
+/// Build [`SearchResult::why`]. Keyword terms are reported only for FTS5 hits:
+/// a term that happens to occur in a vector-only hit is not why it matched.
+fn explain_match(
+    name: &str,
+    content: Option<&str>,
+    terms: &[&str],
+    keyword_hit: bool,
+    distance: Option<f64>,
+) -> String {
+    let mut parts = Vec::new();
+    if keyword_hit {
+        let lower_name = name.to_lowercase();
+        let normalized_name = normalize_symbol_name(name);
+        let lower_content = content.map(str::to_lowercase).unwrap_or_default();
+        let (mut in_name, mut in_normalized, mut in_content) = (Vec::new(), Vec::new(), Vec::new());
+        for term in terms {
+            let needle = term.to_lowercase();
+            if needle.is_empty() {
+                continue;
+            }
+            if lower_name.contains(&needle) {
+                in_name.push(*term);
+            } else {
+                let normalized = normalize_symbol_name(term);
+                if !normalized.is_empty() && normalized_name.contains(&normalized) {
+                    in_normalized.push(*term);
+                }
+            }
+            if lower_content.contains(&needle) {
+                in_content.push(format!("\"{term}\""));
+            }
+        }
+        if !in_name.is_empty() {
+            parts.push(format!("name:{}", in_name.join("+")));
+        }
+        if !in_normalized.is_empty() {
+            parts.push(format!("normalized:{}", in_normalized.join("+")));
+        }
+        if !in_content.is_empty() {
+            parts.push(format!("content:{}", in_content.join("+")));
+        }
+        if parts.is_empty() {
+            // FTS5 tokenization matched something the plain substring check did not.
+            parts.push("keyword".to_string());
+        }
+    }
+    if let Some(distance) = distance {
+        parts.push(format!("vector:{distance:.2}"));
+    }
+    parts.join(", ")
+}
+
 /// Re-rank candidates in place using a cross-encoder.
 ///
 /// Batches all (query, content) pairs for a single ONNX inference call,
-/// then re-sorts by cross-encoder score descending.
+/// then re-sorts by cross-encoder score descending. Scores are calibrated
+/// to a relevance at `temperature`.
 /// Candidates without content retain their original order at the end.
 fn rerank_candidates(
     engine: &mut CrossEncoderEngine,
     query: &str,
     candidates: &mut [SearchResult],
+    temperature: f64,
 ) {
     // Collect indices of candidates that have content (no cloning).
     let scoreable_indices: Vec<usize> = candidates
@@ -238,6 +688,7 @@ fn rerank_candidates(
         Ok(scores) => {
             for (&idx, score) in scoreable_indices.iter().zip(scores.iter()) {
                 candidates[idx].rerank_score = Some(*score as f64);
+                candidates[idx].relevance = Some(reranker::calibrate(*score as f64, temperature));
             }
         }
         Err(e) => {
@@ -306,11 +757,52 @@ fn is_fts5_syntax_error(err: &anyhow::Error) -> bool {
 }
 
 /// Vector search: embed the query and find nearest neighbors.
-fn vector_search(db: &Database, query: &str, limit: u32) -> Result<Vec<String>> {
-    let query_embedding = with_embedding_engine(|engine| engine.embed(query))?;
-    let query_bytes = embedding_to_bytes(&query_embedding);
+///
+/// During an embedding model migration the query is embedded with both models.
+/// Returns `Ok(None)` when the index's embedding model is not available.
+fn vector_search(db: &Database, query: &str, limit: u32) -> Result<Option<Vec<(String, f64)>>> {
+    let models = IndexModels::of(db)?;
+    let Some(current) = with_embedding_engine(models.current, |engine| engine.embed(query))? else {
+        return Ok(None);
+    };
+    let next = match models.next {
+        Some(next) if db.next_embedding_count()? > 0 => {
+            with_embedding_engine(next, |engine| engine.embed(query))?
+        }
+        _ => None,
+    };
+    nearest_symbols(db, &current, next.as_deref(), limit).map(Some)
+}
 
-    let nn_results = db.vector_search(&query_bytes, limit)?;
+/// Symbol IDs and distances of the `limit` embeddings nearest to the query, closest
+/// first. `next` is the query embedded with the model being migrated to, whose hits
+/// are merged in with [`merge_normalized`].
+fn nearest_symbols(
+    db: &Database,
+    current: &[f32],
+    next: Option<&[f32]>,
+    limit: u32,
+) -> Result<Vec<(String, f64)>> {
+    let hits = nearest_in(db, VectorTable::Current, current, limit)?;
+    match next {
+        Some(next) => {
+            let next_hits = nearest_in(db, VectorTable::Next, next, limit)?;
+            Ok(merge_normalized(hits, next_hits, limit as usize))
+        }
+        None => Ok(hits),
+    }
+}
+
+/// Symbol IDs and distances of the `limit` vectors of `table` nearest to `embedding`.
+fn nearest_in(
+    db: &Database,
+    table: VectorTable,
+    embedding: &[f32],
+    limit: u32,
+) -> Result<Vec<(String, f64)>> {
+    let query_bytes = embedding_to_bytes(embedding);
+
+    let nn_results = db.vector_search_in(table, &query_bytes, limit)?;
 
     // Map embedding IDs back to symbol IDs
     let embedding_ids: Vec<i64> = nn_results.iter().map(|(id, _)| *id).collect();
@@ -318,14 +810,59 @@ fn vector_search(db: &Database, query: &str, limit: u32) -> Result<Vec<String>>
     let id_lookup: HashMap<i64, String> = id_map.into_iter().collect();
 
     // Preserve distance ordering
-    let symbol_ids: Vec<String> = nn_results
+    let symbol_ids: Vec<(String, f64)> = nn_results
         .iter()
-        .filter_map(|(eid, _)| id_lookup.get(eid).cloned())
+        .filter_map(|(eid, distance)| Some((id_lookup.get(eid)?.clone(), *distance)))
         .collect();
 
     Ok(symbol_ids)
 }
 
+/// Merge the nearest neighbors found with the current and the next model.
+///
+/// Distances of two models are on different scales, so each list's distances are
+/// min-max normalized to a similarity in `[0, 1]` first. A symbol found by both
+/// keeps its better similarity (the next model's on a tie) and that list's distance.
+fn merge_normalized(
+    current: Vec<(String, f64)>,
+    next: Vec<(String, f64)>,
+    limit: usize,
+) -> Vec<(String, f64)> {
+    fn normalized(hits: Vec<(String, f64)>) -> impl Iterator<Item = (String, f64, f64)> {
+        let min = hits.iter().map(|(_, d)| *d).fold(f64::INFINITY, f64::min);
+        let max = hits
+            .iter()
+            .map(|(_, d)| *d)
+            .fold(f64::NEG_INFINITY, f64::max);
+        hits.into_iter().map(move |(id, distance)| {
+            let similarity = if max > min {
+                1.0 - (distance - min) / (max - min)
+            } else {
+                1.0
+            };
+            (id, similarity, distance)
+        })
+    }
+
+    let mut best: HashMap<String, (f64, f64)> = HashMap::new();
+    for (id, similarity, distance) in normalized(next).chain(normalized(current)) {
+        let entry = best.entry(id).or_insert((similarity, distance));
+        if similarity > entry.0 {
+            *entry = (similarity, distance);
+        }
+    }
+    let mut merged: Vec<(String, f64, f64)> = best
+        .into_iter()
+        .map(|(id, (similarity, distance))| (id, similarity, distance))
+        .collect();
+    merged.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    merged.truncate(limit);
+    merged
+        .into_iter()
+        .map(|(id, _, distance)| (id, distance))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -412,6 +949,27 @@ mod tests {
         assert!(merged.is_empty());
     }
 
+    #[test]
+    fn test_merge_normalized_across_models() {
+        let hits = |pairs: &[(&str, f64)]| -> Vec<(String, f64)> {
+            pairs.iter().map(|(id, d)| (id.to_string(), *d)).collect()
+        };
+        // The models' distances are on different scales; ranks within each list
+        // decide, not the raw values.
+        let current = hits(&[("a", 0.2), ("b", 0.3), ("c", 0.4)]);
+        let next = hits(&[("c", 10.0), ("d", 15.0), ("a", 20.0)]);
+        let merged = merge_normalized(current, next, 10);
+        let ids: Vec<&str> = merged.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["a", "c", "b", "d"]);
+        // Each symbol keeps the distance of the list it ranked best in.
+        assert_eq!(merged[0].1, 0.2);
+        assert_eq!(merged[1].1, 10.0);
+
+        assert_eq!(merge_normalized(hits(&[("x", 0.5)]), vec![], 1).len(), 1);
+        let many = hits(&[("a", 0.1), ("b", 0.2), ("c", 0.3)]);
+        assert_eq!(merge_normalized(many, vec![], 2).len(), 2);
+    }
+
     // ── hybrid_search integration tests (FTS5-only, no model needed) ──
     //
     // These tests populate an in-memory DB with realistic code symbols and assert
@@ -471,7 +1029,7 @@ mod tests {
         seed_python_corpus(&db);
 
         // "validate token" should rank validate_token #1 (both terms in name+content)
-        let result = hybrid_search(&db, "validate token", 10, None).unwrap();
+        let result = hybrid_search(&db, "validate token", 10, Default::default()).unwrap();
         assert!(result.fts_count > 0, "FTS5 should find results");
         assert_eq!(result.vec_count, 0, "no embeddings → no vector results");
         assert_eq!(result.results[0].symbol.name, "validate_token");
@@ -490,7 +1048,7 @@ mod tests {
         }
 
         // "authenticate" should find AuthService (content match)
-        let result = hybrid_search(&db, "authenticate", 10, None).unwrap();
+        let result = hybrid_search(&db, "authenticate", 10, Default::default()).unwrap();
         assert_eq!(result.results[0].symbol.name, "AuthService");
 
         // send_email should NOT appear for an auth-related query
@@ -505,6 +1063,67 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_keyword_search_mode_same_shape_as_hybrid() {
+        let db = Database::open_memory().unwrap();
+        seed_python_corpus(&db);
+
+        let keyword = keyword_search(&db, "validate token", 10, Default::default()).unwrap();
+        assert_eq!(keyword.mode, SearchMode::Keyword);
+        assert_eq!(keyword.vec_count, 0);
+        assert_eq!(keyword.results[0].symbol.name, "validate_token");
+        assert!(keyword
+            .results
+            .iter()
+            .all(|r| r.rerank_score.is_none() && r.sources == ["fts5"]));
+
+        // No embeddings in the DB: hybrid ranks the same candidates via FTS5.
+        let hybrid = hybrid_search(&db, "validate token", 10, Default::default()).unwrap();
+        assert_eq!(hybrid.fts_count, keyword.fts_count);
+
+        let keys = |v: serde_json::Value| -> Vec<String> {
+            v.as_object().unwrap().keys().cloned().collect()
+        };
+        let kw_json = serde_json::to_value(&keyword).unwrap();
+        let hy_json = serde_json::to_value(&hybrid).unwrap();
+        assert_eq!(kw_json["mode"], "keyword");
+        assert_eq!(keys(kw_json.clone()), keys(hy_json.clone()));
+        assert_eq!(
+            keys(kw_json["results"][0].clone()),
+            keys(hy_json["results"][0].clone())
+        );
+    }
+
+    #[test]
+    fn test_search_batch_matches_single_searches() {
+        let db = Database::open_memory().unwrap();
+        seed_python_corpus(&db);
+
+        let queries = [
+            "validate token",
+            "authenticate",
+            "nothing matches this",
+            "email",
+        ];
+        for mode in [SearchMode::Hybrid, SearchMode::Keyword] {
+            let batch = search_batch(&db, &queries, 5, Default::default(), mode, None).unwrap();
+            assert_eq!(batch.len(), queries.len());
+            for (query, result) in queries.iter().zip(&batch) {
+                let single = search(&db, query, 5, Default::default(), mode, None).unwrap();
+                assert_eq!(
+                    serde_json::to_value(result).unwrap(),
+                    serde_json::to_value(&single).unwrap(),
+                    "{query}"
+                );
+            }
+        }
+        assert!(
+            search_batch(&db, &[], 5, Default::default(), SearchMode::Hybrid, None)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[test]
     fn test_hybrid_search_typescript_ranking() {
         let db = Database::open_memory().unwrap();
@@ -534,7 +1153,7 @@ mod tests {
         );
 
         // "connect" matches DatabaseConnection's content; the others don't mention "connect"
-        let result = hybrid_search(&db, "connect", 10, None).unwrap();
+        let result = hybrid_search(&db, "connect", 10, Default::default()).unwrap();
         assert_eq!(result.results[0].symbol.name, "DatabaseConnection");
         assert_eq!(
             result.results.len(),
@@ -543,7 +1162,7 @@ mod tests {
         );
 
         // "router" should rank createRouter #1
-        let result = hybrid_search(&db, "router", 10, None).unwrap();
+        let result = hybrid_search(&db, "router", 10, Default::default()).unwrap();
         assert_eq!(result.results[0].symbol.name, "createRouter");
     }
 
@@ -576,15 +1195,15 @@ mod tests {
         );
 
         // "extract symbols" — both terms in extract's content; Database/resolve_edges don't have "extract"
-        let result = hybrid_search(&db, "extract symbols", 10, None).unwrap();
+        let result = hybrid_search(&db, "extract symbols", 10, Default::default()).unwrap();
         assert_eq!(result.results[0].symbol.name, "extract");
 
         // "resolve edges" — only resolve_edges has both terms
-        let result = hybrid_search(&db, "resolve edges", 10, None).unwrap();
+        let result = hybrid_search(&db, "resolve edges", 10, Default::default()).unwrap();
         assert_eq!(result.results[0].symbol.name, "resolve_edges");
 
         // "Database" should not return extract or resolve_edges as #1
-        let result = hybrid_search(&db, "Database", 10, None).unwrap();
+        let result = hybrid_search(&db, "Database", 10, Default::default()).unwrap();
         assert_eq!(result.results[0].symbol.name, "Database");
     }
 
@@ -609,7 +1228,7 @@ mod tests {
         );
 
         // "handle request" — HandleRequest has both terms in name+content
-        let result = hybrid_search(&db, "handle request", 10, None).unwrap();
+        let result = hybrid_search(&db, "handle request", 10, Default::default()).unwrap();
         assert_eq!(result.results[0].symbol.name, "HandleRequest");
 
         // Repository should not appear for "handle request" (no shared terms)
@@ -642,7 +1261,7 @@ mod tests {
         );
 
         // "session" — SessionManager has it in name+content, migrate doesn't
-        let result = hybrid_search(&db, "session", 10, None).unwrap();
+        let result = hybrid_search(&db, "session", 10, Default::default()).unwrap();
         assert_eq!(result.results[0].symbol.name, "SessionManager");
         let names: Vec<&str> = result
             .results
@@ -655,7 +1274,7 @@ mod tests {
         );
 
         // "migrate" — exact name match
-        let result = hybrid_search(&db, "migrate", 10, None).unwrap();
+        let result = hybrid_search(&db, "migrate", 10, Default::default()).unwrap();
         assert_eq!(result.results[0].symbol.name, "migrate");
     }
 
@@ -667,7 +1286,7 @@ mod tests {
         seed_python_corpus(&db);
 
         // "token" appears in validate_token and generate_token content, NOT in send_email
-        let result = hybrid_search(&db, "token", 10, None).unwrap();
+        let result = hybrid_search(&db, "token", 10, Default::default()).unwrap();
         let names: Vec<&str> = result
             .results
             .iter()
@@ -695,7 +1314,7 @@ mod tests {
         // "validate token" as a phrase matches validate_token exactly (FTS5 splits
         // underscores into separate tokens). generate_token doesn't match the phrase
         // because "validate" is not in its content.
-        let result = hybrid_search(&db, "validate token", 10, None).unwrap();
+        let result = hybrid_search(&db, "validate token", 10, Default::default()).unwrap();
         assert_eq!(
             result.results[0].symbol.name, "validate_token",
             "symbol matching both terms as phrase should rank #1"
@@ -703,7 +1322,7 @@ mod tests {
 
         // Now test OR ranking: "generate token" — generate_token and AuthService both
         // contain "generate" and "token". Both should appear in top results.
-        let result = hybrid_search(&db, "generate token", 10, None).unwrap();
+        let result = hybrid_search(&db, "generate token", 10, Default::default()).unwrap();
         let top_names: Vec<&str> = result
             .results
             .iter()
@@ -745,7 +1364,7 @@ mod tests {
         );
 
         // "database" matches via normalized_name column ("database connection")
-        let result = hybrid_search(&db, "database", 10, None).unwrap();
+        let result = hybrid_search(&db, "database", 10, Default::default()).unwrap();
         assert_eq!(
             result.results.len(),
             1,
@@ -775,7 +1394,7 @@ mod tests {
         );
 
         // "validate token" as phrase matches normalized_name "validate token" exactly
-        let result = hybrid_search(&db, "validate token", 10, None).unwrap();
+        let result = hybrid_search(&db, "validate token", 10, Default::default()).unwrap();
         assert!(
             !result.results.is_empty(),
             "phrase 'validate token' should match validateToken via normalized_name"
@@ -795,7 +1414,7 @@ mod tests {
             "TOKEN_EXPIRY = 3600",
         );
 
-        let result = hybrid_search(&db, "token expiry", 10, None).unwrap();
+        let result = hybrid_search(&db, "token expiry", 10, Default::default()).unwrap();
         assert_eq!(
             result.results.len(),
             1,
@@ -818,7 +1437,7 @@ mod tests {
         // FTS5 is token-based, not substring-based.
         // "valid" does NOT match "validate" or "validate_token".
         // Use `cartog search` for substring matching.
-        let result = hybrid_search(&db, "valid", 10, None).unwrap();
+        let result = hybrid_search(&db, "valid", 10, Default::default()).unwrap();
         assert!(
             result.results.is_empty(),
             "FTS5 does not do substring matching — 'valid' should not match 'validate_token'. \
@@ -851,7 +1470,7 @@ mod tests {
         // "validate response" — no symbol has these words adjacent (phrase won't match).
         // AND fallback: process_request has both "validate" and "response" in content.
         // build_response has only "response" — should rank below process_request.
-        let result = hybrid_search(&db, "validate response", 10, None).unwrap();
+        let result = hybrid_search(&db, "validate response", 10, Default::default()).unwrap();
         assert!(
             !result.results.is_empty(),
             "AND fallback should find results"
@@ -870,17 +1489,35 @@ mod tests {
         seed_python_corpus(&db);
 
         // Without filter: "token" matches functions and possibly classes
-        let all = hybrid_search(&db, "token", 10, None).unwrap();
+        let all = hybrid_search(&db, "token", 10, Default::default()).unwrap();
         assert!(all.results.len() >= 2);
 
         // With kind=Function filter: only functions returned, still respects limit
-        let funcs = hybrid_search(&db, "token", 10, Some(SymbolKind::Function)).unwrap();
+        let funcs = hybrid_search(
+            &db,
+            "token",
+            10,
+            SearchFilter {
+                kind: Some(SymbolKind::Function),
+                ..Default::default()
+            },
+        )
+        .unwrap();
         for r in &funcs.results {
             assert_eq!(r.symbol.kind, SymbolKind::Function);
         }
 
         // With kind=Class: AuthService mentions "token" in content
-        let classes = hybrid_search(&db, "token", 10, Some(SymbolKind::Class)).unwrap();
+        let classes = hybrid_search(
+            &db,
+            "token",
+            10,
+            SearchFilter {
+                kind: Some(SymbolKind::Class),
+                ..Default::default()
+            },
+        )
+        .unwrap();
         for r in &classes.results {
             assert_eq!(r.symbol.kind, SymbolKind::Class);
         }
@@ -912,7 +1549,16 @@ mod tests {
         }
 
         // Request 3 functions — should get exactly 3 despite 10 total matches
-        let result = hybrid_search(&db, "handler", 3, Some(SymbolKind::Function)).unwrap();
+        let result = hybrid_search(
+            &db,
+            "handler",
+            3,
+            SearchFilter {
+                kind: Some(SymbolKind::Function),
+                ..Default::default()
+            },
+        )
+        .unwrap();
         assert_eq!(
             result.results.len(),
             3,
@@ -923,6 +1569,74 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_hybrid_search_path_lang_and_exclude_filters() {
+        let db = Database::open_memory().unwrap();
+        for (file, language, name) in [
+            ("src/auth/session.ts", "typescript", "refreshSession"),
+            ("src/auth/Login.tsx", "tsx", "LoginSession"),
+            ("src/auth/session.py", "python", "refresh_session"),
+            ("src/auth/session.test.ts", "typescript", "testSession"),
+            ("src/billing/session.ts", "typescript", "billingSession"),
+        ] {
+            // `--lang` goes by the language recorded for the file.
+            db.upsert_file(&crate::types::FileInfo {
+                path: file.to_string(),
+                last_modified: 0.0,
+                hash: String::new(),
+                language: language.to_string(),
+                num_symbols: 1,
+                generation: 0,
+                lines: Default::default(),
+                truncated: false,
+            })
+            .unwrap();
+            insert_symbol_with_content(
+                &db,
+                name,
+                SymbolKind::Function,
+                file,
+                1,
+                &format!("function {name}() {{ return session.refresh(); }}"),
+            );
+        }
+        let names = |filter: SearchFilter<'_>| -> Vec<String> {
+            let mut names: Vec<String> = hybrid_search(&db, "session", 10, filter)
+                .unwrap()
+                .results
+                .into_iter()
+                .map(|r| r.symbol.name)
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(names(Default::default()).len(), 5);
+        assert_eq!(
+            names(SearchFilter {
+                path: Some("src/auth/**"),
+                lang: Some("typescript"),
+                exclude: Some("*.test.ts"),
+                ..Default::default()
+            }),
+            ["LoginSession", "refreshSession"]
+        );
+        assert_eq!(
+            names(SearchFilter {
+                lang: Some("python"),
+                ..Default::default()
+            }),
+            ["refresh_session"]
+        );
+        assert_eq!(
+            names(SearchFilter {
+                exclude: Some("src/auth/"),
+                ..Default::default()
+            }),
+            ["billingSession"]
+        );
+    }
+
     // ── Cross-language test ──
 
     #[test]
@@ -953,7 +1667,7 @@ mod tests {
             "func validate(token string) bool {\n\treturn checkSignature(token)\n}",
         );
 
-        let result = hybrid_search(&db, "validate", 10, None).unwrap();
+        let result = hybrid_search(&db, "validate", 10, Default::default()).unwrap();
         assert_eq!(
             result.results.len(),
             3,
@@ -978,7 +1692,7 @@ mod tests {
             "def foo(): pass",
         );
 
-        let result = hybrid_search(&db, "zzz_nonexistent_term", 10, None).unwrap();
+        let result = hybrid_search(&db, "zzz_nonexistent_term", 10, Default::default()).unwrap();
         assert!(result.results.is_empty());
         assert_eq!(result.fts_count, 0);
         assert_eq!(result.vec_count, 0);
@@ -990,7 +1704,7 @@ mod tests {
         let content = "def greet(name: str) -> str:\n    return f'Hello, {name}!'";
         insert_symbol_with_content(&db, "greet", SymbolKind::Function, "hello.py", 1, content);
 
-        let result = hybrid_search(&db, "greet", 10, None).unwrap();
+        let result = hybrid_search(&db, "greet", 10, Default::default()).unwrap();
         assert_eq!(result.results.len(), 1);
         assert_eq!(result.results[0].content.as_deref(), Some(content));
     }
@@ -1009,7 +1723,7 @@ mod tests {
             );
         }
 
-        let result = hybrid_search(&db, "handler", 3, None).unwrap();
+        let result = hybrid_search(&db, "handler", 3, Default::default()).unwrap();
         assert_eq!(
             result.results.len(),
             3,
@@ -1018,6 +1732,103 @@ mod tests {
         assert!(result.fts_count > 3, "FTS should over-retrieve");
     }
 
+    #[test]
+    fn test_hybrid_search_match_spans() {
+        let db = Database::open_memory().unwrap();
+        insert_symbol_with_content(
+            &db,
+            "validateToken",
+            SymbolKind::Function,
+            "auth.ts",
+            1,
+            "function validateToken(token) {\n  return check(token);\n}",
+        );
+
+        let sr = hybrid_search(&db, "validate token", 5, Default::default()).unwrap();
+        let r = &sr.results[0];
+        // "validate" at 0..8 and "Token" at 8..13 merge into one span
+        assert_eq!(r.name_matches, vec![MatchSpan { start: 0, end: 13 }]);
+
+        let content = r.content.as_deref().unwrap();
+        let matched: Vec<&str> = r
+            .content_matches
+            .iter()
+            .map(|m| &content[m.start as usize..m.end as usize])
+            .collect();
+        assert_eq!(matched, vec!["validateToken", "token", "token"]);
+        assert_eq!(r.why, r#"name:validate+token, content:"validate"+"token""#);
+    }
+
+    #[test]
+    fn test_explain_match_sources() {
+        let content = Some("def validate_token(token):\n    raise TokenError('expired')");
+        assert_eq!(
+            explain_match("validate_token", content, &["token", "expired"], true, None),
+            r#"name:token, content:"token"+"expired""#
+        );
+        // `validate_token` is not a substring of `validateToken`, only of its normalized form.
+        assert_eq!(
+            explain_match(
+                "validateToken",
+                None,
+                &["validate_token"],
+                true,
+                Some(0.123)
+            ),
+            "normalized:validate_token, vector:0.12"
+        );
+        // Vector-only hit: keyword terms are not the reason it matched.
+        assert_eq!(
+            explain_match("validate_token", content, &["token"], false, Some(0.3)),
+            "vector:0.30"
+        );
+        assert_eq!(
+            explain_match("check", None, &["checks"], true, None),
+            "keyword"
+        );
+    }
+
+    #[test]
+    fn test_match_spans_case_insensitive_and_merged() {
+        assert_eq!(
+            match_spans("getHTTPResponse", &["http", "response"]),
+            vec![MatchSpan { start: 3, end: 15 }]
+        );
+        assert_eq!(
+            match_spans("a_b_a", &["a"]),
+            vec![
+                MatchSpan { start: 0, end: 1 },
+                MatchSpan { start: 4, end: 5 }
+            ]
+        );
+        assert!(match_spans("foo", &["bar", ""]).is_empty());
+        // Byte offsets stay valid around non-ASCII characters
+        assert_eq!(
+            match_spans("héllo_world", &["world"]),
+            vec![MatchSpan { start: 7, end: 12 }]
+        );
+        // Non-ASCII case folding, including a lower-case form longer than the original
+        assert_eq!(
+            match_spans("ΣύνοψηΔεδομένων", &["δεδομένων"]),
+            vec![MatchSpan { start: 12, end: 30 }]
+        );
+        assert_eq!(
+            match_spans("İstanbulClient", &["client"]),
+            vec![MatchSpan { start: 9, end: 15 }]
+        );
+    }
+
+    // ── Model preloading ──
+
+    #[cfg(not(feature = "rag"))]
+    #[test]
+    fn test_preload_without_models_reports_unavailable() {
+        preload_models();
+        let status = models_status();
+        assert_eq!(status.embedding, ModelStatus::Unavailable);
+        assert_eq!(status.reranker, ModelStatus::Unavailable);
+    }
+
     // ── Rerank sorting tests ──
 
     fn make_result(
@@ -1031,7 +1842,13 @@ mod tests {
             content: content.map(|s| s.to_string()),
             rrf_score: rrf,
             rerank_score: rerank,
+            relevance: rerank
+                .map(|r| reranker::calibrate(r, RerankCalibration::default().temperature)),
             sources: vec!["fts5".to_string()],
+            name_matches: Vec::new(),
+            content_matches: Vec::new(),
+            why: "name:test".to_string(),
+            body_omitted: false,
         }
     }
 
@@ -1110,7 +1927,7 @@ mod tests {
             "def process_data(items):\n    return [transform(i) for i in items]",
         );
 
-        let result = hybrid_search(&db, "process data", 10, None).unwrap();
+        let result = hybrid_search(&db, "process data", 10, Default::default()).unwrap();
         assert!(!result.results.is_empty());
 
         // Re-ranking depends on whether the cross-encoder model is downloadable.
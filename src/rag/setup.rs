@@ -5,7 +5,7 @@ use super::model_cache_dir;
 use super::reranker::CrossEncoderEngine;
 
 /// Result of the setup operation.
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
 pub struct SetupResult {
     pub model_dir: String,
 }
@@ -15,6 +15,7 @@ pub struct SetupResult {
 /// fastembed automatically downloads the ONNX model from HuggingFace on first use.
 /// This function eagerly triggers that download so the user sees progress.
 pub fn download_model() -> Result<SetupResult> {
+    super::ensure_models_enabled()?;
     let cache_dir = model_cache_dir();
 
     let _engine =
@@ -29,6 +30,7 @@ pub fn download_model() -> Result<SetupResult> {
 ///
 /// fastembed automatically downloads the ONNX model from HuggingFace on first use.
 pub fn download_cross_encoder() -> Result<SetupResult> {
+    super::ensure_models_enabled()?;
     let cache_dir = model_cache_dir();
 
     let _engine = CrossEncoderEngine::load_with_progress()
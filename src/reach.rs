@@ -0,0 +1,301 @@
+//! Live-code analysis (`cartog reachable`): the symbols reachable from entry
+//! points, and the functions, methods, classes and modules that are not.
+//!
+//! Reachability follows resolved call, reference, inheritance and raises edges
+//! forward from the seeds, so code only called by other dead code is reported
+//! too, which a zero-fan-in check misses. A reached method also keeps its class
+//! alive. Imports are not followed: importing a module does not run its functions.
+//!
+//! The graph is static, so dynamic dispatch, reflection and calls the resolver
+//! could not pin down are invisible to it. Unreachable symbols that such calls
+//! may still reach are reported with [`Confidence::Low`] and the reason.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::{bail, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::shard::ShardedDatabase;
+use crate::testmap::{self, TestRole};
+use crate::types::{EdgeKind, Symbol, SymbolKind};
+
+/// How sure the analysis is that an unreachable symbol is dead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    /// No edge and no unresolved name from live code can reach it.
+    High,
+    /// Live code may reach it in ways the static graph cannot see.
+    Low,
+}
+
+impl std::fmt::Display for Confidence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Confidence::High => "high",
+            Confidence::Low => "low",
+        })
+    }
+}
+
+/// A function, method or class no seed reaches.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Unreachable {
+    pub symbol: Symbol,
+    pub confidence: Confidence,
+    /// Why it may be live after all, for [`Confidence::Low`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Result of [`reachable`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ReachReport {
+    /// IDs of the symbols the walk started from.
+    pub seeds: Vec<String>,
+    /// Functions, methods and classes outside test code that were reached.
+    pub reachable: usize,
+    /// Functions, methods and classes outside test code.
+    pub total: usize,
+    /// Unreachable symbols, high-confidence ones first, then by file and line.
+    pub unreachable: Vec<Unreachable>,
+    /// Files of functions and classes outside test code none of whose symbols were reached.
+    pub unreachable_files: Vec<String>,
+}
+
+/// Walk the graph forward from `from` (a symbol name or ID) or, by default, from
+/// every entry point, and report what was not reached, optionally restricted to
+/// paths starting with `dir`. Test code is neither a seed nor reported.
+pub fn reachable(
+    db: &ShardedDatabase,
+    from: Option<&str>,
+    dir: Option<&str>,
+) -> Result<ReachReport> {
+    let symbols = db.all_symbols()?;
+    let by_id: HashMap<&str, &Symbol> = symbols.iter().map(|s| (s.id.as_str(), s)).collect();
+    let mut memo = HashMap::new();
+    let production: Vec<&Symbol> = symbols
+        .iter()
+        .filter(|s| testmap::role_in(s, &by_id, &mut memo) == TestRole::None)
+        .collect();
+
+    let seeds: Vec<&Symbol> = match from {
+        Some(target) => symbols
+            .iter()
+            .filter(|s| s.name == target || s.id == target)
+            .collect(),
+        None => production
+            .iter()
+            .copied()
+            .filter(|s| s.entrypoint.is_some())
+            .collect(),
+    };
+    if seeds.is_empty() {
+        match from {
+            Some(target) => bail!("No symbol named '{target}' in the index"),
+            None => bail!(
+                "No entry points in the index; pass --from <symbol> to start from a known root"
+            ),
+        }
+    }
+
+    let mut callees: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut unresolved: HashMap<&str, Vec<&str>> = HashMap::new();
+    let edges = db.all_edges()?;
+    for edge in &edges {
+        if edge.kind == EdgeKind::Imports {
+            continue;
+        }
+        match &edge.target_id {
+            Some(target) => callees.entry(&edge.source_id).or_default().push(target),
+            None => unresolved
+                .entry(&edge.source_id)
+                .or_default()
+                .push(testmap::short_name(&edge.target_name)),
+        }
+    }
+
+    let mut live: HashSet<&str> = seeds.iter().map(|s| s.id.as_str()).collect();
+    let mut queue: VecDeque<&str> = live.iter().copied().collect();
+    while let Some(id) = queue.pop_front() {
+        let parent = by_id.get(id).and_then(|s| s.parent_id.as_deref());
+        let next = callees.get(id).into_iter().flatten().copied();
+        for target in next.chain(parent) {
+            if live.insert(target) {
+                queue.push_back(target);
+            }
+        }
+    }
+    let live_names: HashSet<&str> = live
+        .iter()
+        .filter_map(|id| unresolved.get(id))
+        .flatten()
+        .copied()
+        .collect();
+
+    let dir = dir.map(|d| d.trim_start_matches("./"));
+    let in_scope = |s: &Symbol| dir.map_or(true, |d| s.file_path.starts_with(d));
+    let candidates: Vec<&Symbol> = production
+        .iter()
+        .copied()
+        .filter(|s| {
+            matches!(
+                s.kind,
+                SymbolKind::Function | SymbolKind::Method | SymbolKind::Class
+            ) && in_scope(s)
+        })
+        .collect();
+
+    let mut unreachable: Vec<Unreachable> = candidates
+        .iter()
+        .filter(|s| !live.contains(s.id.as_str()))
+        .map(|s| {
+            let live_parent = s
+                .parent_id
+                .as_deref()
+                .filter(|p| live.contains(p))
+                .and_then(|p| by_id.get(p));
+            let reason = match live_parent {
+                Some(parent) if s.kind == SymbolKind::Method => Some(format!(
+                    "method of reachable {}, may be called through dynamic dispatch",
+                    parent.name
+                )),
+                _ if live_names.contains(s.name.as_str()) => {
+                    Some(format!("reachable code calls an unresolved '{}'", s.name))
+                }
+                _ => None,
+            };
+            Unreachable {
+                symbol: (*s).clone(),
+                confidence: if reason.is_some() {
+                    Confidence::Low
+                } else {
+                    Confidence::High
+                },
+                reason,
+            }
+        })
+        .collect();
+    unreachable.sort_by_key(|u| u.confidence);
+
+    // Files of functions and classes, so a module of constants is not reported
+    let mut files: Vec<&str> = candidates.iter().map(|s| s.file_path.as_str()).collect();
+    files.dedup();
+    let live_files: HashSet<&str> = production
+        .iter()
+        .filter(|s| live.contains(s.id.as_str()))
+        .map(|s| s.file_path.as_str())
+        .collect();
+
+    Ok(ReachReport {
+        seeds: seeds.iter().map(|s| s.id.clone()).collect(),
+        reachable: candidates
+            .iter()
+            .filter(|s| live.contains(s.id.as_str()))
+            .count(),
+        total: candidates.len(),
+        unreachable,
+        unreachable_files: files
+            .into_iter()
+            .filter(|f| !live_files.contains(f))
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::types::Edge;
+
+    fn setup() -> ShardedDatabase {
+        let db = Database::open_memory().unwrap();
+        let sym = |name: &str, kind, file: &str, line| {
+            Symbol::new(name, kind, file, line, line + 2, 0, 10)
+        };
+        let main =
+            sym("main", SymbolKind::Function, "app/main.py", 1).with_entrypoint(Some("script"));
+        let run = sym("run", SymbolKind::Function, "app/main.py", 10);
+        let handler = sym("Handler", SymbolKind::Class, "app/handler.py", 1);
+        let handle =
+            sym("handle", SymbolKind::Method, "app/handler.py", 2).with_parent(Some(&handler.id));
+        let close =
+            sym("close", SymbolKind::Method, "app/handler.py", 6).with_parent(Some(&handler.id));
+        let hook = sym("on_event", SymbolKind::Function, "app/hooks.py", 1);
+        let legacy = sym("legacy", SymbolKind::Function, "app/old.py", 1);
+        let legacy_helper = sym("legacy_helper", SymbolKind::Function, "app/old.py", 10);
+        let test = sym("test_legacy", SymbolKind::Function, "tests/test_old.py", 1);
+        db.insert_symbols(&[
+            main.clone(),
+            run.clone(),
+            handler,
+            handle,
+            close,
+            hook,
+            legacy.clone(),
+            legacy_helper,
+            test.clone(),
+        ])
+        .unwrap();
+        db.insert_edges(&[
+            Edge::new(&main.id, "run", EdgeKind::Calls, "app/main.py", 2),
+            Edge::new(&run.id, "handle", EdgeKind::Calls, "app/main.py", 11),
+            Edge::new(
+                &legacy.id,
+                "legacy_helper",
+                EdgeKind::Calls,
+                "app/old.py",
+                2,
+            ),
+            Edge::new(&test.id, "legacy", EdgeKind::Calls, "tests/test_old.py", 2),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
+        // A call the resolver could not pin down (inserted after resolution)
+        db.insert_edges(&[Edge::new(
+            &run.id,
+            "registry.on_event",
+            EdgeKind::Calls,
+            "app/main.py",
+            12,
+        )])
+        .unwrap();
+        ShardedDatabase::from(db)
+    }
+
+    #[test]
+    fn test_reachable_from_entrypoints() {
+        let db = setup();
+        let report = reachable(&db, None, None).unwrap();
+        assert_eq!(report.seeds, ["app/main.py:main:1"]);
+        let found: Vec<_> = report
+            .unreachable
+            .iter()
+            .map(|u| (u.symbol.name.as_str(), u.confidence))
+            .collect();
+        // Called only from dead code or tests is still dead; a live class's method
+        // and a name live code calls unresolved may be reached dynamically.
+        assert_eq!(
+            found,
+            [
+                ("legacy", Confidence::High),
+                ("legacy_helper", Confidence::High),
+                ("close", Confidence::Low),
+                ("on_event", Confidence::Low),
+            ]
+        );
+        assert_eq!((report.reachable, report.total), (4, 8));
+        assert_eq!(report.unreachable_files, ["app/hooks.py", "app/old.py"]);
+    }
+
+    #[test]
+    fn test_reachable_from_symbol() {
+        let db = setup();
+        let report = reachable(&db, Some("legacy"), Some("app/old.py")).unwrap();
+        assert!(report.unreachable.is_empty());
+        assert_eq!((report.reachable, report.total), (2, 2));
+        assert!(reachable(&db, Some("missing"), None).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Rename detection when a file is re-indexed.
+//!
+//! Symbol IDs embed the qualified name and a hash of the code
+//! (`file:qualified.name:hash`), so renaming a function gives it a new ID and
+//! re-indexing sees one symbol removed and another added. The two bodies still
+//! match once the name is blanked out: pairing removed and added symbols by that
+//! hash lets the re-index carry the embedding, scratch notes and pin of the old
+//! symbol over to the new one.
+
+use std::collections::{HashMap, HashSet};
+
+use sha2::{Digest, Sha256};
+
+use crate::types::SymbolKind;
+
+/// A symbol of a file with the hash of its body, as compared across re-indexes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolBody {
+    pub id: String,
+    pub name: String,
+    pub kind: SymbolKind,
+    /// [`body_hash`] of the symbol's content.
+    pub hash: String,
+}
+
+impl SymbolBody {
+    pub fn new(id: &str, name: &str, kind: SymbolKind, content: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            kind,
+            hash: body_hash(content, name),
+        }
+    }
+}
+
+/// A removed symbol matched to the added symbol that replaces it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    pub old_id: String,
+    pub new_id: String,
+    pub old_name: String,
+    pub new_name: String,
+    /// Whether state keyed by name (notes, pins) follows too: the name changed and
+    /// no symbol of the updated file still has the old one.
+    pub moves_name: bool,
+}
+
+/// SHA-256 of `content` with every whole-word occurrence of `name` blanked out, so
+/// a renamed symbol, recursive calls included, hashes as before.
+pub fn body_hash(content: &str, name: &str) -> String {
+    let mut hasher = Sha256::new();
+    let mut copied = 0;
+    if !name.is_empty() {
+        for (start, _) in content.match_indices(name) {
+            let end = start + name.len();
+            let bounded = !content[..start].chars().next_back().is_some_and(is_ident)
+                && !content[end..].chars().next().is_some_and(is_ident);
+            if bounded {
+                hasher.update(&content.as_bytes()[copied..start]);
+                hasher.update(b"\0");
+                copied = end;
+            }
+        }
+    }
+    hasher.update(&content.as_bytes()[copied..]);
+    format!("{:x}", hasher.finalize())
+}
+
+fn is_ident(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Pair the symbols of `old` missing from `new` with the symbols of `new` missing
+/// from `old` that have the same kind and body hash.
+///
+/// Only unambiguous pairs count: when several removed or added symbols share a
+/// kind and hash, none of them is paired.
+pub fn match_renames(old: &[SymbolBody], new: &[SymbolBody]) -> Vec<Rename> {
+    let old_ids: HashSet<&str> = old.iter().map(|s| s.id.as_str()).collect();
+    let new_ids: HashSet<&str> = new.iter().map(|s| s.id.as_str()).collect();
+    let new_names: HashSet<&str> = new.iter().map(|s| s.name.as_str()).collect();
+
+    let mut removed: HashMap<(SymbolKind, &str), Vec<&SymbolBody>> = HashMap::new();
+    for sym in old.iter().filter(|s| !new_ids.contains(s.id.as_str())) {
+        removed
+            .entry((sym.kind, sym.hash.as_str()))
+            .or_default()
+            .push(sym);
+    }
+    let mut added: HashMap<(SymbolKind, &str), Vec<&SymbolBody>> = HashMap::new();
+    for sym in new.iter().filter(|s| !old_ids.contains(s.id.as_str())) {
+        added
+            .entry((sym.kind, sym.hash.as_str()))
+            .or_default()
+            .push(sym);
+    }
+
+    let mut renames: Vec<Rename> = removed
+        .iter()
+        .filter_map(
+            |(key, gone)| match (gone.as_slice(), added.get(key)?.as_slice()) {
+                ([before], [after]) => Some(Rename {
+                    old_id: before.id.clone(),
+                    new_id: after.id.clone(),
+                    old_name: before.name.clone(),
+                    new_name: after.name.clone(),
+                    moves_name: before.name != after.name
+                        && !new_names.contains(before.name.as_str()),
+                }),
+                _ => None,
+            },
+        )
+        .collect();
+    renames.sort_by(|a, b| a.old_id.cmp(&b.old_id));
+    renames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(name: &str, line: u32, content: &str) -> SymbolBody {
+        let id = format!("a.py:{name}:{line}");
+        SymbolBody::new(&id, name, SymbolKind::Function, content)
+    }
+
+    #[test]
+    fn test_body_hash_ignores_the_name_only() {
+        let login = body_hash("def login(u):\n    return login(u.next)", "login");
+        let sign_in = body_hash("def sign_in(u):\n    return sign_in(u.next)", "sign_in");
+        assert_eq!(login, sign_in);
+        // `login_url` is another identifier, not the name.
+        assert_ne!(
+            body_hash("def login(): login_url()", "login"),
+            body_hash("def go(): go_url()", "go")
+        );
+        assert_ne!(login, body_hash("def login(u):\n    return None", "login"));
+    }
+
+    #[test]
+    fn test_match_renames_pairs_unambiguous_bodies() {
+        let old = vec![
+            body("login", 1, "def login(): return 1"),
+            body("keep", 3, "def keep(): return 2"),
+            body("a", 5, "def a(): pass"),
+            body("b", 7, "def b(): pass"),
+        ];
+        let new = vec![
+            body("sign_in", 1, "def sign_in(): return 1"),
+            body("keep", 3, "def keep(): return 2"),
+            body("c", 5, "def c(): pass"),
+            body("d", 7, "def d(): pass"),
+        ];
+        let renames = match_renames(&old, &new);
+        assert_eq!(renames.len(), 1);
+        assert_eq!(renames[0].old_id, "a.py:login:1");
+        assert_eq!(renames[0].new_id, "a.py:sign_in:1");
+        assert!(renames[0].moves_name);
+
+        // A symbol moved down a line keeps its name-keyed state where it is.
+        let moved = match_renames(
+            &[body("keep", 3, "def keep(): return 2")],
+            &[body("keep", 4, "def keep(): return 2")],
+        );
+        assert_eq!(moved[0].new_id, "a.py:keep:4");
+        assert!(!moved[0].moves_name);
+    }
+}
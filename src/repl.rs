@@ -0,0 +1,373 @@
+//! `cartog repl`: queries at a prompt against one open index.
+//!
+//! Every listing is numbered, and a later command takes a number in place of a
+//! name to act on that result (`refs 3`, `show 2`), so exploring the graph needs no
+//! copying of names or paths. The database stays open between queries, and so do
+//! the embedding models once a `rag` search loaded them. Nothing is written to the
+//! index.
+
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+
+use anyhow::{bail, Result};
+
+use crate::rag::search::{self, SearchFilter, SearchMode};
+use crate::shard::ShardedDatabase;
+use crate::source::{self, SymbolSource};
+use crate::types::Symbol;
+
+const PROMPT: &str = "cartog> ";
+/// Most results listed by `search` and `rag`.
+const RESULT_LIMIT: u32 = 30;
+/// Context lines around the code printed by `show`.
+const SHOW_CONTEXT: u32 = 2;
+/// Hops followed by `impact`.
+const IMPACT_DEPTH: u32 = 3;
+
+const HELP: &str = "\
+Commands (a number N stands for result N of the last listing):
+  search|s QUERY        symbols whose name matches QUERY
+  rag QUERY             semantic search (hybrid: keyword + vector)
+  outline|o FILE|N      symbols of a file, or of result N's file
+  refs|r NAME|N         references to a symbol
+  callers|c NAME|N      who calls a symbol
+  callees|e NAME|N      what a symbol calls
+  impact|i NAME|N       transitive references, 3 hops deep
+  show NAME|N           source of a symbol (keeps the listing)
+  help|?                this help
+  quit|exit|q           leave (Ctrl+D too)
+";
+
+/// Read commands from stdin until `quit` or end of input, printing each reply.
+/// The prompt is only shown when stdin is a terminal, so commands can be piped in.
+pub fn run_repl(db: &ShardedDatabase, root: &Path) -> Result<()> {
+    let stdin = std::io::stdin();
+    let interactive = stdin.is_terminal();
+    let mut out = std::io::stdout().lock();
+    if interactive {
+        writeln!(out, "Type `help` for commands, `quit` to leave.")?;
+    }
+    let mut session = Session::new(db, root);
+    let mut lines = stdin.lock().lines();
+    loop {
+        if interactive {
+            write!(out, "{PROMPT}")?;
+            out.flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        match session.eval(&line) {
+            Ok(Some(reply)) => write!(out, "{reply}")?,
+            Ok(None) => break,
+            // A failed query ends the command, not the session.
+            Err(e) => writeln!(out, "error: {e:#}")?,
+        }
+    }
+    Ok(())
+}
+
+/// What a number in a command refers to: the symbols of the last listing.
+struct Session<'a> {
+    db: &'a ShardedDatabase,
+    root: &'a Path,
+    /// One entry per listed line; `None` for lines without a symbol (unresolved
+    /// callees, references from outside any symbol).
+    results: Vec<Option<Symbol>>,
+}
+
+/// A command's argument: a symbol of the last listing, or text as typed.
+enum Target {
+    Result(Symbol),
+    Text(String),
+}
+
+impl Target {
+    fn name(&self) -> &str {
+        match self {
+            Self::Result(symbol) => &symbol.name,
+            Self::Text(text) => text,
+        }
+    }
+}
+
+impl<'a> Session<'a> {
+    fn new(db: &'a ShardedDatabase, root: &'a Path) -> Self {
+        Self {
+            db,
+            root,
+            results: Vec::new(),
+        }
+    }
+
+    /// Run one command line. `None` means quit.
+    fn eval(&mut self, line: &str) -> Result<Option<String>> {
+        let line = line.trim();
+        let (command, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let arg = arg.trim();
+        let reply = match command {
+            "" => String::new(),
+            "quit" | "exit" | "q" => return Ok(None),
+            "help" | "?" => HELP.to_string(),
+            _ if arg.is_empty() => bail!("`{command}` needs an argument (see `help`)"),
+            "search" | "s" => self.search(arg)?,
+            "rag" => self.rag(arg)?,
+            "outline" | "o" => self.outline(arg)?,
+            "refs" | "r" => self.refs(arg)?,
+            "callers" | "c" => self.callers(arg)?,
+            "callees" | "e" => self.callees(arg)?,
+            "impact" | "i" => self.impact(arg)?,
+            "show" => self.show(arg)?,
+            _ => bail!("unknown command `{command}` (see `help`)"),
+        };
+        Ok(Some(reply))
+    }
+
+    /// `arg` as a result number when it is one, else as typed.
+    fn target(&self, arg: &str) -> Result<Target> {
+        let Ok(n) = arg.parse::<usize>() else {
+            return Ok(Target::Text(arg.to_string()));
+        };
+        match self.results.get(n.wrapping_sub(1)) {
+            Some(Some(symbol)) => Ok(Target::Result(symbol.clone())),
+            Some(None) => bail!("result {n} has no symbol to open"),
+            None if self.results.is_empty() => bail!("no results yet; run a search first"),
+            None => bail!("no result {n}; the last listing has {}", self.results.len()),
+        }
+    }
+
+    /// Number `entries` and make them the results later numbers refer to. An empty
+    /// listing prints `empty` and keeps the previous results.
+    fn list(&mut self, entries: Vec<(String, Option<Symbol>)>, empty: &str) -> String {
+        if entries.is_empty() {
+            return format!("{empty}\n");
+        }
+        let width = entries.len().to_string().len();
+        let mut text = String::new();
+        self.results.clear();
+        for (i, (line, symbol)) in entries.into_iter().enumerate() {
+            text.push_str(&format!("{:>width$}. {line}\n", i + 1));
+            self.results.push(symbol);
+        }
+        text
+    }
+
+    fn search(&mut self, query: &str) -> Result<String> {
+        let symbols = self.db.search(query, None, None, RESULT_LIMIT)?;
+        let entries = symbols.into_iter().map(symbol_entry).collect();
+        Ok(self.list(entries, &format!("No symbols matching '{query}'")))
+    }
+
+    fn rag(&mut self, query: &str) -> Result<String> {
+        self.db.ensure_rag_supported()?;
+        let found = search::search(
+            self.db,
+            query,
+            RESULT_LIMIT,
+            SearchFilter::default(),
+            SearchMode::Hybrid,
+            None,
+        )?;
+        let entries = found
+            .results
+            .into_iter()
+            .map(|r| symbol_entry(r.symbol))
+            .collect();
+        Ok(self.list(entries, &format!("No results found for '{query}'")))
+    }
+
+    fn outline(&mut self, arg: &str) -> Result<String> {
+        let file = match self.target(arg)? {
+            Target::Result(symbol) => symbol.file_path,
+            Target::Text(file) => file,
+        };
+        let symbols = self.db.outline(&file)?;
+        let entries = symbols
+            .into_iter()
+            .map(|s| {
+                let line = format!("{} {}  {}-{}", s.kind, s.name, s.start_line, s.end_line);
+                (line, Some(s))
+            })
+            .collect();
+        Ok(self.list(entries, &format!("No symbols in '{file}'")))
+    }
+
+    fn refs(&mut self, arg: &str) -> Result<String> {
+        let target = self.target(arg)?;
+        let name = target.name();
+        let entries = self
+            .db
+            .refs(name, None)?
+            .into_iter()
+            .map(|(edge, source)| {
+                let from = source.as_ref().map_or(&edge.source_id, |s| &s.name);
+                let line = format!("{} {from}  {}:{}", edge.kind, edge.file_path, edge.line);
+                (line, source)
+            })
+            .collect();
+        Ok(self.list(entries, &format!("No references to '{name}'")))
+    }
+
+    fn callers(&mut self, arg: &str) -> Result<String> {
+        let target = self.target(arg)?;
+        let name = target.name();
+        let entries = self
+            .db
+            .callers(name, None)?
+            .into_iter()
+            .map(|c| {
+                let line = format!(
+                    "{} {}  {}:{}",
+                    c.caller.kind, c.caller.name, c.edge.file_path, c.edge.line
+                );
+                (line, Some(c.caller))
+            })
+            .collect();
+        Ok(self.list(entries, &format!("No callers of '{name}'")))
+    }
+
+    fn callees(&mut self, arg: &str) -> Result<String> {
+        let target = self.target(arg)?;
+        let name = target.name();
+        let entries = self
+            .db
+            .call_sites(name, false, None)?
+            .into_iter()
+            .map(|site| {
+                let edge = &site.edge;
+                let to = site.target.as_ref().map_or(String::new(), |t| {
+                    format!("  -> {}:{}", t.file_path, t.start_line)
+                });
+                let line = format!("{}  {}:{}{to}", edge.target_name, edge.file_path, edge.line);
+                (line, site.target)
+            })
+            .collect();
+        Ok(self.list(entries, &format!("No calls from '{name}'")))
+    }
+
+    fn impact(&mut self, arg: &str) -> Result<String> {
+        let target = self.target(arg)?;
+        let name = target.name();
+        let mut entries = Vec::new();
+        for (edge, depth) in self.db.impact(name, IMPACT_DEPTH)? {
+            let source = self.db.get_symbol(&edge.source_id)?;
+            let line = format!(
+                "{indent}{kind} {from}  {file}:{line}",
+                indent = "  ".repeat(depth.saturating_sub(1) as usize),
+                kind = edge.kind,
+                from = source.as_ref().map_or(&edge.source_id, |s| &s.name),
+                file = edge.file_path,
+                line = edge.line,
+            );
+            entries.push((line, source));
+        }
+        Ok(self.list(entries, &format!("No impact found for '{name}'")))
+    }
+
+    /// Unlike the other commands, leaves the numbered results as they are, so
+    /// several of them can be shown in turn.
+    fn show(&mut self, arg: &str) -> Result<String> {
+        let sources = match self.target(arg)? {
+            Target::Result(symbol) => {
+                vec![source::snippet(self.db, self.root, symbol, SHOW_CONTEXT)?]
+            }
+            Target::Text(name) => {
+                source::symbol_sources(self.db, self.root, &name, None, SHOW_CONTEXT)?
+            }
+        };
+        if sources.is_empty() {
+            return Ok(format!("No definition of '{arg}' found\n"));
+        }
+        Ok(sources
+            .iter()
+            .map(render_source)
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+fn symbol_entry(s: Symbol) -> (String, Option<Symbol>) {
+    let line = format!("{} {}  {}:{}", s.kind, s.name, s.file_path, s.start_line);
+    (line, Some(s))
+}
+
+/// A symbol's header line and numbered code, as `cartog show` prints them.
+fn render_source(src: &SymbolSource) -> String {
+    let sym = &src.symbol;
+    let mut text = format!(
+        "{} {}  {}:{}-{}\n",
+        sym.kind, sym.name, sym.file_path, sym.start_line, sym.end_line
+    );
+    match &src.code {
+        Some(code) => {
+            let width = src.last_line.to_string().len();
+            for (n, line) in (src.first_line..).zip(code.lines()) {
+                text.push_str(&format!("{n:>width$}  {line}\n"));
+            }
+        }
+        None => text.push_str("  (source unavailable)\n"),
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn eval(session: &mut Session, line: &str) -> String {
+        session.eval(line).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_numbered_results_feed_follow_up_commands() {
+        let tmp = std::env::temp_dir().join("cartog_test_repl");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        std::fs::write(
+            tmp.join("auth.py"),
+            "def check():\n    pass\n\n\ndef login():\n    check()\n",
+        )
+        .unwrap();
+        std::fs::write(
+            tmp.join("web.py"),
+            "from auth import login\n\n\ndef handler():\n    login()\n",
+        )
+        .unwrap();
+        let db = ShardedDatabase::from(Database::open_memory().unwrap());
+        crate::indexer::index_directory(&db, &tmp, false).unwrap();
+        let mut session = Session::new(&db, &tmp);
+
+        assert_eq!(
+            eval(&mut session, "s login"),
+            "1. function login  auth.py:5\n"
+        );
+        assert!(eval(&mut session, "refs 1").contains(". calls handler  web.py:5\n"));
+        assert_eq!(
+            eval(&mut session, "callees login"),
+            "1. check  auth.py:6  -> auth.py:1\n"
+        );
+        // `show` keeps the listing, so the same number still works after it.
+        let shown = eval(&mut session, "show 1");
+        assert!(shown.starts_with("function check  auth.py:1-2\n1  def check():\n"));
+        assert!(eval(&mut session, "callers 1").contains("function login  auth.py:6"));
+        assert_eq!(
+            eval(&mut session, "o 1"),
+            "1. function check  1-2\n2. function login  5-6\n"
+        );
+        assert!(eval(&mut session, "impact check").contains("  calls handler  web.py:5"));
+
+        // An empty listing keeps the previous results.
+        assert_eq!(
+            eval(&mut session, "s nothing"),
+            "No symbols matching 'nothing'\n"
+        );
+        assert!(session.eval("show 7").is_err());
+        assert!(session.eval("refs").is_err());
+        assert!(session.eval("frobnicate x").is_err());
+        assert_eq!(eval(&mut session, "  "), "");
+        assert!(session.eval("quit").unwrap().is_none());
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+}
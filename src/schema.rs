@@ -0,0 +1,295 @@
+//! JSON Schemas of `--json` output, generated from the serde types of each command.
+//!
+//! Printed by `cartog schema <command>` and served as `cartog://schema/<command>` MCP
+//! resources. MCP tools return the same shapes as the matching CLI command. Commands with
+//! several output shapes (`search --group`, `stats --by-dir`) get an `anyOf`.
+
+use schemars::generate::SchemaSettings;
+use schemars::{Schema, SchemaGenerator};
+use serde_json::{json, Value};
+
+use crate::callpath::CallPath;
+use crate::commands::{
+    CleanResult, RagSetup, RebuildFtsResult, SearchWithAnchors, UnpinResult, VerifyReport,
+};
+use crate::context::ContextPack;
+use crate::cycles::CycleReport;
+use crate::db::{
+    AuditEntry, BackupResult, DirStats, DocCoverage, FtsWeights, HotSymbol, IndexStats,
+    MaintenanceReport, RerankCalibration, ResolutionExplanation,
+};
+use crate::doctor::DoctorReport;
+use crate::export::ExportedTable;
+use crate::health::Health;
+use crate::impact::{ImpactLevel, ImpactSummary};
+use crate::indexer::{IndexResult, PruneResult};
+use crate::indexes::NamedIndex;
+use crate::orm::TableUsages;
+use crate::overloads::{RefGroup, Reference, SymbolGroup};
+use crate::owners::OwnersImpactReport;
+use crate::packages::{Package, PackageDeps, SyncResult};
+use crate::rag::freshness::RagStatus;
+use crate::rag::indexer::RagIndexResult;
+use crate::rag::migrate::MigrationStatus;
+use crate::rag::search::HybridSearchResult;
+use crate::reach::ReachReport;
+use crate::selftest::SelftestReport;
+use crate::setops::SetResult;
+use crate::source::SymbolSource;
+use crate::sync::SyncDelta;
+use crate::testmap::{TestHit, TestSelection};
+use crate::training::TrainingSet;
+use crate::types::{
+    Anchor, CallSite, Caller, Edge, HierarchyEntry, ImpactEntry, Injection, Symbol, SymbolMatch,
+};
+
+/// Commands with a schema, named like their subcommand (`rag search` → `rag-search`).
+pub const COMMANDS: &[&str] = &[
+    "index",
+    "outline",
+    "show",
+    "context",
+    "callees",
+    "callers",
+    "impact",
+    "raises",
+    "path",
+    "refs",
+    "hierarchy",
+    "deps",
+    "stats",
+    "health",
+    "verify",
+    "doctor",
+    "selftest",
+    "search",
+    "set",
+    "doc-coverage",
+    "todos",
+    "injections",
+    "tests-for",
+    "select-tests",
+    "untested",
+    "entrypoints",
+    "reachable",
+    "cycles",
+    "table-usages",
+    "explain-resolution",
+    "pin",
+    "unpin",
+    "clean",
+    "export",
+    "indexes",
+    "backup",
+    "maintain",
+    "prune",
+    "owners-impact",
+    "sync",
+    "rag-setup",
+    "rag-index",
+    "rag-migrate",
+    "rag-status",
+    "rag-rebuild-fts",
+    "rag-fts-weights",
+    "rag-calibration",
+    "rag-search",
+    "audit-tail",
+    "emit-training-data",
+    "packages-sync",
+    "packages-list",
+    "packages-deps",
+];
+
+/// URI of a command's schema as an MCP resource.
+pub fn resource_uri(command: &str) -> String {
+    format!("cartog://schema/{command}")
+}
+
+/// JSON Schema (draft 2020-12) of `cartog <command> --json` output, or `None` for an
+/// unknown command.
+pub fn schema(command: &str) -> Option<Value> {
+    let mut g = SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator();
+    let shapes = match command {
+        "index" => vec![g.subschema_for::<IndexResult>()],
+        "outline" | "untested" | "entrypoints" => vec![g.subschema_for::<Vec<Symbol>>()],
+        "show" => vec![g.subschema_for::<Vec<SymbolSource>>()],
+        "context" => vec![g.subschema_for::<ContextPack>()],
+        "callees" => vec![g.subschema_for::<Vec<CallSite>>()],
+        "callers" => vec![g.subschema_for::<Vec<Caller>>()],
+        "impact" => vec![
+            g.subschema_for::<Vec<ImpactEntry>>(),
+            g.subschema_for::<Vec<ImpactLevel>>(),
+            g.subschema_for::<ImpactSummary>(),
+        ],
+        "raises" => vec![g.subschema_for::<Vec<ImpactEntry>>()],
+        "path" => vec![g.subschema_for::<Vec<CallPath>>()],
+        "refs" => vec![
+            g.subschema_for::<Vec<Reference>>(),
+            g.subschema_for::<Vec<RefGroup>>(),
+        ],
+        "hierarchy" => vec![g.subschema_for::<Vec<HierarchyEntry>>()],
+        "deps" => vec![g.subschema_for::<Vec<Edge>>()],
+        "stats" => vec![
+            g.subschema_for::<IndexStats>(),
+            g.subschema_for::<Vec<DirStats>>(),
+            g.subschema_for::<Vec<HotSymbol>>(),
+        ],
+        "health" => vec![g.subschema_for::<Health>()],
+        "verify" => vec![g.subschema_for::<VerifyReport>()],
+        "doctor" => vec![g.subschema_for::<DoctorReport>()],
+        "selftest" => vec![g.subschema_for::<SelftestReport>()],
+        "search" => vec![
+            g.subschema_for::<Vec<SymbolMatch>>(),
+            g.subschema_for::<Vec<SymbolGroup>>(),
+            g.subschema_for::<SearchWithAnchors<SymbolMatch>>(),
+            g.subschema_for::<SearchWithAnchors<SymbolGroup>>(),
+        ],
+        "set" => vec![g.subschema_for::<SetResult>()],
+        "doc-coverage" => vec![g.subschema_for::<DocCoverage>()],
+        "todos" => vec![g.subschema_for::<Vec<Anchor>>()],
+        "injections" => vec![g.subschema_for::<Vec<Injection>>()],
+        "tests-for" => vec![g.subschema_for::<Vec<TestHit>>()],
+        "select-tests" => vec![
+            g.subschema_for::<TestSelection>(),
+            g.subschema_for::<Vec<String>>(),
+        ],
+        "reachable" => vec![g.subschema_for::<ReachReport>()],
+        "cycles" => vec![g.subschema_for::<CycleReport>()],
+        "table-usages" => vec![g.subschema_for::<TableUsages>()],
+        "explain-resolution" => vec![g.subschema_for::<Vec<ResolutionExplanation>>()],
+        "pin" => vec![g.subschema_for::<Symbol>()],
+        "unpin" => vec![g.subschema_for::<UnpinResult>()],
+        "clean" => vec![g.subschema_for::<CleanResult>()],
+        "export" => vec![g.subschema_for::<Vec<ExportedTable>>()],
+        "indexes" => vec![g.subschema_for::<Vec<NamedIndex>>()],
+        "backup" => vec![g.subschema_for::<BackupResult>()],
+        "maintain" => vec![g.subschema_for::<MaintenanceReport>()],
+        "prune" => vec![g.subschema_for::<PruneResult>()],
+        "owners-impact" => vec![g.subschema_for::<OwnersImpactReport>()],
+        "sync" => vec![g.subschema_for::<SyncDelta>()],
+        "rag-setup" => vec![g.subschema_for::<RagSetup>()],
+        "rag-index" => vec![g.subschema_for::<RagIndexResult>()],
+        "rag-migrate" => vec![g.subschema_for::<MigrationStatus>()],
+        "rag-status" => vec![g.subschema_for::<RagStatus>()],
+        "rag-rebuild-fts" => vec![g.subschema_for::<RebuildFtsResult>()],
+        "rag-fts-weights" => vec![g.subschema_for::<FtsWeights>()],
+        "rag-calibration" => vec![g.subschema_for::<RerankCalibration>()],
+        "rag-search" => vec![g.subschema_for::<HybridSearchResult>()],
+        "audit-tail" => vec![g.subschema_for::<Vec<AuditEntry>>()],
+        "emit-training-data" => vec![g.subschema_for::<TrainingSet>()],
+        "packages-sync" => vec![g.subschema_for::<SyncResult>()],
+        "packages-list" => vec![g.subschema_for::<Vec<Package>>()],
+        "packages-deps" => vec![g.subschema_for::<PackageDeps>()],
+        _ => return None,
+    };
+    Some(finish(command, g, shapes))
+}
+
+/// Assemble the root schema: one shape inline or several under `anyOf`, plus the
+/// shared `$defs`.
+fn finish(command: &str, mut g: SchemaGenerator, shapes: Vec<Schema>) -> Value {
+    let mut root = match <[Schema; 1]>::try_from(shapes) {
+        Ok([shape]) => shape.to_value(),
+        Err(shapes) => json!({ "anyOf": shapes }),
+    };
+    let mut defs = Value::Object(g.take_definitions(true));
+    add_token_estimates(&mut root);
+    add_token_estimates(&mut defs);
+
+    let mut schema = json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": resource_uri(command),
+        "title": format!("cartog {} --json", subcommand(command)),
+    });
+    if let (Value::Object(schema), Value::Object(shape)) = (&mut schema, root) {
+        schema.extend(shape);
+    }
+    if defs.as_object().is_some_and(|d| !d.is_empty()) {
+        schema["$defs"] = defs;
+    }
+    schema
+}
+
+/// Command line spelling of a schema name: `rag-search` → `rag search`.
+pub fn subcommand(command: &str) -> String {
+    match command.split_once('-') {
+        Some((group @ ("rag" | "audit" | "packages"), rest)) => format!("{group} {rest}"),
+        _ => command.to_string(),
+    }
+}
+
+/// Declare the `estimated_tokens` field [`crate::tokens::annotate`] adds to results
+/// carrying code: objects with `start_byte`/`end_byte` or a `content` string.
+fn add_token_estimates(value: &mut Value) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(add_token_estimates),
+        Value::Object(obj) => {
+            if let Some(Value::Object(props)) = obj.get_mut("properties") {
+                let carries_code = (props.contains_key("start_byte")
+                    && props.contains_key("end_byte"))
+                    || props.get("content").is_some_and(is_string);
+                if carries_code && !props.contains_key("estimated_tokens") {
+                    props.insert(
+                        "estimated_tokens".to_string(),
+                        json!({
+                            "description": "Approximate cost of reading this result into a model's context (about 4 bytes per token).",
+                            "type": "integer",
+                            "minimum": 0
+                        }),
+                    );
+                }
+            }
+            obj.values_mut().for_each(add_token_estimates);
+        }
+        _ => {}
+    }
+}
+
+/// Whether a property schema allows a string (`"string"` or `["string", "null"]`).
+fn is_string(prop: &Value) -> bool {
+    match &prop["type"] {
+        Value::String(t) => t == "string",
+        Value::Array(types) => types.iter().any(|t| t == "string"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_command_has_a_schema() {
+        for command in COMMANDS {
+            let schema = schema(command).unwrap_or_else(|| panic!("{command}"));
+            assert_eq!(schema["$id"], resource_uri(command));
+        }
+        assert!(schema("serve").is_none());
+    }
+
+    #[test]
+    fn test_schema_shapes_and_token_estimates() {
+        let outline = schema("outline").unwrap();
+        assert_eq!(outline["type"], "array");
+        let symbol = &outline["$defs"]["Symbol"]["properties"];
+        assert!(symbol["start_byte"].is_object());
+        assert_eq!(symbol["estimated_tokens"]["type"], "integer");
+        let hit = schema("rag-search").unwrap();
+        assert!(hit["$defs"]["SearchResult"]["properties"]["estimated_tokens"].is_object());
+        let weights = schema("rag-fts-weights").unwrap();
+        assert!(weights["$defs"]["FtsWeights"]["properties"]
+            .get("estimated_tokens")
+            .is_none());
+
+        let refs = schema("refs").unwrap();
+        assert_eq!(refs["anyOf"].as_array().unwrap().len(), 2);
+        assert!(refs["$defs"]["RefGroup"]["properties"]["definitions"].is_object());
+        assert_eq!(refs["title"], "cartog refs --json");
+        assert_eq!(
+            schema("rag-search").unwrap()["title"],
+            "cartog rag search --json"
+        );
+    }
+}
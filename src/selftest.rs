@@ -0,0 +1,353 @@
+//! `cartog selftest`: run every extractor over the benchmark fixtures and compare
+//! what they find with golden files, to check a build (grammar versions, platform
+//! quirks) before trusting its index.
+//!
+//! Each fixture `benchmarks/fixtures/<name>/` has a golden file
+//! `benchmarks/selftest/<name>.json` holding its file, symbol and edge counts (in
+//! total and per kind), the fingerprints of the grammars they were taken with, and
+//! key symbols that must be found. Any difference is reported as drift. `--update`
+//! rewrites the counts and fingerprints after an intended extractor change, keeping
+//! the key symbols, which are maintained by hand.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::languages::{detect_language, get_extractor, grammar_version};
+use crate::types::SymbolKind;
+
+/// Fixture sources, under the benchmarks directory.
+pub const FIXTURES_DIR: &str = "fixtures";
+/// Golden files, under the benchmarks directory.
+pub const GOLDEN_DIR: &str = "selftest";
+
+/// The benchmarks directory: `dir` when given, else `benchmarks/` in the current
+/// directory, else the one of the source tree cartog was built from.
+pub fn benchmarks_dir(dir: Option<&Path>) -> Result<PathBuf> {
+    if let Some(dir) = dir {
+        anyhow::ensure!(
+            dir.join(FIXTURES_DIR).is_dir(),
+            "no {FIXTURES_DIR}/ directory in {}",
+            dir.display()
+        );
+        return Ok(dir.to_path_buf());
+    }
+    [
+        PathBuf::from("benchmarks"),
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("benchmarks"),
+    ]
+    .into_iter()
+    .find(|dir| dir.join(FIXTURES_DIR).is_dir())
+    .context("benchmark fixtures not found; run from a cartog checkout or pass --dir")
+}
+
+/// What the extractors are expected to find in one fixture.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Golden {
+    pub files: u32,
+    pub symbols: u32,
+    pub edges: u32,
+    pub symbol_kinds: BTreeMap<String, u32>,
+    pub edge_kinds: BTreeMap<String, u32>,
+    /// Grammar fingerprints of the fixture's languages when the counts were taken.
+    #[serde(default)]
+    pub grammars: BTreeMap<String, String>,
+    /// Symbols that must be extracted, whatever the counts.
+    #[serde(default)]
+    pub expected: Vec<ExpectedSymbol>,
+}
+
+/// A key symbol of a fixture.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub struct ExpectedSymbol {
+    /// Path relative to the fixture root.
+    pub file: String,
+    pub name: String,
+    pub kind: SymbolKind,
+}
+
+/// A count differing from the golden file.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Drift {
+    /// `files`, `symbols`, `edges`, or a kind such as `symbols.method` or `edges.calls`.
+    pub count: String,
+    pub expected: u32,
+    pub actual: u32,
+}
+
+/// Outcome for one fixture.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct FixtureReport {
+    pub fixture: String,
+    pub files: u32,
+    pub symbols: u32,
+    pub edges: u32,
+    pub drift: Vec<Drift>,
+    /// Key symbols the extractors no longer find.
+    pub missing: Vec<ExpectedSymbol>,
+    /// Languages whose grammar differs from the one the golden file was made with,
+    /// the likely cause of any drift.
+    pub grammars_changed: Vec<String>,
+    /// Files that failed to extract, and a missing golden file.
+    pub errors: Vec<String>,
+    pub ok: bool,
+}
+
+/// Result of `cartog selftest`.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SelftestReport {
+    pub version: String,
+    pub fixtures: Vec<FixtureReport>,
+    /// Golden files were rewritten (`--update`).
+    pub updated: bool,
+    pub ok: bool,
+}
+
+/// Extract every fixture under `dir` and compare with its golden file, or with
+/// `update`, rewrite the golden files from the extraction.
+pub fn run(dir: &Path, update: bool) -> Result<SelftestReport> {
+    let fixtures_dir = dir.join(FIXTURES_DIR);
+    let mut fixtures: Vec<PathBuf> = std::fs::read_dir(&fixtures_dir)
+        .with_context(|| format!("cannot read {}", fixtures_dir.display()))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_dir())
+        .collect();
+    fixtures.sort();
+
+    let golden_dir = dir.join(GOLDEN_DIR);
+    if update {
+        std::fs::create_dir_all(&golden_dir)?;
+    }
+    let mut reports = Vec::with_capacity(fixtures.len());
+    for root in fixtures {
+        let name = root
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let golden_path = golden_dir.join(format!("{name}.json"));
+        let golden: Option<Golden> = match std::fs::read_to_string(&golden_path) {
+            Ok(text) => Some(
+                serde_json::from_str(&text)
+                    .with_context(|| format!("invalid golden file {}", golden_path.display()))?,
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let extraction = extract_fixture(&root)?;
+        if update {
+            let mut updated = extraction.golden.clone();
+            updated.expected = golden.map(|g| g.expected).unwrap_or_default();
+            std::fs::write(&golden_path, serde_json::to_string_pretty(&updated)? + "\n")?;
+            reports.push(compare(&name, &extraction, &updated));
+        } else {
+            match golden {
+                Some(golden) => reports.push(compare(&name, &extraction, &golden)),
+                None => {
+                    let mut report = compare(&name, &extraction, &extraction.golden);
+                    report.errors.push(format!(
+                        "no golden file {} (create it with `cartog selftest --update`)",
+                        golden_path.display()
+                    ));
+                    report.ok = false;
+                    reports.push(report);
+                }
+            }
+        }
+    }
+
+    Ok(SelftestReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        ok: reports.iter().all(|r| r.ok),
+        fixtures: reports,
+        updated: update,
+    })
+}
+
+/// Counts and symbols extracted from one fixture.
+struct Extraction {
+    golden: Golden,
+    found: HashSet<ExpectedSymbol>,
+    errors: Vec<String>,
+}
+
+fn extract_fixture(root: &Path) -> Result<Extraction> {
+    let mut paths: Vec<(PathBuf, &'static str)> = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| Some((e.path().to_path_buf(), detect_language(e.path())?)))
+        .collect();
+    paths.sort();
+
+    let mut golden = Golden::default();
+    let mut found = HashSet::new();
+    let mut errors = Vec::new();
+    for (path, language) in paths {
+        let rel = path
+            .strip_prefix(root)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let Some(mut extractor) = get_extractor(language) else {
+            continue;
+        };
+        let source = std::fs::read_to_string(&path)?;
+        let result = match extractor.extract(&source, &rel) {
+            Ok(result) => result,
+            Err(e) => {
+                errors.push(format!("{rel}: {e}"));
+                continue;
+            }
+        };
+
+        golden.files += 1;
+        golden.symbols += result.symbols.len() as u32;
+        golden.edges += result.edges.len() as u32;
+        for sym in &result.symbols {
+            *golden
+                .symbol_kinds
+                .entry(sym.kind.as_str().to_string())
+                .or_default() += 1;
+            found.insert(ExpectedSymbol {
+                file: rel.clone(),
+                name: sym.name.clone(),
+                kind: sym.kind,
+            });
+        }
+        for edge in &result.edges {
+            *golden
+                .edge_kinds
+                .entry(edge.kind.as_str().to_string())
+                .or_default() += 1;
+        }
+        if let Some(version) = grammar_version(language) {
+            golden.grammars.insert(language.to_string(), version);
+        }
+    }
+    Ok(Extraction {
+        golden,
+        found,
+        errors,
+    })
+}
+
+fn compare(fixture: &str, extraction: &Extraction, golden: &Golden) -> FixtureReport {
+    let actual = &extraction.golden;
+    let mut drift = Vec::new();
+    let mut check = |count: String, expected: u32, actual: u32| {
+        if expected != actual {
+            drift.push(Drift {
+                count,
+                expected,
+                actual,
+            });
+        }
+    };
+    check("files".into(), golden.files, actual.files);
+    check("symbols".into(), golden.symbols, actual.symbols);
+    check("edges".into(), golden.edges, actual.edges);
+    for (prefix, expected, actual) in [
+        ("symbols", &golden.symbol_kinds, &actual.symbol_kinds),
+        ("edges", &golden.edge_kinds, &actual.edge_kinds),
+    ] {
+        let kinds: BTreeSet<&String> = expected.keys().chain(actual.keys()).collect();
+        for kind in kinds {
+            check(
+                format!("{prefix}.{kind}"),
+                expected.get(kind).copied().unwrap_or(0),
+                actual.get(kind).copied().unwrap_or(0),
+            );
+        }
+    }
+
+    let missing: Vec<ExpectedSymbol> = golden
+        .expected
+        .iter()
+        .filter(|sym| !extraction.found.contains(sym))
+        .cloned()
+        .collect();
+    let grammars_changed: Vec<String> = actual
+        .grammars
+        .iter()
+        .filter(|(lang, version)| golden.grammars.get(*lang).is_some_and(|g| g != *version))
+        .map(|(lang, _)| lang.clone())
+        .collect();
+    let errors = extraction.errors.clone();
+    FixtureReport {
+        fixture: fixture.to_string(),
+        files: actual.files,
+        symbols: actual.symbols,
+        edges: actual.edges,
+        ok: drift.is_empty() && missing.is_empty() && errors.is_empty(),
+        drift,
+        missing,
+        grammars_changed,
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selftest_detects_drift_and_updates() {
+        let dir = std::env::temp_dir().join("cartog_test_selftest");
+        let _ = std::fs::remove_dir_all(&dir);
+        let app = dir.join(FIXTURES_DIR).join("app");
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(app.join("a.py"), "def login():\n    check()\n").unwrap();
+
+        // No golden file yet.
+        let report = run(&dir, false).unwrap();
+        assert!(!report.ok);
+        assert!(report.fixtures[0].errors[0].contains("no golden file"));
+
+        let report = run(&dir, true).unwrap();
+        assert!(report.ok && report.updated);
+        assert!(run(&dir, false).unwrap().ok);
+
+        // Key symbols survive updates and must be found.
+        let golden_path = dir.join(GOLDEN_DIR).join("app.json");
+        let mut golden: Golden =
+            serde_json::from_str(&std::fs::read_to_string(&golden_path).unwrap()).unwrap();
+        assert_eq!((golden.files, golden.symbols), (1, 1));
+        assert_eq!(golden.edge_kinds.get("calls"), Some(&1));
+        golden.expected = vec![ExpectedSymbol {
+            file: "a.py".into(),
+            name: "login".into(),
+            kind: SymbolKind::Function,
+        }];
+        std::fs::write(&golden_path, serde_json::to_string(&golden).unwrap()).unwrap();
+        run(&dir, true).unwrap();
+        assert!(run(&dir, false).unwrap().ok);
+
+        // An extra function drifts the counts; a renamed one goes missing.
+        std::fs::write(
+            app.join("a.py"),
+            "def signin():\n    check()\n\ndef logout():\n    pass\n",
+        )
+        .unwrap();
+        let report = run(&dir, false).unwrap();
+        let fixture = &report.fixtures[0];
+        assert!(!report.ok);
+        assert_eq!(
+            fixture.drift[0],
+            Drift {
+                count: "symbols".into(),
+                expected: 1,
+                actual: 2
+            }
+        );
+        assert_eq!(fixture.missing[0].name, "login");
+        assert!(fixture.grammars_changed.is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
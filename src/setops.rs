@@ -0,0 +1,265 @@
+//! Set operations over the symbols of query results: `cartog set` and the
+//! `cartog_set` MCP tool.
+//!
+//! An operand is a set of symbol IDs, read from a prior `--json` output
+//! ([`symbol_ids`]) or produced by a query run on the spot ([`SetQuery`]), e.g.
+//! "symbols matching `login` that also reference `check`":
+//!
+//! ```text
+//! cartog set intersect search:login refs:check
+//! cartog --json search login > a.json && cartog set diff a.json callers:check
+//! ```
+//!
+//! The result keeps the order of the first operand (then of the later ones, for a
+//! union) and is resolved back to the indexed symbols.
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::db::MAX_SEARCH_LIMIT;
+use crate::shard::ShardedDatabase;
+use crate::types::Symbol;
+
+/// Hops of an `impact` operand, as `cartog impact`'s default.
+const IMPACT_DEPTH: u32 = 3;
+
+/// How the operands combine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SetOp {
+    /// Symbols in every operand.
+    Intersect,
+    /// Symbols in any operand.
+    Union,
+    /// Symbols of the first operand in none of the others.
+    Diff,
+}
+
+/// A query whose symbols make up an operand, written `{"refs": "check"}` in JSON
+/// and `refs:check` on the command line.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SetQuery {
+    /// Symbols whose name matches, as `search` (first 100).
+    Search(String),
+    /// Symbols referencing the name: the sources of `refs`.
+    Refs(String),
+    /// Symbols calling the name.
+    Callers(String),
+    /// Symbols the name's calls resolve to.
+    Callees(String),
+    /// Symbols transitively referencing the name, 3 hops deep, as `impact`.
+    Impact(String),
+    /// Symbols of a file, as `outline`.
+    Outline(String),
+    /// These symbol IDs.
+    Ids(Vec<String>),
+}
+
+impl SetQuery {
+    /// A `kind:argument` operand (`refs:check`, `ids:ID1,ID2`); `None` when `text`
+    /// has another form.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (kind, arg) = text.split_once(':')?;
+        if arg.is_empty() {
+            return None;
+        }
+        let arg = arg.to_string();
+        Some(match kind {
+            "search" => Self::Search(arg),
+            "refs" => Self::Refs(arg),
+            "callers" => Self::Callers(arg),
+            "callees" => Self::Callees(arg),
+            "impact" => Self::Impact(arg),
+            "outline" => Self::Outline(arg),
+            "ids" => Self::Ids(arg.split(',').map(str::to_string).collect()),
+            _ => return None,
+        })
+    }
+
+    /// Run the query, returning the IDs of its symbols in result order.
+    pub fn symbol_ids(&self, db: &ShardedDatabase) -> Result<Vec<String>> {
+        Ok(match self {
+            Self::Search(query) => ids(db.search(query, None, None, MAX_SEARCH_LIMIT)?),
+            Self::Refs(name) => db
+                .refs(name, None)?
+                .into_iter()
+                .filter_map(|(_, source)| source.map(|s| s.id))
+                .collect(),
+            Self::Callers(name) => db
+                .callers(name, None)?
+                .into_iter()
+                .map(|c| c.caller.id)
+                .collect(),
+            Self::Callees(name) => db
+                .call_sites(name, false, None)?
+                .into_iter()
+                .filter_map(|site| site.target.map(|t| t.id))
+                .collect(),
+            Self::Impact(name) => db
+                .impact(name, IMPACT_DEPTH)?
+                .into_iter()
+                .map(|(edge, _)| edge.source_id)
+                .collect(),
+            Self::Outline(file) => ids(db.outline(file)?),
+            Self::Ids(ids) => ids.clone(),
+        })
+    }
+}
+
+fn ids(symbols: Vec<Symbol>) -> Vec<String> {
+    symbols.into_iter().map(|s| s.id).collect()
+}
+
+/// Symbol IDs in a cartog `--json` output (or MCP tool result), as found walking it:
+/// every symbol object (one with `id`, `name`, `kind` and `file_path`), every
+/// `symbol_id` field, and the `source_id` of every edge.
+pub fn symbol_ids(output: &Value) -> Vec<String> {
+    let mut found = Vec::new();
+    collect_ids(output, &mut found);
+    found
+}
+
+fn collect_ids(value: &Value, found: &mut Vec<String>) {
+    match value {
+        Value::Array(items) => items.iter().for_each(|item| collect_ids(item, found)),
+        Value::Object(fields) => {
+            let is_symbol = ["name", "kind", "file_path"]
+                .iter()
+                .all(|key| fields.contains_key(*key));
+            for (key, field) in fields {
+                let is_id = match key.as_str() {
+                    "id" => is_symbol,
+                    "symbol_id" | "source_id" => true,
+                    _ => false,
+                };
+                match field {
+                    Value::String(id) if is_id => found.push(id.clone()),
+                    _ => collect_ids(field, found),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Combine `operands` with `op`, without duplicates. Intersect and diff keep the
+/// order of the first operand; union appends each operand's new IDs in turn.
+pub fn combine(op: SetOp, operands: &[Vec<String>]) -> Vec<String> {
+    let Some((first, rest)) = operands.split_first() else {
+        return Vec::new();
+    };
+    let rest: Vec<HashSet<&str>> = rest
+        .iter()
+        .map(|ids| ids.iter().map(String::as_str).collect())
+        .collect();
+    let mut seen = HashSet::new();
+    let mut keep = |id: &String| seen.insert(id.clone());
+    match op {
+        SetOp::Intersect => first
+            .iter()
+            .filter(|id| rest.iter().all(|ids| ids.contains(id.as_str())))
+            .filter(|&id| keep(id))
+            .cloned()
+            .collect(),
+        SetOp::Diff => first
+            .iter()
+            .filter(|id| !rest.iter().any(|ids| ids.contains(id.as_str())))
+            .filter(|&id| keep(id))
+            .cloned()
+            .collect(),
+        SetOp::Union => operands
+            .iter()
+            .flatten()
+            .filter(|&id| keep(id))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// The symbols of a set operation.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SetResult {
+    pub op: SetOp,
+    pub symbols: Vec<Symbol>,
+    /// IDs in the result that are no longer indexed: their file was re-indexed or
+    /// removed since the output they come from was written.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+}
+
+/// Combine `operands` with `op` and look the resulting IDs up in `db`.
+pub fn evaluate(db: &ShardedDatabase, op: SetOp, operands: &[Vec<String>]) -> Result<SetResult> {
+    let mut result = SetResult {
+        op,
+        symbols: Vec::new(),
+        missing: Vec::new(),
+    };
+    for id in combine(op, operands) {
+        match db.get_symbol(&id)? {
+            Some(symbol) => result.symbols.push(symbol),
+            None => result.missing.push(id),
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_combine_keeps_first_operand_order() {
+        let a = set(&["x", "y", "z", "y"]);
+        let b = set(&["z", "w", "x"]);
+        let c = set(&["x", "z"]);
+        assert_eq!(
+            combine(SetOp::Intersect, &[a.clone(), b.clone(), c.clone()]),
+            ["x", "z"]
+        );
+        assert_eq!(
+            combine(SetOp::Union, &[a.clone(), b.clone()]),
+            ["x", "y", "z", "w"]
+        );
+        assert_eq!(combine(SetOp::Diff, &[a.clone(), c]), ["y"]);
+        assert_eq!(combine(SetOp::Diff, &[a]), ["x", "y", "z"]);
+        assert!(combine(SetOp::Union, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_symbol_ids_from_outputs() {
+        let refs = serde_json::json!([
+            {"edge": {"source_id": "web.py:handler:1a", "target_name": "login", "file_path": "web.py"},
+             "source": {"id": "web.py:handler:1a", "name": "handler", "kind": "function", "file_path": "web.py"}},
+            {"edge": {"source_id": "web.py:main:2b", "target_name": "login", "file_path": "web.py"}}
+        ]);
+        assert_eq!(
+            symbol_ids(&refs),
+            ["web.py:handler:1a", "web.py:handler:1a", "web.py:main:2b"]
+        );
+        // Other objects with an `id` (anchors, jobs) are not symbols.
+        let rag = serde_json::json!({"results": [{"symbol_id": "a.py:f:3c", "id": 7}], "job": {"id": "j1"}});
+        assert_eq!(symbol_ids(&rag), ["a.py:f:3c"]);
+
+        assert_eq!(
+            SetQuery::parse("refs:check"),
+            Some(SetQuery::Refs("check".into()))
+        );
+        assert_eq!(
+            SetQuery::parse("ids:a.py:f:3c,b.py:g:4d"),
+            Some(SetQuery::Ids(set(&["a.py:f:3c", "b.py:g:4d"])))
+        );
+        assert_eq!(SetQuery::parse("results.json"), None);
+        assert_eq!(SetQuery::parse("refs:"), None);
+        let query: SetQuery = serde_json::from_str(r#"{"callers": "check"}"#).unwrap();
+        assert_eq!(query, SetQuery::Callers("check".into()));
+    }
+}
@@ -0,0 +1,1056 @@
+//! Directory-sharded indexes for very large repositories.
+//!
+//! A single SQLite file slows down past a few million symbols. In sharded mode the
+//! main database (the coordinator) keeps the files directly in the indexed root, the
+//! metadata, the audit log and scratch notes, and every top-level directory gets its own database
+//! under [`SHARD_DIR`]. [`ShardedDatabase`] fans queries out to all
+//! shards and merges the results, so CLI and MCP callers see a single index.
+//!
+//! Sharding is switched on by `cartog index --shard`, or automatically once an index
+//! without embeddings grows past [`SHARD_THRESHOLD`] symbols. Edges are resolved
+//! within their shard: references across shards are still found by name (`refs`,
+//! `impact`), but keep an empty `target_id`. RAG (embeddings, FTS) is not sharded
+//! and is refused.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+use crate::db::{
+    self, by_count_desc, BackupResult, Database, DocCoverage, FileStats, IndexStats,
+    MaintenanceReport, ModuleDocCoverage, ModuleLink, Orphans, ResolutionExplanation,
+};
+use crate::export::{Cell, ExportTable};
+use crate::indexer::{self, IndexOptions, IndexResult, IndexScope, PruneResult};
+use crate::types::{
+    Anchor, CallSite, Caller, Edge, EdgeKind, FileInfo, Injection, LineCounts, Symbol, SymbolKind,
+    SymbolMatch, Visibility,
+};
+
+/// Directory holding one database per top-level directory, next to the coordinator.
+pub const SHARD_DIR: &str = ".cartog-shards";
+
+/// Symbol count past which an unsharded index switches to sharding.
+pub const SHARD_THRESHOLD: u32 = 2_000_000;
+
+/// Coordinator metadata key, `"on"` once the index is sharded.
+const SHARDING_KEY: &str = "sharding";
+
+struct Shard {
+    /// Top-level directory covered by this shard.
+    dir: String,
+    db: Database,
+}
+
+/// The index database, possibly split into per-directory shards.
+///
+/// Dereferences to the coordinator [`Database`] for everything that is not
+/// sharded (metadata, audit log, notes, embeddings).
+pub struct ShardedDatabase {
+    coordinator: Database,
+    shard_dir: PathBuf,
+    shards: Vec<Shard>,
+}
+
+impl Deref for ShardedDatabase {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.coordinator
+    }
+}
+
+impl From<Database> for ShardedDatabase {
+    /// Wrap an unsharded database, e.g. an in-memory one.
+    fn from(coordinator: Database) -> Self {
+        Self {
+            coordinator,
+            shard_dir: PathBuf::from(SHARD_DIR),
+            shards: Vec::new(),
+        }
+    }
+}
+
+impl ShardedDatabase {
+    /// Open the coordinator at `path` and, if sharded, its shards.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let mut db = Self::from(Database::open(path)?);
+        db.shard_dir = path
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(SHARD_DIR);
+        if db.is_sharded()? {
+            db.open_existing_shards()?;
+        }
+        Ok(db)
+    }
+
+    pub fn is_sharded(&self) -> Result<bool> {
+        Ok(self.coordinator.get_metadata(SHARDING_KEY)?.as_deref() == Some("on"))
+    }
+
+    /// Switch to sharded mode; the next [`index`](Self::index) run fills the shards.
+    pub fn enable_sharding(&self) -> Result<()> {
+        self.coordinator.set_metadata(SHARDING_KEY, "on")
+    }
+
+    /// Embeddings and the FTS index are not sharded: refuse RAG on a sharded index
+    /// rather than silently covering only the root files.
+    pub fn ensure_rag_supported(&self) -> Result<()> {
+        anyhow::ensure!(
+            !self.is_sharded()?,
+            "semantic search is not supported on a sharded index yet"
+        );
+        Ok(())
+    }
+
+    /// Number of shard databases (0 when unsharded).
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    fn open_existing_shards(&mut self) -> Result<()> {
+        let Ok(entries) = std::fs::read_dir(&self.shard_dir) else {
+            return Ok(());
+        };
+        let mut dirs: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                name.strip_suffix(".db").map(str::to_string)
+            })
+            .collect();
+        dirs.sort();
+        for dir in dirs {
+            self.open_shard(&dir)?;
+        }
+        Ok(())
+    }
+
+    fn shard_path(&self, dir: &str) -> PathBuf {
+        self.shard_dir.join(format!("{dir}.db"))
+    }
+
+    fn open_shard(&mut self, dir: &str) -> Result<&Database> {
+        let idx = match self.shards.iter().position(|s| s.dir == dir) {
+            Some(idx) => idx,
+            None => {
+                std::fs::create_dir_all(&self.shard_dir)?;
+                let db = Database::open(self.shard_path(dir))
+                    .with_context(|| format!("Failed to open shard for {dir}/"))?;
+                self.shards.push(Shard {
+                    dir: dir.to_string(),
+                    db,
+                });
+                self.shards.sort_by(|a, b| a.dir.cmp(&b.dir));
+                self.shards.iter().position(|s| s.dir == dir).unwrap_or(0)
+            }
+        };
+        Ok(&self.shards[idx].db)
+    }
+
+    /// Open the shard for `dir` with its generation counter set to `generation`, the
+    /// coordinator's before this run, so the symbols it writes carry the generation
+    /// the whole index moves to.
+    /// The shard also follows the coordinator's string literal scanning and parse
+    /// cache settings.
+    fn open_shard_at(&mut self, dir: &str, generation: u64) -> Result<&Database> {
+        let scan_injections = self.coordinator.scan_injections()?;
+        let parse_cache = self.coordinator.parse_cache()?;
+        let shard = self.open_shard(dir)?;
+        shard.set_metadata("index_generation", &generation.to_string())?;
+        if shard.scan_injections()? != scan_injections {
+            shard.set_scan_injections(scan_injections)?;
+        }
+        if shard.parse_cache()? != parse_cache {
+            shard.set_parse_cache(parse_cache)?;
+        }
+        Ok(shard)
+    }
+
+    fn remove_shard(&mut self, dir: &str) -> Result<()> {
+        self.shards.retain(|s| s.dir != dir);
+        let path = self.shard_path(dir);
+        for suffix in ["", "-wal", "-shm"] {
+            let mut file = path.clone().into_os_string();
+            file.push(suffix);
+            match std::fs::remove_file(&file) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Coordinator first, then shards in directory order.
+    fn databases(&self) -> impl Iterator<Item = &Database> {
+        std::iter::once(&self.coordinator).chain(self.shards.iter().map(|s| &s.db))
+    }
+
+    /// The database holding `file_path` (relative to the indexed root).
+    fn database_for(&self, file_path: &str) -> &Database {
+        file_path
+            .split_once('/')
+            .and_then(|(dir, _)| self.shards.iter().find(|s| s.dir == dir))
+            .map_or(&self.coordinator, |s| &s.db)
+    }
+
+    // ── Indexing ──
+
+    /// Index `root` like [`indexer::index_directory`], one database per top-level
+    /// directory when sharded. Switches to sharding once the index grows past
+    /// [`SHARD_THRESHOLD`] symbols, unless it has embeddings that sharding would
+    /// make unsearchable.
+    pub fn index(&mut self, root: &Path, force: bool) -> Result<IndexResult> {
+        let options = IndexOptions {
+            force,
+            ..Default::default()
+        };
+        self.index_with(root, options)
+    }
+
+    /// [`Self::index`] with all the settings of the run.
+    pub fn index_with(&mut self, root: &Path, options: IndexOptions) -> Result<IndexResult> {
+        if !self.is_sharded()? {
+            let result = indexer::index_scoped(&self.coordinator, root, IndexScope::All, options)?;
+            if result.files_indexed == 0 || self.coordinator.stats()?.num_symbols <= SHARD_THRESHOLD
+            {
+                return Ok(result);
+            }
+            if self.coordinator.embedding_count()? > 0 {
+                warn!(
+                    threshold = SHARD_THRESHOLD,
+                    "index is past the sharding threshold but has embeddings, which a sharded \
+                     index cannot search; staying unsharded (`cartog index --shard` splits it anyway)"
+                );
+                return Ok(result);
+            }
+            info!(
+                threshold = SHARD_THRESHOLD,
+                "index is past the sharding threshold, splitting it by top-level directory"
+            );
+            self.enable_sharding()?;
+        }
+
+        let root = root.canonicalize().context("Failed to resolve root path")?;
+        let generation = self.coordinator.index_generation()?;
+        let mut result =
+            indexer::index_scoped(&self.coordinator, &root, IndexScope::RootFiles, options)?;
+        let coordinator_changed = result.files_indexed > 0 || result.files_removed > 0;
+
+        let dirs = top_level_dirs(&root)?;
+        let mut stale = Vec::new();
+        for shard in self.shards.iter().filter(|s| !dirs.contains(&s.dir)) {
+            let files = shard.db.all_files()?;
+            result.files_removed += files.len() as u32;
+            // The shard file goes away, so its removals are recorded by the coordinator.
+            for file in &files {
+                self.coordinator.record_removed_file(file, generation + 1)?;
+            }
+            stale.push(shard.dir.clone());
+        }
+        for dir in stale {
+            self.remove_shard(&dir)?;
+        }
+        for dir in &dirs {
+            let shard = self.open_shard_at(dir, generation)?;
+            add_result(
+                &mut result,
+                indexer::index_scoped(shard, &root, IndexScope::Dir(dir), options)?,
+            );
+        }
+
+        let changed = result.files_indexed > 0 || result.files_removed > 0;
+        if changed && !coordinator_changed {
+            // Readers such as the metrics endpoint watch the coordinator's generation.
+            self.coordinator.bump_index_generation()?;
+        }
+        Ok(result)
+    }
+
+    /// Re-index only `files`, like [`indexer::index_files`], routing each to its shard.
+    pub fn index_files(&mut self, root: &Path, files: &[PathBuf]) -> Result<IndexResult> {
+        if !self.is_sharded()? {
+            return indexer::index_files(&self.coordinator, root, files);
+        }
+
+        let mut groups: BTreeMap<Option<String>, Vec<PathBuf>> = BTreeMap::new();
+        for file in files {
+            let dir = match file.components().next() {
+                Some(std::path::Component::Normal(dir)) if file.components().count() > 1 => {
+                    let dir = dir.to_string_lossy().to_string();
+                    let known = self.shards.iter().any(|s| s.dir == dir);
+                    (known || root.join(&dir).is_dir()).then_some(dir)
+                }
+                _ => None,
+            };
+            groups.entry(dir).or_default().push(file.clone());
+        }
+
+        let generation = self.coordinator.index_generation()?;
+        let mut result = IndexResult::default();
+        let mut coordinator_changed = false;
+        for (dir, files) in groups {
+            let files = match &dir {
+                Some(dir) if !indexer::is_ignored_dirname(dir) => {
+                    indexer::index_files(self.open_shard_at(dir, generation)?, root, &files)?
+                }
+                _ => {
+                    let files = indexer::index_files(&self.coordinator, root, &files)?;
+                    coordinator_changed = files.files_indexed > 0 || files.files_removed > 0;
+                    files
+                }
+            };
+            add_result(&mut result, files);
+        }
+        let changed = result.files_indexed > 0 || result.files_removed > 0;
+        if changed && !coordinator_changed {
+            self.coordinator.bump_index_generation()?;
+        }
+        Ok(result)
+    }
+
+    // ── Queries (fan out + merge) ──
+
+    /// See [`Database::search`]. Results of all shards are merged in the same order.
+    pub fn search(
+        &self,
+        query: &str,
+        kind_filter: Option<SymbolKind>,
+        file_filter: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<Symbol>> {
+        self.search_in(query, kind_filter, file_filter, None, limit)
+    }
+
+    /// See [`Database::search_in`].
+    pub fn search_in(
+        &self,
+        query: &str,
+        kind_filter: Option<SymbolKind>,
+        file_filter: Option<&str>,
+        language: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<Symbol>> {
+        if let Some(file) = file_filter {
+            return self.database_for(file).search_in(
+                query,
+                kind_filter,
+                Some(file),
+                language,
+                limit,
+            );
+        }
+        if self.shards.is_empty() {
+            return self
+                .coordinator
+                .search_in(query, kind_filter, None, language, limit);
+        }
+
+        let pins: HashSet<(String, String)> = self.pins()?.into_iter().collect();
+        let mut rows = Vec::new();
+        for db in self.databases() {
+            rows.extend(db.search_in(query, kind_filter, None, language, limit)?);
+        }
+        let query = query.to_lowercase();
+        rows.sort_by_cached_key(|sym| {
+            let pinned = pins.contains(&(sym.name.clone(), sym.file_path.clone()));
+            search_order(&query, sym, pinned)
+        });
+        rows.truncate(limit as usize);
+        Ok(rows)
+    }
+
+    pub fn outline(&self, file_path: &str) -> Result<Vec<Symbol>> {
+        self.database_for(file_path).outline(file_path)
+    }
+
+    pub fn file_deps(&self, file_path: &str) -> Result<Vec<Edge>> {
+        self.database_for(file_path).file_deps(file_path)
+    }
+
+    pub fn callees(&self, name: &str) -> Result<Vec<Edge>> {
+        let mut rows = Vec::new();
+        for db in self.databases() {
+            rows.extend(db.callees(name)?);
+        }
+        rows.sort_by(|a, b| callee_order(a).cmp(&callee_order(b)));
+        Ok(rows)
+    }
+
+    pub fn call_sites(
+        &self,
+        name: &str,
+        line_text: bool,
+        language: Option<&str>,
+    ) -> Result<Vec<CallSite>> {
+        let mut sites = Vec::new();
+        for db in self.databases() {
+            sites.extend(db.call_sites(name, line_text, language)?);
+        }
+        sites.sort_by(|a, b| callee_order(&a.edge).cmp(&callee_order(&b.edge)));
+        Ok(sites)
+    }
+
+    pub fn callers(&self, name: &str, language: Option<&str>) -> Result<Vec<Caller>> {
+        let mut callers = Vec::new();
+        for db in self.databases() {
+            callers.extend(db.callers(name, language)?);
+        }
+        callers.sort_by(|a, b| callee_order(&a.edge).cmp(&callee_order(&b.edge)));
+        Ok(callers)
+    }
+
+    pub fn hierarchy(&self, class_name: &str) -> Result<Vec<(String, String)>> {
+        let mut rows = Vec::new();
+        for db in self.databases() {
+            rows.extend(db.hierarchy(class_name)?);
+        }
+        Ok(rows)
+    }
+
+    /// See [`Database::refs`]. Proximity ordering spans all shards.
+    pub fn refs(
+        &self,
+        name: &str,
+        kind_filter: Option<EdgeKind>,
+    ) -> Result<Vec<(Edge, Option<Symbol>)>> {
+        self.refs_in(name, kind_filter, None, None, None)
+    }
+
+    pub fn refs_in(
+        &self,
+        name: &str,
+        kind_filter: Option<EdgeKind>,
+        path_glob: Option<&str>,
+        target_kind: Option<SymbolKind>,
+        language: Option<&str>,
+    ) -> Result<Vec<(Edge, Option<Symbol>)>> {
+        if self.shards.is_empty() {
+            return self
+                .coordinator
+                .refs_in(name, kind_filter, path_glob, target_kind, language);
+        }
+        let mut rows = Vec::new();
+        let mut defs = HashMap::new();
+        for db in self.databases() {
+            rows.extend(db.refs_in(name, kind_filter, path_glob, target_kind, language)?);
+            defs.extend(db.definition_files(name)?);
+        }
+        db::sort_refs_by_proximity(&mut rows, &defs);
+        Ok(rows)
+    }
+
+    /// See [`Database::impact`]. Hops cross shards by name.
+    pub fn impact(&self, name: &str, max_depth: u32) -> Result<Vec<(Edge, u32)>> {
+        self.impact_of(name, max_depth, None, None)
+    }
+
+    /// See [`Database::impact_of`].
+    pub fn impact_of(
+        &self,
+        name: &str,
+        max_depth: u32,
+        target_kind: Option<SymbolKind>,
+        language: Option<&str>,
+    ) -> Result<Vec<(Edge, u32)>> {
+        db::transitive_impact(name, max_depth, |current| {
+            let kind = target_kind.filter(|_| current == name);
+            self.refs_in(current, None, None, kind, language)
+        })
+    }
+
+    /// See [`Database::raises`]. Hops cross shards by name.
+    pub fn raises(&self, exception: &str, max_depth: u32) -> Result<Vec<(Edge, u32)>> {
+        db::transitive_raisers(exception, max_depth, |current, kind| {
+            self.refs(current, kind)
+        })
+    }
+
+    pub fn anchors(
+        &self,
+        path: Option<&str>,
+        tag: Option<&str>,
+        text: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<Anchor>> {
+        let mut rows = Vec::new();
+        for db in self.databases() {
+            rows.extend(db.anchors(path, tag, text, limit)?);
+        }
+        rows.sort_by(|a, b| a.file_path.cmp(&b.file_path).then(a.line.cmp(&b.line)));
+        rows.truncate(limit as usize);
+        Ok(rows)
+    }
+
+    pub fn injections(
+        &self,
+        query: Option<&str>,
+        language: Option<&str>,
+        path: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<Injection>> {
+        let mut rows = Vec::new();
+        for db in self.databases() {
+            rows.extend(db.injections(query, language, path, limit)?);
+        }
+        rows.sort_by(|a, b| a.file_path.cmp(&b.file_path).then(a.line.cmp(&b.line)));
+        rows.truncate(limit as usize);
+        Ok(rows)
+    }
+
+    /// See [`Database::set_scan_injections`]. Applies to every shard.
+    pub fn set_scan_injections(&self, on: bool) -> Result<()> {
+        for db in self.databases() {
+            db.set_scan_injections(on)?;
+        }
+        Ok(())
+    }
+
+    /// See [`Database::set_parse_cache`]. Applies to every shard.
+    pub fn set_parse_cache(&self, on: bool) -> Result<()> {
+        for db in self.databases() {
+            db.set_parse_cache(on)?;
+        }
+        Ok(())
+    }
+
+    pub fn has_indexed_files(&self) -> Result<bool> {
+        for db in self.databases() {
+            if db.has_indexed_files()? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    pub fn all_files(&self) -> Result<Vec<String>> {
+        let mut files = Vec::new();
+        for db in self.databases() {
+            files.extend(db.all_files()?);
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// [`indexer::prune_missing`] on every database, merged.
+    pub fn prune_missing(
+        &self,
+        root: &Path,
+        older_than: Option<Duration>,
+        dry_run: bool,
+    ) -> Result<PruneResult> {
+        let mut merged = PruneResult {
+            dry_run,
+            ..Default::default()
+        };
+        for db in self.databases() {
+            let result = indexer::prune_missing(db, root, older_than, dry_run)?;
+            merged.files_checked += result.files_checked;
+            merged.files.extend(result.files);
+            merged.rows_removed += result.rows_removed;
+            merged.edges_resolved += result.edges_resolved;
+        }
+        merged.files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(merged)
+    }
+
+    pub fn files_changed_since(&self, since: u64) -> Result<Vec<FileInfo>> {
+        let mut files = Vec::new();
+        for db in self.databases() {
+            files.extend(db.files_changed_since(since)?);
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    pub fn files_removed_since(&self, since: u64) -> Result<Vec<String>> {
+        let mut files = Vec::new();
+        for db in self.databases() {
+            files.extend(db.files_removed_since(since)?);
+        }
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    pub fn quick_check(&self) -> Result<Vec<String>> {
+        let mut problems = Vec::new();
+        for db in self.databases() {
+            problems.extend(db.quick_check()?);
+        }
+        Ok(problems)
+    }
+
+    pub fn check_orphans(&self) -> Result<Orphans> {
+        let mut orphans = Orphans::default();
+        for db in self.databases() {
+            orphans += db.check_orphans()?;
+        }
+        Ok(orphans)
+    }
+
+    pub fn delete_orphans(&self) -> Result<Orphans> {
+        let mut deleted = Orphans::default();
+        for db in self.databases() {
+            deleted += db.delete_orphans()?;
+        }
+        Ok(deleted)
+    }
+
+    pub fn file_mtimes(&self) -> Result<Vec<(String, f64)>> {
+        let mut files = Vec::new();
+        for db in self.databases() {
+            files.extend(db.file_mtimes()?);
+        }
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(files)
+    }
+
+    pub fn file_stats(&self) -> Result<Vec<FileStats>> {
+        let mut files = Vec::new();
+        for db in self.databases() {
+            files.extend(db.file_stats()?);
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    pub fn entrypoints(&self, kind: Option<&str>) -> Result<Vec<Symbol>> {
+        let mut symbols = Vec::new();
+        for db in self.databases() {
+            symbols.extend(db.entrypoints(kind)?);
+        }
+        symbols.sort_by(|a, b| (&a.file_path, a.start_line).cmp(&(&b.file_path, b.start_line)));
+        Ok(symbols)
+    }
+
+    pub fn definitions(&self, name: &str, file_path: Option<&str>) -> Result<Vec<Symbol>> {
+        if let Some(file_path) = file_path {
+            return self
+                .database_for(file_path)
+                .definitions(name, Some(file_path));
+        }
+        let mut symbols = Vec::new();
+        for db in self.databases() {
+            symbols.extend(db.definitions(name, None)?);
+        }
+        symbols.sort_by(|a, b| (&a.file_path, a.start_line).cmp(&(&b.file_path, b.start_line)));
+        Ok(symbols)
+    }
+
+    pub fn all_symbols(&self) -> Result<Vec<Symbol>> {
+        let mut symbols = Vec::new();
+        for db in self.databases() {
+            symbols.extend(db.all_symbols()?);
+        }
+        Ok(symbols)
+    }
+
+    pub fn models_for_table(&self, table: &str) -> Result<Vec<Symbol>> {
+        let mut symbols = Vec::new();
+        for db in self.databases() {
+            symbols.extend(db.models_for_table(table)?);
+        }
+        symbols.sort_by(|a, b| (&a.file_path, a.start_line).cmp(&(&b.file_path, b.start_line)));
+        Ok(symbols)
+    }
+
+    pub fn symbols_mentioning(&self, needle: &str) -> Result<Vec<(Symbol, String)>> {
+        let mut found = Vec::new();
+        for db in self.databases() {
+            found.extend(db.symbols_mentioning(needle)?);
+        }
+        found.sort_by(|a, b| {
+            (&a.0.file_path, a.0.start_line).cmp(&(&b.0.file_path, b.0.start_line))
+        });
+        Ok(found)
+    }
+
+    pub fn explain_resolution(
+        &self,
+        target: &str,
+        file: Option<&str>,
+        limit: u32,
+    ) -> Result<Vec<ResolutionExplanation>> {
+        let mut explanations = Vec::new();
+        for db in self.databases() {
+            explanations.extend(db.explain_resolution(target, file, limit)?);
+        }
+        explanations.sort_by(|a, b| {
+            (&a.edge.file_path, a.edge.line).cmp(&(&b.edge.file_path, b.edge.line))
+        });
+        explanations.truncate(limit as usize);
+        Ok(explanations)
+    }
+
+    /// [`Database::maintain`] on every database, summed.
+    pub fn maintain(&self, analyze: bool, vacuum: bool) -> Result<MaintenanceReport> {
+        let mut total = MaintenanceReport {
+            analyzed: analyze,
+            vacuumed: vacuum,
+            ..Default::default()
+        };
+        for db in self.databases() {
+            let report = db.maintain(analyze, vacuum)?;
+            total.databases += 1;
+            total.size_before += report.size_before;
+            total.size_after += report.size_after;
+            total.duration_ms += report.duration_ms;
+        }
+        Ok(total)
+    }
+
+    pub fn all_edges(&self) -> Result<Vec<Edge>> {
+        let mut edges = Vec::new();
+        for db in self.databases() {
+            edges.extend(db.all_edges()?);
+        }
+        Ok(edges)
+    }
+
+    /// See [`Database::get_symbol`]. Symbol ids start with their file path, so the
+    /// owning shard is known.
+    pub fn get_symbol(&self, id: &str) -> Result<Option<Symbol>> {
+        self.database_for(id).get_symbol(id)
+    }
+
+    /// Fill in [`SymbolMatch::parent_docstring`] of hits nested in a documented
+    /// symbol. A parent lives in its child's file, so in the same shard.
+    pub fn attach_parent_docs(&self, matches: &mut [SymbolMatch]) -> Result<()> {
+        for m in matches {
+            if let Some(parent) = &m.symbol.parent_id {
+                m.parent_docstring = self.database_for(parent).docstring(parent)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_file(&self, path: &str) -> Result<Option<FileInfo>> {
+        self.database_for(path).get_file(path)
+    }
+
+    pub fn get_symbol_content(&self, id: &str) -> Result<Option<(String, String)>> {
+        self.database_for(id).get_symbol_content(id)
+    }
+
+    /// See [`Database::module_links`]. Edges only resolve within a shard, so links
+    /// never cross top-level directories.
+    pub fn module_links(&self) -> Result<Vec<ModuleLink>> {
+        let mut links = Vec::new();
+        for db in self.databases() {
+            links.extend(db.module_links()?);
+        }
+        Ok(links)
+    }
+
+    pub fn stats(&self) -> Result<IndexStats> {
+        if self.shards.is_empty() {
+            return self.coordinator.stats();
+        }
+        let mut total = IndexStats {
+            num_files: 0,
+            num_symbols: 0,
+            num_edges: 0,
+            num_resolved: 0,
+            languages: Vec::new(),
+            lines: LineCounts::default(),
+            language_lines: Vec::new(),
+            largest_files: Vec::new(),
+            symbol_kinds: Vec::new(),
+            pins: self.pins()?,
+            resolution: Vec::new(),
+        };
+        let mut languages: HashMap<String, u32> = HashMap::new();
+        let mut language_lines: HashMap<String, LineCounts> = HashMap::new();
+        let mut kinds: HashMap<String, u32> = HashMap::new();
+        for db in self.databases() {
+            let stats = db.stats()?;
+            total.num_files += stats.num_files;
+            total.num_symbols += stats.num_symbols;
+            total.num_edges += stats.num_edges;
+            total.num_resolved += stats.num_resolved;
+            for (lang, n) in stats.languages {
+                *languages.entry(lang).or_default() += n;
+            }
+            total.lines += stats.lines;
+            for (lang, counts) in stats.language_lines {
+                *language_lines.entry(lang).or_default() += counts;
+            }
+            total.largest_files.extend(stats.largest_files);
+            for (kind, n) in stats.symbol_kinds {
+                *kinds.entry(kind).or_default() += n;
+            }
+            for pass in stats.resolution {
+                match total.resolution.iter_mut().find(|p| p.stage == pass.stage) {
+                    Some(p) => {
+                        p.resolved += pass.resolved;
+                        p.duration_ms += pass.duration_ms;
+                    }
+                    None => total.resolution.push(pass),
+                }
+            }
+        }
+        total.languages = by_count_desc(languages);
+        let mut language_lines: Vec<(String, LineCounts)> = language_lines.into_iter().collect();
+        language_lines.sort_by(|a, b| b.1.code.cmp(&a.1.code).then_with(|| a.0.cmp(&b.0)));
+        total.language_lines = language_lines;
+        total.symbol_kinds = by_count_desc(kinds);
+        total.largest_files.sort_by(|a, b| {
+            (b.symbols + b.edges)
+                .cmp(&(a.symbols + a.edges))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        total.largest_files.truncate(db::LARGEST_FILES as usize);
+        Ok(total)
+    }
+
+    pub fn doc_coverage(
+        &self,
+        visibility: Option<Visibility>,
+        largest: u32,
+    ) -> Result<DocCoverage> {
+        if self.shards.is_empty() {
+            return self.coordinator.doc_coverage(visibility, largest);
+        }
+        let mut modules: Vec<ModuleDocCoverage> = Vec::new();
+        let mut undocumented: Vec<Symbol> = Vec::new();
+        for db in self.databases() {
+            let report = db.doc_coverage(visibility, largest)?;
+            modules.extend(report.modules);
+            undocumented.extend(report.largest_undocumented);
+        }
+        // Same orders as Database::doc_coverage.
+        modules.sort_by(|a, b| {
+            a.coverage
+                .total_cmp(&b.coverage)
+                .then(b.total.cmp(&a.total))
+                .then_with(|| a.file_path.cmp(&b.file_path))
+        });
+        undocumented.sort_by(|a, b| {
+            (b.end_line - b.start_line)
+                .cmp(&(a.end_line - a.start_line))
+                .then_with(|| a.file_path.cmp(&b.file_path))
+                .then(a.start_line.cmp(&b.start_line))
+        });
+        undocumented.truncate(largest as usize);
+
+        let total = modules.iter().map(|m| m.total).sum();
+        let documented = modules.iter().map(|m| m.documented).sum();
+        Ok(DocCoverage {
+            total,
+            documented,
+            coverage: db::coverage_ratio(documented, total),
+            modules,
+            largest_undocumented: undocumented,
+        })
+    }
+
+    /// See [`Database::pin`]. The pin lives in the shard of `file_path` and applies to
+    /// edge resolution within that shard.
+    pub fn pin(&self, name: &str, file_path: &str) -> Result<Symbol> {
+        let target = self.database_for(file_path);
+        for db in self.databases() {
+            if !std::ptr::eq(db, target) {
+                db.unpin(name)?;
+            }
+        }
+        target.pin(name, file_path)
+    }
+
+    pub fn unpin(&self, name: &str) -> Result<bool> {
+        let mut removed = false;
+        for db in self.databases() {
+            removed |= db.unpin(name)?;
+        }
+        Ok(removed)
+    }
+
+    pub fn pins(&self) -> Result<Vec<(String, String)>> {
+        let mut pins = Vec::new();
+        for db in self.databases() {
+            pins.extend(db.pins()?);
+        }
+        pins.sort();
+        Ok(pins)
+    }
+
+    /// See [`Database::export_rows`]; rows of all shards are concatenated.
+    pub fn export_rows(&self, table: ExportTable) -> Result<Vec<Vec<Cell>>> {
+        let mut rows = Vec::new();
+        for db in self.databases() {
+            rows.extend(db.export_rows(table)?);
+        }
+        Ok(rows)
+    }
+
+    /// See [`Database::backup_to`]. Shards are copied to `<dest>.shards/<dir>.db`;
+    /// rename that directory to `.cartog-shards` next to the copy to restore it.
+    pub fn backup_to(&self, dest: impl AsRef<Path>) -> Result<BackupResult> {
+        let dest = dest.as_ref();
+        let mut result = self.coordinator.backup_to(dest)?;
+        if self.shards.is_empty() {
+            return Ok(result);
+        }
+        let mut shard_dest = dest.as_os_str().to_owned();
+        shard_dest.push(".shards");
+        let shard_dest = PathBuf::from(shard_dest);
+        std::fs::create_dir_all(&shard_dest)?;
+        for shard in &self.shards {
+            let r = shard
+                .db
+                .backup_to(shard_dest.join(format!("{}.db", shard.dir)))?;
+            result.pages += r.pages;
+            result.size_bytes += r.size_bytes;
+        }
+        Ok(result)
+    }
+}
+
+/// Non-ignored directories directly under `root`, sorted.
+fn top_level_dirs(root: &Path) -> Result<Vec<String>> {
+    let mut dirs = Vec::new();
+    for entry in std::fs::read_dir(root)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.path().is_dir() && !indexer::is_ignored_dirname(&name) {
+            dirs.push(name);
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn add_result(total: &mut IndexResult, r: IndexResult) {
+    total.files_indexed += r.files_indexed;
+    total.files_skipped += r.files_skipped;
+    total.files_removed += r.files_removed;
+    total.symbols_added += r.symbols_added;
+    total.edges_added += r.edges_added;
+    total.edges_resolved += r.edges_resolved;
+    total.symbols_renamed += r.symbols_renamed;
+    total.files_from_cache += r.files_from_cache;
+    total.indexed.extend(r.indexed);
+    total.oversized.extend(r.oversized);
+}
+
+/// Merge order of [`Database::callees`] and [`Database::callers`] across shards.
+fn callee_order(edge: &Edge) -> (&str, u32, &str, &str) {
+    (
+        &edge.file_path,
+        edge.line,
+        &edge.source_id,
+        &edge.target_name,
+    )
+}
+
+/// Sort key mirroring the `ORDER BY` of [`Database::search`]; `query` is lower-cased.
+fn search_order(query: &str, sym: &Symbol, pinned: bool) -> (u8, bool, u8, String, u32, String) {
+    let name = sym.name.to_lowercase();
+    let tier = if name == query {
+        0
+    } else if name.starts_with(query) {
+        1
+    } else {
+        2
+    };
+    let (penalty, kind_order) = match sym.kind {
+        SymbolKind::Function => (0, 0),
+        SymbolKind::Method => (0, 1),
+        SymbolKind::Class => (0, 2),
+        SymbolKind::Variable => (3, 3),
+        SymbolKind::Import => (6, 3),
+    };
+    (
+        tier + penalty,
+        !pinned,
+        kind_order,
+        sym.file_path.clone(),
+        sym.start_line,
+        sym.id.clone(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, file: &str, content: &str) {
+        let path = root.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_sharded_index_fans_out_queries() {
+        let tmp = std::env::temp_dir().join("cartog_test_shard");
+        let _ = std::fs::remove_dir_all(&tmp);
+        let src = tmp.join("project");
+        write(
+            &src,
+            "main.py",
+            "from auth.tokens import validate\n\ndef main():\n    validate()\n",
+        );
+        write(&src, "auth/tokens.py", "def validate():\n    pass\n");
+        write(&src, "api/routes.py", "def handler():\n    validate()\n");
+
+        let mut db = ShardedDatabase::open(tmp.join(".cartog.db")).unwrap();
+        db.enable_sharding().unwrap();
+        let result = db.index(&src, false).unwrap();
+        assert_eq!(result.files_indexed, 3);
+        assert_eq!(db.shard_count(), 2);
+        assert!(tmp.join(SHARD_DIR).join("auth.db").is_file());
+
+        // Reopening finds the shards again.
+        let mut db = ShardedDatabase::open(tmp.join(".cartog.db")).unwrap();
+        assert_eq!(db.shard_count(), 2);
+        assert_eq!(db.stats().unwrap().num_files, 3);
+        assert_eq!(db.outline("auth/tokens.py").unwrap()[0].name, "validate");
+        assert_eq!(
+            db.search("validate", None, None, 10).unwrap()[0].kind,
+            SymbolKind::Function
+        );
+
+        // Callers in the root file and in another shard are both found.
+        let callers: Vec<String> = db
+            .refs("validate", Some(EdgeKind::Calls))
+            .unwrap()
+            .into_iter()
+            .map(|(e, _)| e.file_path)
+            .collect();
+        assert_eq!(callers.len(), 2);
+        assert!(callers.contains(&"api/routes.py".to_string()));
+        assert!(callers.contains(&"main.py".to_string()));
+
+        // A deleted top-level directory drops its shard.
+        std::fs::remove_dir_all(src.join("api")).unwrap();
+        let result = db.index(&src, false).unwrap();
+        assert_eq!(result.files_removed, 1);
+        assert_eq!(db.shard_count(), 1);
+        assert!(!tmp.join(SHARD_DIR).join("api.db").exists());
+
+        // Notified files are routed to their shard.
+        write(&src, "auth/tokens.py", "def validate2():\n    pass\n");
+        db.index_files(&src, &[PathBuf::from("auth/tokens.py")])
+            .unwrap();
+        assert_eq!(db.outline("auth/tokens.py").unwrap()[0].name, "validate2");
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_search_order_matches_sql_ranking() {
+        let func = Symbol::new("Validate", SymbolKind::Function, "a.py", 1, 2, 0, 10);
+        let var = Symbol::new("validate", SymbolKind::Variable, "b.py", 1, 1, 0, 10);
+        let prefix = Symbol::new("validate_all", SymbolKind::Method, "c.py", 1, 2, 0, 10);
+        assert!(search_order("validate", &func, false) < search_order("validate", &prefix, false));
+        // Definitions outrank an exact-match variable.
+        assert!(search_order("validate", &prefix, false) < search_order("validate", &var, false));
+        assert!(search_order("validate", &func, true) < search_order("validate", &func, false));
+    }
+}
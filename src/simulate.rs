@@ -0,0 +1,314 @@
+//! `cartog watch --simulate`: replay scripted file changes through the watcher
+//! and check the index after each step.
+//!
+//! The repository is copied to a temporary directory (minus ignored directories
+//! and the index), indexed into a fresh database, then each step's writes,
+//! renames and deletes are applied to the copy and handed to [`WatchCore`] as one
+//! batch of debounced events, as `notify` would deliver them. Hooks don't run and
+//! nothing is embedded.
+//!
+//! ```json
+//! {
+//!   "steps": [
+//!     {
+//!       "write": { "src/auth.py": "def login():\n    pass\n" },
+//!       "expect": { "reindex": true, "files": ["src/auth.py"], "symbols": ["login"] }
+//!     },
+//!     {
+//!       "rename": { "src/auth.py": "src/session.py" },
+//!       "expect": { "files": ["src/session.py"], "absent_files": ["src/auth.py"] }
+//!     },
+//!     {
+//!       "delete": ["src/session.py"],
+//!       "expect": { "absent_symbols": ["login"] }
+//!     }
+//!   ]
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::hooks::Hooks;
+use crate::indexer::is_ignored_dirname;
+use crate::shard::ShardedDatabase;
+use crate::watch::{WatchConfig, WatchCore};
+
+/// A scripted sequence of file changes.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Script {
+    pub steps: Vec<Step>,
+}
+
+/// Changes delivered to the watcher as one batch, then checked. Paths are
+/// relative to the watched root; renames apply first, then writes, then deletes.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// Old path → new path.
+    #[serde(default)]
+    pub rename: BTreeMap<String, String>,
+    /// Files created or overwritten, path → content.
+    #[serde(default)]
+    pub write: BTreeMap<String, String>,
+    #[serde(default)]
+    pub delete: Vec<String>,
+    #[serde(default)]
+    pub expect: Expect,
+}
+
+/// The index state expected after a step.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expect {
+    /// Whether the step's changes trigger a re-index.
+    pub reindex: Option<bool>,
+    /// Files that must be indexed.
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// Files that must not be indexed.
+    #[serde(default)]
+    pub absent_files: Vec<String>,
+    /// Symbol names that must have a definition.
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    /// Symbol names that must have none.
+    #[serde(default)]
+    pub absent_symbols: Vec<String>,
+}
+
+/// Outcome of a simulation.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct SimulationReport {
+    pub steps: Vec<StepReport>,
+    /// Failed expectations over all steps.
+    pub failures: usize,
+}
+
+/// Outcome of one step.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct StepReport {
+    /// 1-based position in the script.
+    pub step: usize,
+    pub reindexed: bool,
+    pub files_indexed: u32,
+    pub files_removed: u32,
+    /// Expectations that did not hold, one sentence each.
+    pub failures: Vec<String>,
+}
+
+impl Script {
+    /// Read a script from a JSON file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("invalid script {}", path.display()))
+    }
+}
+
+/// Replay `script` against a temporary copy of `root`, watched with `config`
+/// (its `root` and `rag` are ignored).
+pub fn simulate(root: &Path, script: &Script, config: WatchConfig) -> Result<SimulationReport> {
+    let root = root.canonicalize().context("cannot resolve watch root")?;
+    let copy = TempCopy::new(&root)?;
+    let db = ShardedDatabase::open(copy.path.join(crate::db::DB_FILE))?;
+    let config = WatchConfig {
+        root: copy.path.clone(),
+        rag: false,
+        ..config
+    };
+    let hooks = Hooks::new(Default::default(), &copy.path);
+    let mut core = WatchCore::new(config, &copy.path, db, hooks)?;
+    core.initial_index().context("initial index failed")?;
+
+    let mut steps = Vec::with_capacity(script.steps.len());
+    for (i, step) in script.steps.iter().enumerate() {
+        let paths = apply(&copy.path, step).with_context(|| format!("step {}", i + 1))?;
+        let result = core
+            .on_changes(&paths)
+            .transpose()
+            .with_context(|| format!("step {}: re-index failed", i + 1))?;
+        let mut failures = check(core.db(), &step.expect)?;
+        if let Some(expected) = step.expect.reindex {
+            if expected != result.is_some() {
+                failures.insert(
+                    0,
+                    format!(
+                        "expected the changes {} a re-index",
+                        if expected {
+                            "to trigger"
+                        } else {
+                            "not to trigger"
+                        }
+                    ),
+                );
+            }
+        }
+        steps.push(StepReport {
+            step: i + 1,
+            reindexed: result.is_some(),
+            files_indexed: result.as_ref().map_or(0, |r| r.files_indexed),
+            files_removed: result.as_ref().map_or(0, |r| r.files_removed),
+            failures,
+        });
+    }
+    Ok(SimulationReport {
+        failures: steps.iter().map(|s| s.failures.len()).sum(),
+        steps,
+    })
+}
+
+/// Apply a step's changes under `root`; returns the changed paths.
+fn apply(root: &Path, step: &Step) -> Result<Vec<PathBuf>> {
+    let mut changed = Vec::new();
+    for (from, to) in &step.rename {
+        let (from, to) = (root.join(from), root.join(to));
+        create_parent(&to)?;
+        std::fs::rename(&from, &to)
+            .with_context(|| format!("failed to rename {}", from.display()))?;
+        changed.extend([from, to]);
+    }
+    for (path, content) in &step.write {
+        let path = root.join(path);
+        create_parent(&path)?;
+        std::fs::write(&path, content)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        changed.push(path);
+    }
+    for path in &step.delete {
+        let path = root.join(path);
+        std::fs::remove_file(&path)
+            .with_context(|| format!("failed to delete {}", path.display()))?;
+        changed.push(path);
+    }
+    Ok(changed)
+}
+
+fn create_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    Ok(())
+}
+
+/// The expectations of `expect` that `db` does not meet.
+fn check(db: &ShardedDatabase, expect: &Expect) -> Result<Vec<String>> {
+    let mut failures = Vec::new();
+    for file in &expect.files {
+        if db.get_file(file)?.is_none() {
+            failures.push(format!("{file} is not indexed"));
+        }
+    }
+    for file in &expect.absent_files {
+        if db.get_file(file)?.is_some() {
+            failures.push(format!("{file} is still indexed"));
+        }
+    }
+    for name in &expect.symbols {
+        if db.definitions(name, None)?.is_empty() {
+            failures.push(format!("no definition of {name}"));
+        }
+    }
+    for name in &expect.absent_symbols {
+        if let Some(def) = db.definitions(name, None)?.first() {
+            failures.push(format!(
+                "{name} is still defined in {}:{}",
+                def.file_path, def.start_line
+            ));
+        }
+    }
+    Ok(failures)
+}
+
+/// A copy of a repository in a temporary directory, deleted when dropped.
+struct TempCopy {
+    path: PathBuf,
+}
+
+impl TempCopy {
+    /// Copy the files under `root`, minus ignored directories and the index.
+    fn new(root: &Path) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("cartog-simulate-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path)
+            .with_context(|| format!("failed to create {}", path.display()))?;
+        let copy = Self {
+            path: path.canonicalize()?,
+        };
+        let walk = WalkDir::new(root).into_iter().filter_entry(|e| {
+            e.depth() == 0
+                || !(e.file_type().is_dir() && is_ignored_dirname(&e.file_name().to_string_lossy()))
+        });
+        for entry in walk {
+            let entry = entry?;
+            let rel = entry.path().strip_prefix(root)?;
+            let target = copy.path.join(rel);
+            if entry.file_type().is_dir() {
+                std::fs::create_dir_all(&target)?;
+            } else if entry.file_type().is_file()
+                && !rel.to_string_lossy().starts_with(crate::db::DB_FILE)
+            {
+                std::fs::copy(entry.path(), &target)
+                    .with_context(|| format!("failed to copy {}", entry.path().display()))?;
+            }
+        }
+        Ok(copy)
+    }
+}
+
+impl Drop for TempCopy {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulated_changes() {
+        let tmp = std::env::temp_dir().join("cartog_test_simulate");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(tmp.join("node_modules/dep")).unwrap();
+        std::fs::write(tmp.join("app.py"), "def main():\n    pass\n").unwrap();
+        std::fs::write(tmp.join("node_modules/dep/index.js"), "function dep() {}\n").unwrap();
+        std::fs::write(tmp.join(".cartogignore"), "generated/\n").unwrap();
+
+        let script: Script = serde_json::from_str(
+            r##"{"steps": [
+                {"expect": {"reindex": false, "files": ["app.py"], "absent_files": ["node_modules/dep/index.js"]}},
+                {"write": {"src/auth.py": "def login():\n    pass\n"},
+                 "expect": {"reindex": true, "symbols": ["login", "main"]}},
+                {"write": {"README.md": "# docs\n", "generated/api.py": "def gen():\n    pass\n"},
+                 "expect": {"reindex": false, "absent_symbols": ["gen"]}},
+                {"rename": {"src/auth.py": "src/session.py"},
+                 "expect": {"files": ["src/session.py"], "absent_files": ["src/auth.py"]}},
+                {"delete": ["src/session.py"], "expect": {"absent_symbols": ["login"], "files": ["src/session.py"]}}
+            ]}"##,
+        )
+        .unwrap();
+        let report = simulate(&tmp, &script, WatchConfig::new(tmp.clone())).unwrap();
+
+        let reindexed: Vec<bool> = report.steps.iter().map(|s| s.reindexed).collect();
+        assert_eq!(reindexed, [false, true, false, true, true]);
+        assert_eq!(report.steps[3].files_removed, 1);
+        // Only the deliberately wrong expectation of the last step fails.
+        assert_eq!(report.failures, 1);
+        assert_eq!(report.steps[4].failures, ["src/session.py is not indexed"]);
+        // The repository itself is left alone.
+        assert!(!tmp.join("src").exists());
+        assert!(!tmp.join(crate::db::DB_FILE).exists());
+
+        assert!(serde_json::from_str::<Script>(r#"{"steps": [{"remove": ["a.py"]}]}"#).is_err());
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+}
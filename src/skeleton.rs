@@ -0,0 +1,126 @@
+//! Minimal API skeletons of a file: `outline --public-only` and `--signature-only`.
+//!
+//! For prompting, a file's outline is often more than needed: private helpers,
+//! imports, variables and byte ranges all cost tokens. These filters cut an outline
+//! down to what another file can call, and to one name + signature line per symbol.
+
+use std::collections::{HashMap, HashSet};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::types::{Symbol, SymbolKind, Visibility};
+
+/// One line of a signature-only outline.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct SignatureLine {
+    pub kind: SymbolKind,
+    pub name: String,
+    /// Parameters and return type as extracted (`(self, token: str) -> bool`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_async: bool,
+    /// Nesting level: 0 for top-level symbols, 1 for methods of a class, ...
+    pub depth: u32,
+}
+
+/// Public symbols whose enclosing symbols are public too, without imports.
+///
+/// A public method of a private class is not reachable from other files, so it is
+/// dropped along with its class.
+pub fn public_only(symbols: Vec<Symbol>) -> Vec<Symbol> {
+    let mut kept: HashSet<String> = HashSet::new();
+    let mut public = Vec::with_capacity(symbols.len());
+    // Outlines are ordered by position, so a parent comes before its children.
+    for sym in symbols {
+        let parent_kept = sym.parent_id.as_ref().map_or(true, |p| kept.contains(p));
+        if parent_kept && sym.visibility == Visibility::Public && sym.kind != SymbolKind::Import {
+            kept.insert(sym.id.clone());
+            public.push(sym);
+        }
+    }
+    public
+}
+
+/// Name and signature of each symbol except variables, with its nesting depth.
+pub fn signature_lines(symbols: &[Symbol]) -> Vec<SignatureLine> {
+    let parents: HashMap<&str, Option<&str>> = symbols
+        .iter()
+        .map(|s| (s.id.as_str(), s.parent_id.as_deref()))
+        .collect();
+    let depth = |sym: &Symbol| {
+        let mut depth = 0;
+        let mut parent = sym.parent_id.as_deref();
+        while let Some(id) = parent {
+            depth += 1;
+            parent = parents.get(id).copied().flatten();
+            if depth > 32 {
+                break;
+            }
+        }
+        depth
+    };
+
+    symbols
+        .iter()
+        .filter(|s| s.kind != SymbolKind::Variable)
+        .map(|s| SignatureLine {
+            kind: s.kind,
+            name: s.name.clone(),
+            signature: s.signature.clone(),
+            is_async: s.is_async,
+            depth: depth(s),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sym(name: &str, kind: SymbolKind, line: u32, parent: Option<&Symbol>) -> Symbol {
+        let mut s = Symbol::new(name, kind, "a.py", line, line + 1, 0, 10);
+        s.parent_id = parent.map(|p| p.id.clone());
+        s.visibility = if name.starts_with('_') {
+            Visibility::Private
+        } else {
+            Visibility::Public
+        };
+        s
+    }
+
+    #[test]
+    fn test_public_only_drops_private_and_their_members() {
+        let import = sym("os", SymbolKind::Import, 1, None);
+        let service = sym("Service", SymbolKind::Class, 2, None);
+        let login = sym("login", SymbolKind::Method, 3, Some(&service));
+        let check = sym("_check", SymbolKind::Method, 4, Some(&service));
+        let internal = sym("_Internal", SymbolKind::Class, 5, None);
+        let run = sym("run", SymbolKind::Method, 6, Some(&internal));
+
+        let kept: Vec<String> = public_only(vec![import, service, login, check, internal, run])
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        assert_eq!(kept, ["Service", "login"]);
+    }
+
+    #[test]
+    fn test_signature_lines_skip_variables_and_nest() {
+        let service = sym("Service", SymbolKind::Class, 2, None);
+        let mut login = sym("login", SymbolKind::Method, 3, Some(&service));
+        login.signature = Some("(self, user: str) -> Token".to_string());
+        login.is_async = true;
+        let retries = sym("RETRIES", SymbolKind::Variable, 10, None);
+
+        let lines = signature_lines(&[service, login, retries]);
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].name.as_str(), lines[0].depth), ("Service", 0));
+        assert_eq!(lines[1].depth, 1);
+        let json = serde_json::to_value(&lines[1]).unwrap();
+        assert_eq!(json["signature"], "(self, user: str) -> Token");
+        assert_eq!(json["is_async"], true);
+        assert!(json.get("start_byte").is_none());
+    }
+}
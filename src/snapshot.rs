@@ -0,0 +1,161 @@
+//! Indexes of historical revisions for `--as-of <rev>` queries.
+//!
+//! The tree of a commit is exported with `git archive` into the repository's git
+//! directory (`.git/cartog/as-of/<commit>/`), indexed once into its own database,
+//! and the exported sources are deleted. Later queries against the same commit
+//! reuse the cached database.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use tracing::info;
+
+use crate::db::Database;
+use crate::indexer::{self, git_cmd};
+
+/// File written once a snapshot index is complete; its absence means a partial build.
+const COMPLETE_MARKER: &str = "complete";
+
+/// Return the path of an index of `root` as of `rev`, building it on first use.
+///
+/// `root` may be a subdirectory of the repository: only that part of the tree is
+/// indexed, so file paths match an index built in `root` today.
+pub fn snapshot_db(root: &Path, rev: &str) -> Result<PathBuf> {
+    let commit = resolve_commit(root, rev)?;
+    let (toplevel, prefix) = indexer::repo_location(root)?;
+    let dir = snapshot_dir(root, &commit, &prefix)?;
+    let db_path = dir.join("index.db");
+    if dir.join(COMPLETE_MARKER).is_file() && db_path.is_file() {
+        return Ok(db_path);
+    }
+
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)
+            .with_context(|| format!("Failed to clear partial snapshot {}", dir.display()))?;
+    }
+    let tree = dir.join("tree");
+    std::fs::create_dir_all(&tree)?;
+    info!(rev, commit = %commit, "indexing historical snapshot");
+    export_tree(&toplevel, &commit, &prefix, &tree)?;
+
+    let db = Database::open(&db_path).context("Failed to create snapshot database")?;
+    indexer::index_directory(&db, &tree, true)?;
+    drop(db);
+
+    std::fs::remove_dir_all(&tree)?;
+    std::fs::write(dir.join(COMPLETE_MARKER), &commit)?;
+    Ok(db_path)
+}
+
+/// Resolve `rev` to a full commit hash.
+fn resolve_commit(root: &Path, rev: &str) -> Result<String> {
+    let spec = format!("{rev}^{{commit}}");
+    let output =
+        git_cmd(root, &["rev-parse", "--verify", "--quiet", &spec]).context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!("unknown revision '{rev}'");
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// `<git-common-dir>/cartog/as-of/<commit>[-<prefix>]`, shared by all worktrees.
+fn snapshot_dir(root: &Path, commit: &str, prefix: &str) -> Result<PathBuf> {
+    let output = git_cmd(root, &["rev-parse", "--git-common-dir"]).context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!("not a git repository");
+    }
+    let git_dir = root
+        .join(String::from_utf8_lossy(&output.stdout).trim())
+        .canonicalize()?;
+
+    let mut key = commit.to_string();
+    let prefix = prefix.trim_end_matches('/');
+    if !prefix.is_empty() {
+        key.push('-');
+        key.push_str(&prefix.replace('/', "_"));
+    }
+    Ok(git_dir.join("cartog").join("as-of").join(key))
+}
+
+/// Extract `<commit>:<prefix>` into `dest` (`git archive | tar -x`), run from the
+/// repository's top level since `git archive` otherwise scopes to the current directory.
+fn export_tree(toplevel: &Path, commit: &str, prefix: &str, dest: &Path) -> Result<()> {
+    let treeish = format!("{commit}:{}", prefix.trim_end_matches('/'));
+    let mut archive = Command::new("git")
+        .args(["archive", "--format=tar", &treeish])
+        .current_dir(toplevel)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to run git archive")?;
+    let archive_out = archive.stdout.take().context("git archive has no stdout")?;
+
+    let untar = Command::new("tar")
+        .arg("-x")
+        .arg("-C")
+        .arg(dest)
+        .stdin(archive_out)
+        .status()
+        .context("Failed to run tar")?;
+    let archived = archive.wait()?;
+    if !archived.success() || !untar.success() {
+        anyhow::bail!("failed to export {treeish}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(root: &Path, args: &[&str]) {
+        let output = git_cmd(root, args).expect("git is available");
+        assert!(output.status.success(), "git {args:?} failed");
+    }
+
+    fn commit_file(root: &Path, file: &str, content: &str) {
+        std::fs::write(root.join(file), content).unwrap();
+        git(root, &["add", "-A"]);
+        git(
+            root,
+            &[
+                "-c",
+                "user.name=cartog",
+                "-c",
+                "user.email=cartog@example.com",
+                "commit",
+                "-q",
+                "-m",
+                file,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_snapshot_db_indexes_past_revision() {
+        let tmp = std::env::temp_dir().join("cartog_test_snapshot");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(tmp.join("pkg")).unwrap();
+        git(&tmp, &["init", "-q"]);
+        commit_file(&tmp, "pkg/app.py", "def old_name():\n    pass\n");
+        commit_file(&tmp, "pkg/app.py", "def new_name():\n    pass\n");
+
+        // Run from a subdirectory: paths stay relative to it, as in a live index.
+        let root = tmp.join("pkg");
+        let db_path = snapshot_db(&root, "HEAD~1").unwrap();
+        let db = Database::open(&db_path).unwrap();
+        let outline = db.outline("app.py").unwrap();
+        assert!(outline.iter().any(|s| s.name == "old_name"));
+        assert!(!outline.iter().any(|s| s.name == "new_name"));
+        drop(db);
+
+        // Second lookup hits the cache; the exported sources are gone.
+        assert_eq!(snapshot_db(&root, "HEAD~1").unwrap(), db_path);
+        assert!(!db_path.parent().unwrap().join("tree").exists());
+        assert!(db_path.starts_with(tmp.join(".git/cartog/as-of")));
+
+        assert!(snapshot_db(&root, "no-such-rev").is_err());
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+}
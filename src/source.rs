@@ -0,0 +1,318 @@
+//! Source snippets of indexed symbols: `cartog show`, the MCP `cartog_show` tool
+//! and the `cartog tui` preview.
+//!
+//! The index knows where each symbol starts and ends, so a caller can get one
+//! definition instead of reading its whole file. The snippet is cut from the file on
+//! disk, with optional context lines around it, as long as the file still hashes to
+//! what was indexed. Once it has changed the line numbers no longer hold, so the
+//! snippet falls back to the content stored at index time, without context.
+//!
+//! Before patching a symbol by its byte offsets, an agent checks them with
+//! [`validate_range`] (the MCP `cartog_validate_range` tool): the `hash` of the
+//! code it read must still be what the file holds at those offsets, or at the
+//! symbol's new ones when only the code around it moved.
+
+use std::path::Path;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::indexer::file_hash;
+use crate::languages::get_extractor;
+use crate::shard::ShardedDatabase;
+use crate::types::Symbol;
+
+/// Most context lines shown on each side of a snippet.
+pub const MAX_CONTEXT_LINES: u32 = 50;
+
+/// A symbol with its code.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct SymbolSource {
+    #[serde(flatten)]
+    pub symbol: Symbol,
+    /// Line numbers of the first and last line of `code`: the symbol's range
+    /// widened by the context lines.
+    pub first_line: u32,
+    pub last_line: u32,
+    /// The snippet; absent when the file is gone and the index holds no content.
+    pub code: Option<String>,
+    /// SHA-256 of the symbol's code (its byte range, without context lines), to
+    /// pass to [`validate_range`] before editing it. Absent when `stale`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// The file changed since it was indexed: `code` is the indexed content,
+    /// without context lines. Re-index for current code.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stale: bool,
+}
+
+/// Snippets of every definition of `name`, optionally only in `file_path`, with
+/// `context` lines (at most [`MAX_CONTEXT_LINES`]) before and after each. Paths
+/// are relative to `root`.
+pub fn symbol_sources(
+    db: &ShardedDatabase,
+    root: &Path,
+    name: &str,
+    file_path: Option<&str>,
+    context: u32,
+) -> Result<Vec<SymbolSource>> {
+    let context = context.min(MAX_CONTEXT_LINES);
+    db.definitions(name, file_path)?
+        .into_iter()
+        .map(|symbol| snippet(db, root, symbol, context))
+        .collect()
+}
+
+/// The code of one indexed `symbol`, with `context` lines before and after it
+/// while its file is unchanged (see [`symbol_sources`]).
+pub fn snippet(
+    db: &ShardedDatabase,
+    root: &Path,
+    symbol: Symbol,
+    context: u32,
+) -> Result<SymbolSource> {
+    let indexed = db.get_file(&symbol.file_path)?.map(|f| f.hash);
+    let current = std::fs::read_to_string(root.join(&symbol.file_path))
+        .ok()
+        .filter(|text| indexed.as_deref() == Some(file_hash(text).as_str()));
+
+    if let Some(text) = current {
+        let first_line = symbol.start_line.saturating_sub(context).max(1);
+        let last_line = symbol.end_line.saturating_add(context);
+        let lines: Vec<&str> = text
+            .lines()
+            .skip(first_line as usize - 1)
+            .take((last_line - first_line + 1) as usize)
+            .collect();
+        return Ok(SymbolSource {
+            first_line,
+            last_line: first_line + lines.len().saturating_sub(1) as u32,
+            code: Some(lines.join("\n")),
+            hash: range_of(&text, &symbol).map(|r| r.hash),
+            stale: false,
+            symbol,
+        });
+    }
+
+    let code = db
+        .get_symbol_content(&symbol.id)?
+        .map(|(content, _)| content);
+    Ok(SymbolSource {
+        first_line: symbol.start_line,
+        last_line: symbol.end_line,
+        code,
+        hash: None,
+        stale: true,
+        symbol,
+    })
+}
+
+/// Whether the code at a symbol's byte offsets is still what the caller read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RangeStatus {
+    /// The offsets hold the expected code: safe to edit.
+    Valid,
+    /// The expected code is intact but elsewhere in the file: edit at `range`.
+    Moved,
+    /// The symbol's code changed since it was read: re-read `range` first.
+    Changed,
+    /// The file or the symbol is gone.
+    Missing,
+}
+
+/// A symbol's code as it stands in the file on disk.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct SourceRange {
+    pub start_byte: u32,
+    pub end_byte: u32,
+    pub start_line: u32,
+    pub end_line: u32,
+    /// SHA-256 of the code in the range.
+    pub hash: String,
+}
+
+/// Result of [`validate_range`].
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct RangeCheck {
+    pub symbol_id: String,
+    pub file_path: String,
+    pub status: RangeStatus,
+    /// Where the symbol's code is now; absent when `missing`.
+    pub range: Option<SourceRange>,
+}
+
+/// Check that the indexed offsets of `symbol_id` still hold the code hashing to
+/// `expected_hash` (the `hash` of [`SymbolSource`]) in the file under `root`.
+///
+/// The file is parsed again and the symbol looked up by name and kind, nearest its
+/// indexed line first. One whose code still hashes to `expected_hash` is `valid`
+/// when it spans the indexed offsets exactly (a body that grew past the indexed end
+/// is not), `moved` otherwise; failing that, the nearest is `changed`.
+pub fn validate_range(
+    db: &ShardedDatabase,
+    root: &Path,
+    symbol_id: &str,
+    expected_hash: &str,
+) -> Result<RangeCheck> {
+    let Some(symbol) = db.get_symbol(symbol_id)? else {
+        anyhow::bail!("unknown symbol id `{symbol_id}`: search for the symbol again");
+    };
+    let (status, range) = match std::fs::read_to_string(root.join(&symbol.file_path)) {
+        Ok(text) => locate(db, &symbol, &text, expected_hash)?,
+        Err(_) => (RangeStatus::Missing, None),
+    };
+    Ok(RangeCheck {
+        symbol_id: symbol.id,
+        file_path: symbol.file_path,
+        status,
+        range,
+    })
+}
+
+fn locate(
+    db: &ShardedDatabase,
+    symbol: &Symbol,
+    text: &str,
+    expected_hash: &str,
+) -> Result<(RangeStatus, Option<SourceRange>)> {
+    let language = db.get_file(&symbol.file_path)?.map(|f| f.language);
+    let Some(mut extractor) = language.as_deref().and_then(get_extractor) else {
+        return Ok((RangeStatus::Missing, None));
+    };
+    let mut candidates: Vec<SourceRange> = extractor
+        .extract(text, &symbol.file_path)?
+        .symbols
+        .iter()
+        .filter(|s| s.name == symbol.name && s.kind == symbol.kind)
+        .filter_map(|s| range_of(text, s))
+        .collect();
+    candidates.sort_by_key(|r| r.start_line.abs_diff(symbol.start_line));
+
+    if let Some(i) = candidates.iter().position(|r| r.hash == expected_hash) {
+        let range = candidates.swap_remove(i);
+        let status = if (range.start_byte, range.end_byte) == (symbol.start_byte, symbol.end_byte) {
+            RangeStatus::Valid
+        } else {
+            RangeStatus::Moved
+        };
+        return Ok((status, Some(range)));
+    }
+    Ok(match candidates.into_iter().next() {
+        Some(range) => (RangeStatus::Changed, Some(range)),
+        None => (RangeStatus::Missing, None),
+    })
+}
+
+/// The range `symbol` spans in `text`, `None` when its offsets fall outside it.
+fn range_of(text: &str, symbol: &Symbol) -> Option<SourceRange> {
+    let code = text.get(symbol.start_byte as usize..symbol.end_byte as usize)?;
+    Some(SourceRange {
+        start_byte: symbol.start_byte,
+        end_byte: symbol.end_byte,
+        start_line: symbol.start_line,
+        end_line: symbol.end_line,
+        hash: file_hash(code),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    #[test]
+    fn test_snippet_with_context_and_stale_fallback() {
+        let tmp = std::env::temp_dir().join("cartog_test_source");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        let code = "import os\n\n\ndef greet(name):\n    \"\"\"Say hello.\"\"\"\n    return f\"hi {name}\"\n\n\nX = 1\n";
+        std::fs::write(tmp.join("a.py"), code).unwrap();
+
+        let db = ShardedDatabase::from(Database::open_memory().unwrap());
+        crate::indexer::index_directory(&db, &tmp, false).unwrap();
+
+        let sources = symbol_sources(&db, &tmp, "greet", None, 1).unwrap();
+        assert_eq!(sources.len(), 1);
+        let greet = &sources[0];
+        assert_eq!((greet.first_line, greet.last_line), (3, 7));
+        assert_eq!(
+            greet.code.as_deref(),
+            Some("\ndef greet(name):\n    \"\"\"Say hello.\"\"\"\n    return f\"hi {name}\"\n")
+        );
+        assert!(!greet.stale);
+        assert!(symbol_sources(&db, &tmp, "greet", Some("b.py"), 0)
+            .unwrap()
+            .is_empty());
+
+        // Edited after indexing: the indexed content, without context.
+        std::fs::write(tmp.join("a.py"), format!("# moved\n{code}")).unwrap();
+        let greet = symbol_sources(&db, &tmp, "greet", Some("a.py"), 3)
+            .unwrap()
+            .remove(0);
+        assert!(greet.stale);
+        assert!(greet.hash.is_none());
+        assert_eq!((greet.first_line, greet.last_line), (4, 6));
+        assert!(greet.code.unwrap().starts_with("def greet(name):"));
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+
+    #[test]
+    fn test_validate_range() {
+        let tmp = std::env::temp_dir().join("cartog_test_validate_range");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        let code = "def greet(name):\n    return name\n\n\ndef bye():\n    pass\n";
+        std::fs::write(tmp.join("a.py"), code).unwrap();
+
+        let db = ShardedDatabase::from(Database::open_memory().unwrap());
+        crate::indexer::index_directory(&db, &tmp, false).unwrap();
+        let greet = symbol_sources(&db, &tmp, "greet", None, 0)
+            .unwrap()
+            .remove(0);
+        let hash = greet.hash.unwrap();
+        let id = greet.symbol.id;
+
+        let check = validate_range(&db, &tmp, &id, &hash).unwrap();
+        assert_eq!(check.status, RangeStatus::Valid);
+        let range = check.range.unwrap();
+        assert_eq!((range.start_byte, range.end_byte), (0, 32));
+
+        // Lines added above: same code, new offsets.
+        std::fs::write(tmp.join("a.py"), format!("import os\n\n\n{code}")).unwrap();
+        let check = validate_range(&db, &tmp, &id, &hash).unwrap();
+        assert_eq!(check.status, RangeStatus::Moved);
+        let range = check.range.unwrap();
+        assert_eq!((range.start_byte, range.start_line), (12, 4));
+        assert_eq!(range.hash, hash);
+
+        // The body grew past the indexed end: the old offsets hold a stale prefix.
+        std::fs::write(
+            tmp.join("a.py"),
+            code.replace("return name\n", "return name\n    print(name)\n"),
+        )
+        .unwrap();
+        let check = validate_range(&db, &tmp, &id, &hash).unwrap();
+        assert_eq!(check.status, RangeStatus::Changed);
+        assert!(check.range.unwrap().end_byte > 32);
+
+        // The body itself was edited.
+        std::fs::write(
+            tmp.join("a.py"),
+            code.replace("return name", "return name.title()"),
+        )
+        .unwrap();
+        let check = validate_range(&db, &tmp, &id, &hash).unwrap();
+        assert_eq!(check.status, RangeStatus::Changed);
+        assert_ne!(check.range.unwrap().hash, hash);
+
+        std::fs::write(tmp.join("a.py"), "def bye():\n    pass\n").unwrap();
+        let check = validate_range(&db, &tmp, &id, &hash).unwrap();
+        assert_eq!((check.status, check.range), (RangeStatus::Missing, None));
+        assert!(validate_range(&db, &tmp, "a.py:nope:1", &hash).is_err());
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+}
@@ -0,0 +1,188 @@
+//! Outline digests for client-side mirrors (`cartog sync`).
+//!
+//! Editor plugins that keep a local copy of file outlines ask for the files
+//! (re)indexed since the index generation they last saw, compare each file's
+//! digest with their copy and refetch only the outlines that differ. Files removed
+//! from the index since then are listed so the mirror can drop them.
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::shard::ShardedDatabase;
+use crate::types::Symbol;
+
+/// One file's outline digest.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct FileDigest {
+    pub path: String,
+    /// Index generation of the run that last (re)indexed the file.
+    pub generation: u64,
+    /// Digest of the file's outline. A re-index that leaves every symbol as it was
+    /// (e.g. an edit below the last symbol) keeps the digest.
+    pub digest: String,
+    pub num_symbols: u32,
+    /// The outline itself, same as `cartog outline` (with `--outlines`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbols: Option<Vec<Symbol>>,
+}
+
+/// Files changed and removed since a generation.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SyncDelta {
+    /// Current index generation: pass it as `since` on the next call.
+    pub generation: u64,
+    /// Generation the delta starts from (0 for a full listing).
+    pub since: u64,
+    /// Whether every indexed file is listed: no `since` was given, or it is ahead
+    /// of the index (rebuilt since). Mirrors should drop files not listed.
+    pub full: bool,
+    pub files: Vec<FileDigest>,
+    /// Files removed from the index since `since` and not indexed again.
+    pub removed: Vec<String>,
+}
+
+/// Digests of the files changed since generation `since` (all files when `None`),
+/// restricted to `paths` when it is not empty.
+pub fn sync(
+    db: &ShardedDatabase,
+    since: Option<u64>,
+    paths: &[String],
+    outlines: bool,
+) -> Result<SyncDelta> {
+    let generation = db.index_generation()?;
+    let since = since.filter(|&since| since <= generation).unwrap_or(0);
+    let wanted: HashSet<&str> = paths.iter().map(String::as_str).collect();
+    let keep = |path: &str| wanted.is_empty() || wanted.contains(path);
+
+    let mut files = Vec::new();
+    for file in db.files_changed_since(since)? {
+        if !keep(&file.path) {
+            continue;
+        }
+        let symbols = db.outline(&file.path)?;
+        files.push(FileDigest {
+            digest: outline_digest(&symbols),
+            num_symbols: symbols.len() as u32,
+            path: file.path,
+            generation: file.generation,
+            symbols: outlines.then_some(symbols),
+        });
+    }
+
+    let listed: HashSet<&str> = files.iter().map(|f| f.path.as_str()).collect();
+    let removed = db
+        .files_removed_since(since)?
+        .into_iter()
+        .filter(|path| keep(path) && !listed.contains(path.as_str()))
+        .collect();
+
+    Ok(SyncDelta {
+        generation,
+        since,
+        full: since == 0,
+        files,
+        removed,
+    })
+}
+
+/// Hash of an outline, leaving out the generation each symbol was written in.
+fn outline_digest(symbols: &[Symbol]) -> String {
+    let mut hasher = Sha256::new();
+    for sym in symbols {
+        let sym = Symbol {
+            generation: 0,
+            ..sym.clone()
+        };
+        hasher.update(serde_json::to_vec(&sym).unwrap_or_default());
+        hasher.update(b"\n");
+    }
+    let hex = format!("{:x}", hasher.finalize());
+    hex[..16].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn write(dir: &std::path::Path, file: &str, content: &str) {
+        std::fs::write(dir.join(file), content).unwrap();
+    }
+
+    #[test]
+    fn test_sync_reports_changed_and_removed_files() {
+        let tmp = std::env::temp_dir().join(format!("cartog_sync_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        write(&tmp, "a.py", "def a():\n    pass\n");
+        write(&tmp, "b.py", "def b():\n    pass\n");
+        write(&tmp, "c.py", "def c():\n    pass\n");
+
+        let mut db = ShardedDatabase::from(Database::open_memory().unwrap());
+        db.index(&tmp, false).unwrap();
+        let first = sync(&db, None, &[], false).unwrap();
+        assert!(first.full);
+        assert_eq!(first.files.len(), 3);
+        assert!(first.files[0].symbols.is_none());
+
+        write(&tmp, "a.py", "# note\n\ndef a():\n    pass\n");
+        write(&tmp, "b.py", "def b():\n    pass\n\ndef b2():\n    pass\n");
+        std::fs::remove_file(tmp.join("c.py")).unwrap();
+        db.index(&tmp, false).unwrap();
+
+        let delta = sync(&db, Some(first.generation), &[], true).unwrap();
+        assert!(!delta.full);
+        let paths: Vec<_> = delta.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["a.py", "b.py"]);
+        assert_eq!(delta.removed, ["c.py"]);
+        assert_ne!(
+            delta.files[0].digest, first.files[0].digest,
+            "a() moved down"
+        );
+        assert_ne!(delta.files[1].digest, first.files[1].digest);
+        assert_eq!(delta.files[1].symbols.as_ref().unwrap().len(), 2);
+
+        let only_b = sync(&db, Some(first.generation), &["b.py".to_string()], false).unwrap();
+        assert_eq!(only_b.files.len(), 1);
+        assert!(only_b.removed.is_empty());
+
+        // Nothing changed since the latest generation; a generation ahead of the
+        // index (rebuilt since) gets a full listing.
+        assert!(sync(&db, Some(delta.generation), &[], false)
+            .unwrap()
+            .files
+            .is_empty());
+        assert!(
+            sync(&db, Some(delta.generation + 10), &[], false)
+                .unwrap()
+                .full
+        );
+
+        // Re-creating a removed file takes it off the removed list.
+        write(&tmp, "c.py", "def c():\n    pass\n");
+        db.index(&tmp, false).unwrap();
+        let back = sync(&db, Some(first.generation), &[], false).unwrap();
+        assert!(back.removed.is_empty());
+
+        std::fs::remove_dir_all(&tmp).unwrap();
+    }
+
+    #[test]
+    fn test_outline_digest_ignores_generation() {
+        let sym = Symbol::new("a", crate::types::SymbolKind::Function, "a.py", 1, 2, 0, 10);
+        let rewritten = Symbol {
+            generation: 7,
+            ..sym.clone()
+        };
+        assert_eq!(
+            outline_digest(std::slice::from_ref(&sym)),
+            outline_digest(&[rewritten])
+        );
+        let moved = Symbol::new("a", crate::types::SymbolKind::Function, "a.py", 3, 4, 8, 18);
+        assert_ne!(outline_digest(&[sym]), outline_digest(&[moved]));
+    }
+}
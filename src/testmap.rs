@@ -0,0 +1,733 @@
+//! Test-to-code mapping: which tests exercise a symbol (`cartog tests-for`), which
+//! tests a diff affects (`cartog select-tests`), and which public symbols no test
+//! references (`cartog untested`).
+//!
+//! Test code is recognized by convention, from the index alone:
+//! - files under a `tests/`, `test/`, `__tests__/` or `spec/` directory, or named like
+//!   `test_*.py`, `*_test.py`, `*_test.go`, `*_test.rb`, `*_spec.rb`, `*.test.ts`, `*.spec.js`
+//! - functions, methods and classes named `test*`/`Test*` or `*Test`/`*Tests`, and
+//!   anything nested in one (a Rust `mod tests`, a Python `TestCase` class)
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::overloads;
+use crate::owners::{self, ChangedLines};
+use crate::shard::ShardedDatabase;
+use crate::types::{Symbol, SymbolKind, Visibility};
+
+/// Directory names whose contents are test code.
+const TEST_DIRS: &[&str] = &["tests", "test", "__tests__", "spec"];
+
+/// File name suffixes (before the extension) of test files.
+const TEST_FILE_SUFFIXES: &[&str] = &["_test", "_tests", "_spec", ".test", ".spec"];
+
+/// Whether `path` (relative to the indexed root) is a test file.
+pub fn is_test_file(path: &str) -> bool {
+    let mut components: Vec<&str> = path.split('/').collect();
+    let file = components.pop().unwrap_or_default();
+    if components.iter().any(|dir| TEST_DIRS.contains(dir)) {
+        return true;
+    }
+    let stem = file.rsplit_once('.').map_or(file, |(stem, _)| stem);
+    stem.starts_with("test_") || TEST_FILE_SUFFIXES.iter().any(|s| stem.ends_with(s))
+}
+
+/// Whether a function, method, class or module name follows a test naming convention:
+/// `test`, `test_foo`, `testFoo`, `TestFoo`, `FooTest`, `FooTests`, `tests`.
+pub fn is_test_name(name: &str) -> bool {
+    let rest = name
+        .strip_prefix("test")
+        .or_else(|| name.strip_prefix("Test"));
+    let prefixed = match rest {
+        Some(rest) => {
+            rest.is_empty() || rest.starts_with(|c: char| c == '_' || c.is_ascii_uppercase())
+        }
+        None => false,
+    };
+    prefixed || name == "tests" || name.ends_with("Test") || name.ends_with("Tests")
+}
+
+/// How a symbol takes part in the test suite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TestRole {
+    /// Production code.
+    None,
+    /// Test code that is not itself a test: a helper or fixture in a test file.
+    Support,
+    /// A test a runner can select: named like one, or nested in a Rust `mod tests`.
+    Case,
+}
+
+impl std::fmt::Display for TestRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TestRole::None => "none",
+            TestRole::Support => "support",
+            TestRole::Case => "case",
+        })
+    }
+}
+
+/// A test reaching the queried symbol, see [`tests_for`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TestHit {
+    pub test: Symbol,
+    pub role: TestRole,
+    /// Enclosing symbol names and the test's own, joined by `::` (`TestAuth::test_login`).
+    pub qualified_name: String,
+    /// Hops from the test to the queried symbol (1 = calls it directly, 0 = is it).
+    pub depth: u32,
+    /// Name the test references at its last hop (the queried symbol or a helper calling it).
+    pub via: String,
+    pub line: u32,
+}
+
+/// Tests that reference `name` directly or through up to `max_depth - 1` callers
+/// (production helpers or test fixtures). Ordered by depth, then file and line.
+pub fn tests_for(db: &ShardedDatabase, name: &str, max_depth: u32) -> Result<Vec<TestHit>> {
+    let mut classifier = Classifier::new(db);
+    let hits = collect_tests(
+        &mut classifier,
+        vec![name.to_string()],
+        max_depth,
+        Vec::new(),
+    )?;
+    Ok(hits)
+}
+
+/// Breadth-first walk over refs from `seeds`, stopping at test cases and walking
+/// on through support code so tests calling a fixture are found too.
+fn collect_tests(
+    classifier: &mut Classifier<'_>,
+    seeds: Vec<String>,
+    max_depth: u32,
+    mut hits: Vec<TestHit>,
+) -> Result<Vec<TestHit>> {
+    let db = classifier.db;
+    let mut seen_tests: HashSet<String> = hits.iter().map(|h| h.test.id.clone()).collect();
+    let mut visited: HashSet<String> = seeds.iter().cloned().collect();
+    let mut frontier = seeds;
+
+    for depth in 1..=max_depth {
+        let mut next = Vec::new();
+        for current in &frontier {
+            for (edge, source) in db.refs(current, None)? {
+                let Some(source) = source else { continue };
+                if source.kind == SymbolKind::Import {
+                    continue;
+                }
+                let role = classifier.role(&source)?;
+                if role != TestRole::Case && visited.insert(source.name.clone()) {
+                    next.push(source.name.clone());
+                }
+                if role != TestRole::None && seen_tests.insert(source.id.clone()) {
+                    hits.push(TestHit {
+                        qualified_name: classifier.qualified_name(&source)?,
+                        test: source,
+                        role,
+                        depth,
+                        via: current.clone(),
+                        line: edge.line,
+                    });
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+
+    hits.sort_by(|a, b| {
+        (a.depth, &a.test.file_path, a.test.start_line).cmp(&(
+            b.depth,
+            &b.test.file_path,
+            b.test.start_line,
+        ))
+    });
+    Ok(hits)
+}
+
+/// Tests affected by a diff, see [`select_tests`].
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TestSelection {
+    pub range: String,
+    /// Names of the symbols the diff touches.
+    pub changed_symbols: Vec<String>,
+    /// Changed tests (depth 0) and tests reaching a changed symbol.
+    pub tests: Vec<TestHit>,
+    /// Changed files without indexed symbols (configs, fixtures, other languages).
+    /// They can affect any test; CI may want to run the full suite when this is not empty.
+    pub unmapped_files: Vec<String>,
+}
+
+/// Map changed lines to symbols, then to the tests that are, or transitively reach,
+/// one of them.
+pub fn select_tests(
+    db: &ShardedDatabase,
+    range: &str,
+    changes: &ChangedLines,
+    max_depth: u32,
+) -> Result<TestSelection> {
+    let mut classifier = Classifier::new(db);
+    let changed = owners::changed_symbols(db, changes)?;
+
+    let mut seeds = Vec::new();
+    let mut hits = Vec::new();
+    for sym in &changed {
+        let role = classifier.role(sym)?;
+        if role != TestRole::Case {
+            seeds.push(sym.name.clone());
+        }
+        if role != TestRole::None {
+            hits.push(TestHit {
+                qualified_name: classifier.qualified_name(sym)?,
+                test: sym.clone(),
+                role,
+                depth: 0,
+                via: sym.name.clone(),
+                line: sym.start_line,
+            });
+        }
+    }
+    seeds.sort();
+    seeds.dedup();
+    let tests = collect_tests(&mut classifier, seeds, max_depth, hits)?;
+
+    let mut unmapped_files = Vec::new();
+    for file in changes.keys() {
+        if db.outline(file)?.is_empty() {
+            unmapped_files.push(file.clone());
+        }
+    }
+
+    Ok(TestSelection {
+        range: range.to_string(),
+        changed_symbols: changed.into_iter().map(|s| s.name).collect(),
+        tests,
+        unmapped_files,
+    })
+}
+
+/// Test runners `select-tests` can format a selection for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestRunner {
+    Pytest,
+    Go,
+    Cargo,
+    Jest,
+}
+
+impl TestRunner {
+    /// Whether the runner handles tests in `path`.
+    fn runs(self, path: &str) -> bool {
+        let ext = path.rsplit_once('.').map_or("", |(_, ext)| ext);
+        match self {
+            TestRunner::Pytest => ext == "py",
+            TestRunner::Go => path.ends_with("_test.go"),
+            TestRunner::Cargo => ext == "rs",
+            TestRunner::Jest => matches!(ext, "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx"),
+        }
+    }
+
+    /// Command-line arguments selecting `tests`, to append to the runner's command:
+    ///
+    /// - pytest: node ids (`tests/test_auth.py::TestLogin::test_ok`)
+    /// - go: `-run '^(TestA|TestB)$'` and the packages holding them; `./...` when empty
+    /// - cargo: test name filters after `--` (`auth::tests::test_ok`)
+    /// - jest: test file paths (jest test names are strings, not symbols)
+    ///
+    /// Apart from go, an empty selection yields no arguments, which makes the runner
+    /// fall back to the full suite.
+    pub fn args(self, tests: &[TestHit]) -> Vec<String> {
+        let mut cases: Vec<&TestHit> = tests
+            .iter()
+            .filter(|t| self.runs(&t.test.file_path))
+            .collect();
+        if self != TestRunner::Jest {
+            cases.retain(|t| t.role == TestRole::Case);
+        }
+
+        let mut args: Vec<String> = match self {
+            TestRunner::Pytest => cases
+                .iter()
+                .map(|t| format!("{}::{}", t.test.file_path, t.qualified_name))
+                .collect(),
+            TestRunner::Jest => cases.iter().map(|t| t.test.file_path.clone()).collect(),
+            TestRunner::Cargo => cases
+                .iter()
+                .map(|t| match rust_module_path(&t.test.file_path) {
+                    Some(module) => format!("{module}::{}", t.qualified_name),
+                    None => t.qualified_name.clone(),
+                })
+                .collect(),
+            TestRunner::Go => {
+                let mut names: Vec<&str> = cases
+                    .iter()
+                    .map(|t| t.test.name.as_str())
+                    .filter(|n| n.starts_with("Test"))
+                    .collect();
+                names.sort_unstable();
+                names.dedup();
+                if names.is_empty() {
+                    return vec!["./...".to_string()];
+                }
+                let mut packages: Vec<String> = cases
+                    .iter()
+                    .map(|t| match t.test.file_path.rsplit_once('/') {
+                        Some((dir, _)) => format!("./{dir}"),
+                        None => ".".to_string(),
+                    })
+                    .collect();
+                packages.sort();
+                packages.dedup();
+                let mut args = vec!["-run".to_string(), format!("^({})$", names.join("|"))];
+                args.extend(packages);
+                return args;
+            }
+        };
+        args.sort();
+        args.dedup();
+        if self == TestRunner::Cargo && !args.is_empty() {
+            args.insert(0, "--".to_string());
+        }
+        args
+    }
+}
+
+/// Module path of a Rust source file inside its crate: `crates/a/src/auth/mod.rs` →
+/// `auth`. `None` for crate roots and integration test files (`tests/*.rs`).
+fn rust_module_path(path: &str) -> Option<String> {
+    let path = path.strip_suffix(".rs")?;
+    let in_crate = match path.rfind("src/") {
+        Some(i) if i == 0 || path.as_bytes()[i - 1] == b'/' => &path[i + 4..],
+        _ => return None,
+    };
+    let mut parts: Vec<&str> = in_crate.split('/').collect();
+    if parts.first() == Some(&"bin") {
+        return None;
+    }
+    if matches!(parts.last(), Some(&"mod")) {
+        parts.pop();
+    }
+    if matches!(parts.as_slice(), [] | ["lib"] | ["main"]) {
+        return None;
+    }
+    Some(parts.join("::"))
+}
+
+/// Quote `arg` for a POSIX shell when it contains anything but safe characters.
+pub fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=@,+".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Public functions, methods and classes outside test code that no test references
+/// directly, optionally restricted to paths starting with `dir`.
+///
+/// Unresolved test edges count by name, so a call the resolver could not pin down
+/// still marks every symbol of that name as tested.
+pub fn untested(db: &ShardedDatabase, dir: Option<&str>) -> Result<Vec<Symbol>> {
+    let symbols = db.all_symbols()?;
+    let by_id: HashMap<&str, &Symbol> = symbols.iter().map(|s| (s.id.as_str(), s)).collect();
+
+    let mut memo = HashMap::new();
+    let test_ids: HashSet<&str> = symbols
+        .iter()
+        .filter(|s| role_in(s, &by_id, &mut memo) != TestRole::None)
+        .map(|s| s.id.as_str())
+        .collect();
+
+    let mut tested_ids = HashSet::new();
+    let mut tested_names = HashSet::new();
+    for edge in db.all_edges()? {
+        if !test_ids.contains(edge.source_id.as_str()) {
+            continue;
+        }
+        match edge.target_id {
+            Some(id) => {
+                tested_ids.insert(id);
+            }
+            None => {
+                tested_names.insert(short_name(&edge.target_name).to_string());
+            }
+        }
+    }
+
+    let dir = dir.map(|d| d.trim_start_matches("./"));
+    Ok(symbols
+        .iter()
+        .filter(|s| {
+            matches!(
+                s.kind,
+                SymbolKind::Function | SymbolKind::Method | SymbolKind::Class
+            ) && s.visibility == Visibility::Public
+                && dir.map_or(true, |d| s.file_path.starts_with(d))
+                && !test_ids.contains(s.id.as_str())
+                && !tested_ids.contains(&s.id)
+                && !tested_names.contains(&s.name)
+        })
+        .cloned()
+        .collect())
+}
+
+/// `a.b.c` / `a::b::c` → `c`.
+pub(crate) fn short_name(target: &str) -> &str {
+    let target = target.rsplit("::").next().unwrap_or(target);
+    target.rsplit('.').next().unwrap_or(target)
+}
+
+/// Role of `sym` given its parent's role.
+///
+/// Anything under a test case is a case in Rust, where `#[test]` functions in
+/// `mod tests` need no special name; elsewhere it is support code (`setUp`).
+fn role_of(sym: &Symbol, parent: TestRole) -> TestRole {
+    let named = sym.kind != SymbolKind::Variable
+        && sym.kind != SymbolKind::Import
+        && is_test_name(&sym.name);
+    if named || (parent == TestRole::Case && sym.file_path.ends_with(".rs")) {
+        TestRole::Case
+    } else if parent != TestRole::None || is_test_file(&sym.file_path) {
+        TestRole::Support
+    } else {
+        TestRole::None
+    }
+}
+
+/// [`role_of`] with ancestors from `by_id`, memoized by id.
+pub(crate) fn role_in<'a>(
+    sym: &'a Symbol,
+    by_id: &HashMap<&str, &'a Symbol>,
+    memo: &mut HashMap<&'a str, TestRole>,
+) -> TestRole {
+    if let Some(&known) = memo.get(sym.id.as_str()) {
+        return known;
+    }
+    let parent = sym
+        .parent_id
+        .as_deref()
+        .and_then(|id| by_id.get(id))
+        .map_or(TestRole::None, |parent| role_in(parent, by_id, memo));
+    let role = role_of(sym, parent);
+    memo.insert(&sym.id, role);
+    role
+}
+
+/// Looks up ancestors on demand, for queries that only touch a few symbols.
+struct Classifier<'a> {
+    db: &'a ShardedDatabase,
+    memo: HashMap<String, TestRole>,
+}
+
+impl<'a> Classifier<'a> {
+    fn new(db: &'a ShardedDatabase) -> Self {
+        Self {
+            db,
+            memo: HashMap::new(),
+        }
+    }
+
+    fn parent(&self, sym: &Symbol) -> Result<Option<Symbol>> {
+        match sym.parent_id.as_deref() {
+            Some(id) => self.db.get_symbol(id),
+            None => Ok(None),
+        }
+    }
+
+    fn role(&mut self, sym: &Symbol) -> Result<TestRole> {
+        if let Some(&known) = self.memo.get(&sym.id) {
+            return Ok(known);
+        }
+        let parent = match self.parent(sym)? {
+            Some(parent) => self.role(&parent)?,
+            None => TestRole::None,
+        };
+        let role = role_of(sym, parent);
+        self.memo.insert(sym.id.clone(), role);
+        Ok(role)
+    }
+
+    fn qualified_name(&self, sym: &Symbol) -> Result<String> {
+        overloads::qualified_name(self.db, sym)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::types::{Edge, EdgeKind};
+
+    #[test]
+    fn test_is_test_file() {
+        for path in [
+            "tests/integration.rs",
+            "pkg/test/util.py",
+            "web/__tests__/app.js",
+            "spec/models/user_spec.rb",
+            "test_auth.py",
+            "auth_test.py",
+            "server_test.go",
+            "src/app.test.ts",
+            "src/app.spec.tsx",
+        ] {
+            assert!(is_test_file(path), "{path}");
+        }
+        for path in ["src/auth.py", "contest.py", "src/latest.rs", "testing.go"] {
+            assert!(!is_test_file(path), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_is_test_name() {
+        for name in [
+            "test",
+            "test_login",
+            "testLogin",
+            "TestLogin",
+            "LoginTest",
+            "LoginTests",
+            "tests",
+        ] {
+            assert!(is_test_name(name), "{name}");
+        }
+        for name in ["testify", "latest", "Tester", "attest", "contest"] {
+            assert!(!is_test_name(name), "{name}");
+        }
+    }
+
+    /// `validate` is called by `login`, which is called by a test in a Rust `mod tests`
+    /// and by `test_direct`; `logout` is public and only called from production code.
+    fn setup() -> ShardedDatabase {
+        let db = Database::open_memory().unwrap();
+        let sym = |name: &str, kind, file: &str, line| {
+            Symbol::new(name, kind, file, line, line + 2, 0, 10)
+        };
+        let validate = sym("validate", SymbolKind::Function, "src/auth.rs", 1);
+        let login = sym("login", SymbolKind::Function, "src/auth.rs", 10);
+        let logout = sym("logout", SymbolKind::Function, "src/auth.rs", 20);
+        let module = sym("tests", SymbolKind::Class, "src/auth.rs", 30);
+        let nested =
+            sym("logs_in", SymbolKind::Function, "src/auth.rs", 31).with_parent(Some(&module.id));
+        let direct = sym("test_direct", SymbolKind::Function, "src/other.rs", 1);
+        let private = sym("helper", SymbolKind::Function, "src/auth.rs", 40)
+            .with_visibility(Visibility::Private);
+        db.insert_symbols(&[
+            validate.clone(),
+            login.clone(),
+            logout.clone(),
+            module,
+            nested.clone(),
+            direct.clone(),
+            private,
+        ])
+        .unwrap();
+        db.insert_edges(&[
+            Edge::new(&login.id, "validate", EdgeKind::Calls, "src/auth.rs", 11),
+            Edge::new(&logout.id, "validate", EdgeKind::Calls, "src/auth.rs", 21),
+            Edge::new(&nested.id, "login", EdgeKind::Calls, "src/auth.rs", 32),
+            Edge::new(&direct.id, "validate", EdgeKind::Calls, "src/other.rs", 2),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
+        ShardedDatabase::from(db)
+    }
+
+    #[test]
+    fn test_tests_for_follows_helpers() {
+        let db = setup();
+        let hits = tests_for(&db, "validate", 3).unwrap();
+        let found: Vec<_> = hits
+            .iter()
+            .map(|h| (h.test.name.as_str(), h.depth, h.via.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [("test_direct", 1, "validate"), ("logs_in", 2, "login")]
+        );
+
+        let direct_only = tests_for(&db, "validate", 1).unwrap();
+        assert_eq!(direct_only.len(), 1);
+    }
+
+    #[test]
+    fn test_tests_for_walks_through_fixtures() {
+        let db = Database::open_memory().unwrap();
+        let create = Symbol::new(
+            "create_user",
+            SymbolKind::Function,
+            "app/users.py",
+            1,
+            3,
+            0,
+            10,
+        );
+        let fixture = Symbol::new(
+            "make_user",
+            SymbolKind::Function,
+            "tests/conftest.py",
+            1,
+            3,
+            0,
+            10,
+        );
+        let test = Symbol::new(
+            "test_signup",
+            SymbolKind::Function,
+            "tests/test_signup.py",
+            1,
+            3,
+            0,
+            10,
+        );
+        db.insert_symbols(&[create, fixture.clone(), test.clone()])
+            .unwrap();
+        db.insert_edges(&[
+            Edge::new(
+                &fixture.id,
+                "create_user",
+                EdgeKind::Calls,
+                "tests/conftest.py",
+                2,
+            ),
+            Edge::new(
+                &test.id,
+                "make_user",
+                EdgeKind::Calls,
+                "tests/test_signup.py",
+                2,
+            ),
+        ])
+        .unwrap();
+        db.resolve_edges().unwrap();
+        let db = ShardedDatabase::from(db);
+
+        let hits = tests_for(&db, "create_user", 3).unwrap();
+        let found: Vec<_> = hits
+            .iter()
+            .map(|h| (h.test.name.as_str(), h.role))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("make_user", TestRole::Support),
+                ("test_signup", TestRole::Case)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_tests_maps_diff_to_tests() {
+        let db = setup();
+        let changes = ChangedLines::from([
+            ("src/auth.rs".to_string(), vec![(2, 2)]),
+            ("README.md".to_string(), vec![(1, 1)]),
+        ]);
+        let selection = select_tests(&db, "HEAD", &changes, 3).unwrap();
+        assert_eq!(selection.changed_symbols, ["validate"]);
+        let names: Vec<_> = selection
+            .tests
+            .iter()
+            .map(|t| t.qualified_name.as_str())
+            .collect();
+        assert_eq!(names, ["test_direct", "tests::logs_in"]);
+        assert_eq!(selection.unmapped_files, ["README.md"]);
+
+        // A changed test selects itself.
+        let changes = ChangedLines::from([("src/other.rs".to_string(), vec![(1, 1)])]);
+        let selection = select_tests(&db, "HEAD", &changes, 3).unwrap();
+        assert_eq!(selection.tests.len(), 1);
+        assert_eq!(selection.tests[0].depth, 0);
+    }
+
+    fn hit(qualified_name: &str, file: &str, role: TestRole) -> TestHit {
+        let name = qualified_name.rsplit("::").next().unwrap();
+        TestHit {
+            test: Symbol::new(name, SymbolKind::Function, file, 1, 2, 0, 10),
+            role,
+            qualified_name: qualified_name.to_string(),
+            depth: 1,
+            via: "x".to_string(),
+            line: 1,
+        }
+    }
+
+    #[test]
+    fn test_runner_args() {
+        let tests = [
+            hit("TestLogin::test_ok", "tests/test_auth.py", TestRole::Case),
+            hit("make_user", "tests/conftest.py", TestRole::Support),
+            hit("TestParse", "pkg/parse/parse_test.go", TestRole::Case),
+            hit("TestLex", "lex_test.go", TestRole::Case),
+            hit(
+                "tests::logs_in",
+                "crates/core/src/auth/mod.rs",
+                TestRole::Case,
+            ),
+            hit("round_trip", "tests/export.rs", TestRole::Case),
+            hit("helper", "src/app.test.ts", TestRole::Support),
+        ];
+        assert_eq!(
+            TestRunner::Pytest.args(&tests),
+            ["tests/test_auth.py::TestLogin::test_ok"]
+        );
+        assert_eq!(
+            TestRunner::Go.args(&tests),
+            ["-run", "^(TestLex|TestParse)$", ".", "./pkg/parse"]
+        );
+        assert_eq!(
+            TestRunner::Cargo.args(&tests),
+            ["--", "auth::tests::logs_in", "round_trip"]
+        );
+        assert_eq!(TestRunner::Jest.args(&tests), ["src/app.test.ts"]);
+
+        assert!(TestRunner::Pytest.args(&[]).is_empty());
+        assert_eq!(TestRunner::Go.args(&[]), ["./..."]);
+    }
+
+    #[test]
+    fn test_rust_module_path() {
+        assert_eq!(rust_module_path("src/db.rs").as_deref(), Some("db"));
+        assert_eq!(
+            rust_module_path("src/languages/mod.rs").as_deref(),
+            Some("languages")
+        );
+        assert_eq!(
+            rust_module_path("crates/a/src/rag/search.rs").as_deref(),
+            Some("rag::search")
+        );
+        assert_eq!(rust_module_path("src/lib.rs"), None);
+        assert_eq!(rust_module_path("src/bin/tool.rs"), None);
+        assert_eq!(rust_module_path("tests/cli.rs"), None);
+        assert_eq!(rust_module_path("mysrc/x.rs"), None);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("tests/a.py::test_x"), "tests/a.py::test_x");
+        assert_eq!(shell_quote("^(TestA|TestB)$"), "'^(TestA|TestB)$'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_untested_lists_public_symbols_without_test_edges() {
+        let db = setup();
+        let names: Vec<_> = untested(&db, None)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        // validate and login are called from tests; tests themselves and private helpers are skipped.
+        assert_eq!(names, ["logout"]);
+        assert!(untested(&db, Some("lib/")).unwrap().is_empty());
+    }
+}
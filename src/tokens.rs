@@ -0,0 +1,97 @@
+//! Token cost estimates for query results.
+//!
+//! Agents page results into a bounded context window, so every result that
+//! points at code carries `estimated_tokens`: what reading that code would
+//! cost. Estimates use the common ~4 bytes per token heuristic, which tracks
+//! BPE tokenizers closely enough on source code to budget with.
+
+use serde_json::{Map, Value};
+
+/// Average bytes per token for source code under BPE tokenizers.
+const BYTES_PER_TOKEN: u64 = 4;
+
+/// Estimated tokens for `bytes` bytes of text (rounded up).
+pub fn estimate_bytes(bytes: u64) -> u64 {
+    (bytes + BYTES_PER_TOKEN - 1) / BYTES_PER_TOKEN
+}
+
+/// Estimated tokens for `text`.
+pub fn estimate(text: &str) -> u64 {
+    estimate_bytes(text.len() as u64)
+}
+
+/// Add `estimated_tokens` to every result in `value` and return their sum.
+///
+/// A result is an object carrying code: a chunk with `content` (RAG search),
+/// or a symbol with `start_byte`/`end_byte` (its body). Annotated objects are
+/// not searched further, so a chunk is not counted again through its symbol.
+/// Nested symbols (a class and its methods in an outline) are each counted.
+pub fn annotate(value: &mut Value) -> u64 {
+    match value {
+        Value::Array(items) => items.iter_mut().map(annotate).sum(),
+        Value::Object(obj) => match result_tokens(obj) {
+            Some(tokens) => {
+                obj.insert("estimated_tokens".to_string(), tokens.into());
+                tokens
+            }
+            None => obj.values_mut().map(annotate).sum(),
+        },
+        _ => 0,
+    }
+}
+
+fn result_tokens(obj: &Map<String, Value>) -> Option<u64> {
+    if let Some(content) = obj.get("content").and_then(Value::as_str) {
+        return Some(estimate(content));
+    }
+    let start = obj.get("start_byte")?.as_u64()?;
+    let end = obj.get("end_byte")?.as_u64()?;
+    Some(estimate_bytes(end.saturating_sub(start)))
+}
+
+/// Human-readable total, e.g. `~1.2k tokens`.
+pub fn format_total(tokens: u64) -> String {
+    if tokens >= 1000 {
+        format!("~{:.1}k tokens", tokens as f64 / 1000.0)
+    } else {
+        format!("~{tokens} tokens")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_estimate_rounds_up() {
+        assert_eq!(estimate(""), 0);
+        assert_eq!(estimate("abc"), 1);
+        assert_eq!(estimate("abcdefghi"), 3);
+    }
+
+    #[test]
+    fn test_annotate_symbols_and_chunks() {
+        let mut value = json!([
+            { "name": "a", "start_byte": 0, "end_byte": 400 },
+            { "edge": { "target_name": "a" }, "depth": 1 },
+            {
+                "symbol": { "name": "b", "start_byte": 0, "end_byte": 4000 },
+                "content": "def b(): pass",
+            },
+            [{ "source": { "start_byte": 10, "end_byte": 18 } }],
+        ]);
+        assert_eq!(annotate(&mut value), 100 + 4 + 2);
+        assert_eq!(value[0]["estimated_tokens"], 100);
+        assert!(value[1].get("estimated_tokens").is_none());
+        assert_eq!(value[2]["estimated_tokens"], 4);
+        assert!(value[2]["symbol"].get("estimated_tokens").is_none());
+        assert_eq!(value[3][0]["source"]["estimated_tokens"], 2);
+    }
+
+    #[test]
+    fn test_format_total() {
+        assert_eq!(format_total(950), "~950 tokens");
+        assert_eq!(format_total(1234), "~1.2k tokens");
+    }
+}
@@ -0,0 +1,320 @@
+//! `cartog emit-training-data`: search queries recorded by `serve --audit`, paired
+//! with the results the agent went on to use, to fine-tune a re-ranker or embedding
+//! model on how this codebase is actually searched.
+//!
+//! A result of `cartog_search` or `cartog_rag_search` counts as used when a later
+//! successful call within [`USE_WINDOW_SECS`] names it: `cartog_show`, `cartog_refs`
+//! and the other tools taking a symbol name, with a matching `file` when the call
+//! gives one. Results returned but not used are the query's hard negatives. Queries
+//! whose results were all ignored are left out, and so are batch searches, whose
+//! hits cannot be told apart by query.
+//!
+//! Everything stays local, and results are written as symbol IDs and SHA-256 hashes
+//! of their indexed code, never the code itself: the trainer joins them back to the
+//! index it runs next to. The query text is the only free text kept.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::db::{AuditCall, SymbolHit};
+use crate::indexer::file_hash;
+use crate::shard::ShardedDatabase;
+
+/// How long after a search a call naming one of its results counts as using it.
+pub const USE_WINDOW_SECS: f64 = 600.0;
+/// Most hard negatives kept per query, best-ranked first.
+const MAX_NEGATIVES: usize = 8;
+
+/// Tools whose `query` and returned symbols make a training query.
+const SEARCH_TOOLS: &[&str] = &["cartog_search", "cartog_rag_search"];
+/// Tools that act on the symbol named by their `name` argument.
+const NAME_TOOLS: &[&str] = &[
+    "cartog_show",
+    "cartog_refs",
+    "cartog_callees",
+    "cartog_callers",
+    "cartog_impact",
+    "cartog_raises",
+    "cartog_hierarchy",
+    "cartog_tests_for",
+    "cartog_annotate_symbol",
+];
+
+/// A search result, anonymized.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct TrainingResult {
+    pub symbol_id: String,
+    /// SHA-256 of the symbol's indexed code (the `hash` of `cartog show`).
+    pub content_hash: String,
+    /// 1-based position in the search results.
+    pub rank: u32,
+}
+
+/// One line of the training set.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TrainingExample {
+    pub query: String,
+    /// The search tool that ran the query.
+    pub tool: String,
+    /// Results the agent went on to use.
+    pub positives: Vec<TrainingResult>,
+    /// Results returned but not used, best-ranked first.
+    pub negatives: Vec<TrainingResult>,
+}
+
+/// Summary of [`emit`].
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TrainingSet {
+    pub path: String,
+    /// Search calls found in the audit log.
+    pub queries: u32,
+    /// Lines written: queries with at least one used result still in the index.
+    pub examples: u32,
+    pub positives: u32,
+    pub negatives: u32,
+}
+
+/// Write the training examples of the audit log since `since` (unix seconds) to
+/// `path` as JSON lines.
+pub fn emit(db: &ShardedDatabase, since: Option<f64>, path: &Path) -> Result<TrainingSet> {
+    let calls = db.audit_calls(since)?;
+    let searches = label(&calls);
+    let mut set = TrainingSet {
+        path: path.display().to_string(),
+        queries: searches.len() as u32,
+        examples: 0,
+        positives: 0,
+        negatives: 0,
+    };
+
+    let mut lines = String::new();
+    for search in searches {
+        let mut example = TrainingExample {
+            query: search.query,
+            tool: search.tool.to_string(),
+            positives: Vec::new(),
+            negatives: Vec::new(),
+        };
+        for (rank, (hit, used)) in search.results.into_iter().enumerate() {
+            if !used && example.negatives.len() == MAX_NEGATIVES {
+                continue;
+            }
+            // Symbols re-indexed away since the call can't be tied to their code.
+            let Some(result) = anonymize(db, hit, rank as u32 + 1)? else {
+                continue;
+            };
+            if used {
+                example.positives.push(result);
+            } else {
+                example.negatives.push(result);
+            }
+        }
+        if example.positives.is_empty() {
+            continue;
+        }
+        set.examples += 1;
+        set.positives += example.positives.len() as u32;
+        set.negatives += example.negatives.len() as u32;
+        lines.push_str(&serde_json::to_string(&example)?);
+        lines.push('\n');
+    }
+
+    std::fs::write(path, lines).with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(set)
+}
+
+/// A search call and whether each of its results was used.
+#[derive(Debug, PartialEq)]
+struct LabeledSearch<'a> {
+    query: String,
+    tool: &'a str,
+    results: Vec<(&'a SymbolHit, bool)>,
+}
+
+/// The successful search calls in `calls` (oldest first), each result marked used
+/// when a later call within [`USE_WINDOW_SECS`] names it.
+fn label(calls: &[AuditCall]) -> Vec<LabeledSearch<'_>> {
+    let parsed: Vec<(&AuditCall, Value)> = calls
+        .iter()
+        .filter(|c| c.entry.ok)
+        .map(|c| {
+            (
+                c,
+                serde_json::from_str(&c.entry.params).unwrap_or(Value::Null),
+            )
+        })
+        .collect();
+
+    let mut searches = Vec::new();
+    for (i, (call, params)) in parsed.iter().enumerate() {
+        let tool = call.entry.tool.as_str();
+        if !SEARCH_TOOLS.contains(&tool) {
+            continue;
+        }
+        let Some(query) = params.get("query").and_then(Value::as_str) else {
+            continue;
+        };
+        let deadline = call.entry.timestamp + USE_WINDOW_SECS;
+        let named: Vec<(String, Option<String>)> = parsed[i + 1..]
+            .iter()
+            .take_while(|(later, _)| later.entry.timestamp <= deadline)
+            .flat_map(|(later, params)| named_symbols(&later.entry.tool, params))
+            .collect();
+        let results = call
+            .hits
+            .iter()
+            .map(|hit| {
+                let used = named.iter().any(|(name, file)| {
+                    *name == hit.name && file.as_ref().map_or(true, |f| *f == hit.file_path)
+                });
+                (hit, used)
+            })
+            .collect();
+        searches.push(LabeledSearch {
+            query: query.to_string(),
+            tool,
+            results,
+        });
+    }
+    searches
+}
+
+/// The symbols a call's arguments name, with their file when given.
+fn named_symbols(tool: &str, params: &Value) -> Vec<(String, Option<String>)> {
+    let field = |key: &str| params.get(key).and_then(Value::as_str).map(str::to_string);
+    match tool {
+        "cartog_context" => field("query").map(|q| (q, None)).into_iter().collect(),
+        // Symbol IDs are `file:qualified.name:hash`.
+        "cartog_validate_range" => field("symbol_id")
+            .and_then(|id| {
+                let (file, rest) = id.split_once(':')?;
+                let (qualified, _hash) = rest.rsplit_once(':')?;
+                let name = qualified.rsplit('.').next()?;
+                Some((name.to_string(), Some(file.to_string())))
+            })
+            .into_iter()
+            .collect(),
+        "cartog_reserve_symbols" => params
+            .get("names")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(|name| (name.to_string(), field("file")))
+            .collect(),
+        _ if NAME_TOOLS.contains(&tool) => field("name")
+            .map(|name| (name, field("file")))
+            .into_iter()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The ID and code hash of the indexed symbol `hit` refers to, if it still exists.
+fn anonymize(db: &ShardedDatabase, hit: &SymbolHit, rank: u32) -> Result<Option<TrainingResult>> {
+    let Some(symbol) = db
+        .definitions(&hit.name, Some(&hit.file_path))?
+        .into_iter()
+        .find(|s| s.kind == hit.kind)
+    else {
+        return Ok(None);
+    };
+    Ok(db
+        .get_symbol_content(&symbol.id)?
+        .map(|(content, _)| TrainingResult {
+            content_hash: file_hash(&content),
+            symbol_id: symbol.id,
+            rank,
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::AuditEntry;
+    use crate::types::SymbolKind;
+
+    fn call(timestamp: f64, tool: &str, params: &str, hits: &[&str]) -> AuditCall {
+        AuditCall {
+            entry: AuditEntry {
+                timestamp,
+                time: String::new(),
+                tool: tool.to_string(),
+                params: params.to_string(),
+                result_count: Some(hits.len() as u32),
+                duration_ms: 1,
+                ok: true,
+            },
+            hits: hits
+                .iter()
+                .map(|name| SymbolHit {
+                    name: name.to_string(),
+                    file_path: "auth.py".to_string(),
+                    kind: SymbolKind::Function,
+                })
+                .collect(),
+        }
+    }
+
+    fn used<'a>(search: &LabeledSearch<'a>) -> Vec<(&'a str, bool)> {
+        search
+            .results
+            .iter()
+            .map(|(hit, used)| (hit.name.as_str(), *used))
+            .collect()
+    }
+
+    #[test]
+    fn test_label_marks_results_named_by_later_calls() {
+        let calls = [
+            call(
+                0.0,
+                "cartog_rag_search",
+                r#"{"query": "check password"}"#,
+                &["hash_password", "login", "logout"],
+            ),
+            call(1.0, "cartog_show", r#"{"name": "login"}"#, &["login"]),
+            call(
+                2.0,
+                "cartog_refs",
+                r#"{"name": "logout", "file": "web.py"}"#,
+                &[],
+            ),
+            call(3.0, "cartog_search", r#"{"query": "session"}"#, &["logout"]),
+            call(
+                4.0,
+                "cartog_validate_range",
+                r#"{"symbol_id": "auth.py:Session.logout:3f2a9c1b7d4e", "expected_hash": "x"}"#,
+                &[],
+            ),
+            call(
+                5.0,
+                "cartog_rag_search_batch",
+                r#"{"queries": ["a"]}"#,
+                &["login"],
+            ),
+            call(
+                1.0 + USE_WINDOW_SECS,
+                "cartog_show",
+                r#"{"name": "hash_password"}"#,
+                &[],
+            ),
+        ];
+
+        let searches = label(&calls);
+        assert_eq!(searches.len(), 2);
+        assert_eq!(searches[0].query, "check password");
+        // `logout` is used through its symbol ID (not the refs in web.py), and
+        // `hash_password` is named too late.
+        assert_eq!(
+            used(&searches[0]),
+            [("hash_password", false), ("login", true), ("logout", true)]
+        );
+        assert_eq!(searches[1].tool, "cartog_search");
+        assert_eq!(used(&searches[1]), [("logout", true)]);
+    }
+}
@@ -0,0 +1,452 @@
+//! State of the browser and what each key does to it.
+
+use std::path::Path;
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::shard::ShardedDatabase;
+use crate::source::{self, SymbolSource};
+use crate::types::Symbol;
+
+/// Most search results listed.
+const RESULT_LIMIT: u32 = 200;
+/// Context lines around the previewed symbol.
+const PREVIEW_CONTEXT: u32 = 3;
+/// Lines scrolled by PageUp / PageDown in the preview.
+const PREVIEW_PAGE: u16 = 10;
+
+/// Where keys go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Pane {
+    Search,
+    Results,
+    Outline,
+    Graph,
+}
+
+impl Pane {
+    /// The next list pane for Tab (`forward`) or Shift+Tab.
+    fn cycle(self, forward: bool) -> Self {
+        let panes = [Self::Results, Self::Outline, Self::Graph];
+        let i = panes.iter().position(|p| *p == self).unwrap_or(0);
+        let next = if forward { i + 1 } else { i + panes.len() - 1 };
+        panes[next % panes.len()]
+    }
+}
+
+/// What the graph pane lists about the open symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum GraphMode {
+    Refs,
+    Callers,
+    Callees,
+}
+
+impl GraphMode {
+    pub(super) fn title(self) -> &'static str {
+        match self {
+            Self::Refs => "References",
+            Self::Callers => "Callers",
+            Self::Callees => "Callees",
+        }
+    }
+}
+
+/// One line of a list, and the symbol Enter opens from it (none for an
+/// unresolved callee).
+#[derive(Debug, Clone)]
+pub(super) struct Entry {
+    pub text: String,
+    pub symbol: Option<Symbol>,
+}
+
+/// A list pane with its selected line.
+#[derive(Debug, Clone, Default)]
+pub(super) struct EntryList {
+    pub entries: Vec<Entry>,
+    pub selected: usize,
+}
+
+impl EntryList {
+    fn new(entries: Vec<Entry>) -> Self {
+        Self {
+            entries,
+            selected: 0,
+        }
+    }
+
+    fn selected_symbol(&self) -> Option<&Symbol> {
+        self.entries.get(self.selected)?.symbol.as_ref()
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
+}
+
+pub(super) struct App<'a> {
+    db: &'a ShardedDatabase,
+    root: &'a Path,
+    pub focus: Pane,
+    pub query: String,
+    pub results: EntryList,
+    pub outline: EntryList,
+    pub graph: EntryList,
+    pub graph_mode: GraphMode,
+    /// The symbol the outline and graph panes are about.
+    pub open: Option<Symbol>,
+    /// Symbols opened before `open`, for Backspace.
+    history: Vec<Symbol>,
+    pub preview: Option<SymbolSource>,
+    pub preview_scroll: u16,
+    /// Last error or notice, shown instead of the key help.
+    pub status: Option<String>,
+    pub quit: bool,
+}
+
+impl<'a> App<'a> {
+    pub fn new(db: &'a ShardedDatabase, root: &'a Path) -> Self {
+        Self {
+            db,
+            root,
+            focus: Pane::Search,
+            query: String::new(),
+            results: EntryList::default(),
+            outline: EntryList::default(),
+            graph: EntryList::default(),
+            graph_mode: GraphMode::Refs,
+            open: None,
+            history: Vec::new(),
+            preview: None,
+            preview_scroll: 0,
+            status: None,
+            quit: false,
+        }
+    }
+
+    /// Search for `query` and move to its results.
+    pub fn search_for(&mut self, query: &str) {
+        self.query = query.to_string();
+        self.search();
+    }
+
+    pub fn on_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+        self.status = None;
+        if self.focus == Pane::Search {
+            match key.code {
+                KeyCode::Char(c) => self.query.push(c),
+                KeyCode::Backspace => {
+                    self.query.pop();
+                }
+                KeyCode::Enter => self.search(),
+                KeyCode::Esc | KeyCode::Down | KeyCode::Tab => self.focus = Pane::Results,
+                _ => {}
+            }
+            return;
+        }
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Char('/') => self.focus = Pane::Search,
+            KeyCode::Tab => self.focus_pane(self.focus.cycle(true)),
+            KeyCode::BackTab => self.focus_pane(self.focus.cycle(false)),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => {
+                self.preview_scroll = self.preview_scroll.saturating_add(PREVIEW_PAGE)
+            }
+            KeyCode::PageUp => {
+                self.preview_scroll = self.preview_scroll.saturating_sub(PREVIEW_PAGE)
+            }
+            KeyCode::Enter => match self.selected_symbol().cloned() {
+                Some(symbol) => self.open_symbol(symbol, true),
+                None => self.status = Some("nothing to open (unresolved)".into()),
+            },
+            KeyCode::Char('r') => self.show_graph(GraphMode::Refs),
+            KeyCode::Char('c') => self.show_graph(GraphMode::Callers),
+            KeyCode::Char('e') => self.show_graph(GraphMode::Callees),
+            KeyCode::Backspace | KeyCode::Char('b') => match self.history.pop() {
+                Some(symbol) => self.open_symbol(symbol, false),
+                None => self.status = Some("no previous symbol".into()),
+            },
+            _ => {}
+        }
+    }
+
+    fn search(&mut self) {
+        let query = self.query.trim();
+        if query.is_empty() {
+            return;
+        }
+        match self.db.search(query, None, None, RESULT_LIMIT) {
+            Ok(symbols) => {
+                if symbols.is_empty() {
+                    self.status = Some(format!("no symbols matching '{query}'"));
+                }
+                let entries = symbols
+                    .into_iter()
+                    .map(|s| Entry {
+                        text: format!("{} {}  {}:{}", s.kind, s.name, s.file_path, s.start_line),
+                        symbol: Some(s),
+                    })
+                    .collect();
+                self.results = EntryList::new(entries);
+            }
+            Err(e) => self.status = Some(format!("search failed: {e}")),
+        }
+        self.focus_pane(Pane::Results);
+    }
+
+    fn focus_pane(&mut self, pane: Pane) {
+        self.focus = pane;
+        self.update_preview();
+    }
+
+    fn list_mut(&mut self) -> Option<&mut EntryList> {
+        match self.focus {
+            Pane::Search => None,
+            Pane::Results => Some(&mut self.results),
+            Pane::Outline => Some(&mut self.outline),
+            Pane::Graph => Some(&mut self.graph),
+        }
+    }
+
+    fn selected_symbol(&self) -> Option<&Symbol> {
+        match self.focus {
+            Pane::Search => None,
+            Pane::Results => self.results.selected_symbol(),
+            Pane::Outline => self.outline.selected_symbol(),
+            Pane::Graph => self.graph.selected_symbol(),
+        }
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        if let Some(list) = self.list_mut() {
+            list.move_by(delta);
+        }
+        self.update_preview();
+    }
+
+    /// Make `symbol` the open one: its file's outline, the graph pane for it, and
+    /// focus on the graph pane. `remember` keeps the previous one for Backspace.
+    fn open_symbol(&mut self, symbol: Symbol, remember: bool) {
+        if let Some(previous) = self.open.take() {
+            if remember && previous.id != symbol.id {
+                self.history.push(previous);
+            }
+        }
+        match self.db.outline(&symbol.file_path) {
+            Ok(symbols) => {
+                let selected = symbols.iter().position(|s| s.id == symbol.id).unwrap_or(0);
+                let entries = symbols
+                    .iter()
+                    .map(|s| Entry {
+                        text: format!(
+                            "{}{} {}  {}-{}",
+                            "  ".repeat(depth(&symbols, s)),
+                            s.kind,
+                            s.name,
+                            s.start_line,
+                            s.end_line
+                        ),
+                        symbol: Some(s.clone()),
+                    })
+                    .collect();
+                self.outline = EntryList { entries, selected };
+            }
+            Err(e) => self.status = Some(format!("outline failed: {e}")),
+        }
+        self.open = Some(symbol);
+        self.show_graph(self.graph_mode);
+    }
+
+    /// List `mode` for the open symbol, or the selected one when none is open.
+    fn show_graph(&mut self, mode: GraphMode) {
+        if self.open.is_none() {
+            if let Some(symbol) = self.selected_symbol().cloned() {
+                return self.open_symbol(symbol, true);
+            }
+        }
+        self.graph_mode = mode;
+        let Some(name) = self.open.as_ref().map(|s| s.name.clone()) else {
+            self.status = Some("open a symbol first (Enter)".into());
+            return;
+        };
+        let entries = match mode {
+            GraphMode::Refs => self.db.refs(&name, None).map(|refs| {
+                refs.into_iter()
+                    .map(|(edge, source)| Entry {
+                        text: format!(
+                            "{} {}  {}:{}",
+                            edge.kind,
+                            source.as_ref().map_or(&edge.source_id, |s| &s.name),
+                            edge.file_path,
+                            edge.line
+                        ),
+                        symbol: source,
+                    })
+                    .collect::<Vec<_>>()
+            }),
+            GraphMode::Callers => self.db.callers(&name, None).map(|callers| {
+                callers
+                    .into_iter()
+                    .map(|c| Entry {
+                        text: format!(
+                            "{} {}  {}:{}",
+                            c.caller.kind, c.caller.name, c.edge.file_path, c.edge.line
+                        ),
+                        symbol: Some(c.caller),
+                    })
+                    .collect()
+            }),
+            GraphMode::Callees => self.db.call_sites(&name, false, None).map(|sites| {
+                sites
+                    .into_iter()
+                    .map(|site| {
+                        let edge = &site.edge;
+                        let target = site.target.as_ref().map_or(String::new(), |t| {
+                            format!("  -> {}:{}", t.file_path, t.start_line)
+                        });
+                        Entry {
+                            text: format!(
+                                "{}  {}:{}{target}",
+                                edge.target_name, edge.file_path, edge.line
+                            ),
+                            symbol: site.target,
+                        }
+                    })
+                    .collect()
+            }),
+        };
+        match entries {
+            Ok(entries) => self.graph = EntryList::new(entries),
+            Err(e) => self.status = Some(format!("{} failed: {e}", mode.title())),
+        }
+        self.focus_pane(Pane::Graph);
+    }
+
+    /// Preview the selected symbol of the focused list, else the open one.
+    fn update_preview(&mut self) {
+        self.preview_scroll = 0;
+        let Some(symbol) = self.selected_symbol().or(self.open.as_ref()).cloned() else {
+            self.preview = None;
+            return;
+        };
+        if self
+            .preview
+            .as_ref()
+            .is_some_and(|p| p.symbol.id == symbol.id)
+        {
+            return;
+        }
+        match source::snippet(self.db, self.root, symbol, PREVIEW_CONTEXT) {
+            Ok(preview) => self.preview = Some(preview),
+            Err(e) => {
+                self.preview = None;
+                self.status = Some(format!("preview failed: {e}"));
+            }
+        }
+    }
+}
+
+/// Nesting depth of `symbol` among `symbols` of its file, by parent.
+fn depth(symbols: &[Symbol], symbol: &Symbol) -> usize {
+    let mut depth = 0;
+    let mut parent = symbol.parent_id.as_deref();
+    while let Some(id) = parent {
+        match symbols.iter().find(|s| s.id == id) {
+            Some(p) if depth < 16 => {
+                depth += 1;
+                parent = p.parent_id.as_deref();
+            }
+            _ => break,
+        }
+    }
+    depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.on_key(KeyEvent::from(code));
+    }
+
+    fn texts(list: &EntryList) -> Vec<&str> {
+        list.entries.iter().map(|e| e.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_search_hit_to_references_and_back() {
+        let tmp = std::env::temp_dir().join("cartog_test_tui");
+        let _ = std::fs::remove_dir_all(&tmp);
+        std::fs::create_dir_all(&tmp).unwrap();
+        std::fs::write(
+            tmp.join("auth.py"),
+            "def check():\n    pass\n\n\ndef login():\n    check()\n",
+        )
+        .unwrap();
+        std::fs::write(
+            tmp.join("web.py"),
+            "from auth import login\n\n\ndef handler():\n    login()\n",
+        )
+        .unwrap();
+        let db = ShardedDatabase::from(Database::open_memory().unwrap());
+        crate::indexer::index_directory(&db, &tmp, false).unwrap();
+        let mut app = App::new(&db, &tmp);
+
+        // Type a query; Enter lists the hits and previews the first.
+        for c in "login".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.focus, Pane::Results);
+        assert_eq!(texts(&app.results), ["function login  auth.py:5"]);
+        assert_eq!(app.preview.as_ref().unwrap().symbol.name, "login");
+
+        // Enter opens the hit: its file's outline and its references.
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.focus, Pane::Graph);
+        assert_eq!(app.open.as_ref().unwrap().name, "login");
+        assert_eq!(app.outline.selected, 1);
+        assert_eq!(app.outline.entries.len(), 2);
+        assert!(texts(&app.graph).contains(&"calls handler  web.py:5"));
+
+        press(&mut app, KeyCode::Char('e'));
+        assert_eq!(app.graph_mode, GraphMode::Callees);
+        assert_eq!(texts(&app.graph), ["check  auth.py:6  -> auth.py:1"]);
+
+        // From the callers, open the caller, then go back.
+        press(&mut app, KeyCode::Char('c'));
+        assert_eq!(texts(&app.graph), ["function handler  web.py:5"]);
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.open.as_ref().unwrap().name, "handler");
+        assert_eq!(app.graph_mode, GraphMode::Callers);
+        assert!(app.graph.entries.is_empty());
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(app.open.as_ref().unwrap().name, "login");
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(app.status.as_deref(), Some("no previous symbol"));
+
+        // Tab moves between lists; the preview follows the selection.
+        press(&mut app, KeyCode::BackTab);
+        assert_eq!(app.focus, Pane::Outline);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.preview.as_ref().unwrap().symbol.name, "check");
+        press(&mut app, KeyCode::Char('/'));
+        assert_eq!(app.focus, Pane::Search);
+        press(&mut app, KeyCode::Char('q'));
+        assert_eq!(app.query, "loginq");
+        press(&mut app, KeyCode::Esc);
+        press(&mut app, KeyCode::Char('q'));
+        assert!(app.quit);
+
+        let _ = std::fs::remove_dir_all(&tmp);
+    }
+}
@@ -0,0 +1,68 @@
+//! `cartog tui`: a terminal browser over the index.
+//!
+//! A search box above four panes: the symbols matching the query, the outline of
+//! the open symbol's file, its references, callers or callees, and the source of
+//! whatever is selected. Enter opens the selected symbol from any list, so a search
+//! hit leads to its references and on to theirs without re-running commands;
+//! Backspace goes back. Nothing is written to the index.
+
+mod app;
+mod ui;
+
+use std::io;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::Terminal;
+
+use crate::shard::ShardedDatabase;
+
+use app::App;
+
+/// Browse `db` (file paths relative to `root`) until the user quits, starting
+/// with the results of `query` when given.
+pub fn run_tui(db: &ShardedDatabase, root: &Path, query: Option<&str>) -> Result<()> {
+    let mut app = App::new(db, root);
+    if let Some(query) = query {
+        app.search_for(query);
+    }
+
+    let _screen = Screen::enter()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    while !app.quit {
+        terminal.draw(|frame| ui::draw(frame, &app))?;
+        if let Event::Key(key) = event::read()? {
+            // Windows also reports releases.
+            if key.kind == KeyEventKind::Press {
+                app.on_key(key);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Raw mode on the alternate screen, restored when dropped, including on error
+/// or panic.
+struct Screen;
+
+impl Screen {
+    fn enter() -> Result<Self> {
+        enable_raw_mode().context("failed to enable raw terminal mode")?;
+        let screen = Self;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        Ok(screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+    }
+}
@@ -0,0 +1,133 @@
+//! Drawing the browser from its [`App`] state.
+
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListState, Paragraph};
+use ratatui::Frame;
+
+use super::app::{App, EntryList, Pane};
+
+const HELP: &str = "/ search  Enter open  r refs  c callers  e callees  Backspace back  Tab pane  PgUp/PgDn scroll  q quit";
+
+pub(super) fn draw(frame: &mut Frame, app: &App) {
+    let [search, lists, preview, status] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Percentage(45),
+        Constraint::Min(5),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [results, outline, graph] = Layout::horizontal([
+        Constraint::Percentage(40),
+        Constraint::Percentage(30),
+        Constraint::Percentage(30),
+    ])
+    .areas(lists);
+
+    let search_block = block("Search", app.focus == Pane::Search);
+    frame.render_widget(
+        Paragraph::new(app.query.as_str()).block(search_block),
+        search,
+    );
+    if app.focus == Pane::Search {
+        let x = search.x + 1 + app.query.chars().count() as u16;
+        frame.set_cursor_position((x.min(search.right().saturating_sub(2)), search.y + 1));
+    }
+
+    draw_list(
+        frame,
+        results,
+        "Results",
+        &app.results,
+        app.focus == Pane::Results,
+    );
+    let outline_title = match &app.open {
+        Some(symbol) => format!("Outline: {}", symbol.file_path),
+        None => "Outline".to_string(),
+    };
+    draw_list(
+        frame,
+        outline,
+        &outline_title,
+        &app.outline,
+        app.focus == Pane::Outline,
+    );
+    let graph_title = match &app.open {
+        Some(symbol) => format!("{} of {}", app.graph_mode.title(), symbol.name),
+        None => app.graph_mode.title().to_string(),
+    };
+    draw_list(
+        frame,
+        graph,
+        &graph_title,
+        &app.graph,
+        app.focus == Pane::Graph,
+    );
+
+    draw_preview(frame, preview, app);
+
+    let status_line = match &app.status {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::new().fg(Color::Yellow)),
+        None => Paragraph::new(HELP).style(Style::new().add_modifier(Modifier::DIM)),
+    };
+    frame.render_widget(status_line, status);
+}
+
+fn block(title: &str, focused: bool) -> Block<'static> {
+    let style = if focused {
+        Style::new().fg(Color::Cyan)
+    } else {
+        Style::new()
+    };
+    Block::bordered()
+        .title(format!(" {title} "))
+        .border_style(style)
+}
+
+fn draw_list(frame: &mut Frame, area: Rect, title: &str, list: &EntryList, focused: bool) {
+    let items: Vec<&str> = list.entries.iter().map(|e| e.text.as_str()).collect();
+    let title = format!("{title} ({})", list.entries.len());
+    let widget = List::new(items)
+        .block(block(&title, focused))
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default().with_selected(Some(list.selected));
+    frame.render_stateful_widget(widget, area, &mut state);
+}
+
+fn draw_preview(frame: &mut Frame, area: Rect, app: &App) {
+    let Some(preview) = &app.preview else {
+        frame.render_widget(block("Source", false), area);
+        return;
+    };
+    let symbol = &preview.symbol;
+    let mut title = format!(
+        "{} {}  {}:{}-{}",
+        symbol.kind, symbol.name, symbol.file_path, symbol.start_line, symbol.end_line
+    );
+    if preview.stale {
+        title.push_str("  (changed since indexed)");
+    }
+    let lines: Vec<Line> = match &preview.code {
+        Some(code) => {
+            let width = preview.last_line.to_string().len();
+            let highlighted = symbol.start_line..=symbol.end_line;
+            (preview.first_line..)
+                .zip(code.lines())
+                .map(|(n, text)| {
+                    let line = Line::from(format!("{n:>width$}  {text}"));
+                    if highlighted.contains(&n) {
+                        line
+                    } else {
+                        line.style(Style::new().add_modifier(Modifier::DIM))
+                    }
+                })
+                .collect()
+        }
+        None => vec![Line::from("(source unavailable)")],
+    };
+    let widget = Paragraph::new(lines)
+        .block(block(&title, false))
+        .scroll((app.preview_scroll, 0));
+    frame.render_widget(widget, area);
+}
@@ -1,6 +1,8 @@
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Symbol {
     pub id: String,
     pub name: String,
@@ -15,10 +17,22 @@ pub struct Symbol {
     pub visibility: Visibility,
     pub is_async: bool,
     pub docstring: Option<String>,
+    /// Why this symbol is invoked from outside the code graph, if it is:
+    /// `main`, `init`, `route`, `cli`, `task` or `script` (see [`ENTRYPOINT_KINDS`]).
+    pub entrypoint: Option<String>,
+    /// Database table an ORM model class or struct maps to (see [`crate::orm`]).
+    pub db_table: Option<String>,
+    /// Index generation that last wrote this symbol (see
+    /// [`crate::db::Database::index_generation`]). Results mixing generations were
+    /// read while an index run, such as the watcher's, was replacing files.
+    pub generation: u64,
 }
 
+/// Values of [`Symbol::entrypoint`].
+pub const ENTRYPOINT_KINDS: &[&str] = &["main", "init", "route", "cli", "task", "script"];
+
 impl Symbol {
-    /// Create a new symbol, computing the ID from `file_path:name:start_line`.
+    /// Create a new symbol, with the provisional ID `file_path:name:start_line`.
     ///
     /// Optional fields (`signature`, `docstring`, `parent_id`) default to `None`,
     /// `visibility` defaults to `Public`, and `is_async` defaults to `false`.
@@ -48,6 +62,9 @@ impl Symbol {
             visibility: Visibility::Public,
             is_async: false,
             docstring: None,
+            entrypoint: None,
+            db_table: None,
+            generation: 0,
         }
     }
 
@@ -80,9 +97,21 @@ impl Symbol {
         self.docstring = docstring;
         self
     }
+
+    /// Mark as an entry point of the given kind.
+    pub fn with_entrypoint(mut self, entrypoint: Option<&str>) -> Self {
+        self.entrypoint = entrypoint.map(str::to_string);
+        self
+    }
+
+    /// Set the database table this model maps to.
+    pub fn with_db_table(mut self, db_table: Option<String>) -> Self {
+        self.db_table = db_table;
+        self
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum SymbolKind {
     Function,
@@ -125,7 +154,7 @@ impl std::fmt::Display for SymbolKind {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum Visibility {
     Public,
@@ -158,14 +187,22 @@ impl std::fmt::Display for Visibility {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Edge {
     pub source_id: String,
     pub target_name: String,
     pub target_id: Option<String>,
+    /// Kind of the resolved target, stored on the edge when it is resolved so
+    /// queries can filter on it without joining `symbols`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_kind: Option<SymbolKind>,
     pub kind: EdgeKind,
     pub file_path: String,
     pub line: u32,
+    /// The call site sits under a condition or feature gate (an `if` branch, a
+    /// `match` arm, `debug && log()`, `#[cfg(..)]`), so it may not run.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub conditional: bool,
 }
 
 impl Edge {
@@ -181,14 +218,60 @@ impl Edge {
             source_id: source_id.into(),
             target_name: target_name.into(),
             target_id: None,
+            target_kind: None,
             kind,
             file_path: file_path.to_string(),
             line,
+            conditional: false,
         }
     }
+
+    /// Mark the edge as made under a condition (see [`Edge::conditional`]).
+    pub fn with_conditional(mut self, conditional: bool) -> Self {
+        self.conditional = conditional;
+        self
+    }
+}
+
+/// One call made by a symbol (`callees`): the edge, the symbol it resolved to
+/// and optionally the text of the call line.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct CallSite {
+    #[serde(flatten)]
+    pub edge: Edge,
+    /// The called symbol when the call was resolved; its file and signature tell
+    /// overloads and same-named functions apart.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<Symbol>,
+    /// The call line, trimmed, when requested and the caller's source is stored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_text: Option<String>,
+}
+
+/// One call to a symbol (`callers`): the call edge and the symbol it is made from.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Caller {
+    #[serde(flatten)]
+    pub edge: Edge,
+    /// The calling function or method, with its file, line range and signature.
+    pub caller: Symbol,
+}
+
+/// One hop of `impact` or `raises`: the referencing edge and its distance from the queried symbol.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ImpactEntry {
+    pub edge: Edge,
+    pub depth: u32,
+}
+
+/// A `child` class inheriting from `parent` (`hierarchy`).
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct HierarchyEntry {
+    pub child: String,
+    pub parent: String,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum EdgeKind {
     Calls,
@@ -231,16 +314,231 @@ impl std::fmt::Display for EdgeKind {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
 pub struct FileInfo {
     pub path: String,
     pub last_modified: f64,
     pub hash: String,
     pub language: String,
     pub num_symbols: u32,
+    /// Index generation of the run that last (re)indexed the file.
+    pub generation: u64,
+    /// Lines of the file, counted when it was indexed (zero for files indexed
+    /// before line counts were recorded).
+    pub lines: LineCounts,
+    /// Symbols or edges past the `[index]` per-file caps were left out.
+    pub truncated: bool,
 }
 
-/// Build a symbol ID from its components: `file_path:name:line`
+/// Code, comment and blank lines of a file, see [`crate::languages::count_lines`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct LineCounts {
+    pub code: u32,
+    pub comment: u32,
+    pub blank: u32,
+}
+
+impl LineCounts {
+    pub fn total(&self) -> u32 {
+        self.code + self.comment + self.blank
+    }
+}
+
+impl std::ops::AddAssign for LineCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.code += other.code;
+        self.comment += other.comment;
+        self.blank += other.blank;
+    }
+}
+
+/// Build a provisional symbol ID from its components: `file_path:name:line`.
+///
+/// Extractors link symbols and edges with these; indexing replaces them with
+/// [`stable_symbol_id`]s (see `ExtractionResult::assign_stable_ids`).
 pub fn symbol_id(file_path: &str, name: &str, line: u32) -> String {
     format!("{file_path}:{name}:{line}")
 }
+
+/// Build the stored ID of a symbol: `file_path:qualified_name:hash`, `hash` being the
+/// first 12 hex digits of the SHA-256 of its code. Lines shifting around the symbol
+/// leave it unchanged; editing the symbol's own code gives it a new one.
+pub fn stable_symbol_id(file_path: &str, qualified_name: &str, code: &str) -> String {
+    let digest = Sha256::digest(code.as_bytes());
+    let hash: String = digest[..6].iter().map(|b| format!("{b:02x}")).collect();
+    format!("{file_path}:{qualified_name}:{hash}")
+}
+
+/// Byte range `[start, end)` of a query term matched inside a string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct MatchSpan {
+    pub start: u32,
+    pub end: u32,
+}
+
+/// A symbol search hit, with the spans of `name` matched by the query.
+///
+/// The symbol is flattened so the JSON shape stays that of a plain [`Symbol`]
+/// plus a `name_matches` array.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct SymbolMatch {
+    #[serde(flatten)]
+    pub symbol: Symbol,
+    pub name_matches: Vec<MatchSpan>,
+    /// IDs of lower-ranked hits folded into this one by [`collapse_similar`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub similar: Vec<String>,
+    /// Scratch notes agents attached to this symbol, see [`crate::db::Database::attach_notes`].
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<crate::db::Note>,
+    /// Docstring of the enclosing symbol (a method's class), filled in on request
+    /// by [`crate::shard::ShardedDatabase::attach_parent_docs`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_docstring: Option<String>,
+}
+
+impl SymbolMatch {
+    /// Wrap a result of a substring name search for `query`.
+    pub fn new(symbol: Symbol, query: &str) -> Self {
+        let name_matches = match_spans(&symbol.name, &[query]);
+        Self {
+            symbol,
+            name_matches,
+            similar: Vec::new(),
+            notes: Vec::new(),
+            parent_docstring: None,
+        }
+    }
+}
+
+/// Fold hits whose names differ only by numbers or generated hash suffixes
+/// (`FooBuilderImpl1`, `FooBuilderImpl2`, `Foo_3fa9c1d2`) into the best-ranked
+/// one, recording the others in [`SymbolMatch::similar`]. Only hits of the same
+/// kind are folded, and names without such parts (14 `Config` classes) never are.
+pub fn collapse_similar(matches: Vec<SymbolMatch>) -> Vec<SymbolMatch> {
+    let mut kept: Vec<SymbolMatch> = Vec::with_capacity(matches.len());
+    let mut groups: std::collections::HashMap<(SymbolKind, String), usize> =
+        std::collections::HashMap::new();
+    for m in matches {
+        let Some(key) = generated_name_key(&m.symbol.name) else {
+            kept.push(m);
+            continue;
+        };
+        match groups.entry((m.symbol.kind, key)) {
+            std::collections::hash_map::Entry::Occupied(group) => {
+                kept[*group.get()].similar.push(m.symbol.id);
+            }
+            std::collections::hash_map::Entry::Vacant(slot) => {
+                slot.insert(kept.len());
+                kept.push(m);
+            }
+        }
+    }
+    kept
+}
+
+/// `name` with digit runs replaced by `#` and a trailing hex hash dropped, or
+/// `None` if it has neither.
+fn generated_name_key(name: &str) -> Option<String> {
+    let base = match name.rfind(['_', '$', '-']) {
+        Some(i) if is_generated_hash(&name[i + 1..]) => &name[..i],
+        _ => name,
+    };
+    let mut key = String::with_capacity(base.len());
+    for c in base.chars() {
+        if !c.is_ascii_digit() {
+            key.push(c);
+        } else if !key.ends_with('#') {
+            key.push('#');
+        }
+    }
+    (key != name).then_some(key)
+}
+
+/// Hex hash as appended by code generators and bundlers: 6+ hex digits, at least one a digit.
+fn is_generated_hash(s: &str) -> bool {
+    s.len() >= 6
+        && s.chars().all(|c| c.is_ascii_hexdigit())
+        && s.chars().any(|c| c.is_ascii_digit())
+}
+
+/// Find every case-insensitive occurrence of `terms` in `text`.
+///
+/// Spans are sorted and overlapping/adjacent spans merged. Case folding is
+/// Unicode-aware; byte offsets always refer to the original `text`, even where
+/// lower-casing changes a character's length (`İ` → `i̇`).
+pub fn match_spans(text: &str, terms: &[&str]) -> Vec<MatchSpan> {
+    // Lower-cased text, plus the original char's byte range for each of its bytes
+    let mut haystack = String::with_capacity(text.len());
+    let mut origin: Vec<(u32, u32)> = Vec::with_capacity(text.len());
+    for (i, c) in text.char_indices() {
+        let range = (i as u32, (i + c.len_utf8()) as u32);
+        for lower in c.to_lowercase() {
+            haystack.push(lower);
+            origin.resize(haystack.len(), range);
+        }
+    }
+    let mut spans: Vec<MatchSpan> = Vec::new();
+    for term in terms {
+        let needle = term.to_lowercase();
+        if needle.is_empty() {
+            continue;
+        }
+        spans.extend(haystack.match_indices(&needle).map(|(i, m)| MatchSpan {
+            start: origin[i].0,
+            end: origin[i + m.len() - 1].1,
+        }));
+    }
+    spans.sort_by_key(|s| (s.start, s.end));
+
+    let mut merged: Vec<MatchSpan> = Vec::with_capacity(spans.len());
+    for span in spans {
+        match merged.last_mut() {
+            Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+            _ => merged.push(span),
+        }
+    }
+    merged
+}
+
+/// A `TODO`/`FIXME`/`HACK`/`XXX`/`SAFETY` comment, linked to its enclosing symbol.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Anchor {
+    /// Upper-case tag, e.g. `FIXME`.
+    pub tag: String,
+    /// Comment text after the tag.
+    pub text: String,
+    pub file_path: String,
+    pub line: u32,
+    /// Innermost symbol whose line range contains the comment.
+    pub symbol_id: Option<String>,
+    pub symbol_name: Option<String>,
+}
+
+/// A name a file imports under another name: `import numpy as np`,
+/// `from x import A as B`, `use foo::Bar as Baz`, `import { a as b }`, or a named
+/// Go import.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportAlias {
+    /// The local name (`np`, `Baz`).
+    pub alias: String,
+    /// The imported name it stands for (`numpy`, `Bar`).
+    pub name: String,
+    pub file_path: String,
+    pub line: u32,
+}
+
+/// A string literal holding SQL, HTML or a regex, linked to its enclosing symbol.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Injection {
+    /// `sql`, `html` or `regex`.
+    pub language: String,
+    /// Literal contents with whitespace runs collapsed (cut past 1000 bytes).
+    pub text: String,
+    pub file_path: String,
+    /// Line of the opening quote.
+    pub line: u32,
+    /// Innermost symbol whose line range contains the literal.
+    pub symbol_id: Option<String>,
+    pub symbol_name: Option<String>,
+}
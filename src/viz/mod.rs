@@ -0,0 +1,262 @@
+//! `cartog viz`: a local web UI for the module graph and symbol neighborhoods.
+//!
+//! The page (`index.html`, embedded in the binary) draws the file-level dependency
+//! graph and, on search, a symbol's callers and callees. It polls the index
+//! generation and reloads when `cartog watch` (or `viz --watch`) re-indexes.
+//! Everything is served from `127.0.0.1`; no external assets are fetched.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write as _};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use tracing::{debug, info, warn};
+
+use crate::db::MAX_SEARCH_LIMIT;
+use crate::shard::ShardedDatabase;
+
+const INDEX_HTML: &str = include_str!("index.html");
+
+/// Serve the UI on `127.0.0.1:<port>` until the process is interrupted.
+///
+/// Requests are handled one at a time on the calling thread; each is a single
+/// short query, so there is nothing to gain from a pool.
+pub fn run_viz(port: u16, db_path: &Path) -> Result<()> {
+    let db = ShardedDatabase::open(db_path).context("failed to open database for viz")?;
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("failed to bind viz to {addr}"))?;
+    info!(%addr, "viz listening");
+    eprintln!("cartog viz: http://{addr}/ (Ctrl+C to stop)");
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = handle_connection(stream, &db) {
+                    debug!(error = %e, "viz request failed");
+                }
+            }
+            Err(e) => warn!(error = %e, "viz connection failed"),
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, db: &ShardedDatabase) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // Refuse other Host names so a web page can't reach the server via DNS rebinding.
+    let mut local_host = true;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("host") {
+                local_host = is_local_host(value.trim());
+            }
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let params = parse_query(query);
+
+    let (status, content_type, body) = if !local_host {
+        ("403 Forbidden", "text/plain", "forbidden\n".to_string())
+    } else if method != "GET" {
+        (
+            "405 Method Not Allowed",
+            "text/plain",
+            "GET only\n".to_string(),
+        )
+    } else if path == "/" {
+        ("200 OK", "text/html; charset=utf-8", INDEX_HTML.to_string())
+    } else {
+        match route(db, path, &params) {
+            Ok(Some(value)) => ("200 OK", "application/json", value.to_string()),
+            Ok(None) => ("404 Not Found", "text/plain", "not found\n".to_string()),
+            Err(e) => (
+                "400 Bad Request",
+                "application/json",
+                json!({ "error": e.to_string() }).to_string(),
+            ),
+        }
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\n\
+         Content-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    Ok(())
+}
+
+/// JSON API used by the page. `None` means an unknown path.
+fn route(
+    db: &ShardedDatabase,
+    path: &str,
+    params: &HashMap<String, String>,
+) -> Result<Option<Value>> {
+    let param = |key: &str| params.get(key).map(String::as_str).unwrap_or("");
+    let value = match path {
+        "/api/generation" => json!({ "generation": db.index_generation()? }),
+        "/api/graph" => json!({
+            "generation": db.index_generation()?,
+            "files": db.all_files()?,
+            "links": db.module_links()?,
+        }),
+        "/api/search" => {
+            let limit = param("limit").parse().unwrap_or(30).min(MAX_SEARCH_LIMIT);
+            json!(db.search(param("q"), None, None, limit.max(1))?)
+        }
+        "/api/outline" => json!(db.outline(param("file"))?),
+        "/api/symbol" => neighborhood(db, param("name"))?,
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+/// Definitions of `name`, the symbols referencing it, and the names it calls.
+fn neighborhood(db: &ShardedDatabase, name: &str) -> Result<Value> {
+    anyhow::ensure!(!name.is_empty(), "missing symbol name");
+    let definitions: Vec<_> = db
+        .search(name, None, None, MAX_SEARCH_LIMIT)?
+        .into_iter()
+        .filter(|s| s.name == name)
+        .collect();
+    let callers: Vec<Value> = db
+        .refs(name, None)?
+        .into_iter()
+        .map(|(edge, source)| {
+            json!({
+                "kind": edge.kind,
+                "file_path": edge.file_path,
+                "line": edge.line,
+                "name": source.as_ref().map(|s| s.name.as_str()),
+                "symbol_kind": source.as_ref().map(|s| s.kind),
+            })
+        })
+        .collect();
+    let mut callees: Vec<String> = db
+        .callees(name)?
+        .into_iter()
+        .map(|e| e.target_name)
+        .collect();
+    callees.sort();
+    callees.dedup();
+
+    Ok(json!({
+        "name": name,
+        "definitions": definitions,
+        "callers": callers,
+        "callees": callees,
+    }))
+}
+
+pub(crate) fn is_local_host(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(""),
+        None => host.split(':').next().unwrap_or(""),
+    };
+    matches!(name, "localhost" | "127.0.0.1" | "::1")
+}
+
+pub(crate) fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('=').or(Some((pair, ""))))
+        .filter(|(k, _)| !k.is_empty())
+        .map(|(k, v)| (percent_decode(k), percent_decode(v)))
+        .collect()
+}
+
+/// Decode `application/x-www-form-urlencoded` text (`+` is a space).
+pub(crate) fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+            (b'+', _) => out.push(b' '),
+            (b, _) => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query_decodes_values() {
+        let params = parse_query("q=impl%20Display&name=a+b&bad=%zz&flag");
+        assert_eq!(params["q"], "impl Display");
+        assert_eq!(params["name"], "a b");
+        assert_eq!(params["bad"], "%zz");
+        assert_eq!(params["flag"], "");
+    }
+
+    #[test]
+    fn test_is_local_host() {
+        assert!(is_local_host("localhost:8080"));
+        assert!(is_local_host("127.0.0.1:8080"));
+        assert!(is_local_host("[::1]:8080"));
+        assert!(!is_local_host("evil.example:8080"));
+        assert!(!is_local_host("localhost.evil.example"));
+    }
+
+    #[test]
+    fn test_route_graph_and_neighborhood() {
+        use crate::db::Database;
+        use crate::types::{Edge, EdgeKind, Symbol, SymbolKind};
+
+        let db = Database::open_memory().unwrap();
+        let sym =
+            |name: &str, file: &str| Symbol::new(name, SymbolKind::Function, file, 1, 2, 0, 10);
+        let (main, helper) = (sym("main", "app.py"), sym("helper", "util.py"));
+        db.insert_symbols(&[main.clone(), helper.clone()]).unwrap();
+        db.insert_edges(&[Edge::new(&main.id, "helper", EdgeKind::Calls, "app.py", 1)])
+            .unwrap();
+        db.resolve_edges().unwrap();
+        let db = ShardedDatabase::from(db);
+        let none = HashMap::new();
+
+        let graph = route(&db, "/api/graph", &none).unwrap().unwrap();
+        assert_eq!(graph["links"][0]["source"], "app.py");
+        assert_eq!(graph["links"][0]["target"], "util.py");
+        assert_eq!(graph["links"][0]["edges"], 1);
+
+        let params = HashMap::from([("name".to_string(), "helper".to_string())]);
+        let hood = route(&db, "/api/symbol", &params).unwrap().unwrap();
+        assert_eq!(hood["definitions"][0]["file_path"], "util.py");
+        assert_eq!(hood["callers"][0]["name"], "main");
+
+        let params = HashMap::from([("name".to_string(), "main".to_string())]);
+        let hood = route(&db, "/api/symbol", &params).unwrap().unwrap();
+        assert_eq!(hood["callees"][0], "helper");
+
+        assert!(route(&db, "/api/nope", &none).unwrap().is_none());
+        assert!(route(&db, "/api/symbol", &none).is_err());
+    }
+}
@@ -7,10 +7,16 @@ use anyhow::{Context, Result};
 use notify_debouncer_mini::{new_debouncer, DebouncedEventKind};
 use tracing::{debug, info, warn};
 
-use crate::db::Database;
-use crate::indexer::{self, is_ignored_dirname};
-use crate::languages::detect_language;
+use crate::config::{ProjectConfig, CONFIG_FILE};
+use crate::hooks::Hooks;
+use crate::ignore::{IndexFilter, GITIGNORE_FILE, IGNORE_FILE};
+use crate::indexer::{is_ignored_dirname, IndexResult};
+use crate::languages::detect_file_language;
 use crate::rag;
+use crate::rag::freshness::EmbedPolicy;
+use crate::rag::indexer::EmbedFilter;
+use crate::shard::ShardedDatabase;
+use crate::testmap::is_test_file;
 
 /// Configuration for the watch loop.
 pub struct WatchConfig {
@@ -22,6 +28,8 @@ pub struct WatchConfig {
     pub rag: bool,
     /// Delay after last index before embedding (only when `rag` is true).
     pub rag_delay: Duration,
+    /// Event classes that do not trigger a re-index, and symbols not embedded.
+    pub filter: WatchFilter,
 }
 
 impl WatchConfig {
@@ -31,10 +39,44 @@ impl WatchConfig {
             debounce: Duration::from_secs(2),
             rag: false,
             rag_delay: Duration::from_secs(30),
+            filter: WatchFilter::default(),
         }
     }
 }
 
+/// Changes the watcher ignores, to save CPU on busy repos.
+///
+/// A skipped change does not trigger a re-index by itself; the file is still
+/// picked up by the next re-index another change triggers.
+#[derive(Debug, Clone, Default)]
+pub struct WatchFilter {
+    /// Languages (as in `cartog stats`, e.g. `ruby`) whose changes are skipped.
+    pub skip_languages: Vec<String>,
+    /// Skip changes to test files.
+    pub skip_tests: bool,
+    /// Which symbols the deferred RAG embedding covers.
+    pub embed: EmbedFilter,
+}
+
+impl WatchFilter {
+    /// Whether a change to `path` (already known to be relevant) is skipped.
+    fn skips(&self, path: &Path, root: &Path) -> bool {
+        let language = match detect_file_language(path) {
+            Some("tsx") => "typescript",
+            language => language.unwrap_or_default(),
+        };
+        if self.skip_languages.iter().any(|l| l == language) {
+            return true;
+        }
+        self.skip_tests
+            && path
+                .strip_prefix(root)
+                .ok()
+                .and_then(Path::to_str)
+                .is_some_and(|rel| is_test_file(&rel.replace('\\', "/")))
+    }
+}
+
 /// Handle returned by `spawn_watch`. Drop or call `stop()` to shut down the watcher.
 pub struct WatchHandle {
     shutdown: Arc<AtomicBool>,
@@ -61,7 +103,7 @@ impl Drop for WatchHandle {
 /// Spawn the watch loop on a background thread.
 ///
 /// Returns a `WatchHandle` that can be used to stop the watcher.
-/// The watcher opens its own `Database` connection (SQLite WAL allows concurrent readers).
+/// The watcher opens its own database connection (SQLite WAL allows concurrent readers).
 pub fn spawn_watch(config: WatchConfig, db_path: &str) -> Result<WatchHandle> {
     let root = config
         .root
@@ -121,6 +163,174 @@ fn install_ctrlc_handler(flag: &Arc<AtomicBool>) {
     });
 }
 
+/// Incremental index of `root`, handing its events to the hooks in the background.
+fn index_with_hooks(db: &mut ShardedDatabase, root: &Path, hooks: &Hooks) -> Result<IndexResult> {
+    let run = hooks.start(db)?;
+    let result = db.index(root, false)?;
+    hooks.spawn(hooks.finish(run, db, root, &result)?);
+    Ok(result)
+}
+
+/// The watcher without the file system notifications: decides which changed
+/// paths trigger a re-index, runs it, and defers the RAG embedding.
+///
+/// [`watch_loop`] feeds it debounced `notify` events; `cartog watch --simulate`
+/// (see [`crate::simulate`]) feeds it scripted ones.
+pub(crate) struct WatchCore {
+    config: WatchConfig,
+    root: PathBuf,
+    db: ShardedDatabase,
+    hooks: Hooks,
+    index_filter: IndexFilter,
+    /// Symbols wait for embedding since `last_index_time`.
+    rag_pending: bool,
+    last_index_time: Option<Instant>,
+}
+
+impl WatchCore {
+    /// A core indexing the canonical `root` into `db`, running `hooks`.
+    pub(crate) fn new(
+        config: WatchConfig,
+        root: &Path,
+        db: ShardedDatabase,
+        hooks: Hooks,
+    ) -> Result<Self> {
+        if config.rag {
+            db.ensure_rag_supported()?;
+            db.set_embed_policy(EmbedPolicy::Eager.as_str())?;
+        }
+        Ok(Self {
+            index_filter: load_index_filter(root),
+            config,
+            root: root.to_path_buf(),
+            db,
+            hooks,
+            rag_pending: false,
+            last_index_time: None,
+        })
+    }
+
+    pub(crate) fn db(&self) -> &ShardedDatabase {
+        &self.db
+    }
+
+    /// Incremental index bringing the database up to date before watching.
+    pub(crate) fn initial_index(&mut self) -> Result<IndexResult> {
+        index_with_hooks(&mut self.db, &self.root, &self.hooks)
+    }
+
+    /// Handle a batch of changed (created, modified or deleted) paths: re-index
+    /// when one of them matters. `None` when none did.
+    pub(crate) fn on_changes(&mut self, paths: &[PathBuf]) -> Option<Result<IndexResult>> {
+        let root = self.root.as_path();
+        // New ignore rules re-index everything they add or drop
+        let rules_changed = paths.iter().any(|path| is_index_settings_path(path, root));
+        if rules_changed {
+            self.index_filter = load_index_filter(root);
+        }
+        // Only supported source files in non-ignored dirs count, minus excluded
+        // files and the classes the watch filter skips
+        let relevant = rules_changed
+            || paths.iter().any(|path| {
+                (is_relevant_path(path, root)
+                    || has_language_override(&self.index_filter, path, root))
+                    && !is_excluded_path(&self.index_filter, path, root)
+                    && !self.config.filter.skips(path, root)
+            });
+        if !relevant {
+            return None;
+        }
+
+        debug!(
+            count = paths.len(),
+            "file change events received, re-indexing"
+        );
+        let result = index_with_hooks(&mut self.db, root, &self.hooks);
+        if let Ok(r) = &result {
+            if r.files_indexed > 0 || r.files_removed > 0 {
+                info!(
+                    files = r.files_indexed,
+                    skipped = r.files_skipped,
+                    removed = r.files_removed,
+                    symbols = r.symbols_added,
+                    renamed = r.symbols_renamed,
+                    "re-indexed"
+                );
+            }
+            if self.config.rag {
+                self.schedule_embedding();
+            }
+        }
+        Some(result)
+    }
+
+    /// Start the RAG timer when the last re-index left symbols to embed.
+    fn schedule_embedding(&mut self) {
+        match rag::indexer::pending_embeddings(&self.db, self.config.filter.embed) {
+            Ok(needing) if !needing.is_empty() => {
+                debug!(
+                    pending = needing.len(),
+                    "symbols need embedding, starting RAG timer"
+                );
+                self.rag_pending = true;
+                self.last_index_time = Some(Instant::now());
+            }
+            Ok(_) => {
+                // No symbols need embedding
+                self.rag_pending = false;
+            }
+            Err(e) => {
+                warn!(error = %e, "failed to check embedding status");
+            }
+        }
+    }
+
+    /// How long to wait for events before calling [`Self::on_idle`] again.
+    fn poll_timeout(&self) -> Duration {
+        if self.config.rag && self.rag_pending {
+            Duration::from_millis(500) // Poll frequently to check RAG timer
+        } else {
+            Duration::from_secs(1) // Idle poll for shutdown check
+        }
+    }
+
+    /// No events for a while: embed once the RAG delay has elapsed.
+    fn on_idle(&mut self) {
+        let due = self
+            .last_index_time
+            .is_some_and(|last| last.elapsed() >= self.config.rag_delay);
+        if !(self.config.rag && self.rag_pending && due) {
+            return;
+        }
+        info!("RAG delay elapsed, embedding pending symbols");
+        match rag::indexer::index_embeddings(&self.db, false, self.config.filter.embed) {
+            Ok(r) => {
+                info!(
+                    embedded = r.symbols_embedded,
+                    skipped = r.symbols_skipped,
+                    "RAG embedding complete"
+                );
+            }
+            Err(e) => {
+                warn!(error = %e, "RAG embedding failed");
+            }
+        }
+        self.rag_pending = false;
+        self.last_index_time = None;
+    }
+
+    /// Flush pending RAG embeddings on shutdown.
+    fn finish(&mut self) {
+        if self.config.rag && self.rag_pending {
+            info!("flushing pending RAG embeddings before shutdown");
+            match rag::indexer::index_embeddings(&self.db, false, self.config.filter.embed) {
+                Ok(r) => info!(embedded = r.symbols_embedded, "final RAG flush complete"),
+                Err(e) => warn!(error = %e, "final RAG flush failed"),
+            }
+        }
+    }
+}
+
 /// Core watch loop. Runs until `shutdown` is set.
 fn watch_loop(
     config: WatchConfig,
@@ -128,18 +338,29 @@ fn watch_loop(
     db_path: &str,
     shutdown: &AtomicBool,
 ) -> Result<()> {
-    let db = Database::open(db_path).context("failed to open database for watcher")?;
+    let db = ShardedDatabase::open(db_path).context("failed to open database for watcher")?;
 
     info!(
         path = %root.display(),
         debounce_ms = config.debounce.as_millis(),
         rag = config.rag,
         rag_delay_s = config.rag_delay.as_secs(),
+        filter = ?config.filter,
         "starting watch"
     );
 
+    let hooks = match ProjectConfig::load(root) {
+        Ok(project) => Hooks::new(project.hooks, root),
+        Err(e) => {
+            warn!(error = %e, "ignoring hooks");
+            Hooks::new(Default::default(), root)
+        }
+    };
+    let debounce = config.debounce;
+    let mut core = WatchCore::new(config, root, db, hooks)?;
+
     // Initial incremental index to ensure DB is current
-    match indexer::index_directory(&db, root, false) {
+    match core.initial_index() {
         Ok(r) => info!(
             files = r.files_indexed,
             skipped = r.files_skipped,
@@ -152,8 +373,7 @@ fn watch_loop(
 
     // Set up the debounced file watcher
     let (tx, rx) = std::sync::mpsc::channel();
-    let mut debouncer =
-        new_debouncer(config.debounce, tx).context("failed to create file watcher")?;
+    let mut debouncer = new_debouncer(debounce, tx).context("failed to create file watcher")?;
 
     debouncer
         .watcher()
@@ -162,97 +382,28 @@ fn watch_loop(
 
     info!("watching for changes (Ctrl+C to stop)");
 
-    // RAG timer state: when we last indexed (to defer embedding)
-    let mut rag_pending = false;
-    let mut last_index_time: Option<Instant> = None;
-
     loop {
         if shutdown.load(Ordering::SeqCst) {
             break;
         }
 
         // Wait for events with a timeout so we can check shutdown + RAG timer
-        let poll_timeout = if config.rag && rag_pending {
-            Duration::from_millis(500) // Poll frequently to check RAG timer
-        } else {
-            Duration::from_secs(1) // Idle poll for shutdown check
-        };
-
-        match rx.recv_timeout(poll_timeout) {
+        match rx.recv_timeout(core.poll_timeout()) {
             Ok(Ok(events)) => {
-                // Filter events to only supported source files in non-ignored dirs
-                let relevant = events.iter().any(|event| {
-                    event.kind == DebouncedEventKind::Any && is_relevant_path(&event.path, root)
-                });
-
-                if relevant {
-                    debug!(
-                        count = events.len(),
-                        "file change events received, re-indexing"
-                    );
-                    match indexer::index_directory(&db, root, false) {
-                        Ok(r) => {
-                            if r.files_indexed > 0 || r.files_removed > 0 {
-                                info!(
-                                    files = r.files_indexed,
-                                    skipped = r.files_skipped,
-                                    removed = r.files_removed,
-                                    symbols = r.symbols_added,
-                                    "re-indexed"
-                                );
-                            }
-                            // Check if RAG embedding is needed
-                            if config.rag {
-                                match db.symbols_needing_embeddings() {
-                                    Ok(needing) if !needing.is_empty() => {
-                                        debug!(
-                                            pending = needing.len(),
-                                            "symbols need embedding, starting RAG timer"
-                                        );
-                                        rag_pending = true;
-                                        last_index_time = Some(Instant::now());
-                                    }
-                                    Ok(_) => {
-                                        // No symbols need embedding
-                                        rag_pending = false;
-                                    }
-                                    Err(e) => {
-                                        warn!(error = %e, "failed to check embedding status");
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => warn!(error = %e, "re-index failed"),
-                    }
+                // `AnyContinuous` events are followed by an `Any` once writes settle
+                let paths: Vec<PathBuf> = events
+                    .into_iter()
+                    .filter(|event| event.kind == DebouncedEventKind::Any)
+                    .map(|event| event.path)
+                    .collect();
+                if let Some(Err(e)) = core.on_changes(&paths) {
+                    warn!(error = %e, "re-index failed");
                 }
             }
             Ok(Err(error)) => {
                 warn!(error = %error, "file watcher error");
             }
-            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
-                // Check RAG timer
-                if config.rag && rag_pending {
-                    if let Some(last) = last_index_time {
-                        if last.elapsed() >= config.rag_delay {
-                            info!("RAG delay elapsed, embedding pending symbols");
-                            match rag::indexer::index_embeddings(&db, false) {
-                                Ok(r) => {
-                                    info!(
-                                        embedded = r.symbols_embedded,
-                                        skipped = r.symbols_skipped,
-                                        "RAG embedding complete"
-                                    );
-                                }
-                                Err(e) => {
-                                    warn!(error = %e, "RAG embedding failed");
-                                }
-                            }
-                            rag_pending = false;
-                            last_index_time = None;
-                        }
-                    }
-                }
-            }
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => core.on_idle(),
             Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
                 warn!("file watcher channel disconnected");
                 break;
@@ -260,28 +411,49 @@ fn watch_loop(
         }
     }
 
-    // Flush pending RAG embeddings on shutdown
-    if config.rag && rag_pending {
-        info!("flushing pending RAG embeddings before shutdown");
-        match rag::indexer::index_embeddings(&db, false) {
-            Ok(r) => info!(embedded = r.symbols_embedded, "final RAG flush complete"),
-            Err(e) => warn!(error = %e, "final RAG flush failed"),
-        }
-    }
-
+    core.finish();
     info!("watch stopped");
     Ok(())
 }
 
+/// The `.cartogignore` and `[index]` rules of `root`; none when they cannot be read,
+/// the index run then reports the error.
+fn load_index_filter(root: &Path) -> IndexFilter {
+    IndexFilter::load(root, true).unwrap_or_else(|e| {
+        warn!(error = %e, "ignoring index exclusions");
+        IndexFilter::default()
+    })
+}
+
+/// Whether `path` is a file holding the index rules of `root`: its settings, or a
+/// `.gitignore` at any depth.
+fn is_index_settings_path(path: &Path, root: &Path) -> bool {
+    path == root.join(IGNORE_FILE)
+        || path == root.join(CONFIG_FILE)
+        || (path.starts_with(root) && path.file_name().is_some_and(|n| n == GITIGNORE_FILE))
+}
+
+/// Whether `path`, under `root`, is left out of the index by `filter`.
+fn is_excluded_path(filter: &IndexFilter, path: &Path, root: &Path) -> bool {
+    path.strip_prefix(root)
+        .is_ok_and(|rel| filter.excludes(&rel.to_string_lossy()))
+}
+
+/// Whether `path`, under `root`, is indexed as a language `[index.languages]` sets.
+fn has_language_override(filter: &IndexFilter, path: &Path, root: &Path) -> bool {
+    path.strip_prefix(root)
+        .is_ok_and(|rel| filter.overrides_language(&rel.to_string_lossy()))
+}
+
 /// Check if a path is relevant for indexing: supported language + not in ignored directory.
 ///
 /// Returns `false` for:
-/// - Files with unsupported extensions (no tree-sitter extractor)
+/// - Files in no supported language, by extension or content (no tree-sitter extractor)
 /// - Files outside the watched root (e.g., symlink escapes)
 /// - Files under an ignored directory (`.git`, `node_modules`, etc.)
 fn is_relevant_path(path: &Path, root: &Path) -> bool {
     // Must be a supported source file
-    if detect_language(path).is_none() {
+    if detect_file_language(path).is_none() {
         return false;
     }
 
@@ -626,6 +798,83 @@ mod tests {
         assert_eq!(config.rag_delay, Duration::from_secs(60));
     }
 
+    // ── WatchFilter ──
+
+    #[test]
+    fn test_filter_default_skips_nothing() {
+        let root = PathBuf::from("/project");
+        let filter = WatchFilter::default();
+        assert!(!filter.skips(Path::new("/project/src/main.py"), &root));
+        assert!(!filter.skips(Path::new("/project/tests/test_main.py"), &root));
+    }
+
+    #[test]
+    fn test_filter_skips_languages() {
+        let root = PathBuf::from("/project");
+        let filter = WatchFilter {
+            skip_languages: vec!["ruby".to_string()],
+            ..Default::default()
+        };
+        assert!(filter.skips(Path::new("/project/lib/service.rb"), &root));
+        assert!(!filter.skips(Path::new("/project/src/main.py"), &root));
+
+        let filter = WatchFilter {
+            skip_languages: vec!["typescript".to_string()],
+            ..Default::default()
+        };
+        assert!(filter.skips(Path::new("/project/src/App.tsx"), &root));
+    }
+
+    #[test]
+    fn test_filter_skips_tests() {
+        let root = PathBuf::from("/project");
+        let filter = WatchFilter {
+            skip_tests: true,
+            ..Default::default()
+        };
+        assert!(filter.skips(Path::new("/project/tests/test_main.py"), &root));
+        assert!(filter.skips(Path::new("/project/pkg/auth_test.go"), &root));
+        assert!(!filter.skips(Path::new("/project/src/main.py"), &root));
+    }
+
+    #[test]
+    fn test_excluded_paths_and_index_settings() {
+        let root = PathBuf::from("/project");
+        let filter = IndexFilter::new(&Default::default(), "third_party/\n*.min.js\n");
+        assert!(is_excluded_path(
+            &filter,
+            Path::new("/project/third_party/lib/a.py"),
+            &root
+        ));
+        assert!(is_excluded_path(
+            &filter,
+            Path::new("/project/static/app.min.js"),
+            &root
+        ));
+        assert!(!is_excluded_path(
+            &filter,
+            Path::new("/project/src/main.py"),
+            &root
+        ));
+
+        assert!(is_index_settings_path(
+            Path::new("/project/.cartogignore"),
+            &root
+        ));
+        assert!(is_index_settings_path(
+            Path::new("/project/.cartog.toml"),
+            &root
+        ));
+        assert!(!is_index_settings_path(
+            Path::new("/project/sub/.cartogignore"),
+            &root
+        ));
+        assert!(is_index_settings_path(
+            Path::new("/project/sub/.gitignore"),
+            &root
+        ));
+    }
+
     // ── spawn_watch error paths ──
 
     #[test]
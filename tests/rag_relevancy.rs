@@ -12,7 +12,7 @@ use std::path::Path;
 
 use cartog::db::Database;
 use cartog::indexer::index_directory;
-use cartog::rag::search::hybrid_search;
+use cartog::rag::search::{search_batch, HybridSearchResult, SearchMode};
 
 /// A single relevancy test case.
 struct QueryCase {
@@ -191,9 +191,30 @@ fn rag_relevancy_benchmark() {
     let mut total_ndcg = 0.0;
     let n = cases.len() as f64;
 
-    for case in &cases {
-        let result = hybrid_search(&db, case.query, case.k as u32, None)
-            .unwrap_or_else(|e| panic!("search failed for '{}': {e}", case.query));
+    // One batched search (a single embedding call) per distinct k.
+    let mut results: Vec<Option<HybridSearchResult>> = cases.iter().map(|_| None).collect();
+    let mut ks: Vec<usize> = cases.iter().map(|c| c.k).collect();
+    ks.sort_unstable();
+    ks.dedup();
+    for k in ks {
+        let indices: Vec<usize> = (0..cases.len()).filter(|&i| cases[i].k == k).collect();
+        let queries: Vec<&str> = indices.iter().map(|&i| cases[i].query).collect();
+        let batch = search_batch(
+            &db,
+            &queries,
+            k as u32,
+            Default::default(),
+            SearchMode::Hybrid,
+            None,
+        )
+        .unwrap_or_else(|e| panic!("search failed for {queries:?}: {e}"));
+        for (i, result) in indices.into_iter().zip(batch) {
+            results[i] = Some(result);
+        }
+    }
+
+    for (case, result) in cases.iter().zip(results) {
+        let result = result.expect("every case searched");
 
         let names: Vec<String> = result
             .results